@@ -0,0 +1,424 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use hyper::body::Bytes;
+use lagon_runtime::{options::RuntimeOptions, Runtime};
+use lagon_runtime_http::{Method, Request, RunResult, StreamResult};
+use lagon_runtime_isolate::{options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest};
+use serde_json::json;
+use tokio::runtime::Handle;
+
+use crate::utils::{bundle_function, debug, info, parse_method, resolve_path, success};
+
+// Same defaults as `lagon dev`
+const TIMEOUT: Duration = Duration::from_millis(1000);
+const STARTUP_TIMEOUT: Duration = Duration::from_millis(2000);
+// In MB (MegaBytes)
+const MEMORY: usize = 128;
+// Requests don't go through a server, but the Function still sees a full URL
+const BENCH_HOST: &str = "127.0.0.1:1234";
+
+pub struct BenchOptions {
+    pub requests: usize,
+    pub concurrency: usize,
+    // Run for this duration instead of a fixed number of requests
+    pub duration: Option<Duration>,
+    pub path: String,
+    pub method: String,
+    pub body: Option<String>,
+    // Requests sent before the benchmark starts, excluded from the statistics
+    pub warmup: usize,
+    pub json: bool,
+}
+
+// The request sent to the Function, created again for each iteration
+struct Target {
+    method: Method,
+    url: String,
+    body: Bytes,
+}
+
+impl Target {
+    fn request(&self) -> Request {
+        Request {
+            headers: None,
+            method: self.method,
+            body: self.body.clone(),
+            url: self.url.clone(),
+        }
+    }
+}
+
+enum Limit {
+    Requests(usize, AtomicUsize),
+    Until(Instant),
+}
+
+impl Limit {
+    fn should_continue(&self) -> bool {
+        match self {
+            Limit::Requests(requests, sent) => sent.fetch_add(1, Ordering::SeqCst) < *requests,
+            Limit::Until(deadline) => Instant::now() < *deadline,
+        }
+    }
+}
+
+struct Sample {
+    latency: Duration,
+    is_error: bool,
+}
+
+struct Report {
+    // Sorted from fastest to slowest
+    latencies: Vec<Duration>,
+    errors: usize,
+    duration: Duration,
+}
+
+impl Report {
+    fn new(samples: Vec<Sample>, duration: Duration) -> Self {
+        let errors = samples.iter().filter(|sample| sample.is_error).count();
+        let mut latencies = samples
+            .into_iter()
+            .map(|sample| sample.latency)
+            .collect::<Vec<_>>();
+        latencies.sort();
+
+        Report {
+            latencies,
+            errors,
+            duration,
+        }
+    }
+
+    fn throughput(&self) -> f64 {
+        match self.duration.as_secs_f64() {
+            secs if secs > 0.0 => self.latencies.len() as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    // Nearest-rank percentile
+    fn percentile(&self, percentile: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::ZERO;
+        }
+
+        let rank = (percentile / 100.0 * self.latencies.len() as f64).ceil() as usize;
+
+        self.latencies[rank.clamp(1, self.latencies.len()) - 1]
+    }
+}
+
+fn to_milliseconds(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+fn parse_target(path: &str, method: &str, body: Option<String>) -> Result<Target> {
+    if !path.starts_with('/') {
+        return Err(anyhow!("Path {} should start with /", path));
+    }
+
+    Ok(Target {
+        method: parse_method(method)?,
+        url: format!("http://{BENCH_HOST}{path}"),
+        body: Bytes::from(body.unwrap_or_default()),
+    })
+}
+
+// Isolates are recreated when they get terminated, e.g after a timeout, until
+// the benchmark is over. The result of the first evaluation is sent back.
+fn spawn_isolate_thread(
+    code: String,
+    rx: flume::Receiver<IsolateEvent>,
+    evaluated_tx: flume::Sender<Option<String>>,
+) -> JoinHandle<()> {
+    let handle = Handle::current();
+
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            loop {
+                let options = IsolateOptions::new(code.clone())
+                    .timeout(TIMEOUT)
+                    .startup_timeout(STARTUP_TIMEOUT)
+                    .memory(MEMORY)
+                    .metadata(Some((String::from(""), String::from(""))));
+
+                let mut isolate = Isolate::new(options, rx.clone());
+                isolate.evaluate();
+
+                let compilation_error = isolate.get_compilation_error().map(String::from);
+                let has_failed = compilation_error.is_some();
+                evaluated_tx.send(compilation_error).unwrap_or(());
+
+                if has_failed {
+                    break;
+                }
+
+                isolate.run_event_loop().await;
+
+                if rx.is_disconnected() {
+                    break;
+                }
+            }
+        });
+    })
+}
+
+// Returns whether the Function responded successfully,
+// once the whole response (or stream) has been received
+async fn send_request(isolate_tx: &flume::Sender<IsolateEvent>, request: Request) -> bool {
+    let (sender, receiver) = flume::unbounded();
+
+    if isolate_tx
+        .send_async(IsolateEvent::Request(IsolateRequest {
+            request,
+            sender,
+            statistics: None,
+            body_stream: None,
+            connection: None,
+        }))
+        .await
+        .is_err()
+    {
+        return false;
+    }
+
+    let mut is_success = true;
+
+    while let Ok(result) = receiver.recv_async().await {
+        match result {
+            RunResult::Response(response) => return response.status < 500,
+            RunResult::Stream(StreamResult::Start(response)) => {
+                is_success = response.status < 500;
+            }
+            RunResult::Stream(StreamResult::Data(_)) => {}
+            RunResult::Stream(StreamResult::Done) => return is_success,
+            _ => return false,
+        }
+    }
+
+    false
+}
+
+async fn run_worker(
+    isolate_tx: flume::Sender<IsolateEvent>,
+    target: Arc<Target>,
+    limit: Arc<Limit>,
+) -> Vec<Sample> {
+    let mut samples = Vec::new();
+
+    while limit.should_continue() {
+        let start = Instant::now();
+        let is_success = send_request(&isolate_tx, target.request()).await;
+
+        samples.push(Sample {
+            latency: start.elapsed(),
+            is_error: !is_success,
+        });
+    }
+
+    samples
+}
+
+fn print_report(report: &Report) {
+    let rows = [
+        (
+            "Requests",
+            format!("{} ({} errors)", report.latencies.len(), report.errors),
+        ),
+        ("Duration", format!("{:.2}s", report.duration.as_secs_f64())),
+        ("Throughput", format!("{:.1} req/s", report.throughput())),
+        (
+            "Latency p50",
+            format!("{:.2}ms", to_milliseconds(report.percentile(50.0))),
+        ),
+        (
+            "Latency p95",
+            format!("{:.2}ms", to_milliseconds(report.percentile(95.0))),
+        ),
+        (
+            "Latency p99",
+            format!("{:.2}ms", to_milliseconds(report.percentile(99.0))),
+        ),
+    ];
+
+    println!();
+    println!("{}", success("Benchmark completed!"));
+    println!();
+
+    for (name, value) in rows {
+        println!("  {} {}", format!("{name:<12}").bright_black(), value);
+    }
+}
+
+fn print_json_report(report: &Report) {
+    let report = json!({
+        "requests": report.latencies.len(),
+        "errors": report.errors,
+        "duration_ms": to_milliseconds(report.duration),
+        "throughput": report.throughput(),
+        "latency_ms": {
+            "p50": to_milliseconds(report.percentile(50.0)),
+            "p95": to_milliseconds(report.percentile(95.0)),
+            "p99": to_milliseconds(report.percentile(99.0)),
+        },
+    });
+
+    println!("{report}");
+}
+
+pub async fn bench(path: Option<PathBuf>, options: BenchOptions) -> Result<()> {
+    let BenchOptions {
+        requests,
+        concurrency,
+        duration,
+        path: request_path,
+        method,
+        body,
+        warmup,
+        json,
+    } = options;
+
+    let target = Arc::new(parse_target(&request_path, &method, body)?);
+    let (root, function_config) = resolve_path(path, None, None)?;
+    let (index, _) = bundle_function(&function_config, &root)?;
+
+    let runtime = Runtime::new(RuntimeOptions::default());
+    let (isolate_tx, isolate_rx) = flume::unbounded();
+    let (evaluated_tx, evaluated_rx) = flume::unbounded();
+    let isolate_thread = spawn_isolate_thread(String::from_utf8(index)?, isolate_rx, evaluated_tx);
+
+    if let Ok(Some(compilation_error)) = evaluated_rx.recv_async().await {
+        isolate_thread.join().unwrap_or(());
+        runtime.dispose();
+
+        return Err(anyhow!(
+            "Failed to evaluate the Function: {}",
+            compilation_error
+        ));
+    }
+
+    if warmup > 0 {
+        println!("{}", info(&format!("Warming up with {warmup} requests...")));
+
+        for _ in 0..warmup {
+            send_request(&isolate_tx, target.request()).await;
+        }
+    }
+
+    println!(
+        "{}",
+        info(&match duration {
+            Some(duration) => format!(
+                "Sending requests for {}s with a concurrency of {concurrency}...",
+                duration.as_secs()
+            ),
+            None => format!("Sending {requests} requests with a concurrency of {concurrency}..."),
+        })
+    );
+
+    let start = Instant::now();
+    let limit = Arc::new(match duration {
+        Some(duration) => Limit::Until(start + duration),
+        None => Limit::Requests(requests, AtomicUsize::new(0)),
+    });
+
+    let workers = (0..concurrency)
+        .map(|_| {
+            tokio::spawn(run_worker(
+                isolate_tx.clone(),
+                Arc::clone(&target),
+                Arc::clone(&limit),
+            ))
+        })
+        .collect::<Vec<_>>();
+
+    let mut samples = Vec::new();
+
+    for worker in workers {
+        samples.extend(worker.await?);
+    }
+
+    let report = Report::new(samples, start.elapsed());
+
+    drop(isolate_tx);
+    isolate_thread.join().unwrap_or(());
+    runtime.dispose();
+
+    if json {
+        print_json_report(&report);
+    } else {
+        print_report(&report);
+        println!();
+        println!(
+            "{}",
+            debug("Requests are sent directly to the isolate, without going through a server")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(latencies: &[u64]) -> Report {
+        Report::new(
+            latencies
+                .iter()
+                .map(|latency| Sample {
+                    latency: Duration::from_millis(*latency),
+                    is_error: *latency > 90,
+                })
+                .collect(),
+            Duration::from_secs(2),
+        )
+    }
+
+    #[test]
+    fn report_percentiles() {
+        let report = report(&(1..=100).rev().collect::<Vec<_>>());
+
+        assert_eq!(report.percentile(50.0), Duration::from_millis(50));
+        assert_eq!(report.percentile(95.0), Duration::from_millis(95));
+        assert_eq!(report.percentile(99.0), Duration::from_millis(99));
+        assert_eq!(report.percentile(100.0), Duration::from_millis(100));
+        assert_eq!(report.errors, 10);
+        assert_eq!(report.throughput(), 50.0);
+    }
+
+    #[test]
+    fn report_without_requests() {
+        let report = report(&[]);
+
+        assert_eq!(report.percentile(50.0), Duration::ZERO);
+        assert_eq!(report.errors, 0);
+        assert_eq!(report.throughput(), 0.0);
+    }
+
+    #[test]
+    fn parse_target_valid() {
+        let target = parse_target("/api?a=b", "post", Some("body".into())).unwrap();
+
+        assert!(matches!(target.method, Method::POST));
+        assert_eq!(target.url, "http://127.0.0.1:1234/api?a=b");
+        assert_eq!(target.body, Bytes::from("body"));
+    }
+
+    #[test]
+    fn parse_target_invalid() {
+        assert!(parse_target("api", "GET", None).is_err());
+        assert!(parse_target("/", "TRACE", None).is_err());
+    }
+}
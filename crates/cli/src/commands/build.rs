@@ -1,45 +1,262 @@
-use std::{fs, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Result};
+use colored::Colorize;
+use envfile::EnvFile;
+use lagon_runtime_utils::validate_environment_variables;
+use serde_json::json;
 
-use crate::utils::{bundle_function, debug, print_progress, resolve_path, success};
+use crate::utils::{
+    bundle_function_with_analysis, debug, format_size, gzip_size, print_progress, resolve_path,
+    success, BundleAnalysis,
+};
+
+pub struct BuildOptions {
+    // Directory to write the output to, `.lagon/out` in the root by default
+    pub out: Option<PathBuf>,
+    // Print the size of each module of the bundle instead of writing it
+    pub analyze: bool,
+    pub json: bool,
+    // In bytes, the build fails if the bundle is larger
+    pub limit: Option<usize>,
+    // Env files that must set the variables declared in the Function's config
+    pub env: Vec<PathBuf>,
+}
+
+// Print the size of each written file, to inspect the output at a glance
+fn print_summary(files: &[(String, &[u8])]) {
+    let width = files
+        .iter()
+        .map(|(path, _)| path.len())
+        .max()
+        .unwrap_or(0)
+        .max("File".len());
+
+    println!();
+    // Padded before being colored, since colored strings ignore the width
+    let header = format!("{:<width$}  {:>10}  {:>10}", "File", "Size", "Gzipped");
+    println!("  {}", header.bright_black());
+
+    for (path, content) in files {
+        let gzipped = gzip_size(content).map_or_else(|_| String::from("-"), format_size);
+
+        println!(
+            "  {:<width$}  {:>10}  {}",
+            path,
+            format_size(content.len()),
+            format!("{gzipped:>10}").bright_black(),
+        );
+    }
+}
+
+fn print_analysis(analysis: &BundleAnalysis, index: &[u8]) {
+    let width = analysis
+        .modules
+        .iter()
+        .map(|module| module.path.len())
+        .max()
+        .unwrap_or(0)
+        .max("Module".len());
+
+    println!();
+    let header = format!("{:<width$}  {:>10}  {:>6}", "Module", "Size", "%");
+    println!("  {}", header.bright_black());
+
+    for module in &analysis.modules {
+        let percentage = module.bytes as f64 / index.len().max(1) as f64 * 100.0;
+
+        println!(
+            "  {:<width$}  {:>10}  {}",
+            module.path,
+            format_size(module.bytes),
+            format!("{percentage:>5.1}%").bright_black(),
+        );
+    }
+
+    let gzipped = gzip_size(index).map_or_else(|_| String::from("-"), format_size);
+
+    println!();
+    println!(
+        "  {} {} {}",
+        "Total:".bold(),
+        format_size(index.len()),
+        debug(&format!("({gzipped} gzipped)"))
+    );
+}
+
+fn print_json_analysis(
+    analysis: &BundleAnalysis,
+    index: &[u8],
+    limit: Option<usize>,
+    env: &[String],
+) {
+    let analysis = json!({
+        "modules": analysis.modules,
+        "size": index.len(),
+        "gzip_size": gzip_size(index).ok(),
+        "limit": limit,
+        "env": env,
+    });
+
+    println!("{analysis}");
+}
+
+fn check_limit(size: usize, limit: Option<usize>) -> Result<()> {
+    match limit {
+        Some(limit) if size > limit => Err(anyhow!(
+            "Function is {} which is larger than the limit of {}",
+            format_size(size),
+            format_size(limit)
+        )),
+        _ => Ok(()),
+    }
+}
+
+// Only the given env files are checked, not the environment of the
+// current process, so CI checks the files that will be deployed
+fn check_environment_variables(root: &Path, required: &[String], env: &[PathBuf]) -> Result<()> {
+    let mut environment_variables = HashMap::new();
+
+    for path in env {
+        let envfile = EnvFile::new(root.join(path))
+            .map_err(|err| anyhow!("Could not read {}: {}", path.display(), err))?;
+
+        environment_variables.extend(envfile.store);
+    }
+
+    validate_environment_variables(required, &environment_variables)
+}
 
 pub fn build(
     path: Option<PathBuf>,
     client: Option<PathBuf>,
     public_dir: Option<PathBuf>,
+    options: BuildOptions,
 ) -> Result<()> {
+    let BuildOptions {
+        out,
+        analyze,
+        json,
+        limit,
+        env,
+    } = options;
+
     let (root, function_config) = resolve_path(path, client, public_dir)?;
-    let (index, assets) = bundle_function(&function_config, &root)?;
+
+    if !env.is_empty() {
+        check_environment_variables(&root, &function_config.env, &env)?;
+    }
+    let (index, assets, analysis) =
+        bundle_function_with_analysis(&function_config, &root, analyze)?;
+
+    // Nothing is written when analyzing the bundle
+    if let Some(analysis) = analysis {
+        match json {
+            true => print_json_analysis(&analysis, &index, limit, &function_config.env),
+            false => print_analysis(&analysis, &index),
+        }
+
+        return check_limit(index.len(), limit);
+    }
+
+    // Written inside .lagon by default, without touching the Function's config.json
+    let out = out.unwrap_or_else(|| root.join(".lagon").join("out"));
 
     let end_progress = print_progress("Writting index.js...");
 
-    fs::create_dir_all(root.join(".lagon"))?;
-    fs::write(root.join(".lagon").join("index.js"), index)?;
+    fs::create_dir_all(&out)?;
+    fs::write(out.join("index.js"), &index)?;
 
     end_progress();
 
-    for (path, content) in assets {
+    let mut files = vec![(String::from("index.js"), index.as_slice())];
+
+    for (path, content) in &assets {
         let message = format!("Writting {path}...");
         let end_progress = print_progress(&message);
 
-        let dir = root.join(".lagon").join("public").join(
-            PathBuf::from(&path)
+        let dir = out.join("public").join(
+            PathBuf::from(path)
                 .parent()
                 .ok_or_else(|| anyhow!("Could not find parent of {}", path))?,
         );
         fs::create_dir_all(dir)?;
-        fs::write(root.join(".lagon").join("public").join(path), content)?;
+        fs::write(out.join("public").join(path), content)?;
 
         end_progress();
+
+        files.push((format!("public/{path}"), content.as_slice()));
     }
 
+    print_summary(&files);
+
     println!();
     println!(
         "{} {}",
         success("Build successful!"),
-        debug("You can find it in .lagon folder.")
+        debug(&format!("You can find it in {}", out.display()))
     );
 
-    Ok(())
+    if !function_config.env.is_empty() {
+        println!(
+            "{}",
+            debug(&format!(
+                "Requires the environment variables {}",
+                function_config.env.join(", ")
+            ))
+        );
+    }
+
+    check_limit(index.len(), limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_env_files() {
+        let root = std::env::temp_dir().join("lagon-build-env");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join(".env"), "API_KEY=secret\n").unwrap();
+        fs::write(root.join(".env.production"), "DATABASE_URL=mysql://\n").unwrap();
+
+        let required = vec![String::from("API_KEY"), String::from("DATABASE_URL")];
+
+        assert!(check_environment_variables(
+            &root,
+            &required,
+            &[PathBuf::from(".env"), PathBuf::from(".env.production")]
+        )
+        .is_ok());
+        assert_eq!(
+            check_environment_variables(&root, &required, &[PathBuf::from(".env")])
+                .unwrap_err()
+                .to_string(),
+            "Missing required environment variables: DATABASE_URL"
+        );
+        assert!(
+            check_environment_variables(&root, &required, &[PathBuf::from(".env.ci")]).is_err()
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn check_size_limit() {
+        assert!(check_limit(1024, None).is_ok());
+        assert!(check_limit(1024, Some(1024)).is_ok());
+        assert_eq!(
+            check_limit(2048, Some(1024)).unwrap_err().to_string(),
+            format!(
+                "Function is {} which is larger than the limit of {}",
+                format_size(2048),
+                format_size(1024)
+            )
+        );
+    }
 }
@@ -0,0 +1,23 @@
+use std::io;
+
+use anyhow::Result;
+use clap::Command;
+use clap_complete::{generate, Shell};
+
+// The package is named `lagon-cli`, but the released binary is `lagon`
+const BIN_NAME: &str = "lagon";
+
+// Values of enums (e.g the templates of `lagon new`) are completed too
+pub fn generate_completions(shell: Shell, command: &mut Command) -> Vec<u8> {
+    let mut script = Vec::new();
+
+    generate(shell, command, BIN_NAME, &mut script);
+    script
+}
+
+pub fn completions(shell: Shell, mut command: Command) -> Result<()> {
+    let script = generate_completions(shell, &mut command);
+
+    io::Write::write_all(&mut io::stdout(), &script)?;
+    Ok(())
+}
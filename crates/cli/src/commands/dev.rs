@@ -1,67 +1,125 @@
-use anyhow::{Error, Result};
+use anyhow::{anyhow, Result};
 use chrono::offset::Local;
-use colored::Colorize;
+use chrono::Utc;
+use colored::{ColoredString, Colorize};
 use envfile::EnvFile;
-use hyper::server::conn::AddrStream;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use hyper::body::{Bytes, HttpBody};
+use hyper::header::{HeaderValue, ORIGIN};
+use hyper::server::accept::{self, Accept};
+use hyper::server::conn::{AddrIncoming, AddrStream};
 use hyper::service::{make_service_fn, service_fn};
-use hyper::{Body, Request as HyperRequest, Response as HyperResponse, Server};
+use hyper::{
+    Body, Method as HyperMethod, Request as HyperRequest, Response as HyperResponse, Server, Uri,
+};
 use lagon_runtime::{options::RuntimeOptions, Runtime};
-use lagon_runtime_http::{Request, Response, RunResult, X_FORWARDED_FOR, X_LAGON_REGION};
-use lagon_runtime_isolate::{options::IsolateOptions, Isolate};
-use lagon_runtime_isolate::{IsolateEvent, IsolateRequest};
-use lagon_runtime_utils::assets::{find_asset, handle_asset};
-use lagon_runtime_utils::response::{handle_response, ResponseEvent, FAVICON_URL};
-use log::{
-    set_boxed_logger, set_max_level, Level, LevelFilter, Log, Metadata, Record, SetLoggerError,
+use lagon_runtime_http::{
+    BodyTooLargeError, Method, Request, Response, RunResult, StreamResult, X_FORWARDED_FOR,
+    X_LAGON_ID, X_LAGON_REGION,
+};
+use lagon_runtime_isolate::{
+    cache::MemoryCacheBackend,
+    hooks::IsolateHookEvent,
+    kv::{KvBackend, MemoryKvBackend},
+    options::IsolateOptions,
+    pool::{IsolateOptionsFactory, IsolatePool, IsolatePoolOptions},
+    queue::QueueBackend,
+    tls::validate_root_certificates,
+};
+use lagon_runtime_isolate::{
+    stream_request_body, HeapStatistics, IsolateEvent, IsolateRequest, IsolateScheduledEvent,
+    RequestBodyChunk,
 };
-use notify::event::ModifyKind;
-use notify::{Config, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
-use std::collections::HashMap;
+use lagon_runtime_utils::assets::{
+    find_asset, find_directory_entries, find_fallback_asset, handle_asset,
+    handle_directory_listing, AssetResolution,
+};
+use lagon_runtime_utils::compression::ContentEncoding;
+use lagon_runtime_utils::response::{handle_response, ResponseEvent, FAVICON_URL};
+use lagon_runtime_utils::validate_environment_variables;
+use lagon_runtime_utils::websocket::{handle_websocket_upgrade, PendingUpgrade};
+use log::{debug, Level};
+use notify::event::{DataChange, ModifyKind};
+use notify::{Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::{json, Map, Value};
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, VecDeque};
 use std::convert::Infallible;
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, IsTerminal, Write};
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::time::Duration;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex as StdMutex, Once, RwLock as StdRwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant, SystemTime};
 use tokio::runtime::Handle;
-use tokio::sync::Mutex;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio_rustls::{server::TlsStream, TlsAcceptor};
+use tokio_util::either::Either;
 
-use crate::utils::{bundle_function, error, info, input, resolve_path, success, warn, Assets};
+use crate::utils::{
+    bundle_function, code_cache_key, debug, error, error_response, find_tsconfig, format_size,
+    get_client_asset_name, get_version, gzip_size, info, init_logger, input, listen_shortcuts,
+    load_mocks, load_snapshot, load_tls_config, print_json, read_assets, read_certificates,
+    resolve_path, self_signed_tls_config, set_masked_values, success, warn, write_log_file, Assets,
+    BrowserOpener, CodeCaches, Cors, Cron, DefaultBrowser, ErrorFormat, FunctionConfig,
+    InspectorServer, LocalQueueBackend, LogFile, LogFormat, LogLevel, Mocks, Proxy, QueuedMessage,
+    Shortcut, TsConfig, DEFAULT_MASK_PATTERNS, SHORTCUTS_HINT,
+};
 
 const LOCAL_REGION: &str = "local";
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(100);
+const IGNORED_DIRECTORIES: [&str; 3] = ["node_modules", ".git", ".lagon"];
+const MAX_CONCURRENT_TLS_HANDSHAKES: usize = 32;
+const RESERVED_ROUTES_PREFIX: &str = "/__lagon/";
+const MAX_RECORDED_BODY_SIZE: usize = 1024 * 1024; // 1MB
+const OPEN_BROWSER_INTERVAL: Duration = Duration::from_millis(50);
+const X_LAGON_REQUEST_ID: &str = "x-lagon-request-id";
+const HEAP_STATISTICS_TIMEOUT: Duration = Duration::from_secs(1);
+// Doubled after each failed delivery of a queued message
+const QUEUE_RETRY_DELAY: Duration = Duration::from_millis(100);
+// How long to wait for a new version of the Function to be evaluated
+// when its startup timeout is disabled
+const RELOAD_EVALUATION_TIMEOUT: Duration = Duration::from_secs(10);
 
-struct SimpleLogger;
-
-impl Log for SimpleLogger {
-    fn enabled(&self, metadata: &Metadata) -> bool {
-        metadata.level() <= Level::Info
-    }
-
-    fn log(&self, record: &Record) {
-        if self.enabled(record.metadata()) {
-            let level = match record.level() {
-                Level::Error => "ERROR".red(),
-                Level::Warn => "WARN".yellow(),
-                _ => "INFO".blue(),
-            };
+type Connection = Either<AddrStream, TlsStream<AddrStream>>;
 
-            println!("{} {}", level, record.args());
+fn parse_environment_variable(env_var: &str) -> Result<(String, String)> {
+    match env_var.split_once('=') {
+        Some((key, value))
+            if !key.is_empty()
+                && key
+                    .chars()
+                    .all(|char| char.is_ascii_alphanumeric() || char == '_') =>
+        {
+            Ok((key.to_string(), value.to_string()))
         }
+        _ => Err(anyhow!(
+            "Invalid environment variable `{}`, expected KEY=VALUE",
+            env_var
+        )),
     }
-
-    fn flush(&self) {}
-}
-
-fn init_logger() -> Result<(), SetLoggerError> {
-    set_boxed_logger(Box::new(SimpleLogger)).map(|()| set_max_level(LevelFilter::Info))?;
-    Ok(())
 }
 
+// Env files are applied in order, so later files override keys of previous
+// ones, and variables given with `--env-var` override all the env files. The
+// variables declared in the Function's config have to be set by one of them,
+// otherwise the names of the missing ones are returned as an error
 fn parse_environment_variables(
     root: &Path,
-    env: Option<PathBuf>,
+    env: &[PathBuf],
+    env_vars: &[String],
+    required: &[String],
 ) -> Result<HashMap<String, String>> {
     let mut environment_variables = HashMap::new();
 
-    if let Some(path) = env {
+    for path in env {
         let envfile = EnvFile::new(root.join(path))?;
 
         for (key, value) in envfile.store {
@@ -69,243 +127,3894 @@ fn parse_environment_variables(
         }
     }
 
+    for env_var in env_vars {
+        let (key, value) = parse_environment_variable(env_var)?;
+
+        environment_variables.insert(key, value);
+    }
+
+    validate_environment_variables(required, &environment_variables)?;
+
     Ok(environment_variables)
 }
 
-// This function is similar to packages/serverless/src/main.rs,
-// except that we don't have multiple deployments and such multiple
-// threads to manage, and we don't manager logs and metrics.
-async fn handle_request(
-    req: HyperRequest<Body>,
-    public_dir: Option<PathBuf>,
-    ip: String,
-    assets: Arc<Mutex<Assets>>,
-    isolate_tx: flume::Sender<IsolateEvent>,
-) -> Result<HyperResponse<Body>> {
-    let url = req.uri().path();
+fn is_change(event: &Event) -> bool {
+    match event.kind {
+        EventKind::Create(_) | EventKind::Remove(_) => true,
+        EventKind::Modify(modify) => {
+            matches!(modify, ModifyKind::Name(_)) || matches!(modify, ModifyKind::Data(_))
+        }
+        _ => false,
+    }
+}
 
-    println!(
-        "{} {} {}",
-        format!("{}", Local::now().time()).bright_black(),
-        req.method().to_string().blue(),
-        url
-    );
+// Config files are files that aren't part of the Function's
+// code but are still watched, e.g env and mock files. Bundle
+// paths are outside of the Function directory but still part
+// of its code, e.g the tsconfig.json and the path aliases
+fn should_rebundle(
+    event: &Event,
+    root: &Path,
+    public_dir: &Option<PathBuf>,
+    config_files: &[PathBuf],
+    bundle_paths: &[PathBuf],
+) -> bool {
+    is_change(event)
+        && event.paths.iter().any(|path| {
+            // Config files outside of the Function directory are watched
+            // through their parent directory, which we need to skip
+            let relative_path = match path.strip_prefix(root) {
+                Ok(relative_path) => relative_path,
+                Err(_) => {
+                    return bundle_paths
+                        .iter()
+                        .any(|bundle_path| path.starts_with(bundle_path))
+                }
+            };
 
-    let (tx, rx) = flume::unbounded();
-    let assets = assets.lock().await.to_owned();
+            let is_ignored = relative_path.components().any(|component| {
+                IGNORED_DIRECTORIES
+                    .iter()
+                    .any(|directory| component.as_os_str() == *directory)
+            });
 
-    let is_favicon = url == FAVICON_URL;
+            let is_public_dir = public_dir
+                .as_ref()
+                .map_or(false, |public_dir| path.starts_with(public_dir));
 
-    if let Some(asset) = find_asset(url, &assets.keys().cloned().collect()) {
-        println!("              {}", input("Asset found"));
+            let is_config_file = config_files.iter().any(|config_file| path == config_file);
 
-        let run_result = match handle_asset(public_dir.unwrap(), asset) {
-            Ok(response) => RunResult::Response(response),
-            Err(error) => RunResult::Error(format!("Could not retrieve asset ({asset}): {error}")),
+            !is_ignored && !is_public_dir && !is_config_file
+        })
+}
+
+// The nearest tsconfig.json and the directories its path aliases point to,
+// when they live outside of the Function directory (e.g in a monorepo)
+fn watch_bundle_paths(watcher: &mut RecommendedWatcher, root: &Path) -> Result<Vec<PathBuf>> {
+    let tsconfig = match find_tsconfig(root) {
+        Some(tsconfig) => tsconfig,
+        None => return Ok(Vec::new()),
+    };
+
+    let alias_paths = TsConfig::load(&tsconfig)
+        .map(|tsconfig| tsconfig.alias_paths())
+        .unwrap_or_default();
+    let mut bundle_paths = Vec::new();
+
+    if !tsconfig.starts_with(root) {
+        if let Some(parent) = tsconfig.parent() {
+            watcher.watch(parent, RecursiveMode::NonRecursive)?;
+        }
+
+        bundle_paths.push(tsconfig);
+    }
+
+    for alias_path in alias_paths {
+        // Aliases can point to files that don't exist yet
+        let alias_path = match alias_path.canonicalize() {
+            Ok(alias_path) => alias_path,
+            Err(_) => continue,
         };
 
-        tx.send_async(run_result).await.unwrap_or(());
-    } else if is_favicon {
-        tx.send_async(RunResult::Response(Response {
-            status: 404,
-            ..Default::default()
-        }))
-        .await
-        .unwrap_or(());
+        if !alias_path.starts_with(root) && !bundle_paths.contains(&alias_path) {
+            watcher.watch(&alias_path, RecursiveMode::Recursive)?;
+            bundle_paths.push(alias_path);
+        }
+    }
+
+    Ok(bundle_paths)
+}
+
+fn should_reload_config(event: &Event, config_files: &[PathBuf]) -> bool {
+    is_change(event)
+        && event
+            .paths
+            .iter()
+            .any(|path| config_files.iter().any(|config_file| path == config_file))
+}
+
+fn should_reload_assets(event: &Event, public_dir: &Option<PathBuf>) -> bool {
+    match public_dir {
+        Some(public_dir) => {
+            is_change(event) && event.paths.iter().any(|path| path.starts_with(public_dir))
+        }
+        None => false,
+    }
+}
+
+fn print_reload_separator(should_clear: bool) {
+    if should_clear {
+        // Clear the screen and put the cursor at first row & first col of the screen.
+        print!("\x1B[2J\x1B[1;1H");
     } else {
-        match Request::from_hyper(req).await {
-            Ok(mut request) => {
-                request.set_header(X_FORWARDED_FOR.to_string(), ip);
-                request.set_header(X_LAGON_REGION.to_string(), LOCAL_REGION.to_string());
+        println!();
+        println!(
+            "{}",
+            debug(&format!("──── {} ────", Local::now().format("%H:%M:%S")))
+        );
+    }
+}
 
-                isolate_tx
-                    .send_async(IsolateEvent::Request(IsolateRequest {
-                        request,
-                        sender: tx,
-                    }))
-                    .await
-                    .unwrap_or(());
-            }
-            Err(error) => {
-                println!("Error while parsing request: {error}");
+// A request forwarded to the Function, kept to be replayed later
+struct RecordedRequest {
+    id: usize,
+    method: Method,
+    url: String,
+    headers: Option<HashMap<String, Vec<String>>>,
+    body: Bytes,
+    // Whether the body was larger than MAX_RECORDED_BODY_SIZE
+    is_truncated: bool,
+}
 
-                tx.send_async(RunResult::Error("Error while parsing request".into()))
-                    .await
-                    .unwrap_or(());
-            }
-        };
+impl RecordedRequest {
+    fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "method": <&str>::from(self.method),
+            "url": self.url,
+            "headers": self.headers,
+            "body": String::from_utf8_lossy(&self.body),
+            "truncated": self.is_truncated,
+        })
     }
+}
 
-    handle_response(
-        rx,
-        (),
-        Box::new(|event, _| match event {
-            ResponseEvent::StreamDoneNoDataError => {
-                println!(
-                    "{}",
-                    error("The stream was done before sending a response/data")
-                );
-            }
-            ResponseEvent::StreamDoneDataError => {
-                println!("{}", error("Got data after stream was done"));
-            }
-            ResponseEvent::UnexpectedStreamResult(result) => {
-                println!("{} {:?}", error("Unexpected stream result:"), result);
-            }
-            ResponseEvent::LimitsReached(result) => {
-                if result == RunResult::Timeout {
-                    println!("{}", error("Function execution timed out"));
-                } else {
-                    println!("{}", error("Function execution reached memory limit"));
-                }
-            }
-            ResponseEvent::Error(result) => {
-                println!("{}", error(result.as_error().as_str()));
-            }
-            _ => {}
-        }),
-    )
-    .await
+impl From<&RecordedRequest> for Request {
+    fn from(recorded_request: &RecordedRequest) -> Self {
+        Request {
+            headers: recorded_request.headers.clone(),
+            method: recorded_request.method,
+            body: recorded_request.body.clone(),
+            url: recorded_request.url.clone(),
+        }
+    }
 }
 
-pub async fn dev(
-    path: Option<PathBuf>,
-    client: Option<PathBuf>,
-    public_dir: Option<PathBuf>,
-    port: Option<u16>,
-    hostname: Option<String>,
-    env: Option<PathBuf>,
-    allow_code_generation: bool,
-) -> Result<()> {
-    let (root, function_config) = resolve_path(path, client, public_dir)?;
-    let (index, assets) = bundle_function(&function_config, &root)?;
-
-    let server_index = index.clone();
-    let assets = Arc::new(Mutex::new(assets));
-
-    let runtime =
-        Runtime::new(RuntimeOptions::default().allow_code_generation(allow_code_generation));
-    let addr = format!(
-        "{}:{}",
-        hostname.unwrap_or_else(|| "127.0.0.1".into()),
-        port.unwrap_or(1234)
-    )
-    .parse()?;
+// Bounded buffer of the last requests forwarded to the Function
+struct RecordedRequests {
+    requests: VecDeque<RecordedRequest>,
+    capacity: usize,
+    next_id: usize,
+}
 
-    let server_public_dir = function_config
-        .assets
-        .as_ref()
-        .map(|assets| root.join(assets));
-    let environment_variables = parse_environment_variables(&root, env)?;
+impl RecordedRequests {
+    fn new(capacity: usize) -> Self {
+        RecordedRequests {
+            requests: VecDeque::with_capacity(capacity),
+            capacity,
+            next_id: 1,
+        }
+    }
 
-    let (tx, rx) = flume::unbounded();
-    let (index_tx, index_rx) = flume::unbounded();
-    let handle = Handle::current();
+    // Returns the id of the recorded request, to record its body if it's streamed
+    fn record(&mut self, request: &Request) -> Option<usize> {
+        if self.capacity == 0 {
+            return None;
+        }
 
-    std::thread::spawn(move || {
-        handle.block_on(async move {
-            let mut index = server_index;
+        if self.requests.len() == self.capacity {
+            self.requests.pop_front();
+        }
 
-            loop {
-                let mut isolate = Isolate::new(
-                    IsolateOptions::new(
-                        String::from_utf8(index.clone()).expect("Code is not UTF-8"),
-                    )
-                    .timeout(Duration::from_secs(1))
-                    .startup_timeout(Duration::from_secs(2))
-                    .metadata(Some((String::from(""), String::from(""))))
-                    .environment_variables(environment_variables.clone()),
-                    rx.clone(),
-                );
+        let is_truncated = request.body.len() > MAX_RECORDED_BODY_SIZE;
 
-                isolate.evaluate();
+        self.requests.push_back(RecordedRequest {
+            id: self.next_id,
+            method: request.method,
+            url: request.url.clone(),
+            headers: request.headers.clone(),
+            body: if is_truncated {
+                request.body.slice(..MAX_RECORDED_BODY_SIZE)
+            } else {
+                request.body.clone()
+            },
+            is_truncated,
+        });
 
-                tokio::select! {
-                    _ = isolate.run_event_loop() => {},
-                    new_index = index_rx.recv_async() => {
-                        index = new_index.unwrap();
-                    }
-                }
+        self.next_id += 1;
+        Some(self.next_id - 1)
+    }
+
+    // Appended to the body of the request, if it hasn't been evicted since
+    fn record_body_chunk(&mut self, id: usize, chunk: &[u8]) {
+        if let Some(request) = self.requests.iter_mut().find(|request| request.id == id) {
+            let available = MAX_RECORDED_BODY_SIZE - request.body.len();
+            request.is_truncated |= chunk.len() > available;
+
+            if available > 0 {
+                let mut body = request.body.to_vec();
+                body.extend_from_slice(&chunk[..chunk.len().min(available)]);
+                request.body = body.into();
             }
-        });
-    });
+        }
+    }
 
-    let server_assets = Arc::clone(&assets);
-    let server = Server::bind(&addr).serve(make_service_fn(move |conn: &AddrStream| {
-        let public_dir = server_public_dir.clone();
-        let assets = Arc::clone(&server_assets);
-        let tx = tx.clone();
-
-        let addr = conn.remote_addr();
-        let ip = addr.ip().to_string();
-
-        async move {
-            Ok::<_, Infallible>(service_fn(move |req| {
-                handle_request(
-                    req,
-                    public_dir.clone(),
-                    ip.clone(),
-                    Arc::clone(&assets),
-                    tx.clone(),
-                )
-            }))
+    fn get(&self, id: usize) -> Option<&RecordedRequest> {
+        self.requests.iter().find(|request| request.id == id)
+    }
+}
+
+// Requests served by the current isolate when simulating cold starts
+struct ColdStart {
+    generation: usize,
+    requests: usize,
+}
+
+// State of a single Function, shared between the server,
+// its watcher and its isolate thread
+struct FunctionState {
+    // Path prefix the Function is mounted on, `/` for the main Function
+    route: String,
+    isolate_tx: flume::Sender<IsolateEvent>,
+    cold_start: Arc<Mutex<ColdStart>>,
+    // Incremented each time the isolate thread creates a new isolate
+    isolate_generation: AtomicUsize,
+    bundle_error: Mutex<Option<String>>,
+    bundle_size: AtomicUsize,
+    // Whether the current isolate successfully evaluated the Function
+    is_ready: AtomicBool,
+    // From the `crons` of the Function's config, invoking its `scheduled` export
+    crons: Vec<Cron>,
+}
+
+impl FunctionState {
+    fn new(
+        route: String,
+        isolate_tx: flume::Sender<IsolateEvent>,
+        bundle_size: usize,
+        crons: Vec<Cron>,
+    ) -> Self {
+        FunctionState {
+            route,
+            isolate_tx,
+            cold_start: Arc::new(Mutex::new(ColdStart {
+                generation: 1,
+                requests: 0,
+            })),
+            isolate_generation: AtomicUsize::new(0),
+            bundle_error: Mutex::new(None),
+            bundle_size: AtomicUsize::new(bundle_size),
+            is_ready: AtomicBool::new(false),
+            crons,
         }
-    }));
+    }
+}
 
-    let (tx, rx) = std::sync::mpsc::channel();
-    let mut watcher = RecommendedWatcher::new(
-        tx,
-        Config::default().with_poll_interval(Duration::from_secs(1)),
-    )?;
+// State shared between the server and the watchers
+struct DevState {
+    assets: Mutex<Assets>,
+    recorded_requests: Mutex<RecordedRequests>,
+    assets_fallback: Option<String>,
+    // Forwards requests not handled by assets
+    proxy: Option<Proxy>,
+    cors: Option<Cors>,
+    function: Arc<FunctionState>,
+    // Functions mounted with `--function`, sorted by longest route first
+    mounted_functions: Vec<Arc<FunctionState>>,
+}
 
-    watcher.watch(
-        &root.join(function_config.index.clone()),
-        RecursiveMode::NonRecursive,
-    )?;
+impl DevState {
+    fn mounted_function(&self, path: &str) -> Option<&Arc<FunctionState>> {
+        self.mounted_functions
+            .iter()
+            .find(|function| matches_route(&function.route, path))
+    }
 
-    tokio::spawn(async move {
-        for event in rx.into_iter().flatten() {
-            let should_update = if let EventKind::Modify(modify) = event.kind {
-                matches!(modify, ModifyKind::Name(_)) || matches!(modify, ModifyKind::Data(_))
-            } else {
-                false
-            };
+    // Requests that don't match a mounted Function go to the main Function
+    fn find_function(&self, path: &str) -> &Arc<FunctionState> {
+        self.mounted_function(path).unwrap_or(&self.function)
+    }
 
-            if should_update {
-                // Clear the screen and put the cursor at first row & first col of the screen.
-                print!("\x1B[2J\x1B[1;1H");
-                println!("{}", info("Found change, updating..."));
+    fn functions(&self) -> impl Iterator<Item = &Arc<FunctionState>> {
+        std::iter::once(&self.function).chain(self.mounted_functions.iter())
+    }
+}
+
+// Routes match on whole path segments, so `/api` matches
+// `/api` and `/api/users` but not `/apis`
+fn matches_route(route: &str, path: &str) -> bool {
+    match path.strip_prefix(route) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
 
-                let (new_index, new_assets) = bundle_function(&function_config, &root)?;
+fn parse_function_mount(mount: &str) -> Result<(String, PathBuf)> {
+    match mount.split_once('=') {
+        Some((route, path)) if route.starts_with('/') && !path.is_empty() => {
+            let route = route.trim_end_matches('/');
 
-                *assets.lock().await = new_assets;
-                index_tx.send_async(new_index).await.unwrap();
+            if route.is_empty() {
+                return Err(anyhow!(
+                    "Invalid Function `{}`, the `/` route is already used by the main Function",
+                    mount
+                ));
             }
+
+            Ok((route.to_string(), PathBuf::from(path)))
         }
+        _ => Err(anyhow!(
+            "Invalid Function `{}`, expected <ROUTE>=<PATH> with a route starting with /",
+            mount
+        )),
+    }
+}
 
-        Ok::<(), Error>(())
-    });
+fn parse_crons(function_config: &FunctionConfig) -> Result<Vec<Cron>> {
+    function_config
+        .crons
+        .iter()
+        .map(|cron| cron.parse())
+        .collect()
+}
 
-    println!();
-    println!("{}", success("Dev Server started!"));
+// Query parameters of reserved routes, decoded
+fn query_param(query: Option<&str>, name: &str) -> Option<String> {
+    query?
+        .split('&')
+        .find_map(|pair| match pair.split_once('=') {
+            Some((key, value)) if key == name => urlencoding::decode(&value.replace('+', " "))
+                .map(String::from)
+                .ok(),
+            _ => None,
+        })
+}
 
-    if allow_code_generation {
+fn warn_reserved_routes(index: &[u8]) {
+    if String::from_utf8_lossy(index).contains(RESERVED_ROUTES_PREFIX) {
         println!(
             "{}",
-            warn("Code generation is allowed due to `--allow-code-generation`")
+            warn(&format!(
+                "Routes starting with {RESERVED_ROUTES_PREFIX} are reserved by the dev server and won't reach the Function"
+            ))
         );
     }
+}
+
+fn text_response(status: u16, body: &str) -> Response {
+    let mut headers = HashMap::with_capacity(1);
+    headers.insert("content-type".into(), vec!["text/plain".into()]);
+
+    Response {
+        status,
+        headers: Some(headers),
+        body: body.to_string().into(),
+    }
+}
+
+fn json_response(value: Value) -> Response {
+    let mut headers = HashMap::with_capacity(1);
+    headers.insert("content-type".into(), vec!["application/json".into()]);
+
+    Response {
+        status: 200,
+        headers: Some(headers),
+        body: value.to_string().into(),
+    }
+}
+
+// Requests are handled one at a time when simulating cold starts: the returned
+// guard must be held until the response is done, so the isolate is never recreated
+// while a request is in flight. Also returns whether the request hits a new isolate.
+async fn acquire_cold_start(
+    function: &FunctionState,
+    every: usize,
+) -> (OwnedMutexGuard<ColdStart>, bool) {
+    let mut cold_start = Arc::clone(&function.cold_start).lock_owned().await;
+    let generation = function.isolate_generation.load(Ordering::SeqCst);
+
+    // The isolate might have been recreated since the last request (e.g after a reload)
+    if generation > cold_start.generation {
+        cold_start.generation = generation;
+        cold_start.requests = 0;
+    }
+
+    if cold_start.requests >= every {
+        // The isolate thread creates a new isolate once the current one is terminated
+        function
+            .isolate_tx
+            .send_async(IsolateEvent::Terminate("Cold start".into()))
+            .await
+            .unwrap_or(());
+
+        cold_start.generation += 1;
+        cold_start.requests = 0;
+    }
+
+    let is_cold = cold_start.requests == 0;
+    cold_start.requests += 1;
+
+    (cold_start, is_cold)
+}
+
+fn colored_status(status: u16) -> ColoredString {
+    let text = status.to_string();
+
+    match status {
+        100..=299 => text.green(),
+        300..=499 => text.yellow(),
+        _ => text.red(),
+    }
+}
+
+// A short id to correlate a request with its logs, not meant to be unique.
+// Each RandomState is seeded differently, so hashing nothing is enough.
+fn generate_request_id() -> String {
+    let hasher = RandomState::new().build_hasher();
+
+    format!("{:04x}", hasher.finish() as u16)
+}
+
+// The isolate can only answer once it's done running
+// code, e.g a request with an infinite loop
+async fn get_heap_statistics(isolate_tx: &flume::Sender<IsolateEvent>) -> Option<HeapStatistics> {
+    let (tx, rx) = flume::bounded(1);
+
+    isolate_tx
+        .send_async(IsolateEvent::HeapStatistics(tx))
+        .await
+        .ok()?;
+
+    tokio::time::timeout(HEAP_STATISTICS_TIMEOUT, rx.recv_async())
+        .await
+        .ok()?
+        .ok()
+}
+
+fn to_megabytes(bytes: usize) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn format_heap_statistics(statistics: &HeapStatistics) -> String {
+    format!(
+        "{:.1}MB used, {:.1}MB total (limit: {:.1}MB), {:.1}MB external",
+        to_megabytes(statistics.used_heap_size),
+        to_megabytes(statistics.total_heap_size),
+        to_megabytes(statistics.heap_size_limit),
+        to_megabytes(statistics.external_memory),
+    )
+}
+
+// Printed after each bundle, to notice when a change makes the Function heavier
+fn print_bundle_size(index: &[u8], assets: &Assets) {
+    let gzip_size = match gzip_size(index) {
+        Ok(gzip_size) => format!(" ({} gzipped)", format_size(gzip_size)),
+        Err(_) => String::new(),
+    };
 
-    println!();
     println!(
-        " {} {}",
-        "➤".bright_black(),
-        format!("http://{addr}").blue()
+        "{}",
+        debug(&format!(
+            "Bundle size: {}{}, {} asset{}",
+            format_size(index.len()),
+            gzip_size,
+            assets.len(),
+            if assets.len() == 1 { "" } else { "s" }
+        ))
     );
+}
+
+fn print_startup_time(function: &FunctionState, startup_time: Duration) {
+    let name = match function.route.as_str() {
+        "/" => String::from("Function"),
+        route => format!("Function mounted on {route}"),
+    };
+
+    println!(
+        "{}",
+        debug(&format!(
+            "{} started in {:.1}ms",
+            name,
+            startup_time.as_secs_f64() * 1000.0
+        ))
+    );
+}
+
+async fn print_heap_statistics(
+    isolate_tx: flume::Sender<IsolateEvent>,
+    every: Duration,
+    log_format: LogFormat,
+) {
+    let mut interval = tokio::time::interval(every);
+    // The first tick completes immediately
+    interval.tick().await;
 
-    init_logger()?;
-    server.await?;
-    runtime.dispose();
+    loop {
+        interval.tick().await;
 
-    Ok(())
+        let statistics = match get_heap_statistics(&isolate_tx).await {
+            Some(statistics) => statistics,
+            None => continue,
+        };
+
+        let mut fields = Map::new();
+        fields.insert("used_heap_size".into(), statistics.used_heap_size.into());
+        fields.insert("total_heap_size".into(), statistics.total_heap_size.into());
+        fields.insert("heap_size_limit".into(), statistics.heap_size_limit.into());
+        fields.insert("external_memory".into(), statistics.external_memory.into());
+        fields.insert(
+            "detached_contexts".into(),
+            statistics.number_of_detached_contexts.into(),
+        );
+
+        write_log_file(Level::Info, "Heap statistics", &fields);
+
+        match log_format {
+            LogFormat::Json => print_json(Level::Info, "Heap statistics", fields),
+            LogFormat::Text => println!(
+                "{}",
+                debug(&format!(
+                    "Heap: {}, {} detached context(s)",
+                    format_heap_statistics(&statistics),
+                    statistics.number_of_detached_contexts,
+                ))
+            ),
+        }
+    }
+}
+
+async fn handle_reserved_route(
+    route: &str,
+    query: Option<&str>,
+    method: &HyperMethod,
+    request_id: &str,
+    state: &DevState,
+    tx: flume::Sender<RunResult>,
+    options: RequestOptions,
+) {
+    let response =
+        match route {
+            "health" => match state
+                .functions()
+                .all(|function| function.is_ready.load(Ordering::SeqCst))
+            {
+                true => text_response(200, "OK"),
+                false => text_response(503, "Not ready"),
+            },
+            "info" => json_response(json!({
+                "version": get_version().ok(),
+                "bundleSize": state.function.bundle_size.load(Ordering::SeqCst),
+                "assetsCount": state.assets.lock().await.len(),
+                "timeout": options.timeout.as_millis() as u64,
+                "startupTimeout": options.startup_timeout.as_millis() as u64,
+                "functions": state
+                    .mounted_functions
+                    .iter()
+                    .map(|function| json!({
+                        "route": function.route,
+                        "bundleSize": function.bundle_size.load(Ordering::SeqCst),
+                    }))
+                    .collect::<Vec<_>>(),
+            })),
+            "heap" => match get_heap_statistics(&state.function.isolate_tx).await {
+                Some(statistics) => json_response(json!({
+                    "usedHeapSize": statistics.used_heap_size,
+                    "totalHeapSize": statistics.total_heap_size,
+                    "heapSizeLimit": statistics.heap_size_limit,
+                    "externalMemory": statistics.external_memory,
+                    "detachedContexts": statistics.number_of_detached_contexts,
+                })),
+                None => text_response(503, "Isolate is busy"),
+            },
+            "requests" => json_response(Value::Array(
+                state
+                    .recorded_requests
+                    .lock()
+                    .await
+                    .requests
+                    .iter()
+                    .map(RecordedRequest::to_json)
+                    .collect(),
+            )),
+            "cron" if *method != HyperMethod::POST => text_response(405, "Method Not Allowed"),
+            "cron" => {
+                let function =
+                    state.find_function(&query_param(query, "path").unwrap_or_else(|| "/".into()));
+                // Defaults to the first cron expression of the Function
+                let cron = match query_param(query, "cron") {
+                    Some(cron) => cron.parse::<Cron>().map_err(|err| err.to_string()),
+                    None => function.crons.first().cloned().ok_or_else(|| {
+                        "No cron expression given with ?cron= and none in the Function's config"
+                            .to_string()
+                    }),
+                };
+
+                match cron {
+                    // Like replayed requests, the result of the `scheduled`
+                    // export is returned as is, e.g an empty 204 response
+                    Ok(cron) => {
+                        function
+                            .isolate_tx
+                            .send_async(IsolateEvent::Scheduled(IsolateScheduledEvent {
+                                cron: cron.to_string(),
+                                scheduled_time: SystemTime::now(),
+                                sender: tx,
+                                statistics: None,
+                            }))
+                            .await
+                            .unwrap_or(());
+
+                        return;
+                    }
+                    Err(err) => text_response(400, &err),
+                }
+            }
+            _ => match route.strip_prefix("replay/") {
+                Some(_) if *method != HyperMethod::POST => text_response(405, "Method Not Allowed"),
+                Some(id) => {
+                    let request =
+                        match id.parse() {
+                            Ok(id) => state.recorded_requests.lock().await.get(id).map(
+                                |recorded_request| {
+                                    if recorded_request.is_truncated {
+                                        println!(
+                                            "{}",
+                                            warn("Replaying a request with a truncated body")
+                                        );
+                                    }
+
+                                    let mut request = Request::from(recorded_request);
+                                    request.set_header(X_LAGON_ID.to_string(), request_id.into());
+
+                                    request
+                                },
+                            ),
+                            Err(_) => None,
+                        };
+
+                    match request {
+                        // Replayed requests go through the same path as live requests,
+                        // and the Function's response is returned as is
+                        Some(request) => {
+                            let path = request
+                                .url
+                                .parse::<Uri>()
+                                .map(|uri| uri.path().to_string())
+                                .unwrap_or_default();
+
+                            state
+                                .find_function(&path)
+                                .isolate_tx
+                                .send_async(IsolateEvent::Request(IsolateRequest {
+                                    request,
+                                    sender: tx,
+                                    statistics: None,
+                                    body_stream: None,
+                                    connection: None,
+                                }))
+                                .await
+                                .unwrap_or(());
+
+                            return;
+                        }
+                        None => text_response(404, &format!("No recorded request with id {id}")),
+                    }
+                }
+                None => text_response(404, "Not Found"),
+            },
+        };
+
+    tx.send_async(RunResult::Response(response))
+        .await
+        .unwrap_or(());
+}
+
+// Forwards the chunks of a streamed request body to the isolate, and records them
+// along the way. Only the chunks read by the Function are recorded
+fn record_request_body(
+    chunks: flume::Receiver<RequestBodyChunk>,
+    state: Arc<DevState>,
+    id: Option<usize>,
+) -> flume::Receiver<RequestBodyChunk> {
+    let id = match id {
+        Some(id) => id,
+        None => return chunks,
+    };
+    let (sender, receiver) = flume::bounded(1);
+
+    tokio::spawn(async move {
+        while let Ok(chunk) = chunks.recv_async().await {
+            if let Ok(chunk) = &chunk {
+                state
+                    .recorded_requests
+                    .lock()
+                    .await
+                    .record_body_chunk(id, chunk);
+            }
+
+            // The receiver is dropped once the request is done
+            if sender.send_async(chunk).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+// This function is similar to packages/serverless/src/main.rs,
+// except that we don't have multiple deployments and such multiple
+// threads to manage, and we don't manager logs and metrics.
+async fn handle_request(
+    mut req: HyperRequest<Body>,
+    public_dir: Option<PathBuf>,
+    ip: String,
+    state: Arc<DevState>,
+    options: RequestOptions,
+) -> Result<HyperResponse<Body>> {
+    let RequestOptions {
+        verbose,
+        error_overlay,
+        log_format,
+        cold_start_every,
+        serve_index,
+        max_body_size,
+        compress,
+        ..
+    } = options;
+
+    let start_time = Instant::now();
+    let url = req.uri().path();
+    let request_id = generate_request_id();
+    let method = req.method().to_string();
+    let path = url.to_string();
+
+    let mut request_fields = Map::new();
+    request_fields.insert("method".into(), method.clone().into());
+    request_fields.insert("path".into(), path.clone().into());
+    request_fields.insert("request_id".into(), request_id.clone().into());
+
+    write_log_file(Level::Info, &format!("{method} {path}"), &request_fields);
+
+    match log_format {
+        LogFormat::Json => print_json(
+            Level::Info,
+            &format!("{method} {path}"),
+            request_fields.clone(),
+        ),
+        LogFormat::Text => println!(
+            "{} {} {} {}",
+            format!("{}", Local::now().time()).bright_black(),
+            method.blue(),
+            url,
+            format!("[{request_id}]").bright_black(),
+        ),
+    }
+
+    if verbose {
+        debug!("Request headers: {:?}", req.headers());
+    }
+
+    let error_format = ErrorFormat::from_headers(req.headers());
+    let handler_error = Arc::new(StdMutex::new(None));
+    let on_event_handler_error = Arc::clone(&handler_error);
+    let cold_start_guard = StdMutex::new(None);
+    let mut is_cold = None;
+
+    let (tx, rx) = flume::unbounded();
+    // Only sent by the isolate, e.g not for assets
+    let (statistics_tx, statistics_rx) = flume::bounded(1);
+    // Only taken from requests sent to the Function
+    let mut pending_upgrade = None;
+    // When the client goes away, this future and the sender are dropped,
+    // which aborts the request in the isolate
+    let (_connection_tx, connection_rx) = flume::bounded::<()>(1);
+    let assets = state.assets.lock().await.to_owned();
+    let asset_names = assets.keys().cloned().collect();
+
+    // Mounted Functions are matched before assets, which
+    // are only served for the main Function
+    let mounted_function = state.mounted_function(url).cloned();
+    let is_mounted = mounted_function.is_some();
+    let function = mounted_function.unwrap_or_else(|| Arc::clone(&state.function));
+    let bundle_error = function.bundle_error.lock().await.clone();
+
+    let is_favicon = url == FAVICON_URL;
+
+    let asset = match is_mounted {
+        true => None,
+        false => find_asset(url, &asset_names, AssetResolution::default()),
+    };
+    let directory_entries = match (asset, serve_index, is_mounted) {
+        (None, true, false) => find_directory_entries(url, &asset_names),
+        _ => None,
+    };
+    // Pages that don't match an asset or a folder are served the fallback asset, if any
+    let asset = match (asset, &directory_entries, is_favicon || is_mounted) {
+        (None, None, false) => find_fallback_asset(
+            state.assets_fallback.as_deref(),
+            req.method(),
+            req.headers(),
+            &asset_names,
+        ),
+        _ => asset,
+    };
+
+    let origin = req.headers().get(ORIGIN).cloned();
+    let encoding = match compress {
+        true => ContentEncoding::negotiate(req.headers()),
+        false => None,
+    };
+
+    // CORS preflight requests and reserved routes are handled by
+    // the dev server itself and never forwarded to the Function
+    if state.cors.is_some() && Cors::is_preflight(req.method(), req.headers()) {
+        tx.send_async(RunResult::Response(Cors::preflight_response(req.headers())))
+            .await
+            .unwrap_or(());
+    } else if let Some(route) = url.strip_prefix(RESERVED_ROUTES_PREFIX) {
+        handle_reserved_route(
+            route,
+            req.uri().query(),
+            req.method(),
+            &request_id,
+            &state,
+            tx,
+            options,
+        )
+        .await;
+    } else if let Some(entries) = directory_entries {
+        tx.send_async(RunResult::Response(handle_directory_listing(url, &entries)))
+            .await
+            .unwrap_or(());
+    } else if let Some(asset) = asset {
+        if log_format == LogFormat::Text {
+            println!("              {}", input("Asset found"));
+        }
+
+        let run_result = match handle_asset(public_dir.unwrap(), asset) {
+            Ok(response) => RunResult::Response(response),
+            Err(error) => RunResult::Error(format!("Could not retrieve asset ({asset}): {error}")),
+        };
+
+        tx.send_async(run_result).await.unwrap_or(());
+    } else if let Some(proxy) = state
+        .proxy
+        .as_ref()
+        .filter(|proxy| !is_mounted && proxy.should_proxy(url))
+    {
+        let mut response = proxy.forward(req).await;
+        let elapsed = start_time.elapsed();
+        let status = response.status().as_u16();
+
+        let mut fields = request_fields;
+        fields.insert("status".into(), status.into());
+        fields.insert("duration_ms".into(), (elapsed.as_millis() as u64).into());
+        fields.insert("proxied".into(), true.into());
+
+        let message = format!("{method} {path} {status}");
+        write_log_file(Level::Info, &message, &fields);
+
+        match log_format {
+            LogFormat::Json => print_json(Level::Info, &message, fields),
+            LogFormat::Text => {
+                println!(
+                    "              {} {} {}{}",
+                    "↳".bright_black(),
+                    colored_status(status),
+                    format!("{}ms", elapsed.as_millis()).bright_black(),
+                    " (proxied)".bright_black(),
+                );
+            }
+        }
+
+        if let Some(cors) = &state.cors {
+            cors.apply(origin.as_ref(), &mut response);
+        }
+
+        response
+            .headers_mut()
+            .insert(X_LAGON_REQUEST_ID, HeaderValue::from_str(&request_id)?);
+
+        return Ok(response);
+    } else if is_favicon {
+        tx.send_async(RunResult::Response(Response {
+            status: 404,
+            ..Default::default()
+        }))
+        .await
+        .unwrap_or(());
+    } else if let Some(bundle_error) = bundle_error {
+        // Show the bundling error directly in the browser
+        // until the Function is fixed
+        let mut headers = HashMap::with_capacity(1);
+        headers.insert("content-type".into(), vec!["text/plain".into()]);
+
+        tx.send_async(RunResult::Response(Response {
+            status: 500,
+            headers: Some(headers),
+            body: bundle_error.into(),
+        }))
+        .await
+        .unwrap_or(());
+    } else {
+        pending_upgrade = PendingUpgrade::from_request(&mut req);
+
+        match Request::from_hyper_streamed(req, 0, max_body_size) {
+            Ok((mut request, body)) => {
+                request.set_header(X_FORWARDED_FOR.to_string(), ip);
+                request.set_header(X_LAGON_REGION.to_string(), LOCAL_REGION.to_string());
+
+                let recorded_id = state.recorded_requests.lock().await.record(&request);
+                let body_stream = (!body.is_end_stream()).then(|| {
+                    record_request_body(
+                        stream_request_body(body, max_body_size, |_| {}),
+                        Arc::clone(&state),
+                        recorded_id,
+                    )
+                });
+
+                // Set after recording the request, so replays get their own id
+                request.set_header(X_LAGON_ID.to_string(), request_id.clone());
+
+                if let Some(every) = cold_start_every {
+                    let (guard, is_cold_start) = acquire_cold_start(&function, every).await;
+
+                    *cold_start_guard.lock().unwrap() = Some(guard);
+                    is_cold = Some(is_cold_start);
+                }
+
+                function
+                    .isolate_tx
+                    .send_async(IsolateEvent::Request(IsolateRequest {
+                        request,
+                        sender: tx,
+                        statistics: Some(statistics_tx),
+                        body_stream,
+                        connection: Some(connection_rx),
+                    }))
+                    .await
+                    .unwrap_or(());
+            }
+            Err(error) if error.is::<BodyTooLargeError>() => {
+                println!("{}", warn(&error.to_string()));
+
+                tx.send_async(RunResult::Response(text_response(413, "Payload Too Large")))
+                    .await
+                    .unwrap_or(());
+            }
+            Err(error) => {
+                println!("Error while parsing request: {error}");
+
+                tx.send_async(RunResult::Error("Error while parsing request".into()))
+                    .await
+                    .unwrap_or(());
+            }
+        };
+    }
+
+    // Handled in its own task, to still log the request once the client disconnected
+    let response = tokio::spawn(handle_response(
+        rx,
+        encoding,
+        (),
+        Box::new(move |event, _| match event {
+            ResponseEvent::Done(summary) => {
+                // Streamed responses are done once the last chunk has been sent, so
+                // the duration reported here includes the whole streaming time
+                let elapsed = start_time.elapsed();
+                // Sent right before the last result, so it's already received
+                let cpu_time = statistics_rx
+                    .try_recv()
+                    .ok()
+                    .map(|statistics| statistics.cpu_time.as_secs_f64() * 1000.0);
+
+                let mut fields = request_fields.clone();
+                fields.insert("status".into(), summary.status.into());
+                fields.insert("duration_ms".into(), (elapsed.as_millis() as u64).into());
+                fields.insert("streamed".into(), summary.streamed.into());
+
+                if let Some(cpu_time) = cpu_time {
+                    fields.insert("cpu_time_ms".into(), cpu_time.into());
+                }
+
+                if let Some(is_cold) = is_cold {
+                    fields.insert("cold".into(), is_cold.into());
+                }
+
+                let message = format!("{} {} {}", method, path, summary.status);
+                write_log_file(Level::Info, &message, &fields);
+
+                match log_format {
+                    LogFormat::Json => print_json(Level::Info, &message, fields),
+                    LogFormat::Text => {
+                        println!(
+                            "              {} {} {}{}{}",
+                            "↳".bright_black(),
+                            colored_status(summary.status),
+                            match cpu_time {
+                                Some(cpu_time) => format!(
+                                    "cpu: {:.1}ms, wall: {}ms",
+                                    cpu_time,
+                                    elapsed.as_millis()
+                                ),
+                                None => format!("{}ms", elapsed.as_millis()),
+                            }
+                            .bright_black(),
+                            if summary.streamed {
+                                " (streamed)".bright_black()
+                            } else {
+                                "".normal()
+                            },
+                            match is_cold {
+                                Some(true) => " (cold)".bright_black(),
+                                Some(false) => " (warm)".bright_black(),
+                                None => "".normal(),
+                            },
+                        );
+                    }
+                }
+
+                // The next request can now be handled, possibly
+                // by a new isolate
+                cold_start_guard.lock().unwrap().take();
+
+                if verbose {
+                    debug!("Response body size: {} bytes", summary.bytes);
+                }
+            }
+            ResponseEvent::StreamDoneNoDataError => {
+                println!(
+                    "{}",
+                    error("The stream was done before sending a response/data")
+                );
+            }
+            ResponseEvent::StreamDoneDataError => {
+                println!("{}", error("Got data after stream was done"));
+            }
+            ResponseEvent::UnexpectedStreamResult(result) => {
+                println!("{} {:?}", error("Unexpected stream result:"), result);
+            }
+            ResponseEvent::LimitsReached(result) => match result {
+                RunResult::Timeout => println!("{}", error("Function execution timed out")),
+                RunResult::CpuTimeLimit => {
+                    println!("{}", error("Function execution reached CPU time limit"))
+                }
+                _ => println!("{}", error("Function execution reached memory limit")),
+            },
+            ResponseEvent::Error(result) => {
+                let message = result.as_error();
+                println!("{}", error(&message));
+
+                if error_overlay {
+                    *on_event_handler_error.lock().unwrap() = Some(message);
+                }
+            }
+            ResponseEvent::Aborted => {
+                println!(
+                    "{}",
+                    warn("The client disconnected before the response was sent")
+                );
+            }
+            _ => {}
+        }),
+    ))
+    .await??;
+    let response = handle_websocket_upgrade(pending_upgrade, response)?;
+
+    // Swap the generic error page with the actual error
+    let mut response = match handler_error.lock().unwrap().take() {
+        Some(handler_error) => error_response(&handler_error, error_format)?,
+        None => response,
+    };
+
+    if let Some(cors) = &state.cors {
+        cors.apply(origin.as_ref(), &mut response);
+    }
+
+    response
+        .headers_mut()
+        .insert(X_LAGON_REQUEST_ID, HeaderValue::from_str(&request_id)?);
+
+    Ok(response)
+}
+
+// Send the message to the Function like a live request, and return its status
+async fn deliver_queued_message(
+    function: &FunctionState,
+    message: &QueuedMessage,
+    url: &str,
+    request_id: &str,
+) -> Result<u16, String> {
+    let mut request = message.to_request(url);
+    request.set_header(X_LAGON_REGION.to_string(), LOCAL_REGION.to_string());
+    request.set_header(X_LAGON_ID.to_string(), request_id.to_string());
+
+    let (tx, rx) = flume::unbounded();
+
+    function
+        .isolate_tx
+        .send_async(IsolateEvent::Request(IsolateRequest {
+            request,
+            sender: tx,
+            statistics: None,
+            body_stream: None,
+            connection: None,
+        }))
+        .await
+        .unwrap_or(());
+
+    match rx.recv_async().await {
+        Ok(RunResult::Response(response)) => Ok(response.status),
+        Ok(RunResult::Stream(StreamResult::Start(response))) => {
+            // Only the status matters, but the stream still has to be consumed
+            while let Ok(RunResult::Stream(StreamResult::Data(_))) = rx.recv_async().await {}
+
+            Ok(response.status)
+        }
+        Ok(RunResult::Error(error)) => Err(error),
+        Ok(RunResult::Timeout) => Err("Function execution timed out".into()),
+        Ok(RunResult::CpuTimeLimit) => Err("Function execution reached CPU time limit".into()),
+        Ok(RunResult::MemoryLimit(_)) => Err("Function execution reached memory limit".into()),
+        Ok(result) => Err(format!("Unexpected result: {result:?}")),
+        Err(_) => Err("The Function stopped before handling the message".into()),
+    }
+}
+
+// Messages are delivered as soon as they are sent, to the Function that sent
+// them. Deliveries that fail or return an error status are retried `retries` times
+async fn deliver_queued_messages(
+    rx: flume::Receiver<QueuedMessage>,
+    state: Arc<DevState>,
+    url: String,
+    retries: usize,
+    log_format: LogFormat,
+) {
+    while let Ok(message) = rx.recv_async().await {
+        let function = Arc::clone(state.find_function(&message.route));
+        let url = url.clone();
+
+        tokio::spawn(async move {
+            let attempts = retries + 1;
+
+            for attempt in 1..=attempts {
+                let start_time = Instant::now();
+                let request_id = generate_request_id();
+                let result = deliver_queued_message(&function, &message, &url, &request_id).await;
+                let elapsed = start_time.elapsed();
+
+                let mut fields = Map::new();
+                fields.insert("queue".into(), message.queue.clone().into());
+                fields.insert("path".into(), message.route.clone().into());
+                fields.insert("request_id".into(), request_id.clone().into());
+                fields.insert("attempt".into(), attempt.into());
+                fields.insert("duration_ms".into(), (elapsed.as_millis() as u64).into());
+
+                let (is_delivered, level, message_line) = match &result {
+                    Ok(status) => {
+                        fields.insert("status".into(), (*status).into());
+
+                        (
+                            *status < 400,
+                            Level::Info,
+                            format!("QUEUE {} {}", message.queue, status),
+                        )
+                    }
+                    Err(error) => {
+                        fields.insert("error".into(), error.clone().into());
+
+                        (
+                            false,
+                            Level::Error,
+                            format!("QUEUE {} failed", message.queue),
+                        )
+                    }
+                };
+
+                write_log_file(level, &message_line, &fields);
+
+                match log_format {
+                    LogFormat::Json => print_json(level, &message_line, fields),
+                    LogFormat::Text => {
+                        println!(
+                            "{} {} {} {} {}",
+                            format!("{}", Local::now().time()).bright_black(),
+                            "QUEUE".magenta(),
+                            message.queue,
+                            match &result {
+                                Ok(status) => colored_status(*status),
+                                Err(_) => "failed".red(),
+                            },
+                            format!(
+                                "{}ms, attempt {attempt}/{attempts} [{request_id}]",
+                                elapsed.as_millis()
+                            )
+                            .bright_black(),
+                        );
+
+                        if let Err(reason) = &result {
+                            println!("{}", error(reason));
+                        }
+                    }
+                }
+
+                if is_delivered {
+                    return;
+                }
+
+                if attempt < attempts {
+                    tokio::time::sleep(QUEUE_RETRY_DELAY * 2u32.pow(attempt as u32 - 1)).await;
+                }
+            }
+
+            println!(
+                "{}",
+                error(&format!(
+                    "Dropped a message of queue {} after {attempts} failed attempts",
+                    message.queue
+                ))
+            );
+        });
+    }
+}
+
+// Send a scheduled event to the Function's `scheduled` export, like a cron trigger
+async fn run_scheduled_event(
+    function: &FunctionState,
+    cron: &Cron,
+    scheduled_time: SystemTime,
+) -> Result<(), String> {
+    let (tx, rx) = flume::unbounded();
+
+    function
+        .isolate_tx
+        .send_async(IsolateEvent::Scheduled(IsolateScheduledEvent {
+            cron: cron.to_string(),
+            scheduled_time,
+            sender: tx,
+            statistics: None,
+        }))
+        .await
+        .unwrap_or(());
+
+    match rx.recv_async().await {
+        Ok(RunResult::Response(_)) => Ok(()),
+        Ok(RunResult::Error(error)) => Err(error),
+        Ok(RunResult::Timeout) => Err("Function execution timed out".into()),
+        Ok(RunResult::CpuTimeLimit) => Err("Function execution reached CPU time limit".into()),
+        Ok(RunResult::MemoryLimit(_)) => Err("Function execution reached memory limit".into()),
+        Ok(result) => Err(format!("Unexpected result: {result:?}")),
+        Err(_) => Err("The Function stopped before handling the event".into()),
+    }
+}
+
+// Logged like the requests of the Function
+async fn fire_cron(
+    function: &FunctionState,
+    cron: &Cron,
+    scheduled_time: SystemTime,
+    log_format: LogFormat,
+) {
+    let start_time = Instant::now();
+    let result = run_scheduled_event(function, cron, scheduled_time).await;
+    let elapsed = start_time.elapsed();
+
+    let mut fields = Map::new();
+    fields.insert("cron".into(), cron.to_string().into());
+    fields.insert("path".into(), function.route.clone().into());
+    fields.insert("duration_ms".into(), (elapsed.as_millis() as u64).into());
+
+    let (level, message_line) = match &result {
+        Ok(()) => (Level::Info, format!("CRON {cron}")),
+        Err(error) => {
+            fields.insert("error".into(), error.clone().into());
+
+            (Level::Error, format!("CRON {cron} failed"))
+        }
+    };
+
+    write_log_file(level, &message_line, &fields);
+
+    match log_format {
+        LogFormat::Json => print_json(level, &message_line, fields),
+        LogFormat::Text => {
+            println!(
+                "{} {} {} {} {}",
+                format!("{}", Local::now().time()).bright_black(),
+                "CRON".magenta(),
+                cron,
+                match &result {
+                    Ok(()) => "done".green(),
+                    Err(_) => "failed".red(),
+                },
+                format!("{}ms → {}", elapsed.as_millis(), function.route).bright_black(),
+            );
+
+            if let Err(reason) = &result {
+                println!("{}", error(reason));
+            }
+        }
+    }
+}
+
+// Runs until the dev server stops. Invocations missed while the
+// scheduled event was running (or the computer slept) are skipped
+async fn schedule_cron(function: Arc<FunctionState>, cron: Cron, log_format: LogFormat) {
+    while let Some(next) = cron.next_after(&Utc::now()) {
+        tokio::time::sleep((next - Utc::now()).to_std().unwrap_or_default()).await;
+
+        fire_cron(&function, &cron, SystemTime::from(next), log_format).await;
+    }
+}
+
+// What the isolate is created from, sent again to
+// the isolate thread each time one of them changes
+struct IsolateSource {
+    index: Vec<u8>,
+    environment_variables: HashMap<String, String>,
+}
+
+// A Function mounted with `--function`, before its
+// isolate thread and watcher are started
+struct FunctionMount {
+    function: Arc<FunctionState>,
+    root: PathBuf,
+    function_config: FunctionConfig,
+    index: Vec<u8>,
+    rx: flume::Receiver<IsolateEvent>,
+}
+
+#[derive(Clone)]
+struct IsolateSettings {
+    timeout: Duration,
+    startup_timeout: Duration,
+    // In MB (MegaBytes)
+    memory: usize,
+    // Each isolate created gets a new inspector session
+    inspector: Option<InspectorServer>,
+    fs_root: Option<PathBuf>,
+    snapshot_blob: Option<&'static [u8]>,
+    // Shared by all the Functions, and kept across reloads
+    kv: Arc<dyn KvBackend>,
+    // Delivers the messages back to the Function, so each Function has its own
+    queue: Arc<dyn QueueBackend>,
+    // The maximum number of isolates handling requests at the same time
+    concurrency: usize,
+    // Shared by all the Functions, keyed by the hash of their code
+    code_caches: Arc<CodeCaches>,
+}
+
+// Sent each time an isolate has been evaluated
+struct Evaluation {
+    compilation_error: Option<String>,
+    startup_time: Duration,
+}
+
+// Isolates created from the same source, one after the other on a dedicated
+// thread, e.g after a timeout or to simulate cold starts. Running them on their
+// own thread allows to evaluate a new source while the previous one is serving.
+struct IsolateGeneration {
+    tx: flume::Sender<IsolateEvent>,
+    evaluated_rx: flume::Receiver<Evaluation>,
+    thread: JoinHandle<()>,
+}
+
+impl IsolateGeneration {
+    fn spawn(
+        source: IsolateSource,
+        settings: IsolateSettings,
+        mocks: Option<Arc<StdRwLock<Mocks>>>,
+    ) -> Self {
+        let handle = Handle::current();
+        let (tx, rx) = flume::unbounded();
+        let (evaluated_tx, evaluated_rx) = flume::unbounded();
+        let concurrency = settings.concurrency;
+        let code_caches = Arc::clone(&settings.code_caches);
+        let code_cache_key = code_cache_key(&source.index, &source.environment_variables);
+        let factory_code_cache_key = code_cache_key.clone();
+
+        let factory: IsolateOptionsFactory = Arc::new(move || {
+            let mut options = IsolateOptions::new(
+                String::from_utf8(source.index.clone()).expect("Code is not UTF-8"),
+            )
+            .timeout(settings.timeout)
+            .startup_timeout(settings.startup_timeout)
+            .memory(settings.memory)
+            .metadata(Some((String::from(""), String::from(""))))
+            .environment_variables(source.environment_variables.clone())
+            .kv_backend(Arc::clone(&settings.kv))
+            .queue_backend(Arc::clone(&settings.queue));
+
+            if let Some(mocks) = &mocks {
+                let mocks = Arc::clone(mocks);
+
+                options = options.on_fetch_callback(Rc::new(move |request: &Request| {
+                    mocks.read().unwrap().find_response(request)
+                }));
+            }
+
+            if let Some(fs_root) = &settings.fs_root {
+                options = options.allow_fs(fs_root.clone());
+            }
+
+            if let Some(inspector) = &settings.inspector {
+                options = options.inspector(inspector.session());
+            }
+
+            if let Some(snapshot_blob) = settings.snapshot_blob {
+                options = options.snapshot_blob(snapshot_blob);
+            }
+
+            options = options.on_memory_pressure_callback(Box::new(|_, statistics| {
+                println!(
+                    "{}",
+                    warn(&format!(
+                        "Heap usage is close to the memory limit: {}",
+                        format_heap_statistics(&statistics)
+                    ))
+                );
+            }));
+
+            if let Some(code_cache) = settings.code_caches.get(&factory_code_cache_key) {
+                options = options.code_cache(code_cache);
+            }
+
+            let evaluated_tx = evaluated_tx.clone();
+
+            options.hook(Box::new(move |_, event| match event {
+                IsolateHookEvent::EvaluationFinished { duration, error } => {
+                    evaluated_tx
+                        .send(Evaluation {
+                            compilation_error: error.map(String::from),
+                            startup_time: *duration,
+                        })
+                        .unwrap_or(());
+                }
+                // Captured when the limit was reached, to see what filled the heap
+                IsolateHookEvent::Terminated {
+                    result: Some(RunResult::MemoryLimit(statistics)),
+                } => {
+                    println!(
+                        "{}",
+                        error(&format!(
+                            "Heap when the memory limit was reached: {}",
+                            format_heap_statistics(statistics)
+                        ))
+                    );
+                }
+                _ => {}
+            }))
+        });
+
+        let pool_options = IsolatePoolOptions::new(concurrency).on_evaluate_callback(Arc::new(
+            move |isolate, _| {
+                if let Some(code_cache) = isolate.get_code_cache() {
+                    code_caches.insert(code_cache_key.clone(), code_cache.to_vec());
+                }
+            },
+        ));
+
+        // New isolates are created once the previous ones are terminated,
+        // or when all of them are busy with `--concurrency`
+        let thread = std::thread::spawn(move || {
+            handle.block_on(IsolatePool::new(factory, pool_options, rx).run());
+        });
+
+        IsolateGeneration {
+            tx,
+            evaluated_rx,
+            thread,
+        }
+    }
+
+    // Dropping the sender lets the isolate finish its in-flight
+    // requests (bounded by the timeout) before its thread stops
+    fn retire(self) -> JoinHandle<()> {
+        self.thread
+    }
+}
+
+// Each Function has its own thread, which forwards the events of the server
+// to the current isolate generation. When the source changes, requests are
+// queued until the new isolate has been evaluated, and only then switched to it.
+fn spawn_isolate_thread(
+    source: IsolateSource,
+    function: Arc<FunctionState>,
+    rx: flume::Receiver<IsolateEvent>,
+    index_rx: flume::Receiver<IsolateSource>,
+    settings: IsolateSettings,
+    mocks: Option<Arc<StdRwLock<Mocks>>>,
+    is_shutting_down: Arc<AtomicBool>,
+) -> JoinHandle<()> {
+    let handle = Handle::current();
+
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            let mut current = IsolateGeneration::spawn(source, settings.clone(), mocks.clone());
+            let mut retired = Vec::new();
+            // Isolates are terminated after missing two heartbeats, so
+            // a stuck evaluation ends within twice the startup timeout
+            let evaluation_timeout = match settings.startup_timeout.is_zero() {
+                true => RELOAD_EVALUATION_TIMEOUT,
+                false => settings.startup_timeout * 3,
+            };
+
+            // Requests are queued until the first isolate has been evaluated
+            if let Ok(evaluation) = current.evaluated_rx.recv_async().await {
+                if evaluation.compilation_error.is_none() {
+                    print_startup_time(&function, evaluation.startup_time);
+                }
+
+                function.isolate_generation.fetch_add(1, Ordering::SeqCst);
+                function
+                    .is_ready
+                    .store(evaluation.compilation_error.is_none(), Ordering::SeqCst);
+            }
+
+            loop {
+                tokio::select! {
+                    event = rx.recv_async() => {
+                        let event = match event {
+                            Ok(event) => event,
+                            Err(_) => break,
+                        };
+                        let is_terminate = matches!(event, IsolateEvent::Terminate(_));
+
+                        current.tx.send_async(event).await.unwrap_or(());
+
+                        if is_terminate && is_shutting_down.load(Ordering::SeqCst) {
+                            break;
+                        }
+                    }
+                    evaluation = current.evaluated_rx.recv_async() => {
+                        // The current generation created a new isolate
+                        if let Ok(evaluation) = evaluation {
+                            function.isolate_generation.fetch_add(1, Ordering::SeqCst);
+                            function
+                                .is_ready
+                                .store(evaluation.compilation_error.is_none(), Ordering::SeqCst);
+                        }
+                    }
+                    new_source = index_rx.recv_async() => {
+                        let new_source = match new_source {
+                            Ok(new_source) => new_source,
+                            Err(_) => break,
+                        };
+
+                        let candidate =
+                            IsolateGeneration::spawn(new_source, settings.clone(), mocks.clone());
+
+                        // Events aren't received from `rx` while waiting, so
+                        // new requests are queued until the switch
+                        let (compilation_error, startup_time) =
+                            match tokio::time::timeout(evaluation_timeout, candidate.evaluated_rx.recv_async()).await {
+                                Ok(Ok(evaluation)) => {
+                                    (evaluation.compilation_error, Some(evaluation.startup_time))
+                                }
+                                Ok(Err(_)) => (None, None),
+                                // The queued requests are sent to the current isolate. The candidate
+                                // isn't joined on shutdown since it might never finish
+                                Err(_) => {
+                                    println!(
+                                        "{}",
+                                        error("The new version of the Function took too long to evaluate, keeping the previous one")
+                                    );
+
+                                    drop(candidate.retire());
+                                    continue;
+                                }
+                            };
+                        let is_ready = function.is_ready.load(Ordering::SeqCst);
+
+                        match compilation_error {
+                            // Keep serving the previous version if it works. Otherwise,
+                            // switch anyway so requests show the latest error
+                            Some(compilation_error) if is_ready => {
+                                println!(
+                                    "{}",
+                                    error(&format!(
+                                        "Failed to evaluate the new version of the Function, keeping the previous one: {compilation_error}"
+                                    ))
+                                );
+
+                                retired.push(candidate.retire());
+                            }
+                            compilation_error => {
+                                retired.push(std::mem::replace(&mut current, candidate).retire());
+
+                                if let (None, Some(startup_time)) = (&compilation_error, startup_time) {
+                                    print_startup_time(&function, startup_time);
+                                }
+
+                                function.isolate_generation.fetch_add(1, Ordering::SeqCst);
+                                function
+                                    .is_ready
+                                    .store(compilation_error.is_none(), Ordering::SeqCst);
+                            }
+                        }
+
+                        retired.retain(|thread: &JoinHandle<()>| !thread.is_finished());
+                    }
+                }
+            }
+
+            for thread in std::iter::once(current.retire()).chain(retired) {
+                thread.join().unwrap_or(());
+            }
+        });
+    })
+}
+
+// A Function mounted on a route with `--function`. Only its code and
+// the environment variables are reloaded: assets and mocks are handled
+// by the main Function's watcher.
+fn watch_mounted_function(
+    root: PathBuf,
+    function_config: FunctionConfig,
+    function: Arc<FunctionState>,
+    index: Vec<u8>,
+    index_tx: flume::Sender<IsolateSource>,
+    environment: (PathBuf, Vec<PathBuf>, Vec<String>),
+    should_clear: bool,
+) -> Result<RecommendedWatcher> {
+    let (env_root, env, env_vars) = environment;
+    let (tx, rx) = flume::unbounded();
+    let mut watcher = RecommendedWatcher::new(
+        move |event: notify::Result<Event>| tx.send(event).unwrap_or(()),
+        Config::default().with_poll_interval(Duration::from_secs(1)),
+    )?;
+
+    let watch_root = root.canonicalize()?;
+    let index_path = root.join(&function_config.index);
+    let watch_env_files = env
+        .iter()
+        .map(|path| env_root.join(path).canonicalize())
+        .collect::<io::Result<Vec<_>>>()?;
+
+    watcher.watch(&watch_root, RecursiveMode::Recursive)?;
+    let watch_bundle_paths = watch_bundle_paths(&mut watcher, &watch_root)?;
+
+    for env_file in &watch_env_files {
+        if !env_file.starts_with(&watch_root) {
+            if let Some(parent) = env_file.parent() {
+                watcher.watch(parent, RecursiveMode::NonRecursive)?;
+            }
+        }
+    }
+
+    tokio::spawn(async move {
+        let mut index = index;
+
+        let get_changes = |event: notify::Result<Event>| match event {
+            Ok(event) => (
+                should_rebundle(
+                    &event,
+                    &watch_root,
+                    &None,
+                    &watch_env_files,
+                    &watch_bundle_paths,
+                ),
+                should_reload_config(&event, &watch_env_files),
+            ),
+            Err(_) => (false, false),
+        };
+
+        while let Ok(event) = rx.recv_async().await {
+            let (mut should_update, mut should_update_env) = get_changes(event);
+
+            if !should_update && !should_update_env {
+                continue;
+            }
+
+            while let Ok(Ok(event)) = tokio::time::timeout(WATCH_DEBOUNCE, rx.recv_async()).await {
+                let (update, update_env) = get_changes(event);
+
+                should_update |= update;
+                should_update_env |= update_env;
+            }
+
+            if should_update && !index_path.exists() {
+                continue;
+            }
+
+            print_reload_separator(should_clear);
+            println!(
+                "{}",
+                info(&format!(
+                    "Found change in Function mounted on {}, updating...",
+                    function.route
+                ))
+            );
+
+            // The environment variables are parsed again on each change, since
+            // the isolate is recreated from both the code and the variables
+            let environment_variables =
+                match parse_environment_variables(&env_root, &env, &env_vars, &function_config.env)
+                {
+                    Ok(environment_variables) => environment_variables,
+                    Err(err) => {
+                        println!(
+                            "{}",
+                            error(&format!("Failed to parse environment variables: {err}"))
+                        );
+                        continue;
+                    }
+                };
+
+            if should_update {
+                match bundle_function(&function_config, &root) {
+                    Ok((new_index, new_assets)) => {
+                        print_bundle_size(&new_index, &new_assets);
+
+                        *function.bundle_error.lock().await = None;
+                        function
+                            .bundle_size
+                            .store(new_index.len(), Ordering::SeqCst);
+                        index = new_index;
+                    }
+                    Err(err) => {
+                        println!("{}", error(&format!("Failed to bundle Function: {err}")));
+
+                        *function.bundle_error.lock().await = Some(err.to_string());
+                        continue;
+                    }
+                }
+            }
+
+            // The dev server is shutting down
+            if index_tx
+                .send_async(IsolateSource {
+                    index: index.clone(),
+                    environment_variables,
+                })
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    Ok(watcher)
+}
+
+#[derive(Clone, Copy)]
+struct RequestOptions {
+    verbose: bool,
+    error_overlay: bool,
+    log_format: LogFormat,
+    timeout: Duration,
+    startup_timeout: Duration,
+    cold_start_every: Option<usize>,
+    serve_index: bool,
+    max_body_size: usize,
+    compress: bool,
+}
+
+pub struct DevOptions {
+    pub port: Option<u16>,
+    pub hostname: Option<String>,
+    pub env: Vec<PathBuf>,
+    pub env_vars: Vec<String>,
+    pub allow_code_generation: bool,
+    // Directory the Function can read files from
+    pub allow_fs: Option<PathBuf>,
+    pub grace_period: Duration,
+    pub verbose: bool,
+    pub no_clear: bool,
+    pub error_overlay: bool,
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    pub self_signed: bool,
+    pub replay_buffer: usize,
+    // A zero duration disables the timeout
+    pub timeout: Duration,
+    pub startup_timeout: Duration,
+    // In MB (MegaBytes)
+    pub memory: usize,
+    // Recreate the isolate after this number of requests
+    pub cold_start_every: Option<usize>,
+    pub mock: Option<PathBuf>,
+    pub mock_strict: bool,
+    // Used to open the dev server's URL once it's ready
+    pub open: Option<Box<dyn BrowserOpener>>,
+    pub log_format: LogFormat,
+    // Minimum level of the Function's console logs
+    pub log_level: Option<LogLevel>,
+    // Render a listing of the public directory's folders without an index.html
+    pub serve_index: bool,
+    // Origin to forward requests to, optionally only for the given path prefixes
+    pub proxy: Option<String>,
+    pub proxy_paths: Vec<String>,
+    pub cors: Option<Cors>,
+    // Print the heap statistics of the isolate at this interval
+    pub heap_stats: Option<Duration>,
+    // Additional Functions to mount, as <ROUTE>=<PATH>
+    pub functions: Vec<String>,
+    // In bytes, larger request bodies are rejected with a 413 status
+    pub max_body_size: usize,
+    // In bytes, the least recently used responses of the Cache API are evicted past this size
+    pub cache_size: usize,
+    // Write the values of `Lagon.kv` to `.lagon/kv.json`, so they are kept across restarts
+    pub persist_kv: bool,
+    // How many times a failed delivery of a `Lagon.queue` message is retried
+    pub queue_retries: usize,
+    // The maximum number of isolates of each Function, created when the others are busy
+    pub concurrency: usize,
+    // Port to listen on for Chrome DevTools connections to the main Function
+    pub inspect: Option<u16>,
+    // Append every log line to this file, as JSON
+    pub log_file: Option<PathBuf>,
+    // In bytes, the log file is rotated once it reaches this size
+    pub log_file_max_size: u64,
+    // Built with `lagon snapshot`, loaded by every isolate
+    pub snapshot: Option<PathBuf>,
+    // Patterns of the names of the environment variables hidden from the logs
+    pub mask: Vec<String>,
+    pub no_mask: bool,
+    // Compress the Function's responses if the client accepts gzip or brotli
+    pub compress: bool,
+    // PEM files of root certificates trusted by fetch(), in addition to the system's ones
+    pub cacert: Vec<PathBuf>,
+    // Don't verify the certificates of fetch() calls
+    pub insecure: bool,
+    // Invoke the main Function's `scheduled` export once at startup
+    pub trigger_cron: Option<String>,
+}
+
+// Same defaults as the options of `lagon dev`
+impl Default for DevOptions {
+    fn default() -> Self {
+        DevOptions {
+            port: None,
+            hostname: None,
+            env: Vec::new(),
+            env_vars: Vec::new(),
+            allow_code_generation: false,
+            allow_fs: None,
+            grace_period: Duration::from_secs(5),
+            verbose: false,
+            no_clear: false,
+            error_overlay: false,
+            cert: None,
+            key: None,
+            self_signed: false,
+            replay_buffer: 25,
+            timeout: Duration::from_millis(1000),
+            startup_timeout: Duration::from_millis(2000),
+            memory: 128,
+            cold_start_every: None,
+            mock: None,
+            mock_strict: false,
+            open: None,
+            log_format: LogFormat::Text,
+            log_level: None,
+            serve_index: false,
+            proxy: None,
+            proxy_paths: Vec::new(),
+            cors: None,
+            heap_stats: None,
+            functions: Vec::new(),
+            max_body_size: 10 * 1024 * 1024,
+            cache_size: 64 * 1024 * 1024,
+            persist_kv: false,
+            queue_retries: 3,
+            concurrency: 1,
+            inspect: None,
+            log_file: None,
+            log_file_max_size: 10 * 1024 * 1024,
+            snapshot: None,
+            mask: DEFAULT_MASK_PATTERNS.map(String::from).to_vec(),
+            no_mask: false,
+            compress: false,
+            cacert: Vec::new(),
+            insecure: false,
+            trigger_cron: None,
+        }
+    }
+}
+
+fn remote_addr(conn: &Connection) -> SocketAddr {
+    match conn {
+        Either::Left(stream) => stream.remote_addr(),
+        Either::Right(stream) => stream.get_ref().0.remote_addr(),
+    }
+}
+
+// Accept plain TCP connections, or run the TLS handshake on each of them
+// if a TLS acceptor is given. Failed handshakes (e.g a browser rejecting
+// a self-signed certificate) only drop the connection.
+fn accept_connections(
+    mut incoming: AddrIncoming,
+    acceptor: Option<TlsAcceptor>,
+) -> BoxStream<'static, io::Result<Connection>> {
+    let connections = stream::poll_fn(move |cx| Pin::new(&mut incoming).poll_accept(cx));
+
+    match acceptor {
+        Some(acceptor) => connections
+            .map(move |stream| {
+                let acceptor = acceptor.clone();
+
+                async move { acceptor.accept(stream?).await }
+            })
+            .buffer_unordered(MAX_CONCURRENT_TLS_HANDSHAKES)
+            .filter_map(|result| async move {
+                match result {
+                    Ok(stream) => Some(Ok(Either::Right(stream))),
+                    Err(err) => {
+                        debug!("TLS handshake failed: {}", err);
+                        None
+                    }
+                }
+            })
+            .boxed(),
+        None => connections.map_ok(Either::Left).boxed(),
+    }
+}
+
+// Wait for the Function to be evaluated before opening the
+// browser, otherwise it would only show an error page
+async fn open_browser_when_ready(
+    opener: Box<dyn BrowserOpener>,
+    url: String,
+    is_ready: impl Fn() -> bool,
+) {
+    while !is_ready() {
+        tokio::time::sleep(OPEN_BROWSER_INTERVAL).await;
+    }
+
+    if let Err(err) = opener.open(&url) {
+        println!("{}", warn(&format!("Could not open the browser: {err}")));
+    }
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        signal(SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+// Restarting sends a change of the entrypoint to the watcher, so it
+// goes through the exact same path as editing the main Function
+async fn handle_shortcuts(
+    shortcuts: flume::Receiver<Shortcut>,
+    watcher_tx: flume::Sender<notify::Result<Event>>,
+    index_path: PathBuf,
+    url: String,
+    quit_tx: flume::Sender<()>,
+) {
+    while let Ok(shortcut) = shortcuts.recv_async().await {
+        match shortcut {
+            Shortcut::Restart => {
+                let event = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Any)))
+                    .add_path(index_path.clone());
+
+                watcher_tx.send(Ok(event)).unwrap_or(());
+            }
+            Shortcut::Clear => {
+                print!("\x1B[2J\x1B[1;1H");
+                io::stdout().flush().unwrap_or(());
+            }
+            Shortcut::Open => {
+                if let Err(err) = DefaultBrowser.open(&url) {
+                    println!("{}", warn(&format!("Could not open the browser: {err}")));
+                }
+            }
+            Shortcut::Quit => {
+                quit_tx.send_async(()).await.unwrap_or(());
+                break;
+            }
+        }
+    }
+}
+
+// V8 can only be initialized once per process, so it's shared by every
+// dev server started by the process and is never disposed
+fn init_runtime(options: RuntimeOptions) {
+    static RUNTIME: Once = Once::new();
+
+    RUNTIME.call_once(|| {
+        Runtime::new(options);
+    });
+}
+
+// Lets the dev server be embedded, e.g in a test harness. The logger and
+// the keyboard shortcuts are only set up if enabled, since both might
+// conflict with the ones of the host.
+#[derive(Default)]
+pub struct DevServerBuilder {
+    path: Option<PathBuf>,
+    client: Option<PathBuf>,
+    public_dir: Option<PathBuf>,
+    options: DevOptions,
+    install_logger: bool,
+    shortcuts: bool,
+}
+
+impl DevServerBuilder {
+    // A file or a directory containing a Function
+    pub fn path(mut self, path: PathBuf) -> Self {
+        self.path = Some(path);
+        self
+    }
+
+    pub fn client(mut self, client: PathBuf) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    pub fn public_dir(mut self, public_dir: PathBuf) -> Self {
+        self.public_dir = Some(public_dir);
+        self
+    }
+
+    pub fn options(mut self, options: DevOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn install_logger(mut self, install_logger: bool) -> Self {
+        self.install_logger = install_logger;
+        self
+    }
+
+    pub fn shortcuts(mut self, shortcuts: bool) -> Self {
+        self.shortcuts = shortcuts;
+        self
+    }
+
+    pub fn build(self) -> DevServer {
+        DevServer(self)
+    }
+}
+
+pub struct DevServer(DevServerBuilder);
+
+// Returned once the dev server is started, to shut it down
+pub struct DevServerHandle {
+    server: tokio::task::JoinHandle<hyper::Result<()>>,
+    shutdown_tx: flume::Sender<()>,
+    quit_rx: flume::Receiver<()>,
+    grace_period: Duration,
+    is_shutting_down: Arc<AtomicBool>,
+    state: Arc<DevState>,
+    isolate_threads: Vec<JoinHandle<()>>,
+    watcher: RecommendedWatcher,
+    mounted_watchers: Vec<RecommendedWatcher>,
+    // Scheduled invocations of the crons, stopped at shutdown
+    schedulers: Vec<tokio::task::JoinHandle<()>>,
+    _inspector: Option<InspectorServer>,
+    // Kept until the end, so the remaining lines are flushed at shutdown
+    _log_file: Option<LogFile>,
+}
+
+impl DevServer {
+    pub fn builder() -> DevServerBuilder {
+        DevServerBuilder::default()
+    }
+
+    // Bundles the Functions and binds the server, which then runs in
+    // the background until `DevServerHandle::shutdown` is called
+    pub async fn start(self) -> Result<(SocketAddr, DevServerHandle)> {
+        let DevServerBuilder {
+            path,
+            client,
+            public_dir,
+            options,
+            install_logger,
+            shortcuts: listen_for_shortcuts,
+        } = self.0;
+        let DevOptions {
+            port,
+            hostname,
+            env,
+            env_vars,
+            allow_code_generation,
+            allow_fs,
+            grace_period,
+            verbose,
+            no_clear,
+            error_overlay,
+            cert,
+            key,
+            self_signed,
+            replay_buffer,
+            timeout,
+            startup_timeout,
+            memory,
+            cold_start_every,
+            mock,
+            mock_strict,
+            open,
+            log_format,
+            log_level,
+            serve_index,
+            proxy,
+            proxy_paths,
+            cors,
+            heap_stats,
+            functions,
+            max_body_size,
+            cache_size,
+            persist_kv,
+            queue_retries,
+            concurrency,
+            inspect,
+            log_file,
+            log_file_max_size,
+            snapshot,
+            mask,
+            no_mask,
+            compress,
+            cacert,
+            insecure,
+            trigger_cron,
+        } = options;
+
+        // Nothing is masked without patterns
+        let mask = match no_mask {
+            true => Vec::new(),
+            false => mask,
+        };
+
+        // Set up first, so colors are disabled for the whole output in JSON mode
+        if install_logger {
+            init_logger(verbose, log_format, log_level)?;
+        }
+
+        let log_file = log_file
+            .map(|log_file| LogFile::open(log_file, log_file_max_size))
+            .transpose()?;
+
+        // Escape codes are only used if the output is an interactive terminal
+        // that supports them, see https://no-color.org
+        let should_clear = !no_clear
+            && log_format == LogFormat::Text
+            && io::stdout().is_terminal()
+            && env::var_os("NO_COLOR").is_none();
+
+        let (root, function_config) = resolve_path(path, client, public_dir)?;
+        let (index, assets) = bundle_function(&function_config, &root)?;
+        print_bundle_size(&index, &assets);
+
+        warn_reserved_routes(&index);
+
+        // Invalid expressions are reported right away, instead of never firing
+        let trigger_cron = trigger_cron.map(|cron| cron.parse::<Cron>()).transpose()?;
+        let (tx, rx) = flume::unbounded();
+        let (index_tx, index_rx) = flume::unbounded();
+        let function = Arc::new(FunctionState::new(
+            "/".into(),
+            tx,
+            index.len(),
+            parse_crons(&function_config)?,
+        ));
+
+        let mut mounted_functions = Vec::with_capacity(functions.len());
+
+        for mount in &functions {
+            let (route, path) = parse_function_mount(mount)?;
+
+            if mounted_functions
+                .iter()
+                .any(|mount: &FunctionMount| mount.function.route == route)
+            {
+                return Err(anyhow!("A Function is already mounted on {}", route));
+            }
+
+            let (root, function_config) = resolve_path(Some(path), None, None)?;
+            let (index, mount_assets) = bundle_function(&function_config, &root)?;
+            print_bundle_size(&index, &mount_assets);
+
+            warn_reserved_routes(&index);
+
+            if !mount_assets.is_empty() {
+                println!(
+                "{}",
+                warn(&format!(
+                    "Assets of the Function mounted on {route} aren't served, only the main Function's are"
+                ))
+            );
+            }
+
+            let crons = parse_crons(&function_config)
+                .map_err(|err| anyhow!("Function mounted on {}: {}", route, err))?;
+            let (tx, rx) = flume::unbounded();
+
+            mounted_functions.push(FunctionMount {
+                function: Arc::new(FunctionState::new(route, tx, index.len(), crons)),
+                root,
+                function_config,
+                index,
+                rx,
+            });
+        }
+
+        // Longest routes first, so nested routes take precedence
+        mounted_functions.sort_by(|a, b| b.function.route.len().cmp(&a.function.route.len()));
+
+        let state = Arc::new(DevState {
+            assets: Mutex::new(assets),
+            recorded_requests: Mutex::new(RecordedRequests::new(replay_buffer)),
+            assets_fallback: function_config.assets_fallback.clone(),
+            proxy: match proxy {
+                Some(proxy) => Some(Proxy::new(&proxy, proxy_paths)?),
+                None => None,
+            },
+            cors,
+            function: Arc::clone(&function),
+            mounted_functions: mounted_functions
+                .iter()
+                .map(|mount| Arc::clone(&mount.function))
+                .collect(),
+        });
+
+        // Invalid certificates are reported right away, instead of on the first fetch() call
+        let mut extra_root_certificates = Vec::new();
+
+        for path in &cacert {
+            extra_root_certificates.extend(read_certificates(&root.join(path))?);
+        }
+
+        validate_root_certificates(&extra_root_certificates)?;
+
+        if insecure {
+            println!(
+                "{}",
+                warn("--insecure disables the verification of TLS certificates in fetch() calls, never use it in production")
+            );
+        }
+
+        init_runtime(
+            RuntimeOptions::default()
+                .allow_code_generation(allow_code_generation)
+                .extra_root_certificates(extra_root_certificates)
+                .danger_accept_invalid_certificates(insecure)
+                // Shared by all the Functions of the dev server, and kept across reloads
+                .cache_backend(Arc::new(MemoryCacheBackend::new(cache_size))),
+        );
+        let addr: SocketAddr = format!(
+            "{}:{}",
+            hostname.unwrap_or_else(|| "127.0.0.1".into()),
+            port.unwrap_or(1234)
+        )
+        .parse()?;
+
+        let server_public_dir = function_config
+            .assets
+            .as_ref()
+            .map(|assets| root.join(assets));
+        let environment_variables =
+            parse_environment_variables(&root, &env, &env_vars, &function_config.env)?;
+        set_masked_values(&environment_variables, &mask);
+        let mock_path = mock.map(|mock| root.join(mock));
+        let mocks = match &mock_path {
+            Some(mock_path) => Some(Arc::new(StdRwLock::new(load_mocks(
+                mock_path,
+                mock_strict,
+            )?))),
+            None => None,
+        };
+
+        // Load the certificate before starting anything, so an
+        // invalid one is reported right away
+        let tls_config = match (cert, key) {
+            (Some(cert), Some(key)) => Some(load_tls_config(&root.join(cert), &root.join(key))?),
+            _ if self_signed => Some(self_signed_tls_config()?),
+            _ => None,
+        };
+        let protocol = if tls_config.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+
+        // Relative to the Function, like other paths
+        let fs_root = match allow_fs {
+            Some(dir) => {
+                let dir = root.join(dir);
+
+                if !dir.is_dir() {
+                    return Err(anyhow!(
+                        "Directory {:?} to allow access to does not exist",
+                        dir
+                    ));
+                }
+
+                Some(dir.canonicalize()?)
+            }
+            None => None,
+        };
+        // DevTools can only debug a single isolate
+        let inspector = match inspect {
+            Some(_) if concurrency > 1 => {
+                return Err(anyhow!(
+                    "--inspect can't be used with --concurrency greater than 1"
+                ))
+            }
+            Some(port) => Some(InspectorServer::start(port)?),
+            None => None,
+        };
+        let snapshot_blob = match snapshot {
+            Some(snapshot) => Some(load_snapshot(&snapshot)?),
+            None => None,
+        };
+        let (queue_tx, queue_rx) = flume::unbounded();
+        let kv: Arc<dyn KvBackend> = match persist_kv {
+            true => Arc::new(MemoryKvBackend::persisted(
+                root.join(".lagon").join("kv.json"),
+            )?),
+            false => Arc::new(MemoryKvBackend::new()),
+        };
+        let settings = IsolateSettings {
+            timeout,
+            startup_timeout,
+            memory,
+            inspector: inspector.clone(),
+            fs_root: fs_root.clone(),
+            snapshot_blob,
+            kv,
+            queue: Arc::new(LocalQueueBackend::new("/".into(), queue_tx.clone())),
+            concurrency,
+            code_caches: Arc::new(CodeCaches::default()),
+        };
+        let is_shutting_down = Arc::new(AtomicBool::new(false));
+
+        let mut isolate_threads = vec![spawn_isolate_thread(
+            IsolateSource {
+                index: index.clone(),
+                environment_variables: environment_variables.clone(),
+            },
+            Arc::clone(&function),
+            rx,
+            index_rx,
+            settings.clone(),
+            mocks.clone(),
+            Arc::clone(&is_shutting_down),
+        )];
+        let mut mounted_watchers = Vec::with_capacity(mounted_functions.len());
+        let mut routes = vec![("/".to_string(), root.join(&function_config.index))];
+
+        // Each mounted Function has its own isolate thread and watcher,
+        // so it's reloaded independently of the other Functions
+        for mount in mounted_functions {
+            let (mount_index_tx, mount_index_rx) = flume::unbounded();
+
+            validate_environment_variables(&mount.function_config.env, &environment_variables)
+                .map_err(|err| anyhow!("Function mounted on {}: {}", mount.function.route, err))?;
+
+            routes.push((
+                mount.function.route.clone(),
+                mount.root.join(&mount.function_config.index),
+            ));
+
+            isolate_threads.push(spawn_isolate_thread(
+                IsolateSource {
+                    index: mount.index.clone(),
+                    environment_variables: environment_variables.clone(),
+                },
+                Arc::clone(&mount.function),
+                mount.rx,
+                mount_index_rx,
+                // Only the main Function can be debugged
+                IsolateSettings {
+                    inspector: None,
+                    queue: Arc::new(LocalQueueBackend::new(
+                        mount.function.route.clone(),
+                        queue_tx.clone(),
+                    )),
+                    ..settings.clone()
+                },
+                mocks.clone(),
+                Arc::clone(&is_shutting_down),
+            ));
+
+            mounted_watchers.push(watch_mounted_function(
+                mount.root,
+                mount.function_config,
+                mount.function,
+                mount.index,
+                mount_index_tx,
+                (root.clone(), env.clone(), env_vars.clone()),
+                should_clear,
+            )?);
+        }
+
+        routes.sort();
+
+        let server_state = Arc::clone(&state);
+        let mut incoming = AddrIncoming::bind(&addr)?;
+        // Like in production, disable Nagle's algorithm so streamed chunks aren't delayed
+        incoming.set_nodelay(true);
+
+        // The port might have been resolved by the OS if it was set to 0
+        let addr = incoming.local_addr();
+        let connections = accept_connections(incoming, tls_config.map(TlsAcceptor::from));
+
+        let server = Server::builder(accept::from_stream(connections)).serve(make_service_fn(
+            move |conn: &Connection| {
+                let public_dir = server_public_dir.clone();
+                let state = Arc::clone(&server_state);
+
+                let ip = remote_addr(conn).ip().to_string();
+
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        handle_request(
+                            req,
+                            public_dir.clone(),
+                            ip.clone(),
+                            Arc::clone(&state),
+                            RequestOptions {
+                                verbose,
+                                error_overlay,
+                                log_format,
+                                timeout,
+                                startup_timeout,
+                                cold_start_every,
+                                serve_index,
+                                max_body_size,
+                                compress,
+                            },
+                        )
+                    }))
+                }
+            },
+        ));
+
+        let (shutdown_tx, shutdown_rx) = flume::bounded(1);
+        let (quit_tx, quit_rx) = flume::bounded(1);
+        let server = tokio::spawn(server.with_graceful_shutdown(async move {
+            shutdown_rx.recv_async().await.unwrap_or(());
+        }));
+
+        let (tx, rx) = flume::unbounded();
+        let shortcuts_tx = tx.clone();
+        let mut watcher = RecommendedWatcher::new(
+            move |event: notify::Result<Event>| tx.send(event).unwrap_or(()),
+            Config::default().with_poll_interval(Duration::from_secs(1)),
+        )?;
+
+        // Watch the whole Function directory and not only the entrypoint, so changes
+        // to imported files also trigger a rebuild. Watching the directory also means
+        // the watcher survives editors that delete and recreate files on save.
+        let watch_root = root.canonicalize()?;
+        let watch_public_dir = function_config
+            .assets
+            .as_ref()
+            .and_then(|assets| root.join(assets).canonicalize().ok());
+        let index_path = root.join(&function_config.index);
+        let watch_index_path = index_path.canonicalize()?;
+        let watch_env_files = env
+            .iter()
+            .map(|path| root.join(path).canonicalize())
+            .collect::<io::Result<Vec<_>>>()?;
+        let watch_mock_files = mock_path
+            .iter()
+            .map(|path| path.canonicalize())
+            .collect::<io::Result<Vec<_>>>()?;
+        let watch_config_files = [watch_env_files.as_slice(), watch_mock_files.as_slice()].concat();
+
+        watcher.watch(&watch_root, RecursiveMode::Recursive)?;
+
+        // Config files outside of the Function directory are watched through their
+        // parent directory, for the same reason as the Function directory above
+        for config_file in &watch_config_files {
+            if !config_file.starts_with(&watch_root) {
+                if let Some(parent) = config_file.parent() {
+                    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+                }
+            }
+        }
+
+        // The public directory can live outside of the Function directory
+        if let Some(public_dir) = &watch_public_dir {
+            if !public_dir.starts_with(&watch_root) {
+                watcher.watch(public_dir, RecursiveMode::Recursive)?;
+            }
+        }
+
+        let watch_bundle_paths = watch_bundle_paths(&mut watcher, &watch_root)?;
+
+        let open_state = Arc::clone(&state);
+        let handle_state = Arc::clone(&state);
+
+        tokio::spawn(async move {
+            let mut index = index;
+            let mut environment_variables = environment_variables;
+
+            let get_changes = |event: notify::Result<Event>| match event {
+                Ok(event) => (
+                    should_rebundle(
+                        &event,
+                        &watch_root,
+                        &watch_public_dir,
+                        &watch_config_files,
+                        &watch_bundle_paths,
+                    ),
+                    should_reload_config(&event, &watch_env_files),
+                    should_reload_config(&event, &watch_mock_files),
+                    should_reload_assets(&event, &watch_public_dir),
+                ),
+                Err(_) => (false, false, false, false),
+            };
+
+            while let Ok(event) = rx.recv_async().await {
+                let (
+                    mut should_update,
+                    mut should_update_env,
+                    mut should_update_mocks,
+                    mut should_update_assets,
+                ) = get_changes(event);
+
+                if !should_update
+                    && !should_update_env
+                    && !should_update_mocks
+                    && !should_update_assets
+                {
+                    continue;
+                }
+
+                // A single save can touch multiple files: wait for the
+                // events to settle so we only rebundle once
+                while let Ok(Ok(event)) =
+                    tokio::time::timeout(WATCH_DEBOUNCE, rx.recv_async()).await
+                {
+                    let (update, update_env, update_mocks, update_assets) = get_changes(event);
+
+                    should_update |= update;
+                    should_update_env |= update_env;
+                    should_update_mocks |= update_mocks;
+                    should_update_assets |= update_assets;
+                }
+
+                // Mocks are read on each fetch() call, so
+                // they don't require restarting the isolate
+                if let (true, Some(mocks), Some(mock_path)) =
+                    (should_update_mocks, &mocks, &mock_path)
+                {
+                    println!("{}", info("Found change in mocks, reloading..."));
+
+                    match load_mocks(mock_path, mock_strict) {
+                        Ok(new_mocks) => *mocks.write().unwrap() = new_mocks,
+                        Err(err) => println!("{}", error(&format!("Failed to load mocks: {err}"))),
+                    }
+                }
+
+                if should_update || should_update_env {
+                    // The entrypoint might have been deleted and not yet recreated,
+                    // in which case we'll receive another event once it's back
+                    if should_update && !index_path.exists() {
+                        continue;
+                    }
+
+                    let changes = match (should_update, should_update_env) {
+                        (true, true) => "code and environment variables",
+                        (true, false) => "code",
+                        _ => "environment variables",
+                    };
+
+                    print_reload_separator(should_clear);
+                    println!(
+                        "{}",
+                        info(&format!("Found change in {changes}, updating..."))
+                    );
+
+                    let mut has_changed = false;
+
+                    // Like bundling errors, keep the previous environment
+                    // variables if the env files can't be parsed
+                    if should_update_env {
+                        match parse_environment_variables(
+                            &root,
+                            &env,
+                            &env_vars,
+                            &function_config.env,
+                        ) {
+                            Ok(new_environment_variables) => {
+                                set_masked_values(&new_environment_variables, &mask);
+                                environment_variables = new_environment_variables;
+                                has_changed = true;
+                            }
+                            Err(err) => {
+                                println!(
+                                    "{}",
+                                    error(&format!("Failed to parse environment variables: {err}"))
+                                );
+                            }
+                        }
+                    }
+
+                    // Keep serving the last working version of the Function if bundling
+                    // fails, and retry on the next change
+                    if should_update {
+                        match bundle_function(&function_config, &root) {
+                            Ok((new_index, new_assets)) => {
+                                print_bundle_size(&new_index, &new_assets);
+                                warn_reserved_routes(&new_index);
+
+                                *state.function.bundle_error.lock().await = None;
+                                *state.assets.lock().await = new_assets;
+                                state
+                                    .function
+                                    .bundle_size
+                                    .store(new_index.len(), Ordering::SeqCst);
+                                index = new_index;
+                                has_changed = true;
+                            }
+                            Err(err) => {
+                                println!("{}", error(&format!("Failed to bundle Function: {err}")));
+
+                                *state.function.bundle_error.lock().await = Some(err.to_string());
+                            }
+                        }
+                    }
+
+                    // The dev server is shutting down
+                    if has_changed
+                        && index_tx
+                            .send_async(IsolateSource {
+                                index: index.clone(),
+                                environment_variables: environment_variables.clone(),
+                            })
+                            .await
+                            .is_err()
+                    {
+                        break;
+                    }
+                } else if let (true, Some(public_dir)) = (should_update_assets, &watch_public_dir) {
+                    // Only the public directory changed, so we don't need to
+                    // restart the isolate: re-scanning the assets is enough
+                    println!(
+                        "{}",
+                        info("Found change in public directory, updating assets...")
+                    );
+
+                    let mut new_assets = match read_assets(public_dir) {
+                        Ok(new_assets) => new_assets,
+                        Err(err) => {
+                            println!("{}", error(&format!("Failed to read assets: {err}")));
+                            continue;
+                        }
+                    };
+                    let mut assets = state.assets.lock().await;
+
+                    // The client file is bundled and isn't part of the public directory
+                    if let Some(client) = &function_config.client {
+                        let client_asset = get_client_asset_name(client);
+
+                        if let Some(content) = assets.remove(&client_asset) {
+                            new_assets.insert(client_asset, content);
+                        }
+                    }
+
+                    *assets = new_assets;
+                }
+            }
+        });
+
+        println!();
+        println!("{}", success("Dev Server started!"));
+
+        if allow_code_generation {
+            println!(
+                "{}",
+                warn("Code generation is allowed due to `--allow-code-generation`")
+            );
+        }
+
+        if let Some(fs_root) = &fs_root {
+            println!(
+                "{}",
+                warn(&format!(
+                    "Filesystem access is allowed in {} due to `--allow-fs`",
+                    fs_root.display()
+                ))
+            );
+        }
+
+        if timeout.is_zero() || startup_timeout.is_zero() {
+            println!(
+                "{}",
+                warn("Timeouts are disabled due to `--timeout 0` or `--startup-timeout 0`")
+            );
+        }
+
+        let url = format!("{protocol}://{addr}");
+
+        tokio::spawn(deliver_queued_messages(
+            queue_rx,
+            Arc::clone(&state),
+            url.clone(),
+            queue_retries,
+            log_format,
+        ));
+
+        let mut schedulers = Vec::new();
+
+        for function in state.functions() {
+            for cron in &function.crons {
+                schedulers.push(tokio::spawn(schedule_cron(
+                    Arc::clone(function),
+                    cron.clone(),
+                    log_format,
+                )));
+            }
+        }
+
+        // Events are queued until the isolate has been evaluated
+        if let Some(cron) = trigger_cron {
+            let function = Arc::clone(&function);
+
+            tokio::spawn(async move {
+                fire_cron(&function, &cron, SystemTime::now(), log_format).await;
+            });
+        }
+
+        println!();
+        println!(" {} {}", "➤".bright_black(), url.blue());
+        println!(
+            " {} {}",
+            "➤".bright_black(),
+            format!("Memory limit: {memory}MB").bright_black()
+        );
+
+        if concurrency > 1 {
+            println!(
+                " {} {}",
+                "➤".bright_black(),
+                format!("Concurrency: up to {concurrency} isolates").bright_black()
+            );
+        }
+
+        if routes.len() > 1 {
+            println!(" {} {}", "➤".bright_black(), "Routes:".bright_black());
+
+            for (route, path) in &routes {
+                println!(
+                    "     {} {}",
+                    route.blue(),
+                    format!("→ {}", path.display()).bright_black()
+                );
+            }
+        }
+
+        if let Some(inspector) = &inspector {
+            println!(
+                " {} {}",
+                "➤".bright_black(),
+                format!("Debugger listening on {}", inspector.websocket_url()).bright_black()
+            );
+            println!(
+                " {} {}",
+                "➤".bright_black(),
+                format!("Open {} in Chrome to debug", inspector.devtools_url()).bright_black()
+            );
+            println!(
+                " {} {}",
+                "➤".bright_black(),
+                "DevTools has to reconnect each time the Function is reloaded".bright_black()
+            );
+        }
+
+        if let Some(shortcuts) = listen_for_shortcuts.then(listen_shortcuts).flatten() {
+            println!();
+            println!(" {} {}", "➤".bright_black(), SHORTCUTS_HINT.bright_black());
+
+            tokio::spawn(handle_shortcuts(
+                shortcuts,
+                shortcuts_tx,
+                watch_index_path,
+                url.clone(),
+                quit_tx,
+            ));
+        }
+
+        if let Some(every) = heap_stats {
+            tokio::spawn(print_heap_statistics(
+                function.isolate_tx.clone(),
+                every,
+                log_format,
+            ));
+        }
+
+        // The server is already bound at this point
+        if let Some(opener) = open {
+            tokio::spawn(open_browser_when_ready(opener, url, move || {
+                open_state
+                    .functions()
+                    .all(|function| function.is_ready.load(Ordering::SeqCst))
+            }));
+        }
+
+        Ok((
+            addr,
+            DevServerHandle {
+                server,
+                shutdown_tx,
+                quit_rx,
+                grace_period,
+                is_shutting_down,
+                state: handle_state,
+                isolate_threads,
+                watcher,
+                mounted_watchers,
+                schedulers,
+                _inspector: inspector,
+                _log_file: log_file,
+            },
+        ))
+    }
+}
+
+impl DevServerHandle {
+    // Resolves once the `q` shortcut is pressed. Without shortcuts, the
+    // sender is dropped right away and this never resolves.
+    pub async fn wait_for_quit(&self) {
+        if self.quit_rx.recv_async().await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    }
+
+    // The server stops accepting new connections and in-flight requests
+    // get a grace period to finish, before the isolates are terminated
+    pub async fn shutdown(self) -> Result<()> {
+        let DevServerHandle {
+            mut server,
+            shutdown_tx,
+            grace_period,
+            is_shutting_down,
+            state,
+            isolate_threads,
+            watcher,
+            mounted_watchers,
+            schedulers,
+            ..
+        } = self;
+
+        println!();
+        println!("{}", info("Shutting down..."));
+
+        for scheduler in schedulers {
+            scheduler.abort();
+        }
+
+        shutdown_tx.send_async(()).await.unwrap_or(());
+
+        // The isolates are still terminated if the server failed, and
+        // the error is only returned once everything is torn down
+        let result = match tokio::time::timeout(grace_period, &mut server).await {
+            Ok(result) => result
+                .map_err(anyhow::Error::from)
+                .and_then(|result| result.map_err(Into::into)),
+            Err(_) => {
+                println!(
+                    "{}",
+                    warn("Grace period elapsed, closing remaining connections")
+                );
+                server.abort();
+                Ok(())
+            }
+        };
+
+        is_shutting_down.store(true, Ordering::SeqCst);
+
+        for function in state.functions() {
+            function
+                .isolate_tx
+                .send_async(IsolateEvent::Terminate("Shutting down".into()))
+                .await
+                .unwrap_or(());
+        }
+
+        // Joining blocks until the isolates stopped
+        tokio::task::spawn_blocking(move || {
+            for isolate_thread in isolate_threads {
+                isolate_thread.join().unwrap_or(());
+            }
+        })
+        .await
+        .unwrap_or(());
+
+        drop(watcher);
+        drop(mounted_watchers);
+
+        result
+    }
+}
+
+// Quitting with the `q` shortcut shuts down the same way as a signal
+pub async fn dev(
+    path: Option<PathBuf>,
+    client: Option<PathBuf>,
+    public_dir: Option<PathBuf>,
+    options: DevOptions,
+) -> Result<()> {
+    let (_, handle) = DevServerBuilder {
+        path,
+        client,
+        public_dir,
+        options,
+        install_logger: true,
+        shortcuts: true,
+    }
+    .build()
+    .start()
+    .await?;
+
+    tokio::select! {
+        _ = shutdown_signal() => {},
+        _ = handle.wait_for_quit() => {},
+    }
+
+    handle.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::JsxConfig;
+    use lagon_runtime_isolate::Isolate;
+
+    use super::*;
+
+    #[test]
+    fn parse_environment_variable_valid() {
+        assert_eq!(
+            parse_environment_variable("KEY=VALUE").unwrap(),
+            ("KEY".into(), "VALUE".into())
+        );
+        assert_eq!(
+            parse_environment_variable("KEY=a=b").unwrap(),
+            ("KEY".into(), "a=b".into())
+        );
+        assert_eq!(
+            parse_environment_variable("KEY=").unwrap(),
+            ("KEY".into(), "".into())
+        );
+    }
+
+    struct TestBrowser(Arc<StdMutex<Vec<String>>>);
+
+    impl BrowserOpener for TestBrowser {
+        fn open(&self, url: &str) -> Result<()> {
+            self.0.lock().unwrap().push(url.into());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn open_browser_once_ready() {
+        let opened = Arc::new(StdMutex::new(Vec::new()));
+        let is_ready = Arc::new(AtomicBool::new(false));
+
+        let task_is_ready = Arc::clone(&is_ready);
+        let task = tokio::spawn(open_browser_when_ready(
+            Box::new(TestBrowser(Arc::clone(&opened))),
+            "http://127.0.0.1:1234".into(),
+            move || task_is_ready.load(Ordering::SeqCst),
+        ));
+
+        tokio::time::sleep(OPEN_BROWSER_INTERVAL * 2).await;
+        assert!(opened.lock().unwrap().is_empty());
+
+        is_ready.store(true, Ordering::SeqCst);
+        task.await.unwrap();
+
+        assert_eq!(*opened.lock().unwrap(), vec!["http://127.0.0.1:1234"]);
+    }
+
+    #[test]
+    fn parse_function_mount_valid() {
+        assert_eq!(
+            parse_function_mount("/api=./api").unwrap(),
+            ("/api".into(), PathBuf::from("./api"))
+        );
+        assert_eq!(
+            parse_function_mount("/api/v2/=api/index.ts").unwrap(),
+            ("/api/v2".into(), PathBuf::from("api/index.ts"))
+        );
+    }
+
+    #[test]
+    fn parse_function_mount_invalid() {
+        assert!(parse_function_mount("/api").is_err());
+        assert!(parse_function_mount("api=./api").is_err());
+        assert!(parse_function_mount("/api=").is_err());
+        assert!(parse_function_mount("/=./api").is_err());
+    }
+
+    #[test]
+    fn format_sizes() {
+        assert_eq!(format_size(512), "512B");
+        assert_eq!(format_size(2048), "2.0KB");
+        assert_eq!(format_size(3 * 1024 * 1024 + 512 * 1024), "3.5MB");
+    }
+
+    #[test]
+    fn matches_route_segments() {
+        assert!(matches_route("/api", "/api"));
+        assert!(matches_route("/api", "/api/users"));
+        assert!(!matches_route("/api", "/apis"));
+        assert!(!matches_route("/api", "/"));
+    }
+
+    fn record_request(recorded_requests: &mut RecordedRequests, url: &str, body: Vec<u8>) {
+        recorded_requests.record(&Request {
+            method: Method::POST,
+            url: url.into(),
+            body: body.into(),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn recorded_requests_capacity() {
+        let mut recorded_requests = RecordedRequests::new(2);
+
+        for url in ["/first", "/second", "/third"] {
+            record_request(&mut recorded_requests, url, b"Hello".to_vec());
+        }
+
+        // The oldest request is evicted, but the ids keep increasing
+        assert!(recorded_requests.get(1).is_none());
+        assert_eq!(recorded_requests.get(2).unwrap().url, "/second");
+        assert_eq!(recorded_requests.get(3).unwrap().url, "/third");
+        assert_eq!(recorded_requests.requests.len(), 2);
+
+        let request = Request::from(recorded_requests.get(3).unwrap());
+        assert!(matches!(request.method, Method::POST));
+        assert_eq!(request.body, "Hello");
+
+        let mut recorded_requests = RecordedRequests::new(0);
+        record_request(&mut recorded_requests, "/", Vec::new());
+        assert!(recorded_requests.requests.is_empty());
+    }
+
+    #[test]
+    fn recorded_requests_truncated_body() {
+        let mut recorded_requests = RecordedRequests::new(2);
+        record_request(&mut recorded_requests, "/", vec![0; MAX_RECORDED_BODY_SIZE]);
+        record_request(
+            &mut recorded_requests,
+            "/",
+            vec![0; MAX_RECORDED_BODY_SIZE + 1],
+        );
+
+        let request = recorded_requests.get(1).unwrap();
+        assert!(!request.is_truncated);
+        assert_eq!(request.body.len(), MAX_RECORDED_BODY_SIZE);
+
+        let request = recorded_requests.get(2).unwrap();
+        assert!(request.is_truncated);
+        assert_eq!(request.body.len(), MAX_RECORDED_BODY_SIZE);
+        assert_eq!(request.to_json()["truncated"], true);
+    }
+
+    #[test]
+    fn recorded_requests_body_chunks() {
+        let mut recorded_requests = RecordedRequests::new(2);
+        let id = recorded_requests
+            .record(&Request {
+                method: Method::POST,
+                url: "/".into(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        recorded_requests.record_body_chunk(id, b"Hello");
+        recorded_requests.record_body_chunk(id, b", World");
+        assert_eq!(recorded_requests.get(id).unwrap().body, "Hello, World");
+
+        recorded_requests.record_body_chunk(id, &vec![0; MAX_RECORDED_BODY_SIZE]);
+        let request = recorded_requests.get(id).unwrap();
+        assert!(request.is_truncated);
+        assert_eq!(request.body.len(), MAX_RECORDED_BODY_SIZE);
+
+        assert!(RecordedRequests::new(0)
+            .record(&Request::default())
+            .is_none());
+    }
+
+    async fn run_bundle(index: Vec<u8>) -> RunResult {
+        init_runtime(RuntimeOptions::default());
+
+        let (isolate_tx, isolate_rx) = flume::unbounded();
+        let (sender, receiver) = flume::unbounded();
+        let code = String::from_utf8(index).unwrap();
+        let handle = Handle::current();
+
+        isolate_tx
+            .send(IsolateEvent::Request(IsolateRequest {
+                request: Request {
+                    url: String::from("http://127.0.0.1:1234/"),
+                    ..Default::default()
+                },
+                sender,
+                statistics: None,
+                body_stream: None,
+                connection: None,
+            }))
+            .unwrap();
+        drop(isolate_tx);
+
+        let isolate_thread = std::thread::spawn(move || {
+            handle.block_on(async move {
+                let mut isolate = Isolate::new(IsolateOptions::new(code), isolate_rx);
+                isolate.evaluate();
+                isolate.run_event_loop().await;
+            });
+        });
+
+        let result = receiver.recv_async().await.unwrap();
+        isolate_thread.join().unwrap();
+
+        result
+    }
+
+    fn write_tsx_function(root: &Path, index: &str) -> FunctionConfig {
+        let jsx_runtime = root.join("node_modules").join("tiny-jsx");
+
+        std::fs::remove_dir_all(root).unwrap_or(());
+        std::fs::create_dir_all(&jsx_runtime).unwrap();
+        std::fs::write(
+            jsx_runtime.join("jsx-runtime.js"),
+            r#"export const jsx = (tag, props) => typeof tag === 'function'
+  ? tag(props)
+  : `<${tag}>${[].concat(props.children ?? []).join('')}</${tag}>`;
+export const jsxs = jsx;"#,
+        )
+        .unwrap();
+        std::fs::write(root.join("index.tsx"), index).unwrap();
+
+        FunctionConfig {
+            function_id: String::new(),
+            organization_id: String::new(),
+            index: PathBuf::from("index.tsx"),
+            client: None,
+            assets: None,
+            assets_fallback: None,
+            jsx: Some(JsxConfig {
+                import_source: Some(String::from("tiny-jsx")),
+                ..Default::default()
+            }),
+            env: Vec::new(),
+            crons: Vec::new(),
+        }
+    }
+
+    // Needs ESBuild to be installed globally, like `lagon dev`
+    #[tokio::test(flavor = "multi_thread")]
+    async fn bundle_tsx_handler() {
+        let root = env::temp_dir().join("lagon-dev-tsx");
+        let function_config = write_tsx_function(
+            &root,
+            r#"const Greeting = ({ name }: { name: string }) => <h1>Hello {name}</h1>;
+
+export function handler(): Response {
+  return new Response(<main><Greeting name="World" /></main>);
+}"#,
+        );
+
+        let (index, _) = bundle_function(&function_config, &root).unwrap();
+
+        assert_eq!(
+            run_bundle(index).await,
+            RunResult::Response(Response::from("<main><h1>Hello World</h1></main>"))
+        );
+
+        // Syntax errors point to the line of the source file, not of the bundle
+        let function_config = write_tsx_function(
+            &root,
+            "export function handler() {\n  return new Response(<p>Hello</div>);\n}",
+        );
+        let err = bundle_function(&function_config, &root)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("index.tsx:2:"), "{err}");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    // Needs ESBuild to be installed globally, like `lagon dev`
+    #[tokio::test(flavor = "multi_thread")]
+    async fn bundle_wasm_import() {
+        let root = env::temp_dir().join("lagon-dev-wasm");
+        std::fs::remove_dir_all(&root).unwrap_or(());
+        std::fs::create_dir_all(&root).unwrap();
+
+        // (module (func (export "add") (param i32 i32) (result i32)
+        //   local.get 0 local.get 1 i32.add))
+        std::fs::write(
+            root.join("add.wasm"),
+            [
+                0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, 0x01, 0x07, 0x01, 0x60, 0x02, 0x7f,
+                0x7f, 0x01, 0x7f, 0x03, 0x02, 0x01, 0x00, 0x07, 0x07, 0x01, 0x03, 0x61, 0x64, 0x64,
+                0x00, 0x00, 0x0a, 0x09, 0x01, 0x07, 0x00, 0x20, 0x00, 0x20, 0x01, 0x6a, 0x0b,
+            ],
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("index.js"),
+            r#"import wasm from './add.wasm';
+
+export async function handler() {
+  const { instance } = await WebAssembly.instantiate(wasm);
+  return new Response(`1 + 2 = ${instance.exports.add(1, 2)}`);
+}"#,
+        )
+        .unwrap();
+
+        let function_config = FunctionConfig {
+            function_id: String::new(),
+            organization_id: String::new(),
+            index: PathBuf::from("index.js"),
+            client: None,
+            assets: None,
+            assets_fallback: None,
+            jsx: None,
+            env: Vec::new(),
+            crons: Vec::new(),
+        };
+
+        let (index, _) = bundle_function(&function_config, &root).unwrap();
+
+        assert_eq!(
+            run_bundle(index).await,
+            RunResult::Response(Response::from("1 + 2 = 3"))
+        );
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    // Writes the Function to a temporary directory named `name` with an empty `public`
+    // directory, and starts the dev server on a random port. Needs ESBuild to be
+    // installed globally, like `lagon dev`
+    async fn start_dev_server(
+        name: &str,
+        index: &str,
+        options: DevOptions,
+    ) -> (PathBuf, SocketAddr, DevServerHandle) {
+        start_dev_server_with_config(name, index, None, options).await
+    }
+
+    // With a `.lagon/config.json`, the dev server is started on the
+    // directory like `lagon dev .`, so the config is loaded
+    async fn start_dev_server_with_config(
+        name: &str,
+        index: &str,
+        config: Option<&str>,
+        options: DevOptions,
+    ) -> (PathBuf, SocketAddr, DevServerHandle) {
+        let root = env::temp_dir().join(name);
+        std::fs::remove_dir_all(&root).unwrap_or(());
+        std::fs::create_dir_all(root.join("public")).unwrap();
+        std::fs::write(root.join("index.js"), index).unwrap();
+
+        let path = match config {
+            Some(config) => {
+                std::fs::create_dir_all(root.join(".lagon")).unwrap();
+                std::fs::write(root.join(".lagon").join("config.json"), config).unwrap();
+                root.clone()
+            }
+            None => root.join("index.js"),
+        };
+
+        let (addr, handle) = DevServer::builder()
+            .path(path)
+            .public_dir(root.join("public"))
+            .options(DevOptions {
+                port: Some(0),
+                grace_period: Duration::from_millis(100),
+                ..options
+            })
+            .build()
+            .start()
+            .await
+            .unwrap();
+
+        (root, addr, handle)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_start_shutdown() {
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server",
+            "export function handler() {\n  return new Response('Hello from the dev server');\n}",
+            DevOptions::default(),
+        )
+        .await;
+
+        assert_ne!(addr.port(), 0);
+
+        let response = reqwest::get(format!("http://{addr}")).await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "Hello from the dev server");
+
+        handle.shutdown().await.unwrap();
+        assert!(reqwest::get(format!("http://{addr}")).await.is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    // The watcher reloads the Function in the background, so retry
+    // until the response matches or give up after a few seconds
+    async fn get_until(url: &str, matches: impl Fn(u16, &str) -> bool) -> (u16, String) {
+        let mut response = (0, String::new());
+
+        for _ in 0..50 {
+            let result = reqwest::get(url).await.unwrap();
+            let status = result.status().as_u16();
+            response = (status, result.text().await.unwrap());
+
+            if matches(response.0, &response.1) {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        response
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_bundle_error_recovery() {
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-bundle-error",
+            "export function handler() {\n  return new Response('Hello');\n}",
+            DevOptions::default(),
+        )
+        .await;
+        let url = format!("http://{addr}");
+
+        std::fs::write(
+            root.join("index.js"),
+            "export function handler() {\n  return new Response('Hello';\n}",
+        )
+        .unwrap();
+
+        let (status, body) = get_until(&url, |status, _| status == 500).await;
+        assert_eq!(status, 500);
+        assert!(body.contains("index.js:2:"), "{body}");
+
+        std::fs::write(
+            root.join("index.js"),
+            "export function handler() {\n  return new Response('Fixed');\n}",
+        )
+        .unwrap();
+
+        let response = get_until(&url, |status, _| status == 200).await;
+        assert_eq!(response, (200, String::from("Fixed")));
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_public_dir_refresh() {
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-assets",
+            "export function handler() {\n  return new Response('Hello from the handler');\n}",
+            DevOptions::default(),
+        )
+        .await;
+        let url = format!("http://{addr}/about");
+
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "Hello from the handler");
+
+        // New files are served without restarting the dev server
+        std::fs::write(root.join("public").join("about.html"), "About").unwrap();
+
+        let response = get_until(&url, |_, body| body == "About").await;
+        assert_eq!(response, (200, String::from("About")));
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_reserved_routes() {
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-reserved",
+            "const start = Date.now();
+while (Date.now() - start < 500) {}
+
+export function handler() {
+  return new Response('Hello from the handler');
+}",
+            DevOptions {
+                timeout: Duration::from_millis(100),
+                startup_timeout: Duration::from_secs(5),
+                ..Default::default()
+            },
+        )
+        .await;
+        let health_url = format!("http://{addr}/__lagon/health");
+
+        // The Function is still being evaluated
+        let response = reqwest::get(&health_url).await.unwrap();
+        assert_eq!(response.status(), 503);
+        assert_eq!(response.text().await.unwrap(), "Not ready");
+
+        let response = get_until(&health_url, |status, _| status == 200).await;
+        assert_eq!(response, (200, String::from("OK")));
+
+        let response = reqwest::get(format!("http://{addr}/__lagon/info"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let mut info = serde_json::from_str::<Value>(&response.text().await.unwrap()).unwrap();
+        assert!(info["bundleSize"].as_u64().unwrap() > 0);
+        info["bundleSize"] = json!(0);
+
+        assert_eq!(
+            info,
+            json!({
+                "version": get_version().ok(),
+                "bundleSize": 0,
+                "assetsCount": 0,
+                "timeout": 100,
+                "startupTimeout": 5000,
+                "functions": [],
+            })
+        );
+
+        // Unknown reserved routes don't fall back to the Function
+        let response = reqwest::get(format!("http://{addr}/__lagon/unknown"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(response.text().await.unwrap(), "Not Found");
+
+        let response = reqwest::get(format!("http://{addr}/unknown"))
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "Hello from the handler");
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_replay_request() {
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-replay",
+            "export async function handler(request) {
+  return new Response(`${request.method} ${new URL(request.url).pathname} ${await request.text()}`);
+}",
+            DevOptions::default(),
+        )
+        .await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("http://{addr}/echo"))
+            .body("Hello")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "POST /echo Hello");
+
+        let response = reqwest::get(format!("http://{addr}/__lagon/requests"))
+            .await
+            .unwrap();
+        let requests = serde_json::from_str::<Value>(&response.text().await.unwrap()).unwrap();
+        assert_eq!(requests.as_array().unwrap().len(), 1);
+        assert_eq!(requests[0]["id"], 1);
+        assert_eq!(requests[0]["method"], "POST");
+        assert_eq!(requests[0]["body"], "Hello");
+        assert_eq!(requests[0]["truncated"], false);
+
+        let replay_url = format!("http://{addr}/__lagon/replay");
+
+        let response = client.get(format!("{replay_url}/1")).send().await.unwrap();
+        assert_eq!(response.status(), 405);
+
+        let response = client.post(format!("{replay_url}/2")).send().await.unwrap();
+        assert_eq!(response.status(), 404);
+        assert_eq!(
+            response.text().await.unwrap(),
+            "No recorded request with id 2"
+        );
+
+        // Sent to the Function like the original request
+        let response = client.post(format!("{replay_url}/1")).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.text().await.unwrap(), "POST /echo Hello");
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_streamed_request_body() {
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-streamed-request-body",
+            "export async function handler(request) {
+  return new Response(await request.text().catch(error => error.message));
+}",
+            DevOptions {
+                max_body_size: 16,
+                ..Default::default()
+            },
+        )
+        .await;
+        let client = hyper::Client::new();
+
+        // Sent with a chunked transfer-encoding, since the length isn't known
+        let post = |chunks: Vec<&'static str>| {
+            let request = HyperRequest::post(format!("http://{addr}/"))
+                .body(Body::wrap_stream(stream::iter(
+                    chunks.into_iter().map(Ok::<_, io::Error>),
+                )))
+                .unwrap();
+
+            client.request(request)
+        };
+
+        let response = post(vec!["Hello", ", ", "World"]).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(body, "Hello, World");
+
+        let response = post(vec!["Hello", ", ", "World", ", ", "again"])
+            .await
+            .unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(
+            body,
+            "Request body is larger than the maximum size of 16 bytes"
+        );
+
+        // Recorded as it was read by the Function
+        let response = reqwest::get(format!("http://{addr}/__lagon/requests"))
+            .await
+            .unwrap();
+        let requests = serde_json::from_str::<Value>(&response.text().await.unwrap()).unwrap();
+        assert_eq!(requests[0]["body"], "Hello, World");
+        assert_eq!(requests[0]["truncated"], false);
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_stream_chunks() {
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-stream",
+            "export function handler() {
+  const encoder = new TextEncoder();
+  let count = 0;
+
+  const body = new ReadableStream({
+    async pull(controller) {
+      await new Promise(resolve => setTimeout(resolve, 200));
+      controller.enqueue(encoder.encode(`data: ${++count}\\n\\n`));
+
+      if (count === 3) {
+        controller.close();
+      }
+    },
+  });
+
+  return new Response(body, { headers: { 'content-type': 'text/event-stream' } });
+}",
+            DevOptions::default(),
+        )
+        .await;
+
+        let mut response = reqwest::get(format!("http://{addr}")).await.unwrap();
+        assert_eq!(response.status(), 200);
+
+        let mut chunks = Vec::new();
+        let mut last_chunk = Instant::now();
+
+        while let Some(chunk) = response.chunk().await.unwrap() {
+            // Each chunk is received as soon as it's enqueued, not with the next ones
+            assert!(last_chunk.elapsed() >= Duration::from_millis(100));
+            last_chunk = Instant::now();
+
+            chunks.push(String::from_utf8(chunk.to_vec()).unwrap());
+        }
+
+        assert_eq!(chunks, ["data: 1\n\n", "data: 2\n\n", "data: 3\n\n"]);
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_client_disconnect() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio::net::{TcpListener, TcpStream};
+
+        // Notified by the Function with fetch() calls that never get a response
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-disconnect",
+            &format!(
+                "export function handler(request) {{
+  return new Promise(resolve => {{
+    request.signal.onabort = () => resolve(fetch('{url}/aborted'));
+    fetch('{url}/started').catch(() => {{}});
+  }});
+}}"
+            ),
+            DevOptions::default(),
+        )
+        .await;
+
+        let mut client = TcpStream::connect(addr).await.unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+
+        let (mut started, _) = listener.accept().await.unwrap();
+        drop(client);
+
+        // The fetch() call in flight is aborted, and the signal fires
+        let mut buffer = Vec::new();
+        tokio::time::timeout(Duration::from_secs(1), started.read_to_end(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (mut aborted, _) = tokio::time::timeout(Duration::from_secs(1), listener.accept())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut request = [0; 1024];
+        let length = aborted.read(&mut request).await.unwrap();
+        assert!(request[..length].starts_with(b"GET /aborted "));
+        drop(aborted);
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_queue_delivery() {
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-queue",
+            "export async function handler(request) {
+  const queue = request.headers.get('x-lagon-queue');
+
+  if (queue) {
+    const { id } = await request.json();
+    const attempts = Number((await Lagon.kv.get(`attempts:${id}`)) ?? 0) + 1;
+    await Lagon.kv.put(`attempts:${id}`, String(attempts));
+
+    // The first delivery fails, so the message is delivered again
+    if (attempts === 1) {
+      return new Response('Retry later', { status: 500 });
+    }
+
+    await Lagon.kv.put(`delivered:${id}`, queue);
+    return new Response('Delivered');
+  }
+
+  if (new URL(request.url).pathname === '/send') {
+    await Lagon.queue.send('jobs', { id: 'job' });
+    return new Response('Sent');
+  }
+
+  return new Response(`${await Lagon.kv.get('delivered:job')} ${await Lagon.kv.get('attempts:job')}`);
+}",
+            DevOptions::default(),
+        )
+        .await;
+
+        let response = reqwest::get(format!("http://{addr}/send")).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "Sent");
+
+        let mut delivered = String::new();
+
+        for _ in 0..20 {
+            tokio::time::sleep(QUEUE_RETRY_DELAY).await;
+
+            delivered = reqwest::get(format!("http://{addr}"))
+                .await
+                .unwrap()
+                .text()
+                .await
+                .unwrap();
+
+            if delivered != "null 1" && delivered != "null null" {
+                break;
+            }
+        }
+
+        assert_eq!(delivered, "jobs 2");
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_cron_trigger() {
+        let (root, addr, handle) = start_dev_server_with_config(
+            "lagon-dev-server-cron",
+            "const crons = [];
+
+export function handler() {
+  return new Response(crons.join(', '));
+}
+
+export function scheduled(event) {
+  crons.push(`${event.cron} ${typeof event.scheduledTime}`);
+}",
+            Some(
+                r#"{"function_id":"","organization_id":"","index":"index.js","crons":["0 9 * * 1-5"]}"#,
+            ),
+            DevOptions {
+                trigger_cron: Some("*/5 * * * *".into()),
+                ..Default::default()
+            },
+        )
+        .await;
+        let client = reqwest::Client::new();
+        let cron_url = format!("http://{addr}/__lagon/cron");
+
+        let response = client
+            .post(format!("{cron_url}?cron=30+8+*+*+*"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 204);
+
+        let response = client.get(&cron_url).send().await.unwrap();
+        assert_eq!(response.status(), 405);
+
+        // Defaults to the first cron of the Function's config
+        let response = client.post(&cron_url).send().await.unwrap();
+        assert_eq!(response.status(), 204);
+
+        let response = client
+            .post(format!("{cron_url}?cron=60+*+*+*+*"))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+
+        // The event of --trigger-cron and the ones of the endpoint
+        let response = reqwest::get(format!("http://{addr}")).await.unwrap();
+        let text = response.text().await.unwrap();
+        let mut crons = text.split(", ").collect::<Vec<_>>();
+        crons.sort();
+
+        assert_eq!(
+            crons,
+            [
+                "*/5 * * * * number",
+                "0 9 * * 1-5 number",
+                "30 8 * * * number"
+            ]
+        );
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn dev_server_websocket_upgrade() {
+        use futures::SinkExt;
+        use tokio_tungstenite::tungstenite::{protocol::CloseFrame, Message};
+
+        let (root, addr, handle) = start_dev_server(
+            "lagon-dev-server-websocket",
+            "export function handler(request) {
+  const { socket, response } = upgradeWebSocket(request);
+  socket.onmessage = event => socket.send(event.data);
+  socket.onclose = event => console.log(`Closed with ${event.code}`);
+  socket.accept();
+  return response;
+}",
+            DevOptions::default(),
+        )
+        .await;
+
+        let (mut websocket, response) = tokio_tungstenite::connect_async(format!("ws://{addr}"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 101);
+
+        websocket.send(Message::text("Hello")).await.unwrap();
+        assert_eq!(
+            websocket.next().await.unwrap().unwrap(),
+            Message::text("Hello")
+        );
+
+        websocket.send(Message::binary([1, 2, 3])).await.unwrap();
+        assert_eq!(
+            websocket.next().await.unwrap().unwrap(),
+            Message::binary([1, 2, 3])
+        );
+
+        websocket
+            .close(Some(CloseFrame {
+                code: 3000.into(),
+                reason: "Bye".into(),
+            }))
+            .await
+            .unwrap();
+
+        match websocket.next().await {
+            Some(Ok(Message::Close(Some(frame)))) => {
+                assert_eq!(u16::from(frame.code), 3000);
+                assert_eq!(frame.reason, "Bye");
+            }
+            message => panic!("Expected a close frame, got {message:?}"),
+        }
+
+        // Plain requests aren't upgraded
+        let response = reqwest::get(format!("http://{addr}")).await.unwrap();
+        assert_eq!(response.status(), 500);
+
+        handle.shutdown().await.unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn parse_environment_variables_required() {
+        let parse = |env_vars: &[&str]| {
+            parse_environment_variables(
+                Path::new("."),
+                &[],
+                &env_vars
+                    .iter()
+                    .map(|env_var| env_var.to_string())
+                    .collect::<Vec<_>>(),
+                &["API_KEY".into(), "DATABASE_URL".into()],
+            )
+        };
+
+        assert!(parse(&["API_KEY=secret", "DATABASE_URL="]).is_ok());
+        assert_eq!(
+            parse(&["API_KEY=secret"]).unwrap_err().to_string(),
+            "Missing required environment variables: DATABASE_URL"
+        );
+        assert_eq!(
+            parse(&[]).unwrap_err().to_string(),
+            "Missing required environment variables: API_KEY, DATABASE_URL"
+        );
+    }
+
+    #[test]
+    fn parse_environment_variable_invalid() {
+        assert!(parse_environment_variable("KEY").is_err());
+        assert!(parse_environment_variable("=VALUE").is_err());
+        assert!(parse_environment_variable("MY KEY=VALUE").is_err());
+    }
 }
@@ -0,0 +1,60 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::utils::format_log_line;
+
+const TAIL_INTERVAL: Duration = Duration::from_millis(200);
+
+fn open(path: &Path) -> Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| anyhow!("Could not open log file {}: {}", path.display(), err))
+}
+
+fn print_line(line: &mut String) {
+    if !line.is_empty() {
+        println!("{}", format_log_line(line.trim_end()));
+        line.clear();
+    }
+}
+
+pub async fn logs(path: PathBuf, tail: bool) -> Result<()> {
+    let mut reader = open(&path)?;
+    let mut line = String::new();
+
+    loop {
+        // An incomplete line is kept until the rest of it is written
+        if reader.read_line(&mut line)? > 0 && line.ends_with('\n') {
+            print_line(&mut line);
+            continue;
+        }
+
+        if !tail {
+            print_line(&mut line);
+            return Ok(());
+        }
+
+        tokio::time::sleep(TAIL_INTERVAL).await;
+
+        // The file is smaller than what has been read when it has been
+        // rotated by `lagon dev`, so start again from the new file
+        let position = reader.stream_position()?;
+        let size = std::fs::metadata(&path).map(|metadata| metadata.len());
+
+        if matches!(size, Ok(size) if size < position) {
+            // Lines written right before the rotation are still in the previous file
+            while reader.read_line(&mut line)? > 0 {
+                print_line(&mut line);
+            }
+
+            print_line(&mut line);
+            reader = open(&path)?;
+        }
+    }
+}
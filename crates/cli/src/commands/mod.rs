@@ -1,21 +1,37 @@
+mod bench;
 mod build;
+mod completions;
 mod deploy;
 mod dev;
 mod link;
 mod login;
 mod logout;
+mod logs;
 mod ls;
+mod new;
 mod promote;
 mod rm;
+mod run;
+mod snapshot;
+mod test;
 mod undeploy;
+mod upgrade;
 
-pub use build::build;
+pub use bench::{bench, BenchOptions};
+pub use build::{build, BuildOptions};
+pub use completions::{completions, generate_completions};
 pub use deploy::deploy;
-pub use dev::dev;
+pub use dev::{dev, DevOptions, DevServer, DevServerBuilder, DevServerHandle};
 pub use link::link;
 pub use login::login;
 pub use logout::logout;
+pub use logs::logs;
 pub use ls::ls;
+pub use new::{new, Template};
 pub use promote::promote;
 pub use rm::rm;
+pub use run::{run, RunOptions};
+pub use snapshot::snapshot;
+pub use test::test;
 pub use undeploy::undeploy;
+pub use upgrade::upgrade;
@@ -0,0 +1,166 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+
+use crate::utils::{print_progress, success, FunctionConfig};
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Template {
+    Plain,
+    Html,
+    #[value(alias = "json")]
+    Api,
+    Streaming,
+}
+
+struct TemplateFile {
+    path: &'static str,
+    content: &'static str,
+}
+
+macro_rules! template_file {
+    ($template:literal, $path:literal) => {
+        TemplateFile {
+            path: $path,
+            content: include_str!(concat!("../../templates/", $template, "/", $path)),
+        }
+    };
+}
+
+// Embedded in the binary, so creating a Function doesn't need the network
+const PLAIN_FILES: &[TemplateFile] = &[template_file!("plain", "index.ts")];
+const HTML_FILES: &[TemplateFile] = &[
+    template_file!("html", "index.ts"),
+    template_file!("html", "public/style.css"),
+];
+const API_FILES: &[TemplateFile] = &[template_file!("api", "index.ts")];
+const STREAMING_FILES: &[TemplateFile] = &[template_file!("streaming", "index.ts")];
+
+impl Template {
+    fn files(&self) -> &'static [TemplateFile] {
+        match self {
+            Template::Plain => PLAIN_FILES,
+            Template::Html => HTML_FILES,
+            Template::Api => API_FILES,
+            Template::Streaming => STREAMING_FILES,
+        }
+    }
+
+    fn public_dir(&self) -> Option<PathBuf> {
+        match self {
+            Template::Html => Some(PathBuf::from("public")),
+            _ => None,
+        }
+    }
+}
+
+pub fn new(name: PathBuf, template: Template, force: bool) -> Result<()> {
+    if name.is_file() {
+        return Err(anyhow!("{} is a file, not a directory", name.display()));
+    }
+
+    if name.is_dir() && fs::read_dir(&name)?.next().is_some() && !force {
+        return Err(anyhow!(
+            "Directory {} is not empty, use --force to overwrite its files",
+            name.display()
+        ));
+    }
+
+    let end_progress = print_progress("Creating Function...");
+
+    for file in template.files() {
+        let path = name.join(file.path);
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, file.content)?;
+    }
+
+    // Same config as the one created by `lagon dev` or `lagon deploy`,
+    // without a Function ID until it's deployed for the first time
+    FunctionConfig {
+        function_id: String::new(),
+        organization_id: String::new(),
+        index: PathBuf::from("index.ts"),
+        client: None,
+        assets: template.public_dir(),
+        assets_fallback: None,
+        jsx: None,
+        env: Vec::new(),
+        crons: Vec::new(),
+    }
+    .write(&name)?;
+
+    end_progress();
+
+    println!();
+    println!(
+        "{}",
+        success(&format!("Function created in {}!", name.display()))
+    );
+    println!();
+    println!(" {} {}", "➤".bright_black(), "Next steps:".bright_black());
+    println!("     {}", format!("cd {}", name.display()).blue());
+    println!(
+        "     {} {}",
+        "lagon dev".blue(),
+        "→ start a local dev server".bright_black()
+    );
+    println!(
+        "     {} {}",
+        "lagon deploy".blue(),
+        "→ deploy it to Lagon".bright_black()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("lagon-new-{name}"));
+        fs::remove_dir_all(&dir).unwrap_or(());
+
+        dir
+    }
+
+    #[test]
+    fn new_html_template() {
+        let dir = temp_dir("html");
+        new(dir.clone(), Template::Html, false).unwrap();
+
+        assert!(dir.join("index.ts").is_file());
+        assert!(dir.join("public").join("style.css").is_file());
+
+        let config = fs::read_to_string(dir.join(".lagon").join("config.json")).unwrap();
+        let config = serde_json::from_str::<FunctionConfig>(&config).unwrap();
+
+        assert_eq!(config.index, PathBuf::from("index.ts"));
+        assert_eq!(config.assets, Some(PathBuf::from("public")));
+    }
+
+    #[test]
+    fn new_non_empty_directory() {
+        let dir = temp_dir("non-empty");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("index.ts"), "existing").unwrap();
+
+        assert!(new(dir.clone(), Template::Plain, false).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.join("index.ts")).unwrap(),
+            "existing"
+        );
+
+        new(dir.clone(), Template::Plain, true).unwrap();
+        assert_ne!(
+            fs::read_to_string(dir.join("index.ts")).unwrap(),
+            "existing"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use hyper::{body::Bytes, StatusCode};
+use lagon_runtime::{options::RuntimeOptions, Runtime};
+use lagon_runtime_http::{Request, Response, RunResult, StreamResult};
+use lagon_runtime_isolate::{options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest};
+use tokio::runtime::Handle;
+
+use crate::utils::{bundle_function, init_logger, parse_method, resolve_path, LogFormat};
+
+// Same defaults as `lagon dev`
+const TIMEOUT: Duration = Duration::from_millis(1000);
+const STARTUP_TIMEOUT: Duration = Duration::from_millis(2000);
+// In MB (MegaBytes)
+const MEMORY: usize = 128;
+// The request doesn't go through a server, but the Function still sees a full URL
+const RUN_HOST: &str = "127.0.0.1:1234";
+
+pub struct RunOptions {
+    pub path: String,
+    pub method: String,
+    // As `Key: Value`
+    pub headers: Vec<String>,
+    pub body: Option<String>,
+    pub body_file: Option<PathBuf>,
+}
+
+fn parse_headers(headers: &[String]) -> Result<Option<HashMap<String, Vec<String>>>> {
+    if headers.is_empty() {
+        return Ok(None);
+    }
+
+    let mut parsed_headers = HashMap::<String, Vec<String>>::new();
+
+    for header in headers {
+        let (key, value) = header
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Header {} should be formatted as Key: Value", header))?;
+        let key = key.trim().to_lowercase();
+
+        if key.is_empty() {
+            return Err(anyhow!("Header {} should have a name", header));
+        }
+
+        parsed_headers
+            .entry(key)
+            .or_default()
+            .push(value.trim().to_string());
+    }
+
+    Ok(Some(parsed_headers))
+}
+
+fn create_request(options: RunOptions) -> Result<Request> {
+    let RunOptions {
+        path,
+        method,
+        headers,
+        body,
+        body_file,
+    } = options;
+
+    if !path.starts_with('/') {
+        return Err(anyhow!("Path {} should start with /", path));
+    }
+
+    let body = match (body, body_file) {
+        (_, Some(body_file)) => fs::read(&body_file)
+            .map(Bytes::from)
+            .map_err(|err| anyhow!("Could not read body file {}: {}", body_file.display(), err))?,
+        (Some(body), None) => Bytes::from(body),
+        (None, None) => Bytes::new(),
+    };
+
+    Ok(Request {
+        headers: parse_headers(&headers)?,
+        method: parse_method(&method)?,
+        body,
+        url: format!("http://{RUN_HOST}{path}"),
+    })
+}
+
+// Printed like `curl --include`, so the output can easily be parsed
+fn print_head(response: &Response) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    let reason = StatusCode::from_u16(response.status)
+        .ok()
+        .and_then(|status| status.canonical_reason())
+        .unwrap_or("");
+
+    writeln!(stdout, "HTTP/1.1 {} {}", response.status, reason)?;
+
+    if let Some(headers) = &response.headers {
+        let mut keys = headers.keys().collect::<Vec<_>>();
+        keys.sort();
+
+        for key in keys {
+            for value in &headers[key] {
+                writeln!(stdout, "{}", format!("{key}: {value}").bright_black())?;
+            }
+        }
+    }
+
+    writeln!(stdout)?;
+    stdout.flush()
+}
+
+fn print_body(body: &[u8]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+
+    stdout.write_all(body)?;
+    stdout.flush()
+}
+
+pub async fn run(function: Option<PathBuf>, options: RunOptions) -> Result<()> {
+    let request = create_request(options)?;
+    let (root, function_config) = resolve_path(function, None, None)?;
+    let (index, _) = bundle_function(&function_config, &root)?;
+
+    // Console logs of the Function are printed like in `lagon dev`
+    init_logger(false, LogFormat::Text, None)?;
+
+    let runtime = Runtime::new(RuntimeOptions::default());
+    let (isolate_tx, isolate_rx) = flume::unbounded();
+    let (sender, receiver) = flume::unbounded();
+    let code = String::from_utf8(index)?;
+    let handle = Handle::current();
+
+    // The event is queued before the isolate starts, which then exits
+    // once the request is done since the channel is disconnected
+    isolate_tx
+        .send(IsolateEvent::Request(IsolateRequest {
+            request,
+            sender,
+            statistics: None,
+            body_stream: None,
+            connection: None,
+        }))
+        .unwrap_or(());
+    drop(isolate_tx);
+
+    let isolate_thread = std::thread::spawn(move || {
+        handle.block_on(async move {
+            let options = IsolateOptions::new(code)
+                .timeout(TIMEOUT)
+                .startup_timeout(STARTUP_TIMEOUT)
+                .memory(MEMORY)
+                .metadata(Some((String::from(""), String::from(""))));
+
+            let mut isolate = Isolate::new(options, isolate_rx);
+            isolate.evaluate();
+            isolate.run_event_loop().await;
+        });
+    });
+
+    let mut result = Ok(());
+    let mut has_body_started = false;
+
+    while let Ok(run_result) = receiver.recv_async().await {
+        match run_result {
+            RunResult::Response(response) => {
+                print_head(&response)?;
+                print_body(&response.body)?;
+                break;
+            }
+            RunResult::Stream(StreamResult::Start(response)) => {
+                if !has_body_started {
+                    print_head(&response)?;
+                    has_body_started = true;
+                }
+            }
+            RunResult::Stream(StreamResult::Data(chunk)) => {
+                // Chunks can be received before the response itself
+                if !has_body_started {
+                    print_head(&Response::default())?;
+                    has_body_started = true;
+                }
+
+                print_body(&chunk)?;
+            }
+            RunResult::Stream(StreamResult::Done) => break,
+            RunResult::Timeout => {
+                result = Err(anyhow!("Function execution timed out"));
+                break;
+            }
+            RunResult::CpuTimeLimit => {
+                result = Err(anyhow!("Function execution reached CPU time limit"));
+                break;
+            }
+            RunResult::MemoryLimit(_) => {
+                result = Err(anyhow!("Function execution reached memory limit"));
+                break;
+            }
+            RunResult::Error(error) => {
+                result = Err(anyhow!(error));
+                break;
+            }
+            RunResult::NotFound => {
+                result = Err(anyhow!("Function not found"));
+                break;
+            }
+            RunResult::Aborted => {
+                result = Err(anyhow!("Function execution was aborted"));
+                break;
+            }
+            RunResult::WebSocket(_) => {
+                result = Err(anyhow!(
+                    "WebSockets can't be upgraded when running a Function"
+                ));
+                break;
+            }
+        }
+    }
+
+    isolate_thread.join().unwrap_or(());
+    runtime.dispose();
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_headers_valid() {
+        assert_eq!(parse_headers(&[]).unwrap(), None);
+        assert_eq!(
+            parse_headers(&[
+                "Content-Type: application/json".into(),
+                "x-token:a:b".into(),
+                "x-token: c".into(),
+            ])
+            .unwrap(),
+            Some(HashMap::from([
+                ("content-type".into(), vec!["application/json".into()]),
+                ("x-token".into(), vec!["a:b".into(), "c".into()]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn parse_headers_invalid() {
+        assert!(parse_headers(&["Content-Type".into()]).is_err());
+        assert!(parse_headers(&[": value".into()]).is_err());
+    }
+
+    #[test]
+    fn create_request_invalid() {
+        let options = |path: &str, method: &str| RunOptions {
+            path: path.into(),
+            method: method.into(),
+            headers: Vec::new(),
+            body: None,
+            body_file: None,
+        };
+
+        assert!(create_request(options("/", "POST")).is_ok());
+        assert!(create_request(options("api", "GET")).is_err());
+        assert!(create_request(options("/", "TRACE")).is_err());
+    }
+}
@@ -0,0 +1,39 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Result;
+use lagon_runtime::{options::RuntimeOptions, Runtime};
+use lagon_runtime_isolate::{options::IsolateOptions, Isolate};
+
+use crate::utils::{debug, format_size, print_progress, stamp_snapshot, success};
+
+// Same as the snapshot built for the serverless package, see `crates/serverless/build.rs`
+pub fn snapshot(out: Option<PathBuf>) -> Result<()> {
+    let out = out.unwrap_or_else(|| PathBuf::from("snapshot.bin"));
+    let end_progress = print_progress("Building snapshot...");
+
+    let runtime = Runtime::new(RuntimeOptions::default());
+    let (_, rx) = flume::unbounded();
+    let mut isolate = Isolate::new(IsolateOptions::new("".into()).snapshot(true), rx);
+
+    let blob = isolate.snapshot();
+    let snapshot = stamp_snapshot(&blob)?;
+
+    drop(isolate);
+    runtime.dispose();
+
+    fs::write(&out, &snapshot)?;
+    end_progress();
+
+    println!();
+    println!(
+        "{} {}",
+        success(&format!("Snapshot written to {}!", out.display())),
+        debug(&format!("({})", format_size(snapshot.len())))
+    );
+    println!(
+        "{}",
+        debug("Use it with `lagon dev --snapshot` to speed up the startup of your Function")
+    );
+
+    Ok(())
+}
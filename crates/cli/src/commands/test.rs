@@ -0,0 +1,285 @@
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use lagon_runtime::{options::RuntimeOptions, Runtime};
+use lagon_runtime_http::{Request, RunResult};
+use lagon_runtime_isolate::{options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest};
+use serde::Deserialize;
+use tokio::runtime::Handle;
+use walkdir::WalkDir;
+
+use crate::utils::{debug, esbuild, init_logger, print_progress, LogFormat};
+
+const TEST_EXTENSIONS: [&str; 6] = ["js", "jsx", "ts", "tsx", "mjs", "cjs"];
+const IGNORED_DIRECTORIES: [&str; 3] = ["node_modules", ".git", ".lagon"];
+// In MB (MegaBytes)
+const MEMORY: usize = 128;
+
+// Sent by the test harness of the isolate, see `IsolateOptions::test_mode`
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+enum TestStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestResult {
+    name: String,
+    status: TestStatus,
+    // In milliseconds
+    duration: u64,
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct Summary {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+fn is_test_file(path: &Path) -> bool {
+    let file_stem = path.file_stem().and_then(|file_stem| file_stem.to_str());
+    let extension = path.extension().and_then(|extension| extension.to_str());
+
+    match (file_stem, extension) {
+        (Some(file_stem), Some(extension)) => {
+            file_stem.ends_with(".test") && TEST_EXTENSIONS.contains(&extension)
+        }
+        _ => false,
+    }
+}
+
+fn find_test_files(root: &Path) -> Vec<PathBuf> {
+    let mut test_files = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            !IGNORED_DIRECTORIES
+                .iter()
+                .any(|directory| entry.file_name() == *directory)
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && is_test_file(entry.path()))
+        .map(|entry| entry.into_path())
+        .collect::<Vec<_>>();
+
+    test_files.sort();
+    test_files
+}
+
+// Each test file runs in its own isolate, with all its tests run by a single request
+async fn run_test_file(
+    code: String,
+    filter: Option<&str>,
+    timeout: Duration,
+) -> Result<Vec<TestResult>> {
+    let (isolate_tx, isolate_rx) = flume::unbounded();
+    let (sender, receiver) = flume::unbounded();
+    let handle = Handle::current();
+
+    let url = match filter {
+        Some(filter) => format!(
+            "http://127.0.0.1:1234/?filter={}",
+            urlencoding::encode(filter)
+        ),
+        None => String::from("http://127.0.0.1:1234/"),
+    };
+
+    isolate_tx
+        .send(IsolateEvent::Request(IsolateRequest {
+            request: Request {
+                url,
+                ..Default::default()
+            },
+            sender,
+            statistics: None,
+            body_stream: None,
+            connection: None,
+        }))
+        .unwrap_or(());
+    drop(isolate_tx);
+
+    let isolate_thread = std::thread::spawn(move || {
+        handle.block_on(async move {
+            let options = IsolateOptions::new(code)
+                .timeout(timeout)
+                .startup_timeout(timeout)
+                .memory(MEMORY)
+                .metadata(Some((String::from(""), String::from(""))))
+                .test_mode(true);
+
+            let mut isolate = Isolate::new(options, isolate_rx);
+            isolate.evaluate();
+            isolate.run_event_loop().await;
+        });
+    });
+
+    let result = receiver.recv_async().await;
+    isolate_thread.join().unwrap_or(());
+
+    match result {
+        Ok(RunResult::Response(response)) => Ok(serde_json::from_slice(&response.body)?),
+        Ok(RunResult::Timeout) => Err(anyhow!("Tests timed out after {}ms", timeout.as_millis())),
+        Ok(RunResult::CpuTimeLimit) => Err(anyhow!("Tests reached CPU time limit")),
+        Ok(RunResult::MemoryLimit(_)) => Err(anyhow!("Tests reached memory limit")),
+        Ok(RunResult::Error(error)) => Err(anyhow!(error)),
+        Ok(result) => Err(anyhow!("Unexpected result: {:?}", result)),
+        Err(_) => Err(anyhow!("The isolate exited without running the tests")),
+    }
+}
+
+fn print_results(results: &[TestResult], summary: &mut Summary) {
+    for result in results {
+        match result.status {
+            TestStatus::Passed => {
+                summary.passed += 1;
+
+                println!(
+                    "  {} {} {}",
+                    "✓".green(),
+                    result.name,
+                    debug(&format!("({}ms)", result.duration))
+                );
+            }
+            TestStatus::Failed => {
+                summary.failed += 1;
+
+                println!(
+                    "  {} {} {}",
+                    "✖".red(),
+                    result.name,
+                    debug(&format!("({}ms)", result.duration))
+                );
+
+                if let Some(error) = &result.error {
+                    for line in error.lines() {
+                        println!("      {}", line.red());
+                    }
+                }
+            }
+            TestStatus::Skipped => summary.skipped += 1,
+        }
+    }
+}
+
+pub async fn test(path: Option<PathBuf>, filter: Option<String>, timeout: Duration) -> Result<()> {
+    let path = path.unwrap_or_else(|| PathBuf::from("."));
+
+    if !path.exists() {
+        return Err(anyhow!("File or directory not found"));
+    }
+
+    // A single test file can be given instead of a directory
+    let (root, test_files) = match path.is_file() {
+        true if is_test_file(&path) => (
+            path.parent().map(PathBuf::from).unwrap_or_default(),
+            vec![path.clone()],
+        ),
+        true => return Err(anyhow!("{} is not a test file", path.display())),
+        false => (path.clone(), find_test_files(&path)),
+    };
+
+    if test_files.is_empty() {
+        return Err(anyhow!(
+            "No test files found in {} (e.g index.test.ts)",
+            root.display()
+        ));
+    }
+
+    // Console logs of the tests are printed like in `lagon dev`
+    init_logger(false, LogFormat::Text, None)?;
+
+    let runtime = Runtime::new(RuntimeOptions::default());
+    let start = Instant::now();
+    let mut summary = Summary::default();
+    let mut failed_files = 0;
+
+    for test_file in &test_files {
+        // Bundled relative to the root, like the Function's files
+        let relative_path = test_file.strip_prefix(&root).unwrap_or(test_file);
+        let name = relative_path.display().to_string();
+
+        let message = format!("Bundling {name}...");
+        let end_progress = print_progress(&message);
+        let code = esbuild(relative_path, &root, None)
+            .and_then(|code| String::from_utf8(code).map_err(anyhow::Error::from));
+        end_progress();
+
+        println!();
+        println!("{}", name.bold());
+
+        let results = match code {
+            Ok(code) => run_test_file(code, filter.as_deref(), timeout).await,
+            Err(err) => Err(err),
+        };
+
+        match results {
+            Ok(results) => print_results(&results, &mut summary),
+            Err(err) => {
+                failed_files += 1;
+
+                println!("  {} {}", "✖".red(), "Failed to run the tests".red());
+
+                for line in err.to_string().lines() {
+                    println!("      {}", line.red());
+                }
+            }
+        }
+    }
+
+    runtime.dispose();
+
+    println!();
+    println!(
+        "{} passed, {} failed, {} skipped {}",
+        summary.passed.to_string().green(),
+        summary.failed.to_string().red(),
+        summary.skipped,
+        debug(&format!(
+            "({} files in {:.2}s)",
+            test_files.len(),
+            start.elapsed().as_secs_f64()
+        ))
+    );
+
+    match (summary.failed, failed_files) {
+        (0, 0) => Ok(()),
+        (0, failed_files) => Err(anyhow!("{} test files failed to run", failed_files)),
+        (failed, _) => Err(anyhow!("{} tests failed", failed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_test_file_extensions() {
+        assert!(is_test_file(Path::new("index.test.ts")));
+        assert!(is_test_file(Path::new("src/utils.test.js")));
+        assert!(is_test_file(Path::new("App.test.tsx")));
+        assert!(!is_test_file(Path::new("index.ts")));
+        assert!(!is_test_file(Path::new("test.ts")));
+        assert!(!is_test_file(Path::new("index.test.json")));
+        assert!(!is_test_file(Path::new("index.tests.ts")));
+    }
+
+    #[test]
+    fn parse_test_results() {
+        let results: Vec<TestResult> = serde_json::from_str(
+            r#"[{"name":"adds","status":"passed","duration":1},{"name":"fails","status":"failed","duration":2,"error":"Expected 1 to be 2"}]"#,
+        )
+        .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].status, TestStatus::Passed);
+        assert_eq!(results[1].status, TestStatus::Failed);
+        assert_eq!(results[1].error.as_deref(), Some("Expected 1 to be 2"));
+    }
+}
@@ -0,0 +1,372 @@
+use std::{
+    env, fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::utils::{debug, get_version, print_progress, success};
+
+const RELEASES_URL: &str = "https://api.github.com/repos/lagonapp/lagon/releases?per_page=100";
+// Releases of the other packages of the monorepo are listed too
+const RELEASE_TAG_PREFIX: &str = "@lagon/cli@";
+
+#[cfg(windows)]
+const BINARY_NAME: &str = "lagon.exe";
+
+#[cfg(not(windows))]
+const BINARY_NAME: &str = "lagon";
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    tag_name: String,
+    draft: bool,
+    prerelease: bool,
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+    // Computed by GitHub for recent uploads, e.g `sha256:<hex>`
+    digest: Option<String>,
+}
+
+impl Release {
+    fn version(&self) -> Option<&str> {
+        self.tag_name.strip_prefix(RELEASE_TAG_PREFIX)
+    }
+
+    fn asset(&self, name: &str) -> Option<&ReleaseAsset> {
+        self.assets.iter().find(|asset| asset.name == name)
+    }
+}
+
+// Same names as the artifacts built by the CD workflow
+fn platform_asset_name() -> Result<&'static str> {
+    match (env::consts::OS, env::consts::ARCH) {
+        ("linux", "x86_64") => Ok("lagon-linux-x64"),
+        ("linux", "aarch64") => Ok("lagon-linux-arm64"),
+        ("macos", _) => Ok("lagon-darwin-x64"),
+        ("windows", _) => Ok("lagon-win-x64"),
+        (os, arch) => Err(anyhow!("No release available for {} {}", os, arch)),
+    }
+}
+
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .split('.')
+        .map(|part| part.parse::<u64>().ok())
+        .collect()
+}
+
+fn is_newer(version: &str, current: &str) -> bool {
+    match (parse_version(version), parse_version(current)) {
+        (Some(version), Some(current)) => version > current,
+        _ => false,
+    }
+}
+
+fn find_latest_release(releases: &[Release]) -> Option<&Release> {
+    releases
+        .iter()
+        .filter(|release| !release.draft && !release.prerelease)
+        .filter(|release| release.version().and_then(parse_version).is_some())
+        .max_by_key(|release| release.version().and_then(parse_version))
+}
+
+fn sha256(content: &[u8]) -> String {
+    Sha256::digest(content)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn verify_checksum(content: &[u8], expected: &str) -> Result<()> {
+    let actual = sha256(content);
+
+    match actual.eq_ignore_ascii_case(expected.trim()) {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Checksum mismatch: expected {}, got {}",
+            expected.trim(),
+            actual
+        )),
+    }
+}
+
+fn parse_octal(field: &[u8]) -> Option<usize> {
+    let field = std::str::from_utf8(field).ok()?;
+    let field = field.trim_matches(|char: char| char == '\0' || char == ' ');
+
+    usize::from_str_radix(field, 8).ok()
+}
+
+// Artifacts are `.tar.gz` archives containing a single directory with the binary,
+// so only regular files of the ustar format need to be supported
+fn extract_binary(archive: &[u8], name: &str) -> Result<Vec<u8>> {
+    let mut tar = Vec::new();
+    GzDecoder::new(archive).read_to_end(&mut tar)?;
+
+    let mut offset = 0;
+
+    while offset + 512 <= tar.len() {
+        let header = &tar[offset..offset + 512];
+
+        if header.iter().all(|byte| *byte == 0) {
+            break;
+        }
+
+        let path = String::from_utf8_lossy(&header[..100]);
+        let path = path.trim_end_matches('\0');
+        let size = parse_octal(&header[124..136])
+            .ok_or_else(|| anyhow!("Invalid archive: could not read size of {}", path))?;
+        let is_file = header[156] == b'0' || header[156] == 0;
+        let start = offset + 512;
+
+        if is_file
+            && Path::new(path)
+                .file_name()
+                .map_or(false, |file| file == name)
+        {
+            return tar
+                .get(start..start + size)
+                .map(|content| content.to_vec())
+                .ok_or_else(|| anyhow!("Invalid archive: {} is truncated", path));
+        }
+
+        // The content is padded to a multiple of 512 bytes
+        offset = start + (size + 511) / 512 * 512;
+    }
+
+    Err(anyhow!("Could not find {} in the archive", name))
+}
+
+fn replace_executable(binary: &[u8]) -> Result<PathBuf> {
+    let executable = env::current_exe()?.canonicalize()?;
+    let new_executable = executable.with_extension("new");
+
+    fs::write(&new_executable, binary)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        fs::set_permissions(&new_executable, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // Renaming is atomic, so the executable is never left half-written
+    #[cfg(not(windows))]
+    fs::rename(&new_executable, &executable)?;
+
+    // A running executable can't be replaced on Windows, but it can be renamed
+    #[cfg(windows)]
+    {
+        let old_executable = executable.with_extension("old");
+
+        fs::remove_file(&old_executable).unwrap_or(());
+        fs::rename(&executable, &old_executable)?;
+
+        if let Err(err) = fs::rename(&new_executable, &executable) {
+            fs::rename(&old_executable, &executable).unwrap_or(());
+            return Err(err.into());
+        }
+    }
+
+    Ok(executable)
+}
+
+async fn download(client: &reqwest::Client, url: &str) -> Result<Vec<u8>> {
+    let response = client.get(url).send().await?.error_for_status()?;
+
+    Ok(response.bytes().await?.to_vec())
+}
+
+// reqwest uses the proxy set in `HTTPS_PROXY` (or `HTTP_PROXY`) by default
+pub async fn upgrade(check: bool) -> Result<()> {
+    let version = get_version()?;
+    let client = reqwest::Client::builder()
+        .user_agent(format!("lagon-cli/{version}"))
+        .build()?;
+
+    let end_progress = print_progress("Checking for updates...");
+    let releases = download(&client, RELEASES_URL).await?;
+    let releases = serde_json::from_slice::<Vec<Release>>(&releases)?;
+    end_progress();
+
+    let release = find_latest_release(&releases).filter(|release| {
+        release
+            .version()
+            .map_or(false, |latest| is_newer(latest, &version))
+    });
+
+    let release = match release {
+        Some(release) => release,
+        None => {
+            println!();
+            println!(
+                "{}",
+                success(&format!("You are using the latest version ({version})"))
+            );
+
+            return Ok(());
+        }
+    };
+    let latest = release.version().unwrap_or_default();
+
+    if check {
+        println!();
+        println!(
+            "{} {}",
+            format!("A new version is available: {version} → {latest}").yellow(),
+            debug("(run `lagon upgrade` to install it)")
+        );
+
+        return Ok(());
+    }
+
+    let asset_name = format!("{}.tar.gz", platform_asset_name()?);
+    let asset = release
+        .asset(&asset_name)
+        .ok_or_else(|| anyhow!("Release {} has no {} artifact", latest, asset_name))?;
+
+    let message = format!("Downloading {asset_name}...");
+    let end_progress = print_progress(&message);
+    let archive = download(&client, &asset.browser_download_url).await?;
+    end_progress();
+
+    // Published by the CD workflow next to each artifact
+    let checksum = match release.asset(&format!("{asset_name}.sha256")) {
+        Some(checksum) => {
+            let checksum = download(&client, &checksum.browser_download_url).await?;
+
+            String::from_utf8_lossy(&checksum)
+                .split_whitespace()
+                .next()
+                .map(String::from)
+        }
+        None => asset
+            .digest
+            .as_ref()
+            .and_then(|digest| digest.strip_prefix("sha256:"))
+            .map(String::from),
+    }
+    .ok_or_else(|| anyhow!("Release {} has no checksum for {}", latest, asset_name))?;
+
+    verify_checksum(&archive, &checksum)?;
+
+    let end_progress = print_progress("Installing...");
+    let binary = extract_binary(&archive, BINARY_NAME)?;
+    let executable = replace_executable(&binary)?;
+    end_progress();
+
+    println!();
+    println!(
+        "{} {}",
+        success(&format!("Upgraded from {version} to {latest}!")),
+        debug(&format!("({})", executable.display()))
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{write::GzEncoder, Compression};
+
+    use super::*;
+
+    fn release(tag_name: &str, prerelease: bool) -> Release {
+        Release {
+            tag_name: tag_name.into(),
+            draft: false,
+            prerelease,
+            assets: Vec::new(),
+        }
+    }
+
+    fn tar_gz(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut tar = Vec::new();
+
+        for (path, content) in files {
+            let mut header = [0u8; 512];
+            header[..path.len()].copy_from_slice(path.as_bytes());
+            header[124..136].copy_from_slice(format!("{:011o}\0", content.len()).as_bytes());
+            header[156] = b'0';
+            header[257..263].copy_from_slice(b"ustar\0");
+
+            tar.extend_from_slice(&header);
+            tar.extend_from_slice(content);
+            tar.resize((tar.len() + 511) / 512 * 512, 0);
+        }
+
+        tar.resize(tar.len() + 1024, 0);
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&tar).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn compare_versions() {
+        assert!(is_newer("0.5.6", "0.5.5"));
+        assert!(is_newer("0.10.0", "0.9.9"));
+        assert!(is_newer("1.0.0", "0.99.0"));
+        assert!(!is_newer("0.5.5", "0.5.5"));
+        assert!(!is_newer("0.5.4", "0.5.5"));
+        assert!(!is_newer("latest", "0.5.5"));
+    }
+
+    #[test]
+    fn latest_cli_release() {
+        let releases = vec![
+            release("@lagon/runtime@0.9.0", false),
+            release("@lagon/cli@0.5.4", false),
+            release("@lagon/cli@0.6.0-beta", true),
+            release("@lagon/cli@0.5.10", false),
+            release("@lagon/cli@0.5.9", false),
+        ];
+
+        assert_eq!(
+            find_latest_release(&releases).unwrap().tag_name,
+            "@lagon/cli@0.5.10"
+        );
+        assert!(find_latest_release(&[release("@lagon/runtime@0.9.0", false)]).is_none());
+    }
+
+    #[test]
+    fn verify_sha256_checksum() {
+        assert!(verify_checksum(
+            b"hello",
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824\n"
+        )
+        .is_ok());
+        assert!(verify_checksum(
+            b"hello!",
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn extract_binary_from_archive() {
+        let archive = tar_gz(&[
+            ("lagon-linux-x64/README.md", b"readme"),
+            ("lagon-linux-x64/lagon", &[0x7f, b'E', b'L', b'F']),
+        ]);
+
+        assert_eq!(
+            extract_binary(&archive, "lagon").unwrap(),
+            vec![0x7f, b'E', b'L', b'F']
+        );
+        assert!(extract_binary(&archive, "lagon.exe").is_err());
+    }
+}
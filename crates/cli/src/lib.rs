@@ -0,0 +1,4 @@
+// The commands are also exposed as a library, e.g to embed
+// the dev server in a test harness using `DevServer`
+pub mod commands;
+pub mod utils;
@@ -1,19 +1,15 @@
-use std::{path::PathBuf, process::exit};
+use std::{path::PathBuf, process::exit, time::Duration};
 
-use clap::{Parser, Subcommand};
-use serde::Deserialize;
+use clap::{parser::ValueSource, CommandFactory, FromArgMatches, Parser, Subcommand};
+use clap_complete::Shell;
 
-use crate::utils::error;
-
-mod commands;
-mod utils;
-
-static PACKAGE_JSON: &str = include_str!("../package.json");
-
-#[derive(Deserialize)]
-struct PackageJson {
-    version: String,
-}
+use lagon_cli::{
+    commands::{self, BenchOptions, BuildOptions, DevOptions, RunOptions, Template},
+    utils::{
+        error, get_version, merge_option, BrowserOpener, Cors, DefaultBrowser, DevConfig,
+        LogFormat, LogLevel, DEFAULT_MASK_PATTERNS,
+    },
+};
 
 #[derive(Parser, Debug)]
 #[command(author, about, long_about = None, arg_required_else_help = true)]
@@ -52,6 +48,18 @@ enum Commands {
         #[clap(value_parser)]
         directory: Option<PathBuf>,
     },
+    /// Create a new Function from a template
+    New {
+        /// Directory to create the Function in
+        #[clap(value_parser)]
+        name: PathBuf,
+        /// Template to create the Function from
+        #[clap(short, long, value_enum, default_value_t = Template::Plain)]
+        template: Template,
+        /// Overwrite the files of a non-empty directory
+        #[clap(long)]
+        force: bool,
+    },
     /// Start a local dev server to test a Functon
     Dev {
         /// Path to a file or a directory containing a Function
@@ -69,12 +77,231 @@ enum Commands {
         /// Hostname to start dev server on
         #[clap(long)]
         hostname: Option<String>,
-        /// Path to a env file to parse
+        /// Path to a env file to parse, can be repeated to override previous files
         #[clap(short, long, value_parser)]
-        env: Option<PathBuf>,
+        env: Vec<PathBuf>,
+        /// Environment variable to set as KEY=VALUE, applied after env files
+        #[clap(long)]
+        env_var: Vec<String>,
         /// Allow code generation from strings using `eval` / `new Function`
         #[clap(long)]
         allow_code_generation: bool,
+        /// Allow reading files in this directory using `Lagon.fs.readFile()`
+        #[clap(long, value_name = "DIR", value_parser)]
+        allow_fs: Option<PathBuf>,
+        /// Seconds to wait for in-flight requests to finish when shutting down
+        #[clap(long, default_value_t = 5)]
+        grace_period: u64,
+        /// Print detailed information about each request
+        #[clap(long)]
+        verbose: bool,
+        /// Keep the terminal output instead of clearing it on reload
+        #[clap(long)]
+        no_clear: bool,
+        /// Show errors thrown by the Function as an HTML page
+        #[clap(long)]
+        error_overlay: bool,
+        /// Path to a PEM certificate to serve over HTTPS
+        #[clap(long, value_parser, requires = "key")]
+        cert: Option<PathBuf>,
+        /// Path to the PEM private key of the certificate
+        #[clap(long, value_parser, requires = "cert")]
+        key: Option<PathBuf>,
+        /// Serve over HTTPS using a generated self-signed certificate for localhost
+        #[clap(long, conflicts_with = "cert")]
+        self_signed: bool,
+        /// Number of requests to record, to replay them using `POST /__lagon/replay/<id>`
+        #[clap(long, default_value_t = 25)]
+        replay_buffer: usize,
+        /// Maximum execution time of a request in milliseconds, 0 to disable
+        #[clap(long, default_value_t = 1000)]
+        timeout: u64,
+        /// Maximum execution time of the Function's startup in milliseconds, 0 to disable
+        #[clap(long, default_value_t = 2000)]
+        startup_timeout: u64,
+        /// Maximum heap size of the Function in megabytes
+        #[clap(long, default_value_t = 128)]
+        memory: usize,
+        /// Recreate the isolate after each request to simulate cold starts
+        #[clap(long)]
+        cold_start: bool,
+        /// Recreate the isolate after the given number of requests to simulate cold starts
+        #[clap(long, value_parser = clap::value_parser!(u64).range(1..))]
+        cold_start_every: Option<u64>,
+        /// Path to a JSON or TOML file of canned responses to use for matching fetch() calls
+        #[clap(long, value_parser)]
+        mock: Option<PathBuf>,
+        /// Make fetch() calls that don't match any mock fail instead of hitting the network
+        #[clap(long, requires = "mock")]
+        mock_strict: bool,
+        /// Open the dev server in the default browser once it's ready
+        #[clap(long)]
+        open: bool,
+        /// Format of the logs and request lines, `json` prints one JSON object per line
+        #[clap(long, value_enum, default_value_t = LogFormat::Text)]
+        log_format: LogFormat,
+        /// Minimum level of the Function's console logs [default: info, debug with --verbose]
+        #[clap(long, value_enum)]
+        log_level: Option<LogLevel>,
+        /// List the content of folders in the public directory that don't have an index.html
+        #[clap(long)]
+        serve_index: bool,
+        /// URL of an origin to forward requests that don't match an asset to
+        #[clap(long)]
+        proxy: Option<String>,
+        /// Only forward requests starting with this path to the proxy, can be repeated
+        #[clap(long, requires = "proxy")]
+        proxy_paths: Vec<String>,
+        /// Allow cross-origin requests from any origin
+        #[clap(long)]
+        cors: bool,
+        /// Allow cross-origin requests from this origin, can be repeated
+        #[clap(long)]
+        cors_origin: Vec<String>,
+        /// Print the heap statistics of the Function every given number of seconds
+        #[clap(long, value_parser = clap::value_parser!(u64).range(1..))]
+        heap_stats: Option<u64>,
+        /// Additional Function to mount on a route as <ROUTE>=<PATH>, can be repeated
+        #[clap(long)]
+        function: Vec<String>,
+        /// Maximum size of request bodies in megabytes
+        #[clap(long, default_value_t = 10)]
+        max_body_size: usize,
+        /// Maximum size in megabytes of the responses stored with the Cache API
+        #[clap(long, default_value_t = 64)]
+        cache_size: usize,
+        /// Write the values of Lagon.kv to `.lagon/kv.json`, to keep them across restarts
+        #[clap(long)]
+        persist_kv: bool,
+        /// How many times failed deliveries of Lagon.queue messages are retried
+        #[clap(long, default_value_t = 3)]
+        queue_retries: usize,
+        /// Maximum number of isolates of each Function, created when the others are busy
+        #[clap(long, default_value_t = 1)]
+        #[clap(value_parser = clap::value_parser!(u64).range(1..))]
+        concurrency: u64,
+        /// Listen for Chrome DevTools connections to debug the Function, on port 9229 by default
+        #[clap(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "9229")]
+        inspect: Option<u16>,
+        /// Append every log line to this file as JSON, e.g to read it with `lagon logs`
+        #[clap(long, value_parser, value_name = "PATH")]
+        log_file: Option<PathBuf>,
+        /// Size in megabytes after which the log file is rotated
+        #[clap(long, default_value_t = 10, requires = "log_file")]
+        #[clap(value_parser = clap::value_parser!(u64).range(1..))]
+        log_file_max_size: u64,
+        /// Path to a snapshot built with `lagon snapshot`, to speed up the Function's startup
+        #[clap(long, value_parser)]
+        snapshot: Option<PathBuf>,
+        /// Hide from the logs the values of the environment variables matching this pattern
+        #[clap(long, value_name = "PATTERN", default_values = DEFAULT_MASK_PATTERNS)]
+        mask: Vec<String>,
+        /// Print the values of the environment variables in the logs as is
+        #[clap(long)]
+        no_mask: bool,
+        /// Compress responses with gzip or brotli when the client accepts it
+        #[clap(long)]
+        compress: bool,
+        /// Path to a PEM file of root certificates to trust in fetch() calls, can be repeated
+        #[clap(long, value_parser, value_name = "PATH")]
+        cacert: Vec<PathBuf>,
+        /// Don't verify the TLS certificates of fetch() calls. Dangerous, only use it for development
+        #[clap(long)]
+        insecure: bool,
+        /// Invoke the `scheduled` export with this cron expression once the Function is ready
+        #[clap(long, value_name = "EXPRESSION")]
+        trigger_cron: Option<String>,
+    },
+    /// Measure the performance of a Function under load locally
+    Bench {
+        /// Path to a file or a directory containing a Function
+        #[clap(value_parser, value_name = "PATH")]
+        function: Option<PathBuf>,
+        /// Number of requests to send
+        #[clap(short = 'n', long, default_value_t = 1000)]
+        requests: usize,
+        /// Number of requests to send at the same time
+        #[clap(short, long, default_value_t = 10)]
+        #[clap(value_parser = clap::value_parser!(u64).range(1..))]
+        concurrency: u64,
+        /// Send requests for this number of seconds instead of a fixed number of requests
+        #[clap(long, value_parser = clap::value_parser!(u64).range(1..))]
+        duration: Option<u64>,
+        /// Path of the requests, e.g /api?query=value
+        #[clap(long, default_value = "/")]
+        path: String,
+        /// HTTP method of the requests
+        #[clap(long, default_value = "GET")]
+        method: String,
+        /// Body of the requests
+        #[clap(long)]
+        body: Option<String>,
+        /// Number of requests to send before the benchmark, excluded from the results
+        #[clap(long, default_value_t = 10)]
+        warmup: usize,
+        /// Print the results as JSON
+        #[clap(long)]
+        json: bool,
+    },
+    /// Send a single request to a Function and print its response
+    Run {
+        /// Path to a file or a directory containing a Function
+        #[clap(value_parser, value_name = "PATH")]
+        function: Option<PathBuf>,
+        /// Path of the request, e.g /api?query=value
+        #[clap(long, default_value = "/")]
+        path: String,
+        /// HTTP method of the request
+        #[clap(short = 'X', long, default_value = "GET")]
+        method: String,
+        /// Header of the request as "Key: Value", can be repeated
+        #[clap(short = 'H', long)]
+        header: Vec<String>,
+        /// Body of the request
+        #[clap(short, long)]
+        body: Option<String>,
+        /// Path to a file to use as the body of the request
+        #[clap(long, value_parser, conflicts_with = "body")]
+        body_file: Option<PathBuf>,
+    },
+    /// Run the tests of a Function (`*.test.ts` files) inside the runtime
+    Test {
+        /// Path to a directory containing tests, or to a single test file
+        #[clap(value_parser)]
+        path: Option<PathBuf>,
+        /// Only run tests whose name contains this value
+        #[clap(short, long)]
+        filter: Option<String>,
+        /// Maximum execution time of each test file in milliseconds, 0 to disable
+        #[clap(long, default_value_t = 5000)]
+        timeout: u64,
+    },
+    /// Print a log file written by `lagon dev --log-file`
+    Logs {
+        /// Path to the log file
+        #[clap(value_parser)]
+        path: PathBuf,
+        /// Keep printing new lines as they are written
+        #[clap(short, long)]
+        tail: bool,
+    },
+    /// Print a completion script for the given shell, e.g `lagon completions zsh > _lagon`
+    Completions {
+        /// Shell to generate the completion script for
+        #[clap(value_enum)]
+        shell: Shell,
+    },
+    /// Upgrade the CLI to the latest version
+    Upgrade {
+        /// Only check if a new version is available
+        #[clap(long)]
+        check: bool,
+    },
+    /// Build a snapshot of the runtime, to speed up the startup of Functions in `lagon dev`
+    Snapshot {
+        /// Path to write the snapshot to [default: snapshot.bin]
+        #[clap(short, long, value_parser)]
+        out: Option<PathBuf>,
     },
     /// Build a Function without deploying it
     Build {
@@ -87,6 +314,21 @@ enum Commands {
         /// Path to a public directory to serve assets from
         #[clap(short, long, value_parser)]
         public_dir: Option<PathBuf>,
+        /// Directory to write the output to [default: .lagon/out]
+        #[clap(short, long, value_parser)]
+        out: Option<PathBuf>,
+        /// Print the size of each module of the bundle instead of writing it
+        #[clap(long)]
+        analyze: bool,
+        /// Print the analysis as JSON
+        #[clap(long, requires = "analyze")]
+        json: bool,
+        /// Fail if the bundle is larger than this size in kilobytes
+        #[clap(long, value_name = "KB")]
+        limit: Option<usize>,
+        /// Path to a env file that must set the variables of the Function's `env`, can be repeated
+        #[clap(short, long, value_parser)]
+        env: Vec<PathBuf>,
     },
     /// Link a local Function file to an already deployed Function
     Link {
@@ -120,7 +362,8 @@ enum Commands {
 
 #[tokio::main]
 async fn main() {
-    let args = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let args = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
 
     if let Some(command) = args.command {
         if let Err(err) = match command {
@@ -133,6 +376,11 @@ async fn main() {
                 prod,
             } => commands::deploy(path, client, public_dir, prod).await,
             Commands::Rm { directory } => commands::rm(directory).await,
+            Commands::New {
+                name,
+                template,
+                force,
+            } => commands::new(name, template, force),
             Commands::Dev {
                 path,
                 client,
@@ -140,24 +388,269 @@ async fn main() {
                 port,
                 hostname,
                 env,
+                env_var,
                 allow_code_generation,
+                allow_fs,
+                grace_period,
+                verbose,
+                no_clear,
+                error_overlay,
+                cert,
+                key,
+                self_signed,
+                replay_buffer,
+                timeout,
+                startup_timeout,
+                memory,
+                cold_start,
+                cold_start_every,
+                mock,
+                mock_strict,
+                open,
+                log_format,
+                log_level,
+                serve_index,
+                proxy,
+                proxy_paths,
+                cors,
+                cors_origin,
+                heap_stats,
+                function,
+                max_body_size,
+                cache_size,
+                persist_kv,
+                queue_retries,
+                concurrency,
+                inspect,
+                log_file,
+                log_file_max_size,
+                snapshot,
+                mask,
+                no_mask,
+                compress,
+                cacert,
+                insecure,
+                trigger_cron,
+            } => match DevConfig::load(path.as_deref()) {
+                Ok(config) => {
+                    // Options given to the CLI take precedence over the ones of `lagon.toml`
+                    let matches = matches.subcommand_matches("dev").unwrap();
+                    let from_cli =
+                        |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+                    let cold_start =
+                        merge_option(cold_start, from_cli("cold_start"), config.cold_start);
+                    let open = merge_option(open, from_cli("open"), config.open);
+                    let cors = merge_option(cors, from_cli("cors"), config.cors);
+                    let cors_origin =
+                        merge_option(cors_origin, from_cli("cors_origin"), config.cors_origin);
+                    let max_body_size = merge_option(
+                        max_body_size,
+                        from_cli("max_body_size"),
+                        config.max_body_size,
+                    );
+                    let cache_size =
+                        merge_option(cache_size, from_cli("cache_size"), config.cache_size);
+                    let log_file_max_size = merge_option(
+                        log_file_max_size,
+                        from_cli("log_file_max_size"),
+                        config.log_file_max_size,
+                    );
+
+                    commands::dev(
+                        path,
+                        client.or(config.client),
+                        public_dir.or(config.public_dir),
+                        DevOptions {
+                            port: port.or(config.port),
+                            hostname: hostname.or(config.hostname),
+                            env: merge_option(env, from_cli("env"), config.env),
+                            env_vars: merge_option(env_var, from_cli("env_var"), config.env_var),
+                            allow_code_generation: merge_option(
+                                allow_code_generation,
+                                from_cli("allow_code_generation"),
+                                config.allow_code_generation,
+                            ),
+                            allow_fs: allow_fs.or(config.allow_fs),
+                            grace_period: Duration::from_secs(merge_option(
+                                grace_period,
+                                from_cli("grace_period"),
+                                config.grace_period,
+                            )),
+                            verbose: merge_option(verbose, from_cli("verbose"), config.verbose),
+                            no_clear: merge_option(no_clear, from_cli("no_clear"), config.no_clear),
+                            error_overlay: merge_option(
+                                error_overlay,
+                                from_cli("error_overlay"),
+                                config.error_overlay,
+                            ),
+                            cert: cert.or(config.cert),
+                            key: key.or(config.key),
+                            self_signed: merge_option(
+                                self_signed,
+                                from_cli("self_signed"),
+                                config.self_signed,
+                            ),
+                            replay_buffer: merge_option(
+                                replay_buffer,
+                                from_cli("replay_buffer"),
+                                config.replay_buffer,
+                            ),
+                            timeout: Duration::from_millis(merge_option(
+                                timeout,
+                                from_cli("timeout"),
+                                config.timeout,
+                            )),
+                            startup_timeout: Duration::from_millis(merge_option(
+                                startup_timeout,
+                                from_cli("startup_timeout"),
+                                config.startup_timeout,
+                            )),
+                            memory: merge_option(memory, from_cli("memory"), config.memory),
+                            cold_start_every: cold_start_every
+                                .or(config.cold_start_every)
+                                .map(|every| every as usize)
+                                .or(cold_start.then_some(1)),
+                            mock: mock.or(config.mock),
+                            mock_strict: merge_option(
+                                mock_strict,
+                                from_cli("mock_strict"),
+                                config.mock_strict,
+                            ),
+                            open: open.then(|| Box::new(DefaultBrowser) as Box<dyn BrowserOpener>),
+                            log_format: merge_option(
+                                log_format,
+                                from_cli("log_format"),
+                                config.log_format,
+                            ),
+                            log_level: log_level.or(config.log_level),
+                            serve_index: merge_option(
+                                serve_index,
+                                from_cli("serve_index"),
+                                config.serve_index,
+                            ),
+                            proxy: proxy.or(config.proxy),
+                            proxy_paths: merge_option(
+                                proxy_paths,
+                                from_cli("proxy_paths"),
+                                config.proxy_paths,
+                            ),
+                            cors: Cors::new(cors, cors_origin),
+                            heap_stats: heap_stats.or(config.heap_stats).map(Duration::from_secs),
+                            functions: merge_option(
+                                function,
+                                from_cli("function"),
+                                config.function,
+                            ),
+                            max_body_size: max_body_size * 1024 * 1024,
+                            cache_size: cache_size * 1024 * 1024,
+                            persist_kv: merge_option(
+                                persist_kv,
+                                from_cli("persist_kv"),
+                                config.persist_kv,
+                            ),
+                            queue_retries: merge_option(
+                                queue_retries,
+                                from_cli("queue_retries"),
+                                config.queue_retries,
+                            ),
+                            concurrency: merge_option(
+                                concurrency,
+                                from_cli("concurrency"),
+                                config.concurrency,
+                            ) as usize,
+                            inspect: inspect.or(config.inspect),
+                            log_file: log_file.or(config.log_file),
+                            log_file_max_size: log_file_max_size * 1024 * 1024,
+                            snapshot: snapshot.or(config.snapshot),
+                            mask: merge_option(mask, from_cli("mask"), config.mask),
+                            no_mask: merge_option(no_mask, from_cli("no_mask"), config.no_mask),
+                            compress: merge_option(compress, from_cli("compress"), config.compress),
+                            cacert,
+                            insecure,
+                            trigger_cron,
+                        },
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            },
+            Commands::Bench {
+                function,
+                requests,
+                concurrency,
+                duration,
+                path,
+                method,
+                body,
+                warmup,
+                json,
             } => {
-                commands::dev(
-                    path,
-                    client,
-                    public_dir,
-                    port,
-                    hostname,
-                    env,
-                    allow_code_generation,
+                commands::bench(
+                    function,
+                    BenchOptions {
+                        requests,
+                        concurrency: concurrency as usize,
+                        duration: duration.map(Duration::from_secs),
+                        path,
+                        method,
+                        body,
+                        warmup,
+                        json,
+                    },
+                )
+                .await
+            }
+            Commands::Run {
+                function,
+                path,
+                method,
+                header,
+                body,
+                body_file,
+            } => {
+                commands::run(
+                    function,
+                    RunOptions {
+                        path,
+                        method,
+                        headers: header,
+                        body,
+                        body_file,
+                    },
                 )
                 .await
             }
+            Commands::Test {
+                path,
+                filter,
+                timeout,
+            } => commands::test(path, filter, Duration::from_millis(timeout)).await,
+            Commands::Completions { shell } => commands::completions(shell, Cli::command()),
+            Commands::Upgrade { check } => commands::upgrade(check).await,
+            Commands::Snapshot { out } => commands::snapshot(out),
+            Commands::Logs { path, tail } => commands::logs(path, tail).await,
             Commands::Build {
                 path,
                 client,
                 public_dir,
-            } => commands::build(path, client, public_dir),
+                out,
+                analyze,
+                json,
+                limit,
+                env,
+            } => commands::build(
+                path,
+                client,
+                public_dir,
+                BuildOptions {
+                    out,
+                    analyze,
+                    json,
+                    limit: limit.map(|limit| limit * 1024),
+                    env,
+                },
+            ),
             Commands::Link { directory } => commands::link(directory).await,
             Commands::Ls { directory } => commands::ls(directory).await,
             Commands::Undeploy {
@@ -173,14 +666,41 @@ async fn main() {
             exit(1);
         }
     } else {
-        match serde_json::from_str(PACKAGE_JSON) {
-            Ok(PackageJson { version }) => {
+        match get_version() {
+            Ok(version) => {
                 println!("{version}");
             }
-            _ => {
-                println!("{}", error("Couldn't extract version from package.json"));
+            Err(err) => {
+                println!("{}", error(&err.to_string()));
                 exit(1);
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::ValueEnum;
+
+    use super::*;
+
+    #[test]
+    fn completions_contain_subcommands() {
+        let command = Cli::command();
+
+        for shell in Shell::value_variants() {
+            let script = commands::generate_completions(*shell, &mut command.clone());
+            let script = String::from_utf8(script).unwrap();
+
+            assert!(!script.is_empty());
+
+            for subcommand in command.get_subcommands() {
+                assert!(
+                    script.contains(subcommand.get_name()),
+                    "{shell} completions don't contain {}",
+                    subcommand.get_name()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+// Only the fields needed from ESBuild's metafile, see https://esbuild.github.io/api/#metafile
+#[derive(Deserialize)]
+struct Metafile {
+    outputs: HashMap<String, MetafileOutput>,
+}
+
+#[derive(Deserialize)]
+struct MetafileOutput {
+    inputs: HashMap<String, MetafileInput>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MetafileInput {
+    bytes_in_output: usize,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct ModuleSize {
+    pub path: String,
+    // Bytes of the bundle coming from this module, after tree shaking
+    pub bytes: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct BundleAnalysis {
+    // Sorted from the largest to the smallest module
+    pub modules: Vec<ModuleSize>,
+}
+
+pub fn parse_metafile(metafile: &str) -> Result<BundleAnalysis> {
+    let metafile = serde_json::from_str::<Metafile>(metafile)?;
+    let mut sizes = HashMap::<String, usize>::new();
+
+    for output in metafile.outputs.into_values() {
+        for (path, input) in output.inputs {
+            *sizes.entry(path).or_default() += input.bytes_in_output;
+        }
+    }
+
+    let mut modules = sizes
+        .into_iter()
+        .filter(|(_, bytes)| *bytes > 0)
+        .map(|(path, bytes)| ModuleSize { path, bytes })
+        .collect::<Vec<_>>();
+
+    modules.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+
+    Ok(BundleAnalysis { modules })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_metafile_sizes() {
+        let analysis = parse_metafile(
+            r#"{
+  "inputs": {},
+  "outputs": {
+    "<stdout>": {
+      "bytes": 1234,
+      "inputs": {
+        "index.ts": { "bytesInOutput": 120 },
+        "node_modules/lodash/lodash.js": { "bytesInOutput": 1000 },
+        "node_modules/lodash/unused.js": { "bytesInOutput": 0 },
+        "utils.ts": { "bytesInOutput": 114 }
+      }
+    }
+  }
+}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            analysis.modules,
+            vec![
+                ModuleSize {
+                    path: "node_modules/lodash/lodash.js".into(),
+                    bytes: 1000
+                },
+                ModuleSize {
+                    path: "index.ts".into(),
+                    bytes: 120
+                },
+                ModuleSize {
+                    path: "utils.ts".into(),
+                    bytes: 114
+                },
+            ]
+        );
+        assert!(parse_metafile("{}").is_err());
+    }
+}
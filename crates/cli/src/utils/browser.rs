@@ -0,0 +1,15 @@
+use anyhow::Result;
+
+// Allows replacing the browser in tests
+pub trait BrowserOpener: Send {
+    fn open(&self, url: &str) -> Result<()>;
+}
+
+pub struct DefaultBrowser;
+
+impl BrowserOpener for DefaultBrowser {
+    fn open(&self, url: &str) -> Result<()> {
+        webbrowser::open(url)?;
+        Ok(())
+    }
+}
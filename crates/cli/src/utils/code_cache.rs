@@ -0,0 +1,108 @@
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+// Enough to switch back and forth between a few versions of each Function
+const MAX_CODE_CACHES: usize = 16;
+
+// V8 only checks the length of the source a code cache was created from,
+// so both the code and the environment variables injected before it are hashed
+pub fn code_cache_key(code: &[u8], environment_variables: &HashMap<String, String>) -> String {
+    let mut environment_variables = environment_variables.iter().collect::<Vec<_>>();
+    environment_variables.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(code);
+
+    for (key, value) in environment_variables {
+        hasher.update(format!("\n{key}={value}"));
+    }
+
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+// The V8 code caches of the last evaluated bundles, so isolates recreated
+// with the same code (e.g after a cold start or a reload) skip its compilation
+#[derive(Debug, Default)]
+pub struct CodeCaches {
+    // The most recently used last
+    entries: Mutex<VecDeque<(String, Vec<u8>)>>,
+}
+
+impl CodeCaches {
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let mut entries = self.entries.lock().unwrap();
+        let index = entries.iter().position(|(entry_key, _)| entry_key == key)?;
+        let entry = entries.remove(index)?;
+        let code_cache = entry.1.clone();
+
+        entries.push_back(entry);
+
+        Some(code_cache)
+    }
+
+    pub fn insert(&self, key: String, code_cache: Vec<u8>) {
+        let mut entries = self.entries.lock().unwrap();
+
+        entries.retain(|(entry_key, _)| *entry_key != key);
+        entries.push_back((key, code_cache));
+
+        if entries.len() > MAX_CODE_CACHES {
+            entries.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_depends_on_code_and_environment_variables() {
+        let environment_variables = HashMap::from([
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+        ]);
+        let key = code_cache_key(b"code", &environment_variables);
+
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, code_cache_key(b"code", &environment_variables.clone()));
+        assert_ne!(key, code_cache_key(b"edoc", &environment_variables));
+        assert_ne!(key, code_cache_key(b"code", &HashMap::new()));
+        assert_ne!(
+            key,
+            code_cache_key(
+                b"code",
+                &HashMap::from([
+                    ("A".to_string(), "2".to_string()),
+                    ("B".to_string(), "1".to_string()),
+                ])
+            )
+        );
+    }
+
+    #[test]
+    fn evict_least_recently_used() {
+        let code_caches = CodeCaches::default();
+
+        for index in 0..MAX_CODE_CACHES {
+            code_caches.insert(index.to_string(), vec![index as u8]);
+        }
+
+        // Used again, so it's kept over the second one
+        assert_eq!(code_caches.get("0"), Some(vec![0]));
+
+        code_caches.insert("new".into(), vec![42]);
+
+        assert_eq!(code_caches.get("0"), Some(vec![0]));
+        assert_eq!(code_caches.get("1"), None);
+        assert_eq!(code_caches.get("new"), Some(vec![42]));
+        assert_eq!(code_caches.get("missing"), None);
+    }
+}
@@ -0,0 +1,200 @@
+use hyper::header::{
+    HeaderName, HeaderValue, ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_ALLOW_HEADERS,
+    ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE,
+    ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, VARY,
+};
+use hyper::{Body, HeaderMap, Method, Response as HyperResponse};
+use lagon_runtime_http::Response;
+use std::collections::HashMap;
+
+const ALLOWED_METHODS: &str = "GET, POST, PUT, PATCH, DELETE, HEAD, OPTIONS";
+const MAX_AGE: &str = "86400"; // 1 day
+
+pub enum Cors {
+    // Allow requests from any origin
+    Any,
+    // Only allow requests from these origins, which then
+    // can also send credentials (e.g cookies)
+    Origins(Vec<String>),
+}
+
+impl Cors {
+    pub fn new(any: bool, origins: Vec<String>) -> Option<Self> {
+        match (any, origins.is_empty()) {
+            (_, false) => Some(Cors::Origins(origins)),
+            (true, true) => Some(Cors::Any),
+            (false, true) => None,
+        }
+    }
+
+    pub fn is_preflight(method: &Method, headers: &HeaderMap) -> bool {
+        method == Method::OPTIONS && headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+    }
+
+    // Preflight requests never reach the Function. The allowed
+    // origin is set afterwards, like for any other response
+    pub fn preflight_response(headers: &HeaderMap) -> Response {
+        let requested_header = |name: HeaderName| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+
+        let mut response_headers = HashMap::with_capacity(3);
+        response_headers.insert(
+            ACCESS_CONTROL_ALLOW_METHODS.to_string(),
+            vec![requested_header(ACCESS_CONTROL_REQUEST_METHOD)
+                .unwrap_or_else(|| ALLOWED_METHODS.into())],
+        );
+        response_headers.insert(ACCESS_CONTROL_MAX_AGE.to_string(), vec![MAX_AGE.into()]);
+
+        if let Some(allowed_headers) = requested_header(ACCESS_CONTROL_REQUEST_HEADERS) {
+            response_headers.insert(
+                ACCESS_CONTROL_ALLOW_HEADERS.to_string(),
+                vec![allowed_headers],
+            );
+        }
+
+        Response {
+            status: 204,
+            headers: Some(response_headers),
+            ..Default::default()
+        }
+    }
+
+    // Headers already set (e.g by the Function) are kept as is
+    pub fn apply(&self, origin: Option<&HeaderValue>, response: &mut HyperResponse<Body>) {
+        let headers = response.headers_mut();
+
+        let allowed_origin = match self {
+            Cors::Any => Some(HeaderValue::from_static("*")),
+            Cors::Origins(origins) => {
+                // The response depends on the origin, so caches shouldn't reuse it
+                let has_vary_origin = headers.get_all(VARY).iter().any(|vary| {
+                    vary.to_str().map_or(false, |vary| {
+                        vary.split(',')
+                            .any(|value| value.trim().eq_ignore_ascii_case("origin"))
+                    })
+                });
+
+                if !has_vary_origin {
+                    headers.append(VARY, HeaderValue::from_static("Origin"));
+                }
+
+                origin
+                    .filter(|origin| {
+                        origin.to_str().map_or(false, |origin| {
+                            origins.iter().any(|allowed| allowed == origin)
+                        })
+                    })
+                    .cloned()
+            }
+        };
+
+        if let Some(allowed_origin) = allowed_origin {
+            if !headers.contains_key(ACCESS_CONTROL_ALLOW_ORIGIN) {
+                headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, allowed_origin);
+            }
+
+            if matches!(self, Cors::Origins(_))
+                && !headers.contains_key(ACCESS_CONTROL_ALLOW_CREDENTIALS)
+            {
+                headers.insert(
+                    ACCESS_CONTROL_ALLOW_CREDENTIALS,
+                    HeaderValue::from_static("true"),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_any() {
+        let mut response = HyperResponse::new(Body::empty());
+        Cors::Any.apply(None, &mut response);
+
+        assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!response.headers().contains_key(VARY));
+    }
+
+    #[test]
+    fn apply_origins() {
+        let cors = Cors::Origins(vec!["http://localhost:3000".into()]);
+
+        let mut response = HyperResponse::new(Body::empty());
+        cors.apply(
+            Some(&HeaderValue::from_static("http://localhost:3000")),
+            &mut response,
+        );
+
+        assert_eq!(
+            response.headers()[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "http://localhost:3000"
+        );
+        assert_eq!(response.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+        assert_eq!(response.headers()[VARY], "Origin");
+
+        let mut response = HyperResponse::new(Body::empty());
+        cors.apply(
+            Some(&HeaderValue::from_static("http://example.com")),
+            &mut response,
+        );
+
+        assert!(!response.headers().contains_key(ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[test]
+    fn apply_merge() {
+        let cors = Cors::Origins(vec!["http://localhost:3000".into()]);
+
+        let mut response = HyperResponse::builder()
+            .header(ACCESS_CONTROL_ALLOW_ORIGIN, "http://localhost:4000")
+            .header(VARY, "Accept-Encoding, origin")
+            .body(Body::empty())
+            .unwrap();
+        cors.apply(
+            Some(&HeaderValue::from_static("http://localhost:3000")),
+            &mut response,
+        );
+
+        let allowed_origins = response
+            .headers()
+            .get_all(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .iter()
+            .collect::<Vec<_>>();
+
+        assert_eq!(allowed_origins, vec!["http://localhost:4000"]);
+        assert_eq!(response.headers().get_all(VARY).iter().count(), 1);
+    }
+
+    #[test]
+    fn preflight() {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCESS_CONTROL_REQUEST_METHOD, "PUT".parse().unwrap());
+        headers.insert(
+            ACCESS_CONTROL_REQUEST_HEADERS,
+            "content-type".parse().unwrap(),
+        );
+
+        assert!(Cors::is_preflight(&Method::OPTIONS, &headers));
+        assert!(!Cors::is_preflight(&Method::OPTIONS, &HeaderMap::new()));
+
+        let response = Cors::preflight_response(&headers);
+        let response_headers = response.headers.unwrap();
+
+        assert_eq!(response.status, 204);
+        assert_eq!(
+            response_headers[ACCESS_CONTROL_ALLOW_METHODS.as_str()],
+            vec!["PUT".to_string()]
+        );
+        assert_eq!(
+            response_headers[ACCESS_CONTROL_ALLOW_HEADERS.as_str()],
+            vec!["content-type".to_string()]
+        );
+    }
+}
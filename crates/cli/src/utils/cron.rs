@@ -0,0 +1,264 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, DurationRound, TimeZone, Timelike, Utc};
+use std::{fmt, str::FromStr};
+
+// A search past this many days means the expression never
+// matches, e.g `0 0 31 2 *`. Covers leap years
+const MAX_SEARCHED_DAYS: i64 = 5 * 366;
+
+// Minimum and maximum values of the five fields
+const FIELDS: [(&str, u32, u32); 5] = [
+    ("minute", 0, 59),
+    ("hour", 0, 23),
+    ("day of month", 1, 31),
+    ("month", 1, 12),
+    ("day of week", 0, 7),
+];
+
+// A standard five fields cron expression, e.g `*/5 * * * *`, evaluated in UTC
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    expression: String,
+    // One bit per allowed value
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    // Like crontab, a day matches either of the day fields when both are restricted
+    days_or: bool,
+}
+
+fn parse_value(value: &str, name: &str, min: u32, max: u32) -> Result<u32> {
+    match value.parse::<u32>() {
+        Ok(value) if (min..=max).contains(&value) => Ok(value),
+        _ => Err(anyhow!(
+            "Invalid {name} `{value}`, expected a number between {min} and {max}"
+        )),
+    }
+}
+
+fn parse_field(field: &str, name: &str, min: u32, max: u32) -> Result<u64> {
+    let mut bits = 0;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(anyhow!("Invalid step `{step}` of the {name} field")),
+            },
+            None => (part, 1),
+        };
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (
+                    parse_value(start, name, min, max)?,
+                    parse_value(end, name, min, max)?,
+                ),
+                // `5/15` means every 15 starting at 5
+                None if part.contains('/') => (parse_value(range, name, min, max)?, max),
+                None => {
+                    let value = parse_value(range, name, min, max)?;
+                    (value, value)
+                }
+            },
+        };
+
+        if start > end {
+            return Err(anyhow!("Invalid range `{range}` of the {name} field"));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+impl Cron {
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let day_of_month = has(self.days_of_month, time.day());
+        let day_of_week = has(self.days_of_week, time.weekday().num_days_from_sunday());
+
+        match self.days_or {
+            true => day_of_month || day_of_week,
+            false => day_of_month && day_of_week,
+        }
+    }
+
+    // The first matching minute strictly after the given time
+    pub fn next_after(&self, time: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut next = time.duration_trunc(Duration::minutes(1)).ok()? + Duration::minutes(1);
+        let limit = next + Duration::days(MAX_SEARCHED_DAYS);
+
+        while next < limit {
+            // Skip whole months, days and hours when possible
+            if !has(self.months, next.month()) {
+                let (year, month) = match next.month() {
+                    12 => (next.year() + 1, 1),
+                    month => (next.year(), month + 1),
+                };
+
+                next = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+            } else if !self.matches_day(&next) {
+                next = Utc.from_utc_datetime(&next.date_naive().and_hms_opt(0, 0, 0)?)
+                    + Duration::days(1);
+            } else if !has(self.hours, next.hour()) {
+                next = next.duration_trunc(Duration::hours(1)).ok()? + Duration::hours(1);
+            } else if !has(self.minutes, next.minute()) {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+
+        None
+    }
+}
+
+impl FromStr for Cron {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let fields = expression.split_whitespace().collect::<Vec<_>>();
+
+        if fields.len() != FIELDS.len() {
+            return Err(anyhow!(
+                "Invalid cron expression `{expression}`, expected 5 fields (minute hour day-of-month month day-of-week)"
+            ));
+        }
+
+        let mut bits = [0; 5];
+
+        for (index, (field, (name, min, max))) in fields.iter().zip(FIELDS).enumerate() {
+            bits[index] = parse_field(field, name, min, max)
+                .map_err(|err| anyhow!("Invalid cron expression `{expression}`: {err}"))?;
+        }
+
+        let [minutes, hours, days_of_month, months, mut days_of_week] = bits;
+
+        // Both 0 and 7 are Sunday
+        if has(days_of_week, 7) {
+            days_of_week |= 1;
+        }
+
+        Ok(Cron {
+            expression: fields.join(" "),
+            minutes,
+            hours,
+            days_of_month,
+            months,
+            days_of_week,
+            days_or: !fields[2].starts_with('*') && !fields[4].starts_with('*'),
+        })
+    }
+}
+
+impl fmt::Display for Cron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(expression: &str, time: &str) -> Option<String> {
+        let time = DateTime::parse_from_rfc3339(time)
+            .unwrap()
+            .with_timezone(&Utc);
+
+        expression
+            .parse::<Cron>()
+            .unwrap()
+            .next_after(&time)
+            .map(|next| next.to_rfc3339())
+    }
+
+    #[test]
+    fn parse_valid() {
+        for expression in [
+            "* * * * *",
+            "*/5 * * * *",
+            "0 9-17 * * 1-5",
+            "0,30 * 1,15 * *",
+            "5/15 0 1 1 7",
+        ] {
+            assert!(expression.parse::<Cron>().is_ok(), "{expression}");
+        }
+
+        assert_eq!(
+            "*/5  *  * * *".parse::<Cron>().unwrap().to_string(),
+            "*/5 * * * *"
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        assert_eq!(
+            "* * * *".parse::<Cron>().unwrap_err().to_string(),
+            "Invalid cron expression `* * * *`, expected 5 fields (minute hour day-of-month month day-of-week)"
+        );
+        assert_eq!(
+            "60 * * * *".parse::<Cron>().unwrap_err().to_string(),
+            "Invalid cron expression `60 * * * *`: Invalid minute `60`, expected a number between 0 and 59"
+        );
+        assert_eq!(
+            "*/0 * * * *".parse::<Cron>().unwrap_err().to_string(),
+            "Invalid cron expression `*/0 * * * *`: Invalid step `0` of the minute field"
+        );
+        assert_eq!(
+            "* 5-1 * * *".parse::<Cron>().unwrap_err().to_string(),
+            "Invalid cron expression `* 5-1 * * *`: Invalid range `5-1` of the hour field"
+        );
+        assert!("* * * jan *".parse::<Cron>().is_err());
+    }
+
+    #[test]
+    fn next_every_five_minutes() {
+        assert_eq!(
+            next("*/5 * * * *", "2023-04-01T10:02:30Z"),
+            Some("2023-04-01T10:05:00+00:00".into())
+        );
+        // Strictly after the given time
+        assert_eq!(
+            next("*/5 * * * *", "2023-04-01T10:05:00Z"),
+            Some("2023-04-01T10:10:00+00:00".into())
+        );
+        assert_eq!(
+            next("*/5 * * * *", "2023-12-31T23:59:00Z"),
+            Some("2024-01-01T00:00:00+00:00".into())
+        );
+    }
+
+    #[test]
+    fn next_days() {
+        // 2023-04-01 is a Saturday
+        assert_eq!(
+            next("0 9 * * 1-5", "2023-04-01T10:00:00Z"),
+            Some("2023-04-03T09:00:00+00:00".into())
+        );
+        assert_eq!(
+            next("0 0 * * 7", "2023-04-01T10:00:00Z"),
+            Some("2023-04-02T00:00:00+00:00".into())
+        );
+        // Either the 15th or a Monday
+        assert_eq!(
+            next("0 0 15 * 1", "2023-04-01T10:00:00Z"),
+            Some("2023-04-03T00:00:00+00:00".into())
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2023-04-01T10:00:00Z"),
+            Some("2024-02-29T00:00:00+00:00".into())
+        );
+        assert_eq!(next("0 0 31 2 *", "2023-04-01T10:00:00Z"), None);
+    }
+}
@@ -15,11 +15,14 @@ use walkdir::{DirEntry, WalkDir};
 use pathdiff::diff_paths;
 use serde::{Deserialize, Serialize};
 
-use crate::utils::{debug, info, print_progress, success, TrpcClient};
+use crate::utils::{
+    debug, find_tsconfig, info, parse_metafile, print_progress, success, BundleAnalysis,
+    TrpcClient, TsConfig,
+};
 
 use super::{
-    validate_assets_dir, validate_code_file, Config, MAX_ASSETS_PER_FUNCTION, MAX_ASSET_SIZE_MB,
-    MAX_FUNCTION_SIZE_MB,
+    suggest_key, validate_assets_dir, validate_code_file, Config, MAX_ASSETS_PER_FUNCTION,
+    MAX_ASSET_SIZE_MB, MAX_FUNCTION_SIZE_MB,
 };
 
 pub type Assets = HashMap<String, Vec<u8>>;
@@ -30,6 +33,80 @@ const ESBUILD: &str = "esbuild.cmd";
 #[cfg(not(windows))]
 const ESBUILD: &str = "esbuild";
 
+// Keys of `.lagon/config.json`, any other key is rejected
+const FUNCTION_CONFIG_KEYS: [&str; 9] = [
+    "function_id",
+    "organization_id",
+    "index",
+    "client",
+    "assets",
+    "assets_fallback",
+    "jsx",
+    "env",
+    "crons",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JsxRuntime {
+    // Imports `jsx` from `<import_source>/jsx-runtime` automatically
+    #[default]
+    Automatic,
+    // Calls the factory and fragment, which need to be in scope
+    Classic,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct JsxConfig {
+    #[serde(default)]
+    pub runtime: JsxRuntime,
+    // e.g `preact`, defaults to `react`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub import_source: Option<String>,
+    // e.g `h`, defaults to `React.createElement`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub factory: Option<String>,
+    // e.g `Fragment`, defaults to `React.Fragment`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fragment: Option<String>,
+}
+
+impl JsxConfig {
+    fn validate(&self) -> Result<()> {
+        match self.runtime {
+            JsxRuntime::Automatic if self.factory.is_some() || self.fragment.is_some() => Err(
+                anyhow!("`factory` and `fragment` are only used by the classic runtime"),
+            ),
+            JsxRuntime::Classic if self.import_source.is_some() => Err(anyhow!(
+                "`import_source` is only used by the automatic runtime"
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn esbuild_args(&self) -> Vec<String> {
+        let mut args = vec![match self.runtime {
+            JsxRuntime::Automatic => String::from("--jsx=automatic"),
+            JsxRuntime::Classic => String::from("--jsx=transform"),
+        }];
+
+        if let Some(import_source) = &self.import_source {
+            args.push(format!("--jsx-import-source={import_source}"));
+        }
+
+        if let Some(factory) = &self.factory {
+            args.push(format!("--jsx-factory={factory}"));
+        }
+
+        if let Some(fragment) = &self.fragment {
+            args.push(format!("--jsx-fragment={fragment}"));
+        }
+
+        args
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct FunctionConfig {
     pub function_id: String,
@@ -37,6 +114,18 @@ pub struct FunctionConfig {
     pub index: PathBuf,
     pub client: Option<PathBuf>,
     pub assets: Option<PathBuf>,
+    // Asset served for pages that don't match any asset, e.g `index.html` for single-page apps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assets_fallback: Option<String>,
+    // How JSX in `.jsx` and `.tsx` files is transformed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jsx: Option<JsxConfig>,
+    // Names of the environment variables the Function needs to run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub env: Vec<String>,
+    // Cron expressions invoking the `scheduled` export, e.g `*/5 * * * *`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub crons: Vec<String>,
 }
 
 impl FunctionConfig {
@@ -112,6 +201,10 @@ impl FunctionConfig {
                 index,
                 client: None,
                 assets,
+                assets_fallback: None,
+                jsx: None,
+                env: Vec::new(),
+                crons: Vec::new(),
             };
 
             config.write(root)?;
@@ -119,26 +212,72 @@ impl FunctionConfig {
             return Ok(config);
         }
 
-        let content = fs::read_to_string(path)?;
-        let mut config = serde_json::from_str::<FunctionConfig>(&content)?;
+        let content = fs::read_to_string(&path)?;
+        let mut config = FunctionConfig::parse(&content)
+            .map_err(|err| anyhow!("Invalid configuration {}: {}", path.display(), err))?;
+
+        // Errors of values coming from the config file mention the
+        // key to fix, but not the ones coming from the CLI's options
+        let invalid_key = |key: &str, err: anyhow::Error| {
+            anyhow!("Invalid `{}` in {}: {}", key, path.display(), err)
+        };
 
-        if let Some(client_override) = client_override {
-            println!("{}", debug("Using custom entrypoint..."));
-            config.client = Some(client_override);
+        validate_code_file(&config.index, root).map_err(|err| invalid_key("index", err))?;
+
+        match client_override {
+            Some(client_override) => {
+                println!("{}", debug("Using custom entrypoint..."));
+                validate_code_file(&client_override, root)?;
+                config.client = Some(client_override);
+            }
+            None => {
+                if let Some(client) = &config.client {
+                    validate_code_file(client, root).map_err(|err| invalid_key("client", err))?;
+                }
+            }
         }
 
-        if let Some(assets_override) = assets_override {
-            println!("{}", debug("Using custom public directory..."));
-            config.assets = Some(assets_override);
+        match assets_override {
+            Some(assets_override) => {
+                println!("{}", debug("Using custom public directory..."));
+                config.assets = Some(assets_override);
+                validate_assets_dir(&config.assets, root)?;
+            }
+            None => {
+                validate_assets_dir(&config.assets, root)
+                    .map_err(|err| invalid_key("assets", err))?;
+            }
         }
 
-        validate_code_file(&config.index, root)?;
+        Ok(config)
+    }
 
-        if let Some(client) = &config.client {
-            validate_code_file(client, root)?;
+    fn parse(content: &str) -> Result<FunctionConfig> {
+        let value = serde_json::from_str::<serde_json::Value>(content)?;
+
+        if let Some(object) = value.as_object() {
+            for key in object.keys() {
+                if !FUNCTION_CONFIG_KEYS.contains(&key.as_str()) {
+                    return Err(match suggest_key(key, &FUNCTION_CONFIG_KEYS) {
+                        Some(suggestion) => {
+                            anyhow!("unknown key `{}`, did you mean `{}`?", key, suggestion)
+                        }
+                        None => anyhow!(
+                            "unknown key `{}`, expected one of {}",
+                            key,
+                            FUNCTION_CONFIG_KEYS.join(", ")
+                        ),
+                    });
+                }
+            }
         }
 
-        validate_assets_dir(&config.assets, root)?;
+        let config = serde_json::from_value::<FunctionConfig>(value)?;
+
+        if let Some(jsx) = &config.jsx {
+            jsx.validate()
+                .map_err(|err| anyhow!("invalid `jsx`: {}", err))?;
+        }
 
         Ok(config)
     }
@@ -194,6 +333,10 @@ pub fn resolve_path(
                     index,
                     client,
                     assets,
+                    assets_fallback: None,
+                    jsx: None,
+                    env: Vec::new(),
+                    crons: Vec::new(),
                 },
             ))
         }
@@ -215,8 +358,9 @@ pub fn get_function_config_path(root: &Path) -> PathBuf {
     root.join(".lagon").join("config.json")
 }
 
-fn esbuild(file: &Path, root: &Path) -> Result<Vec<u8>> {
-    let result = Command::new(ESBUILD)
+fn esbuild_command(file: &Path, root: &Path, jsx: Option<&JsxConfig>) -> Command {
+    let mut command = Command::new(ESBUILD);
+    command
         .arg(root.join(file))
         .arg("--define:process.env.NODE_ENV=\"production\"")
         .arg("--bundle")
@@ -224,8 +368,65 @@ fn esbuild(file: &Path, root: &Path) -> Result<Vec<u8>> {
         .arg("--target=esnext")
         .arg("--platform=browser")
         .arg("--conditions=lagon")
-        .arg("--loader:.wasm=binary")
-        .output()?;
+        // `import wasm from './lib.wasm'` gives a `Uint8Array` inlined in the
+        // bundle, which can be passed to `WebAssembly.instantiate()`
+        .arg("--loader:.wasm=binary");
+
+    // ESBuild resolves `compilerOptions.paths` and `baseUrl` itself, but
+    // from the tsconfig.json closest to each imported file instead of the Function
+    let tsconfig = find_tsconfig(root);
+
+    if let Some(tsconfig) = &tsconfig {
+        command.arg(format!("--tsconfig={}", tsconfig.display()));
+    }
+
+    // The JSX options of the Function's config take precedence over the
+    // ones of tsconfig.json, which take precedence over the defaults
+    let tsconfig_jsx = tsconfig
+        .and_then(|tsconfig| TsConfig::load(&tsconfig).ok())
+        .map_or(false, |tsconfig| tsconfig.jsx.is_some());
+
+    match jsx {
+        Some(jsx) => {
+            command.args(jsx.esbuild_args());
+        }
+        None if !tsconfig_jsx => {
+            command.args(JsxConfig::default().esbuild_args());
+        }
+        None => {}
+    }
+
+    command
+}
+
+pub fn esbuild(file: &Path, root: &Path, jsx: Option<&JsxConfig>) -> Result<Vec<u8>> {
+    run_esbuild(esbuild_command(file, root, jsx))
+}
+
+// Also returns the bytes each input module contributes to
+// the bundle, read from the metafile written by ESBuild
+pub fn esbuild_with_analysis(
+    file: &Path,
+    root: &Path,
+    jsx: Option<&JsxConfig>,
+) -> Result<(Vec<u8>, BundleAnalysis)> {
+    let metafile = std::env::temp_dir().join(format!("lagon-metafile-{}.json", std::process::id()));
+
+    let mut command = esbuild_command(file, root, jsx);
+    command.arg(format!("--metafile={}", metafile.display()));
+
+    let output = run_esbuild(command)?;
+    let analysis = fs::read_to_string(&metafile)
+        .map_err(|err| anyhow!("Could not read ESBuild metafile: {}", err))
+        .and_then(|metafile| parse_metafile(&metafile));
+
+    fs::remove_file(&metafile).unwrap_or(());
+
+    Ok((output, analysis?))
+}
+
+fn run_esbuild(mut command: Command) -> Result<Vec<u8>> {
+    let result = command.output()?;
 
     // TODO: check status code
     if result.status.success() {
@@ -248,7 +449,86 @@ fn esbuild(file: &Path, root: &Path) -> Result<Vec<u8>> {
     ))
 }
 
+// ESBuild gathers the exports of the bundle in a single
+// `export { ... }` statement at its end, e.g `export { handler };`
+fn exports_handler(bundle: &[u8]) -> bool {
+    let bundle = String::from_utf8_lossy(bundle);
+
+    bundle
+        .rfind("export {")
+        .and_then(|start| {
+            let exports = &bundle[start + "export {".len()..];
+            exports.find('}').map(|end| exports[..end].to_string())
+        })
+        .map_or(false, |exports| {
+            exports
+                .split(',')
+                .any(|export| export.split_whitespace().last() == Some("handler"))
+        })
+}
+
+// Service worker style Functions register a fetch listener instead of exporting
+// a handler. ESBuild always prints strings with double quotes
+fn registers_fetch_listener(bundle: &[u8]) -> bool {
+    String::from_utf8_lossy(bundle).contains("addEventListener(\"fetch\"")
+}
+
+pub fn get_client_asset_name(client: &Path) -> String {
+    client.file_stem().unwrap().to_str().unwrap().to_string() + ".js"
+}
+
+pub fn read_assets(assets: &Path) -> Result<Assets> {
+    let mut final_assets = Assets::new();
+
+    let files = WalkDir::new(assets)
+        .into_iter()
+        .collect::<Vec<walkdir::Result<DirEntry>>>();
+
+    if files.len() >= MAX_ASSETS_PER_FUNCTION {
+        return Err(anyhow!(
+            "Too many assets in public directory, max is {}",
+            MAX_ASSETS_PER_FUNCTION
+        ));
+    }
+
+    for file in files {
+        let file = file?;
+        let path = file.path();
+
+        if path.is_file() {
+            if path.metadata()?.len() >= MAX_ASSET_SIZE_MB {
+                return Err(anyhow!(
+                    "File {:?} can't be larger than {} bytes",
+                    path,
+                    MAX_ASSET_SIZE_MB
+                ));
+            }
+
+            let diff = diff_paths(path, assets)
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            let file_content = fs::read(path)?;
+
+            final_assets.insert(diff, file_content);
+        }
+    }
+
+    Ok(final_assets)
+}
+
 pub fn bundle_function(function_config: &FunctionConfig, root: &Path) -> Result<(Vec<u8>, Assets)> {
+    bundle_function_with_analysis(function_config, root, false)
+        .map(|(index, assets, _)| (index, assets))
+}
+
+// Only the handler is analyzed, the client file being an asset
+pub fn bundle_function_with_analysis(
+    function_config: &FunctionConfig,
+    root: &Path,
+    analyze: bool,
+) -> Result<(Vec<u8>, Assets, Option<BundleAnalysis>)> {
     if let Err(error) = Command::new(ESBUILD).arg("--version").output() {
         return if error.kind() == ErrorKind::NotFound {
             Err(anyhow!(
@@ -262,15 +542,42 @@ pub fn bundle_function(function_config: &FunctionConfig, root: &Path) -> Result<
         };
     }
 
+    if let Some(tsconfig) = find_tsconfig(root) {
+        let tsconfig = TsConfig::load(&tsconfig)?;
+
+        if !tsconfig.aliases.is_empty() {
+            println!(
+                "{}",
+                debug(&format!(
+                    "Using {} path alias(es) from {}",
+                    tsconfig.aliases.len(),
+                    tsconfig.path.display()
+                ))
+            );
+        }
+    }
+
+    let jsx = function_config.jsx.as_ref();
     let end_progress = print_progress("Bundling Function handler...");
-    let index_output = esbuild(&function_config.index, root)?;
+    let (index_output, analysis) = match analyze {
+        true => esbuild_with_analysis(&function_config.index, root, jsx)
+            .map(|(index_output, analysis)| (index_output, Some(analysis)))?,
+        false => (esbuild(&function_config.index, root, jsx)?, None),
+    };
     end_progress();
 
+    if !exports_handler(&index_output) && !registers_fetch_listener(&index_output) {
+        return Err(anyhow!(
+            "{} doesn't export a `handler` function, e.g `export function handler(request) {{}}`, or register a fetch listener",
+            root.join(&function_config.index).display()
+        ));
+    }
+
     let mut final_assets = Assets::new();
 
     if let Some(client) = &function_config.client {
         let end_progress = print_progress("Bundling client file...");
-        let client_output = esbuild(client, root)?;
+        let client_output = esbuild(client, root, jsx)?;
         end_progress();
 
         let client_path = client.as_path().with_extension("js");
@@ -281,17 +588,7 @@ pub fn bundle_function(function_config: &FunctionConfig, root: &Path) -> Result<
             fs::write(client_path, &client_output)?;
         }
 
-        final_assets.insert(
-            client
-                .as_path()
-                .file_stem()
-                .unwrap()
-                .to_str()
-                .unwrap()
-                .to_string()
-                + ".js",
-            client_output,
-        );
+        final_assets.insert(get_client_asset_name(client), client_output);
     }
 
     if let Some(assets) = &function_config.assets {
@@ -302,47 +599,23 @@ pub fn bundle_function(function_config: &FunctionConfig, root: &Path) -> Result<
         );
         let end_progress = print_progress(&msg);
 
-        let files = WalkDir::new(&assets)
-            .into_iter()
-            .collect::<Vec<walkdir::Result<DirEntry>>>();
-
-        if files.len() >= MAX_ASSETS_PER_FUNCTION {
-            return Err(anyhow!(
-                "Too many assets in public directory, max is {}",
-                MAX_ASSETS_PER_FUNCTION
-            ));
-        }
-
-        for file in files {
-            let file = file?;
-            let path = file.path();
-
-            if path.is_file() {
-                if path.metadata()?.len() >= MAX_ASSET_SIZE_MB {
-                    return Err(anyhow!(
-                        "File {:?} can't be larger than {} bytes",
-                        path,
-                        MAX_ASSET_SIZE_MB
-                    ));
-                }
-
-                let diff = diff_paths(path, &assets)
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                    .to_string();
-                let file_content = fs::read(path)?;
-
-                final_assets.insert(diff, file_content);
-            }
-        }
+        final_assets.extend(read_assets(&assets)?);
 
         end_progress();
     } else {
         println!("{}", debug("No public directory found, skipping..."));
     }
 
-    Ok((index_output, final_assets))
+    if let Some(assets_fallback) = &function_config.assets_fallback {
+        if !final_assets.contains_key(assets_fallback) {
+            return Err(anyhow!(
+                "Fallback asset {} not found in public directory",
+                assets_fallback
+            ));
+        }
+    }
+
+    Ok((index_output, final_assets, analysis))
 }
 
 #[derive(Serialize, Debug)]
@@ -357,6 +630,10 @@ struct CreateDeploymentRequest {
     function_id: String,
     function_size: usize,
     assets: Vec<Asset>,
+    // Stored with the deployment, and checked before starting its isolates
+    required_env: Vec<String>,
+    // Served by the deployment for pages that don't match any asset
+    assets_fallback: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -404,6 +681,8 @@ pub async fn create_deployment(
                         size: value.len(),
                     })
                     .collect(),
+                required_env: function_config.env.clone(),
+                assets_fallback: function_config.assets_fallback.clone(),
             },
         )
         .await?;
@@ -477,3 +756,195 @@ async fn upload_asset(trpc_client: Arc<TrpcClient>, asset: Vec<u8>, url: String)
     trpc_client.client.request(request).await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use lagon_runtime_utils::assets::{find_asset, AssetResolution};
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn parse_unknown_keys() {
+        let config = FunctionConfig::parse(
+            r#"{"function_id":"","organization_id":"","index":"index.ts","assets":"public"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.assets, Some(PathBuf::from("public")));
+
+        assert_eq!(
+            FunctionConfig::parse(
+                r#"{"function_id":"","organization_id":"","index":"index.ts","asets":"public"}"#
+            )
+            .unwrap_err()
+            .to_string(),
+            "unknown key `asets`, did you mean `assets`?"
+        );
+        assert!(FunctionConfig::parse(
+            r#"{"function_id":"","organization_id":"","index":"index.ts","region":"eu"}"#
+        )
+        .unwrap_err()
+        .to_string()
+        .starts_with("unknown key `region`, expected one of function_id"));
+    }
+
+    #[test]
+    fn parse_crons_config() {
+        let config = FunctionConfig::parse(
+            r#"{"function_id":"","organization_id":"","index":"index.ts","crons":["*/5 * * * *","0 9 * * 1-5"]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.crons, vec!["*/5 * * * *", "0 9 * * 1-5"]);
+
+        let config =
+            FunctionConfig::parse(r#"{"function_id":"","organization_id":"","index":"index.ts"}"#)
+                .unwrap();
+        assert!(config.crons.is_empty());
+
+        assert!(FunctionConfig::parse(
+            r#"{"function_id":"","organization_id":"","index":"index.ts","crons":"* * * * *"}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_jsx_config() {
+        let parse = |jsx: &str| {
+            FunctionConfig::parse(&format!(
+                r#"{{"function_id":"","organization_id":"","index":"index.tsx","jsx":{jsx}}}"#
+            ))
+        };
+
+        let jsx = parse(r#"{"import_source":"preact"}"#).unwrap().jsx.unwrap();
+        assert_eq!(jsx.runtime, JsxRuntime::Automatic);
+        assert_eq!(
+            jsx.esbuild_args(),
+            vec!["--jsx=automatic", "--jsx-import-source=preact"]
+        );
+
+        let jsx = parse(r#"{"runtime":"classic","factory":"h","fragment":"Fragment"}"#)
+            .unwrap()
+            .jsx
+            .unwrap();
+        assert_eq!(
+            jsx.esbuild_args(),
+            vec![
+                "--jsx=transform",
+                "--jsx-factory=h",
+                "--jsx-fragment=Fragment"
+            ]
+        );
+
+        assert_eq!(
+            parse(r#"{"runtime":"classic","import_source":"preact"}"#)
+                .unwrap_err()
+                .to_string(),
+            "invalid `jsx`: `import_source` is only used by the automatic runtime"
+        );
+        assert!(parse(r#"{"factory":"h"}"#).is_err());
+        assert!(parse(r#"{"runtime":"preact"}"#).is_err());
+        assert!(parse(r#"{"importSource":"preact"}"#).is_err());
+    }
+
+    #[test]
+    fn load_invalid_index() {
+        let root = std::env::temp_dir().join("lagon-load-invalid-index");
+        fs::remove_dir_all(&root).unwrap_or(());
+
+        FunctionConfig {
+            function_id: String::new(),
+            organization_id: String::new(),
+            index: PathBuf::from("index.ts"),
+            client: None,
+            assets: None,
+            assets_fallback: None,
+            jsx: None,
+            env: Vec::new(),
+            crons: Vec::new(),
+        }
+        .write(&root)
+        .unwrap();
+
+        let err = FunctionConfig::load(&root, None, None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with(&format!(
+            "Invalid `index` in {}:",
+            get_function_config_path(&root).display()
+        )));
+
+        fs::write(root.join("index.ts"), "").unwrap();
+        assert!(FunctionConfig::load(&root, None, None).is_ok());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn exports_handler_statement() {
+        assert!(exports_handler(
+            b"function handler() {}\nexport {\n  handler\n};\n"
+        ));
+        assert!(exports_handler(
+            b"var x = 1;\nexport {\n  foo,\n  handler2 as handler\n};\n"
+        ));
+        assert!(!exports_handler(
+            b"function handler() {}\nexport {\n  handler as main\n};\n"
+        ));
+        assert!(!exports_handler(
+            b"var src_default = 1;\nexport {\n  src_default as default\n};\n"
+        ));
+        assert!(!exports_handler(b"function handler() {}\n"));
+    }
+
+    #[test]
+    fn registers_fetch_listener_call() {
+        assert!(registers_fetch_listener(
+            b"addEventListener(\"fetch\", (event) => {\n  event.respondWith(new Response());\n});\n"
+        ));
+        assert!(!registers_fetch_listener(
+            b"addEventListener(\"message\", () => {\n});\n"
+        ));
+    }
+
+    #[test]
+    fn read_assets_refresh() {
+        let public_dir = std::env::temp_dir().join("lagon-read-assets-refresh");
+        fs::remove_dir_all(&public_dir).unwrap_or(());
+        fs::create_dir_all(&public_dir).unwrap();
+        fs::write(public_dir.join("index.html"), "Hello").unwrap();
+
+        let assets = read_assets(&public_dir).unwrap();
+        let assets = assets.keys().cloned().collect::<HashSet<String>>();
+
+        assert_eq!(
+            find_asset("/", &assets, AssetResolution::default()),
+            Some(&"index.html".into())
+        );
+        assert_eq!(
+            find_asset("/about", &assets, AssetResolution::default()),
+            None
+        );
+
+        fs::write(public_dir.join("about.html"), "About").unwrap();
+
+        let assets = read_assets(&public_dir).unwrap();
+        let assets = assets.keys().cloned().collect::<HashSet<String>>();
+
+        assert_eq!(
+            find_asset("/about", &assets, AssetResolution::default()),
+            Some(&"about.html".into())
+        );
+
+        fs::remove_file(public_dir.join("about.html")).unwrap();
+
+        let assets = read_assets(&public_dir).unwrap();
+        let assets = assets.keys().cloned().collect::<HashSet<String>>();
+
+        assert_eq!(
+            find_asset("/about", &assets, AssetResolution::default()),
+            None
+        );
+
+        fs::remove_dir_all(&public_dir).unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+use super::{warn, LogFormat, LogLevel};
+
+const DEV_CONFIG_FILE: &str = "lagon.toml";
+
+// Same names as the options of `lagon dev`
+const DEV_CONFIG_KEYS: [&str; 45] = [
+    "client",
+    "public_dir",
+    "port",
+    "hostname",
+    "env",
+    "env_var",
+    "allow_code_generation",
+    "allow_fs",
+    "grace_period",
+    "verbose",
+    "no_clear",
+    "error_overlay",
+    "cert",
+    "key",
+    "self_signed",
+    "replay_buffer",
+    "timeout",
+    "startup_timeout",
+    "memory",
+    "cold_start",
+    "cold_start_every",
+    "mock",
+    "mock_strict",
+    "open",
+    "log_format",
+    "log_level",
+    "serve_index",
+    "proxy",
+    "proxy_paths",
+    "cors",
+    "cors_origin",
+    "heap_stats",
+    "function",
+    "max_body_size",
+    "cache_size",
+    "persist_kv",
+    "queue_retries",
+    "concurrency",
+    "inspect",
+    "log_file",
+    "log_file_max_size",
+    "snapshot",
+    "mask",
+    "no_mask",
+    "compress",
+];
+
+// The `[dev]` section of a `lagon.toml` file, using the same units as the
+// CLI options (e.g milliseconds for `timeout`, megabytes for `memory`)
+#[derive(Deserialize, Default, Debug, PartialEq)]
+pub struct DevConfig {
+    pub client: Option<PathBuf>,
+    pub public_dir: Option<PathBuf>,
+    pub port: Option<u16>,
+    pub hostname: Option<String>,
+    pub env: Option<Vec<PathBuf>>,
+    pub env_var: Option<Vec<String>>,
+    pub allow_code_generation: Option<bool>,
+    pub allow_fs: Option<PathBuf>,
+    pub grace_period: Option<u64>,
+    pub verbose: Option<bool>,
+    pub no_clear: Option<bool>,
+    pub error_overlay: Option<bool>,
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    pub self_signed: Option<bool>,
+    pub replay_buffer: Option<usize>,
+    pub timeout: Option<u64>,
+    pub startup_timeout: Option<u64>,
+    pub memory: Option<usize>,
+    pub cold_start: Option<bool>,
+    pub cold_start_every: Option<u64>,
+    pub mock: Option<PathBuf>,
+    pub mock_strict: Option<bool>,
+    pub open: Option<bool>,
+    pub log_format: Option<LogFormat>,
+    pub log_level: Option<LogLevel>,
+    pub serve_index: Option<bool>,
+    pub proxy: Option<String>,
+    pub proxy_paths: Option<Vec<String>>,
+    pub cors: Option<bool>,
+    pub cors_origin: Option<Vec<String>>,
+    pub heap_stats: Option<u64>,
+    pub function: Option<Vec<String>>,
+    pub max_body_size: Option<usize>,
+    pub cache_size: Option<usize>,
+    pub persist_kv: Option<bool>,
+    pub queue_retries: Option<usize>,
+    pub concurrency: Option<u64>,
+    pub inspect: Option<u16>,
+    pub log_file: Option<PathBuf>,
+    pub log_file_max_size: Option<u64>,
+    pub snapshot: Option<PathBuf>,
+    pub mask: Option<Vec<String>>,
+    pub no_mask: Option<bool>,
+    pub compress: Option<bool>,
+}
+
+fn resolve(dir: &Path, path: Option<PathBuf>) -> Option<PathBuf> {
+    path.map(|path| match path.is_absolute() {
+        true => path,
+        false => dir.join(path),
+    })
+}
+
+impl DevConfig {
+    // The config file is looked up next to the Function, i.e in the given
+    // directory or in the directory of the given file
+    pub fn load(path: Option<&Path>) -> Result<DevConfig> {
+        let dir = match path {
+            Some(path) if path.is_file() => path.parent().unwrap_or(Path::new(".")),
+            Some(path) => path,
+            None => Path::new("."),
+        };
+        let config_path = dir.join(DEV_CONFIG_FILE);
+
+        if !config_path.is_file() {
+            return Ok(DevConfig::default());
+        }
+
+        let content = fs::read_to_string(&config_path)?;
+
+        DevConfig::parse(&content, dir)
+            .map_err(|err| anyhow!("Could not parse {}: {}", config_path.display(), err))
+    }
+
+    fn parse(content: &str, dir: &Path) -> Result<DevConfig> {
+        let mut table = toml::from_str::<toml::Table>(content)?;
+
+        for key in table.keys().filter(|key| *key != "dev") {
+            println!(
+                "{}",
+                warn(&format!(
+                    "Unknown section `{key}` in {DEV_CONFIG_FILE}, only `dev` is supported"
+                ))
+            );
+        }
+
+        let dev = match table.remove("dev") {
+            Some(toml::Value::Table(dev)) => dev,
+            Some(_) => return Err(anyhow!("`dev` must be a table")),
+            None => return Ok(DevConfig::default()),
+        };
+
+        let unknown_keys = dev
+            .keys()
+            .filter(|key| !DEV_CONFIG_KEYS.contains(&key.as_str()))
+            .map(|key| format!("`{key}`"))
+            .collect::<Vec<_>>();
+
+        if !unknown_keys.is_empty() {
+            println!(
+                "{}",
+                warn(&format!(
+                    "Unknown keys {} in {DEV_CONFIG_FILE}, valid keys are: {}",
+                    unknown_keys.join(", "),
+                    DEV_CONFIG_KEYS.join(", ")
+                ))
+            );
+        }
+
+        let mut config = toml::Value::Table(dev).try_into::<DevConfig>()?;
+        config.resolve_paths(dir);
+
+        Ok(config)
+    }
+
+    // Paths in the config file are relative to it, not to the current directory
+    fn resolve_paths(&mut self, dir: &Path) {
+        self.client = resolve(dir, self.client.take());
+        self.public_dir = resolve(dir, self.public_dir.take());
+        self.allow_fs = resolve(dir, self.allow_fs.take());
+        self.cert = resolve(dir, self.cert.take());
+        self.key = resolve(dir, self.key.take());
+        self.mock = resolve(dir, self.mock.take());
+        self.log_file = resolve(dir, self.log_file.take());
+        self.snapshot = resolve(dir, self.snapshot.take());
+        self.env = self.env.take().map(|env| {
+            env.into_iter()
+                .filter_map(|path| resolve(dir, Some(path)))
+                .collect()
+        });
+        self.function = self.function.take().map(|functions| {
+            functions
+                .into_iter()
+                .map(|function| match function.split_once('=') {
+                    Some((route, path)) => match resolve(dir, Some(PathBuf::from(path))) {
+                        Some(path) => format!("{}={}", route, path.display()),
+                        None => function,
+                    },
+                    None => function,
+                })
+                .collect()
+        });
+    }
+}
+
+// Options given to the CLI take precedence over the config file,
+// which takes precedence over the default values of the options
+pub fn merge_option<T>(cli: T, from_cli: bool, file: Option<T>) -> T {
+    match (from_cli, file) {
+        (false, Some(file)) => file,
+        _ => cli,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{parser::ValueSource, Arg, ArgAction, Command};
+
+    use super::*;
+
+    #[test]
+    fn merge_precedence() {
+        let command = Command::new("dev")
+            .arg(Arg::new("port").long("port").default_value("1234"))
+            .arg(
+                Arg::new("verbose")
+                    .long("verbose")
+                    .action(ArgAction::SetTrue),
+            );
+
+        let merge = |args: &[&str], file: &DevConfig| {
+            let matches = command.clone().get_matches_from(args);
+            let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
+
+            (
+                merge_option(
+                    matches.get_one::<String>("port").unwrap().parse().unwrap(),
+                    from_cli("port"),
+                    file.port,
+                ),
+                merge_option(
+                    matches.get_flag("verbose"),
+                    from_cli("verbose"),
+                    file.verbose,
+                ),
+            )
+        };
+
+        let file = DevConfig {
+            port: Some(5678),
+            verbose: Some(true),
+            ..Default::default()
+        };
+
+        assert_eq!(merge(&["dev"], &DevConfig::default()), (1234, false));
+        assert_eq!(merge(&["dev"], &file), (5678, true));
+        assert_eq!(
+            merge(&["dev", "--port", "4321", "--verbose"], &file),
+            (4321, true)
+        );
+        assert_eq!(
+            merge(
+                &["dev", "--port", "4321"],
+                &DevConfig {
+                    verbose: Some(false),
+                    ..Default::default()
+                }
+            ),
+            (4321, false)
+        );
+    }
+
+    #[test]
+    fn parse_config() {
+        let config = DevConfig::parse(
+            r#"
+[dev]
+port = 4000
+allow_code_generation = true
+log_format = "json"
+timeout = 5000
+"#,
+            Path::new("."),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config,
+            DevConfig {
+                port: Some(4000),
+                allow_code_generation: Some(true),
+                log_format: Some(LogFormat::Json),
+                timeout: Some(5000),
+                ..Default::default()
+            }
+        );
+        assert!(DevConfig::parse("[dev]\nport = \"4000\"", Path::new(".")).is_err());
+        assert_eq!(
+            DevConfig::parse("[dev]\nprot = 4000", Path::new(".")).unwrap(),
+            DevConfig::default()
+        );
+    }
+
+    #[test]
+    fn parse_config_relative_paths() {
+        let dir = Path::new("functions").join("hello");
+        let shared_env = std::env::temp_dir().join(".env.shared");
+        let config = DevConfig::parse(
+            &format!(
+                r#"
+[dev]
+public_dir = "public"
+env = [".env", '{}']
+function = ["/api=../api"]
+"#,
+                shared_env.display()
+            ),
+            &dir,
+        )
+        .unwrap();
+
+        assert_eq!(config.public_dir, Some(dir.join("public")));
+        assert_eq!(config.env, Some(vec![dir.join(".env"), shared_env]));
+        assert_eq!(
+            config.function,
+            Some(vec![format!("/api={}", dir.join("../api").display())])
+        );
+    }
+
+    #[test]
+    fn load_config_next_to_function() {
+        let dir = std::env::temp_dir().join("lagon-dev-config");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("index.ts"), "").unwrap();
+        fs::write(dir.join(DEV_CONFIG_FILE), "[dev]\nmock = \"mocks.json\"").unwrap();
+
+        assert_eq!(
+            DevConfig::load(Some(&dir.join("index.ts"))).unwrap().mock,
+            Some(dir.join("mocks.json"))
+        );
+        assert_eq!(
+            DevConfig::load(Some(&dir)).unwrap().mock,
+            Some(dir.join("mocks.json"))
+        );
+    }
+}
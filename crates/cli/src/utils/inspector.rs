@@ -0,0 +1,441 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::header::{
+    HeaderValue, CONNECTION, CONTENT_TYPE, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE,
+};
+use hyper::server::conn::AddrIncoming;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::upgrade::Upgraded;
+use hyper::{Body, Request, Response, Server, StatusCode};
+use lagon_runtime_isolate::InspectorSession;
+use log::debug;
+use serde_json::{json, Value};
+use sha1::{Digest, Sha1};
+use std::convert::Infallible;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use super::{error, get_version, info};
+
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const WEBSOCKET_PATH: &str = "/lagon";
+const DEVTOOLS_FRONTEND_URL: &str = "devtools://devtools/bundled/js_app.html";
+// Protocol messages can be large, e.g the source of a bundled Function
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024; // 64MB
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+// Resumes the isolate if it was paused, and removes all breakpoints
+const DETACH_MESSAGE: &str = r#"{"id":0,"method":"Debugger.disable"}"#;
+
+// The inspector's side of the session of the current isolate
+struct Peer {
+    to_isolate: flume::Sender<String>,
+    from_isolate: flume::Receiver<String>,
+}
+
+struct InspectorState {
+    addr: SocketAddr,
+    peer: Mutex<Option<Peer>>,
+    // Only a single client can be connected at a time
+    is_connected: AtomicBool,
+}
+
+#[derive(Clone)]
+pub struct InspectorServer {
+    state: Arc<InspectorState>,
+}
+
+impl InspectorServer {
+    // Only listen on localhost, since clients can run arbitrary code
+    pub fn start(port: u16) -> Result<Self> {
+        let addr = SocketAddr::from(([127, 0, 0, 1], port));
+        let incoming = AddrIncoming::bind(&addr)
+            .map_err(|err| anyhow!("Could not start the inspector on {}: {}", addr, err))?;
+
+        let state = Arc::new(InspectorState {
+            addr: incoming.local_addr(),
+            peer: Mutex::new(None),
+            is_connected: AtomicBool::new(false),
+        });
+        let server_state = Arc::clone(&state);
+
+        let server = Server::builder(incoming).serve(make_service_fn(move |_| {
+            let state = Arc::clone(&server_state);
+
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    handle_request(req, Arc::clone(&state))
+                }))
+            }
+        }));
+
+        tokio::spawn(async move {
+            if let Err(err) = server.await {
+                println!("{}", error(&format!("Inspector stopped: {err}")));
+            }
+        });
+
+        Ok(Self { state })
+    }
+
+    pub fn websocket_url(&self) -> String {
+        format!("ws://{}{}", self.state.addr, WEBSOCKET_PATH)
+    }
+
+    pub fn devtools_url(&self) -> String {
+        devtools_url(self.state.addr)
+    }
+
+    // Each isolate has its own session. Creating a new one closes the
+    // connection to the client of the previous isolate, which has to reconnect
+    pub fn session(&self) -> InspectorSession {
+        let (to_isolate, incoming) = flume::unbounded();
+        let (outgoing, from_isolate) = flume::unbounded();
+
+        *self.state.peer.lock().unwrap() = Some(Peer {
+            to_isolate,
+            from_isolate,
+        });
+
+        InspectorSession { incoming, outgoing }
+    }
+}
+
+fn devtools_url(addr: SocketAddr) -> String {
+    format!("{DEVTOOLS_FRONTEND_URL}?experiments=true&v8only=true&ws={addr}{WEBSOCKET_PATH}")
+}
+
+fn accept_key(key: &[u8]) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key);
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+
+    STANDARD.encode(hasher.finalize())
+}
+
+fn json_response(value: Value) -> Response<Body> {
+    let mut response = Response::new(Body::from(value.to_string()));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+
+    response
+}
+
+fn status_response(status: StatusCode, message: &str) -> Response<Body> {
+    let mut response = Response::new(Body::from(message.to_string()));
+    *response.status_mut() = status;
+
+    response
+}
+
+// Endpoints used by Chrome (chrome://inspect) to discover debugging targets
+async fn handle_request(
+    req: Request<Body>,
+    state: Arc<InspectorState>,
+) -> Result<Response<Body>, Infallible> {
+    let response = match req.uri().path() {
+        "/json" | "/json/list" => json_response(json!([{
+            "id": "lagon",
+            "type": "node",
+            "title": "Lagon Function",
+            "description": "Lagon Function",
+            "url": "file://",
+            "devtoolsFrontendUrl": devtools_url(state.addr),
+            "webSocketDebuggerUrl": format!("ws://{}{}", state.addr, WEBSOCKET_PATH),
+        }])),
+        "/json/version" => json_response(json!({
+            "Browser": format!("Lagon/{}", get_version().unwrap_or_default()),
+            "Protocol-Version": "1.3",
+        })),
+        WEBSOCKET_PATH => upgrade(req, state),
+        _ => status_response(StatusCode::NOT_FOUND, "Not Found"),
+    };
+
+    Ok(response)
+}
+
+fn upgrade(req: Request<Body>, state: Arc<InspectorState>) -> Response<Body> {
+    let accept = match req.headers().get(SEC_WEBSOCKET_KEY) {
+        Some(key) => accept_key(key.as_bytes()),
+        None => return status_response(StatusCode::BAD_REQUEST, "Expected a WebSocket upgrade"),
+    };
+
+    let (to_isolate, from_isolate) = match state.peer.lock().unwrap().as_ref() {
+        Some(peer) => (peer.to_isolate.clone(), peer.from_isolate.clone()),
+        None => {
+            return status_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "The Function isn't started yet",
+            )
+        }
+    };
+
+    if state.is_connected.swap(true, Ordering::SeqCst) {
+        return status_response(StatusCode::CONFLICT, "A debugger is already connected");
+    }
+
+    tokio::spawn(async move {
+        match hyper::upgrade::on(req).await {
+            Ok(upgraded) => {
+                println!("{}", info("Debugger attached"));
+
+                handle_connection(upgraded, to_isolate, from_isolate).await;
+
+                println!("{}", info("Debugger detached"));
+            }
+            Err(err) => debug!("WebSocket upgrade failed: {}", err),
+        }
+
+        state.is_connected.store(false, Ordering::SeqCst);
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(CONNECTION, "Upgrade")
+        .header(UPGRADE, "websocket")
+        .header(SEC_WEBSOCKET_ACCEPT, accept)
+        .body(Body::empty())
+        .unwrap()
+}
+
+enum Message {
+    Text(String),
+    Ping(Vec<u8>),
+}
+
+async fn handle_connection(
+    upgraded: Upgraded,
+    to_isolate: flume::Sender<String>,
+    from_isolate: flume::Receiver<String>,
+) {
+    let (reader, mut writer) = tokio::io::split(upgraded);
+    let (messages_tx, messages_rx) = flume::unbounded();
+
+    // Frames are read on their own task, since reading isn't cancel safe
+    let reader = tokio::spawn(async move {
+        if let Err(err) = read_messages(reader, messages_tx).await {
+            debug!("Failed to read WebSocket frame: {}", err);
+        }
+    });
+
+    // Drop what was sent to a previous client, e.g responses to its requests
+    while from_isolate.try_recv().is_ok() {}
+
+    loop {
+        let result = tokio::select! {
+            message = messages_rx.recv_async() => match message {
+                Ok(Message::Text(message)) => {
+                    to_isolate.send_async(message).await.unwrap_or(());
+                    Ok(())
+                }
+                Ok(Message::Ping(payload)) => write_frame(&mut writer, OPCODE_PONG, &payload).await,
+                // The client closed the connection
+                Err(_) => break,
+            },
+            message = from_isolate.recv_async() => match message {
+                Ok(message) => write_frame(&mut writer, OPCODE_TEXT, message.as_bytes()).await,
+                // The isolate has been dropped, e.g after a reload
+                Err(_) => break,
+            },
+        };
+
+        if let Err(err) = result {
+            debug!("Failed to write WebSocket frame: {}", err);
+            break;
+        }
+    }
+
+    write_frame(&mut writer, OPCODE_CLOSE, &[])
+        .await
+        .unwrap_or(());
+    reader.abort();
+
+    // Don't leave the isolate paused without any client to resume it
+    to_isolate.send(DETACH_MESSAGE.into()).unwrap_or(());
+}
+
+async fn read_messages<R: AsyncRead + Unpin>(
+    mut reader: R,
+    messages: flume::Sender<Message>,
+) -> io::Result<()> {
+    let mut fragments = Vec::new();
+
+    loop {
+        let (fin, opcode, payload) = read_frame(&mut reader).await?;
+
+        let message = match opcode {
+            OPCODE_TEXT | OPCODE_CONTINUATION => {
+                fragments.extend_from_slice(&payload);
+
+                if fragments.len() > MAX_MESSAGE_SIZE {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "WebSocket message too large",
+                    ));
+                }
+
+                if !fin {
+                    continue;
+                }
+
+                let text = String::from_utf8(std::mem::take(&mut fragments))
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+                Message::Text(text)
+            }
+            OPCODE_PING => Message::Ping(payload),
+            OPCODE_CLOSE => return Ok(()),
+            // Binary messages and pongs aren't used by the protocol
+            _ => continue,
+        };
+
+        if messages.send_async(message).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+// Returns whether it's the final frame of a message, its opcode and its payload
+async fn read_frame<R: AsyncRead + Unpin>(reader: &mut R) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header).await?;
+
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0F;
+    let is_masked = header[1] & 0x80 != 0;
+
+    let length = match header[1] & 0x7F {
+        126 => reader.read_u16().await? as u64,
+        127 => reader.read_u64().await?,
+        length => length as u64,
+    };
+
+    if length > MAX_MESSAGE_SIZE as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "WebSocket frame too large",
+        ));
+    }
+
+    let mut mask = [0; 4];
+
+    if is_masked {
+        reader.read_exact(&mut mask).await?;
+    }
+
+    let mut payload = vec![0; length as usize];
+    reader.read_exact(&mut payload).await?;
+
+    // Frames sent by clients are always masked
+    if is_masked {
+        for (index, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[index % 4];
+        }
+    }
+
+    Ok((fin, opcode, payload))
+}
+
+// Frames sent by servers are never masked nor fragmented
+async fn write_frame<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    opcode: u8,
+    payload: &[u8],
+) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode);
+
+    match payload.len() {
+        length if length < 126 => frame.push(length as u8),
+        length if length <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+
+    frame.extend_from_slice(payload);
+
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn websocket_accept_key() {
+        // Example from RFC 6455
+        assert_eq!(
+            accept_key(b"dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[tokio::test]
+    async fn read_masked_frame() {
+        let mask = [0x37, 0xFA, 0x21, 0x3D];
+        let mut frame = vec![0x81, 0x80 | 5];
+        frame.extend_from_slice(&mask);
+        frame.extend(
+            b"Hello"
+                .iter()
+                .enumerate()
+                .map(|(index, byte)| byte ^ mask[index % 4]),
+        );
+
+        let (fin, opcode, payload) = read_frame(&mut frame.as_slice()).await.unwrap();
+
+        assert!(fin);
+        assert_eq!(opcode, OPCODE_TEXT);
+        assert_eq!(payload, b"Hello");
+    }
+
+    #[tokio::test]
+    async fn write_and_read_frames() {
+        for length in [0, 125, 126, u16::MAX as usize + 1] {
+            let payload = vec![b'a'; length];
+            let mut frame = Vec::new();
+            write_frame(&mut frame, OPCODE_TEXT, &payload)
+                .await
+                .unwrap();
+
+            let (fin, opcode, read_payload) = read_frame(&mut frame.as_slice()).await.unwrap();
+
+            assert!(fin);
+            assert_eq!(opcode, OPCODE_TEXT);
+            assert_eq!(read_payload, payload);
+        }
+    }
+
+    #[tokio::test]
+    async fn read_fragmented_message() {
+        let mut frames = Vec::new();
+        frames.extend_from_slice(&[OPCODE_TEXT, 3]);
+        frames.extend_from_slice(b"Hel");
+        frames.extend_from_slice(&[0x80 | OPCODE_CONTINUATION, 2]);
+        frames.extend_from_slice(b"lo");
+        frames.extend_from_slice(&[0x80 | OPCODE_CLOSE, 0]);
+
+        let (tx, rx) = flume::unbounded();
+        read_messages(frames.as_slice(), tx).await.unwrap();
+
+        match rx.try_recv() {
+            Ok(Message::Text(message)) => assert_eq!(message, "Hello"),
+            _ => panic!("Expected a text message"),
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use colored::Colorize;
+use log::Level;
+use serde_json::{Map, Value};
+
+use super::{json_line, mask};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+static LOG_FILE: Mutex<Option<flume::Sender<String>>> = Mutex::new(None);
+
+// Lines are appended as JSON by a separate thread, so logging never waits for
+// the disk. They are buffered and flushed every second, and when it's dropped.
+pub struct LogFile {
+    thread: Option<JoinHandle<()>>,
+}
+
+impl LogFile {
+    pub fn open(path: PathBuf, max_size: u64) -> Result<Self> {
+        let (file, size) = open_file(&path)
+            .map_err(|err| anyhow!("Could not open log file {}: {}", path.display(), err))?;
+        let (tx, rx) = flume::unbounded();
+
+        match LOG_FILE.lock() {
+            Ok(mut log_file) if log_file.is_none() => *log_file = Some(tx),
+            _ => return Err(anyhow!("A log file is already open")),
+        }
+
+        let thread = std::thread::spawn(move || write_lines(path, file, size, max_size, rx));
+
+        Ok(LogFile {
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for LogFile {
+    fn drop(&mut self) {
+        // Dropping the sender stops the thread once all the lines are written
+        if let Ok(mut log_file) = LOG_FILE.lock() {
+            log_file.take();
+        }
+
+        if let Some(thread) = self.thread.take() {
+            thread.join().unwrap_or(());
+        }
+    }
+}
+
+fn open_file(path: &Path) -> io::Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let size = file.metadata()?.len();
+
+    Ok((BufWriter::new(file), size))
+}
+
+// Only the previous file is kept, so at most twice the max size is used
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated_path = OsString::from(path.as_os_str());
+    rotated_path.push(".1");
+
+    PathBuf::from(rotated_path)
+}
+
+fn write_lines(
+    path: PathBuf,
+    mut file: BufWriter<File>,
+    mut size: u64,
+    max_size: u64,
+    rx: flume::Receiver<String>,
+) {
+    let mut last_flush = Instant::now();
+
+    loop {
+        match rx.recv_timeout(FLUSH_INTERVAL) {
+            Ok(line) => {
+                let line_size = line.len() as u64 + 1;
+
+                if size > 0 && size + line_size > max_size {
+                    file.flush().unwrap_or(());
+
+                    let rotated =
+                        fs::rename(&path, rotated_path(&path)).and_then(|()| open_file(&path));
+
+                    match rotated {
+                        Ok((new_file, new_size)) => {
+                            file = new_file;
+                            size = new_size;
+                        }
+                        Err(err) => {
+                            eprintln!("Could not rotate log file {}: {}", path.display(), err);
+                            break;
+                        }
+                    }
+                }
+
+                if writeln!(file, "{line}").is_ok() {
+                    size += line_size;
+                }
+
+                // The timeout is never reached while lines keep coming
+                if last_flush.elapsed() >= FLUSH_INTERVAL {
+                    file.flush().unwrap_or(());
+                    last_flush = Instant::now();
+                }
+            }
+            Err(flume::RecvTimeoutError::Timeout) => {
+                file.flush().unwrap_or(());
+                last_flush = Instant::now();
+            }
+            Err(flume::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+
+    file.flush().unwrap_or(());
+}
+
+// Does nothing if no log file has been opened
+pub fn write_log_file(level: Level, message: &str, fields: &Map<String, Value>) {
+    if let Ok(log_file) = LOG_FILE.lock() {
+        if let Some(tx) = log_file.as_ref() {
+            let line = json_line(level, message, fields.clone()).to_string();
+
+            tx.send(mask(&line).into_owned()).unwrap_or(());
+        }
+    }
+}
+
+// Pretty-print a line of a log file, or return it as is if it's not JSON
+pub fn format_log_line(line: &str) -> String {
+    let line = match serde_json::from_str::<Map<String, Value>>(line) {
+        Ok(line) => line,
+        Err(_) => return line.to_string(),
+    };
+    let field = |key: &str| line.get(key).and_then(|value| value.as_str()).unwrap_or("");
+
+    let time = DateTime::parse_from_rfc3339(field("timestamp"))
+        .map(|timestamp| timestamp.format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+    let level = match field("level") {
+        "error" => "ERROR".red(),
+        "warn" => "WARN".yellow(),
+        "debug" => "DEBUG".bright_black(),
+        _ => "INFO".blue(),
+    };
+    let request = match field("request_id") {
+        "" => String::new(),
+        request_id => format!(" {}", format!("[{request_id}]").bright_black()),
+    };
+    let duration = match line.get("duration_ms").and_then(|value| value.as_u64()) {
+        Some(duration) => format!(" {}", format!("{duration}ms").bright_black()),
+        None => String::new(),
+    };
+
+    format!(
+        "{} {}{} {}{}",
+        time.bright_black(),
+        level,
+        request,
+        field("message"),
+        duration
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_log_lines() {
+        colored::control::set_override(false);
+
+        assert_eq!(
+            format_log_line(
+                r#"{"timestamp":"2023-05-01T12:30:45+02:00","level":"info","message":"GET / 200","request_id":"abc123","duration_ms":12}"#
+            ),
+            "12:30:45 INFO [abc123] GET / 200 12ms"
+        );
+        assert_eq!(
+            format_log_line(
+                r#"{"timestamp":"2023-05-01T12:30:45+02:00","level":"warn","message":"Hello"}"#
+            ),
+            "12:30:45 WARN Hello"
+        );
+        assert_eq!(format_log_line("not json"), "not json");
+    }
+
+    #[test]
+    fn rotated_paths() {
+        assert_eq!(
+            rotated_path(Path::new("logs/dev.log")),
+            PathBuf::from("logs/dev.log.1")
+        );
+    }
+}
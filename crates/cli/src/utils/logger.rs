@@ -0,0 +1,155 @@
+use anyhow::Result;
+use chrono::offset::Local;
+use clap::ValueEnum;
+use colored::Colorize;
+use lagon_runtime_isolate::CONSOLE_SOURCE;
+use log::{set_boxed_logger, set_max_level, Level, Log, Metadata, Record};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::{mask, write_log_file};
+
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    // Newline-delimited JSON objects, without colors
+    Json,
+}
+
+// Minimum level of the logs made by the Function using `console.*`
+#[derive(ValueEnum, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl From<LogLevel> for Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Debug => Level::Debug,
+            LogLevel::Info => Level::Info,
+            LogLevel::Warn => Level::Warn,
+            LogLevel::Error => Level::Error,
+        }
+    }
+}
+
+struct SimpleLogger {
+    level: Level,
+    // Only applies to the Function's logs, the CLI's own logs use `level`
+    console_level: Level,
+    format: LogFormat,
+}
+
+impl SimpleLogger {
+    fn is_console(record: &Record) -> bool {
+        log::kv::Source::get(record.key_values(), log::kv::Key::from("source"))
+            .map_or(false, |source| {
+                source.to_borrowed_str() == Some(CONSOLE_SOURCE)
+            })
+    }
+}
+
+impl Log for SimpleLogger {
+    // The source of a log isn't known here, so this only
+    // filters out the logs that neither level allows
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level.max(self.console_level)
+    }
+
+    fn log(&self, record: &Record) {
+        let level = match SimpleLogger::is_console(record) {
+            true => self.console_level,
+            false => self.level,
+        };
+
+        if record.level() <= level {
+            // Console logs made while handling a request are
+            // prefixed with the id of that request
+            let request = log::kv::Source::get(record.key_values(), log::kv::Key::from("request"));
+            let request = request
+                .as_ref()
+                .and_then(|request| request.to_borrowed_str());
+
+            let mut fields = Map::new();
+
+            if let Some(request) = request {
+                fields.insert("request_id".into(), request.into());
+            }
+
+            write_log_file(record.level(), &record.args().to_string(), &fields);
+
+            if self.format == LogFormat::Json {
+                print_json(record.level(), &record.args().to_string(), fields);
+                return;
+            }
+
+            let level = match record.level() {
+                Level::Error => "ERROR".red(),
+                Level::Warn => "WARN".yellow(),
+                Level::Debug => "DEBUG".bright_black(),
+                _ => "INFO".blue(),
+            };
+
+            let line = match request {
+                Some(request) => format!(
+                    "{} {} {}",
+                    level,
+                    format!("[{request}]").bright_black(),
+                    record.args()
+                ),
+                None => format!("{} {}", level, record.args()),
+            };
+
+            println!("{}", mask(&line));
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+// The Function's logs use the same level as the CLI's, unless another one is given
+pub fn init_logger(
+    verbose: bool,
+    format: LogFormat,
+    console_level: Option<LogLevel>,
+) -> Result<()> {
+    let level = if verbose { Level::Debug } else { Level::Info };
+    let console_level = console_level.map_or(level, Level::from);
+
+    if format == LogFormat::Json {
+        colored::control::set_override(false);
+    }
+
+    set_boxed_logger(Box::new(SimpleLogger {
+        level,
+        console_level,
+        format,
+    }))
+    .map(|()| set_max_level(level.max(console_level).to_level_filter()))?;
+    Ok(())
+}
+
+// A single JSON line with the common fields, and any additional
+// fields (e.g the method and path of a request)
+pub fn json_line(level: Level, message: &str, fields: Map<String, Value>) -> Value {
+    let mut line = json!({
+        "timestamp": Local::now().to_rfc3339(),
+        "level": level.as_str().to_lowercase(),
+        "message": message,
+    });
+
+    if let Value::Object(ref mut line) = line {
+        line.extend(fields);
+    }
+
+    line
+}
+
+pub fn print_json(level: Level, message: &str, fields: Map<String, Value>) {
+    println!("{}", mask(&json_line(level, message, fields).to_string()));
+}
@@ -0,0 +1,138 @@
+use std::{borrow::Cow, collections::HashMap, sync::RwLock};
+
+pub const DEFAULT_MASK_PATTERNS: [&str; 4] = ["*KEY*", "*SECRET*", "*TOKEN*", "*PASSWORD*"];
+
+const MASK: &str = "****";
+
+// Values of the environment variables to hide from the logs, set by `lagon dev`
+static MASKED_VALUES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+// Case-insensitive, `*` matching any number of characters
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_uppercase();
+    let name = name.to_uppercase();
+    let parts = pattern.split('*').collect::<Vec<_>>();
+
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+
+    if !name.starts_with(first) || !name[first.len()..].ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &name[first.len()..name.len() - last.len()];
+
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
+
+fn masked_values(
+    environment_variables: &HashMap<String, String>,
+    patterns: &[String],
+) -> Vec<String> {
+    let mut values = environment_variables
+        .iter()
+        .filter(|(name, value)| {
+            !value.is_empty()
+                && patterns
+                    .iter()
+                    .any(|pattern| matches_pattern(pattern, name))
+        })
+        .flat_map(|(_, value)| {
+            // Also mask the value when it's escaped in a JSON string
+            let escaped = serde_json::to_string(value).unwrap_or_default();
+            let escaped = escaped[1..escaped.len() - 1].to_string();
+
+            [value.clone(), escaped]
+        })
+        .collect::<Vec<_>>();
+
+    // Longer values first, in case a value contains another one
+    values.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    values.dedup();
+    values
+}
+
+pub fn set_masked_values(environment_variables: &HashMap<String, String>, patterns: &[String]) {
+    if let Ok(mut values) = MASKED_VALUES.write() {
+        *values = masked_values(environment_variables, patterns);
+    }
+}
+
+fn mask_values<'a>(line: &'a str, values: &[String]) -> Cow<'a, str> {
+    let mut line = Cow::Borrowed(line);
+
+    for value in values {
+        if line.contains(value.as_str()) {
+            line = Cow::Owned(line.replace(value.as_str(), MASK));
+        }
+    }
+
+    line
+}
+
+// Applied to the final formatted lines, so values embedded in
+// e.g a JSON object logged by the Function are masked too
+pub fn mask(line: &str) -> Cow<str> {
+    match MASKED_VALUES.read() {
+        Ok(values) => mask_values(line, &values),
+        _ => Cow::Borrowed(line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns() -> Vec<String> {
+        DEFAULT_MASK_PATTERNS.map(String::from).to_vec()
+    }
+
+    #[test]
+    fn match_patterns() {
+        assert!(matches_pattern("*KEY*", "API_KEY"));
+        assert!(matches_pattern("*KEY*", "key"));
+        assert!(matches_pattern("*KEY*", "STRIPE_KEY_LIVE"));
+        assert!(matches_pattern("*_URL", "DATABASE_URL"));
+        assert!(matches_pattern("DB_*_PASS", "DB_MAIN_PASS"));
+        assert!(matches_pattern("NAME", "name"));
+        assert!(!matches_pattern("*_URL", "URL_PREFIX"));
+        assert!(!matches_pattern("DB_*_PASS", "DB_PASS"));
+        assert!(!matches_pattern("NAME", "USERNAME"));
+        assert!(!matches_pattern("*SECRET*", "PUBLIC_URL"));
+    }
+
+    #[test]
+    fn mask_matching_values() {
+        let environment_variables = HashMap::from([
+            ("API_KEY".into(), "sk_live_123".into()),
+            ("GITHUB_TOKEN".into(), "ghp_\"quoted\"".into()),
+            ("DB_PASSWORD".into(), "".into()),
+            ("PUBLIC_URL".into(), "https://lagon.app".into()),
+        ]);
+        let values = masked_values(&environment_variables, &patterns());
+
+        assert_eq!(
+            mask_values("Calling https://lagon.app with sk_live_123", &values),
+            "Calling https://lagon.app with ****"
+        );
+        assert_eq!(
+            mask_values(r#"{"token":"ghp_\"quoted\"","key":"sk_live_123"}"#, &values),
+            r#"{"token":"****","key":"****"}"#
+        );
+        assert!(matches!(
+            mask_values("Nothing to hide", &values),
+            Cow::Borrowed(_)
+        ));
+        assert!(masked_values(&environment_variables, &[]).is_empty());
+    }
+}
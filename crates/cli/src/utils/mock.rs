@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Result};
+use hyper::body::Bytes;
+use lagon_runtime_http::{Request, Response};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn default_status() -> u16 {
+    200
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MockConfig {
+    url: String,
+    method: Option<String>,
+    #[serde(default = "default_status")]
+    status: u16,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    body: Option<String>,
+    body_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct MocksConfig {
+    #[serde(default)]
+    mocks: Vec<MockConfig>,
+}
+
+struct Mock {
+    // Either an exact URL, or a glob where `*` matches any characters
+    url: String,
+    method: Option<String>,
+    response: Response,
+}
+
+pub struct Mocks {
+    mocks: Vec<Mock>,
+    // Whether unmatched requests should fail instead of hitting the network
+    strict: bool,
+}
+
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    match pattern.split_once('*') {
+        Some((prefix, rest)) => match value.strip_prefix(prefix) {
+            Some(value) => (0..=value.len())
+                .filter(|index| value.is_char_boundary(*index))
+                .any(|index| matches_pattern(rest, &value[index..])),
+            None => false,
+        },
+        None => pattern == value,
+    }
+}
+
+impl Mocks {
+    pub fn find_response(&self, request: &Request) -> Option<Result<Response>> {
+        let method: &str = request.method.into();
+
+        let mock = self.mocks.iter().find(|mock| {
+            mock.method
+                .as_ref()
+                .map_or(true, |mock_method| mock_method.eq_ignore_ascii_case(method))
+                && matches_pattern(&mock.url, &request.url)
+        });
+
+        match mock {
+            Some(mock) => Some(Ok(mock.response.clone())),
+            None if self.strict => {
+                Some(Err(anyhow!("No mock found for {} {}", method, request.url)))
+            }
+            None => None,
+        }
+    }
+}
+
+pub fn load_mocks(path: &Path, strict: bool) -> Result<Mocks> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| anyhow!("Could not read mock file {:?}: {}", path, err))?;
+
+    let config: MocksConfig = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => toml::from_str(&content)
+            .map_err(|err| anyhow!("Could not parse mock file {:?}: {}", path, err))?,
+        _ => serde_json::from_str(&content)
+            .map_err(|err| anyhow!("Could not parse mock file {:?}: {}", path, err))?,
+    };
+
+    // Body files are relative to the mock file
+    let root = path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mocks = config
+        .mocks
+        .into_iter()
+        .map(|mock| {
+            let body = match (mock.body, mock.body_file) {
+                (Some(_), Some(_)) => {
+                    return Err(anyhow!(
+                        "Mock for {} can't have both a body and a body file",
+                        mock.url
+                    ))
+                }
+                (Some(body), None) => Bytes::from(body),
+                (None, Some(body_file)) => {
+                    let body_file = root.join(body_file);
+
+                    Bytes::from(fs::read(&body_file).map_err(|err| {
+                        anyhow!("Could not read body file {:?}: {}", body_file, err)
+                    })?)
+                }
+                (None, None) => Bytes::new(),
+            };
+
+            let headers = mock
+                .headers
+                .into_iter()
+                .map(|(key, value)| (key, vec![value]))
+                .collect::<HashMap<_, _>>();
+
+            Ok(Mock {
+                url: mock.url,
+                method: mock.method,
+                response: Response {
+                    status: mock.status,
+                    headers: if headers.is_empty() {
+                        None
+                    } else {
+                        Some(headers)
+                    },
+                    body,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Mocks { mocks, strict })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lagon_runtime_http::Method;
+
+    fn request(method: Method, url: &str) -> Request {
+        Request {
+            method,
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    fn mocks(strict: bool) -> Mocks {
+        Mocks {
+            mocks: vec![
+                Mock {
+                    url: "https://api.example.com/users/*".into(),
+                    method: Some("GET".into()),
+                    response: Response::from("user"),
+                },
+                Mock {
+                    url: "https://api.example.com/health".into(),
+                    method: None,
+                    response: Response::from("ok"),
+                },
+            ],
+            strict,
+        }
+    }
+
+    #[test]
+    fn glob() {
+        assert!(matches_pattern("https://a.com/*", "https://a.com/b/c"));
+        assert!(matches_pattern("https://*.com/*/c", "https://a.com/b/c"));
+        assert!(matches_pattern("https://a.com", "https://a.com"));
+        assert!(!matches_pattern("https://a.com", "https://a.com/"));
+        assert!(!matches_pattern("https://*.com/c", "https://a.com/b"));
+    }
+
+    #[test]
+    fn find_response() {
+        let mocks = mocks(false);
+
+        assert_eq!(
+            mocks
+                .find_response(&request(Method::GET, "https://api.example.com/users/1"))
+                .unwrap()
+                .unwrap(),
+            Response::from("user")
+        );
+        assert_eq!(
+            mocks
+                .find_response(&request(Method::POST, "https://api.example.com/health"))
+                .unwrap()
+                .unwrap(),
+            Response::from("ok")
+        );
+        assert!(mocks
+            .find_response(&request(Method::POST, "https://api.example.com/users/1"))
+            .is_none());
+    }
+
+    #[test]
+    fn find_response_strict() {
+        let mocks = mocks(true);
+
+        assert!(mocks
+            .find_response(&request(Method::GET, "https://example.com"))
+            .unwrap()
+            .is_err());
+    }
+}
@@ -1,20 +1,108 @@
+mod analysis;
+mod browser;
+mod code_cache;
 mod config;
 mod console;
+mod cors;
+mod cron;
 mod deployments;
+mod dev_config;
+mod inspector;
+mod log_file;
+mod logger;
+mod mask;
+mod mock;
+mod overlay;
+mod proxy;
+mod queue;
+mod shortcuts;
+mod snapshot;
+mod tls;
 mod trpc;
+mod tsconfig;
 
-use std::path::{Path, PathBuf};
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Result};
+use flate2::{write::GzEncoder, Compression};
+use lagon_runtime_http::Method;
+use serde::Deserialize;
+
+pub use analysis::*;
+pub use browser::*;
+pub use code_cache::*;
 pub use config::*;
 pub use console::*;
+pub use cors::*;
+pub use cron::*;
 pub use deployments::*;
+pub use dev_config::*;
+pub use inspector::*;
+pub use log_file::*;
+pub use logger::*;
+pub use mask::*;
+pub use mock::*;
+pub use overlay::*;
+pub use proxy::*;
+pub use queue::*;
+pub use shortcuts::*;
+pub use snapshot::*;
+pub use tls::*;
 pub use trpc::*;
+pub use tsconfig::*;
 
 pub const MAX_FUNCTION_SIZE_MB: usize = 10 * 1024 * 1024; // 10MB
 pub const MAX_ASSET_SIZE_MB: u64 = 10 * 1024 * 1024; // 10MB
 pub const MAX_ASSETS_PER_FUNCTION: usize = 100;
 
+static PACKAGE_JSON: &str = include_str!("../../package.json");
+
+#[derive(Deserialize)]
+struct PackageJson {
+    version: String,
+}
+
+pub fn get_version() -> Result<String> {
+    serde_json::from_str::<PackageJson>(PACKAGE_JSON)
+        .map(|package_json| package_json.version)
+        .map_err(|_| anyhow!("Couldn't extract version from package.json"))
+}
+
+pub fn format_size(bytes: usize) -> String {
+    match bytes {
+        bytes if bytes < 1024 => format!("{bytes}B"),
+        bytes if bytes < 1024 * 1024 => format!("{:.1}KB", bytes as f64 / 1024.0),
+        bytes => format!("{:.1}MB", bytes as f64 / (1024.0 * 1024.0)),
+    }
+}
+
+pub fn gzip_size(bytes: &[u8]) -> io::Result<usize> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(bytes)?;
+
+    Ok(encoder.finish()?.len())
+}
+
+const METHODS: [&str; 7] = ["GET", "POST", "PUT", "PATCH", "DELETE", "HEAD", "OPTIONS"];
+
+// `Method::from` falls back to GET, which would hide typos
+pub fn parse_method(method: &str) -> Result<Method> {
+    let method = method.to_uppercase();
+
+    if !METHODS.contains(&method.as_str()) {
+        return Err(anyhow!(
+            "Method {} is not supported (should be one of {})",
+            method,
+            METHODS.join(", ")
+        ));
+    }
+
+    Ok(Method::from(method.as_str()))
+}
+
 pub fn validate_code_file(file: &Path, root: &Path) -> Result<()> {
     let path = root.join(file);
 
@@ -40,6 +128,33 @@ pub fn validate_code_file(file: &Path, root: &Path) -> Result<()> {
     }
 }
 
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous = distances[0];
+        distances[0] = i + 1;
+
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous + usize::from(a_char != *b_char);
+            previous = distances[j + 1];
+            distances[j + 1] = substitution.min(distances[j] + 1).min(previous + 1);
+        }
+    }
+
+    distances[b.len()]
+}
+
+// The closest key to an unknown one, to suggest a fix for typos
+pub fn suggest_key<'a>(key: &str, keys: &[&'a str]) -> Option<&'a str> {
+    keys.iter()
+        .map(|candidate| (edit_distance(key, candidate), *candidate))
+        .filter(|(distance, candidate)| *distance <= 2.max(candidate.len() / 3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
 pub fn validate_assets_dir(assets_dir: &Option<PathBuf>, root: &Path) -> Result<()> {
     if let Some(dir) = assets_dir {
         let path = root.join(dir);
@@ -0,0 +1,61 @@
+use hyper::{header::ACCEPT, http, Body, HeaderMap, Response as HyperResponse};
+
+const ERROR_OVERLAY: &str = include_str!("../../public/error-overlay.html");
+
+pub enum ErrorFormat {
+    Html,
+    Json,
+    Text,
+}
+
+impl ErrorFormat {
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let accept = headers
+            .get(ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .unwrap_or("");
+
+        if accept.contains("application/json") {
+            ErrorFormat::Json
+        } else if accept.contains("text/html") {
+            ErrorFormat::Html
+        } else {
+            ErrorFormat::Text
+        }
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+pub fn render_error_overlay(error: &str) -> String {
+    // The first line is the error message, and the following
+    // lines (if any) are the stack trace
+    let (message, stack) = error.split_once('\n').unwrap_or((error, ""));
+
+    ERROR_OVERLAY
+        .replace("{{message}}", &escape_html(message))
+        .replace("{{stack}}", &escape_html(stack))
+}
+
+pub fn error_response(error: &str, format: ErrorFormat) -> http::Result<HyperResponse<Body>> {
+    let builder = HyperResponse::builder().status(500);
+
+    match format {
+        ErrorFormat::Html => builder
+            .header("content-type", "text/html; charset=utf-8")
+            .body(render_error_overlay(error).into()),
+        ErrorFormat::Json => builder
+            .header("content-type", "application/json")
+            .body(serde_json::json!({ "error": error }).to_string().into()),
+        ErrorFormat::Text => builder
+            .header("content-type", "text/plain; charset=utf-8")
+            .body(error.to_string().into()),
+    }
+}
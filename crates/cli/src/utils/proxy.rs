@@ -0,0 +1,134 @@
+use anyhow::{anyhow, Result};
+use hyper::client::HttpConnector;
+use hyper::header::{HeaderValue, CONTENT_TYPE, HOST};
+use hyper::http::uri::{Authority, Scheme};
+use hyper::{Body, Client, Request, Response, StatusCode, Uri};
+use hyper_tls::HttpsConnector;
+
+pub struct Proxy {
+    client: Client<HttpsConnector<HttpConnector>>,
+    scheme: Scheme,
+    authority: Authority,
+    // Path of the origin, prepended to the path of each request
+    base_path: String,
+    // Only requests starting with one of these prefixes are
+    // forwarded. All requests are forwarded if empty
+    paths: Vec<String>,
+}
+
+impl Proxy {
+    pub fn new(origin: &str, paths: Vec<String>) -> Result<Self> {
+        let uri = origin
+            .parse::<Uri>()
+            .map_err(|err| anyhow!("Invalid proxy URL `{}`: {}", origin, err))?;
+
+        let scheme = match uri.scheme() {
+            Some(scheme) if *scheme == Scheme::HTTP || *scheme == Scheme::HTTPS => scheme.clone(),
+            _ => {
+                return Err(anyhow!(
+                    "Invalid proxy URL `{}`, expected an http:// or https:// URL",
+                    origin
+                ))
+            }
+        };
+
+        let authority = uri
+            .authority()
+            .cloned()
+            .ok_or_else(|| anyhow!("Invalid proxy URL `{}`, expected a host", origin))?;
+
+        Ok(Self {
+            client: Client::builder().build::<_, Body>(HttpsConnector::new()),
+            scheme,
+            authority,
+            base_path: uri.path().trim_end_matches('/').to_string(),
+            paths,
+        })
+    }
+
+    pub fn should_proxy(&self, path: &str) -> bool {
+        self.paths.is_empty() || self.paths.iter().any(|prefix| path.starts_with(prefix))
+    }
+
+    fn origin(&self) -> String {
+        format!("{}://{}", self.scheme, self.authority)
+    }
+
+    fn bad_gateway(&self, message: String) -> Response<Body> {
+        let mut response = Response::new(Body::from(message));
+        *response.status_mut() = StatusCode::BAD_GATEWAY;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+
+        response
+    }
+
+    // The response is streamed back as is. Errors, e.g when the origin
+    // isn't started, are returned as a 502 response
+    pub async fn forward(&self, mut req: Request<Body>) -> Response<Body> {
+        let path_and_query = req
+            .uri()
+            .path_and_query()
+            .map_or("/", |path_and_query| path_and_query.as_str());
+
+        let uri = match Uri::builder()
+            .scheme(self.scheme.clone())
+            .authority(self.authority.clone())
+            .path_and_query(format!("{}{}", self.base_path, path_and_query))
+            .build()
+        {
+            Ok(uri) => uri,
+            Err(err) => return self.bad_gateway(format!("Invalid proxied URL: {err}")),
+        };
+
+        *req.uri_mut() = uri;
+
+        if let Ok(host) = HeaderValue::from_str(self.authority.as_str()) {
+            req.headers_mut().insert(HOST, host);
+        }
+
+        match self.client.request(req).await {
+            Ok(response) => response,
+            Err(err) => self.bad_gateway(format!(
+                "Could not proxy request to {}: {}",
+                self.origin(),
+                err
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_origin() {
+        assert!(Proxy::new("localhost:3000", Vec::new()).is_err());
+        assert!(Proxy::new("ftp://localhost:3000", Vec::new()).is_err());
+        assert!(Proxy::new("http://localhost:3000", Vec::new()).is_ok());
+    }
+
+    #[test]
+    fn should_proxy() {
+        let proxy = Proxy::new("http://localhost:3000", Vec::new()).unwrap();
+        assert!(proxy.should_proxy("/"));
+        assert!(proxy.should_proxy("/about"));
+
+        let proxy = Proxy::new("http://localhost:3000", vec!["/_next".into()]).unwrap();
+        assert!(proxy.should_proxy("/_next/static/app.js"));
+        assert!(!proxy.should_proxy("/api/users"));
+    }
+
+    #[tokio::test]
+    async fn unreachable_origin() {
+        // Nothing should be listening on port 1
+        let proxy = Proxy::new("http://127.0.0.1:1", Vec::new()).unwrap();
+        let response = proxy
+            .forward(Request::get("/").body(Body::empty()).unwrap())
+            .await;
+
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+}
@@ -0,0 +1,102 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use hyper::body::Bytes;
+use lagon_runtime_http::{Method, Request, X_LAGON_QUEUE};
+use lagon_runtime_isolate::queue::QueueBackend;
+use std::collections::HashMap;
+
+// A message sent with `Lagon.queue.send()`, delivered back to the Function that sent it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueuedMessage {
+    // The route of the Function, `/` for the main Function
+    pub route: String,
+    pub queue: String,
+    // Already serialized to JSON
+    pub message: String,
+}
+
+impl QueuedMessage {
+    // The request delivering the message, sent to the Function like a live request
+    pub fn to_request(&self, base_url: &str) -> Request {
+        let mut headers = HashMap::with_capacity(2);
+        headers.insert("content-type".into(), vec!["application/json".into()]);
+        headers.insert(X_LAGON_QUEUE.into(), vec![self.queue.clone()]);
+
+        Request {
+            headers: Some(headers),
+            method: Method::POST,
+            body: Bytes::from(self.message.clone()),
+            url: format!("{base_url}{}", self.route),
+        }
+    }
+}
+
+// Used by `lagon dev`, which delivers the messages right away instead of using a broker
+#[derive(Debug)]
+pub struct LocalQueueBackend {
+    route: String,
+    tx: flume::Sender<QueuedMessage>,
+}
+
+impl LocalQueueBackend {
+    pub fn new(route: String, tx: flume::Sender<QueuedMessage>) -> Self {
+        LocalQueueBackend { route, tx }
+    }
+}
+
+#[async_trait]
+impl QueueBackend for LocalQueueBackend {
+    async fn send(&self, queue: &str, message: String) -> Result<()> {
+        self.tx
+            .send_async(QueuedMessage {
+                route: self.route.clone(),
+                queue: queue.to_string(),
+                message,
+            })
+            .await
+            .map_err(|_| anyhow!("The dev server is shutting down"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn local_backend_send() {
+        let (tx, rx) = flume::unbounded();
+        let backend = LocalQueueBackend::new("/api".into(), tx);
+
+        backend.send("emails", "{}".into()).await.unwrap();
+
+        assert_eq!(
+            rx.recv_async().await.unwrap(),
+            QueuedMessage {
+                route: "/api".into(),
+                queue: "emails".into(),
+                message: "{}".into(),
+            }
+        );
+
+        drop(rx);
+        assert!(backend.send("emails", "{}".into()).await.is_err());
+    }
+
+    #[test]
+    fn queued_message_request() {
+        let request = QueuedMessage {
+            route: "/".into(),
+            queue: "emails".into(),
+            message: r#"{"to":"hello@lagon.app"}"#.into(),
+        }
+        .to_request("http://127.0.0.1:1234");
+
+        assert_eq!(request.url, "http://127.0.0.1:1234/");
+        assert_eq!(<&str>::from(request.method), "POST");
+        assert_eq!(request.body, Bytes::from(r#"{"to":"hello@lagon.app"}"#));
+        assert_eq!(
+            request.headers.unwrap().get(X_LAGON_QUEUE),
+            Some(&vec!["emails".to_string()])
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use ::console::{Key, Term};
+use std::io::{self, IsTerminal};
+
+pub const SHORTCUTS_HINT: &str =
+    "Press r to restart, c to clear, o to open in the browser, q to quit";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Shortcut {
+    Restart,
+    Clear,
+    Open,
+    Quit,
+}
+
+impl Shortcut {
+    fn from_key(key: Key) -> Option<Self> {
+        match key {
+            Key::Char('r') => Some(Shortcut::Restart),
+            Key::Char('c') => Some(Shortcut::Clear),
+            Key::Char('o') => Some(Shortcut::Open),
+            // Ctrl+C doesn't send SIGINT while the terminal is in raw mode
+            Key::Char('q') | Key::Char('\u{3}') => Some(Shortcut::Quit),
+            _ => None,
+        }
+    }
+}
+
+// Keys are read in raw mode on a separate thread, since reading is blocking.
+// Returns None if stdin isn't an interactive terminal (e.g in CI or when the
+// input is piped), so we don't consume input that isn't meant for us.
+pub fn listen_shortcuts() -> Option<flume::Receiver<Shortcut>> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        return None;
+    }
+
+    let (tx, rx) = flume::unbounded();
+
+    std::thread::spawn(move || {
+        let term = Term::stdout();
+
+        loop {
+            let shortcut = match term.read_key() {
+                Ok(key) => Shortcut::from_key(key),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => Some(Shortcut::Quit),
+                Err(_) => break,
+            };
+
+            if let Some(shortcut) = shortcut {
+                if tx.send(shortcut).is_err() || shortcut == Shortcut::Quit {
+                    break;
+                }
+            }
+        }
+    });
+
+    Some(rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcut_from_key() {
+        assert_eq!(Shortcut::from_key(Key::Char('r')), Some(Shortcut::Restart));
+        assert_eq!(Shortcut::from_key(Key::Char('c')), Some(Shortcut::Clear));
+        assert_eq!(Shortcut::from_key(Key::Char('o')), Some(Shortcut::Open));
+        assert_eq!(Shortcut::from_key(Key::Char('q')), Some(Shortcut::Quit));
+        assert_eq!(Shortcut::from_key(Key::Char('\u{3}')), Some(Shortcut::Quit));
+        assert_eq!(Shortcut::from_key(Key::Char('x')), None);
+        assert_eq!(Shortcut::from_key(Key::Enter), None);
+    }
+}
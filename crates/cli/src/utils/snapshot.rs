@@ -0,0 +1,76 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+
+use super::get_version;
+
+// Snapshots are only compatible with the exact same runtime (and V8 version),
+// so they start with the version of the CLI that created them
+const SNAPSHOT_MAGIC: &str = "LAGON_SNAPSHOT";
+
+pub fn stamp_snapshot(blob: &[u8]) -> Result<Vec<u8>> {
+    let mut snapshot = format!("{SNAPSHOT_MAGIC}\n{}\n", get_version()?).into_bytes();
+    snapshot.extend_from_slice(blob);
+
+    Ok(snapshot)
+}
+
+fn parse_snapshot<'a>(content: &'a [u8], version: &str) -> Result<&'a [u8]> {
+    let mut parts = content.splitn(3, |byte| *byte == b'\n');
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(magic), Some(snapshot_version), Some(blob)) if magic == SNAPSHOT_MAGIC.as_bytes() => {
+            let snapshot_version = String::from_utf8_lossy(snapshot_version);
+
+            match snapshot_version == version {
+                true => Ok(blob),
+                false => Err(anyhow!(
+                    "Snapshot was built by version {} of the runtime, but this is version {}. Run `lagon snapshot` again to rebuild it",
+                    snapshot_version,
+                    version
+                )),
+            }
+        }
+        _ => Err(anyhow!("File is not a snapshot built by `lagon snapshot`")),
+    }
+}
+
+// The snapshot is used by every isolate until the CLI exits, so it's leaked
+pub fn load_snapshot(path: &Path) -> Result<&'static [u8]> {
+    let content = fs::read(path)
+        .map_err(|err| anyhow!("Could not read snapshot {}: {}", path.display(), err))?;
+    let blob = parse_snapshot(&content, &get_version()?)
+        .map_err(|err| anyhow!("Could not load snapshot {}: {}", path.display(), err))?
+        .to_vec();
+
+    Ok(Box::leak(blob.into_boxed_slice()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_stamped_snapshot() {
+        let snapshot = stamp_snapshot(b"blob\nwith\nnewlines").unwrap();
+
+        assert_eq!(
+            parse_snapshot(&snapshot, &get_version().unwrap()).unwrap(),
+            b"blob\nwith\nnewlines"
+        );
+    }
+
+    #[test]
+    fn parse_snapshot_other_version() {
+        let snapshot = b"LAGON_SNAPSHOT\n0.0.1\nblob";
+
+        assert!(parse_snapshot(snapshot, "0.0.1").is_ok());
+        assert!(parse_snapshot(snapshot, "0.0.2")
+            .unwrap_err()
+            .to_string()
+            .starts_with(
+                "Snapshot was built by version 0.0.1 of the runtime, but this is version 0.0.2"
+            ));
+        assert!(parse_snapshot(b"not a snapshot", "0.0.1").is_err());
+    }
+}
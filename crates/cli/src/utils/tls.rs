@@ -0,0 +1,170 @@
+use anyhow::{anyhow, Result};
+use rustls::client::{ServerCertVerified, ServerCertVerifier};
+use rustls::{
+    Certificate, ClientConfig, ClientConnection, Connection, PrivateKey, ServerConfig,
+    ServerConnection, ServerName,
+};
+use rustls_pemfile::Item;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+// Only used to check that a certificate and a key match, by doing
+// a handshake with ourselves: the signature sent by the server is
+// still verified against the certificate.
+struct AnyServerCertVerifier;
+
+impl ServerCertVerifier for AnyServerCertVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn open_pem(path: &Path) -> Result<BufReader<File>> {
+    let file = File::open(path).map_err(|err| anyhow!("Could not open {:?}: {}", path, err))?;
+
+    Ok(BufReader::new(file))
+}
+
+pub fn read_certificates(path: &Path) -> Result<Vec<Certificate>> {
+    let certificates = rustls_pemfile::certs(&mut open_pem(path)?)
+        .map_err(|err| anyhow!("Could not parse certificate {:?}: {}", path, err))?;
+
+    if certificates.is_empty() {
+        return Err(anyhow!("No certificate found in {:?}", path));
+    }
+
+    Ok(certificates.into_iter().map(Certificate).collect())
+}
+
+fn read_private_key(path: &Path) -> Result<PrivateKey> {
+    let mut reader = open_pem(path)?;
+
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)
+        .map_err(|err| anyhow!("Could not parse private key {:?}: {}", path, err))?
+    {
+        match item {
+            Item::PKCS8Key(key) | Item::RSAKey(key) | Item::ECKey(key) => {
+                return Ok(PrivateKey(key))
+            }
+            _ => continue,
+        }
+    }
+
+    Err(anyhow!("No private key found in {:?}", path))
+}
+
+fn transfer(from: &mut Connection, to: &mut Connection) -> Result<bool> {
+    let mut buffer = Vec::new();
+
+    while from.wants_write() {
+        from.write_tls(&mut buffer)?;
+    }
+
+    let mut data = buffer.as_slice();
+
+    while !data.is_empty() {
+        to.read_tls(&mut data)?;
+        to.process_new_packets()?;
+    }
+
+    Ok(!buffer.is_empty())
+}
+
+fn verify_key_pair(config: Arc<ServerConfig>) -> Result<()> {
+    let client_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(AnyServerCertVerifier))
+        .with_no_client_auth();
+
+    let mut client = Connection::from(ClientConnection::new(
+        Arc::new(client_config),
+        ServerName::try_from("localhost")?,
+    )?);
+    let mut server = Connection::from(ServerConnection::new(config)?);
+
+    while client.is_handshaking() || server.is_handshaking() {
+        let client_sent = transfer(&mut client, &mut server)?;
+        let server_sent = transfer(&mut server, &mut client)?;
+
+        if !client_sent && !server_sent {
+            return Err(anyhow!("TLS handshake did not complete"));
+        }
+    }
+
+    Ok(())
+}
+
+fn create_tls_config(certificates: Vec<Certificate>, key: PrivateKey) -> Result<Arc<ServerConfig>> {
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certificates, key)?;
+
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    let config = Arc::new(config);
+
+    verify_key_pair(Arc::clone(&config))
+        .map_err(|err| anyhow!("Certificate and private key do not match: {}", err))?;
+
+    Ok(config)
+}
+
+pub fn load_tls_config(cert: &Path, key: &Path) -> Result<Arc<ServerConfig>> {
+    let certificates = read_certificates(cert)?;
+    let key = read_private_key(key)?;
+
+    create_tls_config(certificates, key)
+}
+
+pub fn self_signed_tls_config() -> Result<Arc<ServerConfig>> {
+    let certificate =
+        rcgen::generate_simple_self_signed(vec!["localhost".into(), "127.0.0.1".into()])?;
+
+    create_tls_config(
+        vec![Certificate(certificate.serialize_der()?)],
+        PrivateKey(certificate.serialize_private_key_der()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn self_signed() {
+        assert!(self_signed_tls_config().is_ok());
+    }
+
+    #[test]
+    fn invalid_certificates() {
+        let path = std::env::temp_dir().join("lagon-invalid-certificate.pem");
+        std::fs::write(&path, "not a certificate").unwrap();
+
+        assert!(read_certificates(&path).is_err());
+        assert!(read_certificates(Path::new("missing.pem")).is_err());
+    }
+
+    #[test]
+    fn mismatched_key() {
+        let certificate = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let other = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+
+        assert!(create_tls_config(
+            vec![Certificate(certificate.serialize_der().unwrap())],
+            PrivateKey(other.serialize_private_key_der()),
+        )
+        .is_err());
+    }
+}
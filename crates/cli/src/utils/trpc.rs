@@ -28,7 +28,7 @@ pub struct TrpcErrorResult {
 
 pub struct TrpcClient {
     pub client: Client<HttpsConnector<HttpConnector>>,
-    config:  Config,
+    config: Config,
 }
 
 impl TrpcClient {
@@ -0,0 +1,299 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+
+const TSCONFIG_FILE: &str = "tsconfig.json";
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct CompilerOptions {
+    base_url: Option<PathBuf>,
+    jsx: Option<String>,
+    #[serde(default)]
+    paths: HashMap<String, Vec<String>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct RawTsConfig {
+    #[serde(default)]
+    compiler_options: CompilerOptions,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct PathAlias {
+    // e.g `@lib/*`, matching any import starting with `@lib/`
+    pub pattern: String,
+    // Absolute, with the same wildcard as the pattern
+    pub targets: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub struct TsConfig {
+    pub path: PathBuf,
+    // Sorted from the most specific pattern to the least specific one
+    pub aliases: Vec<PathAlias>,
+    // e.g `react-jsx`
+    pub jsx: Option<String>,
+}
+
+// Nearest tsconfig.json from the root of the Function, up to the root of the file system
+pub fn find_tsconfig(root: &Path) -> Option<PathBuf> {
+    let root = root.canonicalize().ok()?;
+
+    root.ancestors()
+        .map(|dir| dir.join(TSCONFIG_FILE))
+        .find(|path| path.is_file())
+}
+
+// tsconfig.json files are JSONC: they can contain comments and trailing commas
+fn strip_json_comments(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(char) = chars.next() {
+        if in_string {
+            output.push(char);
+
+            match char {
+                '\\' => output.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+
+            continue;
+        }
+
+        match (char, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                output.push(char);
+            }
+            ('/', Some('/')) => {
+                for char in chars.by_ref() {
+                    if char == '\n' {
+                        output.push(char);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+
+                while let Some(char) = chars.next() {
+                    if char == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            ('}', _) | (']', _) => {
+                let trimmed = output.trim_end().len();
+
+                if output[..trimmed].ends_with(',') {
+                    output.remove(trimmed - 1);
+                }
+
+                output.push(char);
+            }
+            _ => output.push(char),
+        }
+    }
+
+    output
+}
+
+impl TsConfig {
+    pub fn load(path: &Path) -> Result<TsConfig> {
+        let content = fs::read_to_string(path)?;
+
+        TsConfig::parse(&content, path)
+            .map_err(|err| anyhow!("Could not parse {}: {}", path.display(), err))
+    }
+
+    fn parse(content: &str, path: &Path) -> Result<TsConfig> {
+        let raw = serde_json::from_str::<RawTsConfig>(&strip_json_comments(content))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        // Paths are relative to `baseUrl` if set, or to the tsconfig.json itself
+        let base_url = match raw.compiler_options.base_url {
+            Some(base_url) => dir.join(base_url),
+            None => dir.to_path_buf(),
+        };
+
+        let mut aliases = raw
+            .compiler_options
+            .paths
+            .into_iter()
+            .map(|(pattern, targets)| PathAlias {
+                pattern,
+                targets: targets.iter().map(|target| base_url.join(target)).collect(),
+            })
+            .collect::<Vec<_>>();
+
+        // Like TypeScript, the pattern with the longest prefix wins
+        aliases.sort_by(|a, b| {
+            let prefix = |pattern: &str| pattern.find('*').unwrap_or(pattern.len());
+
+            prefix(&b.pattern)
+                .cmp(&prefix(&a.pattern))
+                .then_with(|| a.pattern.cmp(&b.pattern))
+        });
+
+        Ok(TsConfig {
+            path: path.to_path_buf(),
+            aliases,
+            jsx: raw.compiler_options.jsx,
+        })
+    }
+
+    // Directories and files the aliases point to, e.g to watch them for changes
+    pub fn alias_paths(&self) -> Vec<PathBuf> {
+        let mut paths = self
+            .aliases
+            .iter()
+            .flat_map(|alias| &alias.targets)
+            .map(|target| {
+                let target = target.to_string_lossy();
+
+                match target.find('*') {
+                    Some(index) => PathBuf::from(&target[..index]),
+                    None => PathBuf::from(target.as_ref()),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TSCONFIG: &str = r#"{
+  // Comments are allowed
+  "compilerOptions": {
+    "strict": true,
+    "baseUrl": "./src",
+    "paths": {
+      "@lib/*": ["lib/*"],
+      "@lib/utils/*": ["../shared/utils/*", "lib/utils/*"],
+      /* Exact aliases too */
+      "@config": ["config.ts"],
+    },
+  },
+}"#;
+
+    #[test]
+    fn strip_comments_and_trailing_commas() {
+        assert_eq!(
+            strip_json_comments(r#"{ "a": "// not a comment", /* b */ "c": [1, 2,], }"#),
+            r#"{ "a": "// not a comment",  "c": [1, 2] }"#
+        );
+        assert_eq!(
+            strip_json_comments("{\n  // comment\n  \"a\": \"\\\"/*\"\n}"),
+            "{\n  \n  \"a\": \"\\\"/*\"\n}"
+        );
+        assert_eq!(
+            strip_json_comments("{ \"a\": 1, // comment\n}"),
+            "{ \"a\": 1 \n}"
+        );
+    }
+
+    #[test]
+    fn parse_nested_aliases() {
+        let dir = Path::new("functions").join("hello");
+        let config = TsConfig::parse(TSCONFIG, &dir.join(TSCONFIG_FILE)).unwrap();
+        let src = dir.join("./src");
+
+        assert_eq!(
+            config.aliases,
+            vec![
+                PathAlias {
+                    pattern: "@lib/utils/*".into(),
+                    targets: vec![src.join("../shared/utils/*"), src.join("lib/utils/*")],
+                },
+                PathAlias {
+                    pattern: "@config".into(),
+                    targets: vec![src.join("config.ts")],
+                },
+                PathAlias {
+                    pattern: "@lib/*".into(),
+                    targets: vec![src.join("lib/*")],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn alias_paths_wildcards() {
+        let dir = Path::new("functions").join("hello");
+        let config = TsConfig::parse(TSCONFIG, &dir.join(TSCONFIG_FILE)).unwrap();
+        let src = dir.join("./src");
+
+        let mut expected = vec![
+            PathBuf::from(
+                src.join("../shared/utils/*")
+                    .to_string_lossy()
+                    .trim_end_matches('*'),
+            ),
+            PathBuf::from(
+                src.join("lib/utils/*")
+                    .to_string_lossy()
+                    .trim_end_matches('*'),
+            ),
+            PathBuf::from(src.join("lib/*").to_string_lossy().trim_end_matches('*')),
+            src.join("config.ts"),
+        ];
+        expected.sort();
+        expected.dedup();
+
+        assert_eq!(config.alias_paths(), expected);
+    }
+
+    #[test]
+    fn parse_without_base_url() {
+        let config = TsConfig::parse(
+            r#"{ "compilerOptions": { "paths": { "~/*": ["./*"] } } }"#,
+            Path::new("tsconfig.json"),
+        )
+        .unwrap();
+
+        assert_eq!(config.aliases[0].targets, vec![Path::new("").join("./*")]);
+        assert!(TsConfig::parse("{}", Path::new("tsconfig.json"))
+            .unwrap()
+            .aliases
+            .is_empty());
+    }
+
+    #[test]
+    fn find_nearest_tsconfig() {
+        let root = std::env::temp_dir().join("lagon-find-tsconfig");
+        let function = root.join("functions").join("hello");
+        fs::create_dir_all(&function).unwrap();
+        fs::write(root.join(TSCONFIG_FILE), "{}").unwrap();
+
+        assert_eq!(
+            find_tsconfig(&function),
+            Some(root.canonicalize().unwrap().join(TSCONFIG_FILE))
+        );
+
+        fs::write(function.join(TSCONFIG_FILE), "{}").unwrap();
+
+        assert_eq!(
+            find_tsconfig(&function),
+            Some(function.canonicalize().unwrap().join(TSCONFIG_FILE))
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
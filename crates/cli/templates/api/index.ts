@@ -0,0 +1,23 @@
+const json = (data: unknown, status = 200) =>
+  new Response(JSON.stringify(data), {
+    status,
+    headers: {
+      'content-type': 'application/json',
+    },
+  });
+
+export async function handler(request: Request): Promise<Response> {
+  const url = new URL(request.url);
+
+  if (url.pathname === '/api/hello') {
+    const name = url.searchParams.get('name') ?? 'World';
+
+    return json({ message: `Hello ${name}!` });
+  }
+
+  if (url.pathname === '/api/echo' && request.method === 'POST') {
+    return json({ body: await request.json() });
+  }
+
+  return json({ error: 'Not found' }, 404);
+}
@@ -0,0 +1,20 @@
+const html = `<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta charset="UTF-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1.0" />
+    <title>Hello World!</title>
+    <link rel="stylesheet" href="/style.css" />
+  </head>
+  <body>
+    <h1>Hello World!</h1>
+  </body>
+</html>`;
+
+export function handler(request: Request): Response {
+  return new Response(html, {
+    headers: {
+      'content-type': 'text/html',
+    },
+  });
+}
@@ -0,0 +1,3 @@
+export function handler(request: Request): Response {
+  return new Response('Hello World!');
+}
@@ -0,0 +1,23 @@
+export function handler(request: Request): Response {
+  const encoder = new TextEncoder();
+  let count = 0;
+
+  const stream = new ReadableStream({
+    start(controller) {
+      const interval = setInterval(() => {
+        controller.enqueue(encoder.encode(`Chunk ${++count}\n`));
+
+        if (count === 5) {
+          clearInterval(interval);
+          controller.close();
+        }
+      }, 100);
+    },
+  });
+
+  return new Response(stream, {
+    headers: {
+      'content-type': 'text/plain',
+    },
+  });
+}
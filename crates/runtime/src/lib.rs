@@ -1,3 +1,8 @@
+use lagon_runtime_isolate::{
+    cache::set_cache_backend,
+    proxy::set_proxy,
+    tls::{set_tls_options, TlsOptions},
+};
 use v8::V8;
 
 pub mod options;
@@ -33,6 +38,24 @@ impl Runtime {
 
         V8::set_flags_from_string(&flags);
 
+        if let Some(fetch_proxy) = options.fetch_proxy {
+            set_proxy(fetch_proxy);
+        }
+
+        if let Some(cache_backend) = options.cache_backend {
+            set_cache_backend(cache_backend);
+        }
+
+        // The system's root certificates are loaded lazily otherwise
+        if !options.extra_root_certificates.is_empty() || options.danger_accept_invalid_certificates
+        {
+            set_tls_options(TlsOptions {
+                extra_root_certificates: options.extra_root_certificates,
+                danger_accept_invalid_certificates: options.danger_accept_invalid_certificates,
+            })
+            .expect("Invalid root certificates");
+        }
+
         let platform = v8::new_default_platform(0, false).make_shared();
         V8::initialize_platform(platform);
         V8::initialize();
@@ -1,7 +1,20 @@
+use lagon_runtime_isolate::{cache::CacheBackend, proxy::Proxy, tls::Certificate};
+use std::sync::Arc;
+
 #[derive(Default)]
 pub struct RuntimeOptions {
     pub allow_code_generation: bool,
     pub expose_gc: bool,
+    // The proxy of fetch() calls, read from the HTTP_PROXY, HTTPS_PROXY
+    // and NO_PROXY environment variables if None
+    pub fetch_proxy: Option<Proxy>,
+    // Trusted by fetch() in addition to the system's root certificates, in DER
+    pub extra_root_certificates: Vec<Certificate>,
+    // Don't verify the certificates of fetch() calls, only for development
+    pub danger_accept_invalid_certificates: bool,
+    // Where the Cache API stores its responses, for the isolates without their own
+    // backend. Without any, responses are never stored
+    pub cache_backend: Option<Arc<dyn CacheBackend>>,
 }
 
 impl RuntimeOptions {
@@ -14,4 +27,27 @@ impl RuntimeOptions {
         self.expose_gc = expose_gc;
         self
     }
+
+    pub fn fetch_proxy(mut self, fetch_proxy: Proxy) -> Self {
+        self.fetch_proxy = Some(fetch_proxy);
+        self
+    }
+
+    pub fn extra_root_certificates(mut self, extra_root_certificates: Vec<Certificate>) -> Self {
+        self.extra_root_certificates = extra_root_certificates;
+        self
+    }
+
+    pub fn danger_accept_invalid_certificates(
+        mut self,
+        danger_accept_invalid_certificates: bool,
+    ) -> Self {
+        self.danger_accept_invalid_certificates = danger_accept_invalid_certificates;
+        self
+    }
+
+    pub fn cache_backend(mut self, cache_backend: Arc<dyn CacheBackend>) -> Self {
+        self.cache_backend = Some(cache_backend);
+        self
+    }
 }
@@ -0,0 +1,299 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{
+    cache::{CacheBackend, CacheEntry, MemoryCacheBackend},
+    options::IsolateOptions,
+};
+use std::{sync::Arc, time::Duration};
+
+mod utils;
+
+// Run the handler in a new isolate using this backend, if any
+async fn run(handler: &str, backend: Option<Arc<MemoryCacheBackend>>) -> RunResult {
+    let mut options = IsolateOptions::new(format!(
+        "export async function handler() {{
+    {handler}
+}}"
+    ));
+
+    if let Some(backend) = backend {
+        options = options.cache_backend(backend);
+    }
+
+    let (send, receiver) = utils::create_isolate(options);
+    send(Request::default());
+
+    receiver.recv_async().await.unwrap()
+}
+
+#[tokio::test]
+async fn cache_hit_and_miss() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+
+    assert_eq!(
+        run(
+            "await caches.default.put('https://example.com/hello', new Response('Hello, World', {
+        status: 201,
+        headers: { 'cache-control': 'max-age=60', 'x-custom': 'value' },
+    }));
+
+    const hit = await caches.default.match('https://example.com/hello');
+    const miss = await caches.default.match('https://example.com/other');
+    const otherCache = await caches.open('other').then(cache => cache.match('https://example.com/hello'));
+
+    return new Response(`${await hit.text()} ${hit.status} ${hit.headers.get('x-custom')} ${miss} ${otherCache}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("Hello, World 201 value undefined undefined"))
+    );
+}
+
+#[tokio::test]
+async fn cache_expiry() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+    let match_hello = "const response = await caches.default.match('https://example.com/hello');
+    return new Response(response ? await response.text() : 'Miss');";
+
+    assert_eq!(
+        run(
+            "await caches.default.put('https://example.com/hello', new Response('Hello, World', {
+        headers: { 'cache-control': 'max-age=1' },
+    }));
+    return new Response('Stored');",
+            Some(Arc::clone(&backend)),
+        )
+        .await,
+        RunResult::Response(Response::from("Stored"))
+    );
+
+    // The backend is shared by all the isolates using it
+    assert_eq!(
+        run(match_hello, Some(Arc::clone(&backend))).await,
+        RunResult::Response(Response::from("Hello, World"))
+    );
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+
+    assert_eq!(
+        run(match_hello, Some(backend)).await,
+        RunResult::Response(Response::from("Miss"))
+    );
+}
+
+#[tokio::test]
+async fn cache_already_expired() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+
+    assert_eq!(
+        run(
+            "await caches.default.put('https://example.com/hello', new Response('Hello, World', {
+        headers: { 'cache-control': 'max-age=60', age: '60' },
+    }));
+    const response = await caches.default.match('https://example.com/hello');
+
+    return new Response(`${response}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("undefined"))
+    );
+}
+
+#[tokio::test]
+async fn cache_vary() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+
+    assert_eq!(
+        run(
+            "const request = language => new Request('https://example.com/hello', {
+        headers: { 'accept-language': language },
+    });
+    const response = body => new Response(body, { headers: { vary: 'Accept-Language' } });
+
+    await caches.default.put(request('en'), response('Hello'));
+    await caches.default.put(request('fr'), response('Bonjour'));
+
+    const en = await caches.default.match(request('en')).then(res => res.text());
+    const fr = await caches.default.match(request('fr')).then(res => res.text());
+    const de = await caches.default.match(request('de'));
+    const none = await caches.default.match('https://example.com/hello');
+
+    return new Response(`${en} ${fr} ${de} ${none}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("Hello Bonjour undefined undefined"))
+    );
+}
+
+#[tokio::test]
+async fn cache_replace() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+
+    assert_eq!(
+        run(
+            "await caches.default.put('https://example.com/hello', new Response('Hello'));
+    await caches.default.put('https://example.com/hello#fragment', new Response('Hello, World'));
+
+    const response = await caches.default.match('https://example.com/hello');
+    return new Response(await response.text());",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("Hello, World"))
+    );
+}
+
+#[tokio::test]
+async fn cache_no_store() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+
+    assert_eq!(
+        run(
+            "await caches.default.put('https://example.com/hello', new Response('Hello, World', {
+        headers: { 'cache-control': 'public, no-store' },
+    }));
+    const response = await caches.default.match('https://example.com/hello');
+
+    return new Response(`${response}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("undefined"))
+    );
+}
+
+#[tokio::test]
+async fn cache_methods() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+
+    assert_eq!(
+        run(
+            "const post = new Request('https://example.com/hello', { method: 'POST' });
+    const error = await caches.default.put(post, new Response('Hello, World')).catch(error => error);
+
+    await caches.default.put('https://example.com/hello', new Response('Hello, World'));
+
+    const miss = await caches.default.match(post);
+    const hit = await caches.default.match(post, { ignoreMethod: true }).then(res => res.text());
+
+    return new Response(`${error instanceof TypeError} ${error.message} ${miss} ${hit}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from(
+            "true Cannot cache the response of a POST request undefined Hello, World"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn cache_invalid_responses() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+
+    assert_eq!(
+        run(
+            "const put = response => caches.default
+        .put('https://example.com/hello', response)
+        .then(() => 'Stored', error => error.message);
+
+    const partial = await put(new Response('Hello', { status: 206 }));
+    const varyAll = await put(new Response('Hello', { headers: { vary: '*' } }));
+
+    return new Response(`${partial}, ${varyAll}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from(
+            "Cannot cache a partial response, Cannot cache a response with a `Vary: *` header"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn cache_delete() {
+    utils::setup();
+    let backend = Arc::new(MemoryCacheBackend::new(1024 * 1024));
+
+    assert_eq!(
+        run(
+            "await caches.default.put('https://example.com/hello', new Response('Hello, World'));
+
+    const deleted = await caches.default.delete('https://example.com/hello');
+    const response = await caches.default.match('https://example.com/hello');
+    const deletedAgain = await caches.default.delete('https://example.com/hello');
+
+    return new Response(`${deleted} ${response} ${deletedAgain}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("true undefined false"))
+    );
+}
+
+#[tokio::test]
+async fn cache_without_backend() {
+    utils::setup();
+
+    assert_eq!(
+        run(
+            "await caches.default.put('https://example.com/hello', new Response('Hello, World'));
+    const response = await caches.default.match('https://example.com/hello');
+
+    return new Response(`${response}`);",
+            None,
+        )
+        .await,
+        RunResult::Response(Response::from("undefined"))
+    );
+}
+
+#[tokio::test]
+async fn memory_backend_eviction() {
+    let entry = |body: &str| CacheEntry {
+        response: Response::from(body),
+        vary: Vec::new(),
+        expires_at: None,
+    };
+    // Enough for two entries of 100 bytes, and their keys
+    let backend = MemoryCacheBackend::new(250);
+
+    for url in ["/first", "/second"] {
+        backend
+            .set("default", url, vec![entry(&"a".repeat(100))])
+            .await
+            .unwrap();
+    }
+
+    // Reading an entry makes it the most recently used
+    assert_eq!(backend.get("default", "/first").await.unwrap().len(), 1);
+
+    backend
+        .set("default", "/third", vec![entry(&"a".repeat(100))])
+        .await
+        .unwrap();
+
+    assert_eq!(backend.get("default", "/first").await.unwrap().len(), 1);
+    assert!(backend.get("default", "/second").await.unwrap().is_empty());
+    assert_eq!(backend.get("default", "/third").await.unwrap().len(), 1);
+
+    // Larger than the whole cache
+    backend
+        .set("default", "/large", vec![entry(&"a".repeat(300))])
+        .await
+        .unwrap();
+
+    assert!(backend.get("default", "/large").await.unwrap().is_empty());
+
+    backend.set("default", "/first", Vec::new()).await.unwrap();
+
+    assert!(backend.get("default", "/first").await.unwrap().is_empty());
+}
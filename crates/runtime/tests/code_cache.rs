@@ -0,0 +1,171 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{
+    options::{IsolateOptions, Metadata},
+    Isolate, IsolateEvent, IsolateRequest,
+};
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+use tokio::runtime::Handle;
+
+mod utils;
+
+fn options(code: &str) -> IsolateOptions {
+    IsolateOptions::new(code.into()).snapshot_blob(include_bytes!("../../serverless/snapshot.bin"))
+}
+
+// Evaluates the code and sends a single request, returning
+// the code cache created by the isolate
+async fn evaluate(options: IsolateOptions) -> (Option<Vec<u8>>, RunResult) {
+    let (tx, rx) = flume::unbounded();
+    let (sender, receiver) = flume::unbounded();
+    let (code_cache_tx, code_cache_rx) = flume::unbounded();
+
+    tx.send(IsolateEvent::Request(IsolateRequest {
+        request: Request::default(),
+        sender,
+        statistics: None,
+        body_stream: None,
+        connection: None,
+    }))
+    .unwrap();
+    drop(tx);
+
+    let handle = Handle::current();
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            let mut isolate = Isolate::new(options, rx);
+            isolate.evaluate();
+
+            code_cache_tx
+                .send(isolate.get_code_cache().map(<[u8]>::to_vec))
+                .unwrap();
+            isolate.run_event_loop().await;
+        })
+    });
+
+    (
+        code_cache_rx.recv_async().await.unwrap(),
+        receiver.recv_async().await.unwrap(),
+    )
+}
+
+#[tokio::test]
+async fn code_cache_skip_compilation() {
+    utils::setup();
+    let code = "function greet(name) {
+    return `Hello ${name}`;
+}
+
+export function handler() {
+    return new Response(greet('world'));
+}";
+    // Whether a code cache was used, for each compilation
+    let compilations = Arc::new(Mutex::new(Vec::new()));
+
+    let on_compile = {
+        let compilations = Arc::clone(&compilations);
+        move |_: Rc<Metadata>, from_code_cache: bool| {
+            compilations.lock().unwrap().push(from_code_cache)
+        }
+    };
+    let (code_cache, result) =
+        evaluate(options(code).on_compile_callback(Box::new(on_compile.clone()))).await;
+
+    assert_eq!(result, RunResult::Response(Response::from("Hello world")));
+    assert_eq!(*compilations.lock().unwrap(), vec![false]);
+
+    let code_cache = code_cache.unwrap();
+    assert!(!code_cache.is_empty());
+
+    let (code_cache, result) = evaluate(
+        options(code)
+            .code_cache(code_cache)
+            .on_compile_callback(Box::new(on_compile)),
+    )
+    .await;
+
+    assert_eq!(result, RunResult::Response(Response::from("Hello world")));
+    assert_eq!(*compilations.lock().unwrap(), vec![false, true]);
+    // The code cache given to the isolate can be reused
+    assert!(code_cache.is_none());
+}
+
+#[tokio::test]
+async fn code_cache_rejected() {
+    utils::setup();
+    let code = "export function handler() {
+    return new Response('Hello world');
+}";
+    let compilations = Arc::new(Mutex::new(Vec::new()));
+
+    let on_compile = {
+        let compilations = Arc::clone(&compilations);
+        move |_: Rc<Metadata>, from_code_cache: bool| {
+            compilations.lock().unwrap().push(from_code_cache)
+        }
+    };
+    // Not a code cache created by V8
+    let (code_cache, result) = evaluate(
+        options(code)
+            .code_cache(vec![0; 64])
+            .on_compile_callback(Box::new(on_compile)),
+    )
+    .await;
+
+    assert_eq!(result, RunResult::Response(Response::from("Hello world")));
+    assert_eq!(*compilations.lock().unwrap(), vec![false]);
+    // A new code cache replaces the rejected one
+    assert!(!code_cache.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn code_cache_environment_variables() {
+    utils::setup();
+    let code = "export function handler() {
+    const { A, B, C } = process.env;
+
+    return new Response(`${A} ${B} ${C}`);
+}";
+    let environment_variables = || {
+        HashMap::from([
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+            ("C".to_string(), "3".to_string()),
+        ])
+    };
+
+    let (code_cache, result) =
+        evaluate(options(code).environment_variables(environment_variables())).await;
+
+    assert_eq!(result, RunResult::Response(Response::from("1 2 3")));
+
+    // Each HashMap iterates in a different order
+    let (_, result) = evaluate(
+        options(code)
+            .environment_variables(environment_variables())
+            .code_cache(code_cache.unwrap()),
+    )
+    .await;
+
+    assert_eq!(result, RunResult::Response(Response::from("1 2 3")));
+}
+
+#[tokio::test]
+async fn code_cache_not_created_on_error() {
+    utils::setup();
+    let (code_cache, result) = evaluate(options(
+        "export function handler() {
+    this syntax is invalid
+}",
+    ))
+    .await;
+
+    assert_eq!(
+        result,
+        RunResult::Error("Uncaught SyntaxError: Unexpected identifier 'syntax'".into())
+    );
+    assert!(code_cache.is_none());
+}
@@ -0,0 +1,202 @@
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use httptest::{matchers::*, responders::*, Expectation, Server};
+use lagon_runtime_http::{Request, Response, RunResult, StreamResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use std::io::{Read, Write};
+
+mod utils;
+
+// Assertions throw so a failing test rejects, like in streams.rs
+async fn run_compression_test(test: &str) {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "function assert_equals(actual, expected) {{
+    if (JSON.stringify(actual) !== JSON.stringify(expected)) {{
+        throw new Error(`Expected ${{JSON.stringify(expected)}} but got ${{JSON.stringify(actual)}}`);
+    }}
+}}
+
+async function transform(stream, chunks) {{
+    const writer = stream.writable.getWriter();
+    // Errors are read from the readable side instead
+    for (const chunk of chunks) writer.write(chunk).catch(() => {{}});
+    writer.close().catch(() => {{}});
+
+    return new Uint8Array(await new Response(stream.readable).arrayBuffer());
+}}
+
+export async function handler() {{
+    {test}
+    return new Response('ok');
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ok"))
+    );
+}
+
+#[tokio::test]
+async fn round_trip() {
+    run_compression_test(
+        "const input = new TextEncoder().encode('Hello World'.repeat(1000));
+
+    for (const format of ['gzip', 'deflate', 'deflate-raw']) {
+        const compressed = await transform(new CompressionStream(format), [input.subarray(0, 5000), input.subarray(5000)]);
+        assert_equals(compressed.length < input.length, true);
+
+        // Feed the compressed data byte by byte, to split it at every position
+        const chunks = [...compressed].map(byte => new Uint8Array([byte]));
+        const decompressed = await transform(new DecompressionStream(format), chunks);
+        assert_equals(new TextDecoder().decode(decompressed), 'Hello World'.repeat(1000));
+    }",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn round_trip_empty() {
+    run_compression_test(
+        "for (const format of ['gzip', 'deflate', 'deflate-raw']) {
+        const compressed = await transform(new CompressionStream(format), []);
+        assert_equals(compressed.length > 0, true);
+
+        const decompressed = await transform(new DecompressionStream(format), [compressed]);
+        assert_equals(decompressed.length, 0);
+    }",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn buffer_sources() {
+    run_compression_test(
+        "const input = new TextEncoder().encode('Hello World');
+    const compressed = await transform(new CompressionStream('gzip'), [
+        input.buffer.slice(0, 5),
+        new DataView(input.buffer, 5),
+    ]);
+    const decompressed = await transform(new DecompressionStream('gzip'), [compressed]);
+
+    assert_equals(new TextDecoder().decode(decompressed), 'Hello World');",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn invalid_format() {
+    run_compression_test(
+        "for (const format of ['br', 'GZIP', undefined]) {
+        try {
+            new CompressionStream(format);
+            throw new Error('Expected a TypeError');
+        } catch (error) {
+            assert_equals(error instanceof TypeError, true);
+        }
+    }",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn invalid_chunk() {
+    run_compression_test(
+        "try {
+        await transform(new CompressionStream('gzip'), ['Hello World']);
+        throw new Error('Expected a TypeError');
+    } catch (error) {
+        assert_equals(error instanceof TypeError, true);
+    }",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn invalid_data() {
+    run_compression_test(
+        "try {
+        await transform(new DecompressionStream('gzip'), [new TextEncoder().encode('Hello World')]);
+        throw new Error('Expected a TypeError');
+    } catch (error) {
+        assert_equals(error instanceof TypeError, true);
+    }",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn trailing_data() {
+    run_compression_test(
+        "const compressed = await transform(new CompressionStream('deflate'), [new TextEncoder().encode('Hello World')]);
+
+    try {
+        await transform(new DecompressionStream('deflate'), [compressed, new Uint8Array([1, 2, 3])]);
+        throw new Error('Expected a TypeError');
+    } catch (error) {
+        assert_equals(error instanceof TypeError, true);
+    }",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn compress_response() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    const body = new Response(new TextEncoder().encode('Hello World'.repeat(100))).body;
+    return new Response(body.pipeThrough(new CompressionStream('gzip')));
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    let mut compressed = Vec::new();
+
+    loop {
+        match receiver.recv_async().await.unwrap() {
+            RunResult::Stream(StreamResult::Data(data)) => compressed.extend(data),
+            RunResult::Stream(StreamResult::Done) => break,
+            RunResult::Stream(StreamResult::Start(_)) => {}
+            result => panic!("Unexpected result: {result:?}"),
+        }
+    }
+
+    let mut body = String::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_string(&mut body)
+        .unwrap();
+
+    assert_eq!(body, "Hello World".repeat(100));
+}
+
+#[tokio::test]
+async fn decompress_fetch() {
+    utils::setup();
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(br#"{"hello":"world"}"#).unwrap();
+
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(200).body(encoder.finish().unwrap())),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const response = await fetch('{url}');
+    const json = await new Response(response.body.pipeThrough(new DecompressionStream('gzip'))).json();
+
+    return new Response(json.hello);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("world"))
+    );
+}
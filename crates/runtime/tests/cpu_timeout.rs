@@ -0,0 +1,91 @@
+use httptest::{matchers::*, responders::*, Expectation, Server};
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use std::time::{Duration, Instant};
+
+mod utils;
+
+#[tokio::test]
+async fn cpu_timeout_reached() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export function handler() {
+    while(true) {}
+    return new Response('Should not be reached');
+}"
+            .into(),
+        )
+        .timeout(Duration::ZERO)
+        .cpu_timeout(Duration::from_millis(50)),
+    );
+    let start_time = Instant::now();
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::CpuTimeLimit
+    );
+    assert!(start_time.elapsed() < Duration::from_millis(500));
+}
+
+#[tokio::test]
+async fn cpu_timeout_accumulated() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    while(true) {
+        const start = Date.now();
+        while(Date.now() - start < 5) {}
+
+        await new Promise(resolve => setTimeout(resolve, 1));
+    }
+
+    return new Response('Should not be reached');
+}"
+            .into(),
+        )
+        .timeout(Duration::from_secs(1))
+        .cpu_timeout(Duration::from_millis(50)),
+    );
+    send(Request::default());
+
+    // Each slice of JS is shorter than both limits
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::CpuTimeLimit
+    );
+}
+
+#[tokio::test]
+async fn cpu_timeout_slow_fetch() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/")).respond_with(delay_and_then(
+            Duration::from_millis(300),
+            status_code(200).body("Slow"),
+        )),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const body = await fetch('{url}').then(res => res.text());
+
+    return new Response(body);
+}}"
+        ))
+        .timeout(Duration::from_secs(1))
+        .cpu_timeout(Duration::from_millis(20)),
+    );
+    send(Request::default());
+
+    // Awaiting the response doesn't use CPU time
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Slow"))
+    );
+}
@@ -0,0 +1,75 @@
+use lagon_runtime_http::{Request, RunResult};
+use lagon_runtime_isolate::{options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest};
+use std::time::Duration;
+use tokio::{net::TcpListener, runtime::Handle};
+
+mod utils;
+
+#[tokio::test]
+async fn client_disconnect() {
+    utils::setup();
+    let log_rx = utils::setup_logger();
+    let (tx, rx) = flume::unbounded();
+    // Accepts the fetch() connection but never responds
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = Handle::current();
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            let mut isolate = Isolate::new(
+                IsolateOptions::new(format!(
+                    "export async function handler(request) {{
+    request.signal.onabort = () => console.log(`aborted ${{request.signal.reason.name}}`);
+
+    try {{
+        await fetch('{url}');
+    }} catch (error) {{
+        console.log(`fetch ${{error.message}}`);
+    }}
+
+    return new Response('Too late');
+}}"
+                ))
+                .snapshot_blob(include_bytes!("../../serverless/snapshot.bin")),
+                rx,
+            );
+            isolate.evaluate();
+            isolate.run_event_loop().await;
+        })
+    });
+
+    let (sender, receiver) = flume::unbounded();
+    let (connection_tx, connection_rx) = flume::bounded::<()>(1);
+    tx.send(IsolateEvent::Request(IsolateRequest {
+        request: Request::default(),
+        sender,
+        statistics: None,
+        body_stream: None,
+        connection: Some(connection_rx),
+    }))
+    .unwrap();
+
+    // The client disconnects while the fetch() call is in flight
+    let _stream = listener.accept().await.unwrap();
+    drop(connection_tx);
+
+    assert_eq!(receiver.recv_async().await.unwrap(), RunResult::Aborted);
+
+    let mut logs = vec![
+        log_rx.recv_async().await.unwrap(),
+        log_rx.recv_async().await.unwrap(),
+    ];
+    logs.sort();
+
+    assert_eq!(
+        logs,
+        ["aborted AbortError", "fetch The operation was aborted"]
+    );
+    // The response returned after the abort is ignored
+    assert!(
+        tokio::time::timeout(Duration::from_millis(100), receiver.recv_async())
+            .await
+            .is_err()
+    );
+}
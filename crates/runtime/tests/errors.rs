@@ -1,4 +1,4 @@
-use lagon_runtime_http::{Request, RunResult};
+use lagon_runtime_http::{Request, Response, RunResult};
 use lagon_runtime_isolate::options::IsolateOptions;
 use std::time::Duration;
 
@@ -105,6 +105,28 @@ async fn execution_timeout_reached() {
     assert_eq!(receiver.recv_async().await.unwrap(), RunResult::Timeout);
 }
 
+#[tokio::test]
+async fn execution_timeout_disabled() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export function handler() {
+    const start = Date.now();
+    while(Date.now() - start < 500) {}
+    return new Response('Hello world');
+}"
+            .into(),
+        )
+        .timeout(Duration::ZERO),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+}
+
 #[tokio::test]
 async fn init_timeout_reached() {
     utils::setup();
@@ -145,7 +167,13 @@ async fn memory_reached() {
     );
     send(Request::default());
 
-    assert_eq!(receiver.recv_async().await.unwrap(), RunResult::MemoryLimit);
+    match receiver.recv_async().await.unwrap() {
+        RunResult::MemoryLimit(statistics) => {
+            assert!(statistics.used_heap_size > 0);
+            assert!(statistics.heap_size_limit > 0);
+        }
+        result => panic!("Unexpected result: {result:?}"),
+    }
 }
 
 #[tokio::test]
@@ -0,0 +1,164 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+
+mod utils;
+
+// Assertions throw so a failing test rejects, like in compression.rs
+async fn run_events_test(test: &str) {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "function assert_equals(actual, expected) {{
+    if (JSON.stringify(actual) !== JSON.stringify(expected)) {{
+        throw new Error(`Expected ${{JSON.stringify(expected)}} but got ${{JSON.stringify(actual)}}`);
+    }}
+}}
+
+export function handler() {{
+    const target = new EventTarget();
+    const log = [];
+    {test}
+    return new Response('ok');
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ok"))
+    );
+}
+
+#[tokio::test]
+async fn once_listener() {
+    run_events_test(
+        "target.addEventListener('test', () => log.push('once'), { once: true });
+    target.addEventListener('test', () => log.push('always'));
+
+    target.dispatchEvent(new Event('test'));
+    target.dispatchEvent(new Event('test'));
+    assert_equals(log, ['once', 'always', 'always']);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn signal_removes_listener() {
+    run_events_test(
+        "const controller = new AbortController();
+    target.addEventListener('test', () => log.push('signal'), { signal: controller.signal });
+
+    target.dispatchEvent(new Event('test'));
+    controller.abort();
+    target.dispatchEvent(new Event('test'));
+    assert_equals(log, ['signal']);
+
+    // Listeners aren't added with an already aborted signal
+    target.addEventListener('test', () => log.push('aborted'), { signal: AbortSignal.abort() });
+    target.dispatchEvent(new Event('test'));
+    assert_equals(log, ['signal']);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn dispatch_return_value() {
+    run_events_test(
+        "target.addEventListener('test', event => event.preventDefault());
+    target.addEventListener('passive', event => event.preventDefault(), { passive: true });
+
+    assert_equals(target.dispatchEvent(new Event('test')), true);
+    assert_equals(target.dispatchEvent(new Event('none', { cancelable: true })), true);
+    assert_equals(target.dispatchEvent(new Event('passive', { cancelable: true })), true);
+
+    const event = new Event('test', { cancelable: true });
+    assert_equals(target.dispatchEvent(event), false);
+    assert_equals([event.defaultPrevented, event.returnValue], [true, false]);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn stop_immediate_propagation() {
+    run_events_test(
+        "target.addEventListener('test', event => {
+        log.push('first');
+        event.stopImmediatePropagation();
+    });
+    target.addEventListener('test', () => log.push('second'));
+
+    target.dispatchEvent(new Event('test'));
+    assert_equals(log, ['first']);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn listener_identity() {
+    run_events_test(
+        "const listener = () => log.push('listener');
+    target.addEventListener('test', listener);
+    target.addEventListener('test', listener);
+    target.addEventListener('test', listener, { capture: true });
+
+    target.dispatchEvent(new Event('test'));
+    assert_equals(log.length, 2);
+
+    target.removeEventListener('test', listener);
+    target.dispatchEvent(new Event('test'));
+    assert_equals(log.length, 3);
+
+    target.removeEventListener('test', listener, true);
+    target.dispatchEvent(new Event('test'));
+    assert_equals(log.length, 3);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn dispatch_state() {
+    run_events_test(
+        "target.addEventListener('test', {
+        handleEvent(event) {
+            log.push(event.target === target, event.currentTarget === target, event.eventPhase);
+        },
+    });
+
+    const event = new CustomEvent('test', { detail: 'detail' });
+    target.dispatchEvent(event);
+    assert_equals(log, [true, true, Event.AT_TARGET]);
+    assert_equals([event.target === target, event.currentTarget, event.eventPhase], [true, null, Event.NONE]);
+    assert_equals([event instanceof Event, event.detail], [true, 'detail']);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn listener_error_isolation() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    const target = new EventTarget();
+    let called = false;
+
+    target.addEventListener('test', () => {
+        throw new Error('Listener failed');
+    });
+    target.addEventListener('test', () => {
+        called = true;
+    });
+
+    if (!target.dispatchEvent(new Event('test')) || !called) {
+        throw new Error('The second listener was not called');
+    }
+
+    return new Promise(resolve => setTimeout(() => resolve(new Response('ok')), 10));
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    match receiver.recv_async().await.unwrap() {
+        RunResult::Error(error) => assert!(error.starts_with("Uncaught Error: Listener failed")),
+        result => panic!("Unexpected result: {result:?}"),
+    }
+}
@@ -1,9 +1,57 @@
+use anyhow::anyhow;
 use httptest::{matchers::*, responders::*, Expectation, Server};
-use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_http::{Request, Response, RunResult, StreamResult};
 use lagon_runtime_isolate::options::IsolateOptions;
+use std::{
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::net::{TcpListener, TcpStream};
 
 mod utils;
 
+// Proxy the connections to the server, to count how many were opened
+async fn count_connections(server: &Server) -> (String, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/", listener.local_addr().unwrap());
+    let server_addr = server.addr();
+    let connections = Arc::new(AtomicUsize::new(0));
+    let count = Arc::clone(&connections);
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            count.fetch_add(1, Ordering::SeqCst);
+
+            tokio::spawn(async move {
+                let mut server_stream = TcpStream::connect(server_addr).await.unwrap();
+                tokio::io::copy_bidirectional(&mut stream, &mut server_stream)
+                    .await
+                    .unwrap_or_default();
+            });
+        }
+    });
+
+    (url, connections)
+}
+
+// Makes 10 sequential fetch() calls per request, to stay under the limit of fetch() calls
+fn sequential_fetches(url: &str) -> IsolateOptions {
+    IsolateOptions::new(format!(
+        "export async function handler() {{
+    for (let i = 0; i < 10; i++) {{
+        await fetch('{url}').then(res => res.text());
+    }}
+
+    return new Response('Done');
+}}"
+    ))
+    .timeout(Duration::from_secs(1))
+}
+
 #[tokio::test]
 async fn basic_fetch() {
     utils::setup();
@@ -177,6 +225,137 @@ async fn request_body() {
     );
 }
 
+#[tokio::test]
+async fn request_binary_body() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/"),
+            request::body(matches(r"^(?-u:\x00\x9F\xFF)$"))
+        ])
+        .respond_with(status_code(200).body("Hello, World")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('{url}', {{
+        method: 'POST',
+        body: new Uint8Array([0, 159, 255]),
+    }}).then(res => res.text());
+
+    return new Response(body);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello, World"))
+    );
+}
+
+#[tokio::test]
+async fn request_blob_body() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/blob"),
+            request::headers(contains(("content-type", "text/plain"))),
+            request::body("Hello, Blob")
+        ])
+        .respond_with(status_code(200).body("Hello, World")),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/custom"),
+            request::headers(contains(("content-type", "application/custom"))),
+            request::body("Hello, Blob")
+        ])
+        .respond_with(status_code(200).body("Hello, World")),
+    );
+    let blob_url = server.url("/blob");
+    let custom_url = server.url("/custom");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const blob = new Blob(['Hello, ', 'Blob'], {{ type: 'text/plain' }});
+    const first = await fetch('{blob_url}', {{
+        method: 'POST',
+        body: blob,
+    }}).then(res => res.text());
+    const second = await fetch('{custom_url}', {{
+        method: 'POST',
+        headers: {{ 'content-type': 'application/custom' }},
+        body: blob,
+    }}).then(res => res.text());
+
+    return new Response(first + ' ' + second);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello, World Hello, World"))
+    );
+}
+
+#[tokio::test]
+async fn request_form_data_body() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/"),
+            request::headers(contains((
+                "content-type",
+                matches("^multipart/form-data; boundary=----LagonFormBoundary[0-9a-f]+$")
+            ))),
+            request::body(matches(concat!(
+                r"^--(----LagonFormBoundary[0-9a-f]+)\r\n",
+                r#"Content-Disposition: form-data; name="hello"\r\n\r\n"#,
+                r"multi\r\nline\r\n",
+                r"--(----LagonFormBoundary[0-9a-f]+)\r\n",
+                r#"Content-Disposition: form-data; name="file"; filename="hello.txt"\r\n"#,
+                r"Content-Type: text/plain\r\n\r\n",
+                r"Hello, File\r\n",
+                r"--(----LagonFormBoundary[0-9a-f]+)\r\n",
+                r#"Content-Disposition: form-data; name="quoted%22name"; filename="blob"\r\n"#,
+                r"Content-Type: application/octet-stream\r\n\r\n",
+                r"(?-u:\x00\xFF)\r\n",
+                r"--(----LagonFormBoundary[0-9a-f]+)--\r\n$",
+            )))
+        ])
+        .respond_with(status_code(200).body("Hello, World")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const formData = new FormData();
+    formData.append('hello', 'multi\\nline');
+    formData.append('file', new File(['Hello, File'], 'hello.txt', {{ type: 'text/plain' }}));
+    formData.append('quoted\"name', new Blob([new Uint8Array([0, 255])]));
+
+    const body = await fetch('{url}', {{
+        method: 'POST',
+        body: formData,
+    }}).then(res => res.text());
+
+    return new Response(body);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello, World"))
+    );
+}
+
 #[tokio::test]
 async fn response_headers() {
     utils::setup();
@@ -210,6 +389,34 @@ async fn response_headers() {
     );
 }
 
+#[tokio::test]
+async fn response_set_cookie_headers() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/")).respond_with(
+            status_code(200)
+                .append_header("set-cookie", "greeting=hello")
+                .append_header("set-cookie", "name=world"),
+        ),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const response = await fetch('{url}');
+
+    return new Response(response.headers.getSetCookie().join(' | '));
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("greeting=hello | name=world"))
+    );
+}
+
 #[tokio::test]
 async fn response_status() {
     utils::setup();
@@ -319,7 +526,7 @@ async fn throw_invalid_header() {
         "export async function handler() {
     const response = await fetch('http://localhost:5555/', {
         headers: {
-            'foo': 'bar\\r\\n'
+            'foo': 'bar\\r\\nbaz'
         }
     });
     const body = await response.text();
@@ -332,12 +539,12 @@ async fn throw_invalid_header() {
 
     assert_eq!(
         receiver.recv_async().await.unwrap(),
-        RunResult::Error("Uncaught Error: failed to parse header value".into())
+        RunResult::Error("Uncaught TypeError: Invalid value for header foo".into())
     );
 }
 
 #[tokio::test]
-async fn abort_signal() {
+async fn response_body_reader() {
     utils::setup();
     let server = Server::run();
     server.expect(
@@ -348,15 +555,15 @@ async fn abort_signal() {
 
     let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
         "export async function handler() {{
-    const controller = new AbortController();
-    const signal = controller.signal;
-
-    const promise = fetch('{url}', {{
-        signal,
-    }}).then(res => res.text()).catch(error => error.message);
-
-    controller.abort();
-    const body = await promise;
+    const reader = (await fetch('{url}')).body.getReader();
+    const decoder = new TextDecoder();
+    let body = '';
+
+    while (true) {{
+        const {{ done, value }} = await reader.read();
+        if (done) break;
+        body += decoder.decode(value);
+    }}
 
     return new Response(body);
 }}"
@@ -365,185 +572,991 @@ async fn abort_signal() {
 
     assert_eq!(
         receiver.recv_async().await.unwrap(),
-        RunResult::Response(Response::from("Aborted"))
+        RunResult::Response(Response::from("Hello, World"))
     );
 }
 
 #[tokio::test]
-async fn redirect() {
+async fn response_body_cancel() {
     utils::setup();
     let server = Server::run();
     server.expect(
         Expectation::matching(request::method_path("GET", "/"))
-            .respond_with(status_code(301).append_header("Location", "https://google.com")),
+            .respond_with(status_code(200).body(vec![b'a'; 1024 * 1024])),
     );
     let url = server.url("/");
 
     let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
         "export async function handler() {{
-    const status = (await fetch('{url}')).status;
-    return new Response(status);
+    const reader = (await fetch('{url}')).body.getReader();
+    const {{ value }} = await reader.read();
+    await reader.cancel();
+
+    return new Response(value.length > 0);
 }}"
     )));
     send(Request::default());
 
     assert_eq!(
         receiver.recv_async().await.unwrap(),
-        RunResult::Response(Response::from("200"))
+        RunResult::Response(Response::from("true"))
     );
 }
 
 #[tokio::test]
-async fn redirect_relative_url() {
+async fn response_body_proxy() {
     utils::setup();
     let server = Server::run();
     server.expect(
         Expectation::matching(request::method_path("GET", "/"))
-            .respond_with(status_code(301).append_header("Location", "/redirected")),
+            .respond_with(status_code(200).body(vec![b'a'; 10 * 1024 * 1024])),
+    );
+    let url = server.url("/");
+
+    // The body would exceed the heap limit if it wasn't streamed
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const response = await fetch('{url}');
+    return new Response(response.body);
+}}"
+        ))
+        .memory(4),
     );
+    send(Request::default());
+
+    let mut size = 0;
+
+    loop {
+        match receiver.recv_async().await.unwrap() {
+            RunResult::Stream(StreamResult::Data(data)) => size += data.len(),
+            RunResult::Stream(StreamResult::Done) => break,
+            RunResult::Stream(StreamResult::Start(_)) => {}
+            result => panic!("Unexpected result: {result:?}"),
+        }
+    }
+
+    assert_eq!(size, 10 * 1024 * 1024);
+}
+
+#[tokio::test]
+async fn abort_signal() {
+    utils::setup();
+    let server = Server::run();
     server.expect(
-        Expectation::matching(request::method_path("GET", "/redirected"))
-            .respond_with(status_code(200)),
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(..)
+            .respond_with(status_code(200).body("Hello, World")),
     );
     let url = server.url("/");
 
     let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
         "export async function handler() {{
-    const status = (await fetch('{url}')).status;
-    return new Response(status);
+    const controller = new AbortController();
+    const signal = controller.signal;
+
+    const promise = fetch('{url}', {{
+        signal,
+    }}).then(res => res.text()).catch(error => `${{error.name}}: ${{error.message}}`);
+
+    controller.abort();
+    const body = await promise;
+
+    return new Response(body);
 }}"
     )));
     send(Request::default());
 
     assert_eq!(
         receiver.recv_async().await.unwrap(),
-        RunResult::Response(Response::from("200"))
+        RunResult::Response(Response::from("AbortError: This operation was aborted"))
     );
 }
 
 #[tokio::test]
-async fn redirect_without_location_header() {
+async fn abort_signal_before_send() {
     utils::setup();
+    // No expectations, so the test fails if a request is received
     let server = Server::run();
-    server.expect(
-        Expectation::matching(request::method_path("GET", "/")).respond_with(status_code(301)),
-    );
     let url = server.url("/");
 
     let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
         "export async function handler() {{
-    const status = (await fetch('{url}')).status;
-    return new Response(status);
+    const controller = new AbortController();
+    controller.abort();
+
+    const body = await fetch('{url}', {{
+        signal: controller.signal,
+    }}).then(res => res.text()).catch(error => `${{error.name}}: ${{error.message}}`);
+
+    return new Response(body);
 }}"
     )));
     send(Request::default());
 
     assert_eq!(
         receiver.recv_async().await.unwrap(),
-        RunResult::Error("Uncaught Error: Got a redirect without Location header".into())
+        RunResult::Response(Response::from("AbortError: This operation was aborted"))
     );
 }
 
 #[tokio::test]
-async fn redirect_loop() {
+async fn abort_signal_reason() {
     utils::setup();
     let server = Server::run();
     server.expect(
         Expectation::matching(request::method_path("GET", "/"))
-            .respond_with(status_code(301).append_header("location", "/a")),
-    );
-    server.expect(
-        Expectation::matching(request::method_path("GET", "/a"))
-            .respond_with(status_code(301).append_header("location", "/b")),
-    );
-    server.expect(
-        Expectation::matching(request::method_path("GET", "/b"))
-            .respond_with(status_code(301).append_header("location", "/c")),
-    );
-    server.expect(
-        Expectation::matching(request::method_path("GET", "/c"))
-            .respond_with(status_code(301).append_header("location", "/d")),
-    );
-    server.expect(
-        Expectation::matching(request::method_path("GET", "/d"))
-            .respond_with(status_code(301).append_header("location", "/e")),
+            .times(..)
+            .respond_with(delay_and_then(
+                Duration::from_secs(1),
+                status_code(200).body("Hello, World"),
+            )),
     );
     let url = server.url("/");
 
     let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
         "export async function handler() {{
-    const status = (await fetch('{url}')).status;
-    return new Response(status);
+    const controller = new AbortController();
+    setTimeout(() => controller.abort('Too slow'), 10);
+
+    const body = await fetch('{url}', {{
+        signal: controller.signal,
+    }}).then(res => res.text()).catch(error => error);
+
+    return new Response(body);
 }}"
     )));
     send(Request::default());
 
     assert_eq!(
         receiver.recv_async().await.unwrap(),
-        RunResult::Error("Uncaught Error: Too many redirects".into())
+        RunResult::Response(Response::from("Too slow"))
     );
 }
 
 #[tokio::test]
-async fn limit_fetch_calls() {
+async fn abort_signal_body() {
     utils::setup();
     let server = Server::run();
     server.expect(
         Expectation::matching(request::method_path("GET", "/"))
-            .times(21)
-            .respond_with(status_code(200)),
+            .respond_with(status_code(200).body("Hello, World")),
     );
     let url = server.url("/");
 
     let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
-        "let pass = false;
-export async function handler() {{
-    if (!pass) {{
-        pass = true
-        while (true) {{
-            await fetch('{url}');
-        }}
-        return new Response('done');
-    }}
-    await fetch('{url}');
-    return new Response('ok')
-}}"
-    )));
-    send(Request::default());
+        "export async function handler() {{
+    const controller = new AbortController();
+    const response = await fetch('{url}', {{
+        signal: controller.signal,
+    }});
 
-    assert_eq!(
-        receiver.recv_async().await.unwrap(),
-        RunResult::Error("Uncaught Error: fetch() can only be called 20 times per requests".into())
-    );
+    controller.abort();
+    const body = await response.text().catch(error => `${{error.name}}: ${{error.message}}`);
 
-    // Test if we can still call fetch in subsequent requests
+    return new Response(body);
+}}"
+    )));
     send(Request::default());
 
     assert_eq!(
         receiver.recv_async().await.unwrap(),
-        RunResult::Error("Uncaught Error: fetch() can only be called 20 times per requests".into())
-    );
-
-    assert_eq!(
-        receiver.recv_async().await.unwrap(),
-        RunResult::Response(Response::from("ok"))
+        RunResult::Response(Response::from("AbortError: This operation was aborted"))
     );
 }
 
 #[tokio::test]
-async fn fetch_https() {
+async fn abort_signal_timeout() {
     utils::setup();
-    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(..)
+            .respond_with(delay_and_then(
+                Duration::from_secs(1),
+                status_code(200).body("Hello, World"),
+            )),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
         "export async function handler() {{
-    const status = (await fetch('https://google.com')).status;
-    return new Response(status);
+    const body = await fetch('{url}', {{
+        signal: AbortSignal.timeout(10),
+    }}).then(res => res.text()).catch(error => `${{error.name}}: ${{error.message}}`);
+
+    return new Response(body);
 }}"
-        .into(),
-    ));
+    )));
     send(Request::default());
 
     assert_eq!(
         receiver.recv_async().await.unwrap(),
-        RunResult::Response(Response::from("200"))
+        RunResult::Response(Response::from("TimeoutError: The operation timed out."))
+    );
+}
+
+#[tokio::test]
+async fn request_timeout() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/slow")).respond_with(delay_and_then(
+            Duration::from_millis(500),
+            status_code(200).body("Slow"),
+        )),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/fast"))
+            .respond_with(status_code(200).body("Fast")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const slow = await fetch('{url}slow', {{ timeout: 50 }})
+        .then(res => res.text())
+        .catch(error => `${{error instanceof TypeError}} ${{error.code}}: ${{error.message}}`);
+    const fast = await fetch('{url}fast', {{ timeout: 1000 }}).then(res => res.text());
+    const invalid = await fetch('{url}fast', {{ timeout: -1 }}).catch(error => error.name);
+
+    return new Response(`${{slow}} ${{fast}} ${{invalid}}`);
+}}"
+        ))
+        .timeout(Duration::from_secs(1)),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "true ETIMEDOUT: The request timed out after 50ms Fast TypeError"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn request_timeout_default() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(2)
+            .respond_with(delay_and_then(
+                Duration::from_millis(200),
+                status_code(200).body("Slow"),
+            )),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const timedOut = await fetch('{url}').catch(error => error.code);
+    const disabled = await fetch('{url}', {{ timeout: 0 }}).then(res => res.text());
+
+    return new Response(`${{timedOut}} ${{disabled}}`);
+}}"
+        ))
+        .timeout(Duration::from_secs(1))
+        .fetch_timeout(Duration::from_millis(50)),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ETIMEDOUT Slow"))
+    );
+}
+
+#[tokio::test]
+async fn redirect() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(301).append_header("Location", "https://google.com")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const status = (await fetch('{url}')).status;
+    return new Response(status);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("200"))
+    );
+}
+
+#[tokio::test]
+async fn redirect_relative_url() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(301).append_header("Location", "/redirected")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/redirected"))
+            .respond_with(status_code(200)),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const status = (await fetch('{url}')).status;
+    return new Response(status);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("200"))
+    );
+}
+
+#[tokio::test]
+async fn redirect_without_location_header() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/")).respond_with(status_code(301)),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const response = await fetch('{url}');
+    return new Response(`${{response.status}} ${{response.redirected}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("301 false"))
+    );
+}
+
+#[tokio::test]
+async fn redirect_url() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(302).append_header("location", "/a")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/a"))
+            .respond_with(status_code(307).append_header("location", "b?c=d")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/b"))
+            .times(2)
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let url = server.url("/");
+    let redirected_url = server.url("/b?c=d");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const response = await fetch('{url}');
+    const notRedirected = await fetch('{redirected_url}').then(res => res.redirected);
+
+    return new Response(`${{response.redirected}} ${{response.url}} ${{await response.text()}} ${{notRedirected}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            format!("true {redirected_url} Hello, World false").as_str()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn redirect_method() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/"),
+            request::body("Hello!")
+        ])
+        .respond_with(status_code(307).append_header("location", "/temporary")),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("POST", "/temporary"),
+            request::body("Hello!"),
+            request::headers(contains(("content-type", "text/plain")))
+        ])
+        .respond_with(status_code(302).append_header("location", "/found")),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/found"),
+            request::body(""),
+            not(request::headers(contains(key("content-type"))))
+        ])
+        .respond_with(status_code(200)),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("PUT", "/"),
+            request::body("Hello!")
+        ])
+        .respond_with(status_code(303).append_header("location", "/other")),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/other"),
+            request::body("")
+        ])
+        .respond_with(status_code(200)),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const init = {{ body: 'Hello!', headers: {{ 'content-type': 'text/plain' }} }};
+    const post = await fetch('{url}', {{ ...init, method: 'POST' }});
+    const put = await fetch('{url}', {{ ...init, method: 'PUT' }});
+
+    return new Response(`${{post.status}} ${{put.status}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("200 200"))
+    );
+}
+
+#[tokio::test]
+async fn redirect_cross_origin() {
+    utils::setup();
+    let server = Server::run();
+    let other_server = Server::run();
+    let other_url = other_server.url("/");
+
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/"),
+            request::headers(contains(("authorization", "Bearer token"))),
+            request::headers(contains(("cookie", "session=1")))
+        ])
+        .respond_with(status_code(301).append_header("location", "/same-origin")),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/same-origin"),
+            request::headers(contains(("authorization", "Bearer token"))),
+            request::headers(contains(("cookie", "session=1")))
+        ])
+        .respond_with(status_code(301).append_header("location", other_url.to_string())),
+    );
+    other_server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/"),
+            request::headers(contains(("x-custom", "kept"))),
+            not(request::headers(contains(key("authorization")))),
+            not(request::headers(contains(key("cookie"))))
+        ])
+        .respond_with(status_code(200).body("Hello, World")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const response = await fetch('{url}', {{
+        headers: {{
+            authorization: 'Bearer token',
+            cookie: 'session=1',
+            'x-custom': 'kept',
+        }},
+    }});
+
+    return new Response(`${{response.url}} ${{await response.text()}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(format!("{other_url} Hello, World").as_str()))
+    );
+}
+
+#[tokio::test]
+async fn redirect_manual() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(301).append_header("location", "/redirected")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const response = await fetch('{url}', {{ redirect: 'manual' }});
+
+    return new Response(
+        `${{response.status}} ${{response.type}} ${{response.headers.get('location')}} ${{response.redirected}} ${{response.body}}`,
+    );
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("301 opaqueredirect /redirected false null"))
+    );
+}
+
+#[tokio::test]
+async fn redirect_error() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(308).append_header("location", "/redirected")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('{url}', {{ redirect: 'error' }})
+        .then(res => res.text())
+        .catch(error => `${{error.name}}: ${{error.message}}`);
+    const invalid = await fetch('{url}', {{ redirect: 'invalid' }}).catch(error => error.name);
+
+    return new Response(`${{body}} ${{invalid}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "TypeError: Got a redirect to /redirected but the redirect mode is 'error' TypeError"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn redirect_loop() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(301).append_header("location", "/a")),
+    );
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/a"))
+            .respond_with(status_code(302).append_header("location", "/")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const status = (await fetch('{url}')).status;
+    return new Response(status);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error(format!(
+            "Uncaught TypeError: Redirect loop detected at {url}"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn redirect_limit() {
+    utils::setup();
+    let server = Server::run();
+
+    // 20 redirects are followed, but not 21
+    for hop in 0..21 {
+        server.expect(
+            Expectation::matching(request::method_path("GET", format!("/{hop}")))
+                .times(if hop == 0 { 1 } else { 2 })
+                .respond_with(status_code(307).append_header("location", format!("/{}", hop + 1))),
+        );
+    }
+
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/21"))
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('{url}1').then(res => res.text());
+    const error = await fetch('{url}0')
+        .catch(error => `${{error.name}} ${{error.code}}: ${{error.message}}`);
+
+    return new Response(`${{body}} ${{error}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "Hello, World TypeError ERR_TOO_MANY_REDIRECTS: Too many redirects, the maximum is 20"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn limit_fetch_calls() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(21)
+            .respond_with(status_code(200)),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "let pass = false;
+export async function handler() {{
+    if (!pass) {{
+        pass = true
+        while (true) {{
+            await fetch('{url}');
+        }}
+        return new Response('done');
+    }}
+    await fetch('{url}');
+    return new Response('ok')
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error("Uncaught Error: fetch() can only be called 20 times per requests".into())
+    );
+
+    // Test if we can still call fetch in subsequent requests
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error("Uncaught Error: fetch() can only be called 20 times per requests".into())
+    );
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ok"))
+    );
+}
+
+#[tokio::test]
+async fn fetch_keep_alive() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(50)
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let (url, connections) = count_connections(&server).await;
+
+    let (send, receiver) = utils::create_isolate(sequential_fetches(&url));
+
+    // The connections are reused across the requests of the isolate
+    for _ in 0..5 {
+        send(Request::default());
+
+        assert_eq!(
+            receiver.recv_async().await.unwrap(),
+            RunResult::Response(Response::from("Done"))
+        );
+    }
+
+    assert!(connections.load(Ordering::SeqCst) < 5);
+}
+
+#[tokio::test]
+async fn fetch_pool_per_isolate() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(20)
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let (url, connections) = count_connections(&server).await;
+
+    let (send, receiver) = utils::create_isolate(sequential_fetches(&url));
+    let (other_send, other_receiver) = utils::create_isolate(sequential_fetches(&url));
+
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Done"))
+    );
+
+    other_send(Request::default());
+
+    assert_eq!(
+        other_receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Done"))
+    );
+
+    assert_eq!(connections.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn fetch_pool_disabled() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(10)
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let (url, connections) = count_connections(&server).await;
+
+    let (send, receiver) =
+        utils::create_isolate(sequential_fetches(&url).fetch_pool_max_idle_per_host(0));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Done"))
+    );
+
+    assert_eq!(connections.load(Ordering::SeqCst), 10);
+}
+
+#[tokio::test]
+async fn fetch_https() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export async function handler() {{
+    const status = (await fetch('https://google.com')).status;
+    return new Response(status);
+}}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("200"))
+    );
+}
+
+#[tokio::test]
+async fn fetch_intercepted() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    const body = await fetch('https://example.com/mocked').then(res => res.text());
+    return new Response(body);
+}"
+            .into(),
+        )
+        .on_fetch_callback(Rc::new(|request: &Request| {
+            if request.url == "https://example.com/mocked" {
+                Some(Ok(Response::from("Mocked")))
+            } else {
+                None
+            }
+        })),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Mocked"))
+    );
+}
+
+#[tokio::test]
+async fn fetch_intercepted_error() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    const body = await fetch('https://example.com').then(res => res.text());
+    return new Response(body);
+}"
+            .into(),
+        )
+        .on_fetch_callback(Rc::new(|_: &Request| Some(Err(anyhow!("Not mocked"))))),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error("Uncaught Error: Not mocked".into())
+    );
+}
+
+#[tokio::test]
+async fn fetch_cache_revalidate() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/config"),
+            not(request::headers(contains(key("if-none-match"))))
+        ])
+        .respond_with(
+            status_code(200)
+                .append_header("etag", "\"v1\"")
+                .body("Hello, World"),
+        ),
+    );
+    server.expect(
+        Expectation::matching(all_of![
+            request::method_path("GET", "/config"),
+            request::headers(contains(("if-none-match", "\"v1\"")))
+        ])
+        .respond_with(status_code(304).append_header("etag", "\"v1\"")),
+    );
+    let url = server.url("/config");
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const first = await fetch('{url}').then(res => res.text());
+    const second = await fetch('{url}');
+
+    return new Response(`${{first}} ${{second.status}} ${{await second.text()}}`);
+}}"
+        ))
+        .fetch_cache_size(1024),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello, World 200 Hello, World"))
+    );
+}
+
+#[tokio::test]
+async fn fetch_cache_per_isolate() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(3)
+            .respond_with(
+                status_code(200)
+                    .append_header("cache-control", "max-age=60")
+                    .body("Hello, World"),
+            ),
+    );
+    let url = server.url("/");
+    let code = format!(
+        "export async function handler() {{
+    const cached = await fetch('{url}').then(res => res.text());
+    const reloaded = await fetch('{url}', {{ cache: 'reload' }}).then(res => res.text());
+    const fresh = await fetch('{url}').then(res => res.text());
+
+    return new Response(`${{cached}} ${{reloaded}} ${{fresh}}`);
+}}"
+    );
+
+    // The second isolate doesn't see the responses cached by the first one
+    for _ in 0..2 {
+        let (send, receiver) =
+            utils::create_isolate(IsolateOptions::new(code.clone()).fetch_cache_size(1024));
+        send(Request::default());
+
+        assert_eq!(
+            receiver.recv_async().await.unwrap(),
+            RunResult::Response(Response::from("Hello, World Hello, World Hello, World"))
+        );
+    }
+}
+
+#[tokio::test]
+async fn fetch_cache_disabled() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(2)
+            .respond_with(
+                status_code(200)
+                    .append_header("cache-control", "max-age=60")
+                    .body("Hello, World"),
+            ),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    await fetch('{url}', {{ cache: 'force-cache' }}).then(res => res.text());
+    await fetch('{url}', {{ cache: 'force-cache' }}).then(res => res.text());
+
+    return new Response('Done');
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Done"))
+    );
+}
+
+#[tokio::test]
+async fn fetch_integrity() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(2)
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let url = server.url("/");
+
+    // Only the strongest algorithm is checked: the sha256 hash is valid, but not the sha384 one
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('{url}', {{
+        integrity: 'sha256-A2daxT/5zRU1zMffzfosRYxSGDcfQY3BNvLRmsH76KU=',
+    }}).then(res => res.text());
+    const error = await fetch('{url}', {{
+        integrity: 'sha256-A2daxT/5zRU1zMffzfosRYxSGDcfQY3BNvLRmsH76KU= sha384-invalid',
+    }}).catch(error => `${{error.name}}: ${{error.message}}`);
+
+    return new Response(`${{body}} ${{error}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            format!("Hello, World TypeError: The response of {url} does not match the integrity sha256-A2daxT/5zRU1zMffzfosRYxSGDcfQY3BNvLRmsH76KU= sha384-invalid").as_str()
+        ))
     );
 }
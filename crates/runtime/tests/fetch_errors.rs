@@ -0,0 +1,191 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use std::{future::Future, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+mod utils;
+
+// Returns whether the fetch() call rejected with a TypeError, its code, whether
+// it has a cause, and its message
+fn fetch_error(url: &str, init: &str) -> IsolateOptions {
+    IsolateOptions::new(format!(
+        "export async function handler() {{
+    const error = await fetch('{url}', {init})
+        .then(res => res.text())
+        .catch(error => error);
+
+    return new Response(
+        `${{error instanceof TypeError}} ${{error.code}} ${{error.cause instanceof Error}}: ${{error.message}}`
+    );
+}}"
+    ))
+    .timeout(Duration::from_secs(1))
+}
+
+// A server handling each connection with `handle`, to simulate failures
+async fn run_server<F, Fut>(handle: F) -> u16
+where
+    F: Fn(TcpStream) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(handle(stream));
+        }
+    });
+
+    port
+}
+
+async fn read_request_head(stream: &mut TcpStream) {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => head.extend_from_slice(&buf[..read]),
+        }
+    }
+}
+
+#[tokio::test]
+async fn dns_error() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(fetch_error("http://doesnotexist.invalid", "{}"));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "true ENOTFOUND true: Could not resolve the host of http://doesnotexist.invalid"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn connection_refused() {
+    utils::setup();
+    // Nothing listens on the port once the listener is dropped
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let url = format!("http://127.0.0.1:{port}");
+
+    let (send, receiver) = utils::create_isolate(fetch_error(&url, "{}"));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            format!("true ECONNREFUSED true: The connection to {url} was refused").as_str()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn connection_reset() {
+    utils::setup();
+    let port = run_server(|mut stream| async move {
+        read_request_head(&mut stream).await;
+        // Closing the socket with a zero linger sends a RST instead of a FIN
+        stream.set_linger(Some(Duration::ZERO)).unwrap();
+    })
+    .await;
+    let url = format!("http://127.0.0.1:{port}");
+
+    let (send, receiver) = utils::create_isolate(fetch_error(&url, "{}"));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            format!("true ECONNRESET true: The connection to {url} was reset").as_str()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn timeout() {
+    utils::setup();
+    let port = run_server(|mut stream| async move {
+        read_request_head(&mut stream).await;
+        tokio::time::sleep(Duration::from_secs(2)).await;
+    })
+    .await;
+    let url = format!("http://127.0.0.1:{port}");
+
+    let (send, receiver) = utils::create_isolate(fetch_error(&url, "{ timeout: 50 }"));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "true ETIMEDOUT false: The request timed out after 50ms"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn tls_error() {
+    utils::setup();
+    // The self-signed certificate isn't trusted
+    let certificate = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let port = utils::run_tls_server(
+        certificate.serialize_der().unwrap(),
+        certificate.serialize_private_key_der(),
+    )
+    .await;
+    let url = format!("https://localhost:{port}");
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const error = await fetch('{url}').catch(error => error);
+    const reason = error.message.replace('The TLS handshake with {url} failed: ', '');
+
+    return new Response(`${{error.code}} ${{reason === error.cause.message}} ${{reason.includes('certificate')}}`);
+}}"
+        ))
+        .timeout(Duration::from_secs(1)),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ERR_TLS true true"))
+    );
+}
+
+#[tokio::test]
+async fn body_read_error() {
+    utils::setup();
+    // The connection is closed before sending the whole body
+    let port = run_server(|mut stream| async move {
+        read_request_head(&mut stream).await;
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 100\r\n\r\nHello")
+            .await
+            .unwrap_or(());
+    })
+    .await;
+    let url = format!("http://127.0.0.1:{port}");
+
+    let (send, receiver) = utils::create_isolate(fetch_error(&url, "{}"));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            format!("true ERR_BODY_READ true: Could not read the response body of {url}").as_str()
+        ))
+    );
+}
@@ -0,0 +1,147 @@
+use lagon_runtime_http::{Method, Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use serial_test::serial;
+
+mod utils;
+
+#[tokio::test]
+async fn fetch_listener() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "addEventListener('fetch', event => {
+    event.respondWith(new Response(`${event.request.method} ${event.request.url}`));
+});"
+        .into(),
+    ));
+    send(Request {
+        method: Method::POST,
+        url: "http://localhost/hello".into(),
+        ..Request::default()
+    });
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("POST http://localhost/hello"))
+    );
+}
+
+#[tokio::test]
+async fn fetch_listener_promise() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "addEventListener('fetch', event => {
+    event.respondWith(new Promise(resolve => {
+        setTimeout(() => resolve(new Response('Delayed')), 10);
+    }));
+});"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Delayed"))
+    );
+}
+
+#[tokio::test]
+async fn fetch_listener_first_response() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "addEventListener('fetch', () => {});
+addEventListener('fetch', event => event.respondWith(new Response('First')));
+addEventListener('fetch', event => event.respondWith(new Response('Second')));"
+            .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("First"))
+    );
+}
+
+#[tokio::test]
+async fn fetch_listener_without_response() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "addEventListener('fetch', () => {});".into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error(
+            "Uncaught Error: No fetch listener called respondWith() to provide a response".into()
+        )
+    );
+}
+
+#[tokio::test]
+async fn respond_with_after_dispatch() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "addEventListener('fetch', event => {
+    event.respondWith(new Promise(resolve => {
+        setTimeout(() => {
+            try {
+                event.respondWith(new Response('Late'));
+            } catch (error) {
+                resolve(new Response(error.name));
+            }
+        });
+    }));
+});"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("InvalidStateError"))
+    );
+}
+
+#[tokio::test]
+async fn exported_handler_is_preferred() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "addEventListener('fetch', event => event.respondWith(new Response('Listener')));
+
+export function handler() {
+    return new Response('Handler');
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Handler"))
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn fetch_listener_wait_until() {
+    utils::setup();
+    let log_rx = utils::setup_logger();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "addEventListener('fetch', event => {
+    event.waitUntil(new Promise(resolve => setTimeout(resolve, 20)).then(() => console.log('background')));
+    event.respondWith(new Response('Hello'));
+});"
+            .into(),
+        )
+        .metadata(Some(("".to_owned(), "".to_owned()))),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello"))
+    );
+    assert!(log_rx.is_empty());
+    assert_eq!(log_rx.recv_async().await.unwrap(), "background".to_string());
+}
@@ -0,0 +1,75 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use std::{fs, path::PathBuf};
+
+mod utils;
+
+const HANDLER: &str = "export async function handler(request) {
+    try {
+        const content = await Lagon.fs.readFile(request.headers.get('x-path'));
+        return new Response(new TextDecoder().decode(content));
+    } catch (error) {
+        return new Response(error);
+    }
+}";
+
+fn create_fixtures(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("lagon-fs-{name}"));
+    let fixtures = root.join("fixtures");
+
+    fs::create_dir_all(&fixtures).unwrap();
+    fs::write(fixtures.join("hello.txt"), "Hello world").unwrap();
+    fs::write(root.join("secret.txt"), "Secret").unwrap();
+
+    fixtures
+}
+
+fn read_request(path: &str) -> Request {
+    let mut request = Request::default();
+    request.set_header("x-path".into(), path.into());
+
+    request
+}
+
+#[tokio::test]
+async fn read_file() {
+    utils::setup();
+    let fixtures = create_fixtures("read");
+    let (send, receiver) =
+        utils::create_isolate(IsolateOptions::new(HANDLER.into()).allow_fs(fixtures));
+    send(read_request("hello.txt"));
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+}
+
+#[tokio::test]
+async fn read_file_outside_root() {
+    utils::setup();
+    let fixtures = create_fixtures("outside");
+    let (send, receiver) =
+        utils::create_isolate(IsolateOptions::new(HANDLER.into()).allow_fs(fixtures));
+    send(read_request("../secret.txt"));
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "Could not read ../secret.txt: the file is outside of the allowed directory"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn read_file_not_allowed() {
+    utils::setup();
+    create_fixtures("not-allowed");
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(HANDLER.into()));
+    send(read_request("hello.txt"));
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Filesystem access is not allowed"))
+    );
+}
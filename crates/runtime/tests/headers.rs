@@ -0,0 +1,155 @@
+// A subset of the WPT tests of the Headers class:
+// https://github.com/web-platform-tests/wpt/tree/master/fetch/api/headers
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use std::collections::HashMap;
+
+mod utils;
+
+// Like testharness.js, assertions throw so a failing test returns an error
+async fn run_headers_test(request: Request, test: &str) {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "function assert_equals(actual, expected) {{
+    if (JSON.stringify(actual) !== JSON.stringify(expected)) {{
+        throw new Error(`Expected ${{JSON.stringify(expected)}} but got ${{JSON.stringify(actual)}}`);
+    }}
+}}
+
+function assert_throws_js(constructor, fn) {{
+    try {{
+        fn();
+    }} catch (error) {{
+        if (error instanceof constructor) return;
+        throw new Error(`Expected a ${{constructor.name}} but got ${{error}}`);
+    }}
+
+    throw new Error(`Expected a ${{constructor.name}} to be thrown`);
+}}
+
+export function handler(request) {{
+    {test}
+    return new Response('ok');
+}}"
+    )));
+    send(request);
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ok"))
+    );
+}
+
+#[tokio::test]
+async fn headers_casing() {
+    run_headers_test(
+        Request::default(),
+        "const headers = new Headers({ 'Content-Type': 'text/plain' });
+    assert_equals(headers.get('content-type'), 'text/plain');
+    assert_equals(headers.get('CONTENT-TYPE'), 'text/plain');
+    assert_equals(headers.has('Content-type'), true);
+
+    headers.set('CONTENT-type', 'text/html');
+    assert_equals([...headers], [['content-type', 'text/html']]);
+
+    headers.delete('content-TYPE');
+    assert_equals(headers.has('content-type'), false);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn request_headers_casing() {
+    let mut headers = HashMap::new();
+    headers.insert("content-type".into(), vec!["application/json".into()]);
+
+    run_headers_test(
+        Request {
+            headers: Some(headers),
+            ..Request::default()
+        },
+        "assert_equals(request.headers.get('Content-Type'), 'application/json');
+    assert_equals(request.headers.has('CONTENT-TYPE'), true);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn headers_combine() {
+    run_headers_test(
+        Request::default(),
+        "const headers = new Headers([['Vary', 'Accept']]);
+    headers.append('vary', 'Origin');
+    assert_equals(headers.get('vary'), 'Accept, Origin');
+
+    headers.append('Set-Cookie', 'greeting=hello');
+    headers.append('set-cookie', 'name=world');
+    assert_equals(headers.get('set-cookie'), 'greeting=hello, name=world');
+    assert_equals(headers.getSetCookie(), ['greeting=hello', 'name=world']);
+    assert_equals([...headers], [
+        ['set-cookie', 'greeting=hello'],
+        ['set-cookie', 'name=world'],
+        ['vary', 'Accept, Origin'],
+    ]);
+
+    headers.set('vary', 'Cookie');
+    assert_equals(headers.get('vary'), 'Cookie');",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn headers_sorted_iteration() {
+    run_headers_test(
+        Request::default(),
+        "const headers = new Headers({
+        'xylophone-header': '1',
+        'WhateverHeader': '2',
+        'Content-Type': '3',
+        'a-': '4',
+    });
+    const entries = [];
+    headers.forEach((value, key) => entries.push([key, value]));
+
+    assert_equals([...headers.keys()], ['a-', 'content-type', 'whateverheader', 'xylophone-header']);
+    assert_equals([...headers.values()], ['4', '3', '2', '1']);
+    assert_equals(entries, [...headers.entries()]);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn headers_normalize() {
+    run_headers_test(
+        Request::default(),
+        "const headers = new Headers({ 'x-name': ' \\t\\r\\n value \\n' });
+    assert_equals(headers.get('x-name'), 'value');
+
+    headers.append('x-empty', '  ');
+    assert_equals(headers.get('x-empty'), '');
+    assert_equals(headers.get('x-missing'), null);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn headers_errors() {
+    run_headers_test(
+        Request::default(),
+        "assert_throws_js(TypeError, () => new Headers([['Header Name', 'value']]));
+    assert_throws_js(TypeError, () => new Headers({ 'Héader': 'value' }));
+    assert_throws_js(TypeError, () => new Headers([['name']]));
+    assert_throws_js(TypeError, () => new Headers(null));
+
+    const headers = new Headers();
+    assert_throws_js(TypeError, () => headers.append('', 'value'));
+    assert_throws_js(TypeError, () => headers.append('name', 'a\\0b'));
+    assert_throws_js(TypeError, () => headers.set('name', 'a\\r\\nb'));
+    assert_throws_js(TypeError, () => headers.get('@'));
+    assert_throws_js(TypeError, () => headers.has('invalid name'));
+    assert_throws_js(TypeError, () => headers.delete('invalid:name'));
+
+    assert_throws_js(TypeError, () => Response.error().headers.set('name', 'value'));",
+    )
+    .await;
+}
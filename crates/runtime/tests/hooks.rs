@@ -0,0 +1,190 @@
+use futures::StreamExt;
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{hooks::IsolateHookEvent, options::IsolateOptions};
+use std::time::Duration;
+
+mod utils;
+
+// The events received by the hook, without the durations
+#[derive(Debug, PartialEq)]
+enum Event {
+    Created,
+    EvaluationStarted,
+    EvaluationFinished(Option<String>),
+    RequestStarted(u32),
+    RequestFinished(u32, RunResult),
+    Terminated(Option<RunResult>),
+}
+
+fn with_hook(options: IsolateOptions) -> (IsolateOptions, flume::Receiver<Event>) {
+    let (tx, rx) = flume::unbounded();
+    let options = options.hook(Box::new(move |_, event| {
+        let event = match event {
+            IsolateHookEvent::Created => Event::Created,
+            IsolateHookEvent::EvaluationStarted => Event::EvaluationStarted,
+            IsolateHookEvent::EvaluationFinished { error, .. } => {
+                Event::EvaluationFinished(error.map(String::from))
+            }
+            IsolateHookEvent::RequestStarted { id } => Event::RequestStarted(*id),
+            IsolateHookEvent::RequestFinished { id, result, .. } => {
+                Event::RequestFinished(*id, (*result).clone())
+            }
+            IsolateHookEvent::Terminated { result } => Event::Terminated(result.cloned()),
+        };
+
+        tx.send(event).unwrap();
+    }));
+
+    (options, rx)
+}
+
+#[tokio::test]
+async fn hooks_lifecycle() {
+    utils::setup();
+    let (options, events) = with_hook(IsolateOptions::new(
+        "export function handler() {
+    return new Response('Hello world');
+}"
+        .into(),
+    ));
+    let (send, receiver) = utils::create_isolate(options);
+
+    for _ in 0..2 {
+        send(Request::default());
+        assert_eq!(
+            receiver.recv_async().await.unwrap(),
+            RunResult::Response(Response::from("Hello world"))
+        );
+    }
+
+    // Stops the event loop once the requests are handled
+    drop(send);
+
+    assert_eq!(
+        events.into_stream().collect::<Vec<_>>().await,
+        vec![
+            Event::Created,
+            Event::EvaluationStarted,
+            Event::EvaluationFinished(None),
+            Event::RequestStarted(1),
+            Event::RequestFinished(1, RunResult::Response(Response::from("Hello world"))),
+            Event::RequestStarted(2),
+            Event::RequestFinished(2, RunResult::Response(Response::from("Hello world"))),
+            Event::Terminated(None),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn hooks_evaluation_error() {
+    utils::setup();
+    let (options, events) = with_hook(IsolateOptions::new(
+        "export function handler() {
+    this syntax is invalid
+}"
+        .into(),
+    ));
+    let (send, receiver) = utils::create_isolate(options);
+    let error = "Uncaught SyntaxError: Unexpected identifier 'syntax'";
+
+    send(Request::default());
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error(error.into())
+    );
+
+    assert_eq!(events.recv_async().await.unwrap(), Event::Created);
+    assert_eq!(events.recv_async().await.unwrap(), Event::EvaluationStarted);
+    assert_eq!(
+        events.recv_async().await.unwrap(),
+        Event::EvaluationFinished(Some(error.into()))
+    );
+    // The handler is never called
+    assert_eq!(events.recv_async().await.unwrap(), Event::Terminated(None));
+}
+
+#[tokio::test]
+async fn hooks_timeout() {
+    utils::setup();
+    let (options, events) = with_hook(IsolateOptions::new(
+        "export function handler() {
+    while(true) {}
+    return new Response('Should not be reached');
+}"
+        .into(),
+    ));
+    let (send, receiver) = utils::create_isolate(options);
+
+    send(Request::default());
+    assert_eq!(receiver.recv_async().await.unwrap(), RunResult::Timeout);
+
+    let events = events.into_stream().collect::<Vec<_>>().await;
+    assert_eq!(
+        events[3..],
+        [
+            Event::RequestStarted(1),
+            Event::RequestFinished(1, RunResult::Timeout),
+            Event::Terminated(Some(RunResult::Timeout)),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn hooks_request_duration() {
+    utils::setup();
+    let (tx, rx) = flume::unbounded();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    await new Promise(resolve => setTimeout(resolve, 100));
+
+    return new Response('Hello world');
+}"
+            .into(),
+        )
+        .hook(Box::new(move |_, event| {
+            if let IsolateHookEvent::RequestFinished {
+                duration, cpu_time, ..
+            } = event
+            {
+                tx.send((*duration, *cpu_time)).unwrap();
+            }
+        })),
+    );
+
+    send(Request::default());
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+
+    let (duration, cpu_time) = rx.recv_async().await.unwrap();
+    assert!(duration >= Duration::from_millis(100));
+    // Waiting for the timer doesn't use CPU time
+    assert!(cpu_time < Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn hooks_panic() {
+    utils::setup();
+    let (options, events) = with_hook(
+        IsolateOptions::new(
+            "export function handler() {
+    return new Response('Hello world');
+}"
+            .into(),
+        )
+        .hook(Box::new(|_, _| panic!("Hook panicked"))),
+    );
+    let (send, receiver) = utils::create_isolate(options);
+
+    send(Request::default());
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+
+    // The hooks registered after the panicking one are still called
+    drop(send);
+    assert_eq!(events.into_stream().collect::<Vec<_>>().await.len(), 6);
+}
@@ -0,0 +1,161 @@
+use hyper::{server::conn::Http, service::service_fn, Body};
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use std::{
+    convert::Infallible,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tokio_rustls::{
+    rustls::{Certificate, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
+
+mod utils;
+
+const LARGE_BODY_SIZE: usize = 5 * 1024 * 1024;
+
+async fn handle(request: hyper::Request<Body>) -> Result<hyper::Response<Body>, Infallible> {
+    let body = match request.uri().path() {
+        "/large" => Body::from(vec![b'a'; LARGE_BODY_SIZE]),
+        _ => {
+            // Concurrent requests only complete together if they are multiplexed
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Body::from(format!("{:?}", request.version()))
+        }
+    };
+
+    Ok(hyper::Response::new(body))
+}
+
+// A TLS server negotiating HTTP/2 or HTTP/1.1 with ALPN, that counts its connections
+async fn run_server() -> (String, Arc<AtomicUsize>) {
+    let (certificate, private_key) = utils::setup_certificate();
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(vec![Certificate(certificate)], PrivateKey(private_key))
+        .unwrap();
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!(
+        "https://localhost:{}",
+        listener.local_addr().unwrap().port()
+    );
+    let connections = Arc::new(AtomicUsize::new(0));
+    let count = Arc::clone(&connections);
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            count.fetch_add(1, Ordering::SeqCst);
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                if let Ok(stream) = acceptor.accept(stream).await {
+                    Http::new()
+                        .serve_connection(stream, service_fn(handle))
+                        .await
+                        .unwrap_or(());
+                }
+            });
+        }
+    });
+
+    (url, connections)
+}
+
+#[tokio::test]
+async fn multiplex_requests() {
+    utils::setup();
+    let (url, connections) = run_server().await;
+
+    // The first request negotiates HTTP/2, which the next ones reuse
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const version = await fetch('{url}').then(res => res.text());
+    const start = Date.now();
+    const versions = await Promise.all(Array.from({{ length: 10 }}, () => fetch('{url}').then(res => res.text())));
+
+    return new Response(`${{version}} ${{versions.every(other => other === version)}} ${{Date.now() - start < 500}}`);
+}}"
+        ))
+        .timeout(Duration::from_secs(5)),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("HTTP/2.0 true true"))
+    );
+    assert_eq!(connections.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn large_body() {
+    utils::setup();
+    let (url, _) = run_server().await;
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const reader = (await fetch('{url}/large')).body.getReader();
+    let size = 0;
+    let chunks = 0;
+    let valid = true;
+
+    while (true) {{
+        const {{ done, value }} = await reader.read();
+
+        if (done) {{
+            break;
+        }}
+
+        size += value.length;
+        chunks++;
+        valid &&= value[0] === 97 && value[value.length - 1] === 97;
+    }}
+
+    return new Response(`${{size}} ${{chunks > 1}} ${{valid}}`);
+}}"
+        ))
+        .timeout(Duration::from_secs(5)),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            format!("{LARGE_BODY_SIZE} true true").as_str()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn http1_only() {
+    utils::setup();
+    let (url, _) = run_server().await;
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const version = await fetch('{url}').then(res => res.text());
+    return new Response(version);
+}}"
+        ))
+        .timeout(Duration::from_secs(5))
+        .fetch_http1_only(true),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("HTTP/1.1"))
+    );
+}
@@ -0,0 +1,295 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{
+    kv::{KvBackend, KvValue, MemoryKvBackend},
+    options::IsolateOptions,
+};
+use std::{sync::Arc, time::Duration};
+
+mod utils;
+
+// Run the handler in a new isolate using this backend, if any
+async fn run(handler: &str, backend: Option<Arc<MemoryKvBackend>>) -> RunResult {
+    let mut options = IsolateOptions::new(format!(
+        "export async function handler() {{
+    {handler}
+}}"
+    ));
+
+    if let Some(backend) = backend {
+        options = options.kv_backend(backend);
+    }
+
+    let (send, receiver) = utils::create_isolate(options);
+    send(Request::default());
+
+    receiver.recv_async().await.unwrap()
+}
+
+#[tokio::test]
+async fn kv_get_put_string() {
+    utils::setup();
+    let backend = Arc::new(MemoryKvBackend::new());
+
+    assert_eq!(
+        run(
+            "await Lagon.kv.put('hello', 'Hello, World');
+    const hello = await Lagon.kv.get('hello');
+    const missing = await Lagon.kv.get('missing');
+
+    return new Response(`${typeof hello} ${hello} ${missing}`);",
+            Some(Arc::clone(&backend)),
+        )
+        .await,
+        RunResult::Response(Response::from("string Hello, World null"))
+    );
+
+    assert_eq!(
+        backend.get("hello").await.unwrap(),
+        Some(KvValue::String("Hello, World".into()))
+    );
+}
+
+#[tokio::test]
+async fn kv_get_put_array_buffer() {
+    utils::setup();
+    let backend = Arc::new(MemoryKvBackend::new());
+
+    assert_eq!(
+        run(
+            "await Lagon.kv.put('buffer', new Uint8Array([1, 2, 3]).buffer);
+    await Lagon.kv.put('view', new Uint8Array([0, 4, 5, 6]).subarray(1));
+    const buffer = await Lagon.kv.get('buffer');
+    const view = await Lagon.kv.get('view');
+
+    return new Response(`${buffer instanceof ArrayBuffer} ${new Uint8Array(buffer)} ${new Uint8Array(view)}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("true 1,2,3 4,5,6"))
+    );
+}
+
+#[tokio::test]
+async fn kv_shared_between_isolates() {
+    utils::setup();
+    let backend = Arc::new(MemoryKvBackend::new());
+
+    assert_eq!(
+        run(
+            "await Lagon.kv.put('hello', 'Hello, World');
+    return new Response('Stored');",
+            Some(Arc::clone(&backend)),
+        )
+        .await,
+        RunResult::Response(Response::from("Stored"))
+    );
+
+    assert_eq!(
+        run(
+            "return new Response(await Lagon.kv.get('hello'));",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("Hello, World"))
+    );
+}
+
+#[tokio::test]
+async fn kv_ttl() {
+    utils::setup();
+    let backend = Arc::new(MemoryKvBackend::new());
+    let get_hello = "return new Response(`${await Lagon.kv.get('hello')}`);";
+
+    assert_eq!(
+        run(
+            "await Lagon.kv.put('hello', 'Hello, World', { ttl: 500 });
+    const error = await Lagon.kv.put('other', 'value', { ttl: -1 }).catch(error => error);
+
+    return new Response(`${error instanceof Error} ${error.message}`);",
+            Some(Arc::clone(&backend)),
+        )
+        .await,
+        RunResult::Response(Response::from(
+            "true The ttl must be a positive number of milliseconds"
+        ))
+    );
+
+    assert_eq!(
+        run(get_hello, Some(Arc::clone(&backend))).await,
+        RunResult::Response(Response::from("Hello, World"))
+    );
+
+    tokio::time::sleep(Duration::from_millis(600)).await;
+
+    assert_eq!(
+        run(get_hello, Some(Arc::clone(&backend))).await,
+        RunResult::Response(Response::from("null"))
+    );
+
+    assert_eq!(backend.list("", 10, None).await.unwrap().keys.len(), 0);
+}
+
+#[tokio::test]
+async fn kv_delete() {
+    utils::setup();
+    let backend = Arc::new(MemoryKvBackend::new());
+
+    assert_eq!(
+        run(
+            "await Lagon.kv.put('hello', 'Hello, World');
+    await Lagon.kv.delete('hello');
+    await Lagon.kv.delete('missing');
+
+    return new Response(`${await Lagon.kv.get('hello')}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from("null"))
+    );
+}
+
+#[tokio::test]
+async fn kv_list() {
+    utils::setup();
+    let backend = Arc::new(MemoryKvBackend::new());
+
+    assert_eq!(
+        run(
+            "for (const key of ['user:3', 'user:1', 'post:1', 'user:2']) {
+        await Lagon.kv.put(key, key);
+    }
+
+    const all = await Lagon.kv.list();
+    const first = await Lagon.kv.list('user:', 2);
+    const next = await Lagon.kv.list('user:', 2, first.cursor);
+
+    return new Response(JSON.stringify([all, first, next]));",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from(
+            r#"[{"keys":["post:1","user:1","user:2","user:3"],"cursor":null},{"keys":["user:1","user:2"],"cursor":"user:2"},{"keys":["user:3"],"cursor":null}]"#
+        ))
+    );
+}
+
+#[tokio::test]
+async fn kv_invalid_arguments() {
+    utils::setup();
+    let backend = Arc::new(MemoryKvBackend::new());
+
+    assert_eq!(
+        run(
+            "const message = promise => promise.then(() => 'Resolved', error => error.message);
+
+    const key = await message(Lagon.kv.get(''));
+    const value = await message(Lagon.kv.put('hello', { hello: 'world' }));
+    const limit = await message(Lagon.kv.list('', 1001));
+
+    return new Response(`${key}, ${value}, ${limit}`);",
+            Some(backend),
+        )
+        .await,
+        RunResult::Response(Response::from(
+            "Keys must be between 1 and 512 bytes long, Values must be strings, ArrayBuffers or ArrayBuffer views, The limit must be a number between 1 and 1000"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn kv_max_value_size() {
+    utils::setup();
+    let backend = Arc::new(MemoryKvBackend::new());
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    const error = await Lagon.kv.put('large', new Uint8Array(11)).catch(error => error);
+    await Lagon.kv.put('small', new Uint8Array(10));
+
+    return new Response(`${error instanceof Error} ${error.message} ${await Lagon.kv.get('large')}`);
+}"
+            .into(),
+        )
+        .kv_backend(Arc::clone(&backend))
+        .kv_max_value_size(10),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "true The value of large is 11 bytes, larger than the maximum of 10 bytes null"
+        ))
+    );
+
+    assert_eq!(
+        backend.get("small").await.unwrap(),
+        Some(KvValue::Bytes(vec![0; 10]))
+    );
+}
+
+#[tokio::test]
+async fn kv_without_backend() {
+    utils::setup();
+
+    assert_eq!(
+        run(
+            "const error = await Lagon.kv.get('hello').catch(error => error);
+    return new Response(`${error}`);",
+            None,
+        )
+        .await,
+        RunResult::Response(Response::from("Lagon.kv is not available in this runtime"))
+    );
+}
+
+#[tokio::test]
+async fn memory_backend_persisted() {
+    let path = std::env::temp_dir()
+        .join(format!("lagon-kv-{}", std::process::id()))
+        .join("kv.json");
+    let _ = std::fs::remove_file(&path);
+
+    let backend = MemoryKvBackend::persisted(path.clone()).unwrap();
+    backend
+        .put("string", KvValue::String("Hello".into()), None)
+        .await
+        .unwrap();
+    backend
+        .put("bytes", KvValue::Bytes(vec![1, 2, 3]), None)
+        .await
+        .unwrap();
+    backend
+        .put(
+            "expired",
+            KvValue::String("Expired".into()),
+            Some(Duration::from_millis(1)),
+        )
+        .await
+        .unwrap();
+    backend
+        .put("deleted", KvValue::String("Deleted".into()), None)
+        .await
+        .unwrap();
+    backend.delete("deleted").await.unwrap();
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+
+    let backend = MemoryKvBackend::persisted(path.clone()).unwrap();
+
+    assert_eq!(
+        backend.get("string").await.unwrap(),
+        Some(KvValue::String("Hello".into()))
+    );
+    assert_eq!(
+        backend.get("bytes").await.unwrap(),
+        Some(KvValue::Bytes(vec![1, 2, 3]))
+    );
+    assert_eq!(
+        backend.list("", 10, None).await.unwrap().keys,
+        vec!["bytes", "string"]
+    );
+
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
@@ -0,0 +1,88 @@
+use lagon_runtime_http::{HeapStatistics, Request, Response, RunResult};
+use lagon_runtime_isolate::options::{IsolateOptions, Metadata};
+use std::{
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+mod utils;
+
+// Allocates 512KB on the heap (unlike typed arrays) between
+// each timer, so the event loop can check the heap in between
+const ALLOCATE_HANDLER: &str = "export async function handler() {
+    const storage = [];
+    while (true) {
+        storage.push(new Array(1024 * 64).fill(storage.length));
+        await new Promise(resolve => setTimeout(resolve, 1));
+    }
+
+    return new Response('Should not be reached');
+}";
+
+#[tokio::test]
+async fn memory_pressure_before_limit() {
+    utils::setup();
+    let pressures = Arc::new(Mutex::new(Vec::<HeapStatistics>::new()));
+    let on_memory_pressure = {
+        let pressures = Arc::clone(&pressures);
+        move |_: Rc<Metadata>, statistics: HeapStatistics| {
+            pressures.lock().unwrap().push(statistics)
+        }
+    };
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(ALLOCATE_HANDLER.into())
+            .memory(16)
+            .memory_pressure_threshold(50)
+            .on_memory_pressure_callback(Box::new(on_memory_pressure))
+            // Increase timeout for CI
+            .timeout(Duration::from_secs(10))
+            .startup_timeout(Duration::from_secs(10)),
+    );
+    send(Request::default());
+
+    let statistics = match receiver.recv_async().await.unwrap() {
+        RunResult::MemoryLimit(statistics) => statistics,
+        result => panic!("Unexpected result: {result:?}"),
+    };
+
+    assert!(statistics.used_heap_size > 0);
+    assert!(statistics.total_heap_size >= statistics.used_heap_size);
+    assert!(statistics.heap_size_limit > 0);
+
+    // Called once, when the heap crossed the threshold
+    let pressures = pressures.lock().unwrap();
+    assert_eq!(pressures.len(), 1);
+    assert!(pressures[0].used_heap_size >= pressures[0].heap_size_limit / 2);
+    assert!(pressures[0].used_heap_size <= statistics.used_heap_size);
+}
+
+#[tokio::test]
+async fn memory_pressure_not_reached() {
+    utils::setup();
+    let pressures = Arc::new(Mutex::new(0));
+    let on_memory_pressure = {
+        let pressures = Arc::clone(&pressures);
+        move |_: Rc<Metadata>, _: HeapStatistics| *pressures.lock().unwrap() += 1
+    };
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    await new Promise(resolve => setTimeout(resolve, 1));
+
+    return new Response('Hello world');
+}"
+            .into(),
+        )
+        .on_memory_pressure_callback(Box::new(on_memory_pressure)),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+    assert_eq!(*pressures.lock().unwrap(), 0);
+}
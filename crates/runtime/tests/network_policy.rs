@@ -0,0 +1,369 @@
+use httptest::{matchers::*, responders::*, Expectation, Server};
+use hyper::{client::HttpConnector, service::Service};
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{
+    network_policy::{NetworkPolicy, NetworkPolicyViolation, PolicyResolver},
+    options::{IsolateOptions, Metadata},
+    proxy::{Proxy, ProxyConnector},
+};
+use std::{
+    net::{IpAddr, Ipv4Addr},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::net::TcpListener;
+
+mod utils;
+
+fn fetch_error(url: &str) -> String {
+    format!(
+        "export async function handler() {{
+    const body = await fetch('{url}')
+        .then(res => res.text())
+        .catch(error => `${{error.name}}: ${{error.message}}`);
+
+    return new Response(body);
+}}"
+    )
+}
+
+#[tokio::test]
+async fn denied_hosts() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(fetch_error("https://api.example.com")).network_policy(NetworkPolicy {
+            denied_hosts: vec!["*.example.com".into()],
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "TypeError: The host api.example.com is not allowed by the network policy"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn allowed_hosts() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const body = await fetch('{url}').then(res => res.text());
+    const denied = await fetch('https://example.com').catch(error => error.message);
+
+    return new Response(`${{body}} ${{denied}}`);
+}}"
+        ))
+        .network_policy(NetworkPolicy {
+            allowed_hosts: vec!["127.0.0.1".into()],
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "Hello, World The host example.com is not allowed by the network policy"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn block_private_ips() {
+    utils::setup();
+    let server = Server::run();
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(fetch_error(&url.to_string())).network_policy(NetworkPolicy {
+            block_private_ips: true,
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "TypeError: The host 127.0.0.1 resolves to the private IP 127.0.0.1, which is blocked by the network policy"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn block_private_ips_after_resolution() {
+    utils::setup();
+    let server = Server::run();
+    let url = format!("http://localhost:{}/", server.addr().port());
+
+    // localhost resolves to both 127.0.0.1 and ::1, which are both blocked
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const error = await fetch('{url}').catch(error => error);
+    const message = 'The host localhost resolves to the private IP';
+
+    return new Response(`${{error.name}} ${{error.message.startsWith(message)}}`);
+}}"
+        ))
+        .network_policy(NetworkPolicy {
+            block_private_ips: true,
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("TypeError true"))
+    );
+}
+
+#[tokio::test]
+async fn denied_cidrs() {
+    utils::setup();
+    let server = Server::run();
+    let url = server.url("/");
+
+    // Denied CIDRs take precedence over the allowed ones
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(fetch_error(&url.to_string())).network_policy(NetworkPolicy {
+            allowed_cidrs: vec!["127.0.0.0/8".parse().unwrap()],
+            denied_cidrs: vec!["127.0.0.1/32".parse().unwrap()],
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "TypeError: The host 127.0.0.1 resolves to 127.0.0.1, which is denied by the network policy"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn redirect_to_private_ip() {
+    utils::setup();
+    let server = Server::run();
+    let port = server.addr().port();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/")).respond_with(
+            status_code(302).append_header("location", format!("http://127.0.0.2:{port}/")),
+        ),
+    );
+    let url = server.url("/");
+
+    // The first request is allowed, but the redirect goes to another private IP
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(fetch_error(&url.to_string())).network_policy(NetworkPolicy {
+            allowed_cidrs: vec!["127.0.0.1/32".parse().unwrap()],
+            block_private_ips: true,
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "TypeError: The host 127.0.0.2 resolves to the private IP 127.0.0.2, which is blocked by the network policy"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn max_response_size() {
+    utils::setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .times(2)
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let url = server.url("/");
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(fetch_error(&url.to_string())).network_policy(NetworkPolicy {
+            max_response_size: Some(5),
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            format!("TypeError: The response of {url} is larger than the maximum size of 5 bytes")
+                .as_str()
+        ))
+    );
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(fetch_error(&url.to_string())).network_policy(NetworkPolicy {
+            max_response_size: Some(12),
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello, World"))
+    );
+}
+
+#[tokio::test]
+async fn on_network_violation() {
+    utils::setup();
+    let server = Server::run();
+    let url = server.url("/");
+    let violations = Arc::new(Mutex::new(Vec::new()));
+    let on_violation = Arc::clone(&violations);
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(fetch_error(&url.to_string()))
+            .metadata(Some(("deployment".into(), "function".into())))
+            .network_policy(NetworkPolicy {
+                block_private_ips: true,
+                ..Default::default()
+            })
+            .on_network_violation_callback(Rc::new(
+                move |metadata: Rc<Metadata>, violation: &NetworkPolicyViolation| {
+                    on_violation
+                        .lock()
+                        .unwrap()
+                        .push((metadata.as_ref().clone(), violation.clone()));
+                },
+            )),
+    );
+    send(Request::default());
+    receiver.recv_async().await.unwrap();
+
+    assert_eq!(
+        *violations.lock().unwrap(),
+        vec![(
+            Some(("deployment".into(), "function".into())),
+            NetworkPolicyViolation::PrivateIp {
+                host: "127.0.0.1".into(),
+                ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            }
+        )]
+    );
+}
+
+#[tokio::test]
+async fn proxied_private_ip() {
+    // Counts the connections to the proxy, which never answers
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy_url = format!("http://{}", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let proxy_connections = Arc::clone(&connections);
+
+    tokio::spawn(async move {
+        while listener.accept().await.is_ok() {
+            proxy_connections.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let resolver = PolicyResolver::new(Arc::new(NetworkPolicy {
+        block_private_ips: true,
+        ..Default::default()
+    }));
+    let mut connector = ProxyConnector::new(
+        HttpConnector::new_with_resolver(resolver.clone()),
+        resolver,
+        Arc::new(Proxy::all(proxy_url.parse().unwrap())),
+    );
+
+    // Both forwarded and tunneled requests are checked before reaching the proxy
+    for url in ["http://localhost:1234/", "https://localhost:1234/"] {
+        let error = match connector.call(url.parse().unwrap()).await {
+            Ok(_) => panic!("{url} should not be proxied"),
+            Err(error) => error,
+        };
+
+        assert!(matches!(
+            error.downcast_ref::<NetworkPolicyViolation>(),
+            Some(NetworkPolicyViolation::PrivateIp { host, .. }) if host == "localhost"
+        ));
+    }
+
+    assert_eq!(connections.load(Ordering::SeqCst), 0);
+}
+
+#[tokio::test]
+async fn websocket_private_ip() {
+    utils::setup();
+    // Counts the connections, the WebSocket should never reach it
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let connections = Arc::new(AtomicUsize::new(0));
+    let server_connections = Arc::clone(&connections);
+
+    tokio::spawn(async move {
+        while listener.accept().await.is_ok() {
+            server_connections.fetch_add(1, Ordering::SeqCst);
+        }
+    });
+
+    let violations = Arc::new(Mutex::new(Vec::new()));
+    let on_violation = Arc::clone(&violations);
+
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('{url}');
+        const events = [];
+
+        websocket.onopen = () => events.push('open');
+        websocket.onerror = () => events.push('error');
+        websocket.onclose = event => {{
+            events.push(`close ${{event.code}}`);
+            resolve(new Response(events.join(',')));
+        }};
+    }});
+}}"
+        ))
+        .network_policy(NetworkPolicy {
+            block_private_ips: true,
+            ..Default::default()
+        })
+        .on_network_violation_callback(Rc::new(
+            move |_: Rc<Metadata>, violation: &NetworkPolicyViolation| {
+                on_violation.lock().unwrap().push(violation.clone());
+            },
+        )),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("error,close 1006"))
+    );
+    assert_eq!(
+        *violations.lock().unwrap(),
+        vec![NetworkPolicyViolation::PrivateIp {
+            host: "127.0.0.1".into(),
+            ip: IpAddr::V4(Ipv4Addr::LOCALHOST),
+        }]
+    );
+    assert_eq!(connections.load(Ordering::SeqCst), 0);
+}
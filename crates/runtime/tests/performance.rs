@@ -0,0 +1,193 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+
+mod utils;
+
+// Assertions throw so a failing test rejects, like in compression.rs
+async fn run_performance_test(test: &str) {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "function assert_equals(actual, expected) {{
+    if (JSON.stringify(actual) !== JSON.stringify(expected)) {{
+        throw new Error(`Expected ${{JSON.stringify(expected)}} but got ${{JSON.stringify(actual)}}`);
+    }}
+}}
+
+function assert_throws(name, callback) {{
+    try {{
+        callback();
+    }} catch (error) {{
+        assert_equals(error.name, name);
+        return;
+    }}
+
+    throw new Error(`Expected a ${{name}}`);
+}}
+
+export function handler() {{
+    {test}
+    return new Response('ok');
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ok"))
+    );
+}
+
+#[tokio::test]
+async fn now() {
+    run_performance_test(
+        "const values = Array.from({ length: 1000 }, () => performance.now());
+
+    assert_equals(values.every((value, index) => index === 0 || value >= values[index - 1]), true);
+    assert_equals(values[0] > 0, true);
+    // The resolution is higher than a millisecond
+    assert_equals(values.some(value => !Number.isInteger(value)), true);
+
+    assert_equals(Math.abs(performance.timeOrigin + performance.now() - Date.now()) < 50, true);
+    assert_equals(performance.toJSON().timeOrigin, performance.timeOrigin);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn now_coarse_timers() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export function handler() {
+    const now = performance.now() * 10;
+    return new Response(`${Math.abs(now - Math.round(now)) < 0.000001}`);
+}"
+            .into(),
+        )
+        .coarse_timers(true),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("true"))
+    );
+}
+
+#[tokio::test]
+async fn mark() {
+    run_performance_test(
+        "const first = performance.mark('first');
+    const second = performance.mark('second', { detail: { hello: 'world' } });
+    const custom = performance.mark('custom', { startTime: 0 });
+
+    assert_equals(first instanceof PerformanceMark && first instanceof PerformanceEntry, true);
+    assert_equals(first.entryType, 'mark');
+    assert_equals(first.duration, 0);
+    assert_equals(first.detail, null);
+    assert_equals(second.startTime >= first.startTime, true);
+    assert_equals(second.detail, { hello: 'world' });
+    assert_equals(custom.startTime, 0);
+    assert_equals(new PerformanceMark('standalone').toJSON().entryType, 'mark');
+
+    // Entries are ordered by start time
+    assert_equals(performance.getEntries().map(entry => entry.name), ['custom', 'first', 'second']);
+    assert_equals(performance.getEntriesByType('mark').length, 3);
+    assert_equals(performance.getEntriesByName('first'), [first]);
+    assert_equals(performance.getEntriesByName('first', 'measure'), []);
+
+    assert_throws('TypeError', () => performance.mark('negative', { startTime: -1 }));",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn measure() {
+    run_performance_test(
+        "performance.mark('start', { startTime: 10 });
+    performance.mark('end', { startTime: 25 });
+
+    const measure = performance.measure('marks', 'start', 'end');
+    assert_equals(measure instanceof PerformanceMeasure, true);
+    assert_equals(measure.entryType, 'measure');
+    assert_equals([measure.startTime, measure.duration], [10, 15]);
+
+    const options = [
+        [{ start: 'start', end: 'end', detail: 1 }, 10, 15],
+        [{ start: 'start', duration: 5 }, 10, 5],
+        [{ end: 'end', duration: 5 }, 20, 5],
+        [{ start: 5, end: 7 }, 5, 2],
+    ];
+
+    for (const [option, startTime, duration] of options) {
+        const measure = performance.measure('options', option);
+        assert_equals([measure.startTime, measure.duration], [startTime, duration]);
+        assert_equals(measure.detail, option.detail ?? null);
+    }
+
+    // Measures end now, and start at the time origin by default
+    const before = performance.now();
+    const fromStart = performance.measure('from start', 'start');
+    assert_equals(fromStart.startTime, 10);
+    assert_equals(fromStart.duration >= before - 10, true);
+    assert_equals(performance.measure('from origin').startTime, 0);
+
+    assert_throws('SyntaxError', () => performance.measure('missing', 'missing'));
+    assert_throws('TypeError', () => performance.measure('end mark', { start: 'start' }, 'end'));
+    assert_throws('TypeError', () => performance.measure('no start and end', { detail: 1 }));
+    assert_throws('TypeError', () => performance.measure('all', { start: 1, end: 2, duration: 1 }));
+    assert_throws('TypeError', () => performance.measure('negative', { start: -1, end: 2 }));",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn clear() {
+    run_performance_test(
+        "performance.mark('a');
+    performance.mark('b');
+    performance.mark('a');
+    performance.measure('a');
+
+    performance.clearMarks('a');
+    assert_equals(performance.getEntries().map(entry => `${entry.entryType} ${entry.name}`), ['measure a', 'mark b']);
+
+    performance.clearMarks();
+    assert_equals(performance.getEntriesByType('mark'), []);
+
+    performance.clearMeasures();
+    assert_equals(performance.getEntries(), []);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn entries_reset_between_requests() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "performance.mark('init');
+
+export async function handler() {
+    performance.mark('request');
+    await new Promise(resolve => setTimeout(resolve, 1));
+    performance.measure('request', 'init', 'request');
+
+    const names = performance.getEntries().map(entry => `${entry.entryType} ${entry.name}`);
+    return new Response(names.join(','));
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("mark init,measure request,mark request"))
+    );
+
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("mark init,measure request,mark request"))
+    );
+}
@@ -0,0 +1,177 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{
+    options::IsolateOptions,
+    pool::{IsolatePool, IsolatePoolOptions},
+    IsolateEvent, IsolateRequest,
+};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+mod utils;
+
+// Each isolate counts the requests it handled
+const COUNTER_HANDLER: &str = "let count = 0;
+
+export function handler(request) {
+    const { pathname } = new URL(request.url);
+
+    if (pathname === '/slow') {
+        const start = Date.now();
+        while (Date.now() - start < 500) {}
+    }
+
+    if (pathname === '/allocate') {
+        const storage = [];
+        while (true) {
+            storage.push(new Uint8Array(1024 * 1024 * 2).fill(1));
+        }
+    }
+
+    return new Response(`${pathname} ${++count}`);
+}";
+
+fn create_pool(
+    options: IsolatePoolOptions,
+    isolate_options: fn(IsolateOptions) -> IsolateOptions,
+) -> flume::Sender<IsolateEvent> {
+    let (tx, rx) = flume::unbounded();
+    let factory = Arc::new(move || {
+        isolate_options(
+            IsolateOptions::new(COUNTER_HANDLER.into())
+                .snapshot_blob(include_bytes!("../../serverless/snapshot.bin")),
+        )
+    });
+
+    tokio::spawn(IsolatePool::new(factory, options, rx).run());
+
+    tx
+}
+
+async fn send(tx: &flume::Sender<IsolateEvent>, path: &str) -> RunResult {
+    let (sender, receiver) = flume::unbounded();
+    tx.send_async(IsolateEvent::Request(IsolateRequest {
+        request: Request {
+            url: format!("http://localhost{path}"),
+            ..Default::default()
+        },
+        sender,
+        statistics: None,
+        body_stream: None,
+        connection: None,
+    }))
+    .await
+    .unwrap();
+
+    receiver.recv_async().await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pool_concurrent_requests() {
+    utils::setup();
+    let tx = create_pool(IsolatePoolOptions::new(2), |options| {
+        options.timeout(Duration::from_secs(1))
+    });
+
+    // Wait for the first isolate to be ready, so its startup isn't measured
+    assert_eq!(
+        send(&tx, "/").await,
+        RunResult::Response(Response::from("/ 1"))
+    );
+
+    let start_time = Instant::now();
+    let (slow, fast) = tokio::join!(send(&tx, "/slow"), async {
+        // Sent while the first isolate is busy
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let result = send(&tx, "/fast").await;
+
+        (result, start_time.elapsed())
+    });
+
+    assert_eq!(slow, RunResult::Response(Response::from("/slow 2")));
+    assert_eq!(fast.0, RunResult::Response(Response::from("/fast 1")));
+    // The fast request doesn't wait for the slow one
+    assert!(fast.1 < Duration::from_millis(450), "{:?}", fast.1);
+    assert!(start_time.elapsed() < Duration::from_millis(600));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pool_max_isolates() {
+    utils::setup();
+    let tx = create_pool(IsolatePoolOptions::new(1), |options| {
+        options.timeout(Duration::from_secs(1))
+    });
+
+    let start_time = Instant::now();
+    let (slow, fast) = tokio::join!(send(&tx, "/slow"), async {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        send(&tx, "/fast").await
+    });
+
+    // Both requests are handled by the same isolate
+    assert_eq!(slow, RunResult::Response(Response::from("/slow 1")));
+    assert_eq!(fast, RunResult::Response(Response::from("/fast 2")));
+    assert!(start_time.elapsed() >= Duration::from_millis(500));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pool_max_requests() {
+    utils::setup();
+    let tx = create_pool(IsolatePoolOptions::new(1).max_requests(2), |options| {
+        options
+    });
+
+    for expected in ["/ 1", "/ 2", "/ 1", "/ 2", "/ 1"] {
+        assert_eq!(
+            send(&tx, "/").await,
+            RunResult::Response(Response::from(expected))
+        );
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pool_memory_limit() {
+    utils::setup();
+    let tx = create_pool(IsolatePoolOptions::new(1), |options| {
+        options
+            .memory(1)
+            // Increase timeout for CI
+            .timeout(Duration::from_secs(10))
+            .startup_timeout(Duration::from_secs(10))
+    });
+
+    assert_eq!(
+        send(&tx, "/").await,
+        RunResult::Response(Response::from("/ 1"))
+    );
+    assert!(matches!(
+        send(&tx, "/allocate").await,
+        RunResult::MemoryLimit(_)
+    ));
+    // Handled by a new isolate
+    assert_eq!(
+        send(&tx, "/").await,
+        RunResult::Response(Response::from("/ 1"))
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pool_terminate() {
+    utils::setup();
+    let tx = create_pool(IsolatePoolOptions::new(2), |options| options);
+
+    assert_eq!(
+        send(&tx, "/").await,
+        RunResult::Response(Response::from("/ 1"))
+    );
+
+    tx.send_async(IsolateEvent::Terminate("Recycled".into()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        send(&tx, "/").await,
+        RunResult::Response(Response::from("/ 1"))
+    );
+}
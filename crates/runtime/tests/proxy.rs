@@ -0,0 +1,283 @@
+use futures::{SinkExt, StreamExt};
+use httptest::{matchers::*, responders::*, Expectation, Server};
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{network_policy::NetworkPolicy, options::IsolateOptions};
+use std::sync::{Arc, Mutex, Once};
+use tokio::{
+    io::{copy_bidirectional, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+mod utils;
+
+// The credentials of the proxy's URL, `user:pa%20ss`, in a Proxy-Authorization header
+const PROXY_AUTHORIZATION: &str = "Basic dXNlcjpwYSBzcw==";
+
+static mut PROXY_REQUESTS: Option<Arc<Mutex<Vec<String>>>> = None;
+
+// A proxy forwarding the requests and opening CONNECT tunnels, which logs each
+// request line and whether it was authenticated
+async fn proxy(mut stream: TcpStream, requests: Arc<Mutex<Vec<String>>>) {
+    let mut head = Vec::new();
+    let mut buf = [0; 1024];
+
+    while !head.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buf).await {
+            Ok(0) | Err(_) => return,
+            Ok(read) => head.extend_from_slice(&buf[..read]),
+        }
+    }
+
+    let head_str = String::from_utf8_lossy(&head).to_string();
+    let request_line = head_str.lines().next().unwrap().to_string();
+    let authenticated = head_str.lines().any(|line| {
+        line.to_lowercase().starts_with("proxy-authorization: ")
+            && line.ends_with(PROXY_AUTHORIZATION)
+    });
+
+    requests
+        .lock()
+        .unwrap()
+        .push(format!("{request_line} {authenticated}"));
+
+    if !authenticated {
+        stream
+            .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\ncontent-length: 0\r\n\r\n")
+            .await
+            .unwrap_or(());
+        return;
+    }
+
+    let mut parts = request_line.split(' ');
+    let method = parts.next().unwrap();
+    let target = parts.next().unwrap();
+
+    let mut server_stream = if method == "CONNECT" {
+        let server_stream = TcpStream::connect(target).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+            .await
+            .unwrap();
+
+        server_stream
+    } else {
+        // Forwarded requests use the absolute-form, e.g `GET http://localhost:1234/ HTTP/1.1`
+        let authority = target
+            .trim_start_matches("http://")
+            .split('/')
+            .next()
+            .unwrap();
+        let mut server_stream = TcpStream::connect(authority).await.unwrap();
+        server_stream.write_all(&head).await.unwrap();
+
+        server_stream
+    };
+
+    copy_bidirectional(&mut stream, &mut server_stream)
+        .await
+        .unwrap_or_default();
+}
+
+// The proxy runs on its own thread, since it's used by all the tests. The environment
+// variables are read when creating the first isolate
+fn setup() -> Arc<Mutex<Vec<String>>> {
+    static START: Once = Once::new();
+
+    START.call_once(|| {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let proxy_url = format!("http://user:pa%20ss@{}", listener.local_addr().unwrap());
+        let proxy_requests = Arc::clone(&requests);
+
+        std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(async move {
+                    listener.set_nonblocking(true).unwrap();
+                    let listener = TcpListener::from_std(listener).unwrap();
+
+                    while let Ok((stream, _)) = listener.accept().await {
+                        tokio::spawn(proxy(stream, Arc::clone(&proxy_requests)));
+                    }
+                });
+        });
+
+        std::env::set_var("HTTP_PROXY", &proxy_url);
+        std::env::set_var("HTTPS_PROXY", &proxy_url);
+        std::env::set_var("NO_PROXY", "127.0.0.1, .internal.example");
+        utils::setup_certificate();
+
+        unsafe { PROXY_REQUESTS = Some(requests) };
+    });
+
+    utils::setup();
+    unsafe { PROXY_REQUESTS.clone() }.unwrap()
+}
+
+fn proxy_requests(requests: &Mutex<Vec<String>>, port: u16) -> Vec<String> {
+    requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|request| {
+            request.contains(&format!(":{port}/")) || request.contains(&format!(":{port} "))
+        })
+        .cloned()
+        .collect()
+}
+
+#[tokio::test]
+async fn forward_http_requests() {
+    let requests = setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/hello"))
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let port = server.addr().port();
+    let url = format!("http://localhost:{port}/hello");
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('{url}').then(res => res.text());
+    return new Response(body);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello, World"))
+    );
+    assert_eq!(
+        proxy_requests(&requests, port),
+        vec![format!("GET {url} HTTP/1.1 true")]
+    );
+}
+
+#[tokio::test]
+async fn tunnel_https_requests() {
+    let requests = setup();
+    let (certificate, private_key) = utils::setup_certificate();
+    let port = utils::run_tls_server(certificate, private_key).await;
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('https://localhost:{port}').then(res => res.text());
+    return new Response(body);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello over TLS"))
+    );
+    assert_eq!(
+        proxy_requests(&requests, port),
+        vec![format!("CONNECT localhost:{port} HTTP/1.1 true")]
+    );
+}
+
+#[tokio::test]
+async fn no_proxy_bypass() {
+    let requests = setup();
+    let server = Server::run();
+    server.expect(
+        Expectation::matching(request::method_path("GET", "/"))
+            .respond_with(status_code(200).body("Hello, World")),
+    );
+    let port = server.addr().port();
+    let url = server.url("/");
+
+    // 127.0.0.1 is in NO_PROXY, and so are the subdomains of internal.example
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('{url}').then(res => res.text());
+    const error = await fetch('http://api.internal.example:{port}').catch(error => error.code);
+
+    return new Response(`${{body}} ${{error}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello, World ENOTFOUND"))
+    );
+    assert_eq!(proxy_requests(&requests, port), Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn network_policy_private_ip() {
+    let requests = setup();
+    let server = Server::run();
+    let port = server.addr().port();
+
+    // The proxy would connect to localhost, which resolves to private IPs
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(format!(
+            "export async function handler() {{
+    const error = await fetch('http://localhost:{port}').catch(error => error);
+    const message = 'The host localhost resolves to the private IP';
+
+    return new Response(`${{error.name}} ${{error.message.startsWith(message)}}`);
+}}"
+        ))
+        .network_policy(NetworkPolicy {
+            block_private_ips: true,
+            ..Default::default()
+        }),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("TypeError true"))
+    );
+    assert_eq!(proxy_requests(&requests, port), Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn tunnel_websocket_connections() {
+    let requests = setup();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut websocket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+        while let Some(Ok(message)) = websocket.next().await {
+            if message.is_text() {
+                websocket.send(message).await.unwrap_or(());
+            }
+        }
+    });
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('ws://localhost:{port}');
+
+        websocket.onopen = () => websocket.send('Hello');
+        websocket.onmessage = event => {{
+            websocket.close();
+            resolve(new Response(event.data));
+        }};
+    }});
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello"))
+    );
+    assert_eq!(
+        proxy_requests(&requests, port),
+        vec![format!("CONNECT localhost:{port} HTTP/1.1 true")]
+    );
+}
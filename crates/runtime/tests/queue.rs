@@ -0,0 +1,160 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{options::IsolateOptions, queue::QueueBackend};
+use std::sync::{Arc, Mutex};
+
+mod utils;
+
+// Keep the sent messages, or reject them all
+#[derive(Debug, Default)]
+struct TestQueueBackend {
+    messages: Mutex<Vec<(String, String)>>,
+    should_fail: bool,
+}
+
+#[async_trait]
+impl QueueBackend for TestQueueBackend {
+    async fn send(&self, queue: &str, message: String) -> Result<()> {
+        if self.should_fail {
+            return Err(anyhow!("The broker is unavailable"));
+        }
+
+        self.messages
+            .lock()
+            .unwrap()
+            .push((queue.to_string(), message));
+
+        Ok(())
+    }
+}
+
+async fn run(handler: &str, options: impl FnOnce(IsolateOptions) -> IsolateOptions) -> RunResult {
+    let (send, receiver) = utils::create_isolate(options(IsolateOptions::new(format!(
+        "export async function handler() {{
+    {handler}
+}}"
+    ))));
+    send(Request::default());
+
+    receiver.recv_async().await.unwrap()
+}
+
+#[tokio::test]
+async fn queue_send() {
+    utils::setup();
+    let backend = Arc::new(TestQueueBackend::default());
+
+    assert_eq!(
+        run(
+            "await Lagon.queue.send('emails', { to: 'hello@lagon.app', subject: 'Welcome' });
+    await Lagon.queue.send('counters', 1);
+
+    return new Response('Sent');",
+            |options| options.queue_backend(Arc::clone(&backend) as Arc<dyn QueueBackend>),
+        )
+        .await,
+        RunResult::Response(Response::from("Sent"))
+    );
+
+    assert_eq!(
+        *backend.messages.lock().unwrap(),
+        vec![
+            (
+                "emails".to_string(),
+                r#"{"to":"hello@lagon.app","subject":"Welcome"}"#.to_string()
+            ),
+            ("counters".to_string(), "1".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn queue_invalid_messages() {
+    utils::setup();
+    let backend = Arc::new(TestQueueBackend::default());
+
+    assert_eq!(
+        run(
+            "const message = promise => promise.then(() => 'Sent', error => error.message);
+    const circular = {};
+    circular.self = circular;
+
+    const name = await message(Lagon.queue.send('', 'Hello'));
+    const undefinedMessage = await message(Lagon.queue.send('emails'));
+    const circularMessage = await message(Lagon.queue.send('emails', circular));
+
+    return new Response(`${name}, ${undefinedMessage}, ${circularMessage.startsWith('Could not serialize the message: TypeError')}`);",
+            |options| options.queue_backend(Arc::clone(&backend) as Arc<dyn QueueBackend>),
+        )
+        .await,
+        RunResult::Response(Response::from(
+            "Queue names must be non-empty strings, Messages must be serializable to JSON, true"
+        ))
+    );
+
+    assert!(backend.messages.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn queue_max_message_size() {
+    utils::setup();
+    let backend = Arc::new(TestQueueBackend::default());
+
+    assert_eq!(
+        run(
+            "const error = await Lagon.queue.send('emails', 'a'.repeat(10)).catch(error => error);
+    await Lagon.queue.send('emails', 'a'.repeat(8));
+
+    return new Response(`${error instanceof Error} ${error.message}`);",
+            |options| {
+                options
+                    .queue_backend(Arc::clone(&backend) as Arc<dyn QueueBackend>)
+                    .queue_max_message_size(10)
+            },
+        )
+        .await,
+        RunResult::Response(Response::from(
+            "true The message is 12 bytes, larger than the maximum of 10 bytes"
+        ))
+    );
+
+    assert_eq!(backend.messages.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn queue_backend_error() {
+    utils::setup();
+
+    assert_eq!(
+        run(
+            "const error = await Lagon.queue.send('emails', 'Hello').catch(error => error);
+    return new Response(`${error instanceof Error} ${error.message}`);",
+            |options| {
+                options.queue_backend(Arc::new(TestQueueBackend {
+                    should_fail: true,
+                    ..Default::default()
+                }))
+            },
+        )
+        .await,
+        RunResult::Response(Response::from("true The broker is unavailable"))
+    );
+}
+
+#[tokio::test]
+async fn queue_without_backend() {
+    utils::setup();
+
+    assert_eq!(
+        run(
+            "const error = await Lagon.queue.send('emails', 'Hello').catch(error => error);
+    return new Response(`${error}`);",
+            |options| options,
+        )
+        .await,
+        RunResult::Response(Response::from(
+            "Lagon.queue is not available in this runtime"
+        ))
+    );
+}
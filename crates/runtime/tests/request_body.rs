@@ -0,0 +1,168 @@
+use lagon_runtime_http::{Method, Request, Response, RunResult};
+use lagon_runtime_isolate::{options::IsolateOptions, RequestBodyChunk};
+use std::time::Duration;
+
+mod utils;
+
+fn post_request() -> Request {
+    Request {
+        method: Method::POST,
+        url: "http://localhost/".into(),
+        ..Request::default()
+    }
+}
+
+// Send the chunks one by one, waiting a bit between each
+// of them to simulate a slow client
+fn send_chunks(chunks: Vec<RequestBodyChunk>) -> flume::Receiver<RequestBodyChunk> {
+    let (sender, receiver) = flume::bounded(1);
+
+    tokio::spawn(async move {
+        for chunk in chunks {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            sender.send_async(chunk).await.unwrap();
+        }
+    });
+
+    receiver
+}
+
+#[tokio::test]
+async fn stream_body_chunks() {
+    utils::setup();
+    let (send, receiver) = utils::create_streamed_isolate(IsolateOptions::new(
+        "export async function handler(request) {
+    const reader = request.body.getReader();
+    const chunks = [];
+
+    while (true) {
+        const { done, value } = await reader.read();
+        if (done) break;
+        chunks.push(new TextDecoder().decode(value));
+    }
+
+    return new Response(`${request.body instanceof ReadableStream} ${JSON.stringify(chunks)}`);
+}"
+        .into(),
+    ));
+    send(
+        post_request(),
+        send_chunks(vec![
+            Ok(b"Hello".to_vec()),
+            Ok(b", ".to_vec()),
+            Ok(b"World".to_vec()),
+        ]),
+    );
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(r#"true ["Hello",", ","World"]"#))
+    );
+}
+
+#[tokio::test]
+async fn stream_body_text() {
+    utils::setup();
+    let (send, receiver) = utils::create_streamed_isolate(IsolateOptions::new(
+        "export async function handler(request) {
+    const body = await request.text();
+    return new Response(`${body.length} ${body.slice(0, 10)}`);
+}"
+        .into(),
+    ));
+    send(
+        post_request(),
+        send_chunks(
+            (0..100)
+                .map(|i| Ok(format!("{i:03}").into_bytes()))
+                .collect(),
+        ),
+    );
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("300 0000010020"))
+    );
+}
+
+#[tokio::test]
+async fn stream_body_json() {
+    utils::setup();
+    let (send, receiver) = utils::create_streamed_isolate(IsolateOptions::new(
+        "export async function handler(request) {
+    const { hello } = await request.json();
+    return new Response(hello);
+}"
+        .into(),
+    ));
+    send(
+        post_request(),
+        send_chunks(vec![
+            Ok(br#"{"hel"#.to_vec()),
+            Ok(br#"lo": "wo"#.to_vec()),
+            Ok(br#"rld"}"#.to_vec()),
+        ]),
+    );
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("world"))
+    );
+}
+
+#[tokio::test]
+async fn stream_body_error() {
+    utils::setup();
+    let (send, receiver) = utils::create_streamed_isolate(IsolateOptions::new(
+        "export async function handler(request) {
+    try {
+        await request.text();
+        return new Response('Body should have errored');
+    } catch (error) {
+        return new Response(error.message);
+    }
+}"
+        .into(),
+    ));
+    send(
+        post_request(),
+        send_chunks(vec![
+            Ok(b"Hello".to_vec()),
+            Err("Request body is larger than the maximum size of 5 bytes".into()),
+        ]),
+    );
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "Request body is larger than the maximum size of 5 bytes"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn unread_body_is_dropped() {
+    utils::setup();
+    let (send, receiver) = utils::create_streamed_isolate(IsolateOptions::new(
+        "export function handler() {
+    return new Response('Hello');
+}"
+        .into(),
+    ));
+    let (sender, body_stream) = flume::bounded(1);
+    send(post_request(), body_stream);
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello"))
+    );
+
+    // Nothing pulls the chunks, so sending only fails once the
+    // body stream is dropped with the request
+    let dropped = tokio::time::timeout(Duration::from_secs(1), async {
+        while sender.send_async(Ok(b"chunk".to_vec())).await.is_ok() {}
+    })
+    .await;
+
+    assert!(dropped.is_ok());
+}
@@ -1,7 +1,9 @@
 use httptest::bytes::Bytes;
 use lagon_runtime_http::{Method, Request, Response, RunResult};
-use lagon_runtime_isolate::options::IsolateOptions;
+use lagon_runtime_isolate::{options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest};
 use std::collections::HashMap;
+use std::time::Duration;
+use tokio::runtime::Handle;
 
 mod utils;
 
@@ -162,6 +164,32 @@ async fn get_headers() {
     );
 }
 
+#[tokio::test]
+async fn hide_request_id_header() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler(request) {
+    return new Response(String(request.headers.get('x-lagon-id')));
+}"
+        .into(),
+    ));
+
+    let mut headers = HashMap::new();
+    headers.insert("x-lagon-id".into(), vec!["a1b2".into()]);
+
+    send(Request {
+        body: Bytes::new(),
+        headers: Some(headers),
+        method: Method::GET,
+        url: "".into(),
+    });
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("null"))
+    );
+}
+
 #[tokio::test]
 async fn return_headers() {
     utils::setup();
@@ -224,6 +252,69 @@ async fn return_headers_from_headers_api() {
     );
 }
 
+#[tokio::test]
+async fn get_repeated_headers() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler(request) {
+    return new Response(request.headers.get('accept'));
+}"
+        .into(),
+    ));
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "accept".into(),
+        vec!["text/html".into(), "application/json".into()],
+    );
+
+    send(Request {
+        body: Bytes::new(),
+        headers: Some(headers),
+        method: Method::GET,
+        url: "".into(),
+    });
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("text/html, application/json"))
+    );
+}
+
+#[tokio::test]
+async fn return_repeated_headers() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    const headers = new Headers();
+    headers.append('set-cookie', 'greeting=hello');
+    headers.append('set-cookie', 'name=world');
+    headers.append('vary', 'Accept');
+    headers.append('vary', 'Origin');
+
+    return new Response('Hello world', { headers });
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "set-cookie".into(),
+        vec!["greeting=hello".into(), "name=world".into()],
+    );
+    headers.insert("vary".into(), vec!["Accept".into(), "Origin".into()]);
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response {
+            body: "Hello world".into(),
+            headers: Some(headers),
+            status: 200,
+        })
+    );
+}
+
 #[tokio::test]
 async fn return_status() {
     utils::setup();
@@ -247,6 +338,174 @@ async fn return_status() {
     );
 }
 
+#[tokio::test]
+async fn return_redirect() {
+    utils::setup();
+
+    for status in [301, 302, 307, 308] {
+        let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+            "export function handler() {{
+    return Response.redirect('https://lagon.app/docs', {status});
+}}"
+        )));
+        send(Request::default());
+
+        let mut headers = HashMap::new();
+        headers.insert("location".into(), vec!["https://lagon.app/docs".into()]);
+
+        assert_eq!(
+            receiver.recv_async().await.unwrap(),
+            RunResult::Response(Response {
+                body: "".into(),
+                headers: Some(headers),
+                status,
+            })
+        );
+    }
+}
+
+#[tokio::test]
+async fn return_redirect_invalid_status() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    return Response.redirect('https://lagon.app/docs', 200);
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error("Uncaught RangeError: Invalid status code".into())
+    );
+}
+
+#[tokio::test]
+async fn return_json() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    return Response.json({ hello: 'world', list: [1, 2] }, { status: 201 });
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "content-type".into(),
+        vec!["application/json;charset=UTF-8".into()],
+    );
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response {
+            body: r#"{"hello":"world","list":[1,2]}"#.into(),
+            headers: Some(headers),
+            status: 201,
+        })
+    );
+}
+
+#[tokio::test]
+async fn return_json_custom_content_type() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    return Response.json('hello', {
+        headers: { 'Content-Type': 'application/vnd.api+json' },
+    });
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    let mut headers = HashMap::new();
+    headers.insert(
+        "content-type".into(),
+        vec!["application/vnd.api+json".into()],
+    );
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response {
+            body: r#""hello""#.into(),
+            headers: Some(headers),
+            status: 200,
+        })
+    );
+}
+
+#[tokio::test]
+async fn return_json_circular() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    const data = {};
+    data.self = data;
+    return Response.json(data);
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert!(matches!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error(error) if error.contains("Converting circular structure to JSON")
+    ));
+}
+
+#[tokio::test]
+async fn get_json_body() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export async function handler(request) {
+    const { name } = await request.json();
+    return new Response(`Hello ${name}`);
+}"
+        .into(),
+    ));
+    send(Request {
+        body: Bytes::from(r#"{"name":"world"}"#),
+        headers: None,
+        method: Method::POST,
+        url: "".into(),
+    });
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+}
+
+#[tokio::test]
+async fn get_invalid_json_body() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export async function handler(request) {
+    try {
+        await request.json();
+        return new Response('Unreachable');
+    } catch (error) {
+        return new Response(error.name);
+    }
+}"
+        .into(),
+    ));
+    send(Request {
+        body: Bytes::from("{ invalid"),
+        headers: None,
+        method: Method::POST,
+        url: "".into(),
+    });
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("SyntaxError"))
+    );
+}
+
 #[tokio::test]
 async fn return_uint8array() {
     utils::setup();
@@ -322,3 +581,128 @@ async fn btoa() {
         RunResult::Response(Response::from("SGVsbG8="))
     );
 }
+
+#[tokio::test]
+async fn heap_statistics() {
+    utils::setup();
+    let (tx, rx) = flume::unbounded();
+
+    let handle = Handle::current();
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            let mut isolate = Isolate::new(
+                IsolateOptions::new(
+                    "export function handler() {
+    return new Response('');
+}"
+                    .into(),
+                )
+                .snapshot_blob(include_bytes!("../../serverless/snapshot.bin")),
+                rx,
+            );
+            isolate.evaluate();
+            isolate.run_event_loop().await;
+        })
+    });
+
+    let (sender, receiver) = flume::unbounded();
+    tx.send(IsolateEvent::HeapStatistics(sender)).unwrap();
+
+    let statistics = receiver.recv_async().await.unwrap();
+
+    assert!(statistics.used_heap_size > 0);
+    assert!(statistics.used_heap_size <= statistics.total_heap_size);
+    assert!(statistics.total_heap_size <= statistics.heap_size_limit);
+}
+
+#[tokio::test]
+async fn finish_requests_when_closed() {
+    utils::setup();
+    let (tx, rx) = flume::unbounded();
+    let (done_tx, done_rx) = flume::bounded(1);
+
+    let handle = Handle::current();
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            let mut isolate = Isolate::new(
+                IsolateOptions::new(
+                    "export async function handler() {
+    await new Promise(resolve => setTimeout(resolve, 100));
+    return new Response('Hello world');
+}"
+                    .into(),
+                )
+                .snapshot_blob(include_bytes!("../../serverless/snapshot.bin")),
+                rx,
+            );
+            isolate.evaluate();
+            isolate.run_event_loop().await;
+            done_tx.send(()).unwrap();
+        })
+    });
+
+    let (sender, receiver) = flume::unbounded();
+    tx.send(IsolateEvent::Request(IsolateRequest {
+        request: Request::default(),
+        sender,
+        statistics: None,
+        body_stream: None,
+        connection: None,
+    }))
+    .unwrap();
+
+    // The in-flight request is still handled once the channel is closed
+    drop(tx);
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+    done_rx.recv_async().await.unwrap();
+}
+
+#[tokio::test]
+async fn request_statistics() {
+    utils::setup();
+    let (tx, rx) = flume::unbounded();
+
+    let handle = Handle::current();
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            let mut isolate = Isolate::new(
+                IsolateOptions::new(
+                    "export async function handler() {
+    await new Promise(resolve => setTimeout(resolve, 100));
+    return new Response('Hello world');
+}"
+                    .into(),
+                )
+                .snapshot_blob(include_bytes!("../../serverless/snapshot.bin")),
+                rx,
+            );
+            isolate.evaluate();
+            isolate.run_event_loop().await;
+        })
+    });
+
+    let (sender, receiver) = flume::unbounded();
+    let (statistics_tx, statistics_rx) = flume::unbounded();
+    tx.send(IsolateEvent::Request(IsolateRequest {
+        request: Request::default(),
+        sender,
+        statistics: Some(statistics_tx),
+        body_stream: None,
+        connection: None,
+    }))
+    .unwrap();
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+
+    // Sent before the response, and awaiting the timer isn't counted
+    let statistics = statistics_rx.try_recv().unwrap();
+    assert!(statistics.cpu_time < Duration::from_millis(50));
+    assert!(statistics.wall_time >= Duration::from_millis(100));
+}
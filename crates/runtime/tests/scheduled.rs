@@ -0,0 +1,153 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{
+    options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest, IsolateScheduledEvent,
+};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::runtime::Handle;
+
+mod utils;
+
+// Unlike utils::create_isolate, both requests and scheduled events can be sent
+fn create_isolate(code: &str) -> flume::Sender<IsolateEvent> {
+    let (tx, rx) = flume::unbounded();
+    let options = IsolateOptions::new(code.into())
+        .snapshot_blob(include_bytes!("../../serverless/snapshot.bin"));
+
+    let handle = Handle::current();
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            let mut isolate = Isolate::new(options, rx);
+            isolate.evaluate();
+            isolate.run_event_loop().await;
+        })
+    });
+
+    tx
+}
+
+async fn send_scheduled(
+    tx: &flume::Sender<IsolateEvent>,
+    cron: &str,
+    scheduled_time: SystemTime,
+) -> RunResult {
+    let (sender, receiver) = flume::unbounded();
+    tx.send(IsolateEvent::Scheduled(IsolateScheduledEvent {
+        cron: cron.into(),
+        scheduled_time,
+        sender,
+        statistics: None,
+    }))
+    .unwrap();
+
+    receiver.recv_async().await.unwrap()
+}
+
+async fn send_request(tx: &flume::Sender<IsolateEvent>) -> RunResult {
+    let (sender, receiver) = flume::unbounded();
+    tx.send(IsolateEvent::Request(IsolateRequest {
+        request: Request::default(),
+        sender,
+        statistics: None,
+        body_stream: None,
+        connection: None,
+    }))
+    .unwrap();
+
+    receiver.recv_async().await.unwrap()
+}
+
+#[tokio::test]
+async fn scheduled_event() {
+    utils::setup();
+    let tx = create_isolate(
+        "export function handler() {
+    return new Response(globalThis.event);
+}
+
+export async function scheduled(event) {
+    globalThis.event = `${event.cron} ${event.scheduledTime}`;
+}",
+    );
+    let scheduled_time = UNIX_EPOCH + Duration::from_millis(1_681_000_000_000);
+
+    assert_eq!(
+        send_scheduled(&tx, "*/5 * * * *", scheduled_time).await,
+        RunResult::Response(Response {
+            status: 204,
+            ..Default::default()
+        })
+    );
+
+    // Requests are still sent to the handler
+    assert_eq!(
+        send_request(&tx).await,
+        RunResult::Response(Response::from("*/5 * * * * 1681000000000"))
+    );
+}
+
+#[tokio::test]
+async fn scheduled_wait_until() {
+    utils::setup();
+    let tx = create_isolate(
+        "export function handler() {
+    return new Response(`${globalThis.done}`);
+}
+
+export function scheduled(event, context) {
+    context.waitUntil(new Promise(resolve => setTimeout(resolve, 10)).then(() => {
+        globalThis.done = true;
+    }));
+}",
+    );
+
+    assert_eq!(
+        send_scheduled(&tx, "* * * * *", SystemTime::now()).await,
+        RunResult::Response(Response {
+            status: 204,
+            ..Default::default()
+        })
+    );
+
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    assert_eq!(
+        send_request(&tx).await,
+        RunResult::Response(Response::from("true"))
+    );
+}
+
+#[tokio::test]
+async fn scheduled_not_defined() {
+    utils::setup();
+    let tx = create_isolate(
+        "export function handler() {
+    return new Response('Hello world');
+}",
+    );
+
+    assert_eq!(
+        send_scheduled(&tx, "* * * * *", SystemTime::now()).await,
+        RunResult::Error(
+            "Uncaught Error: Scheduled function is not defined or is not a function".into()
+        )
+    );
+}
+
+#[tokio::test]
+async fn scheduled_throw_error() {
+    utils::setup();
+    let tx = create_isolate(
+        "export function handler() {
+    return new Response('Hello world');
+}
+
+export async function scheduled() {
+    throw new Error('Could not run the job');
+}",
+    );
+
+    assert_eq!(
+        send_scheduled(&tx, "* * * * *", SystemTime::now()).await,
+        RunResult::Error("Uncaught Error: Could not run the job".into())
+    );
+}
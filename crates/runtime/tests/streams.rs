@@ -1,10 +1,56 @@
 use httptest::{bytes::Bytes, matchers::*, responders::*, Expectation, Server};
 use lagon_runtime_http::{Request, Response, RunResult, StreamResult};
 use lagon_runtime_isolate::options::IsolateOptions;
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 mod utils;
 
+// Like testharness.js, assertions throw so a failing test rejects. Tests are
+// ported from https://github.com/web-platform-tests/wpt/tree/master/streams
+async fn run_streams_test(test: &str) {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "function assert_equals(actual, expected) {{
+    if (JSON.stringify(actual) !== JSON.stringify(expected)) {{
+        throw new Error(`Expected ${{JSON.stringify(expected)}} but got ${{JSON.stringify(actual)}}`);
+    }}
+}}
+
+async function promise_rejects(expected, promise) {{
+    try {{
+        await promise;
+    }} catch (error) {{
+        assert_equals(error, expected);
+        return;
+    }}
+
+    throw new Error('Expected the promise to reject');
+}}
+
+async function read_all(readable) {{
+    const chunks = [];
+    const reader = readable.getReader();
+
+    while (true) {{
+        const {{ done, value }} = await reader.read();
+        if (done) return chunks;
+        chunks.push(value);
+    }}
+}}
+
+export async function handler() {{
+    {test}
+    return new Response('ok');
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ok"))
+    );
+}
+
 #[tokio::test]
 async fn sync_streaming() {
     utils::setup();
@@ -331,3 +377,402 @@ async fn promise_reject_callback_after_response() {
 
     assert_eq!(receiver.recv_async().await.unwrap(), RunResult::Error("Uncaught ReferenceError: doesNotExists is not defined\n  at 12:17\n  at stream (11:19)".to_owned()));
 }
+
+#[tokio::test]
+async fn transform_stream() {
+    run_streams_test(
+        "const ts = new TransformStream({
+        transform(chunk, controller) {
+            controller.enqueue(chunk.toUpperCase());
+        },
+        flush(controller) {
+            controller.enqueue('!');
+        },
+    });
+
+    const writer = ts.writable.getWriter();
+    writer.write('a');
+    writer.write('b');
+    writer.close();
+
+    assert_equals(await read_all(ts.readable), ['A', 'B', '!']);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn transform_stream_identity() {
+    run_streams_test(
+        "const ts = new TransformStream();
+    const writer = ts.writable.getWriter();
+    writer.write('a');
+    writer.close();
+
+    assert_equals(await read_all(ts.readable), ['a']);
+    await writer.closed;",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn transform_stream_errors() {
+    run_streams_test(
+        "const ts = new TransformStream({
+        transform() {
+            throw 'error';
+        },
+    });
+
+    const writer = ts.writable.getWriter();
+    await promise_rejects('error', writer.write('a'));
+    await promise_rejects('error', ts.readable.getReader().read());",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn pipe_through() {
+    run_streams_test(
+        "const readable = new ReadableStream({
+        start(controller) {
+            controller.enqueue(1);
+            controller.enqueue(2);
+            controller.close();
+        },
+    });
+
+    const doubled = readable.pipeThrough(new TransformStream({
+        transform(chunk, controller) {
+            controller.enqueue(chunk * 2);
+        },
+    }));
+
+    assert_equals(readable.locked, true);
+    assert_equals(await read_all(doubled), [2, 4]);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn pipe_to() {
+    run_streams_test(
+        "const chunks = [];
+    const readable = new ReadableStream({
+        start(controller) {
+            controller.enqueue('a');
+            controller.enqueue('b');
+            controller.close();
+        },
+    });
+    const writable = new WritableStream({
+        write(chunk) {
+            chunks.push(chunk);
+        },
+    });
+
+    await readable.pipeTo(writable);
+    assert_equals(chunks, ['a', 'b']);
+    assert_equals(writable.locked, false);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn pipe_to_errors() {
+    run_streams_test(
+        "const readable = new ReadableStream({
+        start(controller) {
+            controller.error('error');
+        },
+    });
+    let abortReason;
+    const writable = new WritableStream({
+        abort(reason) {
+            abortReason = reason;
+        },
+    });
+
+    await promise_rejects('error', readable.pipeTo(writable));
+    assert_equals(abortReason, 'error');",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn pipe_to_prevent_close() {
+    run_streams_test(
+        "const readable = new ReadableStream({
+        start(controller) {
+            controller.close();
+        },
+    });
+    const writable = new WritableStream();
+
+    await readable.pipeTo(writable, { preventClose: true });
+
+    const writer = writable.getWriter();
+    await writer.write('a');
+    await writer.close();",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn pipe_to_abort_signal() {
+    run_streams_test(
+        "const controller = new AbortController();
+    const readable = new ReadableStream();
+    const writable = new WritableStream();
+    const promise = readable.pipeTo(writable, { signal: controller.signal });
+
+    controller.abort();
+
+    try {
+        await promise;
+        throw new Error('Expected the promise to reject');
+    } catch (error) {
+        assert_equals(error.name, 'AbortError');
+    }",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn tee() {
+    run_streams_test(
+        "const readable = new ReadableStream({
+        start(controller) {
+            controller.enqueue('a');
+            controller.enqueue('b');
+            controller.close();
+        },
+    });
+
+    const [branch1, branch2] = readable.tee();
+    assert_equals(await read_all(branch1), ['a', 'b']);
+    assert_equals(await read_all(branch2), ['a', 'b']);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn tee_cancel() {
+    run_streams_test(
+        "let cancelReason;
+    const readable = new ReadableStream({
+        cancel(reason) {
+            cancelReason = reason;
+        },
+    });
+
+    const [branch1, branch2] = readable.tee();
+    branch1.cancel('reason 1');
+    assert_equals(cancelReason, undefined);
+
+    await branch2.cancel('reason 2');
+    assert_equals(cancelReason, ['reason 1', 'reason 2']);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn count_queuing_strategy() {
+    run_streams_test(
+        "const strategy = new CountQueuingStrategy({ highWaterMark: 2 });
+    assert_equals(strategy.highWaterMark, 2);
+    assert_equals(strategy.size('chunk'), 1);
+
+    let controller;
+    new ReadableStream({
+        start(c) {
+            controller = c;
+        },
+    }, strategy);
+
+    assert_equals(controller.desiredSize, 2);
+    controller.enqueue('a');
+    controller.enqueue('b');
+    assert_equals(controller.desiredSize, 0);
+    controller.enqueue('c');
+    assert_equals(controller.desiredSize, -1);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn byte_length_queuing_strategy() {
+    run_streams_test(
+        "const strategy = new ByteLengthQueuingStrategy({ highWaterMark: 4 });
+    assert_equals(strategy.size(new Uint8Array(3)), 3);
+
+    const writable = new WritableStream({
+        write() {
+            return new Promise(() => {});
+        },
+    }, strategy);
+    const writer = writable.getWriter();
+
+    assert_equals(writer.desiredSize, 4);
+    writer.write(new Uint8Array(3));
+    assert_equals(writer.desiredSize, 1);
+    writer.write(new Uint8Array(3));
+    assert_equals(writer.desiredSize, -2);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn text_decoder_stream() {
+    run_streams_test(
+        "const bytes = new TextEncoder().encode('a😊b');
+    const readable = new ReadableStream({
+        start(controller) {
+            controller.enqueue(bytes.subarray(0, 3));
+            controller.enqueue(bytes.subarray(3));
+            controller.close();
+        },
+    });
+
+    const chunks = await read_all(readable.pipeThrough(new TextDecoderStream()));
+    assert_equals(chunks.join(''), 'a😊b');",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn text_encoder_stream() {
+    run_streams_test(
+        "const readable = new ReadableStream({
+        start(controller) {
+            controller.enqueue('a\\ud83d');
+            controller.enqueue('\\ude0ab');
+            controller.close();
+        },
+    });
+
+    const chunks = await read_all(readable.pipeThrough(new TextEncoderStream()).pipeThrough(new TextDecoderStream()));
+    assert_equals(chunks.join(''), 'a😊b');",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn pipe_through_response() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    const readable = new ReadableStream({
+        start(controller) {
+            controller.enqueue('Hello');
+            controller.close();
+        },
+    });
+
+    return new Response(readable.pipeThrough(new TransformStream({
+        transform(chunk, controller) {
+            controller.enqueue(new TextEncoder().encode(chunk.toUpperCase()));
+        },
+    })));
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    let mut body = Vec::new();
+
+    loop {
+        match receiver.recv_async().await.unwrap() {
+            RunResult::Stream(StreamResult::Data(data)) => body.extend(data),
+            RunResult::Stream(StreamResult::Done) => break,
+            RunResult::Stream(StreamResult::Start(_)) => {}
+            result => panic!("Unexpected result: {result:?}"),
+        }
+    }
+
+    assert_eq!(body, b"HELLO");
+}
+
+#[tokio::test]
+async fn backpressure() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    const { readable, writable } = new TransformStream();
+    const writer = writable.getWriter();
+
+    (async () => {
+        for (let i = 0; i < 100; i++) {
+            await writer.ready;
+            writer.write(new Uint8Array([i]));
+        }
+
+        writer.close();
+    })();
+
+    return new Response(readable);
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    // Without reading the results, the handler stops writing once
+    // the results that are waiting to be read reach the limit
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(receiver.len() < 20);
+
+    let mut body = Vec::new();
+
+    loop {
+        match receiver.recv_async().await.unwrap() {
+            RunResult::Stream(StreamResult::Data(data)) => body.extend(data),
+            RunResult::Stream(StreamResult::Done) => break,
+            RunResult::Stream(StreamResult::Start(_)) => {}
+            result => panic!("Unexpected result: {result:?}"),
+        }
+    }
+
+    assert_eq!(body, (0..100).collect::<Vec<u8>>());
+}
+
+#[tokio::test]
+async fn cancel_on_disconnect() {
+    utils::setup();
+    let log_rx = utils::setup_logger();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    const encoder = new TextEncoder();
+
+    return new Response(
+        new ReadableStream({
+            start(controller) {
+                controller.enqueue(encoder.encode('data: 1\\n\\n'));
+            },
+            cancel() {
+                console.log('cancelled');
+            },
+        }),
+    );
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    let mut has_started = false;
+    let mut has_data = false;
+
+    while !has_started || !has_data {
+        match receiver.recv_async().await.unwrap() {
+            RunResult::Stream(StreamResult::Start(_)) => has_started = true,
+            RunResult::Stream(StreamResult::Data(data)) => {
+                assert_eq!(data, b"data: 1\n\n");
+                has_data = true;
+            }
+            result => panic!("Unexpected result: {result:?}"),
+        }
+    }
+
+    // The stream never closes by itself, until the client disconnects
+    drop(receiver);
+
+    assert_eq!(log_rx.recv_async().await.unwrap(), "cancelled");
+}
@@ -0,0 +1,178 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+
+mod utils;
+
+// Assertions throw so a failing test rejects, like in compression.rs
+async fn run_structured_clone_test(test: &str) {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "function assert_equals(actual, expected) {{
+    if (actual !== expected) {{
+        throw new Error(`Expected ${{expected}} but got ${{actual}}`);
+    }}
+}}
+
+function assert_throws(name, callback) {{
+    try {{
+        callback();
+    }} catch (error) {{
+        assert_equals(error.name, name);
+        return error;
+    }}
+
+    throw new Error(`Expected a ${{name}}`);
+}}
+
+export function handler() {{
+    {test}
+    return new Response('ok');
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("ok"))
+    );
+}
+
+#[tokio::test]
+async fn clone_values() {
+    run_structured_clone_test(
+        "const date = new Date(0);
+    const bytes = new Uint16Array([1, 2, 3]);
+    const value = {
+        string: 'hello',
+        number: 1.5,
+        bigint: 10n,
+        array: [1, 'a', null, undefined],
+        map: new Map([['key', { nested: true }]]),
+        set: new Set([1, 2]),
+        date,
+        regexp: /lagon/gi,
+        buffer: new Uint8Array([1, 2]).buffer,
+        bytes,
+        view: new DataView(bytes.buffer, 2),
+    };
+
+    const clone = structuredClone(value);
+
+    assert_equals(clone === value, false);
+    assert_equals(clone.string, 'hello');
+    assert_equals(clone.number, 1.5);
+    assert_equals(clone.bigint, 10n);
+    assert_equals(JSON.stringify(clone.array), JSON.stringify([1, 'a', null, null]));
+    assert_equals(clone.array.length, 4);
+    assert_equals(clone.map.get('key').nested, true);
+    assert_equals(clone.map.get('key') === value.map.get('key'), false);
+    assert_equals([...clone.set].join(), '1,2');
+    assert_equals(clone.date instanceof Date && clone.date !== date, true);
+    assert_equals(clone.date.getTime(), 0);
+    assert_equals(clone.regexp.source, 'lagon');
+    assert_equals(clone.regexp.flags, 'gi');
+    assert_equals(new Uint8Array(clone.buffer).join(), '1,2');
+    assert_equals(clone.bytes instanceof Uint16Array, true);
+    assert_equals(clone.bytes.join(), '1,2,3');
+    assert_equals(clone.bytes.buffer === bytes.buffer, false);
+    // Views of the same buffer still share their clone
+    assert_equals(clone.view.buffer === clone.bytes.buffer, true);
+    assert_equals(clone.view.getUint16(0, true), 2);
+
+    assert_equals(structuredClone('primitive'), 'primitive');
+    assert_equals(structuredClone(undefined), undefined);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn clone_circular() {
+    run_structured_clone_test(
+        "const value = { name: 'parent', children: [] };
+    value.self = value;
+    value.children.push({ parent: value });
+
+    const map = new Map();
+    map.set('map', map);
+    value.map = map;
+
+    const clone = structuredClone(value);
+
+    assert_equals(clone === value, false);
+    assert_equals(clone.self, clone);
+    assert_equals(clone.children[0].parent, clone);
+    assert_equals(clone.map.get('map'), clone.map);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn clone_errors() {
+    run_structured_clone_test(
+        "for (const value of [() => {}, Symbol('symbol'), { nested: { callback() {} } }]) {
+        const error = assert_throws('DataCloneError', () => structuredClone(value));
+        assert_equals(error instanceof DOMException, true);
+        assert_equals(error.code, 25);
+    }
+
+    // Errors thrown while reading the value are rethrown as is
+    const value = {
+        get property() {
+            throw new RangeError('getter');
+        },
+    };
+    assert_equals(assert_throws('RangeError', () => structuredClone(value)).message, 'getter');",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn clone_transfer() {
+    run_structured_clone_test(
+        "const buffer = new Uint8Array([1, 2, 3]).buffer;
+    const clone = structuredClone({ bytes: new Uint8Array(buffer, 1) }, { transfer: [buffer] });
+
+    assert_equals(clone.bytes.join(), '2,3');
+    assert_equals(clone.bytes.buffer.byteLength, 3);
+
+    // The original buffer is detached and isn't usable anymore
+    assert_equals(buffer.byteLength, 0);
+    assert_throws('TypeError', () => new Uint8Array(buffer));
+    assert_throws('DataCloneError', () => structuredClone(buffer));
+    assert_throws('DataCloneError', () => structuredClone(null, { transfer: [buffer] }));",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn clone_transfer_errors() {
+    run_structured_clone_test(
+        "const buffer = new ArrayBuffer(8);
+
+    assert_throws('TypeError', () => structuredClone(null, { transfer: 1 }));
+    assert_throws('DataCloneError', () => structuredClone(null, { transfer: [new Uint8Array(8)] }));
+    assert_throws('DataCloneError', () => structuredClone(null, { transfer: [buffer, buffer] }));
+
+    // A failed clone doesn't detach the buffers
+    assert_throws('DataCloneError', () => structuredClone({ buffer, callback() {} }, { transfer: [buffer] }));
+    assert_equals(buffer.byteLength, 8);",
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn clone_large_object() {
+    run_structured_clone_test(
+        "const value = {
+        bytes: new Uint8Array(10 * 1024 * 1024).fill(1),
+        items: Array.from({ length: 10000 }, (_, index) => ({ index, name: `item ${index}` })),
+    };
+
+    const clone = structuredClone(value);
+
+    assert_equals(clone.bytes.length, 10 * 1024 * 1024);
+    assert_equals(clone.bytes[clone.bytes.length - 1], 1);
+    assert_equals(clone.items[9999].name, 'item 9999');",
+    )
+    .await;
+}
@@ -0,0 +1,76 @@
+use lagon_runtime_http::{Request, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+
+mod utils;
+
+const TESTS: &str = "test('adds', () => {
+    expect(1 + 1).toBe(2);
+    expect({ a: [1, 2] }).toEqual({ a: [1, 2] });
+});
+
+test('awaits', async () => {
+    const value = await new Promise(resolve => setTimeout(() => resolve('done'), 10));
+    expect(value).not.toBe('pending');
+});
+
+test('fails', () => {
+    expect('hello').toContain('world');
+});";
+
+fn run_request(filter: Option<&str>) -> Request {
+    let mut request = Request::default();
+    request.url = match filter {
+        Some(filter) => format!("http://localhost/?filter={filter}"),
+        None => "http://localhost/".into(),
+    };
+
+    request
+}
+
+async fn run_tests(filter: Option<&str>) -> String {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(TESTS.into()).test_mode(true));
+    send(run_request(filter));
+
+    match receiver.recv_async().await.unwrap() {
+        RunResult::Response(response) => String::from_utf8(response.body.to_vec()).unwrap(),
+        result => panic!("Unexpected result: {result:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_mode_results() {
+    let results = run_tests(None).await;
+
+    assert!(results.contains(r#"{"name":"adds","status":"passed""#));
+    assert!(results.contains(r#"{"name":"awaits","status":"passed""#));
+    assert!(results.contains(r#"{"name":"fails","status":"failed""#));
+    assert!(results.contains(r#""error":"Expected \"hello\" to contain \"world\"""#));
+}
+
+#[tokio::test]
+async fn test_mode_filter() {
+    let results = run_tests(Some("add")).await;
+
+    assert!(results.contains(r#"{"name":"adds","status":"passed""#));
+    assert!(results.contains(r#"{"name":"awaits","status":"skipped""#));
+    assert!(results.contains(r#"{"name":"fails","status":"skipped""#));
+}
+
+#[tokio::test]
+async fn test_mode_disabled() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "test('adds', () => {});
+export function handler() {
+    return new Response('Hello world');
+}"
+        .into(),
+    ));
+    send(run_request(None));
+
+    assert!(matches!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error(error) if error.starts_with("Uncaught ReferenceError: test is not defined")
+    ));
+}
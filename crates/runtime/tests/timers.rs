@@ -222,3 +222,281 @@ async fn timers_order() {
         RunResult::Response(Response::from("Hello world"))
     );
 }
+
+#[tokio::test]
+#[serial]
+async fn set_immediate_order() {
+    let log_rx = utils::setup_logger();
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    setTimeout(() => {
+        console.log('timeout')
+    }, 0)
+
+    setImmediate(() => {
+        console.log('immediate')
+
+        Promise.resolve().then(() => {
+            console.log('immediate promise')
+        })
+    })
+
+    Promise.resolve().then(() => {
+        console.log('promise')
+    })
+
+    console.log('main');
+
+    await new Promise(resolve => setTimeout(resolve, 10))
+
+    console.log('main 2');
+
+    return new Response('Hello world');
+}"
+            .into(),
+        )
+        .metadata(Some(("".to_owned(), "".to_owned()))),
+    );
+    send(Request::default());
+
+    assert_eq!(log_rx.recv_async().await.unwrap(), "main".to_string());
+    assert_eq!(log_rx.recv_async().await.unwrap(), "promise".to_string());
+    assert_eq!(log_rx.recv_async().await.unwrap(), "immediate".to_string());
+    assert_eq!(
+        log_rx.recv_async().await.unwrap(),
+        "immediate promise".to_string()
+    );
+    assert_eq!(log_rx.recv_async().await.unwrap(), "timeout".to_string());
+    assert_eq!(log_rx.recv_async().await.unwrap(), "main 2".to_string());
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn set_immediate_nested() {
+    let log_rx = utils::setup_logger();
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    await new Promise(resolve => {
+        setImmediate(() => {
+            console.log('immediate 1')
+
+            // Runs on the next turn, after the immediates already queued
+            setImmediate(() => {
+                console.log('immediate 3')
+                resolve()
+            })
+        })
+
+        setImmediate(() => {
+            console.log('immediate 2')
+        })
+    })
+
+    return new Response('Hello world');
+}"
+            .into(),
+        )
+        .metadata(Some(("".to_owned(), "".to_owned()))),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        log_rx.recv_async().await.unwrap(),
+        "immediate 1".to_string()
+    );
+    assert_eq!(
+        log_rx.recv_async().await.unwrap(),
+        "immediate 2".to_string()
+    );
+    assert_eq!(
+        log_rx.recv_async().await.unwrap(),
+        "immediate 3".to_string()
+    );
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn clear_immediate() {
+    let log_rx = utils::setup_logger();
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    const id = setImmediate(() => {
+        console.log('cleared')
+    })
+
+    setImmediate(() => {
+        console.log('immediate')
+    })
+
+    clearImmediate(id)
+
+    await new Promise(resolve => setTimeout(resolve, 10))
+
+    console.log('main');
+
+    return new Response('Hello world');
+}"
+            .into(),
+        )
+        .metadata(Some(("".to_owned(), "".to_owned()))),
+    );
+    send(Request::default());
+
+    assert_eq!(log_rx.recv_async().await.unwrap(), "immediate".to_string());
+    assert_eq!(log_rx.recv_async().await.unwrap(), "main".to_string());
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+}
+
+#[tokio::test]
+async fn set_timeout_nesting_clamp() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export async function handler() {
+    const delays = await new Promise(resolve => {
+        const delays = [];
+
+        const nest = () => {
+            const start = performance.now();
+
+            setTimeout(() => {
+                delays.push(performance.now() - start);
+
+                if (delays.length === 10) {
+                    resolve(delays);
+                } else {
+                    nest();
+                }
+            }, 0);
+        };
+
+        nest();
+    });
+
+    // Only the timers nested more than 5 levels deep are clamped to 4ms
+    return new Response(delays.slice(6).every(delay => delay >= 4).toString());
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("true"))
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn clear_interval_in_handler() {
+    let log_rx = utils::setup_logger();
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export async function handler() {
+    let count = 0;
+    const id = setInterval(() => {
+        count++;
+        console.log('interval', count);
+
+        if (count >= 2) {
+            clearInterval(id);
+        }
+    }, 0);
+
+    await new Promise(resolve => setTimeout(resolve, 20));
+    console.log('count', count);
+
+    return new Response('Hello world');
+}"
+            .into(),
+        )
+        .metadata(Some(("".to_owned(), "".to_owned()))),
+    );
+    send(Request::default());
+
+    assert_eq!(log_rx.recv_async().await.unwrap(), "interval 1".to_string());
+    assert_eq!(log_rx.recv_async().await.unwrap(), "interval 2".to_string());
+    assert_eq!(log_rx.recv_async().await.unwrap(), "count 2".to_string());
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello world"))
+    );
+}
+
+#[tokio::test]
+async fn set_timeout_arguments() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export async function handler() {
+    const timeout = await new Promise(resolve => {
+        setTimeout((a, b) => resolve(`${a} ${b}`), 10, 'x', 'y');
+    });
+
+    const interval = await new Promise(resolve => {
+        const id = setInterval((...args) => {
+            clearInterval(id);
+            resolve(args.join(' '));
+        }, 10, 1, 2, 3);
+    });
+
+    const immediate = await new Promise(resolve => {
+        setImmediate(value => resolve(value), 'z');
+    });
+
+    return new Response(`${timeout},${interval},${immediate}`);
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("x y,1 2 3,z"))
+    );
+}
+
+#[tokio::test]
+async fn set_timeout_delays() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export async function handler() {
+    const order = [];
+
+    await new Promise(resolve => {
+        setTimeout(() => order.push('5ms'), '5');
+        setTimeout(() => order.push('no delay'));
+        setTimeout(() => order.push('NaN'), 'invalid');
+        setTimeout(() => order.push('negative'), -100);
+        // Overflows to 1ms like browsers
+        setTimeout(() => order.push('overflow'), 2 ** 31);
+        setTimeout(resolve, 20);
+    });
+
+    return new Response(`${order.slice(0, 4).sort().join(',')} ${order[4]}`);
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("NaN,negative,no delay,overflow 5ms"))
+    );
+}
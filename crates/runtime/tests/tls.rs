@@ -0,0 +1,120 @@
+use futures::{SinkExt, StreamExt};
+use lagon_runtime::{options::RuntimeOptions, Runtime};
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::{options::IsolateOptions, tls::Certificate};
+use std::sync::{Arc, Once};
+use tokio::net::TcpListener;
+use tokio_rustls::{
+    rustls::{self, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
+
+mod utils;
+
+static mut CERTIFICATE: Option<(Vec<u8>, Vec<u8>)> = None;
+
+// The self-signed certificate is only trusted with the runtime's options
+fn setup() -> (Vec<u8>, Vec<u8>) {
+    static START: Once = Once::new();
+
+    START.call_once(|| {
+        let certificate = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let der = certificate.serialize_der().unwrap();
+
+        Runtime::new(
+            RuntimeOptions::default().extra_root_certificates(vec![Certificate(der.clone())]),
+        );
+
+        unsafe { CERTIFICATE = Some((der, certificate.serialize_private_key_der())) };
+    });
+
+    unsafe { CERTIFICATE.clone() }.unwrap()
+}
+
+#[tokio::test]
+async fn extra_root_certificates() {
+    let (certificate, private_key) = setup();
+    let port = utils::run_tls_server(certificate, private_key).await;
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('https://localhost:{port}').then(res => res.text());
+    return new Response(body);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello over TLS"))
+    );
+}
+
+#[tokio::test]
+async fn untrusted_certificate() {
+    setup();
+    let other = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let port = utils::run_tls_server(
+        other.serialize_der().unwrap(),
+        other.serialize_private_key_der(),
+    )
+    .await;
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const error = await fetch('https://localhost:{port}').catch(error => error);
+    return new Response(`${{error instanceof Error}} ${{error.message.includes('certificate')}}`);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("true true"))
+    );
+}
+
+#[tokio::test]
+async fn websocket_extra_root_certificates() {
+    let (certificate, private_key) = setup();
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![rustls::Certificate(certificate)],
+            PrivateKey(private_key),
+        )
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let stream = acceptor.accept(stream).await.unwrap();
+        let mut websocket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+        if let Some(Ok(message)) = websocket.next().await {
+            websocket.send(message).await.unwrap_or(());
+        }
+    });
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('wss://localhost:{port}');
+        websocket.onopen = () => websocket.send('Hello over TLS');
+        websocket.onmessage = event => {{
+            websocket.close();
+            resolve(new Response(event.data));
+        }};
+    }});
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello over TLS"))
+    );
+}
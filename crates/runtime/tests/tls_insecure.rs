@@ -0,0 +1,38 @@
+use lagon_runtime::{options::RuntimeOptions, Runtime};
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use std::sync::Once;
+
+mod utils;
+
+fn setup() {
+    static START: Once = Once::new();
+
+    START.call_once(|| {
+        Runtime::new(RuntimeOptions::default().danger_accept_invalid_certificates(true));
+    });
+}
+
+#[tokio::test]
+async fn accept_invalid_certificates() {
+    setup();
+    let certificate = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+    let port = utils::run_tls_server(
+        certificate.serialize_der().unwrap(),
+        certificate.serialize_private_key_der(),
+    )
+    .await;
+
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export async function handler() {{
+    const body = await fetch('https://localhost:{port}').then(res => res.text());
+    return new Response(body);
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello over TLS"))
+    );
+}
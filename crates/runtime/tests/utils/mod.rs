@@ -1,8 +1,18 @@
+use hyper::{server::conn::Http, service::service_fn, Body};
 use lagon_runtime::{options::RuntimeOptions, Runtime};
 use lagon_runtime_http::{Request, RunResult};
-use lagon_runtime_isolate::{options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest};
-use std::sync::Once;
-use tokio::runtime::Handle;
+use lagon_runtime_isolate::{
+    options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest, RequestBodyChunk,
+};
+use std::{
+    convert::Infallible,
+    sync::{Arc, Once},
+};
+use tokio::{net::TcpListener, runtime::Handle};
+use tokio_rustls::{
+    rustls::{Certificate, PrivateKey, ServerConfig},
+    TlsAcceptor,
+};
 
 #[allow(dead_code)]
 pub fn setup() {
@@ -55,7 +65,66 @@ pub fn setup_logger() -> flume::Receiver<String> {
     unsafe { RX.clone() }.unwrap()
 }
 
+#[allow(dead_code)]
+static mut CERTIFICATE: Option<(Vec<u8>, Vec<u8>)> = None;
+
+// The root certificates are loaded once by the runtime, so the self-signed certificate
+// for localhost is trusted with SSL_CERT_FILE before creating any isolate. Returns the
+// certificate and its private key, in DER
+#[allow(dead_code)]
+pub fn setup_certificate() -> (Vec<u8>, Vec<u8>) {
+    static START: Once = Once::new();
+
+    START.call_once(|| {
+        let certificate = rcgen::generate_simple_self_signed(vec!["localhost".into()]).unwrap();
+        let path = std::env::temp_dir().join(format!("lagon-test-{}.pem", std::process::id()));
+
+        std::fs::write(&path, certificate.serialize_pem().unwrap()).unwrap();
+        std::env::set_var("SSL_CERT_FILE", path);
+
+        unsafe {
+            CERTIFICATE = Some((
+                certificate.serialize_der().unwrap(),
+                certificate.serialize_private_key_der(),
+            ))
+        };
+    });
+
+    unsafe { CERTIFICATE.clone() }.unwrap()
+}
+
+// A TLS server responding to every request with `Hello over TLS`. Returns its port
+#[allow(dead_code)]
+pub async fn run_tls_server(certificate: Vec<u8>, private_key: Vec<u8>) -> u16 {
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(vec![Certificate(certificate)], PrivateKey(private_key))
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            if let Ok(stream) = acceptor.accept(stream).await {
+                let service = service_fn(|_: hyper::Request<Body>| async {
+                    Ok::<_, Infallible>(hyper::Response::new(Body::from("Hello over TLS")))
+                });
+
+                Http::new()
+                    .serve_connection(stream, service)
+                    .await
+                    .unwrap_or(());
+            }
+        }
+    });
+
+    port
+}
+
 type SendRequest = Box<dyn Fn(Request)>;
+type SendStreamedRequest = Box<dyn Fn(Request, flume::Receiver<RequestBodyChunk>)>;
 
 #[allow(dead_code)]
 pub fn create_isolate(options: IsolateOptions) -> (SendRequest, flume::Receiver<RunResult>) {
@@ -79,6 +148,9 @@ pub fn create_isolate(options: IsolateOptions) -> (SendRequest, flume::Receiver<
             .send(IsolateEvent::Request(IsolateRequest {
                 request,
                 sender: sender.clone(),
+                statistics: None,
+                body_stream: None,
+                connection: None,
             }))
             .unwrap();
     });
@@ -107,9 +179,48 @@ pub fn create_isolate_without_snapshot(
             .send(IsolateEvent::Request(IsolateRequest {
                 request,
                 sender: sender.clone(),
+                statistics: None,
+                body_stream: None,
+                connection: None,
             }))
             .unwrap();
     });
 
     (send_isolate_event, receiver)
 }
+
+#[allow(dead_code)]
+pub fn create_streamed_isolate(
+    options: IsolateOptions,
+) -> (SendStreamedRequest, flume::Receiver<RunResult>) {
+    let (request_tx, request_rx) = flume::unbounded();
+    let (sender, receiver) = flume::unbounded();
+
+    let handle = Handle::current();
+    std::thread::spawn(move || {
+        handle.block_on(async move {
+            let mut isolate = Isolate::new(
+                options.snapshot_blob(include_bytes!("../../../serverless/snapshot.bin")),
+                request_rx,
+            );
+            isolate.evaluate();
+            isolate.run_event_loop().await;
+        })
+    });
+
+    let send_isolate_event = Box::new(
+        move |request: Request, body_stream: flume::Receiver<RequestBodyChunk>| {
+            request_tx
+                .send(IsolateEvent::Request(IsolateRequest {
+                    request,
+                    sender: sender.clone(),
+                    statistics: None,
+                    body_stream: Some(body_stream),
+                    connection: None,
+                }))
+                .unwrap();
+        },
+    );
+
+    (send_isolate_event, receiver)
+}
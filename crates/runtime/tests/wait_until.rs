@@ -0,0 +1,126 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+use serial_test::serial;
+use std::time::Duration;
+
+mod utils;
+
+#[tokio::test]
+#[serial]
+async fn wait_until_after_response() {
+    utils::setup();
+    let log_rx = utils::setup_logger();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export function handler(request, context) {
+    context.waitUntil(new Promise(resolve => setTimeout(resolve, 20)).then(() => console.log('background')));
+    return new Response('Hello');
+}"
+            .into(),
+        )
+        .metadata(Some(("".to_owned(), "".to_owned()))),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello"))
+    );
+    assert!(log_rx.is_empty());
+    assert_eq!(log_rx.recv_async().await.unwrap(), "background".to_string());
+}
+
+#[tokio::test]
+#[serial]
+async fn wait_until_rejection_is_logged() {
+    utils::setup();
+    let log_rx = utils::setup_logger();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export function handler(request, context) {
+    context.waitUntil(Promise.reject(new Error('Failed')));
+    return new Response('Hello');
+}"
+            .into(),
+        )
+        .metadata(Some(("".to_owned(), "".to_owned()))),
+    );
+    send(Request::default());
+    send(Request::default());
+
+    // The rejection isn't sent as the result of the next request
+    for _ in 0..2 {
+        assert_eq!(
+            receiver.recv_async().await.unwrap(),
+            RunResult::Response(Response::from("Hello"))
+        );
+    }
+
+    assert_eq!(
+        log_rx.recv_async().await.unwrap(),
+        "Uncaught error in waitUntil(): Error: Failed".to_string()
+    );
+}
+
+#[tokio::test]
+async fn statistics_after_background_work() {
+    utils::setup();
+    let (statistics_tx, statistics_rx) = flume::unbounded();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export function handler(request, context) {
+    context.waitUntil(new Promise(resolve => setTimeout(resolve, 50)));
+    return new Response('Hello');
+}"
+            .into(),
+        )
+        .on_statistics_callback(Box::new(move |_, statistics| {
+            statistics_tx.send(statistics).unwrap_or(());
+        })),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello"))
+    );
+    assert!(statistics_rx.is_empty());
+    assert!(statistics_rx.recv_async().await.is_ok());
+}
+
+#[tokio::test]
+#[serial]
+async fn background_timeout() {
+    utils::setup();
+    let log_rx = utils::setup_logger();
+    let (statistics_tx, statistics_rx) = flume::unbounded();
+    let (send, receiver) = utils::create_isolate(
+        IsolateOptions::new(
+            "export function handler(request, context) {
+    context.waitUntil(new Promise(resolve => setTimeout(resolve, 200)).then(() => console.log('background')));
+    return new Response('Hello');
+}"
+            .into(),
+        )
+        .metadata(Some(("".to_owned(), "".to_owned())))
+        .background_timeout(Duration::from_millis(20))
+        .on_statistics_callback(Box::new(move |_, statistics| {
+            statistics_tx.send(statistics).unwrap_or(());
+        })),
+    );
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Hello"))
+    );
+
+    // The request is finished once the background timeout is reached,
+    // and the pending work doesn't run anymore while the isolate is idle
+    assert!(statistics_rx.recv_async().await.is_ok());
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), log_rx.recv_async())
+            .await
+            .is_err()
+    );
+}
@@ -0,0 +1,67 @@
+use lagon_runtime_http::{Request, Response, RunResult};
+use lagon_runtime_isolate::options::IsolateOptions;
+
+mod utils;
+
+// (module (func (export "add") (param i32 i32) (result i32) local.get 0 local.get 1 i32.add))
+const ADD_WASM: &str = "AGFzbQEAAAABBwFgAn9/AX8DAgEABwcBA2FkZAAACgkBBwAgACABags=";
+
+// Decoded like the `binary` loader of ESBuild does for `.wasm` imports
+fn wasm_handler(body: &str) -> String {
+    format!(
+        "const wasm = Uint8Array.from(atob('{ADD_WASM}'), char => char.charCodeAt(0));
+
+export async function handler() {{
+    {body}
+}}"
+    )
+}
+
+#[tokio::test]
+async fn instantiate_wasm() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(wasm_handler(
+        "const { instance } = await WebAssembly.instantiate(wasm);
+    return new Response(instance.exports.add(1, 2));",
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("3"))
+    );
+}
+
+#[tokio::test]
+async fn instantiate_wasm_sync() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(wasm_handler(
+        "const module = new WebAssembly.Module(wasm);
+    const instance = new WebAssembly.Instance(module);
+    return new Response(instance.exports.add(40, 2));",
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("42"))
+    );
+}
+
+#[tokio::test]
+async fn invalid_wasm() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export async function handler() {
+    await WebAssembly.instantiate(new Uint8Array([0, 1, 2, 3]));
+    return new Response('Unreachable');
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert!(matches!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error(error) if error.contains("CompileError")
+    ));
+}
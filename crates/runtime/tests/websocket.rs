@@ -0,0 +1,443 @@
+use futures::{SinkExt, StreamExt};
+use lagon_runtime_http::{Request, Response, RunResult, WebSocketMessage};
+use lagon_runtime_isolate::options::IsolateOptions;
+use std::{collections::HashMap, time::Duration};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::{
+    protocol::{frame::coding::CloseCode, CloseFrame},
+    Message,
+};
+
+mod utils;
+
+// Echoes text and binary messages, and closes the connection with a
+// 4000 code when receiving "close"
+async fn run_echo_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut websocket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+                while let Some(Ok(message)) = websocket.next().await {
+                    match message {
+                        Message::Text(text) if text == "close" => {
+                            websocket
+                                .close(Some(CloseFrame {
+                                    code: CloseCode::from(4000),
+                                    reason: "Bye".into(),
+                                }))
+                                .await
+                                .unwrap_or(());
+                        }
+                        Message::Text(_) | Message::Binary(_) => {
+                            websocket.send(message).await.unwrap_or(());
+                        }
+                        _ => {}
+                    }
+                }
+            });
+        }
+    });
+
+    format!("ws://{address}")
+}
+
+#[tokio::test]
+async fn text_messages() {
+    utils::setup();
+    let url = run_echo_server().await;
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('{url}');
+        const states = [websocket.readyState];
+
+        websocket.onopen = () => {{
+            states.push(websocket.readyState);
+            websocket.send('Hello');
+        }};
+
+        websocket.addEventListener('message', event => {{
+            websocket.close();
+            resolve(new Response(`${{event.data}} ${{states}} ${{event.origin}}`));
+        }});
+    }});
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(format!("Hello 0,1 {url}").as_str()))
+    );
+}
+
+#[tokio::test]
+async fn binary_messages() {
+    utils::setup();
+    let url = run_echo_server().await;
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('{url}');
+        const messages = [];
+
+        websocket.onopen = () => {{
+            websocket.send(new Uint8Array([1, 2, 3]));
+            websocket.send(new Uint16Array([1]).buffer);
+        }};
+
+        websocket.onmessage = async event => {{
+            messages.push(event.data);
+
+            if (messages.length === 1) {{
+                websocket.binaryType = 'arraybuffer';
+                return;
+            }}
+
+            const blob = messages[0] instanceof Blob ? [...new Uint8Array(await messages[0].arrayBuffer())] : null;
+            const buffer = messages[1] instanceof ArrayBuffer ? [...new Uint8Array(messages[1])] : null;
+
+            websocket.close();
+            resolve(new Response(`${{blob}} ${{buffer}}`));
+        }};
+    }});
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("1,2,3 1,0"))
+    );
+}
+
+#[tokio::test]
+async fn close_handshake() {
+    utils::setup();
+    let url = run_echo_server().await;
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('{url}');
+
+        websocket.onopen = () => {{
+            websocket.close(3001, 'Done');
+
+            // Only the first close() starts the closing handshake
+            websocket.close();
+            websocket.send('Ignored');
+        }};
+
+        websocket.onclose = event => {{
+            resolve(new Response(`${{event.code}} ${{event.reason}} ${{event.wasClean}} ${{websocket.readyState}}`));
+        }};
+    }});
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("3001 Done true 3"))
+    );
+}
+
+#[tokio::test]
+async fn server_close_handshake() {
+    utils::setup();
+    let url = run_echo_server().await;
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('{url}');
+        const events = [];
+
+        websocket.onopen = () => websocket.send('close');
+        websocket.onerror = () => events.push('error');
+        websocket.onclose = event => {{
+            events.push(`close ${{event.code}} ${{event.reason}} ${{event.wasClean}}`);
+            resolve(new Response(events.join(',')));
+        }};
+    }});
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("close 4000 Bye true"))
+    );
+}
+
+#[tokio::test]
+async fn connection_error() {
+    utils::setup();
+    // Nothing listens on this port once the listener is dropped
+    let url = {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!("ws://{}", listener.local_addr().unwrap())
+    };
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('{url}');
+        const events = [];
+
+        websocket.onopen = () => events.push('open');
+        websocket.onerror = () => events.push('error');
+        websocket.onclose = event => {{
+            events.push(`close ${{event.code}} ${{event.wasClean}} ${{websocket.readyState}}`);
+            resolve(new Response(events.join(',')));
+        }};
+    }});
+}}"
+    )));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("error,close 1006 false 3"))
+    );
+}
+
+#[tokio::test]
+async fn invalid_arguments() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler() {
+    const errors = [];
+
+    for (const args of [['http://localhost'], ['ftp://localhost'], ['ws://localhost#hash'], ['ws://localhost', ['a', 'a']]]) {
+        try {
+            new WebSocket(...args).close();
+            errors.push('none');
+        } catch (error) {
+            errors.push(error.name);
+        }
+    }
+
+    return new Response(errors.join(','));
+}"
+        .into(),
+    ));
+    send(Request::default());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("none,SyntaxError,SyntaxError,SyntaxError"))
+    );
+}
+
+#[tokio::test]
+async fn closed_with_request() {
+    utils::setup();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("ws://{}", listener.local_addr().unwrap());
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(format!(
+        "export function handler() {{
+    return new Promise(resolve => {{
+        const websocket = new WebSocket('{url}');
+        websocket.onopen = () => resolve(new Response('Done'));
+    }});
+}}"
+    )));
+    send(Request::default());
+
+    let (stream, _) = listener.accept().await.unwrap();
+    let mut websocket = tokio_tungstenite::accept_async(stream).await.unwrap();
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from("Done"))
+    );
+
+    // The connection is dropped without a closing handshake once the request is done
+    let message = tokio::time::timeout(Duration::from_secs(1), websocket.next())
+        .await
+        .unwrap();
+
+    assert!(!matches!(message, Some(Ok(_))));
+}
+
+fn upgrade_request() -> Request {
+    let mut headers = HashMap::new();
+    headers.insert("upgrade".into(), vec!["websocket".into()]);
+
+    Request {
+        headers: Some(headers),
+        url: "http://localhost/socket".into(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn upgrade() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler(request) {
+    const { socket, response } = upgradeWebSocket(request, { protocol: 'chat' });
+
+    socket.onmessage = event => {
+        if (event.data === 'close') {
+            socket.close(4000, 'Bye');
+        } else {
+            socket.send(event.data);
+        }
+    };
+
+    socket.accept();
+    return response;
+}"
+        .into(),
+    ));
+    send(upgrade_request());
+
+    let upgrade = match receiver.recv_async().await.unwrap() {
+        RunResult::WebSocket(upgrade) => upgrade,
+        result => panic!("Expected a WebSocket upgrade, got {result:?}"),
+    };
+
+    assert_eq!(upgrade.response.status, 101);
+    assert_eq!(
+        upgrade
+            .response
+            .headers
+            .as_ref()
+            .unwrap()
+            .get("sec-websocket-protocol"),
+        Some(&vec!["chat".to_string()])
+    );
+
+    upgrade
+        .incoming
+        .send_async(WebSocketMessage::Text("Hello".into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        upgrade.outgoing.recv_async().await.unwrap(),
+        WebSocketMessage::Text("Hello".into())
+    );
+
+    upgrade
+        .incoming
+        .send_async(WebSocketMessage::Binary(vec![1, 2, 3]))
+        .await
+        .unwrap();
+    assert_eq!(
+        upgrade.outgoing.recv_async().await.unwrap(),
+        WebSocketMessage::Binary(vec![1, 2, 3])
+    );
+
+    upgrade
+        .incoming
+        .send_async(WebSocketMessage::Text("close".into()))
+        .await
+        .unwrap();
+    assert_eq!(
+        upgrade.outgoing.recv_async().await.unwrap(),
+        WebSocketMessage::Close(Some((4000, "Bye".into())))
+    );
+
+    // Completes the closing handshake, which drops the WebSocket
+    upgrade
+        .incoming
+        .send_async(WebSocketMessage::Close(Some((4000, "Bye".into()))))
+        .await
+        .unwrap();
+    assert!(upgrade.outgoing.recv_async().await.is_err());
+}
+
+#[tokio::test]
+async fn upgrade_client_close() {
+    utils::setup();
+    let log_rx = utils::setup_logger();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler(request) {
+    const { socket, response } = upgradeWebSocket(request);
+    socket.onclose = event => console.log(`${event.code} ${event.reason} ${event.wasClean}`);
+    socket.accept();
+    return response;
+}"
+        .into(),
+    ));
+    send(upgrade_request());
+
+    let upgrade = match receiver.recv_async().await.unwrap() {
+        RunResult::WebSocket(upgrade) => upgrade,
+        result => panic!("Expected a WebSocket upgrade, got {result:?}"),
+    };
+
+    upgrade
+        .incoming
+        .send_async(WebSocketMessage::Close(Some((3000, "Done".into()))))
+        .await
+        .unwrap();
+
+    assert_eq!(log_rx.recv_async().await.unwrap(), "3000 Done true");
+    assert!(upgrade.outgoing.recv_async().await.is_err());
+}
+
+#[tokio::test]
+async fn upgrade_errors() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler(request) {
+    const errors = [];
+
+    try {
+        upgradeWebSocket(new Request('http://localhost'));
+    } catch (error) {
+        errors.push(error.name);
+    }
+
+    const { socket } = upgradeWebSocket(request);
+
+    try {
+        new WebSocket('ws://localhost').accept();
+    } catch (error) {
+        errors.push(error.name);
+    }
+
+    socket.accept();
+
+    try {
+        socket.accept();
+    } catch (error) {
+        errors.push(error.name);
+    }
+
+    socket.close();
+    return new Response(errors.join(','));
+}"
+        .into(),
+    ));
+    send(upgrade_request());
+
+    assert_eq!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Response(Response::from(
+            "TypeError,InvalidStateError,InvalidStateError"
+        ))
+    );
+}
+
+#[tokio::test]
+async fn upgrade_not_accepted() {
+    utils::setup();
+    let (send, receiver) = utils::create_isolate(IsolateOptions::new(
+        "export function handler(request) {
+    return upgradeWebSocket(request).response;
+}"
+        .into(),
+    ));
+    send(upgrade_request());
+
+    assert!(matches!(
+        receiver.recv_async().await.unwrap(),
+        RunResult::Error(error) if error.contains("The WebSocket must be accepted with accept()")
+    ));
+}
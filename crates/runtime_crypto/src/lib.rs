@@ -2,14 +2,18 @@ use aes::{cipher::typenum::U16, Aes256};
 use aes_gcm::AesGcm;
 use anyhow::{anyhow, Result};
 use hmac::Hmac;
-use lagon_runtime_v8_utils::{extract_v8_string, extract_v8_uint8array, v8_string};
+use lagon_runtime_v8_utils::{
+    extract_v8_integer, extract_v8_string, extract_v8_uint8array, v8_string,
+};
 use sha2::Sha256;
 
 pub mod methods;
+mod padding;
 
 pub type HmacSha256 = Hmac<Sha256>;
 pub type Aes256Gcm = AesGcm<Aes256, U16>;
 
+#[derive(Clone)]
 pub enum Sha {
     Sha1,
     Sha256,
@@ -20,35 +24,204 @@ pub enum Sha {
 pub enum Algorithm {
     Hmac,
     AesGcm(Vec<u8>),
+    Ecdsa(Sha),
+    Ed25519,
+    RsassaPkcs1v15(Sha),
+    // The salt length
+    RsaPss(Sha, usize),
+    // The optional label
+    RsaOaep(Sha, Option<Vec<u8>>),
+    // The salt and iterations
+    Pbkdf2(Sha, Vec<u8>, u32),
+    // The salt and info
+    Hkdf(Sha, Vec<u8>, Vec<u8>),
+    // The peer's public key
+    Ecdh(Vec<u8>),
 }
 
+pub enum NamedCurve {
+    P256,
+    P384,
+}
+
+// The algorithm of an asymmetric key, needed to import and export it
+pub enum KeyAlgorithm {
+    // ECDSA and ECDH keys are the same
+    Ec(NamedCurve),
+    Ed25519,
+    // RSASSA-PKCS1-v1_5, RSA-PSS and RSA-OAEP keys are the same
+    Rsa,
+}
+
+// The algorithm of a generated key pair
+pub enum GenerateKeyAlgorithm {
+    // The modulus length and public exponent
+    Rsa(usize, Vec<u8>),
+    Ec(NamedCurve),
+}
+
+pub enum KeyFormat {
+    Raw,
+    Pkcs8,
+    Spki,
+    Jwk,
+}
+
+fn get_property<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    object: v8::Local<v8::Object>,
+    name: &str,
+) -> Option<v8::Local<'a, v8::Value>> {
+    let key = v8_string(scope, name);
+    object.get(scope, key.into())
+}
+
+// Algorithms without parameters can also be given by name
+fn get_parameter<'a>(
+    scope: &mut v8::HandleScope<'a>,
+    algorithm: v8::Local<v8::Value>,
+    name: &str,
+) -> Option<v8::Local<'a, v8::Value>> {
+    if algorithm.is_string() {
+        return None;
+    }
+
+    let algorithm = algorithm.to_object(scope)?;
+    get_property(scope, algorithm, name).filter(|value| !value.is_undefined())
+}
+
+fn extract_hash(scope: &mut v8::HandleScope, algorithm: v8::Local<v8::Value>) -> Result<Sha> {
+    match get_parameter(scope, algorithm, "hash") {
+        Some(hash) => get_sha(&extract_algorithm_object_or_string(scope, hash)?),
+        None => Err(anyhow!("Algorithm hash not found")),
+    }
+}
+
+fn extract_buffer(
+    scope: &mut v8::HandleScope,
+    algorithm: v8::Local<v8::Value>,
+    name: &str,
+) -> Result<Vec<u8>> {
+    match get_parameter(scope, algorithm, name) {
+        Some(buffer) => extract_v8_uint8array(buffer),
+        None => Err(anyhow!("Algorithm {} not found", name)),
+    }
+}
+
+// The key is needed by RSA algorithms, which use the hash of the key
 pub fn extract_algorithm_object(
     scope: &mut v8::HandleScope,
     value: v8::Local<v8::Value>,
+    key: v8::Local<v8::Value>,
 ) -> Result<Algorithm> {
-    if let Some(algorithm) = value.to_object(scope) {
-        let name_key = v8_string(scope, "name");
-        let name = match algorithm.get(scope, name_key.into()) {
-            Some(name) => extract_v8_string(name, scope)?,
-            None => return Err(anyhow!("Algorithm name not found")),
-        };
+    let name = extract_algorithm_object_or_string(scope, value)?;
+
+    match name.as_str() {
+        "HMAC" => Ok(Algorithm::Hmac),
+        "AES-GCM" => Ok(Algorithm::AesGcm(extract_buffer(scope, value, "iv")?)),
+        "ECDSA" => Ok(Algorithm::Ecdsa(extract_hash(scope, value)?)),
+        "Ed25519" => Ok(Algorithm::Ed25519),
+        "RSASSA-PKCS1-v1_5" => Ok(Algorithm::RsassaPkcs1v15(extract_cryptokey_hash(
+            scope, key,
+        )?)),
+        "RSA-PSS" => {
+            let salt_length = match get_parameter(scope, value, "saltLength") {
+                Some(salt_length) => extract_v8_integer(salt_length, scope)?,
+                None => return Err(anyhow!("Algorithm saltLength not found")),
+            };
 
-        if name == "HMAC" {
-            return Ok(Algorithm::Hmac);
+            Ok(Algorithm::RsaPss(
+                extract_cryptokey_hash(scope, key)?,
+                salt_length.try_into()?,
+            ))
         }
+        "RSA-OAEP" => {
+            let label = match get_parameter(scope, value, "label") {
+                Some(label) => Some(extract_v8_uint8array(label)?),
+                None => None,
+            };
 
-        if name == "AES-GCM" {
-            let iv_key = v8_string(scope, "iv");
-            let iv = match algorithm.get(scope, iv_key.into()) {
-                Some(iv) => extract_v8_uint8array(iv)?,
-                None => return Err(anyhow!("Algorithm iv not found")),
+            Ok(Algorithm::RsaOaep(
+                extract_cryptokey_hash(scope, key)?,
+                label,
+            ))
+        }
+        "PBKDF2" => {
+            let iterations = match get_parameter(scope, value, "iterations") {
+                Some(iterations) => extract_v8_integer(iterations, scope)?,
+                None => return Err(anyhow!("Algorithm iterations not found")),
             };
 
-            return Ok(Algorithm::AesGcm(iv));
+            Ok(Algorithm::Pbkdf2(
+                extract_hash(scope, value)?,
+                extract_buffer(scope, value, "salt")?,
+                iterations.try_into()?,
+            ))
         }
+        "HKDF" => Ok(Algorithm::Hkdf(
+            extract_hash(scope, value)?,
+            extract_buffer(scope, value, "salt")?,
+            extract_buffer(scope, value, "info")?,
+        )),
+        "ECDH" => match get_parameter(scope, value, "public") {
+            Some(public) => Ok(Algorithm::Ecdh(extract_cryptokey_key_value(scope, public)?)),
+            None => Err(anyhow!("Algorithm public not found")),
+        },
+        _ => Err(anyhow!("Algorithm not supported")),
     }
+}
 
-    Err(anyhow!("Algorithm not supported"))
+fn extract_named_curve(
+    scope: &mut v8::HandleScope,
+    algorithm: v8::Local<v8::Value>,
+) -> Result<NamedCurve> {
+    let named_curve = match get_parameter(scope, algorithm, "namedCurve") {
+        Some(named_curve) => extract_v8_string(named_curve, scope)?,
+        None => return Err(anyhow!("Algorithm namedCurve not found")),
+    };
+
+    match named_curve.as_str() {
+        "P-256" => Ok(NamedCurve::P256),
+        "P-384" => Ok(NamedCurve::P384),
+        _ => Err(anyhow!("Named curve not supported")),
+    }
+}
+
+pub fn extract_key_algorithm(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Result<KeyAlgorithm> {
+    let name = extract_algorithm_object_or_string(scope, value)?;
+
+    match name.as_str() {
+        "ECDSA" | "ECDH" => Ok(KeyAlgorithm::Ec(extract_named_curve(scope, value)?)),
+        "Ed25519" => Ok(KeyAlgorithm::Ed25519),
+        "RSASSA-PKCS1-v1_5" | "RSA-PSS" | "RSA-OAEP" => Ok(KeyAlgorithm::Rsa),
+        _ => Err(anyhow!("Algorithm not supported")),
+    }
+}
+
+pub fn extract_generate_key_algorithm(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Result<GenerateKeyAlgorithm> {
+    let name = extract_algorithm_object_or_string(scope, value)?;
+
+    match name.as_str() {
+        "RSASSA-PKCS1-v1_5" | "RSA-PSS" | "RSA-OAEP" => {
+            let modulus_length = match get_parameter(scope, value, "modulusLength") {
+                Some(modulus_length) => extract_v8_integer(modulus_length, scope)?,
+                None => return Err(anyhow!("Algorithm modulusLength not found")),
+            };
+
+            Ok(GenerateKeyAlgorithm::Rsa(
+                modulus_length.try_into()?,
+                extract_buffer(scope, value, "publicExponent")?,
+            ))
+        }
+        "ECDSA" | "ECDH" => Ok(GenerateKeyAlgorithm::Ec(extract_named_curve(scope, value)?)),
+        _ => Err(anyhow!("Algorithm not supported")),
+    }
 }
 
 pub fn extract_algorithm_object_or_string(
@@ -58,8 +231,7 @@ pub fn extract_algorithm_object_or_string(
     if value.is_string() {
         return extract_v8_string(value, scope);
     } else if let Some(algorithm) = value.to_object(scope) {
-        let name_key = v8_string(scope, "name");
-        let name = match algorithm.get(scope, name_key.into()) {
+        let name = match get_property(scope, algorithm, "name") {
             Some(name) => extract_v8_string(name, scope)?,
             None => return Err(anyhow!("Algorithm name not found")),
         };
@@ -75,9 +247,7 @@ pub fn extract_cryptokey_key_value(
     value: v8::Local<v8::Value>,
 ) -> Result<Vec<u8>> {
     if let Some(key) = value.to_object(scope) {
-        let value_key = v8_string(scope, "keyValue");
-
-        return match key.get(scope, value_key.into()) {
+        return match get_property(scope, key, "keyValue") {
             Some(value) => Ok(extract_v8_uint8array(value)?),
             None => Err(anyhow!("CryptoKey keyValue not found")),
         };
@@ -86,6 +256,25 @@ pub fn extract_cryptokey_key_value(
     Err(anyhow!("CryptoKey not supported"))
 }
 
+pub fn extract_cryptokey_hash(
+    scope: &mut v8::HandleScope,
+    value: v8::Local<v8::Value>,
+) -> Result<Sha> {
+    if let Some(key) = value.to_object(scope) {
+        let algorithm = match get_property(scope, key, "algorithm") {
+            Some(algorithm) => algorithm,
+            None => return Err(anyhow!("CryptoKey algorithm not found")),
+        };
+
+        return match get_parameter(scope, algorithm, "hash") {
+            Some(hash) => get_sha(&extract_algorithm_object_or_string(scope, hash)?),
+            None => Err(anyhow!("CryptoKey hash not found")),
+        };
+    }
+
+    Err(anyhow!("CryptoKey not supported"))
+}
+
 pub fn get_sha(hash: &str) -> Result<Sha> {
     match hash {
         "SHA-1" => Ok(Sha::Sha1),
@@ -95,3 +284,13 @@ pub fn get_sha(hash: &str) -> Result<Sha> {
         _ => Err(anyhow!("hash not found")),
     }
 }
+
+pub fn get_key_format(format: &str) -> Result<KeyFormat> {
+    match format {
+        "raw" => Ok(KeyFormat::Raw),
+        "pkcs8" => Ok(KeyFormat::Pkcs8),
+        "spki" => Ok(KeyFormat::Spki),
+        "jwk" => Ok(KeyFormat::Jwk),
+        _ => Err(anyhow!("Key format not supported")),
+    }
+}
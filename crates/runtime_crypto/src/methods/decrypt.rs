@@ -1,7 +1,9 @@
 use aes_gcm::{aead::Aead, KeyInit, Nonce};
 use anyhow::{anyhow, Result};
+use rand::rngs::OsRng;
+use rsa::{pkcs8::DecodePrivateKey, RsaPrivateKey};
 
-use crate::{Aes256Gcm, Algorithm};
+use crate::{padding::oaep, Aes256Gcm, Algorithm};
 
 pub fn decrypt(algorithm: Algorithm, key_value: Vec<u8>, data: Vec<u8>) -> Result<Vec<u8>> {
     match algorithm {
@@ -14,6 +16,16 @@ pub fn decrypt(algorithm: Algorithm, key_value: Vec<u8>, data: Vec<u8>) -> Resul
                 Err(_) => Err(anyhow!("Decryption failed")),
             }
         }
+        Algorithm::RsaOaep(sha, label) => {
+            let key = RsaPrivateKey::from_pkcs8_der(&key_value)
+                .map_err(|_| anyhow!("Invalid RSA private key"))?;
+
+            // Blinding protects against timing attacks
+            match key.decrypt_blinded(&mut OsRng, oaep(sha, label)?, &data) {
+                Ok(result) => Ok(result),
+                Err(_) => Err(anyhow!("Decryption failed")),
+            }
+        }
         _ => Err(anyhow!("Algorithm not supported")),
     }
 }
@@ -0,0 +1,119 @@
+use anyhow::{anyhow, Result};
+use hkdf::Hkdf;
+use p256::pkcs8::{DecodePrivateKey as _, DecodePublicKey as _};
+use pbkdf2::pbkdf2_hmac;
+use sha1::Sha1;
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::{Algorithm, Sha};
+
+// PBKDF2 and HKDF need a length in bits that fits in bytes
+fn byte_length(length: Option<usize>) -> Result<usize> {
+    match length {
+        Some(length) if length % 8 == 0 => Ok(length / 8),
+        Some(_) => Err(anyhow!("Length must be a multiple of 8")),
+        None => Err(anyhow!("Length is required")),
+    }
+}
+
+fn pbkdf2(sha: Sha, password: &[u8], salt: &[u8], iterations: u32, length: usize) -> Vec<u8> {
+    let mut bits = vec![0; length];
+
+    match sha {
+        Sha::Sha1 => pbkdf2_hmac::<Sha1>(password, salt, iterations, &mut bits),
+        Sha::Sha256 => pbkdf2_hmac::<Sha256>(password, salt, iterations, &mut bits),
+        Sha::Sha384 => pbkdf2_hmac::<Sha384>(password, salt, iterations, &mut bits),
+        Sha::Sha512 => pbkdf2_hmac::<Sha512>(password, salt, iterations, &mut bits),
+    }
+
+    bits
+}
+
+fn hkdf(sha: Sha, key: &[u8], salt: &[u8], info: &[u8], length: usize) -> Result<Vec<u8>> {
+    let mut bits = vec![0; length];
+
+    // An empty salt is the same as a salt of zeros, as described by RFC 5869
+    let result = match sha {
+        Sha::Sha1 => Hkdf::<Sha1>::new(Some(salt), key).expand(info, &mut bits),
+        Sha::Sha256 => Hkdf::<Sha256>::new(Some(salt), key).expand(info, &mut bits),
+        Sha::Sha384 => Hkdf::<Sha384>::new(Some(salt), key).expand(info, &mut bits),
+        Sha::Sha512 => Hkdf::<Sha512>::new(Some(salt), key).expand(info, &mut bits),
+    };
+
+    match result {
+        Ok(_) => Ok(bits),
+        Err(_) => Err(anyhow!("Length is too long")),
+    }
+}
+
+fn invalid_public_key<E>(_: E) -> anyhow::Error {
+    anyhow!("Public key must be on the same curve")
+}
+
+fn ecdh(private_key: &[u8], public_key: &[u8]) -> Result<Vec<u8>> {
+    // The PKCS#8 document contains the curve, so it can only be parsed by one of them
+    if let Ok(key) = p256::SecretKey::from_pkcs8_der(private_key) {
+        let public_key =
+            p256::PublicKey::from_public_key_der(public_key).map_err(invalid_public_key)?;
+        let secret = p256::ecdh::diffie_hellman(key.to_nonzero_scalar(), public_key.as_affine());
+
+        return Ok(secret.raw_secret_bytes().to_vec());
+    }
+
+    if let Ok(key) = p384::SecretKey::from_pkcs8_der(private_key) {
+        let public_key =
+            p384::PublicKey::from_public_key_der(public_key).map_err(invalid_public_key)?;
+        let secret = p384::ecdh::diffie_hellman(key.to_nonzero_scalar(), public_key.as_affine());
+
+        return Ok(secret.raw_secret_bytes().to_vec());
+    }
+
+    Err(anyhow!("Invalid private key"))
+}
+
+// The length is in bits, and is optional for ECDH
+pub fn derive_bits(
+    algorithm: Algorithm,
+    key_value: Vec<u8>,
+    length: Option<usize>,
+) -> Result<Vec<u8>> {
+    match algorithm {
+        Algorithm::Pbkdf2(sha, salt, iterations) => {
+            let length = byte_length(length)?;
+
+            if length == 0 {
+                return Err(anyhow!("Length must be greater than 0"));
+            }
+
+            if iterations == 0 {
+                return Err(anyhow!("Iterations must be greater than 0"));
+            }
+
+            Ok(pbkdf2(sha, &key_value, &salt, iterations, length))
+        }
+        Algorithm::Hkdf(sha, salt, info) => {
+            hkdf(sha, &key_value, &salt, &info, byte_length(length)?)
+        }
+        Algorithm::Ecdh(public_key) => {
+            let mut bits = ecdh(&key_value, &public_key)?;
+
+            if let Some(length) = length {
+                if length > bits.len() * 8 {
+                    return Err(anyhow!("Length is too long"));
+                }
+
+                // Only keep the first bits of the shared secret
+                bits.truncate(length.div_ceil(8));
+
+                if length % 8 != 0 {
+                    if let Some(last) = bits.last_mut() {
+                        *last &= 0xff << (8 - length % 8);
+                    }
+                }
+            }
+
+            Ok(bits)
+        }
+        _ => Err(anyhow!("Algorithm not supported")),
+    }
+}
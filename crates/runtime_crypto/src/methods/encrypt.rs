@@ -1,7 +1,9 @@
 use aes_gcm::{aead::Aead, KeyInit, Nonce};
 use anyhow::{anyhow, Result};
+use rand::rngs::OsRng;
+use rsa::{pkcs8::DecodePublicKey, RsaPublicKey};
 
-use crate::{Aes256Gcm, Algorithm};
+use crate::{padding::oaep, Aes256Gcm, Algorithm};
 
 pub fn encrypt(algorithm: Algorithm, key_value: Vec<u8>, data: Vec<u8>) -> Result<Vec<u8>> {
     match algorithm {
@@ -14,6 +16,15 @@ pub fn encrypt(algorithm: Algorithm, key_value: Vec<u8>, data: Vec<u8>) -> Resul
                 Err(_) => Err(anyhow!("Encryption failed")),
             }
         }
+        Algorithm::RsaOaep(sha, label) => {
+            let key = RsaPublicKey::from_public_key_der(&key_value)
+                .map_err(|_| anyhow!("Invalid RSA public key"))?;
+
+            match key.encrypt(&mut OsRng, oaep(sha, label)?, &data) {
+                Ok(result) => Ok(result),
+                Err(_) => Err(anyhow!("Encryption failed")),
+            }
+        }
         _ => Err(anyhow!("Algorithm not supported")),
     }
 }
@@ -0,0 +1,140 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::pkcs8::{DecodePrivateKey as _, DecodePublicKey as _};
+use rsa::{
+    pkcs8::{DecodePrivateKey as _, DecodePublicKey as _},
+    traits::{PrivateKeyParts, PublicKeyParts},
+    BigUint, RsaPrivateKey, RsaPublicKey,
+};
+use serde_json::json;
+
+use crate::{KeyAlgorithm, KeyFormat, NamedCurve};
+
+// Keys are stored as PKCS#8 (private) or SPKI (public) documents,
+// which are returned as-is. JWKs are returned as JSON
+macro_rules! export_ec_key {
+    ($curve: ident, $format: expr, $key_value: expr, $private: expr) => {{
+        use $curve::{
+            elliptic_curve::sec1::ToEncodedPoint,
+            pkcs8::{DecodePrivateKey as _, DecodePublicKey as _},
+            PublicKey, SecretKey,
+        };
+
+        let key_value: Vec<u8> = $key_value;
+
+        match ($format, $private) {
+            (KeyFormat::Pkcs8, true) | (KeyFormat::Spki, false) => Ok(key_value),
+            (KeyFormat::Jwk, true) => {
+                let key = SecretKey::from_pkcs8_der(&key_value).map_err(invalid_key)?;
+                Ok(key.to_jwk_string().as_bytes().to_vec())
+            }
+            (KeyFormat::Jwk, false) => {
+                let key = PublicKey::from_public_key_der(&key_value).map_err(invalid_key)?;
+                Ok(key.to_jwk_string().into_bytes())
+            }
+            (KeyFormat::Raw, false) => {
+                let key = PublicKey::from_public_key_der(&key_value).map_err(invalid_key)?;
+                Ok(key.to_encoded_point(false).as_bytes().to_vec())
+            }
+            _ => Err(anyhow!("Key can't be exported in this format")),
+        }
+    }};
+}
+
+fn invalid_key<E>(_: E) -> anyhow::Error {
+    anyhow!("Invalid key")
+}
+
+fn export_ed25519_key(format: KeyFormat, key_value: Vec<u8>, private: bool) -> Result<Vec<u8>> {
+    match (format, private) {
+        (KeyFormat::Pkcs8, true) | (KeyFormat::Spki, false) => Ok(key_value),
+        (KeyFormat::Jwk, true) => {
+            let key = ed25519_dalek::SigningKey::from_pkcs8_der(&key_value).map_err(invalid_key)?;
+            let jwk = json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "x": URL_SAFE_NO_PAD.encode(key.verifying_key().to_bytes()),
+                "d": URL_SAFE_NO_PAD.encode(key.to_bytes()),
+            });
+
+            Ok(jwk.to_string().into_bytes())
+        }
+        (KeyFormat::Jwk, false) => {
+            let key = ed25519_dalek::VerifyingKey::from_public_key_der(&key_value)
+                .map_err(invalid_key)?;
+            let jwk = json!({
+                "kty": "OKP",
+                "crv": "Ed25519",
+                "x": URL_SAFE_NO_PAD.encode(key.to_bytes()),
+            });
+
+            Ok(jwk.to_string().into_bytes())
+        }
+        (KeyFormat::Raw, false) => {
+            let key = ed25519_dalek::VerifyingKey::from_public_key_der(&key_value)
+                .map_err(invalid_key)?;
+            Ok(key.to_bytes().to_vec())
+        }
+        _ => Err(anyhow!("Key can't be exported in this format")),
+    }
+}
+
+fn encode_uint(value: &BigUint) -> String {
+    URL_SAFE_NO_PAD.encode(value.to_bytes_be())
+}
+
+fn export_rsa_key(format: KeyFormat, key_value: Vec<u8>, private: bool) -> Result<Vec<u8>> {
+    match (format, private) {
+        (KeyFormat::Pkcs8, true) | (KeyFormat::Spki, false) => Ok(key_value),
+        (KeyFormat::Jwk, true) => {
+            let key = RsaPrivateKey::from_pkcs8_der(&key_value).map_err(invalid_key)?;
+            let (p, q) = match key.primes() {
+                [p, q] => (p, q),
+                _ => return Err(anyhow!("Multi-prime keys can't be exported")),
+            };
+            let (dp, dq, qi) = match (key.dp(), key.dq(), key.crt_coefficient()) {
+                (Some(dp), Some(dq), Some(qi)) => (dp, dq, qi),
+                _ => return Err(anyhow!("Invalid key")),
+            };
+
+            let jwk = json!({
+                "kty": "RSA",
+                "n": encode_uint(key.n()),
+                "e": encode_uint(key.e()),
+                "d": encode_uint(key.d()),
+                "p": encode_uint(p),
+                "q": encode_uint(q),
+                "dp": encode_uint(dp),
+                "dq": encode_uint(dq),
+                "qi": encode_uint(&qi),
+            });
+
+            Ok(jwk.to_string().into_bytes())
+        }
+        (KeyFormat::Jwk, false) => {
+            let key = RsaPublicKey::from_public_key_der(&key_value).map_err(invalid_key)?;
+            let jwk = json!({
+                "kty": "RSA",
+                "n": encode_uint(key.n()),
+                "e": encode_uint(key.e()),
+            });
+
+            Ok(jwk.to_string().into_bytes())
+        }
+        _ => Err(anyhow!("Key can't be exported in this format")),
+    }
+}
+
+pub fn export_key(
+    algorithm: KeyAlgorithm,
+    format: KeyFormat,
+    key_value: Vec<u8>,
+    private: bool,
+) -> Result<Vec<u8>> {
+    match algorithm {
+        KeyAlgorithm::Ec(NamedCurve::P256) => export_ec_key!(p256, format, key_value, private),
+        KeyAlgorithm::Ec(NamedCurve::P384) => export_ec_key!(p384, format, key_value, private),
+        KeyAlgorithm::Ed25519 => export_ed25519_key(format, key_value, private),
+        KeyAlgorithm::Rsa => export_rsa_key(format, key_value, private),
+    }
+}
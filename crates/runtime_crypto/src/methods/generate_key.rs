@@ -0,0 +1,59 @@
+use anyhow::{anyhow, Result};
+use rand::rngs::OsRng;
+use rsa::{
+    pkcs8::{EncodePrivateKey, EncodePublicKey},
+    BigUint, RsaPrivateKey,
+};
+
+use crate::{GenerateKeyAlgorithm, NamedCurve};
+
+const MIN_MODULUS_LENGTH: usize = 1024;
+// Bigger keys are too slow to generate and use
+const MAX_MODULUS_LENGTH: usize = 4096;
+
+macro_rules! generate_ec_key {
+    ($curve: ident) => {{
+        use $curve::{
+            pkcs8::{EncodePrivateKey as _, EncodePublicKey as _},
+            SecretKey,
+        };
+
+        let private_key = SecretKey::random(&mut OsRng);
+        let public_key = private_key.public_key();
+
+        Ok(vec![
+            private_key.to_pkcs8_der()?.as_bytes().to_vec(),
+            public_key.to_public_key_der()?.as_bytes().to_vec(),
+        ])
+    }};
+}
+
+fn generate_rsa_key(modulus_length: usize, public_exponent: Vec<u8>) -> Result<Vec<Vec<u8>>> {
+    if !(MIN_MODULUS_LENGTH..=MAX_MODULUS_LENGTH).contains(&modulus_length) {
+        return Err(anyhow!(
+            "Modulus length must be between {} and {} bits",
+            MIN_MODULUS_LENGTH,
+            MAX_MODULUS_LENGTH
+        ));
+    }
+
+    let public_exponent = BigUint::from_bytes_be(&public_exponent);
+    let private_key = RsaPrivateKey::new_with_exp(&mut OsRng, modulus_length, &public_exponent)?;
+    let public_key = private_key.to_public_key();
+
+    Ok(vec![
+        private_key.to_pkcs8_der()?.as_bytes().to_vec(),
+        public_key.to_public_key_der()?.as_bytes().to_vec(),
+    ])
+}
+
+// Returns the private (as PKCS#8) and public (as SPKI) keys
+pub fn generate_key(algorithm: GenerateKeyAlgorithm) -> Result<Vec<Vec<u8>>> {
+    match algorithm {
+        GenerateKeyAlgorithm::Rsa(modulus_length, public_exponent) => {
+            generate_rsa_key(modulus_length, public_exponent)
+        }
+        GenerateKeyAlgorithm::Ec(NamedCurve::P256) => generate_ec_key!(p256),
+        GenerateKeyAlgorithm::Ec(NamedCurve::P384) => generate_ec_key!(p384),
+    }
+}
@@ -0,0 +1,231 @@
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ed25519_dalek::pkcs8::{
+    DecodePrivateKey as _, DecodePublicKey as _, EncodePrivateKey as _, EncodePublicKey as _,
+    KeypairBytes,
+};
+use rsa::{
+    pkcs8::{
+        DecodePrivateKey as _, DecodePublicKey as _, EncodePrivateKey as _, EncodePublicKey as _,
+    },
+    traits::{PrivateKeyParts, PublicKeyParts},
+    BigUint, RsaPrivateKey, RsaPublicKey,
+};
+use serde_json::Value;
+
+use crate::{KeyAlgorithm, KeyFormat, NamedCurve};
+
+fn invalid_key_data<E>(_: E) -> anyhow::Error {
+    anyhow!("Invalid key data")
+}
+
+fn is_private_jwk(jwk: &[u8]) -> Result<bool> {
+    let jwk: Value = serde_json::from_slice(jwk).map_err(invalid_key_data)?;
+
+    Ok(jwk.get("d").is_some())
+}
+
+fn jwk_bytes(jwk: &Value, name: &str) -> Result<[u8; 32]> {
+    let value = jwk[name]
+        .as_str()
+        .ok_or_else(|| anyhow!("JWK {} not found", name))?;
+    let bytes = URL_SAFE_NO_PAD.decode(value).map_err(invalid_key_data)?;
+
+    bytes.as_slice().try_into().map_err(invalid_key_data)
+}
+
+fn jwk_uint(jwk: &Value, name: &str) -> Result<BigUint> {
+    let value = jwk[name]
+        .as_str()
+        .ok_or_else(|| anyhow!("JWK {} not found", name))?;
+    let bytes = URL_SAFE_NO_PAD.decode(value).map_err(invalid_key_data)?;
+
+    Ok(BigUint::from_bytes_be(&bytes))
+}
+
+// Private keys are stored as PKCS#8 and public keys as SPKI documents
+macro_rules! import_ec_key {
+    ($curve: ident, $format: expr, $key_data: expr) => {{
+        use $curve::{
+            elliptic_curve::JwkEcKey,
+            pkcs8::{
+                DecodePrivateKey as _, DecodePublicKey as _, EncodePrivateKey as _,
+                EncodePublicKey as _,
+            },
+            PublicKey, SecretKey,
+        };
+
+        let key_data: &[u8] = $key_data;
+
+        let private_key = |key: SecretKey| -> Result<Vec<u8>> {
+            Ok(key
+                .to_pkcs8_der()
+                .map_err(invalid_key_data)?
+                .as_bytes()
+                .to_vec())
+        };
+        let public_key = |key: PublicKey| -> Result<Vec<u8>> {
+            Ok(key
+                .to_public_key_der()
+                .map_err(invalid_key_data)?
+                .as_bytes()
+                .to_vec())
+        };
+
+        match $format {
+            KeyFormat::Raw => {
+                public_key(PublicKey::from_sec1_bytes(key_data).map_err(invalid_key_data)?)
+            }
+            KeyFormat::Spki => {
+                public_key(PublicKey::from_public_key_der(key_data).map_err(invalid_key_data)?)
+            }
+            KeyFormat::Pkcs8 => {
+                private_key(SecretKey::from_pkcs8_der(key_data).map_err(invalid_key_data)?)
+            }
+            KeyFormat::Jwk => {
+                let jwk: JwkEcKey = serde_json::from_slice(key_data).map_err(invalid_key_data)?;
+
+                if is_private_jwk(key_data)? {
+                    private_key(jwk.to_secret_key().map_err(invalid_key_data)?)
+                } else {
+                    public_key(jwk.to_public_key().map_err(invalid_key_data)?)
+                }
+            }
+        }
+    }};
+}
+
+fn import_ed25519_key(format: KeyFormat, key_data: &[u8]) -> Result<Vec<u8>> {
+    let private_key = |key: ed25519_dalek::SigningKey| -> Result<Vec<u8>> {
+        // Without the public key, like other implementations export them
+        let keypair = KeypairBytes {
+            secret_key: key.to_bytes(),
+            public_key: None,
+        };
+
+        Ok(keypair
+            .to_pkcs8_der()
+            .map_err(invalid_key_data)?
+            .as_bytes()
+            .to_vec())
+    };
+    let public_key = |key: ed25519_dalek::VerifyingKey| -> Result<Vec<u8>> {
+        Ok(key
+            .to_public_key_der()
+            .map_err(invalid_key_data)?
+            .as_bytes()
+            .to_vec())
+    };
+
+    match format {
+        KeyFormat::Raw => {
+            let bytes: &[u8; 32] = key_data.try_into().map_err(invalid_key_data)?;
+            public_key(ed25519_dalek::VerifyingKey::from_bytes(bytes).map_err(invalid_key_data)?)
+        }
+        KeyFormat::Spki => public_key(
+            ed25519_dalek::VerifyingKey::from_public_key_der(key_data).map_err(invalid_key_data)?,
+        ),
+        KeyFormat::Pkcs8 => private_key(
+            ed25519_dalek::SigningKey::from_pkcs8_der(key_data).map_err(invalid_key_data)?,
+        ),
+        KeyFormat::Jwk => {
+            let jwk: Value = serde_json::from_slice(key_data).map_err(invalid_key_data)?;
+
+            if jwk["kty"] != "OKP" || jwk["crv"] != "Ed25519" {
+                return Err(anyhow!("Invalid key data"));
+            }
+
+            let x = jwk_bytes(&jwk, "x")?;
+
+            if jwk.get("d").is_some() {
+                let key = ed25519_dalek::SigningKey::from_bytes(&jwk_bytes(&jwk, "d")?);
+
+                if key.verifying_key().to_bytes() != x {
+                    return Err(anyhow!("Invalid key data"));
+                }
+
+                private_key(key)
+            } else {
+                public_key(ed25519_dalek::VerifyingKey::from_bytes(&x).map_err(invalid_key_data)?)
+            }
+        }
+    }
+}
+
+// The modulus and public exponent are returned after the key,
+// since they are part of the key's algorithm
+fn import_rsa_key(format: KeyFormat, key_data: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let private_key = |key: RsaPrivateKey| -> Result<Vec<Vec<u8>>> {
+        let key_value = key.to_pkcs8_der().map_err(invalid_key_data)?;
+
+        Ok(vec![
+            key_value.as_bytes().to_vec(),
+            key.n().to_bytes_be(),
+            key.e().to_bytes_be(),
+        ])
+    };
+    let public_key = |key: RsaPublicKey| -> Result<Vec<Vec<u8>>> {
+        let key_value = key.to_public_key_der().map_err(invalid_key_data)?;
+
+        Ok(vec![
+            key_value.as_bytes().to_vec(),
+            key.n().to_bytes_be(),
+            key.e().to_bytes_be(),
+        ])
+    };
+
+    match format {
+        KeyFormat::Spki => {
+            public_key(RsaPublicKey::from_public_key_der(key_data).map_err(invalid_key_data)?)
+        }
+        KeyFormat::Pkcs8 => {
+            private_key(RsaPrivateKey::from_pkcs8_der(key_data).map_err(invalid_key_data)?)
+        }
+        KeyFormat::Jwk => {
+            let jwk: Value = serde_json::from_slice(key_data).map_err(invalid_key_data)?;
+
+            if jwk["kty"] != "RSA" {
+                return Err(anyhow!("Invalid key data"));
+            }
+
+            let n = jwk_uint(&jwk, "n")?;
+            let e = jwk_uint(&jwk, "e")?;
+
+            if jwk.get("d").is_none() {
+                return public_key(RsaPublicKey::new(n, e).map_err(invalid_key_data)?);
+            }
+
+            // Multi-prime keys aren't supported
+            let primes = vec![jwk_uint(&jwk, "p")?, jwk_uint(&jwk, "q")?];
+            let key = RsaPrivateKey::from_components(n, e, jwk_uint(&jwk, "d")?, primes)
+                .map_err(invalid_key_data)?;
+
+            // The CRT parameters are optional, but must match the key when given
+            for (name, expected) in [
+                ("dp", key.dp().cloned()),
+                ("dq", key.dq().cloned()),
+                ("qi", key.crt_coefficient()),
+            ] {
+                if jwk.get(name).is_some() && Some(jwk_uint(&jwk, name)?) != expected {
+                    return Err(anyhow!("Invalid key data"));
+                }
+            }
+
+            private_key(key)
+        }
+        KeyFormat::Raw => Err(anyhow!("Key format not supported")),
+    }
+}
+
+pub fn import_key(
+    algorithm: KeyAlgorithm,
+    format: KeyFormat,
+    key_data: Vec<u8>,
+) -> Result<Vec<Vec<u8>>> {
+    match algorithm {
+        KeyAlgorithm::Ec(NamedCurve::P256) => Ok(vec![import_ec_key!(p256, format, &key_data)?]),
+        KeyAlgorithm::Ec(NamedCurve::P384) => Ok(vec![import_ec_key!(p384, format, &key_data)?]),
+        KeyAlgorithm::Ed25519 => Ok(vec![import_ed25519_key(format, &key_data)?]),
+        KeyAlgorithm::Rsa => import_rsa_key(format, &key_data),
+    }
+}
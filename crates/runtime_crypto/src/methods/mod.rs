@@ -1,17 +1,25 @@
 mod decrypt;
+mod derive_bits;
 mod digest;
 mod encrypt;
+mod export_key;
+mod generate_key;
 mod get_key;
+mod import_key;
 mod random_values;
 mod sign;
 mod uuid;
 mod verify;
 
-pub use self::uuid::uuid;
+pub use self::uuid::{uuid, uuid_v7};
 pub use decrypt::decrypt;
+pub use derive_bits::derive_bits;
 pub use digest::digest;
 pub use encrypt::encrypt;
+pub use export_key::export_key;
+pub use generate_key::generate_key;
 pub use get_key::get_key;
+pub use import_key::import_key;
 pub use random_values::random_values;
 pub use sign::sign;
 pub use verify::verify;
@@ -1,7 +1,19 @@
 use anyhow::{anyhow, Result};
+use ed25519_dalek::{pkcs8::DecodePrivateKey as _, Signer};
 use hmac::Mac;
+use p256::{ecdsa::signature::hazmat::PrehashSigner, pkcs8::DecodePrivateKey as _};
+use rand::rngs::OsRng;
+use rsa::{pkcs8::DecodePrivateKey as _, RsaPrivateKey};
 
-use crate::{Algorithm, HmacSha256};
+use super::digest::hash;
+use crate::{
+    padding::{pkcs1v15, pss},
+    Algorithm, HmacSha256,
+};
+
+fn rsa_private_key(key_value: &[u8]) -> Result<RsaPrivateKey> {
+    RsaPrivateKey::from_pkcs8_der(key_value).map_err(|_| anyhow!("Invalid RSA private key"))
+}
 
 pub fn sign(algorithm: Algorithm, key_value: Vec<u8>, data: Vec<u8>) -> Result<Vec<u8>> {
     match algorithm {
@@ -10,6 +22,46 @@ pub fn sign(algorithm: Algorithm, key_value: Vec<u8>, data: Vec<u8>) -> Result<V
             mac.update(&data);
             Ok(mac.finalize().into_bytes().to_vec())
         }
+        Algorithm::Ecdsa(sha) => {
+            let digest = hash(sha, data);
+
+            // The PKCS#8 document contains the curve, so it can only be parsed by one of them
+            if let Ok(key) = p256::SecretKey::from_pkcs8_der(&key_value) {
+                let signature: p256::ecdsa::Signature = p256::ecdsa::SigningKey::from(key)
+                    .sign_prehash(&digest)
+                    .map_err(|_| anyhow!("Failed to sign data"))?;
+
+                return Ok(signature.to_bytes().to_vec());
+            }
+
+            if let Ok(key) = p384::SecretKey::from_pkcs8_der(&key_value) {
+                let signature: p384::ecdsa::Signature = p384::ecdsa::SigningKey::from(key)
+                    .sign_prehash(&digest)
+                    .map_err(|_| anyhow!("Failed to sign data"))?;
+
+                return Ok(signature.to_bytes().to_vec());
+            }
+
+            Err(anyhow!("Invalid ECDSA private key"))
+        }
+        Algorithm::Ed25519 => {
+            let key = ed25519_dalek::SigningKey::from_pkcs8_der(&key_value)
+                .map_err(|_| anyhow!("Invalid Ed25519 private key"))?;
+
+            Ok(key.sign(&data).to_bytes().to_vec())
+        }
+        Algorithm::RsassaPkcs1v15(sha) => {
+            let key = rsa_private_key(&key_value)?;
+            let padding = pkcs1v15(sha.clone())?;
+
+            Ok(key.sign(padding, &hash(sha, data))?)
+        }
+        Algorithm::RsaPss(sha, salt_length) => {
+            let key = rsa_private_key(&key_value)?;
+            let padding = pss(sha.clone(), salt_length)?;
+
+            Ok(key.sign_with_rng(&mut OsRng, padding, &hash(sha, data))?)
+        }
         _ => Err(anyhow!("Algorithm not supported")),
     }
 }
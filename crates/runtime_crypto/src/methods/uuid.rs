@@ -1,5 +1,53 @@
+use rand::random;
+use std::{
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
 use uuid::Uuid;
 
+// The 74 random bits of a UUIDv7 (12 of rand_a and 62 of rand_b)
+const RANDOM_BITS: u32 = 74;
+const RANDOM_MASK: u128 = (1 << RANDOM_BITS) - 1;
+
+// The timestamp and random bits of the last UUIDv7, shared by all isolates
+static LAST_UUID_V7: Mutex<(u64, u128)> = Mutex::new((0, 0));
+
 pub fn uuid() -> String {
     Uuid::new_v4().to_string()
 }
+
+// RFC 9562 UUIDv7: a 48 bits Unix timestamp in milliseconds followed by random
+// bits. Within the same millisecond (or if the clock goes backward), the random
+// bits of the last UUID are incremented instead to keep UUIDs sortable
+pub fn uuid_v7() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_millis() as u64);
+
+    let mut last = LAST_UUID_V7
+        .lock()
+        .unwrap_or_else(|error| error.into_inner());
+    let (last_timestamp, last_random) = *last;
+
+    let (timestamp, random_bits) = if now > last_timestamp {
+        (now, random::<u128>() & RANDOM_MASK)
+    } else if last_random < RANDOM_MASK {
+        (last_timestamp, last_random + 1)
+    } else {
+        // The counter overflowed, so borrow the next millisecond
+        (last_timestamp + 1, random::<u128>() & RANDOM_MASK)
+    };
+
+    *last = (timestamp, random_bits);
+    drop(last);
+
+    let rand_a = random_bits >> 62;
+    let rand_b = random_bits & ((1 << 62) - 1);
+    let value = ((timestamp as u128 & 0xffff_ffff_ffff) << 80)
+        | (0x7 << 76)
+        | (rand_a << 64)
+        | (0b10 << 62)
+        | rand_b;
+
+    Uuid::from_u128(value).to_string()
+}
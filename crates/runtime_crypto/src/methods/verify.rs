@@ -1,7 +1,18 @@
 use anyhow::{anyhow, Result};
+use ed25519_dalek::{pkcs8::DecodePublicKey as _, Verifier};
 use hmac::Mac;
+use p256::{ecdsa::signature::hazmat::PrehashVerifier, pkcs8::DecodePublicKey as _};
+use rsa::{pkcs8::DecodePublicKey as _, RsaPublicKey};
 
-use crate::{Algorithm, HmacSha256};
+use super::digest::hash;
+use crate::{
+    padding::{pkcs1v15, pss},
+    Algorithm, HmacSha256,
+};
+
+fn rsa_public_key(key_value: &[u8]) -> Result<RsaPublicKey> {
+    RsaPublicKey::from_public_key_der(key_value).map_err(|_| anyhow!("Invalid RSA public key"))
+}
 
 pub fn verify(
     algorithm: Algorithm,
@@ -15,6 +26,52 @@ pub fn verify(
             mac.update(&data);
             Ok(mac.verify_slice(&signature).is_ok())
         }
+        Algorithm::Ecdsa(sha) => {
+            let digest = hash(sha, data);
+
+            // The SPKI document contains the curve, so it can only be parsed by one of them.
+            // Malformed signatures don't verify, instead of being an error
+            if let Ok(key) = p256::PublicKey::from_public_key_der(&key_value) {
+                return Ok(match p256::ecdsa::Signature::from_slice(&signature) {
+                    Ok(signature) => p256::ecdsa::VerifyingKey::from(key)
+                        .verify_prehash(&digest, &signature)
+                        .is_ok(),
+                    Err(_) => false,
+                });
+            }
+
+            if let Ok(key) = p384::PublicKey::from_public_key_der(&key_value) {
+                return Ok(match p384::ecdsa::Signature::from_slice(&signature) {
+                    Ok(signature) => p384::ecdsa::VerifyingKey::from(key)
+                        .verify_prehash(&digest, &signature)
+                        .is_ok(),
+                    Err(_) => false,
+                });
+            }
+
+            Err(anyhow!("Invalid ECDSA public key"))
+        }
+        Algorithm::Ed25519 => {
+            let key = ed25519_dalek::VerifyingKey::from_public_key_der(&key_value)
+                .map_err(|_| anyhow!("Invalid Ed25519 public key"))?;
+
+            Ok(match ed25519_dalek::Signature::from_slice(&signature) {
+                Ok(signature) => key.verify(&data, &signature).is_ok(),
+                Err(_) => false,
+            })
+        }
+        Algorithm::RsassaPkcs1v15(sha) => {
+            let key = rsa_public_key(&key_value)?;
+            let padding = pkcs1v15(sha.clone())?;
+
+            Ok(key.verify(padding, &hash(sha, data), &signature).is_ok())
+        }
+        Algorithm::RsaPss(sha, salt_length) => {
+            let key = rsa_public_key(&key_value)?;
+            let padding = pss(sha.clone(), salt_length)?;
+
+            Ok(key.verify(padding, &hash(sha, data), &signature).is_ok())
+        }
         _ => Err(anyhow!("Algorithm not supported")),
     }
 }
@@ -0,0 +1,44 @@
+use anyhow::{anyhow, Result};
+use rsa::{Oaep, Pkcs1v15Sign, Pss};
+use sha2::{Sha256, Sha384, Sha512};
+
+use crate::Sha;
+
+// Only the SHA-2 hashes are supported with RSA
+pub fn pkcs1v15(sha: Sha) -> Result<Pkcs1v15Sign> {
+    match sha {
+        Sha::Sha256 => Ok(Pkcs1v15Sign::new::<Sha256>()),
+        Sha::Sha384 => Ok(Pkcs1v15Sign::new::<Sha384>()),
+        Sha::Sha512 => Ok(Pkcs1v15Sign::new::<Sha512>()),
+        Sha::Sha1 => Err(anyhow!("Hash not supported")),
+    }
+}
+
+pub fn pss(sha: Sha, salt_length: usize) -> Result<Pss> {
+    match sha {
+        Sha::Sha256 => Ok(Pss::new_with_salt::<Sha256>(salt_length)),
+        Sha::Sha384 => Ok(Pss::new_with_salt::<Sha384>(salt_length)),
+        Sha::Sha512 => Ok(Pss::new_with_salt::<Sha512>(salt_length)),
+        Sha::Sha1 => Err(anyhow!("Hash not supported")),
+    }
+}
+
+pub fn oaep(sha: Sha, label: Option<Vec<u8>>) -> Result<Oaep> {
+    // Labels are strings for the rsa crate, and empty labels are the same as no label
+    let label = match label {
+        Some(label) if !label.is_empty() => {
+            Some(String::from_utf8(label).map_err(|_| anyhow!("Label must be valid UTF-8"))?)
+        }
+        _ => None,
+    };
+
+    Ok(match (sha, label) {
+        (Sha::Sha256, None) => Oaep::new::<Sha256>(),
+        (Sha::Sha384, None) => Oaep::new::<Sha384>(),
+        (Sha::Sha512, None) => Oaep::new::<Sha512>(),
+        (Sha::Sha256, Some(label)) => Oaep::new_with_label::<Sha256, _>(label),
+        (Sha::Sha384, Some(label)) => Oaep::new_with_label::<Sha384, _>(label),
+        (Sha::Sha512, Some(label)) => Oaep::new_with_label::<Sha512, _>(label),
+        (Sha::Sha1, _) => return Err(anyhow!("Hash not supported")),
+    })
+}
@@ -3,3 +3,5 @@ pub const X_REAL_IP: &str = "x-real-ip";
 
 pub const X_LAGON_REGION: &str = "x-lagon-region";
 pub const X_LAGON_ID: &str = "x-lagon-id";
+// Set on the requests delivering the messages of `Lagon.queue`, to the name of the queue
+pub const X_LAGON_QUEUE: &str = "x-lagon-queue";
@@ -4,11 +4,13 @@ mod headers;
 mod method;
 mod request;
 mod response;
+mod websocket;
 
 pub use headers::*;
 pub use method::*;
 pub use request::*;
 pub use response::*;
+pub use websocket::*;
 
 pub trait IntoV8 {
     fn into_v8<'a>(self, scope: &mut v8::HandleScope<'a>) -> v8::Local<'a, v8::Object>;
@@ -28,14 +30,30 @@ pub enum StreamResult {
     Done,
 }
 
+// All sizes are in bytes, for the whole heap (not each of its spaces)
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct HeapStatistics {
+    pub used_heap_size: usize,
+    pub total_heap_size: usize,
+    pub heap_size_limit: usize,
+    pub external_memory: usize,
+    pub number_of_detached_contexts: usize,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum RunResult {
     Response(Response),
     Stream(StreamResult),
     Timeout,
-    MemoryLimit,
+    // The request spent more time executing JS than the CPU time limit
+    CpuTimeLimit,
+    // The heap statistics of the isolate when it reached the limit
+    MemoryLimit(HeapStatistics),
     Error(String),
     NotFound,
+    WebSocket(WebSocketUpgrade),
+    // The client disconnected before the response was sent
+    Aborted,
 }
 
 impl RunResult {
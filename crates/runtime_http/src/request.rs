@@ -1,19 +1,59 @@
 use anyhow::{anyhow, Result};
 use hyper::{
-    body::{self, Bytes},
-    header::HeaderName,
+    body::{Bytes, HttpBody},
+    header::{HeaderName, CONTENT_LENGTH},
     http::{self, HeaderValue},
     Body, Request as HyperRequest,
 };
 use lagon_runtime_v8_utils::{
-    extract_v8_headers_object, extract_v8_string, v8_headers_object, v8_string,
+    extract_v8_headers_object, extract_v8_string, extract_v8_uint8array, v8_headers_object,
+    v8_string,
 };
-use std::{collections::HashMap, str::FromStr};
+use std::{collections::HashMap, fmt, str::FromStr};
 
 use crate::X_LAGON_ID;
 
 use super::{FromV8, IntoV8, Method};
 
+pub const DEFAULT_MAX_BODY_SIZE: usize = 10 * 1024 * 1024; // 10MB
+
+// Returned by `Request::from_hyper` when the body is larger than the
+// allowed size, so callers can respond with a 413 status
+#[derive(Debug)]
+pub struct BodyTooLargeError {
+    pub max_body_size: usize,
+}
+
+impl fmt::Display for BodyTooLargeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Request body is larger than the maximum size of {} bytes",
+            self.max_body_size
+        )
+    }
+}
+
+impl std::error::Error for BodyTooLargeError {}
+
+// Collect the body without ever buffering more than `max_body_size`
+// bytes, instead of trusting the Content-Length header
+async fn to_bytes_limited(mut body: Body, max_body_size: usize) -> Result<Bytes> {
+    let mut bytes = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+
+        if bytes.len() + chunk.len() > max_body_size {
+            return Err(BodyTooLargeError { max_body_size }.into());
+        }
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(Bytes::from(bytes))
+}
+
 #[derive(Debug)]
 pub struct Request {
     pub headers: Option<HashMap<String, Vec<String>>>,
@@ -82,7 +122,9 @@ impl FromV8 for Request {
         let body_key = v8_string(scope, "b");
 
         if let Some(body_value) = request.get(scope, body_key.into()) {
-            if !body_value.is_null_or_undefined() {
+            if body_value.is_uint8_array() {
+                body = Bytes::from(extract_v8_uint8array(body_value)?);
+            } else if !body_value.is_null_or_undefined() {
                 body = Bytes::from(extract_v8_string(body_value, scope)?);
             }
         }
@@ -158,13 +200,38 @@ impl Request {
     }
 
     pub async fn from_hyper(request: HyperRequest<Body>) -> Result<Self> {
-        Self::from_hyper_with_capacity(request, 0).await
+        Self::from_hyper_with_capacity(request, 0, DEFAULT_MAX_BODY_SIZE).await
     }
 
     pub async fn from_hyper_with_capacity(
         request: HyperRequest<Body>,
         capacity: usize,
+        max_body_size: usize,
     ) -> Result<Self> {
+        let (mut request, body) = Self::from_hyper_streamed(request, capacity, max_body_size)?;
+        request.body = to_bytes_limited(body, max_body_size).await?;
+
+        Ok(request)
+    }
+
+    // Like `from_hyper_with_capacity`, but leave the body empty and return the
+    // hyper body instead, so it can be streamed into the isolate as it arrives
+    pub fn from_hyper_streamed(
+        request: HyperRequest<Body>,
+        capacity: usize,
+        max_body_size: usize,
+    ) -> Result<(Self, Body)> {
+        // Fail early if the announced body is already too large
+        let content_length = request
+            .headers()
+            .get(CONTENT_LENGTH)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<usize>().ok());
+
+        if content_length.map_or(false, |content_length| content_length > max_body_size) {
+            return Err(BodyTooLargeError { max_body_size }.into());
+        }
+
         let mut headers =
             HashMap::<String, Vec<String>>::with_capacity(request.headers().keys_len() + capacity);
 
@@ -184,18 +251,19 @@ impl Request {
         });
         let url = format!("http://{}{}", host, request.uri().to_string().as_str());
 
-        let body = body::to_bytes(request.into_body()).await?;
-
-        Ok(Request {
-            headers: if !headers.is_empty() {
-                Some(headers)
-            } else {
-                None
+        Ok((
+            Request {
+                headers: if !headers.is_empty() {
+                    Some(headers)
+                } else {
+                    None
+                },
+                method,
+                body: Bytes::new(),
+                url,
             },
-            method,
-            body,
-            url,
-        })
+            request.into_body(),
+        ))
     }
 
     pub fn set_header(&mut self, key: String, value: String) {
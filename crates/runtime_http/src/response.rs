@@ -51,8 +51,11 @@ impl IntoV8 for Response {
         let mut names = Vec::with_capacity(len);
         let mut values = Vec::with_capacity(len);
 
-        names.push(v8_string(scope, "b").into());
-        values.push(v8_uint8array(scope, self.body.to_vec()).into());
+        // Streamed bodies are pulled separately
+        if !self.is_streamed() {
+            names.push(v8_string(scope, "b").into());
+            values.push(v8_uint8array(scope, self.body.to_vec()).into());
+        }
 
         names.push(v8_string(scope, "s").into());
         values.push(v8_integer(scope, self.status.into()).into());
@@ -159,6 +162,15 @@ impl Response {
     }
 
     pub async fn from_hyper(response: HyperResponse<Body>) -> Result<Self> {
+        let (mut response, body) = Self::from_hyper_streamed(response)?;
+        response.body = body::to_bytes(body).await?;
+
+        Ok(response)
+    }
+
+    // Like `from_hyper`, but mark the response as streamed and
+    // return the body to be read separately
+    pub fn from_hyper_streamed(response: HyperResponse<Body>) -> Result<(Self, Body)> {
         let mut headers =
             HashMap::<String, Vec<String>>::with_capacity(response.headers().keys_len());
 
@@ -170,16 +182,18 @@ impl Response {
         }
 
         let status = response.status().as_u16();
-        let body = body::to_bytes(response.into_body()).await?;
 
-        Ok(Response {
-            status,
-            headers: if !headers.is_empty() {
-                Some(headers)
-            } else {
-                None
+        Ok((
+            Response {
+                status,
+                headers: if !headers.is_empty() {
+                    Some(headers)
+                } else {
+                    None
+                },
+                body: Bytes::from_static(READABLE_STREAM_STR),
             },
-            body,
-        })
+            response.into_body(),
+        ))
     }
 }
@@ -0,0 +1,31 @@
+use crate::Response;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebSocketMessage {
+    Text(String),
+    Binary(Vec<u8>),
+    // The close code and reason, if any
+    Close(Option<(u16, String)>),
+}
+
+// Returned by the isolate when the handler accepted a WebSocket upgrade. The
+// messages are then forwarded between the client and the isolate, until one
+// of them closes the connection
+#[derive(Debug, Clone)]
+pub struct WebSocketUpgrade {
+    pub response: Response,
+    // Messages sent by the isolate, to send to the client
+    pub outgoing: flume::Receiver<WebSocketMessage>,
+    // Messages received from the client, to send to the isolate
+    pub incoming: flume::Sender<WebSocketMessage>,
+}
+
+impl PartialEq for WebSocketUpgrade {
+    fn eq(&self, other: &Self) -> bool {
+        self.response == other.response
+            && self.outgoing.same_channel(&other.outgoing)
+            && self.incoming.same_channel(&other.incoming)
+    }
+}
+
+impl Eq for WebSocketUpgrade {}
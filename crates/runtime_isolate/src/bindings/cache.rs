@@ -0,0 +1,139 @@
+use anyhow::{anyhow, Result};
+use hyper::body::Bytes;
+use lagon_runtime_http::{FromV8, Request, Response};
+use lagon_runtime_v8_utils::{
+    extract_v8_headers_object, extract_v8_integer, extract_v8_string, extract_v8_uint8array,
+    v8_string,
+};
+use std::sync::Arc;
+
+use crate::{
+    bindings::PromiseResult,
+    cache::{self, CacheBackend},
+    Isolate,
+};
+
+use super::BindingResult;
+
+// The backend, the name of the cache and the request. Without a
+// backend, responses are never stored
+type Arg = Option<(Arc<dyn CacheBackend>, String, Request)>;
+type PutArg = Option<(Arc<dyn CacheBackend>, String, Request, Response)>;
+
+fn cache_args(scope: &mut v8::HandleScope, args: &v8::FunctionCallbackArguments) -> Result<Arg> {
+    let backend = match &Isolate::state(scope).borrow().cache_backend {
+        Some(backend) => Arc::clone(backend),
+        None => return Ok(None),
+    };
+
+    let name = extract_v8_string(args.get(0), scope)?;
+    let request = match args.get(1).to_object(scope) {
+        Some(request) => Request::from_v8(scope, request.into())?,
+        None => return Err(anyhow!("Invalid request")),
+    };
+
+    Ok(Some((backend, name, request)))
+}
+
+// The response is given with its whole body
+fn extract_response(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Result<Response> {
+    let response = match value.to_object(scope) {
+        Some(response) => response,
+        None => return Err(anyhow!("Invalid response")),
+    };
+
+    let status_key = v8_string(scope, "s");
+    let status = match response.get(scope, status_key.into()) {
+        Some(status) => extract_v8_integer(status, scope)? as u16,
+        None => return Err(anyhow!("Invalid response")),
+    };
+
+    let headers_key = v8_string(scope, "h");
+    let headers = match response.get(scope, headers_key.into()) {
+        Some(headers) if !headers.is_null_or_undefined() => {
+            extract_v8_headers_object(headers, scope)?
+        }
+        _ => None,
+    };
+
+    let body_key = v8_string(scope, "b");
+    let body = match response.get(scope, body_key.into()) {
+        Some(body) => extract_v8_uint8array(body)?,
+        None => return Err(anyhow!("Invalid response")),
+    };
+
+    Ok(Response {
+        headers,
+        body: Bytes::from(body),
+        status,
+    })
+}
+
+pub fn cache_match_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    cache_args(scope, &args)
+}
+
+pub async fn cache_match_binding(id: usize, arg: Arg) -> BindingResult {
+    let result = match arg {
+        Some((backend, name, request)) => {
+            match cache::match_request(backend.as_ref(), &name, &request).await {
+                Ok(Some(response)) => PromiseResult::Response(response),
+                Ok(None) => PromiseResult::Undefined,
+                Err(error) => PromiseResult::Error(error.to_string()),
+            }
+        }
+        None => PromiseResult::Undefined,
+    };
+
+    BindingResult { id, result }
+}
+
+pub fn cache_put_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<PutArg> {
+    let response = extract_response(scope, args.get(2))?;
+
+    Ok(
+        cache_args(scope, &args)?
+            .map(|(backend, name, request)| (backend, name, request, response)),
+    )
+}
+
+pub async fn cache_put_binding(id: usize, arg: PutArg) -> BindingResult {
+    let result = match arg {
+        Some((backend, name, request, response)) => {
+            match cache::put(backend.as_ref(), &name, &request, response).await {
+                Ok(()) => PromiseResult::Undefined,
+                Err(error) => PromiseResult::Error(error.to_string()),
+            }
+        }
+        None => PromiseResult::Undefined,
+    };
+
+    BindingResult { id, result }
+}
+
+pub fn cache_delete_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    cache_args(scope, &args)
+}
+
+pub async fn cache_delete_binding(id: usize, arg: Arg) -> BindingResult {
+    let result = match arg {
+        Some((backend, name, request)) => {
+            match cache::delete(backend.as_ref(), &name, &request).await {
+                Ok(deleted) => PromiseResult::Boolean(deleted),
+                Err(error) => PromiseResult::Error(error.to_string()),
+            }
+        }
+        None => PromiseResult::Boolean(false),
+    };
+
+    BindingResult { id, result }
+}
@@ -0,0 +1,157 @@
+use anyhow::{anyhow, Result};
+use flate2::{
+    write::{DeflateDecoder, DeflateEncoder, GzDecoder, GzEncoder, ZlibDecoder, ZlibEncoder},
+    Compression,
+};
+use lagon_runtime_v8_utils::{extract_v8_uint8array, v8_exception};
+use std::{
+    fmt::Debug,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use crate::{bindings::PromiseResult, Isolate};
+
+use super::{request_id, BindingResult};
+
+// Compresses or decompresses the chunks written to it,
+// buffering the output until it is taken
+pub trait Codec: Write + Send + Debug {
+    fn take_output(&mut self) -> Vec<u8>;
+    fn try_finish(&mut self) -> io::Result<()>;
+}
+
+macro_rules! codec {
+    ($($codec: ident),*) => {
+        $(
+            impl Codec for $codec<Vec<u8>> {
+                fn take_output(&mut self) -> Vec<u8> {
+                    std::mem::take(self.get_mut())
+                }
+
+                fn try_finish(&mut self) -> io::Result<()> {
+                    $codec::try_finish(self)
+                }
+            }
+        )*
+    };
+}
+
+codec!(
+    GzEncoder,
+    ZlibEncoder,
+    DeflateEncoder,
+    GzDecoder,
+    ZlibDecoder,
+    DeflateDecoder
+);
+
+pub type SharedCodec = Arc<Mutex<Box<dyn Codec>>>;
+
+// The codec, and the chunk to write or None to finish the stream
+type Arg = (SharedCodec, Option<Vec<u8>>);
+
+fn create_codec(format: &str, decompress: bool) -> Result<Box<dyn Codec>> {
+    let output = Vec::new();
+
+    Ok(match (format, decompress) {
+        ("gzip", false) => Box::new(GzEncoder::new(output, Compression::default())),
+        ("deflate", false) => Box::new(ZlibEncoder::new(output, Compression::default())),
+        ("deflate-raw", false) => Box::new(DeflateEncoder::new(output, Compression::default())),
+        ("gzip", true) => Box::new(GzDecoder::new(output)),
+        ("deflate", true) => Box::new(ZlibDecoder::new(output)),
+        ("deflate-raw", true) => Box::new(DeflateDecoder::new(output)),
+        _ => return Err(anyhow!("Unsupported compression format: {}", format)),
+    })
+}
+
+pub fn create_codec_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _retval: v8::ReturnValue,
+) {
+    let id = request_id(scope);
+    let codec_id = args.get(0).uint32_value(scope).unwrap_or(0);
+    let format = args.get(1).to_rust_string_lossy(scope);
+    let decompress = args.get(2).boolean_value(scope);
+
+    let codec = match create_codec(&format, decompress) {
+        Ok(codec) => codec,
+        Err(error) => {
+            let exception = v8_exception(scope, &error.to_string());
+            scope.throw_exception(exception);
+            return;
+        }
+    };
+
+    // Codecs are dropped with the request, even if the stream wasn't closed
+    if let Some(handler_result) = Isolate::state(scope)
+        .borrow_mut()
+        .handler_results
+        .get_mut(&id)
+    {
+        handler_result
+            .context
+            .codecs
+            .insert(codec_id, Arc::new(Mutex::new(codec)));
+    }
+}
+
+pub fn transform_codec_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let id = request_id(scope);
+    let codec_id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    let chunk = match args.get(1) {
+        chunk if chunk.is_undefined() => None,
+        chunk => Some(extract_v8_uint8array(chunk)?),
+    };
+
+    let isolate_state = Isolate::state(scope);
+    let mut state = isolate_state.borrow_mut();
+    let codecs = &mut state
+        .handler_results
+        .get_mut(&id)
+        .ok_or_else(|| anyhow!("Compression streams can only be used during a request"))?
+        .context
+        .codecs;
+
+    // The last call finishes the stream, so the codec isn't needed anymore
+    let codec = match chunk {
+        Some(_) => codecs.get(&codec_id).cloned(),
+        None => codecs.remove(&codec_id),
+    };
+
+    match codec {
+        Some(codec) => Ok((codec, chunk)),
+        None => Err(anyhow!("Compression stream is closed")),
+    }
+}
+
+fn transform_codec(codec: SharedCodec, chunk: Option<Vec<u8>>) -> Result<Vec<u8>> {
+    let mut codec = codec
+        .lock()
+        .map_err(|_| anyhow!("Compression stream is poisoned"))?;
+
+    match chunk {
+        Some(chunk) => codec.write_all(&chunk)?,
+        None => codec.try_finish()?,
+    }
+
+    Ok(codec.take_output())
+}
+
+// (De)compressing big chunks is CPU-bound, so it shouldn't block the isolate's thread
+pub async fn transform_codec_binding(id: usize, arg: Arg) -> BindingResult {
+    let (codec, chunk) = arg;
+
+    let result = match tokio::task::spawn_blocking(move || transform_codec(codec, chunk)).await {
+        Ok(Ok(output)) => PromiseResult::ArrayBuffer(output),
+        Ok(Err(error)) => PromiseResult::Error(error.to_string()),
+        Err(error) => PromiseResult::Error(error.to_string()),
+    };
+
+    BindingResult { id, result }
+}
@@ -1,6 +1,6 @@
 use log::{debug, error, info, warn};
 
-use crate::Isolate;
+use crate::{inspector::send_console_message, Isolate};
 
 pub const CONSOLE_SOURCE: &str = "console";
 
@@ -11,25 +11,37 @@ pub fn console_binding(
 ) {
     let level = args.get(0).to_rust_string_lossy(scope);
     let message = args.get(1).to_rust_string_lossy(scope);
+    let id = scope
+        .get_continuation_preserved_embedder_data()
+        .to_uint32(scope)
+        .map_or(0, |value| value.value());
     let state = Isolate::state(scope);
     let state = state.borrow();
 
+    if let Some(outgoing) = &state.inspector_outgoing {
+        send_console_message(outgoing, &level, &message);
+    }
+
     if let Some((deployment, function)) = &state.metadata.as_ref() {
         let deployment = deployment.as_str();
         let function = function.as_str();
+        let request = state
+            .handler_results
+            .get(&id)
+            .and_then(|handler_result| handler_result.context.request_id.as_deref());
 
         match level.as_str() {
             "debug" => {
-                debug!(source = CONSOLE_SOURCE, deployment = deployment, function = function; "{}", message)
+                debug!(source = CONSOLE_SOURCE, deployment = deployment, function = function, request = request; "{}", message)
             }
             "warn" => {
-                warn!(source = CONSOLE_SOURCE, deployment = deployment, function = function; "{}", message)
+                warn!(source = CONSOLE_SOURCE, deployment = deployment, function = function, request = request; "{}", message)
             }
             "error" => {
-                error!(source = CONSOLE_SOURCE, deployment = deployment, function = function; "{}", message)
+                error!(source = CONSOLE_SOURCE, deployment = deployment, function = function, request = request; "{}", message)
             }
             _ => {
-                info!(source = CONSOLE_SOURCE, deployment = deployment, function = function; "{}", message)
+                info!(source = CONSOLE_SOURCE, deployment = deployment, function = function, request = request; "{}", message)
             }
         };
     }
@@ -12,7 +12,7 @@ pub fn decrypt_init(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
 ) -> Result<Arg> {
-    let algorithm = extract_algorithm_object(scope, args.get(0))?;
+    let algorithm = extract_algorithm_object(scope, args.get(0), args.get(1))?;
     let key_value = extract_cryptokey_key_value(scope, args.get(1))?;
     let data = extract_v8_uint8array(args.get(2))?;
 
@@ -24,14 +24,12 @@ pub async fn decrypt_binding(id: usize, arg: Arg) -> BindingResult {
     let key_value = arg.1;
     let data = arg.2;
 
-    match decrypt(algorithm, key_value, data) {
-        Ok(result) => BindingResult {
-            id,
-            result: PromiseResult::ArrayBuffer(result),
-        },
-        Err(error) => BindingResult {
-            id,
-            result: PromiseResult::Error(error.to_string()),
-        },
-    }
+    let result =
+        match tokio::task::spawn_blocking(move || decrypt(algorithm, key_value, data)).await {
+            Ok(Ok(result)) => PromiseResult::ArrayBuffer(result),
+            Ok(Err(error)) => PromiseResult::Error(error.to_string()),
+            Err(error) => PromiseResult::Error(error.to_string()),
+        };
+
+    BindingResult { id, result }
 }
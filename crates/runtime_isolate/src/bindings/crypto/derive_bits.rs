@@ -0,0 +1,44 @@
+use anyhow::Result;
+use lagon_runtime_crypto::{
+    extract_algorithm_object, extract_cryptokey_key_value, methods::derive_bits, Algorithm,
+};
+use lagon_runtime_v8_utils::extract_v8_integer;
+
+use crate::bindings::{BindingResult, PromiseResult};
+
+type Arg = (Algorithm, Vec<u8>, Option<usize>);
+
+pub fn derive_bits_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let algorithm = extract_algorithm_object(scope, args.get(0), args.get(1))?;
+    let key_value = extract_cryptokey_key_value(scope, args.get(1))?;
+
+    // ECDH can derive the whole shared secret when no length is given
+    let length = match args.get(2) {
+        length if length.is_null_or_undefined() => None,
+        length => Some(extract_v8_integer(length, scope)?.try_into()?),
+    };
+
+    Ok((algorithm, key_value, length))
+}
+
+// PBKDF2 with many iterations is CPU-bound, so it shouldn't block the isolate's thread
+pub async fn derive_bits_binding(id: usize, arg: Arg) -> BindingResult {
+    let algorithm = arg.0;
+    let key_value = arg.1;
+    let length = arg.2;
+
+    let result = match tokio::task::spawn_blocking(move || {
+        derive_bits(algorithm, key_value, length)
+    })
+    .await
+    {
+        Ok(Ok(result)) => PromiseResult::ArrayBuffer(result),
+        Ok(Err(error)) => PromiseResult::Error(error.to_string()),
+        Err(error) => PromiseResult::Error(error.to_string()),
+    };
+
+    BindingResult { id, result }
+}
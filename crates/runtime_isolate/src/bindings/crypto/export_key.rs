@@ -0,0 +1,57 @@
+use anyhow::{anyhow, Result};
+use lagon_runtime_crypto::{
+    extract_cryptokey_key_value, extract_key_algorithm, get_key_format, methods::export_key,
+    KeyAlgorithm, KeyFormat,
+};
+use lagon_runtime_v8_utils::{extract_v8_string, v8_string};
+
+use crate::bindings::{BindingResult, PromiseResult};
+
+type Arg = (KeyAlgorithm, KeyFormat, Vec<u8>, bool);
+
+pub fn export_key_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let format = extract_v8_string(args.get(0), scope)?;
+    let format = get_key_format(&format)?;
+    let key_value = extract_cryptokey_key_value(scope, args.get(1))?;
+
+    let key = args
+        .get(1)
+        .to_object(scope)
+        .ok_or_else(|| anyhow!("CryptoKey not supported"))?;
+
+    let algorithm_key = v8_string(scope, "algorithm");
+    let algorithm = match key.get(scope, algorithm_key.into()) {
+        Some(algorithm) => extract_key_algorithm(scope, algorithm)?,
+        None => return Err(anyhow!("CryptoKey algorithm not found")),
+    };
+
+    let type_key = v8_string(scope, "type");
+    let private = match key.get(scope, type_key.into()) {
+        Some(key_type) => extract_v8_string(key_type, scope)? == "private",
+        None => return Err(anyhow!("CryptoKey type not found")),
+    };
+
+    Ok((algorithm, format, key_value, private))
+}
+
+pub async fn export_key_binding(id: usize, arg: Arg) -> BindingResult {
+    let algorithm = arg.0;
+    let format = arg.1;
+    let key_value = arg.2;
+    let private = arg.3;
+
+    let result = match tokio::task::spawn_blocking(move || {
+        export_key(algorithm, format, key_value, private)
+    })
+    .await
+    {
+        Ok(Ok(result)) => PromiseResult::ArrayBuffer(result),
+        Ok(Err(error)) => PromiseResult::Error(error.to_string()),
+        Err(error) => PromiseResult::Error(error.to_string()),
+    };
+
+    BindingResult { id, result }
+}
@@ -0,0 +1,26 @@
+use anyhow::Result;
+use lagon_runtime_crypto::{
+    extract_generate_key_algorithm, methods::generate_key, GenerateKeyAlgorithm,
+};
+
+use crate::bindings::{BindingResult, PromiseResult};
+
+type Arg = GenerateKeyAlgorithm;
+
+pub fn generate_key_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    extract_generate_key_algorithm(scope, args.get(0))
+}
+
+pub async fn generate_key_binding(id: usize, arg: Arg) -> BindingResult {
+    // Generating RSA keys can take seconds, so it shouldn't block the isolate's thread
+    let result = match tokio::task::spawn_blocking(move || generate_key(arg)).await {
+        Ok(Ok(result)) => PromiseResult::ArrayBuffers(result),
+        Ok(Err(error)) => PromiseResult::Error(error.to_string()),
+        Err(error) => PromiseResult::Error(error.to_string()),
+    };
+
+    BindingResult { id, result }
+}
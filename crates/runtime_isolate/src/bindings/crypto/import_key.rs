@@ -0,0 +1,41 @@
+use anyhow::Result;
+use lagon_runtime_crypto::{
+    extract_key_algorithm, get_key_format, methods::import_key, KeyAlgorithm, KeyFormat,
+};
+use lagon_runtime_v8_utils::{extract_v8_string, extract_v8_uint8array};
+
+use crate::bindings::{BindingResult, PromiseResult};
+
+type Arg = (KeyAlgorithm, KeyFormat, Vec<u8>);
+
+pub fn import_key_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let format = extract_v8_string(args.get(0), scope)?;
+    let format = get_key_format(&format)?;
+
+    // JWKs are given as JSON
+    let key_data = match format {
+        KeyFormat::Jwk => extract_v8_string(args.get(1), scope)?.into_bytes(),
+        _ => extract_v8_uint8array(args.get(1))?,
+    };
+    let algorithm = extract_key_algorithm(scope, args.get(2))?;
+
+    Ok((algorithm, format, key_data))
+}
+
+pub async fn import_key_binding(id: usize, arg: Arg) -> BindingResult {
+    let algorithm = arg.0;
+    let format = arg.1;
+    let key_data = arg.2;
+
+    let result =
+        match tokio::task::spawn_blocking(move || import_key(algorithm, format, key_data)).await {
+            Ok(Ok(result)) => PromiseResult::ArrayBuffers(result),
+            Ok(Err(error)) => PromiseResult::Error(error.to_string()),
+            Err(error) => PromiseResult::Error(error.to_string()),
+        };
+
+    BindingResult { id, result }
+}
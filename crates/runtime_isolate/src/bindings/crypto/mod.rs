@@ -1,17 +1,25 @@
 mod decrypt;
+mod derive_bits;
 mod digest;
 mod encrypt;
+mod export_key;
+mod generate_key;
 mod get_key_value;
+mod import_key;
 mod random_values;
 mod sign;
 mod uuid;
 mod verify;
 
-pub use self::uuid::uuid_binding;
+pub use self::uuid::{uuid_binding, uuid_v7_binding};
 pub use decrypt::{decrypt_binding, decrypt_init};
+pub use derive_bits::{derive_bits_binding, derive_bits_init};
 pub use digest::{digest_binding, digest_init};
 pub use encrypt::{encrypt_binding, encrypt_init};
+pub use export_key::{export_key_binding, export_key_init};
+pub use generate_key::{generate_key_binding, generate_key_init};
 pub use get_key_value::get_key_value_binding;
+pub use import_key::{import_key_binding, import_key_init};
 pub use random_values::random_values_binding;
 pub use sign::{sign_binding, sign_init};
 pub use verify::{verify_binding, verify_init};
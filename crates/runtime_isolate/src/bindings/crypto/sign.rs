@@ -9,7 +9,7 @@ use crate::bindings::{BindingResult, PromiseResult};
 type Arg = (Algorithm, Vec<u8>, Vec<u8>);
 
 pub fn sign_init(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments) -> Result<Arg> {
-    let algorithm = extract_algorithm_object(scope, args.get(0))?;
+    let algorithm = extract_algorithm_object(scope, args.get(0), args.get(1))?;
     let key_value = extract_cryptokey_key_value(scope, args.get(1))?;
     let data = extract_v8_uint8array(args.get(2))?;
 
@@ -21,14 +21,12 @@ pub async fn sign_binding(id: usize, arg: Arg) -> BindingResult {
     let key_value = arg.1;
     let data = arg.2;
 
-    match sign(algorithm, key_value, data) {
-        Ok(result) => BindingResult {
-            id,
-            result: PromiseResult::ArrayBuffer(result),
-        },
-        Err(error) => BindingResult {
-            id,
-            result: PromiseResult::Error(error.to_string()),
-        },
-    }
+    // RSA keys make this CPU-bound, so it shouldn't block the isolate's thread
+    let result = match tokio::task::spawn_blocking(move || sign(algorithm, key_value, data)).await {
+        Ok(Ok(result)) => PromiseResult::ArrayBuffer(result),
+        Ok(Err(error)) => PromiseResult::Error(error.to_string()),
+        Err(error) => PromiseResult::Error(error.to_string()),
+    };
+
+    BindingResult { id, result }
 }
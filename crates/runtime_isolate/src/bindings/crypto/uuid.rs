@@ -1,4 +1,4 @@
-use lagon_runtime_crypto::methods::uuid;
+use lagon_runtime_crypto::methods::{uuid, uuid_v7};
 
 use lagon_runtime_v8_utils::v8_string;
 
@@ -12,3 +12,14 @@ pub fn uuid_binding(
 
     retval.set(uuid.into());
 }
+
+pub fn uuid_v7_binding(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut retval: v8::ReturnValue,
+) {
+    let uuid = uuid_v7();
+    let uuid = v8_string(scope, &uuid);
+
+    retval.set(uuid.into());
+}
@@ -12,7 +12,7 @@ pub fn verify_init(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
 ) -> Result<Arg> {
-    let algorithm = extract_algorithm_object(scope, args.get(0))?;
+    let algorithm = extract_algorithm_object(scope, args.get(0), args.get(1))?;
     let key_value = extract_cryptokey_key_value(scope, args.get(1))?;
     let signature = extract_v8_uint8array(args.get(2))?;
     let data = extract_v8_uint8array(args.get(3))?;
@@ -26,14 +26,14 @@ pub async fn verify_binding(id: usize, arg: Arg) -> BindingResult {
     let signature = arg.2;
     let data = arg.3;
 
-    match verify(algorithm, key_value, signature, data) {
-        Ok(result) => BindingResult {
-            id,
-            result: PromiseResult::Boolean(result),
-        },
-        Err(error) => BindingResult {
-            id,
-            result: PromiseResult::Error(error.to_string()),
-        },
-    }
+    let result =
+        match tokio::task::spawn_blocking(move || verify(algorithm, key_value, signature, data))
+            .await
+        {
+            Ok(Ok(result)) => PromiseResult::Boolean(result),
+            Ok(Err(error)) => PromiseResult::Error(error.to_string()),
+            Err(error) => PromiseResult::Error(error.to_string()),
+        };
+
+    BindingResult { id, result }
 }
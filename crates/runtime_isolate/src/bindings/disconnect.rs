@@ -0,0 +1,35 @@
+use anyhow::{anyhow, Result};
+
+use crate::{bindings::PromiseResult, Isolate};
+
+use super::BindingResult;
+
+type Arg = flume::Receiver<()>;
+
+pub fn wait_disconnect_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let id = args.get(0).uint32_value(scope).unwrap_or(0);
+    let isolate_state = Isolate::state(scope);
+    let mut state = isolate_state.borrow_mut();
+
+    let handler_result = state
+        .handler_results
+        .get_mut(&id)
+        .ok_or_else(|| anyhow!("The request is not being handled"))?;
+
+    let (disconnect_tx, disconnect_rx) = flume::bounded(1);
+    handler_result.disconnect = Some(disconnect_tx);
+
+    Ok(disconnect_rx)
+}
+
+// Resolves with true once the client disconnected, either before the response
+// was sent or while it was streamed, or with false when the request is done
+pub async fn wait_disconnect_binding(id: usize, arg: Arg) -> BindingResult {
+    BindingResult {
+        id,
+        result: PromiseResult::Boolean(arg.recv_async().await.is_ok()),
+    }
+}
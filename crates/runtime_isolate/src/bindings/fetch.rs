@@ -1,30 +1,140 @@
 use anyhow::{anyhow, Result};
 use async_recursion::async_recursion;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures::future::{AbortHandle, AbortRegistration, Abortable};
 use hyper::{
+    body::{Bytes, HttpBody},
     client::HttpConnector,
-    http::{request::Builder, Uri},
+    header::{HeaderValue, CONTENT_LENGTH, LOCATION, PROXY_AUTHORIZATION},
+    http::request::Builder,
     Body, Client, Response as HyperResponse,
 };
-use hyper_tls::HttpsConnector;
-use lagon_runtime_http::{FromV8, Request, Response};
-use lazy_static::lazy_static;
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use lagon_runtime_http::{FromV8, Method, Request, Response};
+use lagon_runtime_v8_utils::{extract_v8_string, v8_string};
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use std::{fmt, io, rc::Rc, sync::Arc, time::Duration};
+use url::Url;
 
-use crate::{bindings::PromiseResult, Isolate};
+use crate::{
+    bindings::PromiseResult,
+    http_cache::{cache_mode, CacheLookup, CacheMode, CachedResponse, SharedHttpCache},
+    network_policy::{NetworkPolicy, NetworkPolicyViolation, PolicyResolver, ResolveError},
+    options::IsolateOptions,
+    proxy::{Proxy, ProxyConnector},
+    tls::get_tls_config,
+    Isolate, IsolateState,
+};
+
+use super::{pull_body::pump_body, request_id, BindingResult};
+
+pub type FetchClient = Client<HttpsConnector<ProxyConnector>>;
 
-use super::BindingResult;
+// https://fetch.spec.whatwg.org/#redirect-status
+const REDIRECT_STATUS: [u16; 5] = [301, 302, 303, 307, 308];
+const MAX_REDIRECTS: usize = 20;
+// https://fetch.spec.whatwg.org/#request-body-header-name
+const REQUEST_BODY_HEADERS: [&str; 5] = [
+    "content-encoding",
+    "content-language",
+    "content-location",
+    "content-type",
+    "content-length",
+];
+// Removed when redirected to another origin, so credentials don't leak to it
+const CROSS_ORIGIN_HEADERS: [&str; 4] = ["authorization", "proxy-authorization", "cookie", "host"];
+// The hash algorithms of subresource integrity, from the weakest to the strongest
+// https://w3c.github.io/webappsec-subresource-integrity/#hash-functions
+const INTEGRITY_ALGORITHMS: [&str; 3] = ["sha256", "sha384", "sha512"];
 
-lazy_static! {
-    static ref CLIENT: Client<HttpsConnector<HttpConnector>> =
-        Client::builder().build::<_, Body>(HttpsConnector::new());
+#[derive(Debug, Clone, Copy)]
+pub enum RedirectMode {
+    Follow,
+    Manual,
+    Error,
 }
 
-type Arg = Request;
+// Rejects fetch() with a TypeError instead of an Error, with a stable `code` to
+// branch on and the low-level error as its `cause`, if any. Other errors, e.g
+// invalid URLs, reject with an Error
+// https://fetch.spec.whatwg.org/#concept-network-error
+#[derive(Debug, Clone)]
+pub struct FetchError {
+    pub message: String,
+    pub code: &'static str,
+    pub cause: Option<String>,
+}
+
+impl FetchError {
+    fn new(code: &'static str, message: String) -> Self {
+        Self {
+            message,
+            code,
+            cause: None,
+        }
+    }
+
+    fn with_cause(mut self, cause: &dyn std::error::Error) -> Self {
+        self.cause = Some(cause.to_string());
+        self
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+// The chunks of a response body, which fail with a FetchError
+pub type FetchBodyChunk = Result<Vec<u8>, FetchError>;
+
+// How to handle redirects, how long to wait for the response, the
+// network policy each URL is checked against, the proxy to use,
+// how to use the isolate's HTTP cache and the integrity to verify
+#[derive(Debug, Clone)]
+struct FetchOptions {
+    redirect: RedirectMode,
+    // Zero disables the timeout
+    timeout: Duration,
+    network_policy: Arc<NetworkPolicy>,
+    proxy: Arc<Proxy>,
+    cache_mode: CacheMode,
+    http_cache: SharedHttpCache,
+    integrity: Option<String>,
+}
+
+// Called on the isolate's thread with the violation that rejected the request
+pub(crate) type OnViolation = Box<dyn FnOnce(&NetworkPolicyViolation)>;
+
+// Notify the isolate's callback, if any, of a violation of the request
+pub(crate) fn on_violation(state: &IsolateState) -> Option<OnViolation> {
+    state.on_network_violation.as_ref().map(|on_violation| {
+        let on_violation = Rc::clone(&on_violation.0);
+        let metadata = Rc::clone(&state.metadata);
+
+        Box::new(move |violation: &NetworkPolicyViolation| on_violation(metadata, violation))
+            as OnViolation
+    })
+}
+
+// The client to make the request with, the request and its options, the response to
+// use instead if the request was intercepted, how to abort the request, where to
+// send the body, and who to notify of network policy violations
+type Arg = (
+    FetchClient,
+    Request,
+    FetchOptions,
+    Option<Result<Response>>,
+    AbortRegistration,
+    flume::Sender<FetchBodyChunk>,
+    Option<OnViolation>,
+);
 
 pub fn fetch_init(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArguments) -> Result<Arg> {
-    let id = scope
-        .get_continuation_preserved_embedder_data()
-        .to_uint32(scope)
-        .map_or(0, |value| value.value());
+    let id = request_id(scope);
 
     let state = Isolate::state(scope);
     let fetch_calls = {
@@ -47,64 +157,686 @@ pub fn fetch_init(scope: &mut v8::HandleScope, args: v8::FunctionCallbackArgumen
         None => return Err(anyhow!("Invalid request")),
     };
 
-    Request::from_v8(scope, request.into())
+    // Identifies this fetch() call, to abort it and pull its body
+    let fetch_key = v8_string(scope, "i");
+    let fetch_id = match request
+        .get(scope, fetch_key.into())
+        .filter(|value| value.is_number())
+        .and_then(|value| value.uint32_value(scope))
+    {
+        Some(fetch_id) => fetch_id,
+        None => return Err(anyhow!("Invalid request")),
+    };
+
+    let redirect_key = v8_string(scope, "r");
+    let redirect = match request.get(scope, redirect_key.into()) {
+        Some(redirect) if !redirect.is_null_or_undefined() => {
+            match extract_v8_string(redirect, scope)?.as_str() {
+                "follow" => RedirectMode::Follow,
+                "manual" => RedirectMode::Manual,
+                "error" => RedirectMode::Error,
+                _ => return Err(anyhow!("Invalid redirect mode")),
+            }
+        }
+        _ => RedirectMode::Follow,
+    };
+
+    let cache_key = v8_string(scope, "c");
+    let cache_mode = match request.get(scope, cache_key.into()) {
+        Some(cache) if !cache.is_null_or_undefined() => {
+            match extract_v8_string(cache, scope)?.as_str() {
+                "default" => CacheMode::Default,
+                "no-store" => CacheMode::NoStore,
+                "reload" => CacheMode::Reload,
+                "no-cache" => CacheMode::NoCache,
+                "force-cache" => CacheMode::ForceCache,
+                _ => return Err(anyhow!("Invalid cache mode")),
+            }
+        }
+        _ => CacheMode::Default,
+    };
+
+    // The subresource integrity metadata, e.g `sha384-<base64 digest>`
+    let integrity_key = v8_string(scope, "s");
+    let integrity = match request.get(scope, integrity_key.into()) {
+        Some(integrity) if !integrity.is_null_or_undefined() => {
+            Some(extract_v8_string(integrity, scope)?).filter(|integrity| !integrity.is_empty())
+        }
+        _ => None,
+    };
+
+    // The timeout of the isolate is used when not set for this request
+    let timeout_key = v8_string(scope, "t");
+    let timeout = match request
+        .get(scope, timeout_key.into())
+        .filter(|value| value.is_number())
+        .and_then(|value| value.number_value(scope))
+    {
+        Some(timeout) => Duration::from_millis(timeout as u64),
+        None => state.borrow().fetch_timeout,
+    };
+
+    let request = Request::from_v8(scope, request.into())?;
+    let intercepted = state
+        .borrow()
+        .on_fetch
+        .as_ref()
+        .and_then(|on_fetch| (on_fetch.0)(&request));
+
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    let (body_sender, body_receiver) = flume::bounded(1);
+
+    if let Some(handler_result) = state.borrow_mut().handler_results.get_mut(&id) {
+        handler_result
+            .context
+            .fetch_aborts
+            .insert(fetch_id, abort_handle);
+        handler_result
+            .context
+            .fetch_bodies
+            .insert(fetch_id, body_receiver);
+    }
+
+    let state = state.borrow();
+    let on_violation = on_violation(&state);
+
+    Ok((
+        state.fetch_client.clone(),
+        request,
+        FetchOptions {
+            redirect,
+            timeout,
+            network_policy: Arc::clone(&state.network_policy),
+            proxy: Arc::clone(&state.fetch_proxy),
+            cache_mode,
+            http_cache: Arc::clone(&state.http_cache),
+            integrity,
+        },
+        intercepted,
+        abort_registration,
+        body_sender,
+        on_violation,
+    ))
+}
+
+// Abort the request or stop reading its body, which drops the connection
+pub fn abort_fetch_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _retval: v8::ReturnValue,
+) {
+    let id = request_id(scope);
+    let fetch_id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    if let Some(handler_result) = Isolate::state(scope)
+        .borrow_mut()
+        .handler_results
+        .get_mut(&id)
+    {
+        handler_result.context.fetch_bodies.remove(&fetch_id);
+
+        if let Some(abort_handle) = handler_result.context.fetch_aborts.remove(&fetch_id) {
+            abort_handle.abort();
+        }
+    }
+}
+
+pub fn pull_fetch_body_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<flume::Receiver<FetchBodyChunk>> {
+    let id = request_id(scope);
+    let fetch_id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    Isolate::state(scope)
+        .borrow()
+        .handler_results
+        .get(&id)
+        .and_then(|handler_result| handler_result.context.fetch_bodies.get(&fetch_id).cloned())
+        .ok_or_else(|| anyhow!("Response body is not available"))
+}
+
+pub async fn pull_fetch_body_binding(
+    id: usize,
+    arg: flume::Receiver<FetchBodyChunk>,
+) -> BindingResult {
+    let result = match arg.recv_async().await {
+        Ok(Ok(chunk)) => PromiseResult::ArrayBuffer(chunk),
+        Ok(Err(error)) => PromiseResult::FetchError(error),
+        // The sender is dropped once the whole body has been read
+        Err(_) => PromiseResult::Undefined,
+    };
+
+    BindingResult { id, result }
+}
+
+// Idle connections are kept alive to be reused by the next requests to the same host.
+// HTTP/2 is negotiated with ALPN, and concurrent requests then share the same connection
+pub fn create_client(
+    options: &IsolateOptions,
+    network_policy: Arc<NetworkPolicy>,
+    proxy: Arc<Proxy>,
+) -> FetchClient {
+    let resolver = PolicyResolver::new(network_policy);
+    let mut http = HttpConnector::new_with_resolver(resolver.clone());
+    http.enforce_http(false);
+    let http = ProxyConnector::new(http, resolver, proxy);
+
+    let connector = HttpsConnectorBuilder::new()
+        .with_tls_config(get_tls_config())
+        .https_or_http()
+        .enable_http1();
+
+    let connector = if options.fetch_http1_only {
+        connector.wrap_connector(http)
+    } else {
+        connector.enable_http2().wrap_connector(http)
+    };
+
+    Client::builder()
+        .pool_idle_timeout(options.fetch_pool_idle_timeout)
+        .pool_max_idle_per_host(options.fetch_pool_max_idle_per_host)
+        .build::<_, Body>(connector)
+}
+
+// The errors of the resolver are wrapped by hyper's connect errors
+pub(crate) fn find_violation(error: &anyhow::Error) -> Option<NetworkPolicyViolation> {
+    error
+        .chain()
+        .find_map(|error| error.downcast_ref::<NetworkPolicyViolation>())
+        .cloned()
+}
+
+// Find why the request to `url` failed in the errors of hyper and its connectors,
+// or None if it's not a network error
+fn classify_error(error: &anyhow::Error, url: &str) -> Option<FetchError> {
+    if let Some(violation) = find_violation(error) {
+        return Some(FetchError::new("ERR_NETWORK_POLICY", violation.to_string()));
+    }
+
+    for source in error.chain() {
+        if let Some(error) = source.downcast_ref::<FetchError>() {
+            return Some(error.clone());
+        }
+
+        if let Some(error) = source.downcast_ref::<ResolveError>() {
+            return Some(
+                FetchError::new("ENOTFOUND", format!("Could not resolve the host of {url}"))
+                    .with_cause(error),
+            );
+        }
+
+        if let Some(error) = source.downcast_ref::<io::Error>() {
+            // TLS errors are wrapped in an io::Error, whose source() skips them
+            if let Some(tls_error) = error
+                .get_ref()
+                .and_then(|error| error.downcast_ref::<rustls::Error>())
+            {
+                return Some(
+                    FetchError::new(
+                        "ERR_TLS",
+                        format!("The TLS handshake with {url} failed: {tls_error}"),
+                    )
+                    .with_cause(tls_error),
+                );
+            }
+
+            match error.kind() {
+                io::ErrorKind::ConnectionRefused => {
+                    return Some(
+                        FetchError::new(
+                            "ECONNREFUSED",
+                            format!("The connection to {url} was refused"),
+                        )
+                        .with_cause(error),
+                    )
+                }
+                io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted => {
+                    return Some(
+                        FetchError::new("ECONNRESET", format!("The connection to {url} was reset"))
+                            .with_cause(error),
+                    )
+                }
+                _ => {}
+            }
+        }
+
+        // The server closed the connection before sending the response
+        if let Some(error) = source.downcast_ref::<hyper::Error>() {
+            if error.is_incomplete_message() {
+                return Some(
+                    FetchError::new("ECONNRESET", format!("The connection to {url} was reset"))
+                        .with_cause(error),
+                );
+            }
+        }
+    }
+
+    None
+}
+
+fn body_error(url: &str, error: hyper::Error) -> FetchError {
+    FetchError::new(
+        "ERR_BODY_READ",
+        format!("Could not read the response body of {url}"),
+    )
+    .with_cause(&error)
+}
+
+fn remove_headers(request: &mut Request, names: &[&str]) {
+    if let Some(headers) = &mut request.headers {
+        headers.retain(|key, _| !names.iter().any(|name| key.eq_ignore_ascii_case(name)));
+    }
+}
+
+// Headers already set by the request are kept
+fn add_header(request: &mut Request, name: &str, value: String) {
+    let headers = request.headers.get_or_insert_with(Default::default);
+
+    if !headers.keys().any(|key| key.eq_ignore_ascii_case(name)) {
+        headers.insert(name.to_string(), vec![value]);
+    }
 }
 
+// Returns the response, with the URL it was fetched from and whether it was
+// redirected. `visited` holds the method and URL of the previous requests
+// https://fetch.spec.whatwg.org/#http-redirect-fetch
 #[async_recursion]
 async fn make_request(
-    request: &Request,
-    url: Option<String>,
-    mut count: u8,
-) -> Result<HyperResponse<Body>> {
-    if count >= 5 {
-        return Err(anyhow!("Too many redirects"));
+    client: &FetchClient,
+    mut request: Request,
+    options: &FetchOptions,
+    mut visited: Vec<String>,
+) -> Result<(HyperResponse<Body>, String, bool)> {
+    // Invalid URLs are rejected by hyper. The URL of each redirect is checked too
+    if let Ok(url) = Url::parse(&request.url) {
+        options.network_policy.check_url(&url)?;
     }
 
-    let mut hyper_request = Builder::try_from(request)?;
+    let mut hyper_request = Builder::try_from(&request)?.body(Body::from(request.body.clone()))?;
 
-    if let Some(url) = url {
-        hyper_request = hyper_request.uri(url);
+    if let Some(authorization) = options.proxy.forward_authorization(hyper_request.uri()) {
+        hyper_request
+            .headers_mut()
+            .insert(PROXY_AUTHORIZATION, HeaderValue::from_str(&authorization)?);
     }
 
-    let hyper_request = hyper_request.body(Body::from(request.body.clone()))?;
-    let uri = hyper_request.uri().clone();
-    let response = CLIENT.request(hyper_request).await?;
+    let response = client.request(hyper_request).await?;
+    let status = response.status().as_u16();
 
-    if response.status().is_redirection() {
-        let mut redirect_url = match response.headers().get("location") {
-            Some(location) => location.to_str()?.to_string(),
-            None => return Err(anyhow!("Got a redirect without Location header")),
-        };
+    // Redirects without a Location header are returned as-is
+    let location = match response.headers().get(LOCATION) {
+        Some(location) if REDIRECT_STATUS.contains(&status) => location.to_str()?.to_string(),
+        _ => return Ok((response, request.url, !visited.is_empty())),
+    };
+
+    match options.redirect {
+        RedirectMode::Follow => {}
+        RedirectMode::Manual => return Ok((response, request.url, !visited.is_empty())),
+        RedirectMode::Error => {
+            return Err(FetchError::new(
+                "ERR_REDIRECT",
+                format!("Got a redirect to {location} but the redirect mode is 'error'"),
+            )
+            .into())
+        }
+    }
+
+    if visited.len() >= MAX_REDIRECTS {
+        return Err(FetchError::new(
+            "ERR_TOO_MANY_REDIRECTS",
+            format!("Too many redirects, the maximum is {MAX_REDIRECTS}"),
+        )
+        .into());
+    }
+
+    let current_url = Url::parse(&request.url)?;
+    let location = current_url.join(&location).map_err(|_| {
+        FetchError::new(
+            "ERR_REDIRECT",
+            format!("Got a redirect to an invalid URL: {location}"),
+        )
+    })?;
+
+    if location.scheme() != "http" && location.scheme() != "https" {
+        return Err(FetchError::new(
+            "ERR_REDIRECT",
+            format!("Got a redirect to a non-HTTP URL: {location}"),
+        )
+        .into());
+    }
+
+    let method: &str = request.method.into();
+    visited.push(format!("{method} {current_url}"));
 
-        // Construct the new URL if it's a relative path
-        if redirect_url.starts_with('/') {
-            let mut uri = uri.into_parts();
-            uri.path_and_query = Some(redirect_url.parse()?);
+    // 303 redirects switch to GET, and so do 301 and 302 redirects for POST
+    if (status == 303 && !matches!(request.method, Method::GET | Method::HEAD))
+        || ((status == 301 || status == 302) && matches!(request.method, Method::POST))
+    {
+        request.method = Method::GET;
+        request.body = Bytes::new();
+        remove_headers(&mut request, &REQUEST_BODY_HEADERS);
+    }
+
+    if location.origin() != current_url.origin() {
+        remove_headers(&mut request, &CROSS_ORIGIN_HEADERS);
+    }
+
+    // The same request would lead to the same redirects again
+    let method: &str = request.method.into();
+
+    if visited.contains(&format!("{method} {location}")) {
+        return Err(FetchError::new(
+            "ERR_REDIRECT",
+            format!("Redirect loop detected at {location}"),
+        )
+        .into());
+    }
+
+    request.url = location.to_string();
+
+    make_request(client, request, options, visited).await
+}
+
+fn digest(algorithm: &str, body: &[u8]) -> Vec<u8> {
+    match algorithm {
+        "sha256" => Sha256::digest(body).to_vec(),
+        "sha384" => Sha384::digest(body).to_vec(),
+        _ => Sha512::digest(body).to_vec(),
+    }
+}
 
-            redirect_url = Uri::from_parts(uri)?.to_string();
+// Only the hashes of the strongest algorithm are compared, and metadata without
+// any supported algorithm always matches
+// https://w3c.github.io/webappsec-subresource-integrity/#does-response-match-metadatalist
+fn matches_integrity(integrity: &str, body: &[u8]) -> bool {
+    let hashes = integrity
+        .split_whitespace()
+        .filter_map(|metadata| {
+            // Options after `?` are reserved for future use
+            let metadata = metadata.split('?').next().unwrap_or_default();
+            let (algorithm, hash) = metadata.split_once('-')?;
+            let strength = INTEGRITY_ALGORITHMS
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(algorithm))?;
+
+            Some((strength, hash))
+        })
+        .collect::<Vec<_>>();
+
+    let strongest = match hashes.iter().map(|(strength, _)| *strength).max() {
+        Some(strongest) => strongest,
+        None => return true,
+    };
+    let expected = digest(INTEGRITY_ALGORITHMS[strongest], body);
+
+    hashes
+        .iter()
+        .filter(|(strength, _)| *strength == strongest)
+        .filter_map(|(_, hash)| STANDARD.decode(hash).ok())
+        .any(|hash| hash == expected)
+}
+
+fn check_integrity(options: &FetchOptions, response: &Response, url: &str) -> Result<()> {
+    match &options.integrity {
+        Some(integrity) if !matches_integrity(integrity, &response.body) => Err(FetchError::new(
+            "ERR_INTEGRITY",
+            format!("The response of {url} does not match the integrity {integrity}"),
+        )
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+async fn read_body(mut body: Body, max_response_size: Option<usize>, url: &str) -> Result<Bytes> {
+    let mut bytes = Vec::new();
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|error| body_error(url, error))?;
+
+        if let Some(max_response_size) = max_response_size {
+            if bytes.len() + chunk.len() > max_response_size {
+                return Err(NetworkPolicyViolation::ResponseTooLarge {
+                    url: url.to_string(),
+                    max_response_size,
+                }
+                .into());
+            }
         }
 
-        count += 1;
-        return make_request(request, Some(redirect_url), count).await;
+        bytes.extend_from_slice(&chunk);
     }
 
-    Ok(response)
+    Ok(Bytes::from(bytes))
 }
 
-pub async fn fetch_binding(id: usize, arg: Arg) -> BindingResult {
-    let hyper_response = match make_request(&arg, None, 0).await {
-        Ok(hyper_response) => hyper_response,
-        Err(error) => {
-            return BindingResult {
-                id,
-                result: PromiseResult::Error(error.to_string()),
+// The response, and its body to stream to the isolate. The whole body is read
+// first when the response is cached or its integrity has to be verified
+type FetchedResponse = (Response, Option<Body>, String, bool);
+
+fn cached_response(options: &FetchOptions, cached: CachedResponse) -> Result<FetchedResponse> {
+    check_integrity(options, &cached.response, &cached.url)?;
+
+    Ok((cached.response, None, cached.url, cached.redirected))
+}
+
+// https://fetch.spec.whatwg.org/#http-network-or-cache-fetch
+async fn fetch_response(
+    client: &FetchClient,
+    mut request: Request,
+    options: &FetchOptions,
+) -> Result<FetchedResponse> {
+    let mode = cache_mode(&request, options.cache_mode);
+
+    // Let the caches between the server and the isolate know about the cache mode
+    match options.cache_mode {
+        CacheMode::NoStore | CacheMode::Reload => {
+            add_header(&mut request, "cache-control", "no-cache".into());
+            add_header(&mut request, "pragma", "no-cache".into());
+        }
+        CacheMode::NoCache => add_header(&mut request, "cache-control", "max-age=0".into()),
+        _ => {}
+    }
+
+    // Unsafe methods invalidate the cached response of their URL
+    if !matches!(request.method, Method::GET | Method::HEAD) {
+        options.http_cache.lock().unwrap().remove(&request.url);
+    }
+
+    let lookup = match mode {
+        CacheMode::NoStore | CacheMode::Reload => CacheLookup::Miss,
+        _ => options.http_cache.lock().unwrap().lookup(&request, mode),
+    };
+
+    // Stored without the validators added below, to compare the headers listed in Vary
+    let cache_request = Request {
+        headers: request.headers.clone(),
+        method: request.method,
+        body: Bytes::new(),
+        url: request.url.clone(),
+    };
+
+    match lookup {
+        CacheLookup::Hit(cached) => return cached_response(options, cached),
+        CacheLookup::Revalidate(ref cached) => {
+            for (name, value) in cached.validators() {
+                add_header(&mut request, name, value);
+            }
+        }
+        CacheLookup::Miss => {}
+    }
+
+    let (hyper_response, url, redirected) =
+        make_request(client, request, options, Vec::new()).await?;
+
+    // Responses without a Content-Length header fail once reading the body
+    let max_response_size = options.network_policy.max_response_size;
+    let content_length = hyper_response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+
+    if let (Some(content_length), Some(max_response_size)) = (content_length, max_response_size) {
+        if content_length > max_response_size {
+            return Err(NetworkPolicyViolation::ResponseTooLarge {
+                url,
+                max_response_size,
             }
+            .into());
         }
+    }
+
+    let (mut response, body) = Response::from_hyper_streamed(hyper_response)?;
+
+    if let CacheLookup::Revalidate(cached) = lookup {
+        if response.status == 304 {
+            let cached = options.http_cache.lock().unwrap().update(
+                &cache_request,
+                cached,
+                &response.headers,
+            );
+
+            return cached_response(options, cached);
+        }
+    }
+
+    let store = mode != CacheMode::NoStore && {
+        let mut http_cache = options.http_cache.lock().unwrap();
+        let store = http_cache.can_store(&cache_request, &response, content_length);
+
+        // The new response replaces the cached one, even if it can't be stored
+        if !store {
+            http_cache.remove(&cache_request.url);
+        }
+
+        store
     };
 
-    let result = match Response::from_hyper(hyper_response).await {
-        Ok(response) => PromiseResult::Response(response),
-        Err(error) => PromiseResult::Error(error.to_string()),
+    if !store && options.integrity.is_none() {
+        return Ok((response, Some(body), url, redirected));
+    }
+
+    response.body = read_body(body, max_response_size, &url).await?;
+
+    if store {
+        options.http_cache.lock().unwrap().store(
+            &cache_request,
+            response.clone(),
+            url.clone(),
+            redirected,
+        );
+    }
+
+    check_integrity(options, &response, &url)?;
+
+    Ok((response, None, url, redirected))
+}
+
+pub async fn fetch_binding(id: usize, arg: Arg) -> BindingResult {
+    let (client, request, options, intercepted, abort_registration, body_sender, on_violation) =
+        arg;
+
+    if let Some(intercepted) = intercepted {
+        return BindingResult {
+            id,
+            result: match intercepted {
+                Ok(response) => PromiseResult::Response(response),
+                Err(error) => PromiseResult::Error(error.to_string()),
+            },
+        };
+    }
+
+    let (response_sender, response_receiver) = flume::bounded(1);
+    let url = request.url.clone();
+
+    // The request and its body are read in a separate task, so the body can be
+    // pulled after the promise resolved. Aborting the task drops the connection
+    tokio::spawn(Abortable::new(
+        async move {
+            // Resolving the host, connecting and following the redirects count toward the
+            // timeout, and so does reading the body when it's read before resolving
+            let response = fetch_response(&client, request, &options);
+            let response = if options.timeout.is_zero() {
+                response.await
+            } else {
+                match tokio::time::timeout(options.timeout, response).await {
+                    Ok(response) => response,
+                    Err(_) => {
+                        let error = FetchError::new(
+                            "ETIMEDOUT",
+                            format!(
+                                "The request timed out after {}ms",
+                                options.timeout.as_millis()
+                            ),
+                        );
+
+                        response_sender
+                            .send((PromiseResult::FetchError(error), None))
+                            .unwrap_or(());
+                        return;
+                    }
+                }
+            };
+
+            let max_response_size = options.network_policy.max_response_size;
+
+            match response {
+                Ok((response, None, url, redirected)) => {
+                    response_sender
+                        .send((PromiseResult::Fetched(response, url, redirected), None))
+                        .unwrap_or(());
+                }
+                Ok((response, Some(body), url, redirected)) => {
+                    let too_large_error = FetchError::new(
+                        "ERR_NETWORK_POLICY",
+                        NetworkPolicyViolation::ResponseTooLarge {
+                            url: url.clone(),
+                            max_response_size: max_response_size.unwrap_or(usize::MAX),
+                        }
+                        .to_string(),
+                    );
+                    let body_url = url.clone();
+
+                    response_sender
+                        .send((PromiseResult::Fetched(response, url, redirected), None))
+                        .unwrap_or(());
+                    // Responses are streamed to the isolate, so they are only
+                    // limited by the network policy
+                    pump_body(
+                        body,
+                        max_response_size.unwrap_or(usize::MAX),
+                        too_large_error,
+                        |error| body_error(&body_url, error),
+                        |_| {},
+                        body_sender,
+                    )
+                    .await;
+                }
+                Err(error) => {
+                    let violation = find_violation(&error);
+                    let result = match classify_error(&error, &url) {
+                        Some(error) => PromiseResult::FetchError(error),
+                        None => PromiseResult::Error(error.to_string()),
+                    };
+
+                    response_sender.send((result, violation)).unwrap_or(());
+                }
+            }
+        },
+        abort_registration,
+    ));
+
+    let result = match response_receiver.recv_async().await {
+        Ok((result, violation)) => {
+            if let (Some(on_violation), Some(violation)) = (on_violation, violation) {
+                on_violation(&violation);
+            }
+
+            result
+        }
+        // The task is dropped before sending the response when aborted
+        Err(_) => PromiseResult::Error("The operation was aborted".into()),
     };
 
     BindingResult { id, result }
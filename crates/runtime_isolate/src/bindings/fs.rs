@@ -0,0 +1,59 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Result};
+
+use crate::{bindings::PromiseResult, Isolate};
+
+use super::BindingResult;
+
+// The allowed directory, and the path to read relative to it
+type Arg = (PathBuf, String);
+
+pub fn read_file_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let root = match &Isolate::state(scope).borrow().fs_root {
+        Some(root) => root.clone(),
+        None => return Err(anyhow!("Filesystem access is not allowed")),
+    };
+
+    if !args.get(0).is_string() {
+        return Err(anyhow!("Invalid path"));
+    }
+
+    Ok((root, args.get(0).to_rust_string_lossy(scope)))
+}
+
+// Symlinks and `..` are resolved first, so the
+// file can't be outside of the allowed directory
+async fn read_file(root: &Path, path: &str) -> Result<Vec<u8>> {
+    let root = tokio::fs::canonicalize(root)
+        .await
+        .map_err(|err| anyhow!("Could not read {}: {}", path, err))?;
+    let resolved_path = tokio::fs::canonicalize(root.join(path))
+        .await
+        .map_err(|err| anyhow!("Could not read {}: {}", path, err))?;
+
+    if !resolved_path.starts_with(&root) {
+        return Err(anyhow!(
+            "Could not read {}: the file is outside of the allowed directory",
+            path
+        ));
+    }
+
+    tokio::fs::read(resolved_path)
+        .await
+        .map_err(|err| anyhow!("Could not read {}: {}", path, err))
+}
+
+pub async fn read_file_binding(id: usize, arg: Arg) -> BindingResult {
+    let (root, path) = arg;
+
+    let result = match read_file(&root, &path).await {
+        Ok(content) => PromiseResult::ArrayBuffer(content),
+        Err(error) => PromiseResult::Error(error.to_string()),
+    };
+
+    BindingResult { id, result }
+}
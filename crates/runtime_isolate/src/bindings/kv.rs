@@ -0,0 +1,210 @@
+use anyhow::{anyhow, Result};
+use lagon_runtime_v8_utils::{extract_v8_string, extract_v8_uint8array, v8_string};
+use std::{sync::Arc, time::Duration};
+
+use crate::{
+    bindings::PromiseResult,
+    kv::{KvBackend, KvValue, MAX_KEY_SIZE, MAX_LIST_LIMIT},
+    Isolate,
+};
+
+use super::BindingResult;
+
+pub enum KvOperation {
+    Get(String),
+    Put(String, KvValue, Option<Duration>),
+    Delete(String),
+    List(String, usize, Option<String>),
+}
+
+// Invalid arguments reject with an Error, once the binding runs
+type Arg = Result<(Arc<dyn KvBackend>, KvOperation), String>;
+
+fn extract_key(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Result<String> {
+    if !value.is_string() {
+        return Err(anyhow!("Keys must be strings"));
+    }
+
+    let key = value.to_rust_string_lossy(scope);
+
+    if key.is_empty() || key.len() > MAX_KEY_SIZE {
+        return Err(anyhow!(
+            "Keys must be between 1 and {MAX_KEY_SIZE} bytes long"
+        ));
+    }
+
+    Ok(key)
+}
+
+fn extract_value(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Result<KvValue> {
+    if value.is_string() {
+        return Ok(KvValue::String(value.to_rust_string_lossy(scope)));
+    }
+
+    if let Ok(view) = v8::Local::<v8::ArrayBufferView>::try_from(value) {
+        let mut bytes = vec![0; view.byte_length()];
+        view.copy_contents(&mut bytes);
+
+        return Ok(KvValue::Bytes(bytes));
+    }
+
+    if let Ok(buffer) = v8::Local::<v8::ArrayBuffer>::try_from(value) {
+        let view = v8::Uint8Array::new(scope, buffer, 0, buffer.byte_length())
+            .ok_or_else(|| anyhow!("Invalid ArrayBuffer"))?;
+
+        return Ok(KvValue::Bytes(extract_v8_uint8array(view.into())?));
+    }
+
+    Err(anyhow!(
+        "Values must be strings, ArrayBuffers or ArrayBuffer views"
+    ))
+}
+
+// A positive number of milliseconds, if given
+fn extract_ttl(
+    scope: &mut v8::HandleScope,
+    options: v8::Local<v8::Value>,
+) -> Result<Option<Duration>> {
+    if options.is_null_or_undefined() {
+        return Ok(None);
+    }
+
+    let options = match options.to_object(scope) {
+        Some(options) => options,
+        None => return Ok(None),
+    };
+
+    let ttl_key = v8_string(scope, "ttl");
+
+    match options.get(scope, ttl_key.into()) {
+        Some(ttl) if !ttl.is_null_or_undefined() => match ttl.number_value(scope) {
+            Some(ttl) if ttl.is_finite() && ttl > 0.0 => {
+                Ok(Some(Duration::from_millis(ttl as u64)))
+            }
+            _ => Err(anyhow!("The ttl must be a positive number of milliseconds")),
+        },
+        _ => Ok(None),
+    }
+}
+
+fn kv_args(
+    scope: &mut v8::HandleScope,
+    args: &v8::FunctionCallbackArguments,
+    operation: impl FnOnce(&mut v8::HandleScope, &v8::FunctionCallbackArguments) -> Result<KvOperation>,
+) -> Result<Arg> {
+    let (backend, max_value_size) = {
+        let state = Isolate::state(scope);
+        let state = state.borrow();
+
+        match &state.kv_backend {
+            Some(backend) => (Arc::clone(backend), state.kv_max_value_size),
+            None => return Err(anyhow!("Lagon.kv is not available in this runtime")),
+        }
+    };
+
+    let operation = match operation(scope, args) {
+        Ok(KvOperation::Put(key, value, _)) if value.size() > max_value_size => {
+            return Ok(Err(format!(
+                "The value of {key} is {} bytes, larger than the maximum of {max_value_size} bytes",
+                value.size()
+            )))
+        }
+        Ok(operation) => operation,
+        Err(error) => return Ok(Err(error.to_string())),
+    };
+
+    Ok(Ok((backend, operation)))
+}
+
+pub fn kv_get_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    kv_args(scope, &args, |scope, args| {
+        Ok(KvOperation::Get(extract_key(scope, args.get(0))?))
+    })
+}
+
+pub fn kv_put_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    kv_args(scope, &args, |scope, args| {
+        Ok(KvOperation::Put(
+            extract_key(scope, args.get(0))?,
+            extract_value(scope, args.get(1))?,
+            extract_ttl(scope, args.get(2))?,
+        ))
+    })
+}
+
+pub fn kv_delete_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    kv_args(scope, &args, |scope, args| {
+        Ok(KvOperation::Delete(extract_key(scope, args.get(0))?))
+    })
+}
+
+// All the arguments are optional: list(prefix, limit, cursor)
+pub fn kv_list_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    kv_args(scope, &args, |scope, args| {
+        let prefix = match args.get(0) {
+            prefix if prefix.is_null_or_undefined() => String::new(),
+            prefix => extract_v8_string(prefix, scope)?,
+        };
+
+        let limit = match args.get(1) {
+            limit if limit.is_null_or_undefined() => MAX_LIST_LIMIT,
+            limit => match limit.number_value(scope) {
+                Some(limit) if (1.0..=MAX_LIST_LIMIT as f64).contains(&limit) => limit as usize,
+                _ => {
+                    return Err(anyhow!(
+                        "The limit must be a number between 1 and {MAX_LIST_LIMIT}"
+                    ))
+                }
+            },
+        };
+
+        let cursor = match args.get(2) {
+            cursor if cursor.is_null_or_undefined() => None,
+            cursor => Some(extract_v8_string(cursor, scope)?),
+        };
+
+        Ok(KvOperation::List(prefix, limit, cursor))
+    })
+}
+
+pub async fn kv_binding(id: usize, arg: Arg) -> BindingResult {
+    let (backend, operation) = match arg {
+        Ok(arg) => arg,
+        Err(error) => {
+            return BindingResult {
+                id,
+                result: PromiseResult::Exception(error),
+            }
+        }
+    };
+
+    let result = match operation {
+        KvOperation::Get(key) => backend.get(&key).await.map(PromiseResult::KvValue),
+        KvOperation::Put(key, value, ttl) => backend
+            .put(&key, value, ttl)
+            .await
+            .map(|_| PromiseResult::Undefined),
+        KvOperation::Delete(key) => backend.delete(&key).await.map(|_| PromiseResult::Undefined),
+        KvOperation::List(prefix, limit, cursor) => backend
+            .list(&prefix, limit, cursor.as_deref())
+            .await
+            .map(PromiseResult::KvList),
+    };
+
+    BindingResult {
+        id,
+        result: result.unwrap_or_else(|error| PromiseResult::Exception(error.to_string())),
+    }
+}
@@ -1,26 +1,101 @@
+use self::url::{parse_url_binding, set_url_binding};
+use cache::{
+    cache_delete_binding, cache_delete_init, cache_match_binding, cache_match_init,
+    cache_put_binding, cache_put_init,
+};
+use compression::{create_codec_binding, transform_codec_binding, transform_codec_init};
 use console::console_binding;
 use crypto::{
-    decrypt_binding, decrypt_init, digest_binding, encrypt_binding, encrypt_init,
-    get_key_value_binding, random_values_binding, sign_binding, sign_init, uuid_binding,
-    verify_binding, verify_init,
+    decrypt_binding, decrypt_init, derive_bits_binding, derive_bits_init, digest_binding,
+    encrypt_binding, encrypt_init, export_key_binding, export_key_init, generate_key_binding,
+    generate_key_init, get_key_value_binding, import_key_binding, import_key_init,
+    random_values_binding, sign_binding, sign_init, uuid_binding, uuid_v7_binding, verify_binding,
+    verify_init,
+};
+use disconnect::{wait_disconnect_binding, wait_disconnect_init};
+use fetch::{
+    abort_fetch_binding, fetch_binding, fetch_init, pull_fetch_body_binding, pull_fetch_body_init,
+    FetchError,
 };
-use fetch::{fetch_binding, fetch_init};
+use fs::{read_file_binding, read_file_init};
+use kv::{kv_binding, kv_delete_init, kv_get_init, kv_list_init, kv_put_init};
 use lagon_runtime_http::{IntoV8, Response};
-use lagon_runtime_v8_utils::{v8_boolean, v8_string, v8_uint8array};
-use pull_stream::pull_stream_binding;
+use lagon_runtime_v8_utils::{v8_boolean, v8_exception, v8_integer, v8_string, v8_uint8array};
+use performance::{
+    performance_entries_binding, performance_now_binding, performance_time_origin_binding,
+};
+use pull_body::{pull_body_binding, pull_body_init};
+use pull_stream::{pull_stream_binding, wait_stream_binding, wait_stream_init};
+use queue::{queue_send_binding, queue_send_init};
 use queue_microtask::queue_microtask_binding;
 use sleep::{sleep_binding, sleep_init};
+use structured_clone::structured_clone_binding;
+use wait_until::wait_until_binding;
+use websocket::{
+    websocket_abort_binding, websocket_close_binding, websocket_connect_binding,
+    websocket_connect_init, websocket_receive_binding, websocket_receive_init,
+    websocket_send_binding, websocket_upgrade_binding,
+};
 
-use crate::{bindings::crypto::digest_init, Isolate};
+use crate::{
+    bindings::crypto::digest_init,
+    kv::{KvList, KvValue},
+    Isolate,
+};
 
+pub mod cache;
+pub mod compression;
 pub mod console;
 pub mod crypto;
+pub mod disconnect;
 pub mod fetch;
+pub mod fs;
+pub mod kv;
+pub mod performance;
+pub mod pull_body;
 pub mod pull_stream;
+pub mod queue;
 pub mod queue_microtask;
 pub mod sleep;
+pub mod structured_clone;
+pub mod url;
+pub mod wait_until;
+pub mod websocket;
 
 pub use console::CONSOLE_SOURCE;
+pub use pull_body::{stream_request_body, RequestBodyChunk};
+
+// The id of the request currently being handled, or 0 outside of a request
+pub fn request_id(scope: &mut v8::HandleScope) -> u32 {
+    scope
+        .get_continuation_preserved_embedder_data()
+        .to_uint32(scope)
+        .map_or(0, |value| value.value())
+}
+
+// A DOMException with the given name, or a TypeError if DOMException isn't defined
+pub fn dom_exception<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    message: v8::Local<'s, v8::String>,
+    name: &str,
+) -> v8::Local<'s, v8::Value> {
+    let global = scope.get_current_context().global(scope);
+    let constructor_name = v8_string(scope, "DOMException");
+
+    if let Some(constructor) = global
+        .get(scope, constructor_name.into())
+        .and_then(|constructor| v8::Local::<v8::Function>::try_from(constructor).ok())
+    {
+        let name = v8_string(scope, name);
+
+        if let Some(exception) = constructor.new_instance(scope, &[message.into(), name.into()]) {
+            return exception.into();
+        }
+    }
+
+    let message = message.to_rust_string_lossy(scope);
+    v8_exception(scope, &message)
+}
 
 pub struct BindingResult {
     pub id: usize,
@@ -29,9 +104,22 @@ pub struct BindingResult {
 
 pub enum PromiseResult {
     Response(Response),
+    // A fetch() response, with its final URL and whether it was redirected
+    Fetched(Response, String, bool),
     ArrayBuffer(Vec<u8>),
+    ArrayBuffers(Vec<Vec<u8>>),
     Boolean(bool),
+    String(String),
+    // The code and reason of a closed WebSocket
+    Close(u16, String),
     Error(String),
+    // Rejects with a TypeError, with the `code` and `cause` of the failure
+    FetchError(FetchError),
+    // Rejects with an Error instead of a string
+    Exception(String),
+    // A string or an ArrayBuffer, or null when the key doesn't exist
+    KvValue(Option<KvValue>),
+    KvList(KvList),
     Undefined,
 }
 
@@ -39,9 +127,99 @@ impl PromiseResult {
     pub fn into_value<'a>(self, scope: &mut v8::HandleScope<'a>) -> v8::Local<'a, v8::Value> {
         match self {
             PromiseResult::Response(response) => response.into_v8(scope).into(),
+            PromiseResult::Fetched(response, url, redirected) => {
+                let object = response.into_v8(scope);
+
+                let key = v8_string(scope, "u");
+                let value = v8_string(scope, &url);
+                object.set(scope, key.into(), value.into());
+
+                let key = v8_string(scope, "r");
+                let value = v8_boolean(scope, redirected);
+                object.set(scope, key.into(), value.into());
+
+                object.into()
+            }
             PromiseResult::ArrayBuffer(bytes) => v8_uint8array(scope, bytes).into(),
+            PromiseResult::ArrayBuffers(buffers) => {
+                let elements = buffers
+                    .into_iter()
+                    .map(|bytes| v8_uint8array(scope, bytes).into())
+                    .collect::<Vec<v8::Local<v8::Value>>>();
+
+                v8::Array::new_with_elements(scope, &elements).into()
+            }
             PromiseResult::Boolean(boolean) => v8_boolean(scope, boolean).into(),
+            PromiseResult::String(string) => v8_string(scope, &string).into(),
+            PromiseResult::Close(code, reason) => {
+                let object = v8::Object::new(scope);
+
+                let key = v8_string(scope, "c");
+                let value = v8_integer(scope, code.into());
+                object.set(scope, key.into(), value.into());
+
+                let key = v8_string(scope, "r");
+                let value = v8_string(scope, &reason);
+                object.set(scope, key.into(), value.into());
+
+                object.into()
+            }
             PromiseResult::Error(error) => v8_string(scope, &error).into(),
+            PromiseResult::FetchError(error) => {
+                let exception = v8_exception(scope, &error.message);
+
+                if let Some(object) = exception.to_object(scope) {
+                    let key = v8_string(scope, "code");
+                    let value = v8_string(scope, error.code);
+                    object.set(scope, key.into(), value.into());
+
+                    if let Some(cause) = error.cause {
+                        let key = v8_string(scope, "cause");
+                        let message = v8_string(scope, &cause);
+                        let value = v8::Exception::error(scope, message);
+                        object.set(scope, key.into(), value);
+                    }
+                }
+
+                exception
+            }
+            PromiseResult::Exception(error) => {
+                let message = v8_string(scope, &error);
+                v8::Exception::error(scope, message)
+            }
+            PromiseResult::KvValue(value) => match value {
+                Some(KvValue::String(string)) => v8_string(scope, &string).into(),
+                Some(KvValue::Bytes(bytes)) => {
+                    let array = v8_uint8array(scope, bytes);
+
+                    match array.buffer(scope) {
+                        Some(buffer) => buffer.into(),
+                        None => array.into(),
+                    }
+                }
+                None => v8::null(scope).into(),
+            },
+            PromiseResult::KvList(list) => {
+                let object = v8::Object::new(scope);
+
+                let keys = list
+                    .keys
+                    .iter()
+                    .map(|key| v8_string(scope, key).into())
+                    .collect::<Vec<v8::Local<v8::Value>>>();
+                let key = v8_string(scope, "keys");
+                let value = v8::Array::new_with_elements(scope, &keys);
+                object.set(scope, key.into(), value.into());
+
+                let key = v8_string(scope, "cursor");
+                let value = match list.cursor {
+                    Some(cursor) => v8_string(scope, &cursor).into(),
+                    None => v8::null(scope).into(),
+                };
+                object.set(scope, key.into(), value);
+
+                object.into()
+            }
             PromiseResult::Undefined => v8::undefined(scope).into(),
         }
     }
@@ -122,6 +300,48 @@ pub fn bind<'a>(
             "queueMicrotask",
             queue_microtask_binding
         );
+        binding!(scope, lagon_object, "parseUrl", parse_url_binding);
+        binding!(scope, lagon_object, "setUrl", set_url_binding);
+        binding!(scope, lagon_object, "abortFetch", abort_fetch_binding);
+        binding!(scope, lagon_object, "createCodec", create_codec_binding);
+        binding!(
+            scope,
+            lagon_object,
+            "structuredClone",
+            structured_clone_binding
+        );
+        binding!(
+            scope,
+            lagon_object,
+            "performanceNow",
+            performance_now_binding
+        );
+        binding!(
+            scope,
+            lagon_object,
+            "performanceTimeOrigin",
+            performance_time_origin_binding
+        );
+        binding!(
+            scope,
+            lagon_object,
+            "performanceEntries",
+            performance_entries_binding
+        );
+        binding!(scope, lagon_object, "waitUntil", wait_until_binding);
+        binding!(scope, lagon_object, "webSocketSend", websocket_send_binding);
+        binding!(
+            scope,
+            lagon_object,
+            "webSocketClose",
+            websocket_close_binding
+        );
+        binding!(
+            scope,
+            lagon_object,
+            "webSocketAbort",
+            websocket_abort_binding
+        );
 
         global.set(v8_string(scope, "LagonSync").into(), lagon_object.into());
     }
@@ -147,9 +367,142 @@ pub fn bind<'a>(
             decrypt_init,
             decrypt_binding
         );
+        async_binding!(
+            scope,
+            lagon_object,
+            "importKey",
+            import_key_init,
+            import_key_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "exportKey",
+            export_key_init,
+            export_key_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "deriveBits",
+            derive_bits_init,
+            derive_bits_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "generateKey",
+            generate_key_init,
+            generate_key_binding
+        );
         async_binding!(scope, lagon_object, "sleep", sleep_init, sleep_binding);
+        async_binding!(
+            scope,
+            lagon_object,
+            "pullBody",
+            pull_body_init,
+            pull_body_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "pullFetchBody",
+            pull_fetch_body_init,
+            pull_fetch_body_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "waitStream",
+            wait_stream_init,
+            wait_stream_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "waitDisconnect",
+            wait_disconnect_init,
+            wait_disconnect_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "transformCodec",
+            transform_codec_init,
+            transform_codec_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "webSocketConnect",
+            websocket_connect_init,
+            websocket_connect_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "webSocketReceive",
+            websocket_receive_init,
+            websocket_receive_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "cacheMatch",
+            cache_match_init,
+            cache_match_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "cachePut",
+            cache_put_init,
+            cache_put_binding
+        );
+        async_binding!(
+            scope,
+            lagon_object,
+            "cacheDelete",
+            cache_delete_init,
+            cache_delete_binding
+        );
 
         global.set(v8_string(scope, "LagonAsync").into(), lagon_object.into());
+
+        // Public APIs, directly exposed to Functions
+        let lagon_object = v8::ObjectTemplate::new(scope);
+        let fs_object = v8::ObjectTemplate::new(scope);
+
+        async_binding!(
+            scope,
+            fs_object,
+            "readFile",
+            read_file_init,
+            read_file_binding
+        );
+
+        let kv_object = v8::ObjectTemplate::new(scope);
+
+        async_binding!(scope, kv_object, "get", kv_get_init, kv_binding);
+        async_binding!(scope, kv_object, "put", kv_put_init, kv_binding);
+        async_binding!(scope, kv_object, "delete", kv_delete_init, kv_binding);
+        async_binding!(scope, kv_object, "list", kv_list_init, kv_binding);
+
+        let queue_object = v8::ObjectTemplate::new(scope);
+
+        async_binding!(
+            scope,
+            queue_object,
+            "send",
+            queue_send_init,
+            queue_send_binding
+        );
+
+        binding!(scope, lagon_object, "uuidv7", uuid_v7_binding);
+        lagon_object.set(v8_string(scope, "fs").into(), fs_object.into());
+        lagon_object.set(v8_string(scope, "kv").into(), kv_object.into());
+        lagon_object.set(v8_string(scope, "queue").into(), queue_object.into());
+        global.set(v8_string(scope, "Lagon").into(), lagon_object.into());
     }
 
     v8::Context::new_from_template(scope, global)
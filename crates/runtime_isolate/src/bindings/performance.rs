@@ -0,0 +1,58 @@
+use crate::Isolate;
+
+use super::request_id;
+
+// Coarse timers are rounded down to this resolution, in milliseconds
+const COARSE_RESOLUTION: f64 = 0.1;
+
+pub fn performance_now_binding(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut retval: v8::ReturnValue,
+) {
+    let (time_origin, coarse_timers) = {
+        let state = Isolate::state(scope);
+        let state = state.borrow();
+
+        (state.time_origin, state.coarse_timers)
+    };
+
+    let now = time_origin.elapsed().as_secs_f64() * 1000.0;
+    let now = match coarse_timers {
+        true => (now / COARSE_RESOLUTION).floor() * COARSE_RESOLUTION,
+        false => now,
+    };
+
+    retval.set(v8::Number::new(scope, now).into());
+}
+
+pub fn performance_time_origin_binding(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut retval: v8::ReturnValue,
+) {
+    let time_origin = Isolate::state(scope).borrow().time_origin_unix;
+
+    retval.set(v8::Number::new(scope, time_origin).into());
+}
+
+// Entries are stored with the request, so they aren't shared between
+// requests and are dropped once the request is done
+pub fn performance_entries_binding(
+    scope: &mut v8::HandleScope,
+    _args: v8::FunctionCallbackArguments,
+    mut retval: v8::ReturnValue,
+) {
+    let id = request_id(scope);
+    let isolate_state = Isolate::state(scope);
+    let mut state = isolate_state.borrow_mut();
+
+    if let Some(handler_result) = state.handler_results.get_mut(&id) {
+        let entries = handler_result.performance_entries.get_or_insert_with(|| {
+            let entries = v8::Array::new(scope, 0);
+            v8::Global::new(scope, entries)
+        });
+
+        retval.set(v8::Local::new(scope, &*entries).into());
+    }
+}
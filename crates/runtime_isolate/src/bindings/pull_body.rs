@@ -0,0 +1,92 @@
+use anyhow::{anyhow, Result};
+use hyper::{body::HttpBody, Body};
+use lagon_runtime_http::BodyTooLargeError;
+
+use crate::{bindings::PromiseResult, Isolate};
+
+use super::BindingResult;
+
+pub type RequestBodyChunk = Result<Vec<u8>, String>;
+
+type Arg = flume::Receiver<RequestBodyChunk>;
+
+// Pump the chunks of a request body as they arrive. The channel only holds a single
+// chunk, so the next one is read from the connection once the isolate pulled it.
+// `on_chunk` is called with the size of each chunk read, e.g to count the bytes received
+pub fn stream_request_body(
+    body: Body,
+    max_body_size: usize,
+    on_chunk: impl Fn(usize) + Send + 'static,
+) -> flume::Receiver<RequestBodyChunk> {
+    let (sender, receiver) = flume::bounded(1);
+
+    let too_large_error = BodyTooLargeError { max_body_size }.to_string();
+    tokio::spawn(pump_body(
+        body,
+        max_body_size,
+        too_large_error,
+        |error| error.to_string(),
+        on_chunk,
+        sender,
+    ));
+
+    receiver
+}
+
+// `too_large_error` is sent instead of the chunk that would exceed `max_body_size`,
+// and errors reading the body are converted with `map_error`
+pub async fn pump_body<E: Clone>(
+    mut body: Body,
+    max_body_size: usize,
+    too_large_error: E,
+    map_error: impl Fn(hyper::Error) -> E,
+    on_chunk: impl Fn(usize),
+    sender: flume::Sender<Result<Vec<u8>, E>>,
+) {
+    let mut size = 0;
+
+    while let Some(chunk) = body.data().await {
+        let chunk = match chunk {
+            Ok(chunk) if chunk.is_empty() => continue,
+            Ok(chunk) if size + chunk.len() > max_body_size => Err(too_large_error.clone()),
+            Ok(chunk) => {
+                size += chunk.len();
+                on_chunk(chunk.len());
+                Ok(chunk.to_vec())
+            }
+            Err(error) => Err(map_error(error)),
+        };
+        let is_error = chunk.is_err();
+
+        // The receiver is dropped when the request is done, and we
+        // don't need to read the rest of the body anymore
+        if sender.send_async(chunk).await.is_err() || is_error {
+            break;
+        }
+    }
+}
+
+pub fn pull_body_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    Isolate::state(scope)
+        .borrow()
+        .handler_results
+        .get(&id)
+        .and_then(|handler_result| handler_result.body_stream.clone())
+        .ok_or_else(|| anyhow!("Request body is not streamed"))
+}
+
+pub async fn pull_body_binding(id: usize, arg: Arg) -> BindingResult {
+    let result = match arg.recv_async().await {
+        Ok(Ok(chunk)) => PromiseResult::ArrayBuffer(chunk),
+        Ok(Err(error)) => PromiseResult::Error(error),
+        // The sender is dropped once the whole body has been read
+        Err(_) => PromiseResult::Undefined,
+    };
+
+    BindingResult { id, result }
+}
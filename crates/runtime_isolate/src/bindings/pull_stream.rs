@@ -1,12 +1,24 @@
-use lagon_runtime_http::StreamResult;
-use lagon_runtime_v8_utils::{extract_v8_uint8array, v8_exception};
+use anyhow::{anyhow, Result};
+use lagon_runtime_http::{RunResult, StreamResult};
+use lagon_runtime_v8_utils::{extract_v8_uint8array, v8_boolean, v8_exception};
+use std::time::Duration;
 
-use crate::Isolate;
+use crate::{bindings::PromiseResult, Isolate};
+
+use super::BindingResult;
+
+// How many results can be waiting to be read by the client before the
+// stream is paused, so a slow client applies backpressure to the handler
+const STREAM_HIGH_WATER_MARK: usize = 8;
+// How often to check again whether the client read the results
+const STREAM_DRAIN_INTERVAL: Duration = Duration::from_millis(1);
+
+type Arg = flume::Sender<RunResult>;
 
 pub fn pull_stream_binding(
     scope: &mut v8::HandleScope,
     args: v8::FunctionCallbackArguments,
-    mut _retval: v8::ReturnValue,
+    mut retval: v8::ReturnValue,
 ) {
     let isolate_state = Isolate::state(scope);
     let state = isolate_state.borrow();
@@ -30,7 +42,41 @@ pub fn pull_stream_binding(
             Err(error) => {
                 let exception = v8_exception(scope, error.to_string().as_str());
                 scope.throw_exception(exception);
+                return;
             }
         }
     }
+
+    // Whether the handler can continue streaming without waiting
+    let ready = match state.handler_results.get(&id) {
+        Some(handler_result) => handler_result.sender.len() < STREAM_HIGH_WATER_MARK,
+        None => true,
+    };
+
+    retval.set(v8_boolean(scope, ready).into());
+}
+
+pub fn wait_stream_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    Isolate::state(scope)
+        .borrow()
+        .handler_results
+        .get(&id)
+        .map(|handler_result| handler_result.sender.clone())
+        .ok_or_else(|| anyhow!("Response is not streamed"))
+}
+
+pub async fn wait_stream_binding(id: usize, arg: Arg) -> BindingResult {
+    while arg.len() >= STREAM_HIGH_WATER_MARK && !arg.is_disconnected() {
+        tokio::time::sleep(STREAM_DRAIN_INTERVAL).await;
+    }
+
+    BindingResult {
+        id,
+        result: PromiseResult::Undefined,
+    }
 }
@@ -0,0 +1,79 @@
+use anyhow::{anyhow, Result};
+use std::sync::Arc;
+
+use crate::{bindings::PromiseResult, queue::QueueBackend, Isolate};
+
+use super::BindingResult;
+
+// Invalid arguments reject with an Error, once the binding runs
+type Arg = Result<(Arc<dyn QueueBackend>, String, String), String>;
+
+// Like the body of a request, the message is sent as JSON
+fn serialize_message(scope: &mut v8::HandleScope, value: v8::Local<v8::Value>) -> Result<String> {
+    // JSON.stringify() returns undefined for these values
+    if value.is_undefined() || value.is_function() || value.is_symbol() {
+        return Err(anyhow!("Messages must be serializable to JSON"));
+    }
+
+    let try_catch = &mut v8::TryCatch::new(scope);
+
+    match v8::json::stringify(try_catch, value) {
+        Some(message) => Ok(message.to_rust_string_lossy(try_catch)),
+        None => {
+            let error = match try_catch.exception() {
+                Some(exception) => exception.to_rust_string_lossy(try_catch),
+                None => "Unknown error".into(),
+            };
+
+            Err(anyhow!("Could not serialize the message: {error}"))
+        }
+    }
+}
+
+pub fn queue_send_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let (backend, max_message_size) = {
+        let state = Isolate::state(scope);
+        let state = state.borrow();
+
+        match &state.queue_backend {
+            Some(backend) => (Arc::clone(backend), state.queue_max_message_size),
+            None => return Err(anyhow!("Lagon.queue is not available in this runtime")),
+        }
+    };
+
+    let queue = args.get(0);
+
+    if !queue.is_string() || queue.to_rust_string_lossy(scope).is_empty() {
+        return Ok(Err("Queue names must be non-empty strings".into()));
+    }
+
+    let queue = queue.to_rust_string_lossy(scope);
+    let message = match serialize_message(scope, args.get(1)) {
+        Ok(message) => message,
+        Err(error) => return Ok(Err(error.to_string())),
+    };
+
+    if message.len() > max_message_size {
+        return Ok(Err(format!(
+            "The message is {} bytes, larger than the maximum of {max_message_size} bytes",
+            message.len()
+        )));
+    }
+
+    Ok(Ok((backend, queue, message)))
+}
+
+pub async fn queue_send_binding(id: usize, arg: Arg) -> BindingResult {
+    let result = match arg {
+        Ok((backend, queue, message)) => match backend.send(&queue, message).await {
+            Ok(()) => PromiseResult::Undefined,
+            Err(error) => PromiseResult::Exception(error.to_string()),
+        },
+        Err(error) => PromiseResult::Exception(error),
+    };
+
+    BindingResult { id, result }
+}
@@ -0,0 +1,124 @@
+use lagon_runtime_v8_utils::v8_string;
+
+use super::dom_exception;
+
+struct Serializer;
+
+impl v8::ValueSerializerImpl for Serializer {
+    fn throw_data_clone_error<'s>(
+        &mut self,
+        scope: &mut v8::HandleScope<'s>,
+        message: v8::Local<'s, v8::String>,
+    ) {
+        let exception = dom_exception(scope, message, "DataCloneError");
+        scope.throw_exception(exception);
+    }
+}
+
+struct Deserializer;
+
+impl v8::ValueDeserializerImpl for Deserializer {}
+
+fn throw_data_clone_error(scope: &mut v8::HandleScope, message: &str) {
+    let message = v8_string(scope, message);
+    let exception = dom_exception(scope, message, "DataCloneError");
+    scope.throw_exception(exception);
+}
+
+fn extract_transfer<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    transfer: v8::Local<'s, v8::Value>,
+) -> Result<Vec<v8::Local<'s, v8::ArrayBuffer>>, String> {
+    let mut buffers: Vec<v8::Local<v8::ArrayBuffer>> = Vec::new();
+
+    let transfer = match v8::Local::<v8::Array>::try_from(transfer) {
+        Ok(transfer) => transfer,
+        Err(_) => return Ok(buffers),
+    };
+
+    for index in 0..transfer.length() {
+        let value = transfer
+            .get_index(scope, index)
+            .ok_or_else(|| format!("Value at index {index} is not transferable"))?;
+
+        // Only ArrayBuffers can be transferred, MessagePorts and streams aren't supported
+        let buffer = v8::Local::<v8::ArrayBuffer>::try_from(value)
+            .map_err(|_| format!("Value at index {index} is not transferable"))?;
+
+        if buffers
+            .iter()
+            .any(|other| other.strict_equals(buffer.into()))
+        {
+            return Err(format!("ArrayBuffer at index {index} is a duplicate"));
+        }
+
+        if buffer.was_detached() || !buffer.is_detachable() {
+            return Err(format!(
+                "ArrayBuffer at index {index} is detached or not detachable"
+            ));
+        }
+
+        buffers.push(buffer);
+    }
+
+    Ok(buffers)
+}
+
+// Returns None when an exception has been thrown, either by the serializer
+// (e.g for functions and symbols) or by the cloned value itself (e.g a getter)
+fn structured_clone<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    value: v8::Local<'s, v8::Value>,
+    transfer: v8::Local<'s, v8::Value>,
+) -> Option<v8::Local<'s, v8::Value>> {
+    let buffers = match extract_transfer(scope, transfer) {
+        Ok(buffers) => buffers,
+        Err(error) => {
+            throw_data_clone_error(scope, &error);
+            return None;
+        }
+    };
+
+    let context = scope.get_current_context();
+    let serializer = v8::ValueSerializer::new(scope, Box::new(Serializer));
+    serializer.write_header();
+
+    for (id, buffer) in buffers.iter().enumerate() {
+        serializer.transfer_array_buffer(id as u32, *buffer);
+    }
+
+    serializer.write_value(context, value)?;
+    let data = serializer.release();
+
+    // Transferred buffers are only detached once the value was successfully
+    // serialized, and their memory is then moved to the clone without copying
+    let backing_stores = buffers
+        .iter()
+        .map(|buffer| {
+            let backing_store = buffer.get_backing_store();
+            buffer.detach(None);
+
+            backing_store
+        })
+        .collect::<Vec<_>>();
+
+    let deserializer = v8::ValueDeserializer::new(scope, Box::new(Deserializer), &data);
+    deserializer.read_header(context)?;
+
+    for (id, backing_store) in backing_stores.iter().enumerate() {
+        let buffer = v8::ArrayBuffer::with_backing_store(scope, backing_store);
+        deserializer.transfer_array_buffer(id as u32, buffer);
+    }
+
+    deserializer.read_value(context)
+}
+
+pub fn structured_clone_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut retval: v8::ReturnValue,
+) {
+    if let Some(value) = structured_clone(scope, args.get(0), args.get(1)) {
+        retval.set(value);
+    }
+}
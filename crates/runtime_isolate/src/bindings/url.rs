@@ -0,0 +1,97 @@
+use lagon_runtime_v8_utils::{v8_exception, v8_string};
+use url::{quirks, Url};
+
+// The components of a parsed URL, serialized like the getters of the URL class
+fn v8_url<'a>(scope: &mut v8::HandleScope<'a>, url: &Url) -> v8::Local<'a, v8::Object> {
+    let components = [
+        ("href", quirks::href(url).to_string()),
+        ("origin", quirks::origin(url)),
+        ("protocol", quirks::protocol(url).to_string()),
+        ("username", quirks::username(url).to_string()),
+        ("password", quirks::password(url).to_string()),
+        ("host", quirks::host(url).to_string()),
+        ("hostname", quirks::hostname(url).to_string()),
+        ("port", quirks::port(url).to_string()),
+        ("pathname", quirks::pathname(url).to_string()),
+        ("search", quirks::search(url).to_string()),
+        ("hash", quirks::hash(url).to_string()),
+    ];
+
+    let object = v8::Object::new(scope);
+
+    for (key, value) in components {
+        let key = v8_string(scope, key);
+        let value = v8_string(scope, &value);
+        object.set(scope, key.into(), value.into());
+    }
+
+    object
+}
+
+fn throw_invalid_url(scope: &mut v8::HandleScope) {
+    let exception = v8_exception(scope, "Invalid URL");
+    scope.throw_exception(exception);
+}
+
+pub fn parse_url_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut retval: v8::ReturnValue,
+) {
+    let input = args.get(0).to_rust_string_lossy(scope);
+    let base = args.get(1);
+
+    let base = match base.is_null_or_undefined() {
+        true => None,
+        false => match Url::parse(&base.to_rust_string_lossy(scope)) {
+            Ok(base) => Some(base),
+            Err(_) => return throw_invalid_url(scope),
+        },
+    };
+
+    match Url::options().base_url(base.as_ref()).parse(&input) {
+        Ok(url) => retval.set(v8_url(scope, &url).into()),
+        Err(_) => throw_invalid_url(scope),
+    }
+}
+
+// Apply a setter of the URL class, and return the updated components. Like the spec,
+// invalid values are ignored, except for the href which throws.
+pub fn set_url_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut retval: v8::ReturnValue,
+) {
+    let href = args.get(0).to_rust_string_lossy(scope);
+    let component = args.get(1).to_rust_string_lossy(scope);
+    let value = args.get(2).to_rust_string_lossy(scope);
+
+    let mut url = match Url::parse(&href) {
+        Ok(url) => url,
+        Err(_) => return throw_invalid_url(scope),
+    };
+
+    match component.as_str() {
+        "href" => {
+            if quirks::set_href(&mut url, &value).is_err() {
+                return throw_invalid_url(scope);
+            }
+        }
+        "protocol" => quirks::set_protocol(&mut url, &value).unwrap_or(()),
+        "username" => quirks::set_username(&mut url, &value).unwrap_or(()),
+        "password" => quirks::set_password(&mut url, &value).unwrap_or(()),
+        "host" => quirks::set_host(&mut url, &value).unwrap_or(()),
+        "hostname" => quirks::set_hostname(&mut url, &value).unwrap_or(()),
+        "port" => quirks::set_port(&mut url, &value).unwrap_or(()),
+        "pathname" => quirks::set_pathname(&mut url, &value),
+        "search" => quirks::set_search(&mut url, &value),
+        "hash" => quirks::set_hash(&mut url, &value),
+        _ => {
+            let exception = v8_exception(scope, &format!("Unknown URL component {component}"));
+            scope.throw_exception(exception);
+            return;
+        }
+    }
+
+    retval.set(v8_url(scope, &url).into());
+}
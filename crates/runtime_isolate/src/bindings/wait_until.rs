@@ -0,0 +1,24 @@
+use crate::Isolate;
+
+use super::request_id;
+
+// Keeps the current request alive until the given promise settles, even
+// once its response has been sent
+pub fn wait_until_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _retval: v8::ReturnValue,
+) {
+    let promise = match v8::Local::<v8::Promise>::try_from(args.get(0)) {
+        Ok(promise) => v8::Global::new(scope, promise),
+        Err(_) => return,
+    };
+
+    let id = request_id(scope);
+    let isolate_state = Isolate::state(scope);
+    let mut state = isolate_state.borrow_mut();
+
+    if let Some(handler_result) = state.handler_results.get_mut(&id) {
+        handler_result.wait_until.push(promise);
+    }
+}
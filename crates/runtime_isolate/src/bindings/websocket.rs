@@ -0,0 +1,516 @@
+use anyhow::{anyhow, Result};
+use futures::{
+    future::{AbortHandle, AbortRegistration, Abortable},
+    SinkExt, StreamExt,
+};
+use hyper::{client::HttpConnector, Uri};
+use lagon_runtime_http::{Response, WebSocketMessage, WebSocketUpgrade};
+use lagon_runtime_v8_utils::{extract_v8_string, v8_exception, v8_string};
+use std::sync::Arc;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{
+    client_async_tls_with_config,
+    tungstenite::{
+        client::IntoClientRequest,
+        handshake::client::{Request, Response as HandshakeResponse},
+        protocol::{frame::coding::CloseCode, CloseFrame},
+        Message,
+    },
+    Connector, MaybeTlsStream, WebSocketStream,
+};
+use url::Url;
+
+use crate::{
+    bindings::{
+        fetch::{find_violation, on_violation, OnViolation},
+        PromiseResult,
+    },
+    network_policy::{NetworkPolicy, PolicyResolver},
+    proxy::{Proxy, ProxyConnector},
+    tls::get_tls_config,
+    Isolate, RequestContext,
+};
+
+use super::{request_id, BindingResult};
+
+// https://www.rfc-editor.org/rfc/rfc6455#section-7.4.1
+const NO_STATUS_RECEIVED: u16 = 1005;
+const ABNORMAL_CLOSURE: u16 = 1006;
+
+// A WebSocket of the current request, either opened with `new WebSocket()` or
+// upgraded from the request itself. Dropping it force-closes the connection
+#[derive(Debug)]
+pub struct WebSocketHandle {
+    messages: flume::Sender<WebSocketMessage>,
+    events: flume::Receiver<WebSocketMessage>,
+    // Only set for client connections, which run in their own task
+    abort_handle: Option<AbortHandle>,
+    // The other side of the channels of an upgraded WebSocket, until its response is sent
+    upgrade: Option<(
+        flume::Receiver<WebSocketMessage>,
+        flume::Sender<WebSocketMessage>,
+    )>,
+    // Set once the upgrade response is sent, which keeps the request alive until closed
+    pub upgraded: bool,
+}
+
+impl Drop for WebSocketHandle {
+    fn drop(&mut self) {
+        if let Some(abort_handle) = &self.abort_handle {
+            abort_handle.abort();
+        }
+    }
+}
+
+impl RequestContext {
+    // Takes the channels of an upgraded WebSocket, to be sent with its response
+    pub fn accept_websocket(&mut self, id: u32, response: Response) -> Option<WebSocketUpgrade> {
+        let websocket = self.websockets.get_mut(&id)?;
+        let (outgoing, incoming) = websocket.upgrade.take()?;
+
+        websocket.upgraded = true;
+
+        Some(WebSocketUpgrade {
+            response,
+            outgoing,
+            incoming,
+        })
+    }
+}
+
+fn into_message(message: WebSocketMessage) -> Message {
+    match message {
+        WebSocketMessage::Text(text) => Message::Text(text),
+        WebSocketMessage::Binary(bytes) => Message::Binary(bytes),
+        WebSocketMessage::Close(frame) => Message::Close(frame.map(|(code, reason)| CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.into(),
+        })),
+    }
+}
+
+// The URL and protocols to connect to, the messages to send, where to send
+// the received messages, how to force-close the connection, the network
+// policy the URL is checked against, who to notify of its violations and
+// the proxy to connect through
+type Arg = (
+    String,
+    Vec<String>,
+    flume::Receiver<WebSocketMessage>,
+    flume::Sender<WebSocketMessage>,
+    AbortRegistration,
+    Arc<NetworkPolicy>,
+    Option<OnViolation>,
+    Arc<Proxy>,
+);
+
+// Like fetch(), the host is checked by the network policy and resolved to the IPs
+// it allows, and the connection goes through the proxy. wss:// URLs use the same
+// TLS config, e.g with the extra root certificates
+async fn connect(
+    request: Request,
+    network_policy: Arc<NetworkPolicy>,
+    proxy: Arc<Proxy>,
+) -> Result<(
+    WebSocketStream<MaybeTlsStream<TcpStream>>,
+    HandshakeResponse,
+)> {
+    let url = Url::parse(&request.uri().to_string())?;
+    network_policy.check_url(&url)?;
+
+    let host = request
+        .uri()
+        .host()
+        .ok_or_else(|| anyhow!("The URL has no host"))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("The URL has no port"))?;
+    // The proxy is picked like for fetch(), e.g HTTPS_PROXY for wss:// URLs
+    let uri = Uri::builder()
+        .scheme(match url.scheme() {
+            "wss" => "https",
+            _ => "http",
+        })
+        .authority(format!("{host}:{port}"))
+        .path_and_query("/")
+        .build()?;
+
+    let resolver = PolicyResolver::new(network_policy);
+    let mut direct = HttpConnector::new_with_resolver(resolver.clone());
+    direct.enforce_http(false);
+    let stream = ProxyConnector::new(direct, resolver, proxy)
+        .connect_tunneled(uri)
+        .await
+        .map_err(|error| anyhow!(error))?;
+
+    let tls_connector = Connector::Rustls(Arc::new(get_tls_config()));
+    let connection =
+        client_async_tls_with_config(request, stream, None, Some(tls_connector)).await?;
+
+    Ok(connection)
+}
+
+pub fn websocket_connect_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<Arg> {
+    let id = request_id(scope);
+
+    let options = match args.get(0).to_object(scope) {
+        Some(options) => options,
+        None => return Err(anyhow!("Invalid WebSocket options")),
+    };
+
+    let url_key = v8_string(scope, "u");
+    let url = extract_v8_string(
+        options
+            .get(scope, url_key.into())
+            .ok_or_else(|| anyhow!("Invalid WebSocket URL"))?,
+        scope,
+    )?;
+
+    let protocols_key = v8_string(scope, "p");
+    let protocols = match options
+        .get(scope, protocols_key.into())
+        .and_then(|protocols| v8::Local::<v8::Array>::try_from(protocols).ok())
+    {
+        Some(protocols) => (0..protocols.length())
+            .filter_map(|index| protocols.get_index(scope, index))
+            .map(|protocol| protocol.to_rust_string_lossy(scope))
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let websocket_key = v8_string(scope, "i");
+    let websocket_id = options
+        .get(scope, websocket_key.into())
+        .and_then(|value| value.uint32_value(scope))
+        .ok_or_else(|| anyhow!("Invalid WebSocket options"))?;
+
+    let (messages_sender, messages_receiver) = flume::unbounded();
+    // Only a single message is buffered, so messages aren't read from the
+    // connection faster than the isolate handles them
+    let (events_sender, events_receiver) = flume::bounded(1);
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+
+    let isolate_state = Isolate::state(scope);
+    let mut state = isolate_state.borrow_mut();
+    let network_policy = Arc::clone(&state.network_policy);
+    let on_violation = on_violation(&state);
+    let proxy = Arc::clone(&state.fetch_proxy);
+
+    match state.handler_results.get_mut(&id) {
+        Some(handler_result) => {
+            handler_result.context.websockets.insert(
+                websocket_id,
+                WebSocketHandle {
+                    messages: messages_sender,
+                    events: events_receiver,
+                    abort_handle: Some(abort_handle),
+                    upgrade: None,
+                    upgraded: false,
+                },
+            );
+        }
+        None => {
+            return Err(anyhow!(
+                "WebSocket connections can only be opened while handling a request"
+            ))
+        }
+    }
+
+    Ok((
+        url,
+        protocols,
+        messages_receiver,
+        events_sender,
+        abort_registration,
+        network_policy,
+        on_violation,
+        proxy,
+    ))
+}
+
+// Resolves with the protocol selected by the server once connected. The connection
+// is then handled in a separate task, which sends the received messages to the
+// isolate and the messages sent by the isolate to the server
+pub async fn websocket_connect_binding(id: usize, arg: Arg) -> BindingResult {
+    let (url, protocols, messages, events, abort_registration, network_policy, on_violation, proxy) =
+        arg;
+    let (connected_sender, connected_receiver) = flume::bounded(1);
+
+    tokio::spawn(Abortable::new(
+        async move {
+            let mut request = match url.into_client_request() {
+                Ok(request) => request,
+                Err(error) => {
+                    connected_sender
+                        .send(Err((error.to_string(), None)))
+                        .unwrap_or(());
+                    return;
+                }
+            };
+
+            if !protocols.is_empty() {
+                match protocols.join(", ").parse() {
+                    Ok(protocols) => {
+                        request
+                            .headers_mut()
+                            .insert("sec-websocket-protocol", protocols);
+                    }
+                    Err(_) => {
+                        connected_sender
+                            .send(Err(("Invalid WebSocket protocols".into(), None)))
+                            .unwrap_or(());
+                        return;
+                    }
+                }
+            }
+
+            let (stream, response) = match connect(request, network_policy, proxy).await {
+                Ok(connection) => connection,
+                Err(error) => {
+                    let violation = find_violation(&error);
+                    let error = match &violation {
+                        Some(violation) => violation.to_string(),
+                        None => error.to_string(),
+                    };
+
+                    connected_sender.send(Err((error, violation))).unwrap_or(());
+                    return;
+                }
+            };
+
+            let protocol = response
+                .headers()
+                .get("sec-websocket-protocol")
+                .and_then(|protocol| protocol.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+
+            connected_sender.send(Ok(protocol)).unwrap_or(());
+
+            let (mut sink, mut stream) = stream.split();
+            let mut close_frame = None;
+
+            // Dropping the events sender (e.g on errors) closes the WebSocket with a 1006 code
+            loop {
+                tokio::select! {
+                    message = messages.recv_async() => {
+                        let message = match message {
+                            Ok(message) => message,
+                            Err(_) => break,
+                        };
+
+                        if sink.send(into_message(message)).await.is_err() {
+                            break;
+                        }
+                    }
+                    message = stream.next() => {
+                        let event = match message {
+                            Some(Ok(Message::Text(text))) => WebSocketMessage::Text(text),
+                            Some(Ok(Message::Binary(bytes))) => WebSocketMessage::Binary(bytes),
+                            // The closing handshake is finished once the server closes the connection
+                            Some(Ok(Message::Close(frame))) => {
+                                close_frame = Some(frame);
+                                continue;
+                            }
+                            // Pings are answered automatically
+                            Some(Ok(_)) => continue,
+                            Some(Err(_)) | None => {
+                                if let Some(frame) = close_frame {
+                                    let frame = frame.map(|frame| {
+                                        (u16::from(frame.code), frame.reason.to_string())
+                                    });
+
+                                    events
+                                        .send_async(WebSocketMessage::Close(frame))
+                                        .await
+                                        .unwrap_or(());
+                                }
+
+                                break;
+                            }
+                        };
+
+                        if events.send_async(event).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        },
+        abort_registration,
+    ));
+
+    let result = match connected_receiver.recv_async().await {
+        Ok(Ok(protocol)) => PromiseResult::String(protocol),
+        Ok(Err((error, violation))) => {
+            if let (Some(on_violation), Some(violation)) = (on_violation, violation) {
+                on_violation(&violation);
+            }
+
+            PromiseResult::Error(error)
+        }
+        // The task is dropped before connecting when the request is done
+        Err(_) => PromiseResult::Error("The WebSocket connection was closed".into()),
+    };
+
+    BindingResult { id, result }
+}
+
+// Creates the channels of a WebSocket upgraded from the current request, which
+// are sent to the client side once the handler returned the upgrade response
+pub fn websocket_upgrade_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _retval: v8::ReturnValue,
+) {
+    let id = request_id(scope);
+    let websocket_id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    let (messages_sender, messages_receiver) = flume::unbounded();
+    let (events_sender, events_receiver) = flume::bounded(1);
+
+    let isolate_state = Isolate::state(scope);
+    let mut state = isolate_state.borrow_mut();
+
+    let is_handling_request = match state.handler_results.get_mut(&id) {
+        Some(handler_result) => {
+            handler_result.context.websockets.insert(
+                websocket_id,
+                WebSocketHandle {
+                    messages: messages_sender,
+                    events: events_receiver,
+                    abort_handle: None,
+                    upgrade: Some((messages_receiver, events_sender)),
+                    upgraded: false,
+                },
+            );
+
+            true
+        }
+        None => false,
+    };
+
+    drop(state);
+
+    if !is_handling_request {
+        let exception = v8_exception(
+            scope,
+            "WebSockets can only be upgraded while handling a request",
+        );
+        scope.throw_exception(exception);
+    }
+}
+
+pub fn websocket_receive_init(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+) -> Result<flume::Receiver<WebSocketMessage>> {
+    let id = request_id(scope);
+    let websocket_id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    Isolate::state(scope)
+        .borrow()
+        .handler_results
+        .get(&id)
+        .and_then(|handler_result| handler_result.context.websockets.get(&websocket_id))
+        .map(|websocket| websocket.events.clone())
+        .ok_or_else(|| anyhow!("The WebSocket connection was closed"))
+}
+
+// Resolves with the next message (a string or a Uint8Array), or with the close code
+// and reason once the connection is closed
+pub async fn websocket_receive_binding(
+    id: usize,
+    arg: flume::Receiver<WebSocketMessage>,
+) -> BindingResult {
+    let result = match arg.recv_async().await {
+        Ok(WebSocketMessage::Text(text)) => PromiseResult::String(text),
+        Ok(WebSocketMessage::Binary(bytes)) => PromiseResult::ArrayBuffer(bytes),
+        Ok(WebSocketMessage::Close(Some((code, reason)))) => PromiseResult::Close(code, reason),
+        Ok(WebSocketMessage::Close(None)) => {
+            PromiseResult::Close(NO_STATUS_RECEIVED, String::new())
+        }
+        // The connection was force-closed
+        Err(_) => PromiseResult::Close(ABNORMAL_CLOSURE, String::new()),
+    };
+
+    BindingResult { id, result }
+}
+
+fn send_message(scope: &mut v8::HandleScope, websocket_id: u32, message: WebSocketMessage) {
+    let id = request_id(scope);
+
+    if let Some(websocket) = Isolate::state(scope)
+        .borrow()
+        .handler_results
+        .get(&id)
+        .and_then(|handler_result| handler_result.context.websockets.get(&websocket_id))
+    {
+        websocket.messages.send(message).unwrap_or(());
+    }
+}
+
+// Sends a text frame for strings, and a binary frame for Uint8Arrays
+pub fn websocket_send_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _retval: v8::ReturnValue,
+) {
+    let websocket_id = args.get(0).uint32_value(scope).unwrap_or(0);
+    let data = args.get(1);
+
+    let message = if data.is_string() {
+        WebSocketMessage::Text(data.to_rust_string_lossy(scope))
+    } else if let Ok(data) = v8::Local::<v8::Uint8Array>::try_from(data) {
+        let mut bytes = vec![0; data.byte_length()];
+        data.copy_contents(&mut bytes);
+
+        WebSocketMessage::Binary(bytes)
+    } else {
+        return;
+    };
+
+    send_message(scope, websocket_id, message);
+}
+
+// Starts the closing handshake, the close event is then received
+// once the other side closed the connection
+pub fn websocket_close_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _retval: v8::ReturnValue,
+) {
+    let websocket_id = args.get(0).uint32_value(scope).unwrap_or(0);
+    let code = args.get(1);
+
+    let frame = match code.is_undefined() {
+        true => None,
+        false => Some((
+            code.uint32_value(scope).unwrap_or(1000) as u16,
+            args.get(2).to_rust_string_lossy(scope),
+        )),
+    };
+
+    send_message(scope, websocket_id, WebSocketMessage::Close(frame));
+}
+
+// Force-closes the connection, e.g when closing before it's open
+pub fn websocket_abort_binding(
+    scope: &mut v8::HandleScope,
+    args: v8::FunctionCallbackArguments,
+    mut _retval: v8::ReturnValue,
+) {
+    let id = request_id(scope);
+    let websocket_id = args.get(0).uint32_value(scope).unwrap_or(0);
+
+    if let Some(handler_result) = Isolate::state(scope)
+        .borrow_mut()
+        .handler_results
+        .get_mut(&id)
+    {
+        handler_result.context.websockets.remove(&websocket_id);
+    }
+}
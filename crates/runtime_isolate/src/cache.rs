@@ -0,0 +1,268 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use lagon_runtime_http::{Request, Response};
+use lazy_static::lazy_static;
+use linked_hash_map::LinkedHashMap;
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use crate::http_cache::{header, header_values, list_header, parse_date};
+
+lazy_static! {
+    // Used by the isolates that don't have their own backend
+    static ref CACHE_BACKEND: RwLock<Option<Arc<dyn CacheBackend>>> = RwLock::new(None);
+}
+
+// A response stored with the Cache API
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    // With its whole body
+    pub response: Response,
+    // The values of the request headers listed in the Vary header of the response
+    pub vary: Vec<(String, Option<Vec<String>>)>,
+    // None if the response doesn't expire
+    pub expires_at: Option<SystemTime>,
+}
+
+impl CacheEntry {
+    fn matches(&self, request: &Request) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| header_values(&request.headers, name) == value.as_ref())
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .map_or(false, |expires_at| expires_at <= now)
+    }
+
+    // An estimation of the memory used by this entry, in bytes
+    pub fn size(&self) -> usize {
+        let headers_size = self.response.headers.as_ref().map_or(0, |headers| {
+            headers
+                .iter()
+                .map(|(key, values)| key.len() + values.iter().map(String::len).sum::<usize>())
+                .sum()
+        });
+        let vary_size = self
+            .vary
+            .iter()
+            .map(|(name, values)| {
+                name.len()
+                    + values
+                        .as_ref()
+                        .map_or(0, |values| values.iter().map(String::len).sum())
+            })
+            .sum::<usize>();
+
+        headers_size + vary_size + self.response.body.len()
+    }
+}
+
+// Where the responses of the Cache API are stored, e.g in memory or in a store shared
+// by multiple processes. Each URL of a cache has a response per variant of its Vary
+// header. Entries can be evicted at any time, and expired ones are ignored
+#[async_trait]
+pub trait CacheBackend: Debug + Send + Sync {
+    // The entries stored for this URL of the given cache, if any
+    async fn get(&self, cache: &str, url: &str) -> Result<Vec<CacheEntry>>;
+
+    // Replace the entries stored for this URL of the given cache,
+    // or remove them when `entries` is empty
+    async fn set(&self, cache: &str, url: &str, entries: Vec<CacheEntry>) -> Result<()>;
+}
+
+#[derive(Debug)]
+struct MemoryCache {
+    entries: LinkedHashMap<(String, String), (Vec<CacheEntry>, usize)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl MemoryCache {
+    fn remove(&mut self, key: &(String, String)) {
+        if let Some((_, size)) = self.entries.remove(key) {
+            self.size -= size;
+        }
+    }
+}
+
+// Store the entries in memory, evicting the least recently used
+// URLs once the cache is larger than `max_size` bytes
+#[derive(Debug)]
+pub struct MemoryCacheBackend {
+    cache: Mutex<MemoryCache>,
+}
+
+impl MemoryCacheBackend {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            cache: Mutex::new(MemoryCache {
+                entries: LinkedHashMap::new(),
+                size: 0,
+                max_size,
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCacheBackend {
+    async fn get(&self, cache: &str, url: &str) -> Result<Vec<CacheEntry>> {
+        let mut memory_cache = self.cache.lock().unwrap();
+        let key = (cache.to_string(), url.to_string());
+
+        Ok(memory_cache
+            .entries
+            .get_refresh(&key)
+            .map(|(entries, _)| entries.clone())
+            .unwrap_or_default())
+    }
+
+    async fn set(&self, cache: &str, url: &str, entries: Vec<CacheEntry>) -> Result<()> {
+        let mut memory_cache = self.cache.lock().unwrap();
+        let key = (cache.to_string(), url.to_string());
+
+        memory_cache.remove(&key);
+
+        let size = cache.len() + url.len() + entries.iter().map(CacheEntry::size).sum::<usize>();
+
+        if entries.is_empty() || size > memory_cache.max_size {
+            return Ok(());
+        }
+
+        while memory_cache.size + size > memory_cache.max_size {
+            match memory_cache.entries.pop_front() {
+                Some((_, (_, evicted_size))) => memory_cache.size -= evicted_size,
+                None => break,
+            }
+        }
+
+        memory_cache.size += size;
+        memory_cache.entries.insert(key, (entries, size));
+
+        Ok(())
+    }
+}
+
+// Use this backend for the isolates that don't have their own. Isolates
+// created before calling it keep using the previous one
+pub fn set_cache_backend(backend: Arc<dyn CacheBackend>) {
+    *CACHE_BACKEND.write().unwrap() = Some(backend);
+}
+
+pub fn get_cache_backend() -> Option<Arc<dyn CacheBackend>> {
+    CACHE_BACKEND.read().unwrap().clone()
+}
+
+// Fragments are ignored when matching requests
+// https://w3c.github.io/ServiceWorker/#request-matches-cached-item-algorithm
+fn cache_url(request: &Request) -> &str {
+    request.url.split('#').next().unwrap_or_default()
+}
+
+// Like other shared caches, s-maxage is preferred over max-age. Responses
+// without explicit expiration are kept until they are evicted
+// https://www.rfc-editor.org/rfc/rfc9111#section-4.2.1
+fn expires_at(response: &Response, now: SystemTime) -> Option<SystemTime> {
+    let cache_control = list_header(&response.headers, "cache-control");
+    let directive = |name: &str| {
+        cache_control.iter().find_map(|directive| {
+            directive
+                .strip_prefix(name)
+                .and_then(|value| value.trim_matches('"').parse().ok())
+        })
+    };
+
+    let lifetime = match directive("s-maxage=").or_else(|| directive("max-age=")) {
+        Some(max_age) => Duration::from_secs(max_age),
+        None => {
+            let expires = parse_date(&response.headers, "expires")?;
+            let date = parse_date(&response.headers, "date").unwrap_or(now);
+
+            expires.duration_since(date).unwrap_or_default()
+        }
+    };
+
+    let age = header(&response.headers, "age")
+        .and_then(|age| age.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_default();
+
+    Some(now + lifetime.saturating_sub(age))
+}
+
+// The request must be a GET request, which is checked before
+pub async fn put(
+    backend: &dyn CacheBackend,
+    cache: &str,
+    request: &Request,
+    response: Response,
+) -> Result<()> {
+    let now = SystemTime::now();
+    let no_store = list_header(&response.headers, "cache-control")
+        .iter()
+        .any(|directive| directive == "no-store");
+    let expires_at = expires_at(&response, now);
+
+    if no_store || expires_at.map_or(false, |expires_at| expires_at <= now) {
+        return Ok(());
+    }
+
+    let vary = list_header(&response.headers, "vary")
+        .into_iter()
+        .map(|name| {
+            let value = header_values(&request.headers, &name).cloned();
+            (name, value)
+        })
+        .collect();
+
+    let url = cache_url(request);
+    let mut entries = backend.get(cache, url).await?;
+
+    // The new response replaces the one of the same variant
+    entries.retain(|entry| !entry.matches(request) && !entry.is_expired(now));
+    entries.push(CacheEntry {
+        response,
+        vary,
+        expires_at,
+    });
+
+    backend.set(cache, url, entries).await
+}
+
+pub async fn match_request(
+    backend: &dyn CacheBackend,
+    cache: &str,
+    request: &Request,
+) -> Result<Option<Response>> {
+    let now = SystemTime::now();
+    let entries = backend.get(cache, cache_url(request)).await?;
+
+    Ok(entries
+        .into_iter()
+        .find(|entry| !entry.is_expired(now) && entry.matches(request))
+        .map(|entry| entry.response))
+}
+
+// Whether a response was deleted
+pub async fn delete(backend: &dyn CacheBackend, cache: &str, request: &Request) -> Result<bool> {
+    let now = SystemTime::now();
+    let url = cache_url(request);
+    let mut entries = backend.get(cache, url).await?;
+    let count = entries.len();
+    let deleted = entries
+        .iter()
+        .any(|entry| !entry.is_expired(now) && entry.matches(request));
+
+    entries.retain(|entry| !entry.matches(request) && !entry.is_expired(now));
+
+    if entries.len() != count {
+        backend.set(cache, url, entries).await?;
+    }
+
+    Ok(deleted)
+}
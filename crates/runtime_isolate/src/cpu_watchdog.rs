@@ -0,0 +1,81 @@
+use lagon_runtime_http::RunResult;
+use std::{
+    sync::{Arc, Condvar, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Default)]
+struct WatchdogState {
+    // Set while JS is running, to when the requests run out of CPU time
+    deadline: Option<Instant>,
+    stopped: bool,
+}
+
+// Terminates the isolate when JS runs past the CPU time left to the requests.
+// The time is measured around each entry into JS, so time spent waiting (e.g
+// for fetch() calls or timers) isn't counted
+#[derive(Debug)]
+pub(crate) struct CpuWatchdog {
+    state: Arc<(Mutex<WatchdogState>, Condvar)>,
+}
+
+impl CpuWatchdog {
+    pub fn new(
+        thread_safe_handle: v8::IsolateHandle,
+        termination_result: Arc<RwLock<Option<RunResult>>>,
+    ) -> Self {
+        let state = Arc::new((Mutex::new(WatchdogState::default()), Condvar::new()));
+        let watchdog_state = Arc::clone(&state);
+
+        std::thread::spawn(move || {
+            let (lock, condvar) = &*watchdog_state;
+            let mut state = lock.lock().unwrap();
+
+            while !state.stopped {
+                match state.deadline {
+                    Some(deadline) if Instant::now() >= deadline => {
+                        termination_result
+                            .write()
+                            .unwrap()
+                            .replace(RunResult::CpuTimeLimit);
+
+                        if !thread_safe_handle.is_execution_terminating() {
+                            thread_safe_handle.terminate_execution();
+                        }
+
+                        break;
+                    }
+                    Some(deadline) => {
+                        let timeout = deadline.saturating_duration_since(Instant::now());
+                        state = condvar.wait_timeout(state, timeout).unwrap().0;
+                    }
+                    None => state = condvar.wait(state).unwrap(),
+                }
+            }
+        });
+
+        CpuWatchdog { state }
+    }
+
+    // Called right before entering JS
+    pub fn start(&self, remaining: Duration) {
+        let (lock, condvar) = &*self.state;
+        lock.lock().unwrap().deadline = Some(Instant::now() + remaining);
+        condvar.notify_one();
+    }
+
+    // Called once JS returned
+    pub fn stop(&self) {
+        let (lock, condvar) = &*self.state;
+        lock.lock().unwrap().deadline = None;
+        condvar.notify_one();
+    }
+}
+
+impl Drop for CpuWatchdog {
+    fn drop(&mut self) {
+        let (lock, condvar) = &*self.state;
+        lock.lock().unwrap().stopped = true;
+        condvar.notify_one();
+    }
+}
@@ -0,0 +1,50 @@
+use lagon_runtime_http::RunResult;
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    rc::Rc,
+    time::Duration,
+};
+
+use crate::options::IsolateOptions;
+
+// What an isolate does during its lifetime, passed to the hooks registered
+// with `IsolateOptions::hook()` to observe it from the embedder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IsolateHookEvent<'a> {
+    // At the end of `Isolate::new`, before the code is evaluated
+    Created,
+    EvaluationStarted,
+    // With the compilation or evaluation error, if any
+    EvaluationFinished {
+        duration: Duration,
+        error: Option<&'a str>,
+    },
+    // Scheduled events are handled like requests, and have their own id
+    RequestStarted {
+        id: u32,
+    },
+    // With the last result sent for the request, e.g `Stream(Done)` for a streamed
+    // response. The `waitUntil()` promises can still run in the background
+    RequestFinished {
+        id: u32,
+        duration: Duration,
+        cpu_time: Duration,
+        result: &'a RunResult,
+    },
+    // Once the event loop stopped, with the result that terminated the isolate
+    // (e.g a timeout or the memory limit), or None if all the senders were dropped
+    Terminated {
+        result: Option<&'a RunResult>,
+    },
+}
+
+// Hooks run on the thread of the isolate, which they block until they return. A
+// panicking hook is ignored, so it can't stop the isolate nor the other hooks
+pub(crate) fn emit(options: &IsolateOptions, event: IsolateHookEvent) {
+    for hook in &options.hooks {
+        catch_unwind(AssertUnwindSafe(|| {
+            hook(Rc::clone(&options.metadata), &event)
+        }))
+        .unwrap_or(());
+    }
+}
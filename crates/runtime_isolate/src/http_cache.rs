@@ -0,0 +1,306 @@
+use lagon_runtime_http::{Method, Request, Response};
+use linked_hash_map::LinkedHashMap;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+pub type Headers = Option<HashMap<String, Vec<String>>>;
+
+// Requests that are already conditional are sent as-is
+// https://fetch.spec.whatwg.org/#concept-request-cache-mode
+const CONDITIONAL_HEADERS: [&str; 5] = [
+    "if-modified-since",
+    "if-none-match",
+    "if-unmodified-since",
+    "if-match",
+    "if-range",
+];
+// The headers of a 304 response that don't replace the stored ones
+// https://www.rfc-editor.org/rfc/rfc9111#section-3.2
+const EXCLUDED_UPDATE_HEADERS: [&str; 4] = [
+    "content-length",
+    "content-encoding",
+    "transfer-encoding",
+    "content-range",
+];
+
+// Shared with the tasks making the requests, but never with other isolates
+pub type SharedHttpCache = Arc<Mutex<HttpCache>>;
+
+// https://fetch.spec.whatwg.org/#concept-request-cache-mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    Default,
+    NoStore,
+    Reload,
+    NoCache,
+    ForceCache,
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    // With its whole body
+    pub response: Response,
+    pub url: String,
+    pub redirected: bool,
+    // The values of the request headers listed in the Vary header
+    vary: Vec<(String, Option<Vec<String>>)>,
+    expires_at: Instant,
+    size: usize,
+}
+
+impl CachedResponse {
+    // The headers to send to check if the response was modified
+    pub fn validators(&self) -> Vec<(&'static str, String)> {
+        let mut validators = Vec::new();
+
+        if let Some(etag) = header(&self.response.headers, "etag") {
+            validators.push(("if-none-match", etag.to_string()));
+        }
+
+        if let Some(last_modified) = header(&self.response.headers, "last-modified") {
+            validators.push(("if-modified-since", last_modified.to_string()));
+        }
+
+        validators
+    }
+
+    fn matches(&self, request: &Request) -> bool {
+        self.vary
+            .iter()
+            .all(|(name, value)| header_values(&request.headers, name) == value.as_ref())
+    }
+}
+
+pub enum CacheLookup {
+    Miss,
+    Hit(CachedResponse),
+    // Send a conditional request, and use the cached response if it wasn't modified
+    Revalidate(CachedResponse),
+}
+
+pub fn header_values<'a>(headers: &'a Headers, name: &str) -> Option<&'a Vec<String>> {
+    headers
+        .as_ref()?
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, values)| values)
+}
+
+pub fn header<'a>(headers: &'a Headers, name: &str) -> Option<&'a str> {
+    header_values(headers, name)?.first().map(String::as_str)
+}
+
+pub fn list_header(headers: &Headers, name: &str) -> Vec<String> {
+    header_values(headers, name)
+        .map(|values| {
+            values
+                .iter()
+                .flat_map(|value| value.split(','))
+                .map(|value| value.trim().to_lowercase())
+                .filter(|value| !value.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub fn parse_date(headers: &Headers, name: &str) -> Option<SystemTime> {
+    header(headers, name).and_then(|value| httpdate::parse_http_date(value).ok())
+}
+
+// How long the response can be used without revalidating it. Responses without
+// explicit expiration are always revalidated
+// https://www.rfc-editor.org/rfc/rfc9111#section-4.2.1
+fn freshness_lifetime(headers: &Headers) -> Duration {
+    let cache_control = list_header(headers, "cache-control");
+
+    if cache_control
+        .iter()
+        .any(|directive| directive == "no-cache")
+    {
+        return Duration::ZERO;
+    }
+
+    let max_age = cache_control.iter().find_map(|directive| {
+        directive
+            .strip_prefix("max-age=")
+            .and_then(|value| value.trim_matches('"').parse().ok())
+    });
+
+    if let Some(max_age) = max_age {
+        return Duration::from_secs(max_age);
+    }
+
+    match parse_date(headers, "expires") {
+        Some(expires) => {
+            let date = parse_date(headers, "date").unwrap_or_else(SystemTime::now);
+            expires.duration_since(date).unwrap_or_default()
+        }
+        None => Duration::ZERO,
+    }
+}
+
+fn expires_at(headers: &Headers) -> Instant {
+    let age = header(headers, "age")
+        .and_then(|age| age.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or_default();
+
+    Instant::now() + freshness_lifetime(headers).saturating_sub(age)
+}
+
+fn headers_size(headers: &Headers) -> usize {
+    headers.as_ref().map_or(0, |headers| {
+        headers
+            .iter()
+            .map(|(key, values)| key.len() + values.iter().map(String::len).sum::<usize>())
+            .sum()
+    })
+}
+
+// Bypass the cache for requests that aren't simple GET requests
+pub fn cache_mode(request: &Request, mode: CacheMode) -> CacheMode {
+    let is_conditional = CONDITIONAL_HEADERS
+        .iter()
+        .any(|name| header_values(&request.headers, name).is_some());
+
+    if !matches!(request.method, Method::GET) || is_conditional {
+        CacheMode::NoStore
+    } else {
+        mode
+    }
+}
+
+// A private cache of the responses to GET requests, keyed by URL. The least
+// recently used responses are evicted once it's larger than `max_size` bytes
+#[derive(Debug)]
+pub struct HttpCache {
+    entries: LinkedHashMap<String, CachedResponse>,
+    size: usize,
+    max_size: usize,
+}
+
+impl HttpCache {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            entries: LinkedHashMap::new(),
+            size: 0,
+            max_size,
+        }
+    }
+
+    pub fn lookup(&mut self, request: &Request, mode: CacheMode) -> CacheLookup {
+        let cached = match self.entries.get_refresh(&request.url) {
+            Some(cached) if cached.matches(request) => cached.clone(),
+            _ => return CacheLookup::Miss,
+        };
+
+        match mode {
+            CacheMode::ForceCache => CacheLookup::Hit(cached),
+            CacheMode::Default if cached.expires_at > Instant::now() => CacheLookup::Hit(cached),
+            CacheMode::Default | CacheMode::NoCache if !cached.validators().is_empty() => {
+                CacheLookup::Revalidate(cached)
+            }
+            _ => CacheLookup::Miss,
+        }
+    }
+
+    // Whether this response (without its body yet) can be stored, and fits in the cache
+    // https://www.rfc-editor.org/rfc/rfc9111#section-3
+    pub fn can_store(
+        &self,
+        request: &Request,
+        response: &Response,
+        content_length: Option<usize>,
+    ) -> bool {
+        let no_store = list_header(&request.headers, "cache-control")
+            .into_iter()
+            .chain(list_header(&response.headers, "cache-control"))
+            .any(|directive| directive == "no-store");
+        let vary_all = list_header(&response.headers, "vary")
+            .iter()
+            .any(|name| name == "*");
+        let is_useful = !freshness_lifetime(&response.headers).is_zero()
+            || header(&response.headers, "etag").is_some()
+            || header(&response.headers, "last-modified").is_some();
+
+        response.status == 200
+            && !no_store
+            && !vary_all
+            && is_useful
+            && content_length.map_or(false, |content_length| content_length <= self.max_size)
+    }
+
+    pub fn store(&mut self, request: &Request, response: Response, url: String, redirected: bool) {
+        let vary = list_header(&response.headers, "vary")
+            .into_iter()
+            .map(|name| {
+                let value = header_values(&request.headers, &name).cloned();
+                (name, value)
+            })
+            .collect();
+
+        self.insert(
+            request.url.clone(),
+            CachedResponse {
+                expires_at: expires_at(&response.headers),
+                size: request.url.len() + headers_size(&response.headers) + response.body.len(),
+                response,
+                url,
+                redirected,
+                vary,
+            },
+        );
+    }
+
+    // Refresh the cached response with the headers of a 304 response
+    pub fn update(
+        &mut self,
+        request: &Request,
+        mut cached: CachedResponse,
+        headers: &Headers,
+    ) -> CachedResponse {
+        if let Some(headers) = headers {
+            let stored = cached.response.headers.get_or_insert_with(HashMap::new);
+
+            for (key, values) in headers {
+                if !EXCLUDED_UPDATE_HEADERS.contains(&key.to_lowercase().as_str()) {
+                    stored.insert(key.to_lowercase(), values.clone());
+                }
+            }
+        }
+
+        cached.expires_at = expires_at(&cached.response.headers);
+        cached.size =
+            request.url.len() + headers_size(&cached.response.headers) + cached.response.body.len();
+
+        self.insert(request.url.clone(), cached.clone());
+        cached
+    }
+
+    pub fn remove(&mut self, url: &str) {
+        if let Some(cached) = self.entries.remove(url) {
+            self.size -= cached.size;
+        }
+    }
+
+    fn insert(&mut self, key: String, cached: CachedResponse) {
+        self.remove(&key);
+
+        if cached.size > self.max_size {
+            return;
+        }
+
+        while self.size + cached.size > self.max_size {
+            match self.entries.pop_front() {
+                Some((_, evicted)) => self.size -= evicted.size,
+                None => break,
+            }
+        }
+
+        self.size += cached.size;
+        self.entries.insert(key, cached);
+    }
+}
@@ -0,0 +1,220 @@
+use serde_json::json;
+use std::{
+    cell::Cell,
+    rc::Rc,
+    sync::{Arc, RwLock},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use v8::inspector::{
+    ChannelBase, ChannelImpl, StringBuffer, StringView, V8Inspector, V8InspectorClientBase,
+    V8InspectorClientImpl, V8InspectorSession,
+};
+use v8::{UniquePtr, UniqueRef};
+
+use crate::{Heartbeat, IsolateEvent};
+
+const CONTEXT_GROUP_ID: i32 = 1;
+// The id of the first (and only) context created in the isolate
+const EXECUTION_CONTEXT_ID: i32 = 1;
+const CONTEXT_NAME: &[u8] = b"Lagon";
+
+// Chrome DevTools Protocol messages, exchanged with a client (e.g
+// DevTools connected over a WebSocket) while the isolate is running
+#[derive(Clone)]
+pub struct InspectorSession {
+    pub incoming: flume::Receiver<String>,
+    pub outgoing: flume::Sender<String>,
+}
+
+struct Channel {
+    base: ChannelBase,
+    outgoing: flume::Sender<String>,
+}
+
+impl Channel {
+    fn send(&self, message: UniquePtr<StringBuffer>) {
+        let message = message.unwrap().string().to_string();
+
+        self.outgoing.send(message).unwrap_or(());
+    }
+}
+
+impl ChannelImpl for Channel {
+    fn base(&self) -> &ChannelBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut ChannelBase {
+        &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const ChannelBase
+    where
+        Self: Sized,
+    {
+        std::ptr::addr_of!((*this).base)
+    }
+
+    fn send_response(&mut self, _call_id: i32, message: UniquePtr<StringBuffer>) {
+        self.send(message);
+    }
+
+    fn send_notification(&mut self, message: UniquePtr<StringBuffer>) {
+        self.send(message);
+    }
+
+    fn flush_protocol_notifications(&mut self) {}
+}
+
+enum Selected {
+    Event(Result<IsolateEvent, flume::RecvError>),
+    Message(Result<String, flume::RecvError>),
+}
+
+// Fields are dropped in order: the session has to be
+// dropped before the inspector it's connected to
+pub struct Inspector {
+    session: Option<UniqueRef<V8InspectorSession>>,
+    inspector: Option<UniqueRef<V8Inspector>>,
+    channel: Box<Channel>,
+    base: V8InspectorClientBase,
+    incoming: flume::Receiver<String>,
+    heartbeat: Arc<RwLock<Heartbeat>>,
+    // Stored outside of the inspector, since it's updated by V8
+    // callbacks while the message loop on pause is running
+    is_paused: Rc<Cell<bool>>,
+}
+
+impl Inspector {
+    // Boxed, because V8 keeps a pointer to the client
+    pub(crate) fn new(
+        isolate: &mut v8::Isolate,
+        context: v8::Local<v8::Context>,
+        session: InspectorSession,
+        heartbeat: Arc<RwLock<Heartbeat>>,
+    ) -> Box<Self> {
+        let mut inspector = Box::new(Inspector {
+            session: None,
+            inspector: None,
+            channel: Box::new(Channel {
+                base: ChannelBase::new::<Channel>(),
+                outgoing: session.outgoing.clone(),
+            }),
+            base: V8InspectorClientBase::new::<Self>(),
+            incoming: session.incoming,
+            heartbeat,
+            is_paused: Rc::new(Cell::new(false)),
+        });
+
+        let mut v8_inspector = V8Inspector::create(isolate, &mut *inspector);
+        v8_inspector.context_created(context, CONTEXT_GROUP_ID, StringView::from(CONTEXT_NAME));
+
+        let v8_session = v8_inspector.connect(
+            CONTEXT_GROUP_ID,
+            &mut *inspector.channel,
+            StringView::empty(),
+        );
+
+        inspector.inspector = Some(v8_inspector);
+        inspector.session = Some(v8_session);
+        inspector
+    }
+
+    fn dispatch(&mut self, message: &str) {
+        if let Some(session) = self.session.as_mut() {
+            session.dispatch_protocol_message(StringView::from(message.as_bytes()));
+        }
+    }
+
+    // Handle the messages received while the isolate was running code
+    pub(crate) fn poll_messages(&mut self) {
+        while let Ok(message) = self.incoming.try_recv() {
+            self.dispatch(&message);
+        }
+    }
+
+    // Wait for the next isolate event, while still handling the messages
+    // received in the meantime, e.g to set breakpoints
+    pub(crate) fn recv_event(
+        &mut self,
+        rx: &flume::Receiver<IsolateEvent>,
+    ) -> Result<IsolateEvent, flume::RecvError> {
+        loop {
+            let selected = flume::Selector::new()
+                .recv(rx, Selected::Event)
+                .recv(&self.incoming, Selected::Message)
+                .wait();
+
+            match selected {
+                Selected::Event(event) => return event,
+                Selected::Message(Ok(message)) => self.dispatch(&message),
+                // The client is gone, only wait for isolate events
+                Selected::Message(Err(_)) => return rx.recv(),
+            }
+        }
+    }
+}
+
+// Console logs go through our own bindings and not V8's console,
+// so they are sent to the client as protocol notifications
+pub fn send_console_message(outgoing: &flume::Sender<String>, level: &str, message: &str) {
+    let level = match level {
+        "warn" => "warning",
+        "error" | "debug" | "info" => level,
+        _ => "log",
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |duration| duration.as_secs_f64() * 1000.0);
+
+    let notification = json!({
+        "method": "Runtime.consoleAPICalled",
+        "params": {
+            "type": level,
+            "args": [{ "type": "string", "value": message }],
+            "executionContextId": EXECUTION_CONTEXT_ID,
+            "timestamp": timestamp,
+        },
+    });
+
+    outgoing.send(notification.to_string()).unwrap_or(());
+}
+
+impl V8InspectorClientImpl for Inspector {
+    fn base(&self) -> &V8InspectorClientBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut V8InspectorClientBase {
+        &mut self.base
+    }
+
+    unsafe fn base_ptr(this: *const Self) -> *const V8InspectorClientBase
+    where
+        Self: Sized,
+    {
+        std::ptr::addr_of!((*this).base)
+    }
+
+    // Called when a breakpoint is hit or when stepping through the code. The
+    // isolate thread is blocked and only handles messages until it's resumed.
+    fn run_message_loop_on_pause(&mut self, _context_group_id: i32) {
+        self.is_paused.set(true);
+
+        // The isolate shouldn't time out while paused
+        *self.heartbeat.write().unwrap() = Heartbeat::Waiting;
+
+        while self.is_paused.get() {
+            match self.incoming.recv() {
+                Ok(message) => self.dispatch(&message),
+                Err(_) => break,
+            }
+        }
+
+        *self.heartbeat.write().unwrap() = Heartbeat::Some;
+    }
+
+    fn quit_message_loop_on_pause(&mut self) {
+        self.is_paused.set(false);
+    }
+}
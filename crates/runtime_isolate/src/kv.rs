@@ -0,0 +1,244 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    ops::Bound,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// Keys are limited like the ones of Cloudflare Workers KV
+pub const MAX_KEY_SIZE: usize = 512;
+pub const MAX_LIST_LIMIT: usize = 1000;
+
+// Values are returned with the type they were stored with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KvValue {
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+impl KvValue {
+    // In bytes
+    pub fn size(&self) -> usize {
+        match self {
+            KvValue::String(string) => string.len(),
+            KvValue::Bytes(bytes) => bytes.len(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KvList {
+    pub keys: Vec<String>,
+    // Given to the next call to list the following keys, None once all of them are listed
+    pub cursor: Option<String>,
+}
+
+// Where the values of `Lagon.kv` are stored, e.g in memory or in Redis.
+// Expired values must not be returned
+#[async_trait]
+pub trait KvBackend: Debug + Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<KvValue>>;
+
+    // Replace the previous value, if any. The value expires after `ttl`, if given
+    async fn put(&self, key: &str, value: KvValue, ttl: Option<Duration>) -> Result<()>;
+
+    async fn delete(&self, key: &str) -> Result<()>;
+
+    // Up to `limit` keys starting with `prefix`, in lexicographic order. The cursor
+    // is the one returned by the previous call, to list the following keys
+    async fn list(&self, prefix: &str, limit: usize, cursor: Option<&str>) -> Result<KvList>;
+}
+
+#[derive(Debug, Clone)]
+struct MemoryValue {
+    value: KvValue,
+    expires_at: Option<SystemTime>,
+}
+
+impl MemoryValue {
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires_at
+            .map_or(false, |expires_at| expires_at <= now)
+    }
+}
+
+// How the values are written to the JSON file, with binary values in base64
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PersistedValue {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    string: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bytes: Option<String>,
+    // A Unix timestamp in milliseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at: Option<u64>,
+}
+
+impl From<&MemoryValue> for PersistedValue {
+    fn from(value: &MemoryValue) -> Self {
+        let (string, bytes) = match &value.value {
+            KvValue::String(string) => (Some(string.clone()), None),
+            KvValue::Bytes(bytes) => (None, Some(STANDARD.encode(bytes))),
+        };
+
+        Self {
+            string,
+            bytes,
+            expires_at: value.expires_at.map(|expires_at| {
+                expires_at
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as u64
+            }),
+        }
+    }
+}
+
+impl TryFrom<PersistedValue> for MemoryValue {
+    type Error = anyhow::Error;
+
+    fn try_from(value: PersistedValue) -> Result<Self> {
+        let kv_value = match (value.string, value.bytes) {
+            (Some(string), None) => KvValue::String(string),
+            (None, Some(bytes)) => KvValue::Bytes(STANDARD.decode(bytes)?),
+            _ => return Err(anyhow!("A value must have either a string or bytes")),
+        };
+
+        Ok(Self {
+            value: kv_value,
+            expires_at: value
+                .expires_at
+                .map(|expires_at| UNIX_EPOCH + Duration::from_millis(expires_at)),
+        })
+    }
+}
+
+// Store the values in memory, and optionally write all of them to a JSON
+// file after each change so they survive restarts, e.g for development
+#[derive(Debug, Default)]
+pub struct MemoryKvBackend {
+    values: Mutex<BTreeMap<String, MemoryValue>>,
+    path: Option<PathBuf>,
+}
+
+impl MemoryKvBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Load the values of the file if it exists, and keep it updated
+    pub fn persisted(path: PathBuf) -> Result<Self> {
+        let mut values = BTreeMap::new();
+
+        if path.is_file() {
+            let content = std::fs::read_to_string(&path)?;
+            let persisted = serde_json::from_str::<HashMap<String, PersistedValue>>(&content)
+                .map_err(|error| anyhow!("Could not parse {}: {}", path.display(), error))?;
+            let now = SystemTime::now();
+
+            for (key, value) in persisted {
+                let value = MemoryValue::try_from(value)
+                    .map_err(|error| anyhow!("Could not parse {}: {}", path.display(), error))?;
+
+                if !value.is_expired(now) {
+                    values.insert(key, value);
+                }
+            }
+        }
+
+        Ok(Self {
+            values: Mutex::new(values),
+            path: Some(path),
+        })
+    }
+
+    fn persist(&self, values: &BTreeMap<String, MemoryValue>) -> Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let persisted = values
+            .iter()
+            .map(|(key, value)| (key, PersistedValue::from(value)))
+            .collect::<BTreeMap<_, _>>();
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::write(path, serde_json::to_string_pretty(&persisted)?)
+            .map_err(|error| anyhow!("Could not write {}: {}", path.display(), error))
+    }
+}
+
+#[async_trait]
+impl KvBackend for MemoryKvBackend {
+    async fn get(&self, key: &str) -> Result<Option<KvValue>> {
+        let values = self.values.lock().unwrap();
+
+        Ok(values
+            .get(key)
+            .filter(|value| !value.is_expired(SystemTime::now()))
+            .map(|value| value.value.clone()))
+    }
+
+    async fn put(&self, key: &str, value: KvValue, ttl: Option<Duration>) -> Result<()> {
+        let mut values = self.values.lock().unwrap();
+
+        values.insert(
+            key.to_string(),
+            MemoryValue {
+                value,
+                expires_at: ttl.map(|ttl| SystemTime::now() + ttl),
+            },
+        );
+
+        self.persist(&values)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let mut values = self.values.lock().unwrap();
+
+        if values.remove(key).is_some() {
+            self.persist(&values)?;
+        }
+
+        Ok(())
+    }
+
+    async fn list(&self, prefix: &str, limit: usize, cursor: Option<&str>) -> Result<KvList> {
+        let values = self.values.lock().unwrap();
+        let now = SystemTime::now();
+
+        // The cursor is the last key of the previous call
+        let start = match cursor {
+            Some(cursor) if cursor >= prefix => Bound::Excluded(cursor),
+            _ => Bound::Included(prefix),
+        };
+
+        let mut keys = values
+            .range::<str, _>((start, Bound::Unbounded))
+            .take_while(|(key, _)| key.starts_with(prefix))
+            .filter(|(_, value)| !value.is_expired(now))
+            .map(|(key, _)| key.clone())
+            .take(limit + 1)
+            .collect::<Vec<_>>();
+
+        let cursor = if keys.len() > limit {
+            keys.truncate(limit);
+            keys.last().cloned()
+        } else {
+            None
+        };
+
+        Ok(KvList { keys, cursor })
+    }
+}
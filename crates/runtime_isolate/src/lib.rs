@@ -1,33 +1,65 @@
-use futures::{future::poll_fn, stream::FuturesUnordered, Future, StreamExt};
-use lagon_runtime_http::{FromV8, IntoV8, Request, Response, RunResult, StreamResult};
+use futures::{
+    future::{poll_fn, AbortHandle},
+    stream::FuturesUnordered,
+    Future, StreamExt,
+};
+use lagon_runtime_http::{FromV8, IntoV8, Request, Response, RunResult, StreamResult, X_LAGON_ID};
 use lagon_runtime_v8_utils::v8_string;
 use lazy_static::lazy_static;
 use linked_hash_map::LinkedHashMap;
 use std::{
     cell::{RefCell, RefMut},
     collections::HashMap,
+    path::PathBuf,
     pin::Pin,
     rc::Rc,
     sync::{
         atomic::{AtomicBool, Ordering},
-        Arc, RwLock,
+        Arc, Mutex, RwLock,
     },
     task::{Context, Poll},
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use tokio_util::task::LocalPoolHandle;
 use v8::MapFnTo;
 
 use self::{
-    bindings::{BindingResult, PromiseResult},
+    bindings::{
+        compression::SharedCodec,
+        fetch::{create_client, FetchBodyChunk, FetchClient},
+        websocket::WebSocketHandle,
+        BindingResult, PromiseResult,
+    },
+    cache::{get_cache_backend, CacheBackend},
     callbacks::{heap_limit_callback, promise_reject_callback, resolve_module_callback},
-    options::{IsolateOptions, Metadata},
+    cpu_watchdog::CpuWatchdog,
+    hooks::IsolateHookEvent,
+    http_cache::{HttpCache, SharedHttpCache},
+    inspector::Inspector,
+    kv::KvBackend,
+    network_policy::NetworkPolicy,
+    options::{IsolateOptions, Metadata, OnFetchCallback, OnNetworkViolationCallback},
+    proxy::{get_proxy, Proxy},
+    queue::QueueBackend,
 };
 
 mod bindings;
+pub mod cache;
 mod callbacks;
+mod cpu_watchdog;
+pub mod hooks;
+mod http_cache;
+mod inspector;
+pub mod kv;
+pub mod network_policy;
 pub mod options;
-pub use bindings::CONSOLE_SOURCE;
+pub mod pool;
+pub mod proxy;
+pub mod queue;
+pub mod tls;
+pub use bindings::{stream_request_body, RequestBodyChunk, CONSOLE_SOURCE};
+pub use inspector::InspectorSession;
+pub use lagon_runtime_http::HeapStatistics;
 
 lazy_static! {
     pub static ref POOL: LocalPoolHandle = LocalPoolHandle::new(1);
@@ -36,20 +68,73 @@ lazy_static! {
 const RUNTIME_ONLY_SCRIPT_NAME: &str = "runtime.js";
 const CODE_ONLY_SCRIPT_NAME: &str = "code.js";
 const ISOLATE_SCRIPT_NAME: &str = "isolate.js";
+// How often to check again when the current timeout is disabled
+const DISABLED_TIMEOUT_INTERVAL: Duration = Duration::from_millis(100);
 
 #[derive(Debug, Default)]
 pub struct RequestContext {
     fetch_calls: usize,
+    // The fetch() calls and their response bodies, by fetch id
+    fetch_aborts: HashMap<u32, AbortHandle>,
+    fetch_bodies: HashMap<u32, flume::Receiver<FetchBodyChunk>>,
+    // The compression streams in use, by codec id
+    codecs: HashMap<u32, SharedCodec>,
+    // The WebSocket connections, by WebSocket id. Dropping them force-closes the connections
+    websockets: HashMap<u32, WebSocketHandle>,
+    // Read from the `x-lagon-id` header, to be attached to console logs
+    request_id: Option<String>,
+}
+
+impl RequestContext {
+    // Stop the fetch() calls in flight and the responses that weren't consumed
+    fn abort_fetches(&mut self) {
+        for (_, abort_handle) in self.fetch_aborts.drain() {
+            abort_handle.abort();
+        }
+
+        self.fetch_bodies.clear();
+    }
+}
+
+impl Drop for RequestContext {
+    fn drop(&mut self) {
+        self.abort_fetches();
+    }
 }
 
 pub struct IsolateRequest {
     pub request: Request,
     pub sender: flume::Sender<RunResult>,
+    // Receives the statistics of the request right before its last result
+    pub statistics: Option<flume::Sender<RequestStatistics>>,
+    // Chunks of the body to stream into `request.body`, instead of `request.body`
+    pub body_stream: Option<flume::Receiver<RequestBodyChunk>>,
+    // Disconnected once the client closed the connection, which aborts the request
+    pub connection: Option<flume::Receiver<()>>,
+}
+
+// Invokes the `scheduled` export instead of the handler, e.g for cron triggers
+pub struct IsolateScheduledEvent {
+    // The cron expression that triggered the invocation
+    pub cron: String,
+    pub scheduled_time: SystemTime,
+    // Receives an empty `204` response once the `scheduled` export resolved, or an error
+    pub sender: flume::Sender<RunResult>,
+    pub statistics: Option<flume::Sender<RequestStatistics>>,
+}
+
+// What is passed to the master handlers of the JS runtime
+enum HandlerEvent {
+    // Whether the body is streamed
+    Request(Request, bool),
+    Scheduled(String, SystemTime),
 }
 
 pub enum IsolateEvent {
     Request(IsolateRequest),
+    Scheduled(IsolateScheduledEvent),
     Terminate(String),
+    HeapStatistics(flume::Sender<HeapStatistics>),
 }
 
 #[derive(Debug)]
@@ -60,11 +145,110 @@ pub struct HandlerResult {
     stream_response_sent: RefCell<bool>,
     stream_status: RefCell<StreamStatus>,
     context: RequestContext,
+    // Time spent executing JS for this request
+    cpu_time: Duration,
+    statistics: Option<flume::Sender<RequestStatistics>>,
+    // Dropped with the handler result, which stops reading the body
+    body_stream: Option<flume::Receiver<RequestBodyChunk>>,
+    // The performance marks and measures of this request, created when first used
+    performance_entries: Option<v8::Global<v8::Array>>,
+    // The promises passed to `waitUntil()`, which can settle after the response is sent
+    wait_until: Vec<v8::Global<v8::Promise>>,
+    // Set once the response is sent, while waiting for the `waitUntil()` promises
+    background_deadline: Option<Instant>,
+    connection: Option<flume::Receiver<()>>,
+    // Aborts `request.signal` and cancels the streamed response in the handler
+    disconnect: Option<flume::Sender<()>>,
+    // Set once the client disconnected before the response was sent
+    aborted: bool,
+}
+
+impl HandlerResult {
+    fn new(
+        sender: flume::Sender<RunResult>,
+        statistics: Option<flume::Sender<RequestStatistics>>,
+        body_stream: Option<flume::Receiver<RequestBodyChunk>>,
+        connection: Option<flume::Receiver<()>>,
+        request_id: Option<String>,
+    ) -> Self {
+        HandlerResult {
+            promise: None,
+            sender,
+            start_time: Instant::now(),
+            stream_response_sent: RefCell::new(false),
+            stream_status: RefCell::new(StreamStatus::None),
+            context: RequestContext {
+                fetch_calls: 0,
+                fetch_aborts: HashMap::new(),
+                fetch_bodies: HashMap::new(),
+                codecs: HashMap::new(),
+                websockets: HashMap::new(),
+                request_id,
+            },
+            cpu_time: Duration::ZERO,
+            statistics,
+            body_stream,
+            performance_entries: None,
+            wait_until: Vec::new(),
+            background_deadline: None,
+            connection,
+            disconnect: None,
+            aborted: false,
+        }
+    }
+
+    fn notify_disconnect(&mut self) {
+        if let Some(disconnect) = self.disconnect.take() {
+            disconnect.send(()).unwrap_or(());
+        }
+    }
+
+    fn send_statistics(&self) {
+        if let Some(statistics) = &self.statistics {
+            statistics
+                .send(RequestStatistics {
+                    cpu_time: self.cpu_time,
+                    wall_time: self.start_time.elapsed(),
+                })
+                .unwrap_or(());
+        }
+    }
+
+    // Called along with `send_statistics`, once the final result of the request is known
+    fn emit_finished(&self, options: &IsolateOptions, id: u32, result: &RunResult) {
+        hooks::emit(
+            options,
+            IsolateHookEvent::RequestFinished {
+                id,
+                duration: self.start_time.elapsed(),
+                cpu_time: self.cpu_time,
+                result,
+            },
+        );
+    }
 }
 
 #[derive(Debug, Clone)]
 struct Global(v8::Global<v8::Context>);
 
+#[derive(Clone)]
+struct OnFetch(OnFetchCallback);
+
+impl std::fmt::Debug for OnFetch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OnFetch")
+    }
+}
+
+#[derive(Clone)]
+struct OnNetworkViolation(OnNetworkViolationCallback);
+
+impl std::fmt::Debug for OnNetworkViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OnNetworkViolation")
+    }
+}
+
 #[derive(Debug)]
 pub struct IsolateState {
     global: Option<Global>,
@@ -76,6 +260,38 @@ pub struct IsolateState {
     rejected_promises: LinkedHashMap<v8::Global<v8::Promise>, String>,
     lines: usize,
     requests_count: u32,
+    on_fetch: Option<OnFetch>,
+    // Dropped with the isolate, so connections are never shared between isolates
+    fetch_client: FetchClient,
+    fetch_timeout: Duration,
+    // Also enforced by the client's resolver, which checks the resolved IPs
+    network_policy: Arc<NetworkPolicy>,
+    // Read once, so the same proxy is used by the whole isolate
+    fetch_proxy: Arc<Proxy>,
+    // Dropped with the isolate too, so cached responses never leak to other isolates
+    http_cache: SharedHttpCache,
+    // Where the Cache API stores its responses, if anywhere
+    cache_backend: Option<Arc<dyn CacheBackend>>,
+    kv_backend: Option<Arc<dyn KvBackend>>,
+    kv_max_value_size: usize,
+    queue_backend: Option<Arc<dyn QueueBackend>>,
+    queue_max_message_size: usize,
+    on_network_violation: Option<OnNetworkViolation>,
+    // Console logs are also sent to the inspector's client, if any
+    inspector_outgoing: Option<flume::Sender<String>>,
+    fs_root: Option<PathBuf>,
+    // performance.now() is relative to when the isolate was created
+    time_origin: Instant,
+    // The same instant as a Unix timestamp in milliseconds, for performance.timeOrigin
+    time_origin_unix: f64,
+    coarse_timers: bool,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct RequestStatistics {
+    // Only the time spent executing JS, e.g not awaiting a fetch() call
+    pub cpu_time: Duration,
+    pub wall_time: Duration,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -116,14 +332,26 @@ impl Heartbeat {
 
 pub struct Isolate {
     options: IsolateOptions,
+    // Has to be dropped before the isolate
+    inspector: Option<Box<Inspector>>,
     isolate: Option<v8::OwnedIsolate>,
     handler: Option<v8::Global<v8::Function>>,
+    scheduled_handler: Option<v8::Global<v8::Function>>,
     compilation_error: Option<String>,
+    // Created after the code has been compiled from source
+    code_cache: Option<Vec<u8>>,
     stream_receiver: flume::Receiver<(u32, StreamResult)>,
     termination_result: Arc<RwLock<Option<RunResult>>>,
     heartbeat: Arc<RwLock<Heartbeat>>,
+    // Only created when `cpu_timeout` is enabled
+    cpu_watchdog: Option<CpuWatchdog>,
+    // Whether `on_memory_pressure` was called since the heap was last under the threshold
+    is_under_memory_pressure: bool,
     rx: flume::Receiver<IsolateEvent>,
     near_heap_limit_callback_data: Option<Box<RefCell<dyn std::any::Any>>>,
+    // Whether JS might run on the next microtask checkpoint, e.g
+    // after promises have been resolved
+    has_pending_microtasks: bool,
 }
 
 unsafe impl Send for Isolate {}
@@ -148,6 +376,9 @@ impl Isolate {
             v8::ExternalReference {
                 function: bindings::crypto::uuid_binding.map_fn_to(),
             },
+            v8::ExternalReference {
+                function: bindings::crypto::uuid_v7_binding.map_fn_to(),
+            },
             v8::ExternalReference {
                 function: bindings::crypto::random_values_binding.map_fn_to(),
             },
@@ -157,6 +388,45 @@ impl Isolate {
             v8::ExternalReference {
                 function: bindings::queue_microtask::queue_microtask_binding.map_fn_to(),
             },
+            v8::ExternalReference {
+                function: bindings::fetch::abort_fetch_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::compression::create_codec_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::url::parse_url_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::url::set_url_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::structured_clone::structured_clone_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::performance::performance_now_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::performance::performance_time_origin_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::performance::performance_entries_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::wait_until::wait_until_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::websocket::websocket_send_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::websocket::websocket_close_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::websocket::websocket_abort_binding.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: bindings::websocket::websocket_upgrade_binding.map_fn_to(),
+            },
         ];
 
         let refs = v8::ExternalReferences::new(&references);
@@ -180,8 +450,9 @@ impl Isolate {
         isolate.set_promise_reject_callback(promise_reject_callback);
 
         let (stream_sender, stream_receiver) = flume::unbounded();
+        let heartbeat = Arc::new(RwLock::new(Heartbeat::None));
 
-        let state: IsolateState = {
+        let (state, inspector) = {
             let isolate_scope = &mut v8::HandleScope::new(&mut isolate);
             let global = if options.snapshot {
                 let context = bindings::bind(isolate_scope, bindings::BindStrategy::Sync);
@@ -196,7 +467,25 @@ impl Isolate {
                 v8::Global::new(isolate_scope, context)
             };
 
-            IsolateState {
+            // Snapshots can't be debugged
+            let inspector = match (&options.inspector, options.snapshot) {
+                (Some(session), false) => {
+                    let context = v8::Local::new(isolate_scope, &global);
+
+                    Some(Inspector::new(
+                        isolate_scope,
+                        context,
+                        session.clone(),
+                        Arc::clone(&heartbeat),
+                    ))
+                }
+                _ => None,
+            };
+
+            let network_policy = Arc::new(options.network_policy.clone());
+            let fetch_proxy = get_proxy();
+
+            let state = IsolateState {
                 global: Some(Global(global)),
                 promises: FuturesUnordered::new(),
                 js_promises: HashMap::new(),
@@ -206,31 +495,75 @@ impl Isolate {
                 rejected_promises: LinkedHashMap::new(),
                 lines: 0,
                 requests_count: 0,
-            }
+                on_fetch: options.on_fetch.clone().map(OnFetch),
+                fetch_client: create_client(
+                    &options,
+                    Arc::clone(&network_policy),
+                    Arc::clone(&fetch_proxy),
+                ),
+                fetch_timeout: options.fetch_timeout,
+                network_policy,
+                fetch_proxy,
+                http_cache: Arc::new(Mutex::new(HttpCache::new(options.fetch_cache_size))),
+                cache_backend: options.cache_backend.clone().or_else(get_cache_backend),
+                kv_backend: options.kv_backend.clone(),
+                kv_max_value_size: options.kv_max_value_size,
+                queue_backend: options.queue_backend.clone(),
+                queue_max_message_size: options.queue_max_message_size,
+                on_network_violation: options.on_network_violation.clone().map(OnNetworkViolation),
+                inspector_outgoing: inspector
+                    .as_ref()
+                    .and(options.inspector.as_ref())
+                    .map(|session| session.outgoing.clone()),
+                fs_root: options.fs_root.clone(),
+                time_origin: Instant::now(),
+                time_origin_unix: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0.0, |duration| duration.as_secs_f64() * 1000.0),
+                coarse_timers: options.coarse_timers,
+            };
+
+            (state, inspector)
         };
 
         isolate.set_slot(Rc::new(RefCell::new(state)));
 
         let mut this = Self {
             options,
+            inspector,
             isolate: Some(isolate),
             handler: None,
+            scheduled_handler: None,
             compilation_error: None,
+            code_cache: None,
             stream_receiver,
             termination_result: Arc::new(RwLock::new(None)),
-            heartbeat: Arc::new(RwLock::new(Heartbeat::None)),
+            heartbeat,
+            cpu_watchdog: None,
+            is_under_memory_pressure: false,
             rx,
             near_heap_limit_callback_data: None,
+            has_pending_microtasks: false,
         };
 
+        // JS is paused on breakpoints while debugging
+        if !this.options.cpu_timeout.is_zero() && this.inspector.is_none() {
+            this.cpu_watchdog = Some(CpuWatchdog::new(
+                this.isolate.as_ref().unwrap().thread_safe_handle(),
+                Arc::clone(&this.termination_result),
+            ));
+        }
+
         let thread_safe_handle = this.isolate.as_ref().unwrap().thread_safe_handle();
         let termination_result_handle = Arc::clone(&this.termination_result);
 
         this.set_heap_limit_callback(move |current: usize| {
+            // The isolate can't be used while V8 calls this callback, so the
+            // statistics are read once it returned, see `record_memory_limit`
             termination_result_handle
                 .write()
                 .unwrap()
-                .replace(RunResult::MemoryLimit);
+                .replace(RunResult::MemoryLimit(HeapStatistics::default()));
 
             if !thread_safe_handle.is_execution_terminating() {
                 thread_safe_handle.terminate_execution();
@@ -241,6 +574,8 @@ impl Isolate {
             current * 2
         });
 
+        hooks::emit(&this.options, IsolateHookEvent::Created);
+
         this
     }
 
@@ -262,6 +597,78 @@ impl Isolate {
         Rc::clone(&self.options.metadata)
     }
 
+    pub fn get_compilation_error(&self) -> Option<&str> {
+        self.compilation_error.as_deref()
+    }
+
+    // The V8 code cache of a successful evaluation, to pass to `IsolateOptions::code_cache()`.
+    // None if the code was already compiled from a code cache that V8 didn't reject
+    pub fn get_code_cache(&self) -> Option<&[u8]> {
+        self.code_cache.as_deref()
+    }
+
+    pub fn get_termination_result(&self) -> Option<RunResult> {
+        self.termination_result.read().unwrap().clone()
+    }
+
+    // Current size of the heap used by the isolate, in bytes
+    pub fn get_memory_usage(&mut self) -> usize {
+        self.get_heap_statistics().used_heap_size
+    }
+
+    pub fn get_heap_statistics(&mut self) -> HeapStatistics {
+        let mut statistics = v8::HeapStatistics::default();
+        self.isolate
+            .as_mut()
+            .unwrap()
+            .get_heap_statistics(&mut statistics);
+
+        HeapStatistics {
+            used_heap_size: statistics.used_heap_size(),
+            total_heap_size: statistics.total_heap_size(),
+            heap_size_limit: statistics.heap_size_limit(),
+            external_memory: statistics.external_memory(),
+            number_of_detached_contexts: statistics.number_of_detached_contexts(),
+        }
+    }
+
+    // Fill the statistics of the memory limit once JS stopped running. The
+    // terminated isolate still holds the heap that reached the limit
+    fn record_memory_limit(&mut self) {
+        let is_missing = matches!(
+            self.termination_result.read().unwrap().as_ref(),
+            Some(RunResult::MemoryLimit(statistics)) if *statistics == HeapStatistics::default()
+        );
+
+        if is_missing {
+            let statistics = self.get_heap_statistics();
+            self.termination_result
+                .write()
+                .unwrap()
+                .replace(RunResult::MemoryLimit(statistics));
+        }
+    }
+
+    // Calls `on_memory_pressure` once when the used heap crosses the
+    // threshold, and again only after it went back under it
+    fn check_memory_pressure(&mut self) {
+        if self.options.on_memory_pressure.is_none() {
+            return;
+        }
+
+        let statistics = self.get_heap_statistics();
+        let threshold = statistics.heap_size_limit / 100 * self.options.memory_pressure_threshold;
+        let is_under_memory_pressure = statistics.used_heap_size >= threshold;
+
+        if is_under_memory_pressure && !self.is_under_memory_pressure {
+            if let Some(on_memory_pressure) = &self.options.on_memory_pressure {
+                on_memory_pressure(Rc::clone(&self.options.metadata), statistics);
+            }
+        }
+
+        self.is_under_memory_pressure = is_under_memory_pressure;
+    }
+
     fn terminate(&mut self, run_result: RunResult) {
         self.termination_result.write().unwrap().replace(run_result);
 
@@ -278,6 +685,22 @@ impl Isolate {
     }
 
     pub fn evaluate(&mut self) {
+        hooks::emit(&self.options, IsolateHookEvent::EvaluationStarted);
+
+        let start_time = Instant::now();
+        self.evaluate_code();
+        self.record_memory_limit();
+
+        hooks::emit(
+            &self.options,
+            IsolateHookEvent::EvaluationFinished {
+                duration: start_time.elapsed(),
+                error: self.compilation_error.as_deref(),
+            },
+        );
+    }
+
+    fn evaluate_code(&mut self) {
         let isolate_state = Isolate::state(self.isolate.as_ref().unwrap());
         let global = {
             let state = isolate_state.borrow();
@@ -302,77 +725,135 @@ impl Isolate {
         let source_map_url = v8_string(try_catch, "");
         isolate_state.borrow_mut().lines = lines;
 
-        let source = v8::script_compiler::Source::new(
-            code,
-            Some(&v8::ScriptOrigin::new(
-                try_catch,
-                resource_name.into(),
-                0,
-                0,
-                false,
-                i32::from(self.options.snapshot_blob.is_some()),
-                source_map_url.into(),
-                false,
-                false,
-                true,
-            )),
+        let origin = v8::ScriptOrigin::new(
+            try_catch,
+            resource_name.into(),
+            0,
+            0,
+            false,
+            i32::from(self.options.snapshot_blob.is_some()),
+            source_map_url.into(),
+            false,
+            false,
+            true,
         );
 
+        // Snapshots are made from the runtime code only
+        let code_cache = match self.options.snapshot {
+            true => None,
+            false => self.options.code_cache.take(),
+        };
+        let (mut source, compile_options) = match &code_cache {
+            Some(code_cache) => (
+                v8::script_compiler::Source::new_with_cached_data(
+                    code,
+                    Some(&origin),
+                    v8::CachedData::new(code_cache),
+                ),
+                v8::script_compiler::CompileOptions::ConsumeCodeCache,
+            ),
+            None => (
+                v8::script_compiler::Source::new(code, Some(&origin)),
+                v8::script_compiler::CompileOptions::NoCompileOptions,
+            ),
+        };
+
         let thread_safe_handle = try_catch.thread_safe_handle();
         let termination_result = Arc::clone(&self.termination_result);
         let startup_duration = self.options.startup_timeout;
         let duration = self.options.timeout;
-        let heartbeat = Arc::clone(&self.heartbeat);
+        // Only a weak reference, for the thread to stop once the isolate is dropped
+        let heartbeat = Arc::downgrade(&self.heartbeat);
         let evaluating = Arc::new(AtomicBool::new(true));
         let evaluating_handle = Arc::clone(&evaluating);
 
-        std::thread::spawn(move || {
-            // Isolates are terminated when they miss at least two heartbeats. The heartbeat
-            // missed count isn't reset to zero when a heartbeat has been successfully received:
-            // instead, the heartbeat missed count is decremented by one, allowing to safely
-            // terminate faulty isolates that are stuck in an infinite loop, and not randomly
-            // terminate isolates that just happen to be "slow"
-            let mut missed_heartbeat = 0;
-
-            loop {
-                std::thread::sleep(if evaluating_handle.load(Ordering::SeqCst) {
-                    startup_duration
-                } else {
-                    duration
-                });
+        // There's nothing to check when both timeouts are disabled
+        if !startup_duration.is_zero() || !duration.is_zero() {
+            std::thread::spawn(move || {
+                // Isolates are terminated when they miss at least two heartbeats. The heartbeat
+                // missed count isn't reset to zero when a heartbeat has been successfully received:
+                // instead, the heartbeat missed count is decremented by one, allowing to safely
+                // terminate faulty isolates that are stuck in an infinite loop, and not randomly
+                // terminate isolates that just happen to be "slow"
+                let mut missed_heartbeat = 0;
+
+                loop {
+                    let timeout = if evaluating_handle.load(Ordering::SeqCst) {
+                        startup_duration
+                    } else {
+                        duration
+                    };
 
-                let heartbeat_value = heartbeat.read().unwrap();
+                    // A zero timeout disables the heartbeat check, waiting
+                    // for the other timeout to apply if it's not disabled
+                    if timeout.is_zero() {
+                        if heartbeat.strong_count() == 0 {
+                            break;
+                        }
 
-                if heartbeat_value.is_waiting() {
-                    continue;
-                }
+                        missed_heartbeat = 0;
+                        std::thread::sleep(DISABLED_TIMEOUT_INTERVAL);
+                        continue;
+                    }
 
-                if heartbeat_value.is_none() {
-                    missed_heartbeat += 1;
-                } else if missed_heartbeat > 0 {
-                    missed_heartbeat -= 1;
-                }
+                    std::thread::sleep(timeout);
 
-                if missed_heartbeat >= 2 {
-                    termination_result
-                        .write()
-                        .unwrap()
-                        .replace(RunResult::Timeout);
+                    let heartbeat = match heartbeat.upgrade() {
+                        Some(heartbeat) => heartbeat,
+                        None => break,
+                    };
+                    let heartbeat_value = heartbeat.read().unwrap();
 
-                    if !thread_safe_handle.is_execution_terminating() {
-                        thread_safe_handle.terminate_execution();
+                    if heartbeat_value.is_waiting() {
+                        continue;
                     }
 
-                    break;
-                } else {
-                    drop(heartbeat_value);
-                    *heartbeat.write().unwrap() = Heartbeat::None;
+                    if heartbeat_value.is_none() {
+                        missed_heartbeat += 1;
+                    } else if missed_heartbeat > 0 {
+                        missed_heartbeat -= 1;
+                    }
+
+                    if missed_heartbeat >= 2 {
+                        termination_result
+                            .write()
+                            .unwrap()
+                            .replace(RunResult::Timeout);
+
+                        if !thread_safe_handle.is_execution_terminating() {
+                            thread_safe_handle.terminate_execution();
+                        }
+
+                        break;
+                    } else {
+                        drop(heartbeat_value);
+                        *heartbeat.write().unwrap() = Heartbeat::None;
+                    }
                 }
-            }
-        });
+            });
+        }
 
-        match v8::script_compiler::compile_module(try_catch, source) {
+        match v8::script_compiler::compile_module2(
+            try_catch,
+            &mut source,
+            compile_options,
+            v8::script_compiler::NoCacheReason::NoReason,
+        ) {
             Some(module) => {
+                // V8 compiles from source when the code cache doesn't match, e.g
+                // because it was created by another version of V8 or from other code
+                let from_code_cache = code_cache.is_some()
+                    && !source
+                        .get_cached_data()
+                        .map_or(true, |cached_data| cached_data.rejected());
+
+                if let Some(on_compile) = &self.options.on_compile {
+                    on_compile(Rc::clone(&self.options.metadata), from_code_cache);
+                }
+
+                // Has to be retrieved before the module is evaluated
+                let unbound_module_script = module.get_unbound_module_script(try_catch);
+
                 if module
                     .instantiate_module(try_catch, resolve_module_callback)
                     .is_none()
@@ -386,6 +867,14 @@ impl Isolate {
                     return;
                 }
 
+                // Created after the evaluation, to include the functions compiled by it. A
+                // rejected code cache is replaced, so that the next isolates can use it
+                if !from_code_cache && !self.options.snapshot {
+                    self.code_cache = unbound_module_script
+                        .create_code_cache()
+                        .map(|code_cache| code_cache.to_vec());
+                }
+
                 if !self.options.snapshot {
                     let global = global.open(try_catch);
                     let global = global.global(try_catch);
@@ -395,6 +884,13 @@ impl Isolate {
                     let handler = v8::Global::new(try_catch, handler);
 
                     self.handler = Some(handler);
+
+                    // Snapshots made by previous versions don't define it
+                    let scheduled_handler_key = v8_string(try_catch, "masterScheduledHandler");
+                    self.scheduled_handler = global
+                        .get(try_catch, scheduled_handler_key.into())
+                        .and_then(|handler| v8::Local::<v8::Function>::try_from(handler).ok())
+                        .map(|handler| v8::Global::new(try_catch, handler));
                 }
             }
             None => {
@@ -407,72 +903,164 @@ impl Isolate {
 
     pub fn handle_event(&mut self, event: IsolateEvent) {
         match event {
-            IsolateEvent::Request(IsolateRequest { request, sender }) => {
-                let isolate_state = Isolate::state(self.isolate.as_ref().unwrap());
-                let (global, requests_count) = {
-                    let mut isolate_state = isolate_state.borrow_mut();
-                    let global = isolate_state.global.as_ref().unwrap().0.clone();
-
-                    isolate_state.requests_count += 1;
+            IsolateEvent::Request(IsolateRequest {
+                mut request,
+                sender,
+                statistics,
+                body_stream,
+                connection,
+            }) => {
+                let request_id = request
+                    .headers
+                    .as_mut()
+                    .and_then(|headers| headers.remove(X_LAGON_ID))
+                    .and_then(|values| values.into_iter().next());
+                let is_streamed = body_stream.is_some();
+
+                self.call_handler(
+                    HandlerEvent::Request(request, is_streamed),
+                    HandlerResult::new(sender, statistics, body_stream, connection, request_id),
+                );
+            }
+            IsolateEvent::Scheduled(IsolateScheduledEvent {
+                cron,
+                scheduled_time,
+                sender,
+                statistics,
+            }) => {
+                if self.scheduled_handler.is_none() {
+                    sender
+                        .send(RunResult::Error(
+                            "Scheduled events are not supported by this snapshot".into(),
+                        ))
+                        .unwrap_or(());
+                    return;
+                }
 
-                    (global, isolate_state.requests_count)
-                };
-                let scope = &mut v8::HandleScope::with_context(
-                    self.isolate.as_mut().unwrap(),
-                    global.clone(),
+                self.call_handler(
+                    HandlerEvent::Scheduled(cron, scheduled_time),
+                    HandlerResult::new(sender, statistics, None, None, None),
                 );
-                let try_catch = &mut v8::TryCatch::new(scope);
+            }
+            IsolateEvent::Terminate(reason) => {
+                self.terminate(RunResult::Error(reason));
+            }
+            IsolateEvent::HeapStatistics(sender) => {
+                sender.send(self.get_heap_statistics()).unwrap_or(());
+            }
+        }
+    }
+
+    // Both master handlers return a promise of a response, so scheduled
+    // events are then handled like requests, e.g with `waitUntil()`
+    fn call_handler(&mut self, event: HandlerEvent, handler_result: HandlerResult) {
+        let isolate_state = Isolate::state(self.isolate.as_ref().unwrap());
+        let (global, requests_count) = {
+            let mut isolate_state = isolate_state.borrow_mut();
+            let global = isolate_state.global.as_ref().unwrap().0.clone();
 
-                let handler = self.handler.as_ref().unwrap();
-                let handler = handler.open(try_catch);
+            isolate_state.requests_count += 1;
 
-                let global = global.open(try_catch);
-                let global = global.global(try_catch);
+            (global, isolate_state.requests_count)
+        };
+        let scope =
+            &mut v8::HandleScope::with_context(self.isolate.as_mut().unwrap(), global.clone());
+        let try_catch = &mut v8::TryCatch::new(scope);
 
+        let handler = match event {
+            HandlerEvent::Request(..) => self.handler.as_ref(),
+            HandlerEvent::Scheduled(..) => self.scheduled_handler.as_ref(),
+        };
+        let handler = handler.unwrap().open(try_catch);
+
+        let global = global.open(try_catch);
+        let global = global.global(try_catch);
+
+        let event: v8::Local<v8::Value> = match event {
+            HandlerEvent::Request(request, is_streamed) => {
                 let request = request.into_v8(try_catch);
-                let id = v8::Integer::new(try_catch, requests_count as i32);
-                try_catch.set_continuation_preserved_embedder_data(id.into());
-
-                isolate_state.borrow_mut().handler_results.insert(
-                    requests_count,
-                    HandlerResult {
-                        promise: None,
-                        sender,
-                        start_time: Instant::now(),
-                        stream_response_sent: RefCell::new(false),
-                        stream_status: RefCell::new(StreamStatus::None),
-                        context: RequestContext::default(),
-                    },
-                );
 
-                match handler.call(try_catch, global.into(), &[id.into(), request.into()]) {
-                    Some(response) => {
-                        let promise = v8::Local::<v8::Promise>::try_from(response)
-                            .expect("Handler did not return a promise");
-                        let promise = v8::Global::new(try_catch, promise);
-
-                        if let Some(handler_result) = isolate_state
-                            .borrow_mut()
-                            .handler_results
-                            .get_mut(&requests_count)
-                        {
-                            handler_result.promise = Some(promise);
-                        }
-                    }
-                    None => {
-                        // Use the current termination result (e.g timeout or memory),
-                        // or try to handle an error
-                        self.termination_result
-                            .write()
-                            .unwrap()
-                            .get_or_insert_with(|| handle_error(try_catch, 0));
-                    }
-                };
+                if is_streamed {
+                    let key = v8_string(try_catch, "s");
+                    let value = v8::Boolean::new(try_catch, true);
+                    request.set(try_catch, key.into(), value.into());
+                }
+
+                request.into()
             }
-            IsolateEvent::Terminate(reason) => {
-                self.terminate(RunResult::Error(reason));
+            HandlerEvent::Scheduled(cron, scheduled_time) => {
+                let event = v8::Object::new(try_catch);
+
+                let key = v8_string(try_catch, "c");
+                let value = v8_string(try_catch, &cron);
+                event.set(try_catch, key.into(), value.into());
+
+                // In milliseconds since the Unix epoch, like Date.now()
+                let key = v8_string(try_catch, "t");
+                let value = v8::Number::new(
+                    try_catch,
+                    scheduled_time
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_millis() as f64,
+                );
+                event.set(try_catch, key.into(), value.into());
+
+                event.into()
             }
+        };
+
+        let id = v8::Integer::new(try_catch, requests_count as i32);
+        try_catch.set_continuation_preserved_embedder_data(id.into());
+
+        isolate_state
+            .borrow_mut()
+            .handler_results
+            .insert(requests_count, handler_result);
+
+        hooks::emit(
+            &self.options,
+            IsolateHookEvent::RequestStarted { id: requests_count },
+        );
+
+        if let Some(cpu_watchdog) = &self.cpu_watchdog {
+            cpu_watchdog.start(self.options.cpu_timeout);
+        }
+
+        let call_time = Instant::now();
+        let response = handler.call(try_catch, global.into(), &[id.into(), event]);
+        let cpu_time = call_time.elapsed();
+
+        if let Some(cpu_watchdog) = &self.cpu_watchdog {
+            cpu_watchdog.stop();
         }
+
+        self.has_pending_microtasks = true;
+
+        match response {
+            Some(response) => {
+                let promise = v8::Local::<v8::Promise>::try_from(response)
+                    .expect("Handler did not return a promise");
+                let promise = v8::Global::new(try_catch, promise);
+
+                if let Some(handler_result) = isolate_state
+                    .borrow_mut()
+                    .handler_results
+                    .get_mut(&requests_count)
+                {
+                    handler_result.promise = Some(promise);
+                    handler_result.cpu_time += cpu_time;
+                }
+            }
+            None => {
+                // Use the current termination result (e.g timeout or memory),
+                // or try to handle an error
+                self.termination_result
+                    .write()
+                    .unwrap()
+                    .get_or_insert_with(|| handle_error(try_catch, 0));
+            }
+        };
     }
 
     fn poll_v8(&mut self) {
@@ -514,9 +1102,16 @@ impl Isolate {
             };
             let scope = &mut v8::HandleScope::with_context(self.isolate.as_mut().unwrap(), global);
 
+            self.has_pending_microtasks |= !promises.is_empty();
+
             for (result, promise) in promises {
                 let promise = promise.open(scope);
-                let should_reject = matches!(result, PromiseResult::Error(_));
+                let should_reject = matches!(
+                    result,
+                    PromiseResult::Error(_)
+                        | PromiseResult::FetchError(_)
+                        | PromiseResult::Exception(_)
+                );
                 let value = result.into_value(scope);
 
                 if should_reject {
@@ -543,6 +1138,12 @@ impl Isolate {
 
                 if let StreamResult::Done = stream_result {
                     *stream_status = StreamStatus::Done;
+                    handler_result.send_statistics();
+                    handler_result.emit_finished(
+                        &self.options,
+                        id,
+                        &RunResult::Stream(StreamResult::Done),
+                    );
                 }
 
                 handler_result
@@ -555,7 +1156,15 @@ impl Isolate {
 
     fn poll_event_loop(&mut self, cx: &mut Context) -> Poll<()> {
         if let Some(compilation_error) = &self.compilation_error {
-            if let Ok(IsolateEvent::Request(IsolateRequest { sender, .. })) = self.rx.try_recv() {
+            // Wait for the next request instead of returning right away,
+            // which would recreate and evaluate the isolate in a loop
+            let sender = match self.rx.recv() {
+                Ok(IsolateEvent::Request(IsolateRequest { sender, .. }))
+                | Ok(IsolateEvent::Scheduled(IsolateScheduledEvent { sender, .. })) => Some(sender),
+                _ => None,
+            };
+
+            if let Some(sender) = sender {
                 let termination_result = match self.termination_result.read().unwrap().as_ref() {
                     Some(termination_result) => termination_result.clone(),
                     None => RunResult::Error(compilation_error.to_string()),
@@ -576,27 +1185,95 @@ impl Isolate {
         if isolate_state.borrow().handler_results.is_empty() {
             *self.heartbeat.write().unwrap() = Heartbeat::Waiting;
 
-            if let Ok(event) = self.rx.recv() {
-                *self.heartbeat.write().unwrap() = Heartbeat::Some;
-                self.handle_event(event);
+            // Messages from the inspector's client, e.g to set breakpoints,
+            // also have to be handled while waiting for a request
+            let event = match self.inspector.as_mut() {
+                Some(inspector) => inspector.recv_event(&self.rx),
+                None => self.rx.recv(),
+            };
+
+            match event {
+                Ok(event) => {
+                    *self.heartbeat.write().unwrap() = Heartbeat::Some;
+                    self.handle_event(event);
+                }
+                // All the senders have been dropped and every request
+                // has been handled, so the isolate can stop
+                Err(_) => return Poll::Ready(()),
             }
         } else {
             *self.heartbeat.write().unwrap() = Heartbeat::Some;
 
+            if let Some(inspector) = self.inspector.as_mut() {
+                inspector.poll_messages();
+            }
+
             while let Ok(event) = self.rx.try_recv() {
                 self.handle_event(event);
             }
         }
 
+        // The event loop spins while requests await e.g fetch() calls, so the
+        // checkpoint is only measured when it can run JS. Microtasks can't be
+        // attributed to a single request: their time is split between them
+        let has_pending_microtasks = std::mem::take(&mut self.has_pending_microtasks);
+
+        if let Some(cpu_watchdog) = &self.cpu_watchdog {
+            let state = isolate_state.borrow();
+            let remaining = state
+                .handler_results
+                .values()
+                .map(|handler_result| {
+                    self.options
+                        .cpu_timeout
+                        .saturating_sub(handler_result.cpu_time)
+                })
+                .min();
+
+            // The time is split between the requests below, so the request with
+            // the least CPU time left reaches its limit after this many times it
+            if let Some(remaining) = remaining {
+                cpu_watchdog.start(remaining * state.handler_results.len() as u32);
+            }
+        }
+
+        let js_time = Instant::now();
+
         self.poll_v8();
         self.resolve_promises(cx);
 
+        let js_time = js_time.elapsed();
+
+        if let Some(cpu_watchdog) = &self.cpu_watchdog {
+            cpu_watchdog.stop();
+        }
+
+        // Checked when JS could have allocated
+        if has_pending_microtasks {
+            self.check_memory_pressure();
+        }
+
         let mut state = isolate_state.borrow_mut();
 
+        if has_pending_microtasks && !state.handler_results.is_empty() {
+            let cpu_time = js_time / state.handler_results.len() as u32;
+
+            for handler_result in state.handler_results.values_mut() {
+                handler_result.cpu_time += cpu_time;
+            }
+        }
+
         self.poll_stream(&state);
+        self.record_memory_limit();
 
         if let Some(termination_result) = self.termination_result.read().unwrap().as_ref() {
-            for handler_result in state.handler_results.values() {
+            for (id, handler_result) in state.handler_results.iter() {
+                // Requests in the background already received their response
+                if handler_result.background_deadline.is_some() {
+                    continue;
+                }
+
+                handler_result.emit_finished(&self.options, *id, termination_result);
                 handler_result
                     .sender
                     .send(termination_result.clone())
@@ -612,6 +1289,10 @@ impl Isolate {
 
             // TODO: only send the error to the request that caused it
             for handler_result in state.handler_results.values() {
+                if handler_result.background_deadline.is_some() {
+                    continue;
+                }
+
                 handler_result
                     .sender
                     .send(RunResult::Error(content.clone()))
@@ -625,26 +1306,62 @@ impl Isolate {
         let lines = state.lines;
         let options = &self.options;
 
-        state.handler_results.retain(|_, handler_result| {
+        state.handler_results.retain(|id, handler_result| {
+            if handler_result.background_deadline.is_some() {
+                return finish_request(options, try_catch, handler_result);
+            }
+
             if *handler_result.stream_response_sent.borrow() {
                 if handler_result.stream_status.borrow().is_done() {
-                    send_statistics(options, try_catch, handler_result.start_time);
-                    return false;
+                    return finish_request(options, try_catch, handler_result);
+                }
+
+                // The client stopped reading the response before the end of the stream
+                if handler_result.sender.is_disconnected() {
+                    handler_result.notify_disconnect();
                 }
 
                 return true;
             }
 
+            let is_disconnected = handler_result
+                .connection
+                .as_ref()
+                .map_or(false, |connection| connection.is_disconnected());
+
+            // The handler can stop early with `request.signal`, but its result is ignored
+            if is_disconnected && !handler_result.aborted {
+                handler_result.aborted = true;
+                handler_result.context.abort_fetches();
+                handler_result.notify_disconnect();
+
+                handler_result.send_statistics();
+                handler_result.emit_finished(options, *id, &RunResult::Aborted);
+                handler_result.sender.send(RunResult::Aborted).unwrap_or(());
+            }
+
             let promise = &handler_result.promise;
             let promise = promise.as_ref().unwrap().open(try_catch);
 
             match promise.state() {
+                v8::PromiseState::Fulfilled | v8::PromiseState::Rejected
+                    if handler_result.aborted =>
+                {
+                    finish_request(options, try_catch, handler_result)
+                }
                 v8::PromiseState::Fulfilled => {
                     let response = promise.result(try_catch);
-
-                    let run_result = match Response::from_v8(try_catch, response) {
-                        Ok(response) => RunResult::Response(response),
-                        Err(error) => RunResult::Error(error.to_string()),
+                    let websocket_id = get_websocket_id(try_catch, response);
+
+                    let run_result = match (Response::from_v8(try_catch, response), websocket_id) {
+                        (Ok(response), Some(id)) => {
+                            match handler_result.context.accept_websocket(id, response) {
+                                Some(upgrade) => RunResult::WebSocket(upgrade),
+                                None => RunResult::Error("The WebSocket was already closed".into()),
+                            }
+                        }
+                        (Ok(response), None) => RunResult::Response(response),
+                        (Err(error), _) => RunResult::Error(error.to_string()),
                     };
 
                     if let RunResult::Response(ref response) = run_result {
@@ -662,23 +1379,22 @@ impl Isolate {
 
                     // It's important to send the response before sending the statistics
                     // because calculating the statistics can take a long time
+                    handler_result.send_statistics();
+                    handler_result.emit_finished(options, *id, &run_result);
                     handler_result.sender.send(run_result).unwrap_or(());
-                    send_statistics(options, try_catch, handler_result.start_time);
 
-                    false
+                    finish_request(options, try_catch, handler_result)
                 }
                 v8::PromiseState::Rejected => {
                     let exception = promise.result(try_catch);
+                    let run_result =
+                        RunResult::Error(get_exception_message(try_catch, exception, lines));
 
-                    handler_result
-                        .sender
-                        .send(RunResult::Error(get_exception_message(
-                            try_catch, exception, lines,
-                        )))
-                        .unwrap_or(());
-                    send_statistics(options, try_catch, handler_result.start_time);
+                    handler_result.send_statistics();
+                    handler_result.emit_finished(options, *id, &run_result);
+                    handler_result.sender.send(run_result).unwrap_or(());
 
-                    false
+                    finish_request(options, try_catch, handler_result)
                 }
                 v8::PromiseState::Pending => true,
             }
@@ -690,6 +1406,14 @@ impl Isolate {
 
     pub async fn run_event_loop(&mut self) {
         poll_fn(|cx| self.poll_event_loop(cx)).await;
+
+        let termination_result = self.get_termination_result();
+        hooks::emit(
+            &self.options,
+            IsolateHookEvent::Terminated {
+                result: termination_result.as_ref(),
+            },
+        );
     }
 
     pub fn snapshot(&mut self) -> v8::StartupData {
@@ -720,12 +1444,43 @@ impl Drop for Isolate {
     }
 }
 
-pub fn send_statistics(options: &IsolateOptions, isolate: &mut v8::Isolate, start_time: Instant) {
-    if let Some(on_statistics) = &options.on_statistics {
-        // We calculate the elapsed time before getting the
-        // heap statistics because it can take a long time
-        let cpu_time = start_time.elapsed();
+// Once its response is sent, a request is kept until the promises passed to
+// `waitUntil()` settle, or until the background timeout. The isolate statistics
+// are only sent then, to include the time spent on background work
+fn finish_request(
+    options: &IsolateOptions,
+    scope: &mut v8::HandleScope,
+    handler_result: &mut HandlerResult,
+) -> bool {
+    let timeout = options.background_timeout;
+    let deadline = *handler_result
+        .background_deadline
+        .get_or_insert_with(|| Instant::now() + timeout);
+
+    handler_result
+        .wait_until
+        .retain(|promise| promise.open(scope).state() == v8::PromiseState::Pending);
+
+    let pending =
+        !handler_result.wait_until.is_empty() && (timeout.is_zero() || Instant::now() < deadline);
+    // Upgraded WebSockets aren't limited by the background timeout, and
+    // keep the request alive until they are closed
+    let pending = pending
+        || handler_result
+            .context
+            .websockets
+            .values()
+            .any(|websocket| websocket.upgraded);
+
+    if !pending {
+        send_statistics(options, scope, handler_result.cpu_time);
+    }
+
+    pending
+}
 
+pub fn send_statistics(options: &IsolateOptions, isolate: &mut v8::Isolate, cpu_time: Duration) {
+    if let Some(on_statistics) = &options.on_statistics {
         let mut statistics = v8::HeapStatistics::default();
         isolate.get_heap_statistics(&mut statistics);
 
@@ -739,6 +1494,18 @@ pub fn send_statistics(options: &IsolateOptions, isolate: &mut v8::Isolate, star
     }
 }
 
+// The id of the WebSocket upgraded by the response, if any
+fn get_websocket_id(scope: &mut v8::HandleScope, response: v8::Local<v8::Value>) -> Option<u32> {
+    let response = response.to_object(scope)?;
+    let key = v8_string(scope, "w");
+    let websocket_id = response.get(scope, key.into())?;
+
+    match websocket_id.is_uint32() {
+        true => websocket_id.uint32_value(scope),
+        false => None,
+    }
+}
+
 pub fn get_exception_message(
     scope: &mut v8::TryCatch<v8::HandleScope>,
     exception: v8::Local<v8::Value>,
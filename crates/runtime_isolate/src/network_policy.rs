@@ -0,0 +1,262 @@
+use hyper::{
+    client::connect::dns::{GaiResolver, Name},
+    service::Service,
+};
+use ipnet::IpNet;
+use std::{
+    fmt,
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use url::{Host, Url};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// Restricts the hosts and IPs fetch() can reach, e.g to prevent Functions from
+// calling internal services. The IPs are checked once the host is resolved
+#[derive(Debug, Clone, Default)]
+pub struct NetworkPolicy {
+    // Only these hosts can be reached when not empty. `*.example.com` matches the subdomains
+    pub allowed_hosts: Vec<String>,
+    pub denied_hosts: Vec<String>,
+    // IPs that are always allowed, even private ones, unless they are denied
+    pub allowed_cidrs: Vec<IpNet>,
+    pub denied_cidrs: Vec<IpNet>,
+    // Deny loopback, private (RFC 1918), link-local and other non-public IPs
+    pub block_private_ips: bool,
+    // The maximum size of the response bodies in bytes, unlimited if None
+    pub max_response_size: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkPolicyViolation {
+    DeniedHost(String),
+    DeniedIp {
+        host: String,
+        ip: IpAddr,
+    },
+    PrivateIp {
+        host: String,
+        ip: IpAddr,
+    },
+    ResponseTooLarge {
+        url: String,
+        max_response_size: usize,
+    },
+}
+
+impl fmt::Display for NetworkPolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkPolicyViolation::DeniedHost(host) => {
+                write!(f, "The host {host} is not allowed by the network policy")
+            }
+            NetworkPolicyViolation::DeniedIp { host, ip } => write!(
+                f,
+                "The host {host} resolves to {ip}, which is denied by the network policy"
+            ),
+            NetworkPolicyViolation::PrivateIp { host, ip } => write!(
+                f,
+                "The host {host} resolves to the private IP {ip}, which is blocked by the network policy"
+            ),
+            NetworkPolicyViolation::ResponseTooLarge {
+                url,
+                max_response_size,
+            } => write!(
+                f,
+                "The response of {url} is larger than the maximum size of {max_response_size} bytes"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NetworkPolicyViolation {}
+
+// The host is already lowercase
+fn matches_host(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .map_or(false, |subdomain| subdomain.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+fn is_private_ipv4(ip: &Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+
+    ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        // Shared address space (RFC 6598)
+        || (first == 100 && (64..128).contains(&second))
+}
+
+fn is_private_ipv6(ip: &Ipv6Addr) -> bool {
+    if let Some(ip) = ip.to_ipv4_mapped() {
+        return is_private_ipv4(&ip);
+    }
+
+    let first = ip.segments()[0];
+
+    ip.is_loopback()
+        || ip.is_unspecified()
+        // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+}
+
+impl NetworkPolicy {
+    pub fn check_host(&self, host: &str) -> Result<(), NetworkPolicyViolation> {
+        let host = host.to_lowercase();
+
+        if self
+            .denied_hosts
+            .iter()
+            .any(|pattern| matches_host(pattern, &host))
+            || (!self.allowed_hosts.is_empty()
+                && !self
+                    .allowed_hosts
+                    .iter()
+                    .any(|pattern| matches_host(pattern, &host)))
+        {
+            return Err(NetworkPolicyViolation::DeniedHost(host));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_ip(&self, host: &str, ip: IpAddr) -> Result<(), NetworkPolicyViolation> {
+        if self.denied_cidrs.iter().any(|cidr| cidr.contains(&ip)) {
+            return Err(NetworkPolicyViolation::DeniedIp {
+                host: host.into(),
+                ip,
+            });
+        }
+
+        if self.allowed_cidrs.iter().any(|cidr| cidr.contains(&ip)) {
+            return Ok(());
+        }
+
+        let is_private = match ip {
+            IpAddr::V4(ip) => is_private_ipv4(&ip),
+            IpAddr::V6(ip) => is_private_ipv6(&ip),
+        };
+
+        if self.block_private_ips && is_private {
+            return Err(NetworkPolicyViolation::PrivateIp {
+                host: host.into(),
+                ip,
+            });
+        }
+
+        Ok(())
+    }
+
+    // IP hosts are checked directly since they aren't resolved
+    pub fn check_url(&self, url: &Url) -> Result<(), NetworkPolicyViolation> {
+        let host = match url.host() {
+            Some(host) => host,
+            None => return Ok(()),
+        };
+
+        let name = host.to_string();
+        self.check_host(&name)?;
+
+        match host {
+            Host::Ipv4(ip) => self.check_ip(&name, IpAddr::V4(ip)),
+            Host::Ipv6(ip) => self.check_ip(&name, IpAddr::V6(ip)),
+            Host::Domain(_) => Ok(()),
+        }
+    }
+}
+
+// The host couldn't be resolved, to tell DNS failures apart from connection errors
+#[derive(Debug)]
+pub struct ResolveError(io::Error);
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+// Resolve the hosts, and only keep the IPs allowed by the network policy, so
+// a host can't resolve to an internal service (e.g with DNS rebinding)
+#[derive(Debug, Clone)]
+pub struct PolicyResolver {
+    policy: Arc<NetworkPolicy>,
+    resolver: GaiResolver,
+}
+
+impl PolicyResolver {
+    pub fn new(policy: Arc<NetworkPolicy>) -> Self {
+        Self {
+            policy,
+            resolver: GaiResolver::new(),
+        }
+    }
+
+    // Check the IPs of a host that is connected to by someone else, e.g a proxy.
+    // Like direct connections, it's rejected if none of its IPs are allowed
+    pub async fn check(&self, host: &str) -> Result<(), BoxError> {
+        // No IP can be denied, so hosts only the proxy can resolve still work
+        if !self.policy.block_private_ips && self.policy.denied_cidrs.is_empty() {
+            return Ok(());
+        }
+
+        let host = host.trim_matches(|c| c == '[' || c == ']');
+
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return self.policy.check_ip(host, ip).map_err(Into::into);
+        }
+
+        let name = Name::from_str(host)?;
+        self.clone().call(name).await.map(|_| ())
+    }
+}
+
+impl Service<Name> for PolicyResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.resolver.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let policy = Arc::clone(&self.policy);
+        let resolving = self.resolver.call(name.clone());
+
+        Box::pin(async move {
+            let mut addrs = Vec::new();
+            let mut violation = None;
+
+            for addr in resolving.await.map_err(ResolveError)? {
+                match policy.check_ip(name.as_str(), addr.ip()) {
+                    Ok(()) => addrs.push(addr),
+                    Err(error) => {
+                        violation.get_or_insert(error);
+                    }
+                }
+            }
+
+            match violation {
+                Some(violation) if addrs.is_empty() => Err(violation.into()),
+                _ => Ok(addrs.into_iter()),
+            }
+        })
+    }
+}
@@ -1,25 +1,94 @@
+use anyhow::Result;
+use lagon_runtime_http::{Request, Response};
 use lagon_runtime_v8_utils::v8_string;
-use std::{collections::HashMap, rc::Rc, time::Duration};
+use std::{collections::HashMap, path::PathBuf, rc::Rc, sync::Arc, time::Duration};
 
-use super::IsolateStatistics;
+use super::{
+    cache::CacheBackend,
+    hooks::IsolateHookEvent,
+    kv::KvBackend,
+    network_policy::{NetworkPolicy, NetworkPolicyViolation},
+    queue::QueueBackend,
+    HeapStatistics, InspectorSession, IsolateStatistics,
+};
 
 const JS_RUNTIME: &str = include_str!("../runtime.js");
+const TEST_HARNESS: &str = include_str!("test_harness.js");
 
 pub type Metadata = Option<(String, String)>;
 type OnIsolateDropCallback = Box<dyn Fn(Rc<Metadata>)>;
 type OnIsolateStatisticsCallback = Box<dyn Fn(Rc<Metadata>, IsolateStatistics)>;
+// Called once the code has been compiled, with whether a code cache was used
+pub type OnIsolateCompileCallback = Box<dyn Fn(Rc<Metadata>, bool)>;
+// Called when the used heap crosses `memory_pressure_threshold`, e.g to
+// log it or stop sending requests before the isolate is terminated
+pub type OnMemoryPressureCallback = Box<dyn Fn(Rc<Metadata>, HeapStatistics)>;
+// Called for each step of the lifecycle of the isolate, see `IsolateHookEvent`
+pub type OnIsolateHookCallback = Box<dyn Fn(Rc<Metadata>, &IsolateHookEvent)>;
+// Called before each fetch() call. Returning a response or an error
+// skips the network request, e.g to mock requests in development
+pub type OnFetchCallback = Rc<dyn Fn(&Request) -> Option<Result<Response>>>;
+// Called when a fetch() call is rejected by the network policy
+pub type OnNetworkViolationCallback = Rc<dyn Fn(Rc<Metadata>, &NetworkPolicyViolation)>;
 
 pub struct IsolateOptions {
     pub code: String,
     pub environment_variables: Option<HashMap<String, String>>,
     pub memory: usize, // in MB (MegaBytes)
-    pub timeout: Duration,
-    pub startup_timeout: Duration,
+    // Percentage of the heap limit after which `on_memory_pressure` is called
+    pub memory_pressure_threshold: usize,
+    pub timeout: Duration,         // zero disables the timeout
+    pub startup_timeout: Duration, // zero disables the timeout
+    // How long each request can spend executing JS in total, while `timeout` limits how
+    // long JS blocks the isolate at once. Zero disables it, and so does the inspector
+    pub cpu_timeout: Duration,
+    // How long `waitUntil()` promises can run after the response is sent, zero disables the timeout
+    pub background_timeout: Duration,
     pub metadata: Rc<Metadata>,
     pub on_drop: Option<OnIsolateDropCallback>,
     pub on_statistics: Option<OnIsolateStatisticsCallback>,
+    pub on_fetch: Option<OnFetchCallback>,
+    pub on_compile: Option<OnIsolateCompileCallback>,
+    pub on_memory_pressure: Option<OnMemoryPressureCallback>,
+    // Called in the order they were registered
+    pub hooks: Vec<OnIsolateHookCallback>,
     pub snapshot: bool,
     pub snapshot_blob: Option<&'static [u8]>,
+    // Created by `Isolate::get_code_cache()` to skip the compilation of the code. V8 only
+    // checks the length of the source, so it has to come from the exact same code and
+    // environment variables
+    pub code_cache: Option<Vec<u8>>,
+    // Debug the isolate using the Chrome DevTools Protocol
+    pub inspector: Option<InspectorSession>,
+    // Directory `Lagon.fs` can read files from, disabled if None
+    pub fs_root: Option<PathBuf>,
+    // Round `performance.now()` to 100µs, to mitigate timing attacks
+    pub coarse_timers: bool,
+    // How long fetch() calls wait for the response by default, zero disables the timeout
+    pub fetch_timeout: Duration,
+    // How long idle fetch() connections are kept alive, and how many per host
+    pub fetch_pool_idle_timeout: Duration,
+    pub fetch_pool_max_idle_per_host: usize,
+    // Don't negotiate HTTP/2 for fetch() calls, e.g for debugging
+    pub fetch_http1_only: bool,
+    // The size in bytes of the HTTP cache of fetch() calls, zero disables it
+    pub fetch_cache_size: usize,
+    // Where the Cache API stores its responses, the runtime's backend if None
+    pub cache_backend: Option<Arc<dyn CacheBackend>>,
+    // Where `Lagon.kv` stores its values, disabled if None
+    pub kv_backend: Option<Arc<dyn KvBackend>>,
+    // The maximum size in bytes of a single value
+    pub kv_max_value_size: usize,
+    // Where `Lagon.queue` sends its messages, disabled if None
+    pub queue_backend: Option<Arc<dyn QueueBackend>>,
+    // The maximum size in bytes of a message, once serialized to JSON
+    pub queue_max_message_size: usize,
+    // The hosts and IPs fetch() calls can reach, all of them by default
+    pub network_policy: NetworkPolicy,
+    pub on_network_violation: Option<OnNetworkViolationCallback>,
+    // Define the `test` and `expect` globals, and run the registered
+    // tests instead of calling the exported handler
+    pub test_mode: bool,
 }
 
 unsafe impl Send for IsolateOptions {}
@@ -31,12 +100,36 @@ impl IsolateOptions {
             environment_variables: None,
             timeout: Duration::from_millis(50),
             startup_timeout: Duration::from_millis(200),
+            cpu_timeout: Duration::ZERO,
+            background_timeout: Duration::from_secs(30),
             memory: 128,
+            memory_pressure_threshold: 80,
             metadata: Rc::new(None),
             on_drop: None,
             on_statistics: None,
+            on_fetch: None,
+            on_compile: None,
+            on_memory_pressure: None,
+            hooks: Vec::new(),
             snapshot: false,
             snapshot_blob: None,
+            code_cache: None,
+            inspector: None,
+            fs_root: None,
+            coarse_timers: false,
+            fetch_timeout: Duration::ZERO,
+            fetch_pool_idle_timeout: Duration::from_secs(90),
+            fetch_pool_max_idle_per_host: 16,
+            fetch_http1_only: false,
+            fetch_cache_size: 0,
+            cache_backend: None,
+            kv_backend: None,
+            kv_max_value_size: 1024 * 1024,
+            queue_backend: None,
+            queue_max_message_size: 128 * 1024,
+            network_policy: NetworkPolicy::default(),
+            on_network_violation: None,
+            test_mode: false,
         }
     }
 
@@ -55,11 +148,26 @@ impl IsolateOptions {
         self
     }
 
+    pub fn cpu_timeout(mut self, cpu_timeout: Duration) -> Self {
+        self.cpu_timeout = cpu_timeout;
+        self
+    }
+
+    pub fn background_timeout(mut self, background_timeout: Duration) -> Self {
+        self.background_timeout = background_timeout;
+        self
+    }
+
     pub fn memory(mut self, memory: usize) -> Self {
         self.memory = memory;
         self
     }
 
+    pub fn memory_pressure_threshold(mut self, memory_pressure_threshold: usize) -> Self {
+        self.memory_pressure_threshold = memory_pressure_threshold;
+        self
+    }
+
     pub fn metadata(mut self, metadata: Metadata) -> Self {
         self.metadata = Rc::new(metadata);
         self
@@ -75,6 +183,29 @@ impl IsolateOptions {
         self
     }
 
+    pub fn on_fetch_callback(mut self, on_fetch: OnFetchCallback) -> Self {
+        self.on_fetch = Some(on_fetch);
+        self
+    }
+
+    pub fn on_compile_callback(mut self, on_compile: OnIsolateCompileCallback) -> Self {
+        self.on_compile = Some(on_compile);
+        self
+    }
+
+    pub fn hook(mut self, hook: OnIsolateHookCallback) -> Self {
+        self.hooks.push(hook);
+        self
+    }
+
+    pub fn on_memory_pressure_callback(
+        mut self,
+        on_memory_pressure: OnMemoryPressureCallback,
+    ) -> Self {
+        self.on_memory_pressure = Some(on_memory_pressure);
+        self
+    }
+
     pub fn snapshot(mut self, snapshot: bool) -> Self {
         self.snapshot = snapshot;
         self
@@ -91,6 +222,94 @@ impl IsolateOptions {
         self
     }
 
+    pub fn code_cache(mut self, code_cache: Vec<u8>) -> Self {
+        self.code_cache = Some(code_cache);
+        self
+    }
+
+    pub fn inspector(mut self, inspector: InspectorSession) -> Self {
+        self.inspector = Some(inspector);
+        self
+    }
+
+    pub fn allow_fs(mut self, fs_root: PathBuf) -> Self {
+        self.fs_root = Some(fs_root);
+        self
+    }
+
+    pub fn coarse_timers(mut self, coarse_timers: bool) -> Self {
+        self.coarse_timers = coarse_timers;
+        self
+    }
+
+    pub fn fetch_timeout(mut self, fetch_timeout: Duration) -> Self {
+        self.fetch_timeout = fetch_timeout;
+        self
+    }
+
+    pub fn fetch_pool_idle_timeout(mut self, fetch_pool_idle_timeout: Duration) -> Self {
+        self.fetch_pool_idle_timeout = fetch_pool_idle_timeout;
+        self
+    }
+
+    pub fn fetch_pool_max_idle_per_host(mut self, fetch_pool_max_idle_per_host: usize) -> Self {
+        self.fetch_pool_max_idle_per_host = fetch_pool_max_idle_per_host;
+        self
+    }
+
+    pub fn fetch_http1_only(mut self, fetch_http1_only: bool) -> Self {
+        self.fetch_http1_only = fetch_http1_only;
+        self
+    }
+
+    pub fn fetch_cache_size(mut self, fetch_cache_size: usize) -> Self {
+        self.fetch_cache_size = fetch_cache_size;
+        self
+    }
+
+    pub fn cache_backend(mut self, cache_backend: Arc<dyn CacheBackend>) -> Self {
+        self.cache_backend = Some(cache_backend);
+        self
+    }
+
+    pub fn kv_backend(mut self, kv_backend: Arc<dyn KvBackend>) -> Self {
+        self.kv_backend = Some(kv_backend);
+        self
+    }
+
+    pub fn kv_max_value_size(mut self, kv_max_value_size: usize) -> Self {
+        self.kv_max_value_size = kv_max_value_size;
+        self
+    }
+
+    pub fn queue_backend(mut self, queue_backend: Arc<dyn QueueBackend>) -> Self {
+        self.queue_backend = Some(queue_backend);
+        self
+    }
+
+    pub fn queue_max_message_size(mut self, queue_max_message_size: usize) -> Self {
+        self.queue_max_message_size = queue_max_message_size;
+        self
+    }
+
+    pub fn network_policy(mut self, network_policy: NetworkPolicy) -> Self {
+        self.network_policy = network_policy;
+        self
+    }
+
+    pub fn on_network_violation_callback(
+        mut self,
+        on_network_violation: OnNetworkViolationCallback,
+    ) -> Self {
+        self.on_network_violation = Some(on_network_violation);
+        self
+    }
+
+    pub fn test_mode(mut self, test_mode: bool) -> Self {
+        self.test_mode = test_mode;
+        self
+    }
+
     pub fn get_runtime_code<'a>(
         &self,
         scope: &mut v8::HandleScope<'a>,
@@ -100,18 +319,31 @@ impl IsolateOptions {
             environment_variables,
             snapshot,
             snapshot_blob,
+            test_mode,
             ..
         } = self;
 
         let environment_variables = match environment_variables {
-            Some(environment_variables) => environment_variables
-                .iter()
-                .map(|(k, v)| format!("globalThis.process.env.{k} = '{v}'"))
-                .collect::<Vec<String>>()
-                .join("\n"),
+            Some(environment_variables) => {
+                // Sorted so the same variables always produce the same
+                // code, which code caches rely on
+                let mut environment_variables = environment_variables
+                    .iter()
+                    .map(|(k, v)| format!("globalThis.process.env.{k} = '{v}'"))
+                    .collect::<Vec<String>>();
+                environment_variables.sort();
+                environment_variables.join("\n")
+            }
             None => "".to_string(),
         };
 
+        // The harness is evaluated before the code, which registers tests at the top level
+        let (test_harness, handler) = if *test_mode {
+            (TEST_HARNESS, "globalThis.__lagonRunTests")
+        } else {
+            ("", "handler")
+        };
+
         if snapshot_blob.is_some() {
             // If we have a snapshot, only return the isolate's code
             // and the environment variables
@@ -120,11 +352,12 @@ impl IsolateOptions {
                     scope,
                     &format!(
                         r"{environment_variables}
-{code}
-globalThis.handler = handler;"
+{test_harness}{code}
+globalThis.handler = typeof {handler} === 'undefined' ? undefined : {handler};
+globalThis.scheduled = typeof scheduled === 'undefined' ? undefined : scheduled;"
                     ),
                 ),
-                environment_variables.lines().count() + 1,
+                environment_variables.lines().count() + test_harness.lines().count() + 1,
             )
         } else if *snapshot {
             // If we are currently making a snapshot, only return
@@ -139,11 +372,15 @@ globalThis.handler = handler;"
                     &format!(
                         r"{JS_RUNTIME}
 {environment_variables}
-{code}
-globalThis.handler = handler;"
+{test_harness}{code}
+globalThis.handler = typeof {handler} === 'undefined' ? undefined : {handler};
+globalThis.scheduled = typeof scheduled === 'undefined' ? undefined : scheduled;"
                     ),
                 ),
-                JS_RUNTIME.lines().count() + environment_variables.lines().count() + 2,
+                JS_RUNTIME.lines().count()
+                    + environment_variables.lines().count()
+                    + test_harness.lines().count()
+                    + 2,
             )
         }
     }
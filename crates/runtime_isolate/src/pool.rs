@@ -0,0 +1,322 @@
+use lagon_runtime_http::{RunResult, StreamResult};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::runtime::Handle;
+
+use crate::{
+    options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest, IsolateScheduledEvent,
+};
+
+// Creates the options of each isolate of the pool. Called on the thread of the
+// isolate, so the options can contain callbacks that aren't `Send`
+pub type IsolateOptionsFactory = Arc<dyn Fn() -> IsolateOptions + Send + Sync>;
+// Called once an isolate has been evaluated, with the time it took
+pub type OnPoolEvaluateCallback = Arc<dyn Fn(&Isolate, Duration) + Send + Sync>;
+// Called once the event loop of an isolate stopped, e.g after a timeout or the memory limit
+pub type OnPoolExitCallback = Arc<dyn Fn(&mut Isolate) + Send + Sync>;
+
+pub struct IsolatePoolOptions {
+    // New isolates are only created when all the others are busy
+    pub max_isolates: usize,
+    // Recycle an isolate after this number of requests, zero disables it
+    pub max_requests: usize,
+    pub on_evaluate: Option<OnPoolEvaluateCallback>,
+    pub on_exit: Option<OnPoolExitCallback>,
+}
+
+impl IsolatePoolOptions {
+    pub fn new(max_isolates: usize) -> Self {
+        Self {
+            max_isolates: max_isolates.max(1),
+            max_requests: 0,
+            on_evaluate: None,
+            on_exit: None,
+        }
+    }
+
+    pub fn max_requests(mut self, max_requests: usize) -> Self {
+        self.max_requests = max_requests;
+        self
+    }
+
+    pub fn on_evaluate_callback(mut self, on_evaluate: OnPoolEvaluateCallback) -> Self {
+        self.on_evaluate = Some(on_evaluate);
+        self
+    }
+
+    pub fn on_exit_callback(mut self, on_exit: OnPoolExitCallback) -> Self {
+        self.on_exit = Some(on_exit);
+        self
+    }
+}
+
+struct Worker {
+    id: usize,
+    // None once the worker is retired: it stops after its remaining events
+    tx: Option<flume::Sender<IsolateEvent>>,
+    // Kept to send the events the isolate didn't handle to another worker
+    rx: flume::Receiver<IsolateEvent>,
+    // Requests sent to the isolate that didn't receive their response yet
+    in_flight: usize,
+    requests: usize,
+}
+
+// Dispatches the events of a single channel to multiple isolates created from
+// the same options, so a slow request doesn't block the ones behind it. Used
+// like an isolate: send events to the other side of `rx`, and run the pool
+// until all the senders are dropped.
+pub struct IsolatePool {
+    factory: IsolateOptionsFactory,
+    options: IsolatePoolOptions,
+    rx: flume::Receiver<IsolateEvent>,
+    workers: Vec<Worker>,
+    next_id: usize,
+    // Sent by the forwarders once a request received its response
+    done_tx: flume::Sender<usize>,
+    done_rx: flume::Receiver<usize>,
+    // Sent by the threads of the isolates when they stop
+    exit_tx: flume::Sender<usize>,
+    exit_rx: flume::Receiver<usize>,
+}
+
+// The last result sent for a request, except for streams which end with `Done`
+fn is_final_result(result: &RunResult) -> bool {
+    !matches!(
+        result,
+        RunResult::Stream(StreamResult::Start(_)) | RunResult::Stream(StreamResult::Data(_))
+    )
+}
+
+impl IsolatePool {
+    pub fn new(
+        factory: IsolateOptionsFactory,
+        options: IsolatePoolOptions,
+        rx: flume::Receiver<IsolateEvent>,
+    ) -> Self {
+        let (done_tx, done_rx) = flume::unbounded();
+        let (exit_tx, exit_rx) = flume::unbounded();
+
+        IsolatePool {
+            factory,
+            options,
+            rx,
+            workers: Vec::new(),
+            next_id: 0,
+            done_tx,
+            done_rx,
+            exit_tx,
+            exit_rx,
+        }
+    }
+
+    fn isolates_count(&self) -> usize {
+        self.workers
+            .iter()
+            .filter(|worker| worker.tx.is_some())
+            .count()
+    }
+
+    fn spawn_worker(&mut self) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let (tx, rx) = flume::unbounded();
+        let handle = Handle::current();
+        let factory = Arc::clone(&self.factory);
+        let on_evaluate = self.options.on_evaluate.clone();
+        let on_exit = self.options.on_exit.clone();
+        let exit_tx = self.exit_tx.clone();
+        let isolate_rx = rx.clone();
+
+        std::thread::spawn(move || {
+            handle.block_on(async move {
+                let mut isolate = Isolate::new(factory(), isolate_rx);
+                let start_time = Instant::now();
+
+                isolate.evaluate();
+
+                if let Some(on_evaluate) = &on_evaluate {
+                    on_evaluate(&isolate, start_time.elapsed());
+                }
+
+                isolate.run_event_loop().await;
+
+                if let Some(on_exit) = &on_exit {
+                    on_exit(&mut isolate);
+                }
+            });
+
+            exit_tx.send(id).unwrap_or(());
+        });
+
+        self.workers.push(Worker {
+            id,
+            tx: Some(tx),
+            rx,
+            in_flight: 0,
+            requests: 0,
+        });
+
+        id
+    }
+
+    // An idle isolate, otherwise a new one if the pool isn't full,
+    // otherwise the isolate with the fewest requests in flight
+    fn acquire_worker(&mut self) -> usize {
+        if let Some(worker) = self
+            .workers
+            .iter()
+            .find(|worker| worker.tx.is_some() && worker.in_flight == 0)
+        {
+            return worker.id;
+        }
+
+        if self.isolates_count() < self.options.max_isolates {
+            return self.spawn_worker();
+        }
+
+        self.workers
+            .iter()
+            .filter(|worker| worker.tx.is_some())
+            .min_by_key(|worker| worker.in_flight)
+            .map(|worker| worker.id)
+            .unwrap()
+    }
+
+    fn worker_mut(&mut self, id: usize) -> Option<&mut Worker> {
+        self.workers.iter_mut().find(|worker| worker.id == id)
+    }
+
+    // Results are forwarded to the original sender, to know when the
+    // isolate is done with the request
+    fn forward_results(
+        &self,
+        id: usize,
+        sender: flume::Sender<RunResult>,
+    ) -> flume::Sender<RunResult> {
+        let (tx, rx) = flume::unbounded();
+        let done_tx = self.done_tx.clone();
+
+        tokio::spawn(async move {
+            while let Ok(result) = rx.recv_async().await {
+                let is_final = is_final_result(&result);
+
+                // Dropping the receiver shows the isolate that the client went away
+                if sender.send_async(result).await.is_err() || is_final {
+                    break;
+                }
+            }
+
+            drop(rx);
+            done_tx.send_async(id).await.unwrap_or(());
+        });
+
+        tx
+    }
+
+    fn send_request(&mut self, mut event: IsolateEvent) {
+        let id = self.acquire_worker();
+
+        match &mut event {
+            IsolateEvent::Request(IsolateRequest { sender, .. })
+            | IsolateEvent::Scheduled(IsolateScheduledEvent { sender, .. }) => {
+                let forwarded = self.forward_results(id, sender.clone());
+                *sender = forwarded;
+            }
+            _ => {}
+        }
+
+        let max_requests = self.options.max_requests;
+        let worker = self.worker_mut(id).unwrap();
+
+        worker.in_flight += 1;
+        worker.requests += 1;
+
+        if let Some(tx) = &worker.tx {
+            tx.send(event).unwrap_or(());
+        }
+
+        if max_requests > 0 && worker.requests >= max_requests {
+            worker.tx = None;
+        }
+    }
+
+    fn dispatch(&mut self, event: IsolateEvent) {
+        match event {
+            IsolateEvent::Request(_) | IsolateEvent::Scheduled(_) => self.send_request(event),
+            // The next requests are sent to new isolates
+            IsolateEvent::Terminate(reason) => {
+                for worker in &mut self.workers {
+                    if let Some(tx) = worker.tx.take() {
+                        tx.send(IsolateEvent::Terminate(reason.clone()))
+                            .unwrap_or(());
+                    }
+                }
+            }
+            IsolateEvent::HeapStatistics(sender) => {
+                let id = match self.workers.iter().find(|worker| worker.tx.is_some()) {
+                    Some(worker) => worker.id,
+                    None => self.spawn_worker(),
+                };
+
+                if let Some(tx) = &self.worker_mut(id).unwrap().tx {
+                    tx.send(IsolateEvent::HeapStatistics(sender)).unwrap_or(());
+                }
+            }
+        }
+    }
+
+    fn handle_exit(&mut self, id: usize) -> Vec<IsolateEvent> {
+        match self.workers.iter().position(|worker| worker.id == id) {
+            // The isolate might have been terminated before handling all its events
+            Some(index) => self
+                .workers
+                .remove(index)
+                .rx
+                .drain()
+                .filter(|event| !matches!(event, IsolateEvent::Terminate(_)))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Runs until all the senders of `rx` are dropped and every isolate stopped
+    pub async fn run(mut self) {
+        // Requests are then queued in this isolate until it's evaluated
+        self.spawn_worker();
+
+        loop {
+            tokio::select! {
+                event = self.rx.recv_async() => match event {
+                    Ok(event) => self.dispatch(event),
+                    Err(_) => break,
+                },
+                Ok(id) = self.done_rx.recv_async() => {
+                    if let Some(worker) = self.worker_mut(id) {
+                        worker.in_flight = worker.in_flight.saturating_sub(1);
+                    }
+                }
+                Ok(id) = self.exit_rx.recv_async() => {
+                    for event in self.handle_exit(id) {
+                        self.dispatch(event);
+                    }
+                }
+            }
+        }
+
+        for worker in &mut self.workers {
+            worker.tx.take();
+        }
+
+        while !self.workers.is_empty() {
+            match self.exit_rx.recv_async().await {
+                Ok(id) => {
+                    self.handle_exit(id);
+                }
+                Err(_) => break,
+            }
+        }
+    }
+}
@@ -0,0 +1,359 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::{
+    client::{
+        connect::{Connected, Connection},
+        HttpConnector,
+    },
+    service::Service,
+    Uri,
+};
+use lazy_static::lazy_static;
+use log::error;
+use percent_encoding::percent_decode_str;
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    sync::{Arc, RwLock},
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::TcpStream,
+};
+use url::Url;
+
+use crate::network_policy::PolicyResolver;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+// The response to CONNECT only has a status line and a few headers
+const MAX_CONNECT_RESPONSE_SIZE: usize = 8 * 1024;
+
+lazy_static! {
+    // Set by the runtime's options, or read from the environment variables
+    static ref PROXY: RwLock<Option<Arc<Proxy>>> = RwLock::new(None);
+}
+
+// The HTTP proxies fetch() calls go through. Requests to http:// URLs are
+// forwarded to the proxy, and https:// URLs are tunneled with CONNECT
+#[derive(Debug, Clone, Default)]
+pub struct Proxy {
+    pub http: Option<Url>,
+    pub https: Option<Url>,
+    // Hosts that are never proxied, including their subdomains. `*` matches all hosts
+    pub no_proxy: Vec<String>,
+}
+
+fn read_env(names: [&str; 2]) -> Option<String> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .filter(|value| !value.is_empty())
+}
+
+fn parse_proxy_url(value: &str) -> Option<Url> {
+    // Proxies are often set without a scheme, e.g `proxy.example.com:8080`
+    let value = if value.contains("://") {
+        value.to_string()
+    } else {
+        format!("http://{value}")
+    };
+
+    match Url::parse(&value) {
+        Ok(url) if url.scheme() == "http" => Some(url),
+        Ok(url) => {
+            error!(
+                "Unsupported proxy scheme {}, only http is supported",
+                url.scheme()
+            );
+            None
+        }
+        Err(error) => {
+            error!("Invalid proxy URL {}: {}", value, error);
+            None
+        }
+    }
+}
+
+impl Proxy {
+    // Read the HTTP_PROXY, HTTPS_PROXY and NO_PROXY environment variables of
+    // the process (or their lowercase variants)
+    pub fn from_env() -> Self {
+        Self {
+            http: read_env(["HTTP_PROXY", "http_proxy"]).and_then(|value| parse_proxy_url(&value)),
+            https: read_env(["HTTPS_PROXY", "https_proxy"])
+                .and_then(|value| parse_proxy_url(&value)),
+            no_proxy: read_env(["NO_PROXY", "no_proxy"])
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(|host| host.trim().to_string())
+                        .filter(|host| !host.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    // Use the same proxy for all the requests
+    pub fn all(url: Url) -> Self {
+        Self {
+            http: Some(url.clone()),
+            https: Some(url),
+            no_proxy: Vec::new(),
+        }
+    }
+
+    pub fn no_proxy(mut self, no_proxy: Vec<String>) -> Self {
+        self.no_proxy = no_proxy;
+        self
+    }
+
+    fn is_bypassed(&self, host: &str) -> bool {
+        let host = host.to_lowercase();
+
+        self.no_proxy.iter().any(|pattern| {
+            let pattern = pattern.to_lowercase();
+            let pattern = pattern.trim_start_matches("*.").trim_start_matches('.');
+
+            pattern == "*"
+                || host == pattern
+                || host
+                    .strip_suffix(pattern)
+                    .map_or(false, |subdomain| subdomain.ends_with('.'))
+        })
+    }
+
+    // The proxy to use for this URL, if any
+    pub fn for_uri(&self, uri: &Uri) -> Option<&Url> {
+        let proxy = match uri.scheme_str() {
+            Some("https") => self.https.as_ref(),
+            _ => self.http.as_ref(),
+        };
+
+        match uri.host() {
+            Some(host) if !self.is_bypassed(host.trim_matches(|c| c == '[' || c == ']')) => proxy,
+            _ => None,
+        }
+    }
+
+    // The Proxy-Authorization header to send with this request, when it's forwarded
+    // to a proxy with credentials. Tunneled requests are authenticated with CONNECT
+    pub fn forward_authorization(&self, uri: &Uri) -> Option<String> {
+        match uri.scheme_str() {
+            Some("http") => self.for_uri(uri).and_then(authorization),
+            _ => None,
+        }
+    }
+}
+
+// The credentials set in the proxy's URL, for the Proxy-Authorization header
+fn authorization(proxy: &Url) -> Option<String> {
+    if proxy.username().is_empty() {
+        return None;
+    }
+
+    let username = percent_decode_str(proxy.username()).decode_utf8_lossy();
+    let password = percent_decode_str(proxy.password().unwrap_or("")).decode_utf8_lossy();
+
+    Some(format!(
+        "Basic {}",
+        STANDARD.encode(format!("{username}:{password}"))
+    ))
+}
+
+// Use this proxy instead of reading the environment variables. Isolates
+// created before calling it keep using the previous one
+pub fn set_proxy(proxy: Proxy) {
+    *PROXY.write().unwrap() = Some(Arc::new(proxy));
+}
+
+pub fn get_proxy() -> Arc<Proxy> {
+    if let Some(proxy) = PROXY.read().unwrap().as_ref() {
+        return Arc::clone(proxy);
+    }
+
+    let mut proxy = PROXY.write().unwrap();
+    Arc::clone(proxy.get_or_insert_with(|| Arc::new(Proxy::from_env())))
+}
+
+// A connection to the server, or to the proxy. Requests sent over a connection
+// forwarded to the proxy use the absolute-form, e.g `GET http://example.com/ HTTP/1.1`
+pub struct ProxyStream {
+    stream: TcpStream,
+    forwarded: bool,
+}
+
+impl Connection for ProxyStream {
+    fn connected(&self) -> Connected {
+        self.stream.connected().proxy(self.forwarded)
+    }
+}
+
+impl AsyncRead for ProxyStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ProxyStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+// Open a tunnel to the server through the proxy
+// https://www.rfc-editor.org/rfc/rfc9110#section-9.3.6
+async fn tunnel(mut stream: TcpStream, uri: &Uri, proxy: &Url) -> Result<TcpStream, BoxError> {
+    let host = uri.host().ok_or("The URL has no host")?;
+    let port = uri.port_u16().unwrap_or(443);
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+
+    if let Some(authorization) = authorization(proxy) {
+        request += &format!("Proxy-Authorization: {authorization}\r\n");
+    }
+
+    request += "\r\n";
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::new();
+    let mut buf = [0; 1024];
+
+    while !response.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buf).await?;
+
+        if read == 0 || response.len() + read > MAX_CONNECT_RESPONSE_SIZE {
+            return Err("The proxy closed the connection while opening the tunnel".into());
+        }
+
+        response.extend_from_slice(&buf[..read]);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+
+    match status_line.split(' ').nth(1) {
+        Some(status) if status.starts_with('2') => Ok(stream),
+        _ => Err(format!("The proxy refused to open a tunnel: {status_line}").into()),
+    }
+}
+
+// Connect to the server directly, or through the proxy. The proxy connects to
+// the server itself, so the server's host is checked by the network policy
+// before asking the proxy to connect to it
+#[derive(Debug, Clone)]
+pub struct ProxyConnector {
+    direct: HttpConnector<PolicyResolver>,
+    to_proxy: HttpConnector,
+    resolver: PolicyResolver,
+    proxy: Arc<Proxy>,
+}
+
+impl ProxyConnector {
+    pub fn new(
+        direct: HttpConnector<PolicyResolver>,
+        resolver: PolicyResolver,
+        proxy: Arc<Proxy>,
+    ) -> Self {
+        Self {
+            direct,
+            to_proxy: HttpConnector::new(),
+            resolver,
+            proxy,
+        }
+    }
+
+    // Connections that aren't made of HTTP requests (e.g WebSockets) can't be
+    // forwarded to the proxy, so they are always tunneled when proxied
+    pub async fn connect_tunneled(&self, uri: Uri) -> Result<TcpStream, BoxError> {
+        let proxy = match self.proxy.for_uri(&uri) {
+            Some(proxy) => proxy.clone(),
+            None => return Ok(self.direct.clone().call(uri).await?),
+        };
+
+        self.resolver
+            .check(uri.host().ok_or("The URL has no host")?)
+            .await?;
+
+        let stream = self.to_proxy.clone().call(proxy_uri(&proxy)?).await?;
+        tunnel(stream, &uri, &proxy).await
+    }
+}
+
+fn proxy_uri(proxy: &Url) -> Result<Uri, BoxError> {
+    Ok(Uri::builder()
+        .scheme("http")
+        .authority(format!(
+            "{}:{}",
+            proxy.host_str().unwrap_or_default(),
+            proxy.port_or_known_default().unwrap_or(80)
+        ))
+        .path_and_query("/")
+        .build()?)
+}
+
+impl Service<Uri> for ProxyConnector {
+    type Response = ProxyStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.direct.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let proxy = match self.proxy.for_uri(&uri) {
+            Some(proxy) => proxy.clone(),
+            None => {
+                let connecting = self.direct.call(uri);
+
+                return Box::pin(async move {
+                    Ok(ProxyStream {
+                        stream: connecting.await?,
+                        forwarded: false,
+                    })
+                });
+            }
+        };
+
+        let mut to_proxy = self.to_proxy.clone();
+        let resolver = self.resolver.clone();
+
+        Box::pin(async move {
+            resolver
+                .check(uri.host().ok_or("The URL has no host")?)
+                .await?;
+
+            let stream = to_proxy.call(proxy_uri(&proxy)?).await?;
+
+            if uri.scheme_str() == Some("https") {
+                Ok(ProxyStream {
+                    stream: tunnel(stream, &uri, &proxy).await?,
+                    forwarded: false,
+                })
+            } else {
+                Ok(ProxyStream {
+                    stream,
+                    forwarded: true,
+                })
+            }
+        })
+    }
+}
@@ -0,0 +1,12 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::fmt::Debug;
+
+// The messages of `Lagon.queue.send()`, sent to a broker that delivers them
+// later to the consumer, e.g the same Function with an `x-lagon-queue` header.
+// Sending resolves once the broker accepted the message, not once it's delivered
+#[async_trait]
+pub trait QueueBackend: Debug + Send + Sync {
+    // The message is already serialized to JSON
+    async fn send(&self, queue: &str, message: String) -> Result<()>;
+}
@@ -0,0 +1,113 @@
+(() => {
+  const tests = [];
+
+  class AssertionError extends Error {
+    constructor(message) {
+      super(message);
+      this.name = 'AssertionError';
+    }
+  }
+
+  const format = value => {
+    if (typeof value === 'function') {
+      return value.name ? `[Function ${value.name}]` : '[Function]';
+    }
+
+    try {
+      return JSON.stringify(value) ?? String(value);
+    } catch {
+      return String(value);
+    }
+  };
+
+  const isEqual = (a, b) => {
+    if (Object.is(a, b)) {
+      return true;
+    }
+
+    if (typeof a !== 'object' || typeof b !== 'object' || a === null || b === null) {
+      return false;
+    }
+
+    if (Array.isArray(a) !== Array.isArray(b)) {
+      return false;
+    }
+
+    const keys = Object.keys(a);
+
+    return keys.length === Object.keys(b).length && keys.every(key => isEqual(a[key], b[key]));
+  };
+
+  const matchers = (actual, not) => {
+    const assert = (pass, message) => {
+      if (pass === not) {
+        throw new AssertionError(`Expected ${format(actual)} ${not ? 'not ' : ''}${message}`);
+      }
+    };
+
+    return {
+      toBe: expected => assert(Object.is(actual, expected), `to be ${format(expected)}`),
+      toEqual: expected => assert(isEqual(actual, expected), `to equal ${format(expected)}`),
+      toBeTruthy: () => assert(!!actual, 'to be truthy'),
+      toBeFalsy: () => assert(!actual, 'to be falsy'),
+      toBeNull: () => assert(actual === null, 'to be null'),
+      toBeUndefined: () => assert(actual === undefined, 'to be undefined'),
+      toBeDefined: () => assert(actual !== undefined, 'to be defined'),
+      toBeGreaterThan: expected => assert(actual > expected, `to be greater than ${format(expected)}`),
+      toBeLessThan: expected => assert(actual < expected, `to be less than ${format(expected)}`),
+      toContain: expected => assert(actual.includes(expected), `to contain ${format(expected)}`),
+      toThrow: expected => {
+        let error;
+
+        try {
+          actual();
+        } catch (thrown) {
+          error = thrown ?? new Error();
+        }
+
+        const message = error instanceof Error ? error.message : String(error);
+
+        assert(
+          error !== undefined && (expected === undefined || message.includes(expected)),
+          expected === undefined ? 'to throw' : `to throw ${format(expected)}`,
+        );
+      },
+    };
+  };
+
+  globalThis.test = (name, fn) => {
+    tests.push({ name, fn });
+  };
+
+  globalThis.expect = actual => ({ ...matchers(actual, false), not: matchers(actual, true) });
+
+  // Used as the handler in test mode: tests run one after the other, and
+  // only the ones whose name contains the `filter` query parameter
+  globalThis.__lagonRunTests = async request => {
+    const filter = new URL(request.url).searchParams.get('filter');
+    const results = [];
+
+    for (const { name, fn } of tests) {
+      if (filter && !name.includes(filter)) {
+        results.push({ name, status: 'skipped', duration: 0 });
+        continue;
+      }
+
+      const start = Date.now();
+
+      try {
+        await fn();
+        results.push({ name, status: 'passed', duration: Date.now() - start });
+      } catch (error) {
+        results.push({
+          name,
+          status: 'failed',
+          duration: Date.now() - start,
+          error: error instanceof Error ? error.message : String(error),
+        });
+      }
+    }
+
+    return new Response(JSON.stringify(results), { headers: { 'content-type': 'application/json' } });
+  };
+})();
@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use log::{error, warn};
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    ClientConfig, RootCertStore, ServerName,
+};
+use std::{
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+pub use rustls::Certificate;
+
+lazy_static! {
+    // Loading the system's root certificates is slow, so it's only done once
+    static ref TLS_CONFIG: RwLock<Option<ClientConfig>> = RwLock::new(None);
+}
+
+// How fetch() verifies the certificates of the servers
+#[derive(Debug, Clone, Default)]
+pub struct TlsOptions {
+    // Trusted in addition to the system's root certificates, e.g a company's CA
+    pub extra_root_certificates: Vec<Certificate>,
+    // Accept any certificate, which should only be used in development
+    pub danger_accept_invalid_certificates: bool,
+}
+
+struct AnyServerCertVerifier;
+
+impl ServerCertVerifier for AnyServerCertVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+fn add_root_certificates(
+    root_store: &mut RootCertStore,
+    certificates: &[Certificate],
+) -> Result<()> {
+    for (index, certificate) in certificates.iter().enumerate() {
+        root_store
+            .add(certificate)
+            .map_err(|error| anyhow!("Invalid root certificate #{}: {}", index + 1, error))?;
+    }
+
+    Ok(())
+}
+
+// Unlike the system's root certificates, invalid extra ones are an error
+fn create_root_store(extra_root_certificates: &[Certificate]) -> Result<RootCertStore> {
+    let mut root_store = RootCertStore::empty();
+
+    match rustls_native_certs::load_native_certs() {
+        Ok(certificates) => {
+            for certificate in certificates {
+                root_store.add(&Certificate(certificate.0)).unwrap_or(());
+            }
+        }
+        Err(error) => error!("Could not load the root certificates: {}", error),
+    }
+
+    add_root_certificates(&mut root_store, extra_root_certificates)?;
+
+    Ok(root_store)
+}
+
+fn create_tls_config(options: &TlsOptions) -> Result<ClientConfig> {
+    let builder = ClientConfig::builder().with_safe_defaults();
+
+    if options.danger_accept_invalid_certificates {
+        warn!(
+            "The TLS certificates of fetch() calls are not verified, never do this in production"
+        );
+
+        return Ok(builder
+            .with_custom_certificate_verifier(Arc::new(AnyServerCertVerifier))
+            .with_no_client_auth());
+    }
+
+    Ok(builder
+        .with_root_certificates(create_root_store(&options.extra_root_certificates)?)
+        .with_no_client_auth())
+}
+
+// Check that the certificates can be trusted before starting anything, instead
+// of failing on the first fetch() call
+pub fn validate_root_certificates(certificates: &[Certificate]) -> Result<()> {
+    add_root_certificates(&mut RootCertStore::empty(), certificates)
+}
+
+// Replace the TLS config of the isolates created afterward
+pub fn set_tls_options(options: TlsOptions) -> Result<()> {
+    *TLS_CONFIG.write().unwrap() = Some(create_tls_config(&options)?);
+
+    Ok(())
+}
+
+pub fn get_tls_config() -> ClientConfig {
+    if let Some(config) = TLS_CONFIG.read().unwrap().as_ref() {
+        return config.clone();
+    }
+
+    let mut config = TLS_CONFIG.write().unwrap();
+
+    config
+        .get_or_insert_with(|| {
+            // Without extra certificates, this can't fail
+            create_tls_config(&TlsOptions::default()).unwrap()
+        })
+        .clone()
+}
@@ -1,22 +1,195 @@
 use anyhow::Result;
-use hyper::body::Bytes;
+use hyper::{body::Bytes, header::ACCEPT, HeaderMap, Method};
 use lagon_runtime_http::Response;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fs,
     path::{Path, PathBuf},
 };
 
-pub fn find_asset<'a>(url: &'a str, assets: &'a HashSet<String>) -> Option<&'a String> {
-    // Remove the leading '/' from the url
-    let url = &url[1..];
+// How urls that don't exactly match an asset are resolved
+#[derive(Debug, Clone, Copy)]
+pub struct AssetResolution {
+    // `/docs` and `/docs/` serve `docs/index.html`
+    pub index_html: bool,
+    // `/about` serves `about.html`
+    pub html_extension: bool,
+}
 
-    assets.iter().find(|asset| {
-        **asset == url
-            || asset.replace(".html", "") == url
-            || asset.replace("/index.html", "") == url
-            || asset.replace("index.html", "") == url
-    })
+impl Default for AssetResolution {
+    fn default() -> Self {
+        Self {
+            index_html: true,
+            html_extension: true,
+        }
+    }
+}
+
+fn decode_url(url: &str) -> String {
+    let bytes = url.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let hex = match bytes[index] {
+            b'%' => bytes
+                .get(index + 1..index + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).unwrap_or_else(|_| url.to_string())
+}
+
+pub fn find_asset<'a>(
+    url: &str,
+    assets: &'a HashSet<String>,
+    resolution: AssetResolution,
+) -> Option<&'a String> {
+    // Remove the query string, the fragment and the leading '/' from the url
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let path = decode_url(path.strip_prefix('/').unwrap_or(path));
+
+    let mut candidates = Vec::with_capacity(3);
+
+    match path.strip_suffix('/') {
+        // Only directories can end with a trailing slash
+        Some(directory) => {
+            if resolution.index_html {
+                candidates.push(format!("{directory}/index.html"));
+            }
+        }
+        None if path.is_empty() => {
+            if resolution.index_html {
+                candidates.push("index.html".into());
+            }
+        }
+        None => {
+            candidates.push(path.clone());
+
+            if resolution.index_html {
+                candidates.push(format!("{path}/index.html"));
+            }
+
+            if resolution.html_extension {
+                candidates.push(format!("{path}.html"));
+            }
+        }
+    }
+
+    candidates
+        .iter()
+        .find_map(|candidate| assets.get(candidate.trim_start_matches('/')))
+}
+
+// Requests that don't match any asset can be served a fallback asset (e.g `index.html`
+// for single-page apps), but only for pages: other methods and requests that don't
+// accept HTML (e.g API calls) still reach the handler
+pub fn find_fallback_asset<'a>(
+    fallback: Option<&str>,
+    method: &Method,
+    headers: &HeaderMap,
+    assets: &'a HashSet<String>,
+) -> Option<&'a String> {
+    let accepts_html = headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .map_or(false, |accept| accept.contains("text/html"));
+
+    match fallback {
+        Some(fallback) if method == Method::GET && accepts_html => assets.get(fallback),
+        _ => None,
+    }
+}
+
+// Find the entries directly inside the directory the url points to, with a trailing
+// `/` for sub-directories. Since entries only come from the list of assets, a
+// listing never contains anything outside of the public directory.
+pub fn find_directory_entries(url: &str, assets: &HashSet<String>) -> Option<Vec<String>> {
+    let mut directory = String::new();
+
+    for segment in url.split('/').filter(|segment| !segment.is_empty()) {
+        if segment == "." || segment == ".." {
+            return None;
+        }
+
+        directory.push_str(segment);
+        directory.push('/');
+    }
+
+    let entries = assets
+        .iter()
+        .filter_map(|asset| asset.strip_prefix(&directory))
+        .map(|entry| match entry.split_once('/') {
+            Some((directory, _)) => format!("{directory}/"),
+            None => entry.to_string(),
+        })
+        .collect::<BTreeSet<String>>();
+
+    if entries.is_empty() {
+        None
+    } else {
+        Some(entries.into_iter().collect())
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn handle_directory_listing(url: &str, entries: &[String]) -> Response {
+    let directory = url.trim_end_matches('/');
+    let mut links = entries
+        .iter()
+        .map(|entry| {
+            let entry = escape_html(entry);
+
+            format!(
+                "<li><a href=\"{}/{entry}\">{entry}</a></li>",
+                escape_html(directory)
+            )
+        })
+        .collect::<Vec<String>>();
+
+    if !directory.is_empty() {
+        let parent = directory.rsplit_once('/').map_or("", |(parent, _)| parent);
+        links.insert(
+            0,
+            format!("<li><a href=\"{}/\">../</a></li>", escape_html(parent)),
+        );
+    }
+
+    let title = escape_html(&format!("{directory}/"));
+    let body = format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>Index of {title}</title></head><body><h1>Index of {title}</h1><ul>{}</ul></body></html>",
+        links.join("")
+    );
+
+    let mut headers = HashMap::with_capacity(1);
+    headers.insert("content-type".into(), vec!["text/html".into()]);
+
+    Response {
+        status: 200,
+        headers: Some(headers),
+        body: Bytes::from(body),
+    }
 }
 
 pub fn handle_asset(root: PathBuf, asset: &String) -> Result<Response> {
@@ -53,6 +226,10 @@ pub fn handle_asset(root: PathBuf, asset: &String) -> Result<Response> {
 mod tests {
     use super::*;
 
+    fn find_asset_default<'a>(url: &str, assets: &'a HashSet<String>) -> Option<&'a String> {
+        find_asset(url, assets, AssetResolution::default())
+    }
+
     #[test]
     fn find_asset_literal() {
         let assets = vec![
@@ -64,14 +241,17 @@ mod tests {
         .into_iter()
         .collect::<HashSet<String>>();
 
-        assert_eq!(find_asset("/", &assets), Some(&"index.html".into()));
-        assert_eq!(find_asset("/about", &assets), Some(&"about.html".into()));
+        assert_eq!(find_asset_default("/", &assets), Some(&"index.html".into()));
+        assert_eq!(
+            find_asset_default("/about", &assets),
+            Some(&"about.html".into())
+        );
         assert_eq!(
-            find_asset("/hello", &assets),
+            find_asset_default("/hello", &assets),
             Some(&"hello/index.html".into())
         );
         assert_eq!(
-            find_asset("/hello/world", &assets),
+            find_asset_default("/hello/world", &assets),
             Some(&"hello/world.html".into())
         );
     }
@@ -88,23 +268,180 @@ mod tests {
         .collect::<HashSet<String>>();
 
         assert_eq!(
-            find_asset("/index.html", &assets),
+            find_asset_default("/index.html", &assets),
             Some(&"index.html".into())
         );
         assert_eq!(
-            find_asset("/about.html", &assets),
+            find_asset_default("/about.html", &assets),
             Some(&"about.html".into())
         );
         assert_eq!(
-            find_asset("/hello/index.html", &assets),
+            find_asset_default("/hello/index.html", &assets),
             Some(&"hello/index.html".into())
         );
         assert_eq!(
-            find_asset("/hello/world.html", &assets),
+            find_asset_default("/hello/world.html", &assets),
             Some(&"hello/world.html".into())
         );
     }
 
+    #[test]
+    fn find_asset_trailing_slash() {
+        let assets = vec![
+            "index.html".into(),
+            "about.html".into(),
+            "docs/index.html".into(),
+        ]
+        .into_iter()
+        .collect::<HashSet<String>>();
+
+        assert_eq!(
+            find_asset_default("/docs", &assets),
+            Some(&"docs/index.html".into())
+        );
+        assert_eq!(
+            find_asset_default("/docs/", &assets),
+            Some(&"docs/index.html".into())
+        );
+        assert_eq!(find_asset_default("/about/", &assets), None);
+        assert_eq!(find_asset_default("/about.html/", &assets), None);
+    }
+
+    #[test]
+    fn find_asset_query_string() {
+        let assets = vec!["index.html".into(), "about.html".into(), "app.js".into()]
+            .into_iter()
+            .collect::<HashSet<String>>();
+
+        assert_eq!(
+            find_asset_default("/?page=1", &assets),
+            Some(&"index.html".into())
+        );
+        assert_eq!(
+            find_asset_default("/about?page=1#top", &assets),
+            Some(&"about.html".into())
+        );
+        assert_eq!(
+            find_asset_default("/app.js?v=2", &assets),
+            Some(&"app.js".into())
+        );
+    }
+
+    #[test]
+    fn find_asset_encoded() {
+        let assets = vec![
+            "hello world.html".into(),
+            "100%.txt".into(),
+            "café.txt".into(),
+        ]
+        .into_iter()
+        .collect::<HashSet<String>>();
+
+        assert_eq!(
+            find_asset_default("/hello%20world", &assets),
+            Some(&"hello world.html".into())
+        );
+        assert_eq!(
+            find_asset_default("/100%25.txt", &assets),
+            Some(&"100%.txt".into())
+        );
+        assert_eq!(
+            find_asset_default("/caf%C3%A9.txt", &assets),
+            Some(&"café.txt".into())
+        );
+        assert_eq!(find_asset_default("/hello%2", &assets), None);
+    }
+
+    #[test]
+    fn find_asset_resolution() {
+        let assets = vec!["about.html".into(), "docs/index.html".into()]
+            .into_iter()
+            .collect::<HashSet<String>>();
+        let exact = AssetResolution {
+            index_html: false,
+            html_extension: false,
+        };
+
+        assert_eq!(find_asset("/about", &assets, exact), None);
+        assert_eq!(find_asset("/docs/", &assets, exact), None);
+        assert_eq!(
+            find_asset("/docs/index.html", &assets, exact),
+            Some(&"docs/index.html".into())
+        );
+        assert_eq!(
+            find_asset(
+                "/about",
+                &assets,
+                AssetResolution {
+                    html_extension: true,
+                    ..exact
+                }
+            ),
+            Some(&"about.html".into())
+        );
+    }
+
+    #[test]
+    fn find_fallback_asset_accept() {
+        let assets = vec!["index.html".into(), "app.js".into()]
+            .into_iter()
+            .collect::<HashSet<String>>();
+
+        let mut html = HeaderMap::new();
+        html.insert(ACCEPT, "text/html,*/*;q=0.8".parse().unwrap());
+
+        let mut json = HeaderMap::new();
+        json.insert(ACCEPT, "application/json".parse().unwrap());
+
+        assert_eq!(
+            find_fallback_asset(Some("index.html"), &Method::GET, &html, &assets),
+            Some(&"index.html".into())
+        );
+        assert_eq!(
+            find_fallback_asset(Some("index.html"), &Method::POST, &html, &assets),
+            None
+        );
+        assert_eq!(
+            find_fallback_asset(Some("index.html"), &Method::GET, &json, &assets),
+            None
+        );
+        assert_eq!(
+            find_fallback_asset(Some("index.html"), &Method::GET, &HeaderMap::new(), &assets),
+            None
+        );
+        assert_eq!(
+            find_fallback_asset(Some("404.html"), &Method::GET, &html, &assets),
+            None
+        );
+        assert_eq!(
+            find_fallback_asset(None, &Method::GET, &html, &assets),
+            None
+        );
+    }
+
+    #[test]
+    fn find_directory_entries_nested() {
+        let assets = vec![
+            "about.html".into(),
+            "hello/world.html".into(),
+            "hello/nested/file.txt".into(),
+        ]
+        .into_iter()
+        .collect::<HashSet<String>>();
+
+        assert_eq!(
+            find_directory_entries("/", &assets),
+            Some(vec!["about.html".into(), "hello/".into()])
+        );
+        assert_eq!(
+            find_directory_entries("/hello/", &assets),
+            Some(vec!["nested/".into(), "world.html".into()])
+        );
+        assert_eq!(find_directory_entries("/about", &assets), None);
+        assert_eq!(find_directory_entries("/hello/../..", &assets), None);
+        assert_eq!(find_directory_entries("/hello/./nested", &assets), None);
+    }
+
     #[test]
     fn find_asset_none() {
         let assets = vec![
@@ -115,11 +452,11 @@ mod tests {
         .into_iter()
         .collect::<HashSet<String>>();
 
-        assert_eq!(find_asset("/", &assets), None);
-        assert_eq!(find_asset("/index", &assets), None);
-        assert_eq!(find_asset("/index.html", &assets), None);
-        assert_eq!(find_asset("/about2", &assets), None);
-        assert_eq!(find_asset("/hello/none", &assets), None);
-        assert_eq!(find_asset("/hello/world/none", &assets), None);
+        assert_eq!(find_asset_default("/", &assets), None);
+        assert_eq!(find_asset_default("/index", &assets), None);
+        assert_eq!(find_asset_default("/index.html", &assets), None);
+        assert_eq!(find_asset_default("/about2", &assets), None);
+        assert_eq!(find_asset_default("/hello/none", &assets), None);
+        assert_eq!(find_asset_default("/hello/world/none", &assets), None);
     }
 }
@@ -0,0 +1,226 @@
+use anyhow::Result;
+use flate2::{write::GzEncoder, Compression};
+use hyper::{header::ACCEPT_ENCODING, HeaderMap};
+use lagon_runtime_http::Response;
+use std::io::Write;
+
+// Smaller bodies aren't worth the CPU time, and can even grow once compressed
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+// Higher qualities are too slow to compress responses on the fly
+const BROTLI_QUALITY: u32 = 4;
+const BROTLI_WINDOW_SIZE: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+// Content types that are already compressed, and wouldn't get any smaller
+const COMPRESSED_CONTENT_TYPES: &[&str] = &[
+    "application/gzip",
+    "application/octet-stream",
+    "application/pdf",
+    "application/vnd.rar",
+    "application/wasm",
+    "application/x-7z-compressed",
+    "application/x-bzip2",
+    "application/x-gzip",
+    "application/x-rar-compressed",
+    "application/x-xz",
+    "application/zip",
+    "application/zstd",
+    "font/woff",
+    "font/woff2",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Brotli,
+    Gzip,
+}
+
+impl ContentEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gzip",
+        }
+    }
+
+    // Picks the encoding with the highest quality value in the request's
+    // `Accept-Encoding` header, preferring Brotli when both are as good
+    pub fn negotiate(headers: &HeaderMap) -> Option<Self> {
+        let mut accepted = Vec::new();
+
+        for value in headers.get_all(ACCEPT_ENCODING) {
+            let value = match value.to_str() {
+                Ok(value) => value,
+                Err(_) => continue,
+            };
+
+            for entry in value.split(',') {
+                let mut parts = entry.split(';');
+                let name = parts.next().unwrap_or("").trim().to_lowercase();
+                let quality = parts
+                    .find_map(|part| part.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |quality| quality.trim().parse::<f32>().ok());
+
+                if let Some(quality) = quality {
+                    accepted.push((name, quality));
+                }
+            }
+        }
+
+        let quality = |name: &str| {
+            accepted
+                .iter()
+                .find(|(accepted, _)| accepted == name)
+                .or_else(|| accepted.iter().find(|(accepted, _)| accepted == "*"))
+                .map_or(0.0, |(_, quality)| *quality)
+        };
+
+        let mut best = None;
+        let mut best_quality = 0.0;
+
+        for encoding in [ContentEncoding::Brotli, ContentEncoding::Gzip] {
+            let quality = quality(encoding.as_str());
+
+            if quality > best_quality {
+                best = Some(encoding);
+                best_quality = quality;
+            }
+        }
+
+        best
+    }
+
+    pub fn compress(&self, body: &[u8]) -> Result<Vec<u8>> {
+        match self {
+            ContentEncoding::Brotli => {
+                let mut writer = brotli::CompressorWriter::new(
+                    Vec::new(),
+                    BROTLI_BUFFER_SIZE,
+                    BROTLI_QUALITY,
+                    BROTLI_WINDOW_SIZE,
+                );
+                writer.write_all(body)?;
+
+                Ok(writer.into_inner())
+            }
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(body)?;
+
+                Ok(encoder.finish()?)
+            }
+        }
+    }
+}
+
+fn header<'a>(response: &'a Response, name: &str) -> Option<&'a str> {
+    response
+        .headers
+        .as_ref()
+        .and_then(|headers| headers.get(name))
+        .and_then(|values| values.first())
+        .map(|value| value.as_str())
+}
+
+// Whether the response is worth compressing: big enough, not already
+// encoded by the Function and not of an already compressed content type
+pub fn should_compress(response: &Response) -> bool {
+    if response.len() < COMPRESSION_THRESHOLD
+        || response.status == 206
+        || header(response, "content-encoding").is_some()
+    {
+        return false;
+    }
+
+    let content_type = header(response, "content-type")
+        .and_then(|content_type| content_type.split(';').next())
+        .map(|content_type| content_type.trim().to_lowercase())
+        .unwrap_or_default();
+
+    match content_type.split_once('/') {
+        Some(("image", subtype)) => subtype == "svg+xml",
+        Some(("audio" | "video", _)) => false,
+        _ => !COMPRESSED_CONTENT_TYPES.contains(&content_type.as_str()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::HeaderValue;
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn accept_encoding(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static(value));
+        headers
+    }
+
+    fn response(content_type: &str, len: usize) -> Response {
+        Response {
+            headers: Some(HashMap::from([(
+                "content-type".into(),
+                vec![content_type.into()],
+            )])),
+            body: "a".repeat(len).into(),
+            status: 200,
+        }
+    }
+
+    #[test]
+    fn negotiate() {
+        assert_eq!(ContentEncoding::negotiate(&HeaderMap::new()), None);
+        assert_eq!(
+            ContentEncoding::negotiate(&accept_encoding("gzip, deflate, br")),
+            Some(ContentEncoding::Brotli)
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(&accept_encoding("deflate, GZIP")),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(&accept_encoding("br;q=0.5, gzip;q=0.8")),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(&accept_encoding("br;q=0, gzip;q=0")),
+            None
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(&accept_encoding("*")),
+            Some(ContentEncoding::Brotli)
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(&accept_encoding("br;q=0, *;q=0.1")),
+            Some(ContentEncoding::Gzip)
+        );
+        assert_eq!(
+            ContentEncoding::negotiate(&accept_encoding("identity")),
+            None
+        );
+    }
+
+    #[test]
+    fn should_compress_content_types() {
+        assert!(should_compress(&response("text/html; charset=utf-8", 2048)));
+        assert!(should_compress(&response("application/json", 2048)));
+        assert!(should_compress(&response("image/svg+xml", 2048)));
+        assert!(!should_compress(&response("image/png", 2048)));
+        assert!(!should_compress(&response("video/mp4", 2048)));
+        assert!(!should_compress(&response("application/zip", 2048)));
+        assert!(!should_compress(&response("text/html", 10)));
+    }
+
+    #[test]
+    fn should_compress_encoded() {
+        let mut response = response("text/html", 2048);
+        response
+            .headers
+            .as_mut()
+            .unwrap()
+            .insert("content-encoding".into(), vec!["gzip".into()]);
+
+        assert!(!should_compress(&response));
+    }
+}
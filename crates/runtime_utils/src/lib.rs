@@ -1,4 +1,5 @@
 use anyhow::{anyhow, Result};
+use sha2::{Digest, Sha256};
 
 use std::{
     collections::{HashMap, HashSet},
@@ -9,7 +10,9 @@ use std::{
 };
 
 pub mod assets;
+pub mod compression;
 pub mod response;
+pub mod websocket;
 
 #[cfg(not(feature = "test"))]
 pub const DEPLOYMENTS_DIR: &str = "deployments";
@@ -30,6 +33,34 @@ pub struct Deployment {
     pub startup_timeout: usize, // in ms (MilliSeconds)
     pub is_production: bool,
     pub cron: Option<String>,
+    // Asset served for pages that don't match any asset
+    pub assets_fallback: Option<String>,
+    // Names of the environment variables declared in the Function's config
+    pub required_environment_variables: Vec<String>,
+    // Set by `with_validated_environment_variables()` once the deployment is
+    // loaded, so the required environment variables aren't checked on each request
+    pub environment_variables_error: Option<String>,
+}
+
+// Fails with the names of the required variables that aren't set,
+// so a Function doesn't start and crash when it tries to read them
+pub fn validate_environment_variables(
+    required_environment_variables: &[String],
+    environment_variables: &HashMap<String, String>,
+) -> Result<()> {
+    let missing = required_environment_variables
+        .iter()
+        .filter(|name| !environment_variables.contains_key(*name))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+
+    match missing.is_empty() {
+        true => Ok(()),
+        false => Err(anyhow!(
+            "Missing required environment variables: {}",
+            missing.join(", ")
+        )),
+    }
 }
 
 impl Deployment {
@@ -56,6 +87,21 @@ impl Deployment {
         domains
     }
 
+    pub fn validate_environment_variables(&self) -> Result<()> {
+        validate_environment_variables(
+            &self.required_environment_variables,
+            &self.environment_variables,
+        )
+    }
+
+    pub fn with_validated_environment_variables(mut self) -> Self {
+        self.environment_variables_error = self
+            .validate_environment_variables()
+            .err()
+            .map(|error| error.to_string());
+        self
+    }
+
     pub fn should_run_cron(&self) -> bool {
         self.is_production && self.cron.is_some()
     }
@@ -83,6 +129,42 @@ impl Deployment {
         Ok(())
     }
 
+    // The environment variables are part of the code compiled by the isolate
+    fn environment_variables_hash(&self) -> Vec<u8> {
+        let mut environment_variables = self.environment_variables.iter().collect::<Vec<_>>();
+        environment_variables.sort();
+
+        let mut hasher = Sha256::new();
+
+        for (key, value) in environment_variables {
+            hasher.update(format!("{key}={value}\n"));
+        }
+
+        hasher.finalize().to_vec()
+    }
+
+    // The V8 code cache created by a previous isolate of this deployment, if
+    // it was created with the same environment variables
+    pub fn get_code_cache(&self) -> Option<Vec<u8>> {
+        let path = Path::new(DEPLOYMENTS_DIR).join(self.id.clone() + ".cache");
+        let content = fs::read(path).ok()?;
+        let hash = self.environment_variables_hash();
+
+        match content.strip_prefix(hash.as_slice()) {
+            Some(code_cache) if !code_cache.is_empty() => Some(code_cache.to_vec()),
+            _ => None,
+        }
+    }
+
+    pub fn write_code_cache(&self, code_cache: &[u8]) -> Result<()> {
+        let mut file = File::create(Path::new(DEPLOYMENTS_DIR).join(self.id.clone() + ".cache"))?;
+
+        file.write_all(&self.environment_variables_hash())?;
+        file.write_all(code_cache)?;
+
+        Ok(())
+    }
+
     pub fn write_asset(&self, asset: &str, content: &[u8]) -> Result<()> {
         let asset = asset.replace("public/", "");
         let asset = asset.as_str();
@@ -122,6 +204,9 @@ mod tests {
             startup_timeout: 1000,
             is_production: false,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         };
 
         assert_eq!(deployment.get_domains(), vec!["123.lagon.test".to_owned()]);
@@ -143,6 +228,9 @@ mod tests {
             startup_timeout: 1000,
             is_production: false,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         };
 
         assert_eq!(deployment.get_domains(), vec!["123.lagon.test".to_owned(),]);
@@ -164,6 +252,9 @@ mod tests {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         };
 
         assert_eq!(
@@ -175,4 +266,27 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn validate_required_environment_variables() {
+        let environment_variables = HashMap::from([("API_KEY".to_owned(), "".to_owned())]);
+
+        assert!(validate_environment_variables(&[], &environment_variables).is_ok());
+        assert!(
+            validate_environment_variables(&["API_KEY".to_owned()], &environment_variables).is_ok()
+        );
+        assert_eq!(
+            validate_environment_variables(
+                &[
+                    "DATABASE_URL".to_owned(),
+                    "API_KEY".to_owned(),
+                    "REDIS_URL".to_owned()
+                ],
+                &environment_variables
+            )
+            .unwrap_err()
+            .to_string(),
+            "Missing required environment variables: DATABASE_URL, REDIS_URL"
+        );
+    }
 }
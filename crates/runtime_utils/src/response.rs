@@ -1,7 +1,16 @@
 use anyhow::Result;
 use flume::Receiver;
-use hyper::{body::Bytes, http::response::Builder, Body, Response as HyperResponse};
+use futures::stream;
+use hyper::{
+    body::Bytes,
+    header::{HeaderValue, CONTENT_ENCODING, CONTENT_LENGTH, VARY},
+    http::response::Builder,
+    Body, Response as HyperResponse,
+};
 use lagon_runtime_http::{RunResult, StreamResult};
+use tokio::sync::mpsc;
+
+use crate::compression::{should_compress, ContentEncoding};
 
 pub const PAGE_404: &str = include_str!("../public/404.html");
 pub const PAGE_403: &str = include_str!("../public/403.html");
@@ -10,19 +19,38 @@ pub const PAGE_500: &str = include_str!("../public/500.html");
 
 pub const FAVICON_URL: &str = "/favicon.ico";
 
+// How many chunks can be waiting to be sent to the client. Once full, the
+// isolate stops streaming until the client caught up
+const STREAM_BUFFER_SIZE: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseSummary {
+    pub status: u16,
+    pub bytes: usize,
+    pub streamed: bool,
+}
+
 pub enum ResponseEvent {
     Bytes(usize),
+    // Sent once the response is complete, which for streams
+    // means after the last chunk of data has been sent
+    Done(ResponseSummary),
     StreamDoneNoDataError,
     StreamDoneDataError,
     UnexpectedStreamResult(RunResult),
     LimitsReached(RunResult),
     Error(RunResult),
+    // The client disconnected before the response was sent
+    Aborted,
 }
 
 type OnEvent<D> = Box<dyn Fn(ResponseEvent, D) + Send>;
 
+// Responses that aren't streamed are compressed with the given encoding, usually
+// negotiated with ContentEncoding::negotiate(), when they are worth compressing
 pub async fn handle_response<D>(
     rx: Receiver<RunResult>,
+    encoding: Option<ContentEncoding>,
     data: D,
     on_event: OnEvent<D>,
 ) -> Result<HyperResponse<Body>>
@@ -33,55 +61,94 @@ where
 
     match result {
         RunResult::Stream(stream_result) => {
-            let (stream_tx, stream_rx) = flume::unbounded::<Result<Bytes, std::io::Error>>();
-            let body = Body::wrap_stream(stream_rx.into_stream());
+            let (stream_tx, mut stream_rx) =
+                mpsc::channel::<Result<Bytes, std::io::Error>>(STREAM_BUFFER_SIZE);
+            // Each chunk is written to the client as soon as it's received,
+            // instead of waiting for the next ones
+            let body = Body::wrap_stream(stream::poll_fn(move |cx| stream_rx.poll_recv(cx)));
 
             let (response_tx, response_rx) = flume::bounded(1);
+            let mut status = None;
+            let mut total_bytes = 0;
+            // Data can be received before the response, and the body isn't
+            // read until then, so it can't be sent to the bounded stream yet
+            let mut pending = Vec::new();
 
             match stream_result {
                 StreamResult::Start(response) => {
+                    status = Some(response.status);
                     response_tx.send_async(response).await.unwrap_or(());
                 }
                 StreamResult::Data(bytes) => {
                     on_event(ResponseEvent::Bytes(bytes.len()), data.clone());
+                    total_bytes += bytes.len();
 
-                    let bytes = Bytes::from(bytes);
-                    stream_tx.send_async(Ok(bytes)).await.unwrap_or(());
+                    pending.push(Bytes::from(bytes));
                 }
                 StreamResult::Done => {
                     on_event(ResponseEvent::StreamDoneNoDataError, data.clone());
 
                     // Close the stream by sending empty bytes
-                    stream_tx.send_async(Ok(Bytes::new())).await.unwrap_or(());
+                    stream_tx.send(Ok(Bytes::new())).await.unwrap_or(());
                 }
             }
 
             tokio::spawn(async move {
                 let mut done = false;
 
-                while let Ok(result) = rx.recv_async().await {
+                loop {
+                    let result = tokio::select! {
+                        result = rx.recv_async() => match result {
+                            Ok(result) => result,
+                            Err(_) => break,
+                        },
+                        // The client disconnected, and dropping the receiver
+                        // cancels the stream in the isolate
+                        _ = stream_tx.closed() => break,
+                    };
+
                     match result {
                         RunResult::Stream(StreamResult::Start(response)) => {
+                            status = Some(response.status);
                             response_tx.send_async(response).await.unwrap_or(());
+
+                            for bytes in pending.drain(..) {
+                                stream_tx.send(Ok(bytes)).await.unwrap_or(());
+                            }
                         }
                         RunResult::Stream(StreamResult::Data(bytes)) => {
                             on_event(ResponseEvent::Bytes(bytes.len()), data.clone());
+                            total_bytes += bytes.len();
 
                             if done {
                                 on_event(ResponseEvent::StreamDoneDataError, data.clone());
 
                                 // Close the stream by sending empty bytes
-                                stream_tx.send_async(Ok(Bytes::new())).await.unwrap_or(());
+                                stream_tx.send(Ok(Bytes::new())).await.unwrap_or(());
                                 break;
                             }
 
                             let bytes = Bytes::from(bytes);
-                            stream_tx.send_async(Ok(bytes)).await.unwrap_or(());
+
+                            if status.is_some() {
+                                stream_tx.send(Ok(bytes)).await.unwrap_or(());
+                            } else {
+                                pending.push(bytes);
+                            }
                         }
                         _ => {
                             done = result == RunResult::Stream(StreamResult::Done);
 
-                            if !done {
+                            if done {
+                                on_event(
+                                    ResponseEvent::Done(ResponseSummary {
+                                        status: status.unwrap_or(200),
+                                        bytes: total_bytes,
+                                        streamed: true,
+                                    }),
+                                    data.clone(),
+                                );
+                            } else {
                                 on_event(
                                     ResponseEvent::UnexpectedStreamResult(result),
                                     data.clone(),
@@ -89,7 +156,7 @@ where
                             }
 
                             // Close the stream by sending empty bytes
-                            stream_tx.send_async(Ok(Bytes::new())).await.unwrap_or(());
+                            stream_tx.send(Ok(Bytes::new())).await.unwrap_or(());
                         }
                     }
                 }
@@ -100,29 +167,109 @@ where
 
             Ok(hyper_response)
         }
-        RunResult::Response(response) => {
-            on_event(ResponseEvent::Bytes(response.len()), data);
+        RunResult::Response(mut response) => {
+            let encoding = encoding.filter(|_| should_compress(&response));
+
+            // Compressing is CPU-bound, so it shouldn't block other requests
+            if let Some(encoding) = encoding {
+                let body = response.body.clone();
+                response.body = tokio::task::spawn_blocking(move || encoding.compress(&body))
+                    .await??
+                    .into();
+            }
+
+            on_event(ResponseEvent::Bytes(response.len()), data.clone());
+            on_event(
+                ResponseEvent::Done(ResponseSummary {
+                    status: response.status,
+                    bytes: response.len(),
+                    streamed: false,
+                }),
+                data,
+            );
+
+            let mut builder = Builder::try_from(&response)?;
 
-            Ok(Builder::try_from(&response)?.body(response.body.into())?)
+            if let (Some(encoding), Some(headers)) = (encoding, builder.headers_mut()) {
+                let varies = headers
+                    .get_all(VARY)
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .flat_map(|value| value.split(','))
+                    .any(|value| value.trim().eq_ignore_ascii_case("accept-encoding"));
+
+                headers.remove(CONTENT_LENGTH);
+                headers.insert(
+                    CONTENT_ENCODING,
+                    HeaderValue::from_static(encoding.as_str()),
+                );
+
+                if !varies {
+                    headers.append(VARY, HeaderValue::from_static("Accept-Encoding"));
+                }
+            }
+
+            Ok(builder.body(response.body.into())?)
         }
-        RunResult::Timeout | RunResult::MemoryLimit => {
-            on_event(ResponseEvent::LimitsReached(result), data);
+        RunResult::Timeout | RunResult::CpuTimeLimit | RunResult::MemoryLimit(_) => {
+            on_event(ResponseEvent::LimitsReached(result), data.clone());
+            on_event(ResponseEvent::Done(page_summary(502, PAGE_502)), data);
 
             Ok(HyperResponse::builder().status(502).body(PAGE_502.into())?)
         }
         RunResult::Error(_) => {
-            on_event(ResponseEvent::Error(result), data);
+            on_event(ResponseEvent::Error(result), data.clone());
+            on_event(ResponseEvent::Done(page_summary(500, PAGE_500)), data);
 
             Ok(HyperResponse::builder().status(500).body(PAGE_500.into())?)
         }
-        RunResult::NotFound => Ok(HyperResponse::builder().status(404).body(PAGE_404.into())?),
+        RunResult::NotFound => {
+            on_event(ResponseEvent::Done(page_summary(404, PAGE_404)), data);
+
+            Ok(HyperResponse::builder().status(404).body(PAGE_404.into())?)
+        }
+        // Nobody reads this response, which is only used for logs and metrics. Like
+        // nginx, 499 means that the client closed the connection
+        RunResult::Aborted => {
+            on_event(ResponseEvent::Aborted, data.clone());
+            on_event(ResponseEvent::Done(page_summary(499, "")), data);
+
+            Ok(HyperResponse::builder().status(499).body(Body::empty())?)
+        }
+        // The upgrade is accepted by `handle_websocket_upgrade()`, which
+        // needs the hyper request and takes it from the extensions
+        RunResult::WebSocket(upgrade) => {
+            on_event(
+                ResponseEvent::Done(ResponseSummary {
+                    status: upgrade.response.status,
+                    bytes: 0,
+                    streamed: false,
+                }),
+                data,
+            );
+
+            let mut response = Builder::try_from(&upgrade.response)?.body(Body::empty())?;
+            response.extensions_mut().insert(upgrade);
+
+            Ok(response)
+        }
+    }
+}
+
+fn page_summary(status: u16, page: &str) -> ResponseSummary {
+    ResponseSummary {
+        status,
+        bytes: page.len(),
+        streamed: false,
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use hyper::body::to_bytes;
-    use lagon_runtime_http::Response;
+    use flate2::read::GzDecoder;
+    use hyper::body::{to_bytes, HttpBody};
+    use lagon_runtime_http::{Response, WebSocketUpgrade};
+    use std::{collections::HashMap, io::Read, time::Duration};
 
     use super::*;
 
@@ -131,7 +278,9 @@ mod tests {
         let (tx, rx) = flume::unbounded::<RunResult>();
 
         let handle = tokio::spawn(async move {
-            let mut response = handle_response(rx, (), Box::new(|_, _| ())).await.unwrap();
+            let mut response = handle_response(rx, None, (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
 
             assert_eq!(response.status(), 200);
             assert_eq!(
@@ -152,7 +301,9 @@ mod tests {
         let (tx, rx) = flume::unbounded::<RunResult>();
 
         let handle = tokio::spawn(async move {
-            let mut response = handle_response(rx, (), Box::new(|_, _| ())).await.unwrap();
+            let mut response = handle_response(rx, None, (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
 
             assert_eq!(response.status(), 200);
             assert_eq!(
@@ -182,12 +333,280 @@ mod tests {
         handle.await.unwrap();
     }
 
+    #[tokio::test]
+    async fn stream_chunks() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        tx.send_async(RunResult::Stream(StreamResult::Start(Response::from(""))))
+            .await
+            .unwrap();
+
+        let mut response = handle_response(rx, None, (), Box::new(|_, _| ()))
+            .await
+            .unwrap();
+
+        // Each chunk is received before the next one is sent
+        for chunk in ["data: 1\n\n", "data: 2\n\n", "data: 3\n\n"] {
+            tx.send_async(RunResult::Stream(StreamResult::Data(chunk.into())))
+                .await
+                .unwrap();
+
+            let data = tokio::time::timeout(Duration::from_secs(1), response.body_mut().data())
+                .await
+                .unwrap()
+                .unwrap()
+                .unwrap();
+
+            assert_eq!(data, Bytes::from(chunk));
+        }
+
+        tx.send_async(RunResult::Stream(StreamResult::Done))
+            .await
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(to_bytes(response.body_mut()).await.unwrap(), Bytes::new());
+    }
+
+    #[tokio::test]
+    async fn stream_client_disconnect() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        tx.send_async(RunResult::Stream(StreamResult::Start(Response::from(""))))
+            .await
+            .unwrap();
+
+        let response = handle_response(rx, None, (), Box::new(|_, _| ()))
+            .await
+            .unwrap();
+
+        // The isolate sees that the client disconnected without sending more data
+        drop(response);
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while !tx.is_disconnected() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn aborted() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+        let (events_tx, events_rx) = flume::unbounded();
+
+        tx.send_async(RunResult::Aborted).await.unwrap();
+
+        let response = handle_response(
+            rx,
+            None,
+            (),
+            Box::new(move |event, _| {
+                events_tx
+                    .send(matches!(event, ResponseEvent::Aborted))
+                    .unwrap()
+            }),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(response.status(), 499);
+        assert_eq!(events_rx.drain().collect::<Vec<_>>(), [true, false]);
+    }
+
+    #[tokio::test]
+    async fn redirect() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        let handle = tokio::spawn(async move {
+            let mut response = handle_response(rx, None, (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), 308);
+            assert_eq!(
+                response.headers().get("location").unwrap(),
+                "https://lagon.app/docs"
+            );
+            assert_eq!(to_bytes(response.body_mut()).await.unwrap(), Bytes::new());
+        });
+
+        tx.send_async(RunResult::Response(Response {
+            headers: Some(HashMap::from([(
+                "location".into(),
+                vec!["https://lagon.app/docs".into()],
+            )])),
+            body: Bytes::new(),
+            status: 308,
+        }))
+        .await
+        .unwrap();
+
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn repeated_headers() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        let handle = tokio::spawn(async move {
+            let response = handle_response(rx, None, (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
+
+            assert_eq!(
+                response
+                    .headers()
+                    .get_all("set-cookie")
+                    .iter()
+                    .collect::<Vec<_>>(),
+                vec!["greeting=hello", "name=world"]
+            );
+        });
+
+        tx.send_async(RunResult::Response(Response {
+            headers: Some(HashMap::from([(
+                "set-cookie".into(),
+                vec!["greeting=hello".into(), "name=world".into()],
+            )])),
+            body: Bytes::from("Hello World"),
+            status: 200,
+        }))
+        .await
+        .unwrap();
+
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn summary() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+        let (events_tx, events_rx) = flume::unbounded::<ResponseSummary>();
+
+        let handle = tokio::spawn(async move {
+            let response = handle_response(
+                rx,
+                None,
+                (),
+                Box::new(move |event, _| {
+                    if let ResponseEvent::Done(summary) = event {
+                        events_tx.send(summary).unwrap();
+                    }
+                }),
+            )
+            .await
+            .unwrap();
+
+            assert_eq!(response.status(), 200);
+        });
+
+        tx.send_async(RunResult::Response(Response::from("Hello World")))
+            .await
+            .unwrap();
+
+        handle.await.unwrap();
+
+        assert_eq!(
+            events_rx.recv_async().await.unwrap(),
+            ResponseSummary {
+                status: 200,
+                bytes: 11,
+                streamed: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_summary() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+        let (events_tx, events_rx) = flume::unbounded::<ResponseSummary>();
+
+        let handle = tokio::spawn(async move {
+            let mut response = handle_response(
+                rx,
+                None,
+                (),
+                Box::new(move |event, _| {
+                    if let ResponseEvent::Done(summary) = event {
+                        events_tx.send(summary).unwrap();
+                    }
+                }),
+            )
+            .await
+            .unwrap();
+
+            to_bytes(response.body_mut()).await.unwrap();
+        });
+
+        tx.send_async(RunResult::Stream(StreamResult::Start(Response::from(""))))
+            .await
+            .unwrap();
+
+        tx.send_async(RunResult::Stream(StreamResult::Data(b"Hello".to_vec())))
+            .await
+            .unwrap();
+
+        tx.send_async(RunResult::Stream(StreamResult::Data(b" world".to_vec())))
+            .await
+            .unwrap();
+
+        tx.send_async(RunResult::Stream(StreamResult::Done))
+            .await
+            .unwrap();
+
+        drop(tx);
+
+        handle.await.unwrap();
+
+        assert_eq!(
+            events_rx.recv_async().await.unwrap(),
+            ResponseSummary {
+                status: 200,
+                bytes: 11,
+                streamed: true,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_backpressure() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        tx.send(RunResult::Stream(StreamResult::Start(Response::from(""))))
+            .unwrap();
+
+        for _ in 0..20 {
+            tx.send(RunResult::Stream(StreamResult::Data(b"a".to_vec())))
+                .unwrap();
+        }
+
+        let mut response = handle_response(rx, None, (), Box::new(|_, _| ()))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        // The body isn't read yet, so the results have to wait
+        assert!(tx.len() >= 20 - STREAM_BUFFER_SIZE - 1);
+
+        tx.send(RunResult::Stream(StreamResult::Done)).unwrap();
+        drop(tx);
+
+        assert_eq!(
+            to_bytes(response.body_mut()).await.unwrap(),
+            Bytes::from("a".repeat(20))
+        );
+    }
+
     #[tokio::test]
     async fn stream_data_before_response() {
         let (tx, rx) = flume::unbounded::<RunResult>();
 
         let handle = tokio::spawn(async move {
-            let mut response = handle_response(rx, (), Box::new(|_, _| ())).await.unwrap();
+            let mut response = handle_response(rx, None, (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
 
             assert_eq!(response.status(), 200);
             assert_eq!(
@@ -216,4 +635,139 @@ mod tests {
 
         handle.await.unwrap();
     }
+
+    #[tokio::test]
+    async fn compressed() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        tx.send(RunResult::Response(Response::from(
+            "Hello World".repeat(200),
+        )))
+        .unwrap();
+
+        let mut response =
+            handle_response(rx, Some(ContentEncoding::Gzip), (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
+
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "gzip");
+        assert_eq!(response.headers().get(VARY).unwrap(), "Accept-Encoding");
+
+        let body = to_bytes(response.body_mut()).await.unwrap();
+        let mut decompressed = String::new();
+        GzDecoder::new(&body[..])
+            .read_to_string(&mut decompressed)
+            .unwrap();
+
+        assert!(body.len() < 2200);
+        assert_eq!(decompressed, "Hello World".repeat(200));
+    }
+
+    #[tokio::test]
+    async fn compressed_brotli() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        tx.send(RunResult::Response(Response {
+            headers: Some(HashMap::from([
+                ("content-length".into(), vec!["2200".into()]),
+                ("vary".into(), vec!["Origin, accept-encoding".into()]),
+            ])),
+            body: Bytes::from("Hello World".repeat(200)),
+            status: 200,
+        }))
+        .unwrap();
+
+        let mut response =
+            handle_response(rx, Some(ContentEncoding::Brotli), (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
+
+        assert_eq!(response.headers().get(CONTENT_ENCODING).unwrap(), "br");
+        assert_eq!(response.headers().get(CONTENT_LENGTH), None);
+        assert_eq!(
+            response.headers().get_all(VARY).iter().collect::<Vec<_>>(),
+            vec!["Origin, accept-encoding"]
+        );
+
+        let body = to_bytes(response.body_mut()).await.unwrap();
+        let mut decompressed = String::new();
+        brotli::Decompressor::new(&body[..], 4096)
+            .read_to_string(&mut decompressed)
+            .unwrap();
+
+        assert_eq!(decompressed, "Hello World".repeat(200));
+    }
+
+    #[tokio::test]
+    async fn not_compressed() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        tx.send(RunResult::Response(Response::from("Hello World")))
+            .unwrap();
+
+        let mut response =
+            handle_response(rx, Some(ContentEncoding::Gzip), (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
+
+        assert_eq!(response.headers().get(CONTENT_ENCODING), None);
+        assert_eq!(
+            to_bytes(response.body_mut()).await.unwrap(),
+            Bytes::from("Hello World")
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_not_compressed() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+
+        tx.send(RunResult::Stream(StreamResult::Start(Response::from(""))))
+            .unwrap();
+        tx.send(RunResult::Stream(StreamResult::Data(
+            "Hello World".repeat(200).into_bytes(),
+        )))
+        .unwrap();
+        tx.send(RunResult::Stream(StreamResult::Done)).unwrap();
+        drop(tx);
+
+        let mut response =
+            handle_response(rx, Some(ContentEncoding::Gzip), (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
+
+        assert_eq!(response.headers().get(CONTENT_ENCODING), None);
+        assert_eq!(
+            to_bytes(response.body_mut()).await.unwrap(),
+            Bytes::from("Hello World".repeat(200))
+        );
+    }
+
+    #[tokio::test]
+    async fn websocket_upgrade() {
+        let (tx, rx) = flume::unbounded::<RunResult>();
+        let (_, outgoing) = flume::unbounded();
+        let (incoming, _) = flume::unbounded();
+        let upgrade = WebSocketUpgrade {
+            response: Response {
+                status: 101,
+                ..Default::default()
+            },
+            outgoing,
+            incoming,
+        };
+
+        tx.send(RunResult::WebSocket(upgrade.clone())).unwrap();
+
+        let mut response =
+            handle_response(rx, Some(ContentEncoding::Gzip), (), Box::new(|_, _| ()))
+                .await
+                .unwrap();
+
+        assert_eq!(response.status(), 101);
+        assert_eq!(response.headers().get(CONTENT_ENCODING), None);
+        assert_eq!(
+            response.extensions_mut().remove::<WebSocketUpgrade>(),
+            Some(upgrade)
+        );
+    }
 }
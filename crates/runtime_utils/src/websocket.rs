@@ -0,0 +1,151 @@
+use anyhow::Result;
+use futures::{SinkExt, StreamExt};
+use hyper::{
+    header::{HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE},
+    upgrade::{OnUpgrade, Upgraded},
+    Body, Request as HyperRequest, Response as HyperResponse,
+};
+use lagon_runtime_http::{WebSocketMessage, WebSocketUpgrade};
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::derive_accept_key,
+        protocol::{frame::coding::CloseCode, CloseFrame, Role},
+        Message,
+    },
+    WebSocketStream,
+};
+
+// A request asking to be upgraded to a WebSocket. It has to be taken before
+// the request is sent to the isolate, which consumes the hyper request
+pub struct PendingUpgrade {
+    on_upgrade: OnUpgrade,
+    accept_key: String,
+}
+
+impl PendingUpgrade {
+    pub fn from_request(req: &mut HyperRequest<Body>) -> Option<Self> {
+        let is_websocket = req
+            .headers()
+            .get(UPGRADE)
+            .and_then(|upgrade| upgrade.to_str().ok())
+            .map_or(false, |upgrade| upgrade.eq_ignore_ascii_case("websocket"));
+
+        if !is_websocket {
+            return None;
+        }
+
+        let accept_key = derive_accept_key(req.headers().get(SEC_WEBSOCKET_KEY)?.as_bytes());
+
+        Some(PendingUpgrade {
+            on_upgrade: hyper::upgrade::on(req),
+            accept_key,
+        })
+    }
+
+    // Finishes the handshake, and forwards the messages between the
+    // client and the isolate once the connection is upgraded
+    fn accept(self, response: &mut HyperResponse<Body>, upgrade: WebSocketUpgrade) -> Result<()> {
+        let headers = response.headers_mut();
+        headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+        headers.insert(CONNECTION, HeaderValue::from_static("Upgrade"));
+        headers.insert(
+            SEC_WEBSOCKET_ACCEPT,
+            HeaderValue::from_str(&self.accept_key)?,
+        );
+
+        tokio::spawn(async move {
+            if let Ok(upgraded) = self.on_upgrade.await {
+                let stream = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+
+                forward_messages(stream, upgrade).await;
+            }
+        });
+
+        Ok(())
+    }
+}
+
+// Accepts the WebSocket upgrade returned by `handle_response()` (if any). Functions
+// can only upgrade requests asking for it, but the response could still be sent
+// to a client that didn't, so it's replaced with an error
+pub fn handle_websocket_upgrade(
+    pending_upgrade: Option<PendingUpgrade>,
+    mut response: HyperResponse<Body>,
+) -> Result<HyperResponse<Body>> {
+    if let Some(upgrade) = response.extensions_mut().remove::<WebSocketUpgrade>() {
+        match pending_upgrade {
+            Some(pending_upgrade) => pending_upgrade.accept(&mut response, upgrade)?,
+            None => {
+                return Ok(HyperResponse::builder()
+                    .status(400)
+                    .body("Expected a WebSocket upgrade request".into())?)
+            }
+        }
+    }
+
+    Ok(response)
+}
+
+fn into_message(message: WebSocketMessage) -> Message {
+    match message {
+        WebSocketMessage::Text(text) => Message::Text(text),
+        WebSocketMessage::Binary(bytes) => Message::Binary(bytes),
+        WebSocketMessage::Close(frame) => Message::Close(frame.map(|(code, reason)| CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.into(),
+        })),
+    }
+}
+
+// Messages are forwarded one by one, and the isolate only buffers a single message
+// at a time, so a slow Function applies backpressure to the client
+async fn forward_messages(stream: WebSocketStream<Upgraded>, upgrade: WebSocketUpgrade) {
+    let WebSocketUpgrade {
+        outgoing, incoming, ..
+    } = upgrade;
+    let (mut sink, mut stream) = stream.split();
+
+    loop {
+        tokio::select! {
+            message = outgoing.recv_async() => {
+                // The isolate dropped the WebSocket, e.g when the Function is closed
+                let message = match message {
+                    Ok(message) => message,
+                    Err(_) => break,
+                };
+
+                if sink.send(into_message(message)).await.is_err() {
+                    break;
+                }
+            }
+            message = stream.next() => {
+                let message = match message {
+                    Some(Ok(Message::Text(text))) => WebSocketMessage::Text(text),
+                    Some(Ok(Message::Binary(bytes))) => WebSocketMessage::Binary(bytes),
+                    Some(Ok(Message::Close(frame))) => {
+                        let frame = frame.map(|frame| {
+                            (u16::from(frame.code), frame.reason.to_string())
+                        });
+
+                        incoming
+                            .send_async(WebSocketMessage::Close(frame))
+                            .await
+                            .unwrap_or(());
+
+                        // Reading until the end replies to the closing frame, unless
+                        // the isolate already started the closing handshake
+                        while let Some(Ok(_)) = stream.next().await {}
+                        break;
+                    }
+                    // Pings are answered automatically
+                    Some(Ok(_)) => continue,
+                    Some(Err(_)) | None => break,
+                };
+
+                if incoming.send_async(message).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
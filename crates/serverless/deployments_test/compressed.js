@@ -0,0 +1,7 @@
+export function handler() {
+  return new Response('Hello world'.repeat(200), {
+    headers: {
+      'content-type': 'text/plain',
+    },
+  });
+}
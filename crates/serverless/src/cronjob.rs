@@ -100,6 +100,9 @@ impl Cronjob {
                             RunResult::Stream(_) => {
                                 error!(source = CONSOLE_SOURCE, deployment = deployment.id, function = deployment.function_id; "Cron can't return a stream")
                             }
+                            RunResult::WebSocket(_) => {
+                                error!(source = CONSOLE_SOURCE, deployment = deployment.id, function = deployment.function_id; "Cron can't upgrade to a WebSocket")
+                            }
                             RunResult::Response(response) => {
                                 let body = String::from_utf8_lossy(&response.body);
                                 let maybe_body = if body == "" { String::from("") } else { format!(": {body}") };
@@ -131,7 +134,15 @@ impl Cronjob {
                                     "Cron execution timed out",
                                 )
                             }
-                            RunResult::MemoryLimit => {
+                            RunResult::CpuTimeLimit => {
+                                warn!(
+                                    source = CONSOLE_SOURCE,
+                                    deployment = deployment.id,
+                                    function = deployment.function_id;
+                                    "Cron execution CPU time limit reached",
+                                )
+                            }
+                            RunResult::MemoryLimit(_) => {
                                 warn!(
                                     source = CONSOLE_SOURCE,
                                     deployment = deployment.id,
@@ -148,7 +159,7 @@ impl Cronjob {
                                     error,
                                 )
                             }
-                            RunResult::NotFound => {}
+                            RunResult::NotFound | RunResult::Aborted => {}
                         }
                     })
                 })?)
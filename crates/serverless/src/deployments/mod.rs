@@ -63,6 +63,10 @@ type QueryResult = (
     Option<String>,
     Option<String>,
     Option<String>,
+    // A JSON array of the names of the required environment variables
+    Option<String>,
+    // The name of the asset served for unmatched pages
+    Option<String>,
 );
 
 pub async fn get_deployments<D>(
@@ -76,6 +80,38 @@ where
     let deployments = Arc::new(DashMap::new());
 
     let mut deployments_list: HashMap<String, Deployment> = HashMap::new();
+    // Loaded before the deployments, to check their required environment variables
+    let mut environment_variables: HashMap<String, HashMap<String, String>> = HashMap::new();
+
+    // Only the variables of the Functions whose deployments are loaded below
+    conn.query_map(
+        format!(
+            "
+SELECT
+    EnvVariable.functionId,
+    EnvVariable.`key`,
+    EnvVariable.value
+FROM
+    EnvVariable
+INNER JOIN Function
+    ON EnvVariable.functionId = Function.id
+WHERE
+    EXISTS (SELECT 1 FROM Deployment WHERE Deployment.functionId = Function.id)
+AND (
+    Function.cron IS NULL
+OR
+    Function.cronRegion = '{}'
+)
+",
+            REGION.as_str()
+        ),
+        |(function_id, key, value): (String, String, String)| {
+            environment_variables
+                .entry(function_id)
+                .or_default()
+                .insert(key, value);
+        },
+    )?;
 
     conn.query_map(
         format!(
@@ -90,7 +126,9 @@ SELECT
     Function.startupTimeout,
     Function.cron,
     Domain.domain,
-    Asset.name
+    Asset.name,
+    Deployment.requiredEnv,
+    Deployment.assetsFallback
 FROM
     Deployment
 INNER JOIN Function
@@ -117,7 +155,14 @@ OR
             cron,
             domain,
             asset,
+            required_env,
+            assets_fallback,
         ): QueryResult| {
+            let function_environment_variables = environment_variables
+                .get(&function_id)
+                .cloned()
+                .unwrap_or_default();
+
             deployments_list
                 .entry(id.clone())
                 .and_modify(|deployment| {
@@ -147,17 +192,25 @@ OR
                             assets
                         })
                         .unwrap_or_default(),
-                    environment_variables: HashMap::new(),
+                    environment_variables: function_environment_variables,
                     memory,
                     timeout,
                     startup_timeout,
                     is_production,
                     cron,
+                    assets_fallback,
+                    required_environment_variables: required_env
+                        .and_then(|required_env| serde_json::from_str(&required_env).ok())
+                        .unwrap_or_default(),
+                    environment_variables_error: None,
                 });
         },
     )?;
 
-    let deployments_list: Vec<Deployment> = deployments_list.values().cloned().collect();
+    let deployments_list: Vec<Deployment> = deployments_list
+        .into_values()
+        .map(Deployment::with_validated_environment_variables)
+        .collect();
 
     info!("Found {} deployment(s) to deploy", deployments_list.len());
 
@@ -76,7 +76,21 @@ where
             startup_timeout: value["startupTimeout"].as_u64().unwrap() as usize,
             is_production: value["isProduction"].as_bool().unwrap(),
             cron,
-        };
+            assets_fallback: value["assetsFallback"]
+                .as_str()
+                .map(|assets_fallback| assets_fallback.to_string()),
+            required_environment_variables: value["requiredEnv"]
+                .as_array()
+                .map(|names| {
+                    names
+                        .iter()
+                        .filter_map(|name| name.as_str().map(|name| name.to_string()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            environment_variables_error: None,
+        }
+        .with_validated_environment_variables();
 
         let workers = Arc::clone(&workers);
 
@@ -8,6 +8,8 @@ pub mod serverless;
 
 lazy_static! {
     pub static ref REGION: String = env::var("LAGON_REGION").expect("LAGON_REGION must be set");
+    // Responses are compressed unless explicitly disabled
+    pub static ref COMPRESSION: bool = !matches!(env::var("LAGON_COMPRESSION").as_deref(), Ok("false"));
 }
 
 pub const SNAPSHOT_BLOB: &[u8] = include_bytes!("../snapshot.bin");
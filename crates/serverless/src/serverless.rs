@@ -1,25 +1,30 @@
 use crate::{
     deployments::{cache::run_cache_clear_task, pubsub::listen_pub_sub, Deployments},
-    REGION, SNAPSHOT_BLOB,
+    COMPRESSION, REGION, SNAPSHOT_BLOB,
 };
 use anyhow::Result;
 use dashmap::DashMap;
 use hyper::{
+    body::HttpBody,
     header::HOST,
     http::response::Builder,
-    server::conn::AddrStream,
+    server::conn::{AddrIncoming, AddrStream},
     service::{make_service_fn, service_fn},
     Body, Request as HyperRequest, Response as HyperResponse, Server,
 };
 use lagon_runtime_http::{
-    Request, Response, RunResult, X_FORWARDED_FOR, X_LAGON_ID, X_LAGON_REGION, X_REAL_IP,
+    BodyTooLargeError, Request, Response, RunResult, DEFAULT_MAX_BODY_SIZE, X_FORWARDED_FOR,
+    X_LAGON_ID, X_LAGON_REGION, X_REAL_IP,
 };
 use lagon_runtime_isolate::{
-    options::IsolateOptions, Isolate, IsolateEvent, IsolateRequest, CONSOLE_SOURCE,
+    options::IsolateOptions, stream_request_body, Isolate, IsolateEvent, IsolateRequest,
+    CONSOLE_SOURCE,
 };
 use lagon_runtime_utils::{
-    assets::{find_asset, handle_asset},
+    assets::{find_asset, find_fallback_asset, handle_asset, AssetResolution},
+    compression::ContentEncoding,
     response::{handle_response, ResponseEvent, FAVICON_URL, PAGE_403, PAGE_404},
+    websocket::{handle_websocket_upgrade, PendingUpgrade},
     DEPLOYMENTS_DIR,
 };
 use lagon_serverless_downloader::Downloader;
@@ -50,9 +55,13 @@ fn handle_error(
             increment_counter!("lagon_isolate_timeouts", labels);
             warn!(deployment = deployment_id, request = request_id, source = CONSOLE_SOURCE; "Function execution timed out")
         }
-        RunResult::MemoryLimit => {
+        RunResult::CpuTimeLimit => {
+            increment_counter!("lagon_isolate_cpu_time_limits", labels);
+            warn!(deployment = deployment_id, request = request_id, source = CONSOLE_SOURCE; "Function execution CPU time limit reached")
+        }
+        RunResult::MemoryLimit(statistics) => {
             increment_counter!("lagon_isolate_memory_limits", labels);
-            warn!(deployment = deployment_id, request = request_id, source = CONSOLE_SOURCE; "Function execution memory limit reached")
+            warn!(deployment = deployment_id, request = request_id, source = CONSOLE_SOURCE; "Function execution memory limit reached ({} bytes used of {})", statistics.used_heap_size, statistics.heap_size_limit)
         }
         RunResult::Error(error) => {
             increment_counter!("lagon_isolate_errors", labels);
@@ -63,7 +72,7 @@ fn handle_error(
 }
 
 async fn handle_request(
-    req: HyperRequest<Body>,
+    mut req: HyperRequest<Body>,
     ip: String,
     deployments: Deployments,
     last_requests: Arc<DashMap<String, Instant>>,
@@ -119,6 +128,10 @@ async fn handle_request(
     let request_id_handle = request_id.clone();
 
     let (sender, receiver) = flume::unbounded();
+    // Only taken from requests sent to the Function
+    let mut pending_upgrade = None;
+    // Dropped with this future when the client disconnects, which aborts the request
+    let (_connection_tx, connection_rx) = flume::bounded::<()>(1);
 
     let labels = [
         ("deployment", deployment.id.clone()),
@@ -130,8 +143,25 @@ async fn handle_request(
 
     let url = req.uri().path();
     let is_favicon = url == FAVICON_URL;
+    let encoding = match *COMPRESSION {
+        true => ContentEncoding::negotiate(req.headers()),
+        false => None,
+    };
+
+    let asset = find_asset(url, &deployment.assets, AssetResolution::default()).or_else(|| {
+        // The favicon is never served the fallback page
+        match is_favicon {
+            true => None,
+            false => find_fallback_asset(
+                deployment.assets_fallback.as_deref(),
+                req.method(),
+                req.headers(),
+                &deployment.assets,
+            ),
+        }
+    });
 
-    if let Some(asset) = find_asset(url, &deployment.assets) {
+    if let Some(asset) = asset {
         let root = Path::new(env::current_dir().unwrap().as_path())
             .join(DEPLOYMENTS_DIR)
             .join(&deployment.id);
@@ -154,14 +184,29 @@ async fn handle_request(
             }))
             .await
             .unwrap_or(());
+    } else if let Some(error) = &deployment.environment_variables_error {
+        // The isolate isn't started, since the Function would crash anyway
+        sender
+            .send_async(RunResult::Error(error.clone()))
+            .await
+            .unwrap_or(());
     } else {
         last_requests.insert(deployment_id.clone(), Instant::now());
 
         increment_counter!("lagon_isolate_requests", &labels);
 
-        match Request::from_hyper_with_capacity(req, 2).await {
-            Ok(mut request) => {
-                counter!("lagon_bytes_in", request.len() as u64, &labels);
+        pending_upgrade = PendingUpgrade::from_request(&mut req);
+
+        match Request::from_hyper_streamed(req, 2, DEFAULT_MAX_BODY_SIZE) {
+            Ok((mut request, body)) => {
+                // The body isn't buffered anymore, so the bytes are counted as they are read
+                let body_stream = (!body.is_end_stream()).then(|| {
+                    let labels = labels.clone();
+
+                    stream_request_body(body, DEFAULT_MAX_BODY_SIZE, move |size| {
+                        counter!("lagon_bytes_in", size as u64, &labels);
+                    })
+                });
 
                 // Try to Extract the X-Real-Ip header or fallback to remote addr IP
                 let ip = request
@@ -193,7 +238,7 @@ async fn handle_request(
 
                                 "".into()
                             });
-                            let options = IsolateOptions::new(code)
+                            let mut options = IsolateOptions::new(code)
                                 .environment_variables(deployment.environment_variables.clone())
                                 .memory(deployment.memory)
                                 .timeout(Duration::from_millis(deployment.timeout as u64))
@@ -234,8 +279,20 @@ async fn handle_request(
                                 }))
                                 .snapshot_blob(SNAPSHOT_BLOB);
 
+                            // Created by a previous isolate of the same deployment
+                            if let Some(code_cache) = deployment.get_code_cache() {
+                                options = options.code_cache(code_cache);
+                            }
+
                             let mut isolate = Isolate::new(options, receiver);
                             isolate.evaluate();
+
+                            if let Some(code_cache) = isolate.get_code_cache() {
+                                if let Err(error) = deployment.write_code_cache(code_cache) {
+                                    error!(deployment = deployment.id; "Error while writing deployment code cache: {}", error);
+                                }
+                            }
+
                             isolate.run_event_loop().await;
 
                             // When the event loop is completed, that means a) the isolate was terminate due to limits
@@ -249,7 +306,22 @@ async fn handle_request(
                 });
 
                 isolate_sender
-                    .send_async(IsolateEvent::Request(IsolateRequest { request, sender }))
+                    .send_async(IsolateEvent::Request(IsolateRequest {
+                        request,
+                        sender,
+                        statistics: None,
+                        body_stream,
+                        connection: Some(connection_rx),
+                    }))
+                    .await
+                    .unwrap_or(());
+            }
+            Err(error) if error.is::<BodyTooLargeError>() => {
+                sender
+                    .send_async(RunResult::Response(Response {
+                        status: 413,
+                        ..Default::default()
+                    }))
                     .await
                     .unwrap_or(());
             }
@@ -264,13 +336,17 @@ async fn handle_request(
         }
     }
 
-    handle_response(
+    // Handled in its own task, so `RunResult::Aborted` is still
+    // received and counted once the client disconnected
+    let response = tokio::spawn(handle_response(
         receiver,
+        encoding,
         (deployment_id, request_id_handle, labels),
         Box::new(|event, (deployment_id, request_id, labels)| match event {
             ResponseEvent::Bytes(bytes) => {
                 counter!("lagon_bytes_out", bytes as u64, &labels);
             }
+            ResponseEvent::Done(_) => {}
             ResponseEvent::StreamDoneNoDataError => {
                 handle_error(
                     RunResult::Error("The stream was done before sending a response/data".into()),
@@ -292,9 +368,14 @@ async fn handle_request(
             | ResponseEvent::Error(result) => {
                 handle_error(result, &deployment_id, &request_id, &labels);
             }
+            ResponseEvent::Aborted => {
+                increment_counter!("lagon_isolate_aborts", &labels);
+            }
         }),
-    )
-    .await
+    ))
+    .await??;
+
+    handle_websocket_upgrade(pending_upgrade, response)
 }
 
 pub async fn start<D, P>(
@@ -322,7 +403,12 @@ where
     );
     run_cache_clear_task(Arc::clone(&last_requests), Arc::clone(&workers));
 
-    let server = Server::bind(&addr).serve(make_service_fn(move |conn: &AddrStream| {
+    let mut incoming = AddrIncoming::bind(&addr)?;
+    // Small chunks of streamed responses, e.g Server-Sent Events, are sent
+    // right away instead of being delayed to be grouped together
+    incoming.set_nodelay(true);
+
+    let server = Server::builder(incoming).serve(make_service_fn(move |conn: &AddrStream| {
         let deployments = Arc::clone(&deployments);
         let last_requests = Arc::clone(&last_requests);
         let workers = Arc::clone(&workers);
@@ -31,6 +31,9 @@ async fn html_assets() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -86,6 +89,9 @@ async fn assets_nested() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -138,6 +144,9 @@ async fn set_content_type() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -169,3 +178,79 @@ async fn set_content_type() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[serial]
+async fn assets_fallback() -> Result<()> {
+    utils::setup();
+    let deployments = Arc::new(DashMap::new());
+    deployments.insert(
+        "127.0.0.1:4000".into(),
+        Arc::new(Deployment {
+            id: "assets".into(),
+            function_id: "function_id".into(),
+            function_name: "function_name".into(),
+            domains: HashSet::new(),
+            assets: HashSet::from(["hello.html".into(), "index.css".into()]),
+            environment_variables: HashMap::new(),
+            memory: 128,
+            timeout: 1000,
+            startup_timeout: 1000,
+            is_production: true,
+            cron: None,
+            assets_fallback: Some("hello.html".into()),
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
+        }),
+    );
+    let serverless = start(
+        deployments,
+        "127.0.0.1:4000".parse().unwrap(),
+        Arc::new(FakeDownloader),
+        FakePubSub::default(),
+        // Arc::new(Mutex::new(Cronjob::new().await)),
+    )
+    .await?;
+    tokio::spawn(serverless);
+
+    let client = reqwest::Client::new();
+
+    let response = client
+        .get("http://127.0.0.1:4000/some/page")
+        .header("accept", "text/html")
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.text().await?, "hello asset!\n");
+
+    // Existing assets are still served as is
+    let response = client
+        .get("http://127.0.0.1:4000/index.css")
+        .header("accept", "text/html")
+        .send()
+        .await?;
+    assert_eq!(response.headers()["content-type"], "text/css");
+
+    let response = client
+        .get("http://127.0.0.1:4000/api")
+        .header("accept", "application/json")
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "Dynamic asset: /api");
+
+    let response = client
+        .post("http://127.0.0.1:4000/some/page")
+        .header("accept", "text/html")
+        .send()
+        .await?;
+    assert_eq!(response.text().await?, "Dynamic asset: /some/page");
+
+    let response = client
+        .get("http://127.0.0.1:4000/favicon.ico")
+        .header("accept", "text/html")
+        .send()
+        .await?;
+    assert_eq!(response.status(), 404);
+
+    Ok(())
+}
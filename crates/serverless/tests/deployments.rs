@@ -31,6 +31,9 @@ async fn simple() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -67,6 +70,9 @@ async fn custom_domains() -> Result<()> {
         startup_timeout: 1000,
         is_production: true,
         cron: None,
+        assets_fallback: None,
+        required_environment_variables: Vec::new(),
+        environment_variables_error: None,
     });
     deployments.insert("127.0.0.1:4000".into(), Arc::clone(&deployment));
     deployments.insert("custom.domain".into(), Arc::clone(&deployment));
@@ -115,6 +121,9 @@ async fn reuse_isolate() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -155,6 +164,9 @@ async fn reuse_isolate_across_domains() -> Result<()> {
         startup_timeout: 1000,
         is_production: true,
         cron: None,
+        assets_fallback: None,
+        required_environment_variables: Vec::new(),
+        environment_variables_error: None,
     });
     deployments.insert("127.0.0.1:4000".into(), Arc::clone(&deployment));
     deployments.insert("another.domain".into(), deployment);
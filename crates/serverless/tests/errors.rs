@@ -55,6 +55,9 @@ async fn return_403_cron_deployment() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: Some("".into()),
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -93,6 +96,9 @@ async fn return_500_unknown_code() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -131,6 +137,9 @@ async fn return_502_timeout_execution() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -169,6 +178,9 @@ async fn return_502_timeout_init() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -207,6 +219,9 @@ async fn return_500_code_invalid() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -245,6 +260,9 @@ async fn return_500_throw_error() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -263,3 +281,47 @@ async fn return_500_throw_error() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[serial]
+async fn return_500_missing_environment_variables() -> Result<()> {
+    utils::setup();
+    let deployments = Arc::new(DashMap::new());
+    deployments.insert(
+        "127.0.0.1:4000".into(),
+        Arc::new(
+            Deployment {
+                id: "missing-env".into(),
+                function_id: "function_id".into(),
+                function_name: "function_name".into(),
+                domains: HashSet::new(),
+                assets: HashSet::new(),
+                environment_variables: HashMap::from([("API_KEY".into(), "secret".into())]),
+                memory: 128,
+                timeout: 1000,
+                startup_timeout: 1000,
+                is_production: true,
+                cron: None,
+                assets_fallback: None,
+                required_environment_variables: vec!["API_KEY".into(), "DATABASE_URL".into()],
+                environment_variables_error: None,
+            }
+            .with_validated_environment_variables(),
+        ),
+    );
+    let serverless = start(
+        deployments,
+        "127.0.0.1:4000".parse().unwrap(),
+        Arc::new(FakeDownloader),
+        FakePubSub::default(),
+        // Arc::new(Mutex::new(Cronjob::new().await)),
+    )
+    .await?;
+    tokio::spawn(serverless);
+
+    let response = reqwest::get("http://127.0.0.1:4000").await?;
+    assert_eq!(response.status(), 500);
+    assert_eq!(response.text().await?, PAGE_500);
+
+    Ok(())
+}
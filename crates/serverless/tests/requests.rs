@@ -1,5 +1,6 @@
 use anyhow::Result;
 use dashmap::DashMap;
+use flate2::read::GzDecoder;
 use futures::StreamExt;
 use hyper::body::Bytes;
 use lagon_runtime_utils::Deployment;
@@ -9,6 +10,7 @@ use lagon_serverless_pubsub::FakePubSub;
 use serial_test::serial;
 use std::{
     collections::{HashMap, HashSet},
+    io::Read,
     sync::Arc,
 };
 
@@ -33,6 +35,9 @@ async fn returns_correct_http() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -74,6 +79,9 @@ async fn returns_correct_path() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -123,6 +131,9 @@ async fn forwards_headers() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -163,6 +174,9 @@ async fn stream_sequentially() -> Result<()> {
             startup_timeout: 1000,
             is_production: true,
             cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
         }),
     );
     let serverless = start(
@@ -186,3 +200,61 @@ async fn stream_sequentially() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+#[serial]
+async fn compresses_response() -> Result<()> {
+    utils::setup();
+    let deployments = Arc::new(DashMap::new());
+    deployments.insert(
+        "127.0.0.1:4000".into(),
+        Arc::new(Deployment {
+            id: "compressed".into(),
+            function_id: "function_id".into(),
+            function_name: "function_name".into(),
+            domains: HashSet::new(),
+            assets: HashSet::new(),
+            environment_variables: HashMap::new(),
+            memory: 128,
+            timeout: 1000,
+            startup_timeout: 1000,
+            is_production: true,
+            cron: None,
+            assets_fallback: None,
+            required_environment_variables: Vec::new(),
+            environment_variables_error: None,
+        }),
+    );
+    let serverless = start(
+        deployments,
+        "127.0.0.1:4000".parse().unwrap(),
+        Arc::new(FakeDownloader),
+        FakePubSub::default(),
+        // Arc::new(Mutex::new(Cronjob::new().await)),
+    )
+    .await?;
+    tokio::spawn(serverless);
+
+    // Decompress the body ourselves, to check the headers
+    let client = reqwest::Client::builder().no_gzip().no_brotli().build()?;
+
+    let response = client
+        .get("http://127.0.0.1:4000")
+        .header("accept-encoding", "gzip")
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-encoding"], "gzip");
+    assert_eq!(response.headers()["vary"], "Accept-Encoding");
+
+    let mut body = String::new();
+    GzDecoder::new(&response.bytes().await?[..]).read_to_string(&mut body)?;
+    assert_eq!(body, "Hello world".repeat(200));
+
+    let response = client.get("http://127.0.0.1:4000").send().await?;
+    assert_eq!(response.status(), 200);
+    assert!(response.headers().get("content-encoding").is_none());
+    assert_eq!(response.text().await?, "Hello world".repeat(200));
+
+    Ok(())
+}
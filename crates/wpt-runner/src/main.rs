@@ -165,6 +165,9 @@ export function handler() {{
     tx.send_async(IsolateEvent::Request(IsolateRequest {
         request: Request::default(),
         sender: request_tx,
+        statistics: None,
+        body_stream: None,
+        connection: None,
     }))
     .await
     .unwrap();
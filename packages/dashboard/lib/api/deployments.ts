@@ -1,4 +1,5 @@
 import { DeleteObjectCommand, DeleteObjectsCommand, GetObjectCommand } from '@aws-sdk/client-s3';
+import { Prisma } from '@prisma/client';
 import redis from 'lib/redis';
 import s3 from 'lib/s3';
 import prisma from 'lib/prisma';
@@ -7,6 +8,16 @@ import { TRPCError } from '@trpc/server';
 import { envStringToObject } from 'lib/utils';
 import { MAX_ASSETS_PER_FUNCTION } from 'lib/constants';
 
+// The names of the environment variables declared in the Function's config,
+// which are checked before starting the Function
+export function getRequiredEnv(requiredEnv: Prisma.JsonValue): string[] {
+  if (!Array.isArray(requiredEnv)) {
+    return [];
+  }
+
+  return requiredEnv.filter((name): name is string => typeof name === 'string');
+}
+
 export async function createDeployment(
   func: {
     id: string;
@@ -20,6 +31,8 @@ export async function createDeployment(
   },
   assets: string[],
   triggerer: string,
+  requiredEnv: string[],
+  assetsFallback: string | null,
 ): Promise<{
   id: string;
   createdAt: Date;
@@ -39,6 +52,8 @@ export async function createDeployment(
       },
       functionId: func.id,
       triggerer,
+      requiredEnv,
+      assetsFallback,
     },
     select: {
       id: true,
@@ -219,6 +234,8 @@ export async function promoteProductionDeployment(functionId: string, newDeploym
           name: true,
         },
       },
+      requiredEnv: true,
+      assetsFallback: true,
     },
   });
 
@@ -238,6 +255,8 @@ export async function promoteProductionDeployment(functionId: string, newDeploym
       env: envStringToObject(func.env),
       isProduction: true,
       assets: deployment.assets.map(({ name }) => name),
+      requiredEnv: getRequiredEnv(deployment.requiredEnv),
+      assetsFallback: deployment.assetsFallback,
     }),
   );
 }
@@ -254,7 +273,13 @@ export async function redeploy(
     cronRegion: string;
     env: { key: string; value: string }[];
   },
-  deployment: { id: string; isProduction: boolean; assets: string[] },
+  deployment: {
+    id: string;
+    isProduction: boolean;
+    assets: string[];
+    requiredEnv: string[];
+    assetsFallback: string | null;
+  },
   oldDomains: string[],
 ) {
   await redis.publish(
@@ -290,6 +315,8 @@ export async function redeploy(
       env: envStringToObject(func.env),
       isProduction: deployment.isProduction,
       assets: deployment.assets,
+      requiredEnv: deployment.requiredEnv,
+      assetsFallback: deployment.assetsFallback,
     }),
   );
 }
@@ -7,6 +7,7 @@ import {
   promoteProductionDeployment,
   checkCanCreateDeployment,
   checkCanUpdateDeployment,
+  getRequiredEnv,
 } from 'lib/api/deployments';
 import prisma from 'lib/prisma';
 import { T } from 'pages/api/trpc/[trpc]';
@@ -30,6 +31,9 @@ export const deploymentsRouter = (t: T) =>
               size: z.number(),
             })
             .array(),
+          // Not sent by older versions of the CLI
+          requiredEnv: z.string().array().optional(),
+          assetsFallback: z.string().nullish(),
         }),
       )
       .mutation(async ({ ctx, input }) => {
@@ -77,6 +81,8 @@ export const deploymentsRouter = (t: T) =>
           },
           input.assets.map(({ name }) => name),
           ctx.session.user.email,
+          input.requiredEnv ?? [],
+          input.assetsFallback ?? null,
         );
 
         const getPresignedUrl = async (key: string, size: number) => {
@@ -160,6 +166,8 @@ export const deploymentsRouter = (t: T) =>
               id: true,
               isProduction: true,
               assets: true,
+              requiredEnv: true,
+              assetsFallback: true,
             },
           }),
         ]);
@@ -185,6 +193,8 @@ export const deploymentsRouter = (t: T) =>
             env: envStringToObject(func.env),
             isProduction: deployment.isProduction,
             assets: deployment.assets.map(({ name }) => name),
+            requiredEnv: getRequiredEnv(deployment.requiredEnv),
+            assetsFallback: deployment.assetsFallback,
           }),
         );
 
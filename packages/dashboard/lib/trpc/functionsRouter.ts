@@ -1,7 +1,7 @@
 import { z } from 'zod';
 import prisma from 'lib/prisma';
 import { TIMEFRAMES } from 'lib/types';
-import { getDeploymentCode, removeFunction, redeploy } from 'lib/api/deployments';
+import { getDeploymentCode, removeFunction, redeploy, getRequiredEnv } from 'lib/api/deployments';
 import {
   CUSTOM_DOMAINS_PER_FUNCTION,
   ENVIRONMENT_VARIABLES_PER_FUNCTION,
@@ -321,6 +321,8 @@ export const functionsRouter = (t: T) =>
                 assets: true,
                 createdAt: true,
                 updatedAt: true,
+                requiredEnv: true,
+                assetsFallback: true,
               },
             },
           },
@@ -430,6 +432,7 @@ export const functionsRouter = (t: T) =>
             {
               ...deployment,
               assets: deployment.assets.map(({ name }) => name),
+              requiredEnv: getRequiredEnv(deployment.requiredEnv),
             },
             oldDomains,
           );
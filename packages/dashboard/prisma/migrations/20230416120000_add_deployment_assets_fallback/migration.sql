@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE `Deployment` ADD COLUMN `assetsFallback` VARCHAR(191) NULL;
@@ -0,0 +1,107 @@
+import { describe, it, expect, vi } from 'vitest';
+import '../';
+
+describe('DOMException', () => {
+  it('should set the name and legacy code', () => {
+    const error = new DOMException('Aborted', 'AbortError');
+    expect(error).toBeInstanceOf(Error);
+    expect(error.message).toEqual('Aborted');
+    expect(error.name).toEqual('AbortError');
+    expect(error.code).toEqual(20);
+  });
+
+  it('should default to Error', () => {
+    const error = new DOMException();
+    expect(error.message).toEqual('');
+    expect(error.name).toEqual('Error');
+    expect(error.code).toEqual(0);
+  });
+});
+
+describe('AbortController', () => {
+  it('should abort the signal', () => {
+    const controller = new AbortController();
+    expect(controller.signal.aborted).toBeFalsy();
+    expect(controller.signal.reason).toBeUndefined();
+
+    controller.abort();
+    expect(controller.signal.aborted).toBeTruthy();
+    expect(controller.signal.reason).toBeInstanceOf(DOMException);
+    expect(controller.signal.reason.name).toEqual('AbortError');
+  });
+
+  it('should abort with a reason', () => {
+    const controller = new AbortController();
+    controller.abort('reason');
+    expect(controller.signal.reason).toEqual('reason');
+  });
+
+  it('should dispatch the abort event once', () => {
+    const controller = new AbortController();
+    const listener = vi.fn();
+    const onabort = vi.fn();
+    controller.signal.addEventListener('abort', listener);
+    controller.signal.onabort = onabort;
+
+    controller.abort();
+    controller.abort('again');
+    expect(listener).toHaveBeenCalledOnce();
+    expect(listener.mock.calls[0][0].type).toEqual('abort');
+    expect(onabort).toHaveBeenCalledOnce();
+    expect(controller.signal.reason.name).toEqual('AbortError');
+  });
+
+  it('should only remove the given listener', () => {
+    const controller = new AbortController();
+    const first = vi.fn();
+    const second = vi.fn();
+    controller.signal.addEventListener('abort', first);
+    controller.signal.addEventListener('abort', second);
+    controller.signal.removeEventListener('abort', first);
+
+    controller.abort();
+    expect(first).not.toHaveBeenCalled();
+    expect(second).toHaveBeenCalledOnce();
+  });
+});
+
+describe('AbortSignal', () => {
+  it('should not be constructable', () => {
+    expect(() => new AbortSignal()).toThrow(TypeError);
+  });
+
+  it('should create an aborted signal', () => {
+    const signal = AbortSignal.abort();
+    expect(signal.aborted).toBeTruthy();
+    expect(signal.reason.name).toEqual('AbortError');
+    expect(AbortSignal.abort('reason').reason).toEqual('reason');
+  });
+
+  it('should throw if aborted', () => {
+    const controller = new AbortController();
+    expect(() => controller.signal.throwIfAborted()).not.toThrow();
+
+    controller.abort('reason');
+    expect(() => controller.signal.throwIfAborted()).toThrow('reason');
+  });
+
+  it('should follow any signal', () => {
+    const first = new AbortController();
+    const second = new AbortController();
+    // @ts-expect-error AbortSignal.any isn't part of TypeScript's DOM types yet
+    const signal = AbortSignal.any([first.signal, second.signal]);
+    expect(signal.aborted).toBeFalsy();
+
+    second.abort('second');
+    first.abort('first');
+    expect(signal.aborted).toBeTruthy();
+    expect(signal.reason).toEqual('second');
+  });
+
+  it('should follow already aborted signals', () => {
+    // @ts-expect-error AbortSignal.any isn't part of TypeScript's DOM types yet
+    const signal = AbortSignal.any([new AbortController().signal, AbortSignal.abort('reason')]);
+    expect(signal.aborted).toBeTruthy();
+    expect(signal.reason).toEqual('reason');
+  });
+});
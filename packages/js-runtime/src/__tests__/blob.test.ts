@@ -44,6 +44,13 @@ describe('Blob', () => {
     expect(blob.type).toEqual('text/plain');
   });
 
+  it('should init with ArrayBufferView', async () => {
+    const bytes = new Uint8Array([1, 2, 3, 4, 5]);
+    const blob = new Blob([new DataView(bytes.buffer, 1, 3), bytes.subarray(3)]);
+    expect(blob.size).toEqual(5);
+    expect(new Uint8Array(await blob.arrayBuffer())).toEqual(new Uint8Array([2, 3, 4, 4, 5]));
+  });
+
   it('should init with different types', () => {
     const blob = new Blob(['hello', new ArrayBuffer(5), new Blob(['world'])], { type: 'text/plain' });
     expect(blob.size).toEqual(15);
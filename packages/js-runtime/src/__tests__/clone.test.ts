@@ -0,0 +1,34 @@
+import { describe, it, expect, vi, beforeEach, afterEach } from 'vitest';
+import '../';
+
+describe('structuredClone', () => {
+  beforeEach(() => {
+    globalThis.LagonSync = {
+      ...globalThis.LagonSync,
+      structuredClone: vi.fn(),
+    };
+  });
+
+  afterEach(() => {
+    vi.resetAllMocks();
+  });
+
+  it('should clone with the runtime', () => {
+    // @ts-expect-error LagonSync is not defined
+    globalThis.LagonSync.structuredClone.mockReturnValueOnce({ hello: 'world' });
+
+    const buffer = new ArrayBuffer(8);
+
+    expect(structuredClone({ hello: 'world' })).toEqual({ hello: 'world' });
+    structuredClone(buffer, { transfer: new Set([buffer]) });
+
+    expect(globalThis.LagonSync.structuredClone).toHaveBeenNthCalledWith(1, { hello: 'world' }, []);
+    expect(globalThis.LagonSync.structuredClone).toHaveBeenNthCalledWith(2, buffer, [buffer]);
+  });
+
+  it('should throw if transfer is not iterable', () => {
+    // @ts-expect-error transfer should be an array
+    expect(() => structuredClone({}, { transfer: 1 })).toThrow(TypeError);
+    expect(globalThis.LagonSync.structuredClone).not.toHaveBeenCalled();
+  });
+});
@@ -0,0 +1,98 @@
+import { describe, it, expect, vi, beforeEach, afterEach } from 'vitest';
+import '../';
+
+const readAll = async (readable: ReadableStream<Uint8Array>): Promise<Uint8Array[]> => {
+  const chunks: Uint8Array[] = [];
+  const reader = readable.getReader();
+
+  while (true) {
+    const { done, value } = await reader.read();
+
+    if (done) {
+      return chunks;
+    }
+
+    chunks.push(value);
+  }
+};
+
+describe('CompressionStream', () => {
+  beforeEach(() => {
+    globalThis.LagonSync = {
+      ...globalThis.LagonSync,
+      createCodec: vi.fn(),
+    };
+    globalThis.LagonAsync = {
+      ...globalThis.LagonAsync,
+      transformCodec: vi.fn(),
+    };
+  });
+
+  afterEach(() => {
+    vi.resetAllMocks();
+  });
+
+  it('should transform chunks with the runtime', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.transformCodec
+      .mockResolvedValueOnce(new Uint8Array([1]))
+      .mockResolvedValueOnce(new Uint8Array())
+      .mockResolvedValueOnce(new Uint8Array([2, 3]));
+
+    const stream = new CompressionStream('gzip');
+    const writer = stream.writable.getWriter();
+    writer.write(new Uint8Array([4]));
+    writer.write(new Uint16Array([5]));
+    writer.close();
+
+    expect(await readAll(stream.readable)).toEqual([new Uint8Array([1]), new Uint8Array([2, 3])]);
+    expect(LagonSync.createCodec).toHaveBeenCalledWith(expect.any(Number), 'gzip', false);
+
+    const [id] = (LagonSync.createCodec as ReturnType<typeof vi.fn>).mock.calls[0];
+    expect(LagonAsync.transformCodec).toHaveBeenNthCalledWith(1, id, new Uint8Array([4]));
+    expect(LagonAsync.transformCodec).toHaveBeenNthCalledWith(2, id, new Uint8Array([5, 0]));
+    expect(LagonAsync.transformCodec).toHaveBeenNthCalledWith(3, id, undefined);
+  });
+
+  it('should throw on unsupported formats', () => {
+    // @ts-expect-error testing an invalid format
+    expect(() => new CompressionStream('br')).toThrow(TypeError);
+    expect(LagonSync.createCodec).not.toHaveBeenCalled();
+  });
+});
+
+describe('DecompressionStream', () => {
+  beforeEach(() => {
+    globalThis.LagonSync = {
+      ...globalThis.LagonSync,
+      createCodec: vi.fn(),
+    };
+    globalThis.LagonAsync = {
+      ...globalThis.LagonAsync,
+      transformCodec: vi.fn(),
+    };
+  });
+
+  afterEach(() => {
+    vi.resetAllMocks();
+  });
+
+  it('should create a decompression codec', () => {
+    new DecompressionStream('deflate-raw');
+
+    expect(LagonSync.createCodec).toHaveBeenCalledWith(expect.any(Number), 'deflate-raw', true);
+  });
+
+  it('should error with a TypeError', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.transformCodec.mockRejectedValueOnce('corrupt deflate stream');
+
+    const stream = new DecompressionStream('deflate');
+    stream.writable
+      .getWriter()
+      .write(new Uint8Array([1]))
+      .catch(() => undefined);
+
+    await expect(stream.readable.getReader().read()).rejects.toThrow(new TypeError('corrupt deflate stream'));
+  });
+});
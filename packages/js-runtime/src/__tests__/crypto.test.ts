@@ -43,4 +43,189 @@ describe('getRandomValues', () => {
 
     expect(uuid).toEqual(new Uint8Array([0, 8, 2]));
   });
+
+  it('should fill BigInt arrays in place', () => {
+    // @ts-expect-error LagonSync is not defined
+    globalThis.LagonSync.randomValues.mockImplementationOnce(array => new Uint8Array(array.byteLength).fill(1));
+
+    const typedArray = new BigUint64Array(2);
+    const result = crypto.getRandomValues(typedArray);
+
+    expect(result).toBe(typedArray);
+    expect(typedArray).toEqual(new BigUint64Array([0x0101010101010101n, 0x0101010101010101n]));
+  });
+
+  it('should throw on float arrays', () => {
+    expect(() => crypto.getRandomValues(new Float64Array(2))).toThrow('not an integer array type');
+    expect(globalThis.LagonSync.randomValues).not.toHaveBeenCalled();
+  });
+
+  it('should throw above 65536 bytes', () => {
+    expect(() => crypto.getRandomValues(new Uint32Array(16385))).toThrow('exceeds the number of bytes of entropy');
+    expect(globalThis.LagonSync.randomValues).not.toHaveBeenCalled();
+  });
+});
+
+describe('importKey', () => {
+  beforeEach(() => {
+    globalThis.LagonAsync = {
+      ...globalThis.LagonAsync,
+      importKey: vi.fn(),
+      exportKey: vi.fn(),
+    };
+  });
+
+  afterEach(() => {
+    vi.resetAllMocks();
+  });
+
+  it('should import asymmetric keys with LagonAsync.importKey', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.importKey.mockResolvedValueOnce([new Uint8Array([1, 2, 3])]);
+
+    const key = await crypto.subtle.importKey('raw', new Uint8Array([4, 5]).buffer, 'Ed25519', true, ['verify']);
+
+    expect(globalThis.LagonAsync.importKey).toHaveBeenCalledWith('raw', new Uint8Array([4, 5]), 'Ed25519');
+    expect(key.type).toEqual('public');
+    expect(key.algorithm).toEqual({ name: 'Ed25519' });
+    expect(key.usages).toEqual(['verify']);
+    expect(key.keyValue).toEqual(new Uint8Array([1, 2, 3]));
+  });
+
+  it('should import JWKs as JSON', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.importKey.mockResolvedValueOnce([new Uint8Array([1])]);
+
+    const key = await crypto.subtle.importKey(
+      'jwk',
+      { kty: 'EC', crv: 'P-256', x: 'x', y: 'y', d: 'd', ext: true },
+      { name: 'ECDSA', namedCurve: 'P-256' },
+      false,
+      ['sign'],
+    );
+
+    expect(globalThis.LagonAsync.importKey).toHaveBeenCalledWith(
+      'jwk',
+      JSON.stringify({ kty: 'EC', crv: 'P-256', x: 'x', y: 'y', d: 'd' }),
+      { name: 'ECDSA', namedCurve: 'P-256' },
+    );
+    expect(key.type).toEqual('private');
+    expect(key.algorithm).toEqual({ name: 'ECDSA', namedCurve: 'P-256' });
+  });
+
+  it('should reject invalid key data with a DataError', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.importKey.mockRejectedValueOnce('Invalid key data');
+
+    await expect(
+      crypto.subtle.importKey('spki', new Uint8Array([1]), 'Ed25519', true, ['verify']),
+    ).rejects.toMatchObject({ name: 'DataError', message: 'Invalid key data' });
+  });
+
+  it('should reject invalid usages and curves', async () => {
+    await expect(
+      crypto.subtle.importKey('pkcs8', new Uint8Array([1]), 'Ed25519', true, ['verify']),
+    ).rejects.toMatchObject({ name: 'SyntaxError' });
+    await expect(
+      crypto.subtle.importKey('raw', new Uint8Array([1]), { name: 'ECDSA', namedCurve: 'P-521' }, true, ['verify']),
+    ).rejects.toMatchObject({ name: 'NotSupportedError' });
+
+    expect(globalThis.LagonAsync.importKey).not.toHaveBeenCalled();
+  });
+
+  it('should export JWKs with their usages', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.importKey.mockResolvedValueOnce([new Uint8Array([1])]);
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.exportKey.mockResolvedValueOnce(
+      new TextEncoder().encode(JSON.stringify({ kty: 'OKP', crv: 'Ed25519', x: 'x' })),
+    );
+
+    const key = await crypto.subtle.importKey('raw', new Uint8Array([1]), 'Ed25519', true, ['verify']);
+
+    expect(await crypto.subtle.exportKey('jwk', key)).toEqual({
+      kty: 'OKP',
+      crv: 'Ed25519',
+      x: 'x',
+      key_ops: ['verify'],
+      ext: true,
+    });
+    await expect(crypto.subtle.exportKey('pkcs8', key)).rejects.toMatchObject({ name: 'InvalidAccessError' });
+  });
+
+  it('should import RSA keys with their modulus length', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.importKey.mockResolvedValueOnce([
+      new Uint8Array([1]),
+      new Uint8Array([0x01, 0, 0]),
+      new Uint8Array([1, 0, 1]),
+    ]);
+
+    const key = await crypto.subtle.importKey(
+      'spki',
+      new Uint8Array([1]),
+      { name: 'RSA-PSS', hash: 'SHA-256' },
+      true,
+      ['verify'],
+    );
+
+    expect(key.algorithm).toEqual({
+      name: 'RSA-PSS',
+      modulusLength: 17,
+      publicExponent: new Uint8Array([1, 0, 1]),
+      hash: { name: 'SHA-256' },
+    });
+  });
+
+  it('should reject RSA JWKs with another algorithm', async () => {
+    await expect(
+      crypto.subtle.importKey(
+        'jwk',
+        { kty: 'RSA', n: 'n', e: 'AQAB', alg: 'PS256' },
+        { name: 'RSASSA-PKCS1-v1_5', hash: 'SHA-256' },
+        true,
+        ['verify'],
+      ),
+    ).rejects.toMatchObject({ name: 'DataError' });
+    await expect(
+      crypto.subtle.importKey('spki', new Uint8Array([1]), { name: 'RSA-OAEP', hash: 'SHA-1' }, true, ['encrypt']),
+    ).rejects.toMatchObject({ name: 'NotSupportedError' });
+
+    expect(globalThis.LagonAsync.importKey).not.toHaveBeenCalled();
+  });
+});
+
+describe('deriveBits', () => {
+  beforeEach(() => {
+    globalThis.LagonAsync = {
+      ...globalThis.LagonAsync,
+      deriveBits: vi.fn(),
+    };
+  });
+
+  afterEach(() => {
+    vi.resetAllMocks();
+  });
+
+  it('should derive bits with LagonAsync.deriveBits', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.deriveBits.mockResolvedValueOnce(new Uint8Array([1, 2]));
+
+    const key = await crypto.subtle.importKey('raw', new Uint8Array([1]), 'PBKDF2', false, ['deriveBits']);
+    const algorithm = { name: 'PBKDF2', salt: new Uint8Array([2]).buffer, iterations: 1, hash: 'SHA-256' };
+
+    expect(await crypto.subtle.deriveBits(algorithm, key, 16)).toEqual(new Uint8Array([1, 2]));
+    expect(globalThis.LagonAsync.deriveBits).toHaveBeenCalledWith({ ...algorithm, salt: new Uint8Array([2]) }, key, 16);
+    expect(key.keyValue).toEqual(new Uint8Array([1]));
+  });
+
+  it('should reject keys without the deriveBits usage', async () => {
+    const key = await crypto.subtle.importKey('raw', new Uint8Array([1]), 'HKDF', false, ['deriveKey']);
+
+    await expect(
+      crypto.subtle.deriveBits({ name: 'HKDF', hash: 'SHA-256', salt: new Uint8Array(), info: new Uint8Array() }, key, 8),
+    ).rejects.toMatchObject({ name: 'InvalidAccessError' });
+
+    expect(globalThis.LagonAsync.deriveBits).not.toHaveBeenCalled();
+  });
 });
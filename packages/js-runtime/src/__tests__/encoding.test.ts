@@ -28,4 +28,124 @@ describe('encoding', () => {
   it('should have encoding field on TextDecoder', async () => {
     expect(new TextDecoder().encoding).toEqual('utf-8');
   });
+
+  it('should replace invalid sequences', () => {
+    expect(new TextDecoder().decode(new Uint8Array([0x61, 0xff, 0x62, 0xe2, 0x82]))).toEqual('a\ufffdb\ufffd');
+    expect(new TextDecoder().decode(new Uint8Array([0xf0, 0x61]))).toEqual('\ufffda');
+  });
+
+  it('should decode null bytes', () => {
+    expect(new TextDecoder().decode(new Uint8Array([0x61, 0x00, 0x62]))).toEqual('a\0b');
+  });
+
+  it('should throw on invalid sequences when fatal', () => {
+    const decoder = new TextDecoder('utf-8', { fatal: true });
+    expect(decoder.fatal).toBeTruthy();
+    expect(() => decoder.decode(new Uint8Array([0xff]))).toThrow(TypeError);
+    expect(() => decoder.decode(new Uint8Array([0xe2, 0x82]))).toThrow(TypeError);
+    expect(decoder.decode(new Uint8Array([0x61]))).toEqual('a');
+  });
+
+  it('should strip the BOM', () => {
+    const bytes = new Uint8Array([0xef, 0xbb, 0xbf, 0x61]);
+    expect(new TextDecoder().decode(bytes)).toEqual('a');
+    expect(new TextDecoder('utf-8', { ignoreBOM: true }).decode(bytes)).toEqual('\ufeffa');
+  });
+
+  it('should throw with an invalid label', () => {
+    expect(new TextDecoder('UTF8').encoding).toEqual('utf-8');
+    expect(() => new TextDecoder('latin1')).toThrow(RangeError);
+  });
+
+  it('should decode split sequences when streaming', () => {
+    const bytes = new TextEncoder().encode('😊');
+    const decoder = new TextDecoder();
+
+    expect(decoder.decode(bytes.subarray(0, 2), { stream: true })).toEqual('');
+    expect(decoder.decode(bytes.subarray(2), { stream: true })).toEqual('😊');
+    expect(decoder.decode(bytes.subarray(0, 1), { stream: true })).toEqual('');
+    expect(decoder.decode()).toEqual('\ufffd');
+  });
+
+  it('should encode lone surrogates', () => {
+    expect(new TextEncoder().encode('\ud83d')).toEqual(new Uint8Array([0xef, 0xbf, 0xbd]));
+  });
+});
+
+const readAll = async <T>(readable: ReadableStream<T>): Promise<T[]> => {
+  const chunks: T[] = [];
+  const reader = readable.getReader();
+
+  while (true) {
+    const { done, value } = await reader.read();
+
+    if (done) {
+      return chunks;
+    }
+
+    chunks.push(value);
+  }
+};
+
+describe('TextDecoderStream', () => {
+  it('should decode chunks', async () => {
+    const stream = new TextDecoderStream();
+    const writer = stream.writable.getWriter();
+    writer.write(new TextEncoder().encode('Hello '));
+    writer.write(new TextEncoder().encode('World'));
+    writer.close();
+
+    expect(await readAll(stream.readable)).toEqual(['Hello ', 'World']);
+  });
+
+  it('should decode an emoji split across chunks', async () => {
+    const bytes = new TextEncoder().encode('a😊b');
+    const stream = new TextDecoderStream();
+    const writer = stream.writable.getWriter();
+    writer.write(bytes.subarray(0, 3));
+    writer.write(bytes.subarray(3));
+    writer.close();
+
+    expect((await readAll(stream.readable)).join('')).toEqual('a😊b');
+  });
+
+  it('should replace an incomplete sequence at the end', async () => {
+    const stream = new TextDecoderStream();
+    const writer = stream.writable.getWriter();
+    writer.write(new Uint8Array([0x61, 0xf0, 0x9f]));
+    writer.close();
+
+    expect((await readAll(stream.readable)).join('')).toEqual('a\ufffd');
+  });
+
+  it('should error when fatal', async () => {
+    const stream = new TextDecoderStream('utf-8', { fatal: true });
+    const writer = stream.writable.getWriter();
+    writer.write(new Uint8Array([0xff])).catch(() => undefined);
+
+    expect(stream.fatal).toBeTruthy();
+    await expect(stream.readable.getReader().read()).rejects.toThrow(TypeError);
+  });
+});
+
+describe('TextEncoderStream', () => {
+  it('should encode chunks', async () => {
+    const stream = new TextEncoderStream();
+    const writer = stream.writable.getWriter();
+    writer.write('Hello');
+    writer.close();
+
+    expect(stream.encoding).toEqual('utf-8');
+    expect(await readAll(stream.readable)).toEqual([new TextEncoder().encode('Hello')]);
+  });
+
+  it('should encode a surrogate pair split across chunks', async () => {
+    const stream = new TextEncoderStream();
+    const writer = stream.writable.getWriter();
+    writer.write('a\ud83d');
+    writer.write('\ude0ab');
+    writer.close();
+
+    expect(await readAll(stream.readable)).toEqual([new TextEncoder().encode('a'), new TextEncoder().encode('😊b')]);
+  });
 });
@@ -0,0 +1,80 @@
+import { describe, it, expect, vi } from 'vitest';
+import '../';
+
+describe('EventTarget', () => {
+  it('should call once listeners once', () => {
+    const target = new EventTarget();
+    const listener = vi.fn();
+    target.addEventListener('test', listener, { once: true });
+
+    target.dispatchEvent(new Event('test'));
+    target.dispatchEvent(new Event('test'));
+    expect(listener).toHaveBeenCalledOnce();
+  });
+
+  it('should remove listeners when the signal aborts', () => {
+    const target = new EventTarget();
+    const controller = new AbortController();
+    const listener = vi.fn();
+    target.addEventListener('test', listener, { signal: controller.signal });
+
+    controller.abort();
+    target.dispatchEvent(new Event('test'));
+    expect(listener).not.toHaveBeenCalled();
+  });
+
+  it('should return false when a cancelable event is canceled', () => {
+    const target = new EventTarget();
+    target.addEventListener('test', event => event.preventDefault());
+
+    expect(target.dispatchEvent(new Event('test'))).toBeTruthy();
+    expect(target.dispatchEvent(new Event('test', { cancelable: true }))).toBeFalsy();
+  });
+
+  it('should stop immediate propagation', () => {
+    const target = new EventTarget();
+    const listener = vi.fn();
+    target.addEventListener('test', event => event.stopImmediatePropagation());
+    target.addEventListener('test', listener);
+
+    target.dispatchEvent(new Event('test'));
+    expect(listener).not.toHaveBeenCalled();
+  });
+
+  it('should throw when dispatching an event twice', () => {
+    const target = new EventTarget();
+    const listener = vi.fn(event => expect(() => target.dispatchEvent(event)).toThrow(DOMException));
+    target.addEventListener('test', listener);
+
+    target.dispatchEvent(new Event('test'));
+    expect(listener).toHaveBeenCalledOnce();
+  });
+});
+
+describe('MessageEvent', () => {
+  it('should have default values', () => {
+    const event = new MessageEvent('message');
+
+    expect(event.data).toBeNull();
+    expect(event.origin).toEqual('');
+    expect(event.ports).toEqual([]);
+  });
+
+  it('should set data and origin', () => {
+    const event = new MessageEvent('message', { data: 'Hello', origin: 'wss://example.com' });
+
+    expect(event).toBeInstanceOf(Event);
+    expect(event.data).toEqual('Hello');
+    expect(event.origin).toEqual('wss://example.com');
+  });
+});
+
+describe('CloseEvent', () => {
+  it('should set code, reason and wasClean', () => {
+    const event = new CloseEvent('close', { code: 1000, reason: 'Done', wasClean: true });
+
+    expect(event).toBeInstanceOf(Event);
+    expect([event.code, event.reason, event.wasClean]).toEqual([1000, 'Done', true]);
+    expect(new CloseEvent('close').wasClean).toBeFalsy();
+  });
+});
@@ -1,6 +1,7 @@
 import { describe, it, expect, beforeAll, afterAll, vi, beforeEach, afterEach } from 'vitest';
 import { createServer } from 'node:http';
 import '../';
+import './setup';
 
 const server = createServer((request, response) => {
   if (request.url === '/json') {
@@ -59,6 +60,25 @@ describe('Headers', () => {
         ]),
       ).toBeDefined();
     });
+
+    it('should instanciate with lists of values from the runtime', () => {
+      // @ts-expect-error HeadersInit doesn't allow lists of values
+      const headers = new Headers({ 'set-cookie': ['greeting=hello', 'name=world'], vary: ['Accept', 'Origin'] });
+      expect(headers.getSetCookie()).toEqual(['greeting=hello', 'name=world']);
+      expect(headers.get('vary')).toEqual('Accept, Origin');
+    });
+  });
+
+  it('should throw on invalid names and values', () => {
+    const headers = new Headers();
+    expect(() => headers.append('invalid name', 'value')).toThrow(TypeError);
+    expect(() => headers.set('name', 'a\r\nb')).toThrow(TypeError);
+    expect(() => headers.get('@')).toThrow(TypeError);
+  });
+
+  it('should normalize values', () => {
+    const headers = new Headers({ 'x-name': ' \tvalue\r\n' });
+    expect(headers.get('X-Name')).toEqual('value');
   });
 
   it('should append', () => {
@@ -154,6 +174,11 @@ describe('fetch', () => {
     globalThis.LagonAsync = {
       ...globalThis.LagonAsync,
       fetch: vi.fn(),
+      pullFetchBody: vi.fn(),
+    };
+    globalThis.LagonSync = {
+      ...globalThis.LagonSync,
+      abortFetch: vi.fn(),
     };
   });
 
@@ -173,6 +198,7 @@ describe('fetch', () => {
     expect(globalThis.LagonAsync.fetch).toHaveBeenCalledWith({
       m: 'GET',
       u: 'https://google.com',
+      i: expect.any(Number),
     });
   });
 
@@ -192,7 +218,162 @@ describe('fetch', () => {
       m: 'POST',
       u: 'https://google.com',
       b: 'A body',
+      i: expect.any(Number),
+    });
+  });
+
+  it('should call LagonAsync.fetch with a Blob body', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({
+      b: 'Hello',
+      s: 200,
+    });
+
+    await fetch('https://google.com', {
+      method: 'POST',
+      body: new Blob(['A body'], { type: 'text/plain' }),
+    });
+
+    expect(globalThis.LagonAsync.fetch).toHaveBeenCalledWith({
+      m: 'POST',
+      u: 'https://google.com',
+      b: new TextEncoder().encode('A body'),
+      h: new Map([['content-type', ['text/plain']]]),
+      i: expect.any(Number),
+    });
+  });
+
+  it('should call LagonAsync.fetch with a redirect mode', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ s: 200 });
+
+    await fetch('https://google.com', { redirect: 'error' });
+
+    expect(globalThis.LagonAsync.fetch).toHaveBeenCalledWith({
+      m: 'GET',
+      u: 'https://google.com',
+      r: 'error',
+      i: expect.any(Number),
+    });
+    // @ts-expect-error invalid redirect mode
+    await expect(fetch('https://google.com', { redirect: 'invalid' })).rejects.toThrow(TypeError);
+  });
+
+  it('should call LagonAsync.fetch with a timeout', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ s: 200 });
+
+    await fetch('https://google.com', { timeout: 100 });
+
+    expect(globalThis.LagonAsync.fetch).toHaveBeenCalledWith({
+      m: 'GET',
+      u: 'https://google.com',
+      t: 100,
+      i: expect.any(Number),
+    });
+    await expect(fetch('https://google.com', { timeout: NaN })).rejects.toThrow(TypeError);
+  });
+
+  it('should call LagonAsync.fetch with a cache mode and integrity', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ s: 200 });
+
+    await fetch('https://google.com', { cache: 'no-store', integrity: 'sha256-abc' });
+
+    expect(globalThis.LagonAsync.fetch).toHaveBeenCalledWith({
+      m: 'GET',
+      u: 'https://google.com',
+      c: 'no-store',
+      s: 'sha256-abc',
+      i: expect.any(Number),
+    });
+    await expect(fetch('https://google.com', { cache: 'only-if-cached' })).rejects.toThrow(TypeError);
+  });
+
+  it('should set the url and redirected properties', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ s: 200, u: 'https://www.google.com/', r: true });
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ b: new Uint8Array(), s: 200 });
+
+    const response = await fetch('https://google.com');
+    expect(response.url).toEqual('https://www.google.com/');
+    expect(response.redirected).toEqual(true);
+
+    const intercepted = await fetch('https://google.com');
+    expect(intercepted.url).toEqual('https://google.com');
+    expect(intercepted.redirected).toEqual(false);
+  });
+
+  it('should not expose the body of manual redirects', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({
+      s: 302,
+      h: { location: '/login' },
+      u: 'https://google.com',
+      r: false,
     });
+
+    const response = await fetch('https://google.com', { redirect: 'manual' });
+    expect(response.type).toEqual('opaqueredirect');
+    expect(response.headers.get('location')).toEqual('/login');
+    expect(response.body).toBeNull();
+    expect(globalThis.LagonSync.abortFetch).toHaveBeenCalledOnce();
+  });
+
+  it('should not call LagonAsync.fetch with an aborted signal', async () => {
+    await expect(fetch('https://google.com', { signal: AbortSignal.abort() })).rejects.toThrow(DOMException);
+    await expect(fetch('https://google.com', { signal: AbortSignal.abort('reason') })).rejects.toEqual('reason');
+
+    expect(globalThis.LagonAsync.fetch).not.toHaveBeenCalled();
+  });
+
+  it('should stream the response body', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ s: 200 });
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.pullFetchBody.mockResolvedValueOnce(new TextEncoder().encode('Hello, '));
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.pullFetchBody.mockResolvedValueOnce(new TextEncoder().encode('World'));
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.pullFetchBody.mockResolvedValueOnce(undefined);
+
+    const response = await fetch('https://google.com');
+    expect(response.body).toBeInstanceOf(ReadableStream);
+    expect(await response.text()).toEqual('Hello, World');
+  });
+
+  it('should drop the connection when cancelling the body', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ s: 200 });
+
+    const response = await fetch('https://google.com');
+    await response.body?.cancel();
+
+    expect(globalThis.LagonSync.abortFetch).toHaveBeenCalledOnce();
+  });
+
+  it('should not have a body with a null body status', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ s: 204 });
+
+    const response = await fetch('https://google.com');
+    expect(response.body).toBeNull();
+    expect(globalThis.LagonAsync.pullFetchBody).not.toHaveBeenCalled();
+  });
+
+  it('should reject reading the body when aborted', async () => {
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.fetch.mockReturnValueOnce({ s: 200 });
+    // @ts-expect-error LagonAsync is not defined
+    globalThis.LagonAsync.pullFetchBody.mockResolvedValueOnce(new TextEncoder().encode('Hello'));
+
+    const controller = new AbortController();
+    const response = await fetch('https://google.com', { signal: controller.signal });
+    controller.abort();
+
+    await expect(response.text()).rejects.toThrow('This operation was aborted');
+    expect(globalThis.LagonSync.abortFetch).toHaveBeenCalledOnce();
   });
 });
 
@@ -219,4 +400,63 @@ describe('Response', () => {
     expect(response.headers.get('Content-Type')).toEqual('text/plain');
     expect(await response.text()).toEqual('Hello');
   });
+
+  it('should redirect', async () => {
+    const response = Response.redirect('https://lagon.app/docs', 301);
+    expect(response.status).toEqual(301);
+    expect(response.headers.get('Location')).toEqual('https://lagon.app/docs');
+    expect(await response.text()).toEqual('');
+    expect(() => response.headers.set('Location', 'https://example.com')).toThrow(TypeError);
+  });
+
+  it('should redirect with 302 by default', () => {
+    const response = Response.redirect(new URL('https://lagon.app'));
+    expect(response.status).toEqual(302);
+    expect(response.headers.get('Location')).toEqual('https://lagon.app/');
+  });
+
+  it('should throw when redirecting with an invalid status or URL', () => {
+    expect(() => Response.redirect('https://lagon.app', 200)).toThrow(RangeError);
+    expect(() => Response.redirect('/docs')).toThrow(TypeError);
+  });
+
+  it('should create a JSON response', async () => {
+    const response = Response.json({ hello: 'world' }, { status: 201 });
+    expect(response.status).toEqual(201);
+    expect(response.headers.get('content-type')).toEqual('application/json;charset=UTF-8');
+    expect(await response.json()).toEqual({ hello: 'world' });
+  });
+
+  it('should keep the content-type of a JSON response', () => {
+    const response = Response.json([], { headers: { 'Content-Type': 'application/vnd.api+json' } });
+    expect(response.headers.get('content-type')).toEqual('application/vnd.api+json');
+  });
+
+  it('should throw when the JSON data is not serializable', () => {
+    const data: Record<string, unknown> = {};
+    data.self = data;
+
+    expect(() => Response.json(data)).toThrow(TypeError);
+    expect(() => Response.json(undefined)).toThrow(TypeError);
+  });
+
+  it('should reject when parsing an invalid JSON body', async () => {
+    const request = new Request('http://localhost', { method: 'POST', body: '{ invalid' });
+    await expect(request.json()).rejects.toThrow(SyntaxError);
+  });
+});
+
+describe('Request', () => {
+  it('should have a signal that is not aborted', () => {
+    const request = new Request('http://localhost');
+    expect(request.signal).toBeInstanceOf(AbortSignal);
+    expect(request.signal.aborted).toBeFalsy();
+  });
+
+  it('should keep the signal from init', () => {
+    const controller = new AbortController();
+    const request = new Request('http://localhost', { signal: controller.signal });
+    expect(request.signal).toBe(controller.signal);
+    expect(request.clone().signal).toBe(controller.signal);
+  });
 });
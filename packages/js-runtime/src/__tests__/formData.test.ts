@@ -90,6 +90,41 @@ describe('FormData', () => {
     expect(Array.from(fields.values())).toEqual(['b', 'd']);
   });
 
+  it('should store blobs as files', () => {
+    const fields = new FormData();
+    fields.append('blob', new Blob(['hello'], { type: 'text/plain' }));
+    fields.append('named', new Blob(['hello']), 'hello.txt');
+    fields.append('file', new File(['hello'], 'file.txt', { lastModified: 123 }));
+    fields.set('renamed', new File(['hello'], 'file.txt'), 'other.txt');
+
+    const blob = fields.get('blob') as File;
+    expect(blob).toBeInstanceOf(File);
+    expect(blob.name).toEqual('blob');
+    expect(blob.type).toEqual('text/plain');
+    expect((fields.get('named') as File).name).toEqual('hello.txt');
+    expect((fields.get('file') as File).lastModified).toEqual(123);
+    expect((fields.get('renamed') as File).name).toEqual('other.txt');
+  });
+
+  it('should keep empty values', () => {
+    const fields = new FormData();
+    fields.append('a', '');
+    expect(fields.get('a')).toEqual('');
+  });
+
+  it('should encode multipart', async () => {
+    const fields = new FormData();
+    fields.append('hello', 'world');
+    fields.append('file', new File(['content'], 'a.txt', { type: 'text/plain' }));
+
+    const { body, boundary } = globalThis.__lagon__.encodeMultipart(fields);
+    expect(new TextDecoder().decode(body)).toEqual(
+      `--${boundary}\r\nContent-Disposition: form-data; name="hello"\r\n\r\nworld\r\n` +
+        `--${boundary}\r\nContent-Disposition: form-data; name="file"; filename="a.txt"\r\n` +
+        `Content-Type: text/plain\r\n\r\ncontent\r\n--${boundary}--\r\n`,
+    );
+  });
+
   describe('application/x-www-form-urlencoded', () => {
     it('should parse FormData', async () => {
       const fields = await new Response('hello=world', {
@@ -0,0 +1,28 @@
+import { URL as NodeURL } from 'node:url';
+
+// URLs are parsed by the runtime, so we use Node.js' implementation
+// instead, which also follows the WHATWG URL specification
+const components = (url: NodeURL): UrlComponents => ({
+  href: url.href,
+  origin: url.origin,
+  protocol: url.protocol,
+  username: url.username,
+  password: url.password,
+  host: url.host,
+  hostname: url.hostname,
+  port: url.port,
+  pathname: url.pathname,
+  search: url.search,
+  hash: url.hash,
+});
+
+globalThis.LagonSync = {
+  ...globalThis.LagonSync,
+  parseUrl: (url, base) => components(new NodeURL(url, base)),
+  setUrl: (href, component, value) => {
+    const url = new NodeURL(href);
+    url[component] = value;
+
+    return components(url);
+  },
+};
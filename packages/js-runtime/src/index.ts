@@ -4,14 +4,20 @@ import './runtime/encoding/TextDecoder';
 import './runtime/encoding/base64';
 import './runtime/core';
 import './runtime/streams';
-import './runtime/abort';
+import './runtime/encoding/TextEncoderStream';
+import './runtime/encoding/TextDecoderStream';
+import './runtime/compression';
 import './runtime/global/context';
 import './runtime/global/event';
+import './runtime/global/domexception';
+import './runtime/abort';
 import './runtime/global/blob';
 import './runtime/global/file';
 import './runtime/global/console';
 import './runtime/global/process';
 import './runtime/global/crypto';
+import './runtime/global/clone';
+import './runtime/global/performance';
 import './runtime/global/navigator';
 import './runtime/global/timers';
 import './runtime/http/URLSearchParams';
@@ -22,6 +28,9 @@ import './runtime/http/FormData';
 import './runtime/http/Response';
 import './runtime/http/Request';
 import './runtime/http/fetch';
+import './runtime/global/caches';
+import './runtime/global/websocket';
+import './runtime/http/FetchEvent';
 
 // Declare the global functions and variables available
 // on the runtime, that are injected from the Rust code.
@@ -52,20 +61,80 @@ declare global {
 
   var AsyncLocalStorage: AsyncLocalStorageConstructor;
 
+  type UrlComponents = Record<
+    | 'href'
+    | 'origin'
+    | 'protocol'
+    | 'username'
+    | 'password'
+    | 'host'
+    | 'hostname'
+    | 'port'
+    | 'pathname'
+    | 'search'
+    | 'hash',
+    string
+  >;
+
   var LagonSync: {
     log: (level: string, message: string) => void;
-    pullStream: (id: number, done: boolean, chunk?: Uint8Array) => void;
+    pullStream: (id: number, done: boolean, chunk?: Uint8Array) => boolean;
     uuid: () => string;
-    randomValues: <T extends ArrayBufferView | null>(array: T) => T;
+    // Returns as many random bytes as the array's byte length
+    randomValues: (array: ArrayBufferView) => Uint8Array;
     getKeyValue: () => ArrayBuffer;
     queueMicrotask: (callback: () => void) => void;
+    parseUrl: (url: string, base?: string) => UrlComponents;
+    setUrl: (href: string, component: Exclude<keyof UrlComponents, 'origin'>, value: string) => UrlComponents;
+    abortFetch: (id: number) => void;
+    createCodec: (id: number, format: CompressionFormat, decompress: boolean) => void;
+    // Throws a DataCloneError for values that can't be cloned or transferred
+    structuredClone: <T>(value: T, transfer: Transferable[]) => T;
+    performanceNow: () => number;
+    performanceTimeOrigin: () => number;
+    // The entries of the current request, or undefined outside of a request
+    performanceEntries: () => PerformanceEntry[] | undefined;
+    // Keeps the current request alive until the promise settles
+    waitUntil: (promise: Promise<unknown>) => void;
+    // Strings are sent as text frames, and Uint8Arrays as binary frames
+    webSocketSend: (id: number, data: string | Uint8Array) => void;
+    webSocketClose: (id: number, code?: number, reason?: string) => void;
+    // Force-closes the connection without a closing handshake
+    webSocketAbort: (id: number) => void;
+    // Creates the channels of a WebSocket upgraded from the current request
+    webSocketUpgrade: (id: number) => void;
   };
 
   var LagonAsync: {
-    fetch: ({ h, m, b, u }: { h?: Map<string, string>; m: string; b?: string; u: string }) => Promise<{
-      b: Uint8Array;
+    fetch: ({
+      h,
+      m,
+      b,
+      u,
+      i,
+      r,
+      t,
+      c,
+      s,
+    }: {
+      h?: Map<string, string[]>;
+      m: string;
+      b?: string | Uint8Array;
+      u: string;
+      i: number;
+      r?: RequestRedirect;
+      t?: number;
+      c?: RequestCache;
+      // The subresource integrity metadata
+      s?: string;
+    }) => Promise<{
+      // Not set when the body is streamed with pullFetchBody()
+      b?: Uint8Array;
       s: number;
       h?: Record<string, string>;
+      // The final URL and whether the request was redirected, not set when intercepted
+      u?: string;
+      r?: boolean;
     }>;
     sign: (
       algorithm: AlgorithmIdentifier | RsaPssParams | EcdsaParams,
@@ -89,16 +158,105 @@ declare global {
       key: CryptoKey,
       data: BufferSource,
     ) => Promise<ArrayBuffer>;
+    // JWKs are given and returned as JSON. RSA keys also resolve
+    // with their modulus and public exponent
+    importKey: (
+      format: KeyFormat,
+      keyData: string | Uint8Array,
+      algorithm: AlgorithmIdentifier | EcKeyImportParams | RsaHashedImportParams,
+    ) => Promise<ArrayBuffer[]>;
+    exportKey: (format: KeyFormat, key: CryptoKey) => Promise<ArrayBuffer>;
+    // Resolves with the PKCS#8 private key and the SPKI public key
+    generateKey: (
+      algorithm: { name: string; modulusLength: number; publicExponent: Uint8Array } | EcKeyGenParams,
+    ) => Promise<ArrayBuffer[]>;
+    // ECDH derives the whole shared secret when no length is given
+    deriveBits: (
+      algorithm: AlgorithmIdentifier | EcdhKeyDeriveParams | HkdfParams | Pbkdf2Params,
+      key: CryptoKey,
+      length: number | null,
+    ) => Promise<ArrayBuffer>;
     sleep: (ms: number) => Promise<void>;
+    pullBody: (id: number) => Promise<Uint8Array | undefined>;
+    pullFetchBody: (id: number) => Promise<Uint8Array | undefined>;
+    waitStream: (id: number) => Promise<void>;
+    // Resolves with true when the client disconnected before the end of the response
+    waitDisconnect: (id: number) => Promise<boolean>;
+    // Finishes the stream when no chunk is given
+    transformCodec: (id: number, chunk?: Uint8Array) => Promise<Uint8Array>;
+    // Resolves with the subprotocol selected by the server
+    webSocketConnect: ({ u, p, i }: { u: string; p: string[]; i: number }) => Promise<string>;
+    // Resolves with the close code and reason once the connection is closed
+    webSocketReceive: (id: number) => Promise<string | Uint8Array | { c: number; r: string }>;
+    // The name of the cache, and the request used as the key
+    cacheMatch: (
+      name: string,
+      request: { m: string; u: string; h: Map<string, string[]> },
+    ) => Promise<{ b: Uint8Array; s: number; h?: Record<string, string> } | undefined>;
+    cachePut: (
+      name: string,
+      request: { m: string; u: string; h: Map<string, string[]> },
+      response: { s: number; h: Map<string, string[]>; b: Uint8Array },
+    ) => Promise<void>;
+    // Resolves with true when a response was deleted
+    cacheDelete: (name: string, request: { m: string; u: string; h: Map<string, string[]> }) => Promise<boolean>;
+  };
+  var Lagon: {
+    uuidv7: () => string;
+    fs: {
+      readFile: (path: string) => Promise<Uint8Array>;
+    };
+    // Values are returned with the type they were stored with
+    kv: {
+      get: (key: string) => Promise<string | ArrayBuffer | null>;
+      // The ttl is in milliseconds
+      put: (key: string, value: string | ArrayBuffer | ArrayBufferView, options?: { ttl?: number }) => Promise<void>;
+      delete: (key: string) => Promise<void>;
+      list: (prefix?: string, limit?: number, cursor?: string) => Promise<{ keys: string[]; cursor: string | null }>;
+    };
+    queue: {
+      // The message is serialized to JSON
+      send: (queue: string, message: unknown) => Promise<void>;
+    };
   };
   var __lagon__: {
     isIterable: (value: unknown) => value is ArrayBuffer;
     parseMultipart: (headers: Headers, body?: string) => FormData;
+    encodeMultipart: (formData: FormData) => { body: Uint8Array; boundary: string };
     TEXT_ENCODER: TextEncoder;
     TEXT_DECODER: TextDecoder;
+    waitUntil: (promise: unknown) => void;
+    // Set once a fetch listener is registered with addEventListener()
+    fetchHandler?: typeof handler;
+    // The id of the WebSocket upgraded by the response, if any
+    getWebSocketUpgrade?: (response: Response) => number | undefined;
+  };
+  // Not part of the Web APIs, but used by many npm packages
+  var setImmediate: (handler: (...args: any[]) => void, ...args: unknown[]) => number;
+  var clearImmediate: (id: number) => void;
+  // Like Deno.upgradeWebSocket(), the socket has to be accepted before returning the response
+  var upgradeWebSocket: (
+    request: Request,
+    options?: { protocol?: string },
+  ) => {
+    socket: WebSocket;
+    response: Response;
   };
   var __storage__: Map<AsyncContext, unknown>;
-  var handler: (request: Request) => Promise<Response>;
+  // Passed to the handler, to keep running tasks once the response is sent
+  interface HandlerContext {
+    waitUntil(promise: Promise<unknown>): void;
+  }
+
+  var handler: (request: Request, context: HandlerContext) => Promise<Response>;
+  // Passed to the `scheduled` export, e.g by cron triggers
+  interface ScheduledEvent {
+    readonly cron: string;
+    // In milliseconds since the Unix epoch, like Date.now()
+    readonly scheduledTime: number;
+  }
+
+  var scheduled: ((event: ScheduledEvent, context: HandlerContext) => Promise<void> | void) | undefined;
   var masterHandler: (
     id: number,
     request: {
@@ -106,12 +264,50 @@ declare global {
       m: RequestInit['method'];
       h: RequestInit['headers'];
       b: RequestInit['body'];
+      // Set when the body has to be pulled chunk by chunk from the runtime
+      s?: boolean;
     },
   ) => Promise<{
     b: string;
     h: ResponseInit['headers'];
     s: ResponseInit['status'];
+    // Set when the response upgrades the request to a WebSocket
+    w?: number;
   }>;
+  var masterScheduledHandler: (
+    id: number,
+    event: {
+      c: string;
+      t: number;
+    },
+  ) => Promise<{
+    b: string;
+    h: Headers;
+    s: number;
+  }>;
+
+  // Not part of the TypeScript 4.9 lib yet
+  type CompressionFormat = 'deflate' | 'deflate-raw' | 'gzip';
+
+  interface CompressionStream extends GenericTransformStream {
+    readonly readable: ReadableStream<Uint8Array>;
+    readonly writable: WritableStream<BufferSource>;
+  }
+
+  var CompressionStream: {
+    prototype: CompressionStream;
+    new (format: CompressionFormat): CompressionStream;
+  };
+
+  interface DecompressionStream extends GenericTransformStream {
+    readonly readable: ReadableStream<Uint8Array>;
+    readonly writable: WritableStream<BufferSource>;
+  }
+
+  var DecompressionStream: {
+    prototype: DecompressionStream;
+    new (format: CompressionFormat): DecompressionStream;
+  };
 
   interface Response {
     readonly isStream: boolean;
@@ -121,43 +317,106 @@ declare global {
     readonly buffer: Uint8Array;
   }
 
+  interface WebSocket {
+    accept(): void;
+  }
+
+  interface RequestInit {
+    // How long to wait for the response in milliseconds, zero disables the timeout
+    timeout?: number;
+  }
+
   interface Headers {
     immutable: boolean;
   }
+
+  interface CacheStorage {
+    readonly default: Cache;
+  }
+
+  interface URLSearchParams {
+    onUpdate?: (search: string) => void;
+    replaceWith(search: string): void;
+  }
 }
 
 globalThis.masterHandler = async (id, request) => {
-  if (typeof handler !== 'function') {
+  // Functions either export a handler, or register a fetch listener
+  const handle = typeof handler === 'function' ? handler : __lagon__.fetchHandler;
+
+  if (typeof handle !== 'function') {
     throw new Error('Handler function is not defined or is not a function');
   }
 
+  const body = request.s
+    ? new ReadableStream<Uint8Array>({
+        async pull(controller) {
+          try {
+            const chunk = await LagonAsync.pullBody(id);
+
+            if (chunk === undefined) {
+              controller.close();
+            } else {
+              controller.enqueue(chunk);
+            }
+          } catch (error) {
+            controller.error(typeof error === 'string' ? new Error(error) : error);
+          }
+        },
+      })
+    : request.b;
+
+  const controller = new AbortController();
+  let reader: ReadableStreamDefaultReader<Uint8Array> | undefined;
+
+  // Aborts request.signal, and runs the cancel() callback of streamed responses, which stops e.g Server-Sent Events
+  LagonAsync.waitDisconnect(id).then(disconnected => {
+    if (disconnected) {
+      controller.abort();
+      reader?.cancel();
+    }
+  });
+
   const handlerRequest = new Request(request.i, {
     method: request.m,
     headers: request.h,
-    body: request.b,
+    body,
+    signal: controller.signal,
   });
 
-  const response = await handler(handlerRequest);
+  const context: HandlerContext = {
+    waitUntil: promise => __lagon__.waitUntil(promise),
+  };
+
+  const response = await handle(handlerRequest, context);
+  const websocket = __lagon__.getWebSocketUpgrade?.(response);
 
   if (response.body && response.isStream) {
-    const reader = response.body.getReader();
+    const streamReader = response.body.getReader();
+    reader = streamReader;
 
     const read = () => {
-      reader.read().then(({ done, value }) => {
+      streamReader.read().then(async ({ done, value }) => {
         if (done) {
           LagonSync.pullStream(id, done);
           return;
         }
 
-        if (value.byteLength !== 0) {
-          LagonSync.pullStream(id, done, value);
+        // Stop reading until the client caught up, which applies backpressure to the stream
+        if (value.byteLength !== 0 && !LagonSync.pullStream(id, done, value)) {
+          await LagonAsync.waitStream(id);
         }
 
         read();
       });
     };
 
-    read();
+    // The client already disconnected, so the response is never sent
+    if (controller.signal.aborted) {
+      streamReader.cancel();
+    } else {
+      read();
+    }
   } else {
     // @ts-expect-error we reassign body even if it's readonly
     response.body = await response.text();
@@ -167,5 +426,25 @@ globalThis.masterHandler = async (id, request) => {
     b: response.body as unknown as string,
     h: response.headers,
     s: response.status,
+    w: websocket,
+  };
+};
+
+// Resolves with an empty response, so the runtime handles scheduled events like requests
+globalThis.masterScheduledHandler = async (id, event) => {
+  if (typeof scheduled !== 'function') {
+    throw new Error('Scheduled function is not defined or is not a function');
+  }
+
+  const context: HandlerContext = {
+    waitUntil: promise => __lagon__.waitUntil(promise),
+  };
+
+  await scheduled({ cron: event.c, scheduledTime: event.t }, context);
+
+  return {
+    b: '',
+    h: new Headers(),
+    s: 204,
   };
 };
@@ -1,6 +1,82 @@
 (globalThis => {
-  const { AbortController, AbortSignal } = require('abortcontroller-polyfill/dist/abortcontroller');
+  // Signals can only be created by AbortController and the static methods
+  const INTERNAL = Symbol('AbortSignal');
 
-  globalThis.AbortController = AbortController;
-  globalThis.AbortSignal = AbortSignal;
+  class LagonAbortSignal extends EventTarget {
+    aborted = false;
+    reason: any = undefined;
+    onabort: ((this: AbortSignal, event: Event) => any) | null = null;
+
+    constructor(key?: symbol) {
+      super();
+
+      if (key !== INTERNAL) {
+        throw new TypeError('Illegal constructor');
+      }
+    }
+
+    static abort(reason?: any): AbortSignal {
+      const signal = new LagonAbortSignal(INTERNAL);
+      signalAbort(signal, reason);
+
+      return signal;
+    }
+
+    static timeout(milliseconds: number): AbortSignal {
+      const signal = new LagonAbortSignal(INTERNAL);
+
+      setTimeout(() => {
+        signalAbort(signal, new DOMException('The operation timed out.', 'TimeoutError'));
+      }, milliseconds);
+
+      return signal;
+    }
+
+    static any(signals: Iterable<AbortSignal>): AbortSignal {
+      const signal = new LagonAbortSignal(INTERNAL);
+      const sources = [...signals];
+      const aborted = sources.find(source => source.aborted);
+
+      if (aborted) {
+        signalAbort(signal, aborted.reason);
+        return signal;
+      }
+
+      for (const source of sources) {
+        source.addEventListener('abort', () => signalAbort(signal, source.reason), { once: true });
+      }
+
+      return signal;
+    }
+
+    throwIfAborted() {
+      if (this.aborted) {
+        throw this.reason;
+      }
+    }
+  }
+
+  // https://dom.spec.whatwg.org/#abortsignal-signal-abort
+  const signalAbort = (signal: LagonAbortSignal, reason: any) => {
+    if (signal.aborted) {
+      return;
+    }
+
+    signal.aborted = true;
+    signal.reason = reason !== undefined ? reason : new DOMException('This operation was aborted', 'AbortError');
+
+    const event = new Event('abort');
+    signal.onabort?.call(signal, event);
+    signal.dispatchEvent(event);
+  };
+
+  globalThis.AbortSignal = LagonAbortSignal;
+
+  globalThis.AbortController = class {
+    readonly signal = new LagonAbortSignal(INTERNAL);
+
+    abort(reason?: any) {
+      signalAbort(this.signal, reason);
+    }
+  };
 })(globalThis);
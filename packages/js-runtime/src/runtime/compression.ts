@@ -0,0 +1,78 @@
+(globalThis => {
+  const FORMATS = ['gzip', 'deflate', 'deflate-raw'];
+  let codecCounter = 0;
+
+  const toUint8Array = (chunk: BufferSource): Uint8Array => {
+    if (chunk instanceof Uint8Array) {
+      return chunk;
+    }
+
+    if (ArrayBuffer.isView(chunk)) {
+      return new Uint8Array(chunk.buffer, chunk.byteOffset, chunk.byteLength);
+    }
+
+    if (chunk instanceof ArrayBuffer) {
+      return new Uint8Array(chunk);
+    }
+
+    throw new TypeError('The provided value is not of type BufferSource');
+  };
+
+  // Chunks are (de)compressed by the runtime, outside of the JS thread
+  const createCodec = (format: CompressionFormat, decompress: boolean) => {
+    format = String(format) as CompressionFormat;
+
+    if (!FORMATS.includes(format)) {
+      throw new TypeError(`Unsupported compression format: ${format}`);
+    }
+
+    const id = codecCounter++;
+    LagonSync.createCodec(id, format, decompress);
+
+    const transform = async (controller: TransformStreamDefaultController<Uint8Array>, chunk?: Uint8Array) => {
+      try {
+        const output = await LagonAsync.transformCodec(id, chunk);
+
+        if (output.length > 0) {
+          controller.enqueue(output);
+        }
+      } catch (error) {
+        throw typeof error === 'string' ? new TypeError(error) : error;
+      }
+    };
+
+    return new TransformStream<BufferSource, Uint8Array>({
+      transform(chunk, controller) {
+        return transform(controller, toUint8Array(chunk));
+      },
+      // Writes the end of the compressed data, or checks that the compressed data is complete
+      flush(controller) {
+        return transform(controller);
+      },
+    });
+  };
+
+  globalThis.CompressionStream = class {
+    readonly readable: ReadableStream<Uint8Array>;
+    readonly writable: WritableStream<BufferSource>;
+
+    constructor(format: CompressionFormat) {
+      const { readable, writable } = createCodec(format, false);
+
+      this.readable = readable;
+      this.writable = writable;
+    }
+  };
+
+  globalThis.DecompressionStream = class {
+    readonly readable: ReadableStream<Uint8Array>;
+    readonly writable: WritableStream<BufferSource>;
+
+    constructor(format: CompressionFormat) {
+      const { readable, writable } = createCodec(format, true);
+
+      this.readable = readable;
+      this.writable = writable;
+    }
+  };
+})(globalThis);
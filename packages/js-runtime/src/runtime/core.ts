@@ -63,6 +63,50 @@
   const TEXT_ENCODER = new TextEncoder();
   const TEXT_DECODER = new TextDecoder();
 
+  // https://html.spec.whatwg.org/multipage/form-control-infrastructure.html#multipart-form-data
+  const escapeMultipartName = (name: string) =>
+    name.replace(/\n/g, '%0A').replace(/\r/g, '%0D').replace(/"/g, '%22');
+
+  const encodeMultipart = (formData: FormData): { body: Uint8Array; boundary: string } => {
+    const randomHex = () => Math.random().toString(16).slice(2);
+    const boundary = `----LagonFormBoundary${randomHex()}${randomHex()}`;
+    const chunks: Uint8Array[] = [];
+
+    for (const [name, value] of formData) {
+      let part = `--${boundary}\r\nContent-Disposition: form-data; name="${escapeMultipartName(name)}"`;
+
+      if (typeof value === 'string') {
+        part += `\r\n\r\n${value.replace(/\r?\n|\r/g, '\r\n')}\r\n`;
+        chunks.push(TEXT_ENCODER.encode(part));
+      } else {
+        part += `; filename="${escapeMultipartName(value.name)}"`;
+        part += `\r\nContent-Type: ${value.type || 'application/octet-stream'}\r\n\r\n`;
+        chunks.push(TEXT_ENCODER.encode(part), value.buffer, TEXT_ENCODER.encode('\r\n'));
+      }
+    }
+
+    chunks.push(TEXT_ENCODER.encode(`--${boundary}--\r\n`));
+
+    const body = new Uint8Array(chunks.reduce((size, chunk) => size + chunk.byteLength, 0));
+    let offset = 0;
+
+    for (const chunk of chunks) {
+      body.set(chunk, offset);
+      offset += chunk.byteLength;
+    }
+
+    return { body, boundary };
+  };
+
+  // Keep the current request alive until the promise settles, even once its
+  // response is sent. Rejections are logged, since there's no response left
+  // to send the error to
+  const waitUntil = (promise: unknown) => {
+    LagonSync.waitUntil(
+      Promise.resolve(promise).catch(error => console.error('Uncaught error in waitUntil():', error)),
+    );
+  };
+
   // https://developer.mozilla.org/en-US/docs/Web/API/WorkerGlobalScope/self
   // @ts-expect-error Workers have a global `self` property, which we assign
   // to `globalThis` because we don't implement all the Workers APIs
@@ -70,7 +114,9 @@
   globalThis.__lagon__ = {
     isIterable,
     parseMultipart,
+    encodeMultipart,
     TEXT_ENCODER,
     TEXT_DECODER,
+    waitUntil,
   };
 })(globalThis);
@@ -1,53 +1,148 @@
 (globalThis => {
-  // Inspired by https://gitlab.istic.univ-rennes1.fr/16004760/genie-logiciel-iot/-/blob/a682f438b34eaa9e80655c321e31cf78c7538879/Raspberry-Pi/node_modules/fast-text-encoding/text.js
+  // https://encoding.spec.whatwg.org/#names-and-labels
+  const LABELS = ['unicode-1-1-utf-8', 'unicode11utf8', 'unicode20utf8', 'utf-8', 'utf8', 'x-unicode20utf8'];
+  const REPLACEMENT_CHARACTER = 0xfffd;
+  // Avoid exceeding the maximum number of arguments of String.fromCharCode()
+  const CHUNK_SIZE = 0x8000;
+
+  // Follows https://encoding.spec.whatwg.org/#utf-8-decoder, keeping the
+  // state of incomplete sequences between calls when streaming
   globalThis.TextDecoder = class {
     readonly encoding = 'utf-8';
-    readonly fatal = true;
-    readonly ignoreBOM = true;
-
-    decode(input: ArrayBuffer): string {
-      const bytes = new Uint8Array(input);
-      let pos = 0;
-      const len = bytes.length;
-      const out = [];
-
-      while (pos < len) {
-        const byte1 = bytes[pos++];
-        if (byte1 === 0) {
-          break; // NULL
-        }
+    readonly fatal: boolean;
+    readonly ignoreBOM: boolean;
+
+    private codePoint = 0;
+    private bytesSeen = 0;
+    private bytesNeeded = 0;
+    private lowerBoundary = 0x80;
+    private upperBoundary = 0xbf;
+    private bomSeen = false;
+    private streaming = false;
+
+    constructor(label = 'utf-8', options?: TextDecoderOptions) {
+      if (!LABELS.includes(String(label).trim().toLowerCase())) {
+        throw new RangeError(`The encoding label provided ('${label}') is invalid.`);
+      }
+
+      this.fatal = !!options?.fatal;
+      this.ignoreBOM = !!options?.ignoreBOM;
+    }
+
+    private reset() {
+      this.codePoint = 0;
+      this.bytesSeen = 0;
+      this.bytesNeeded = 0;
+      this.lowerBoundary = 0x80;
+      this.upperBoundary = 0xbf;
+    }
+
+    private error(output: number[]) {
+      if (this.fatal) {
+        this.reset();
+        this.bomSeen = false;
+        this.streaming = false;
+
+        throw new TypeError('The encoded data was not valid.');
+      }
+
+      output.push(REPLACEMENT_CHARACTER);
+    }
+
+    decode(input?: BufferSource, options?: TextDecodeOptions): string {
+      if (!this.streaming) {
+        this.reset();
+        this.bomSeen = false;
+      }
+
+      this.streaming = !!options?.stream;
+
+      let bytes: Uint8Array;
+
+      if (input === undefined) {
+        bytes = new Uint8Array();
+      } else if (ArrayBuffer.isView(input)) {
+        bytes = new Uint8Array(input.buffer, input.byteOffset, input.byteLength);
+      } else {
+        bytes = new Uint8Array(input);
+      }
 
-        if ((byte1 & 0x80) === 0) {
-          // 1-byte
-          out.push(byte1);
-        } else if ((byte1 & 0xe0) === 0xc0) {
-          // 2-byte
-          const byte2 = bytes[pos++] & 0x3f;
-          out.push(((byte1 & 0x1f) << 6) | byte2);
-        } else if ((byte1 & 0xf0) === 0xe0) {
-          const byte2 = bytes[pos++] & 0x3f;
-          const byte3 = bytes[pos++] & 0x3f;
-          out.push(((byte1 & 0x1f) << 12) | (byte2 << 6) | byte3);
-        } else if ((byte1 & 0xf8) === 0xf0) {
-          const byte2 = bytes[pos++] & 0x3f;
-          const byte3 = bytes[pos++] & 0x3f;
-          const byte4 = bytes[pos++] & 0x3f;
-
-          // this can be > 0xffff, so possibly generate surrogates
-          let codepoint = ((byte1 & 0x07) << 0x12) | (byte2 << 0x0c) | (byte3 << 0x06) | byte4;
-          if (codepoint > 0xffff) {
-            // codepoint &= ~0x10000;
-            codepoint -= 0x10000;
-            out.push(((codepoint >>> 10) & 0x3ff) | 0xd800);
-            codepoint = 0xdc00 | (codepoint & 0x3ff);
+      const codePoints: number[] = [];
+
+      for (let i = 0; i < bytes.length; i++) {
+        const byte = bytes[i];
+
+        if (this.bytesNeeded === 0) {
+          if (byte <= 0x7f) {
+            codePoints.push(byte);
+          } else if (byte >= 0xc2 && byte <= 0xdf) {
+            this.bytesNeeded = 1;
+            this.codePoint = byte & 0x1f;
+          } else if (byte >= 0xe0 && byte <= 0xef) {
+            if (byte === 0xe0) {
+              this.lowerBoundary = 0xa0;
+            } else if (byte === 0xed) {
+              this.upperBoundary = 0x9f;
+            }
+
+            this.bytesNeeded = 2;
+            this.codePoint = byte & 0xf;
+          } else if (byte >= 0xf0 && byte <= 0xf4) {
+            if (byte === 0xf0) {
+              this.lowerBoundary = 0x90;
+            } else if (byte === 0xf4) {
+              this.upperBoundary = 0x8f;
+            }
+
+            this.bytesNeeded = 3;
+            this.codePoint = byte & 0x7;
+          } else {
+            this.error(codePoints);
           }
-          out.push(codepoint);
-        } else {
-          // FIXME: we're ignoring this
+
+          continue;
+        }
+
+        if (byte < this.lowerBoundary || byte > this.upperBoundary) {
+          // The byte starts the next sequence, so it's processed again
+          this.reset();
+          this.error(codePoints);
+          i--;
+          continue;
+        }
+
+        this.lowerBoundary = 0x80;
+        this.upperBoundary = 0xbf;
+        this.codePoint = (this.codePoint << 6) | (byte & 0x3f);
+        this.bytesSeen++;
+
+        if (this.bytesSeen === this.bytesNeeded) {
+          codePoints.push(this.codePoint);
+          this.reset();
         }
       }
 
-      return String.fromCharCode.apply(null, out);
+      if (!this.streaming && this.bytesNeeded !== 0) {
+        this.reset();
+        this.error(codePoints);
+      }
+
+      // The BOM is only stripped at the start of the stream
+      if (codePoints.length > 0 && !this.bomSeen) {
+        this.bomSeen = true;
+
+        if (!this.ignoreBOM && codePoints[0] === 0xfeff) {
+          codePoints.shift();
+        }
+      }
+
+      let output = '';
+
+      for (let i = 0; i < codePoints.length; i += CHUNK_SIZE) {
+        output += String.fromCodePoint(...codePoints.slice(i, i + CHUNK_SIZE));
+      }
+
+      return output;
     }
   };
 })(globalThis);
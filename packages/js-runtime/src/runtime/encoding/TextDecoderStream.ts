@@ -0,0 +1,38 @@
+(globalThis => {
+  // Incomplete sequences at the end of a chunk are decoded with the next one
+  globalThis.TextDecoderStream = class {
+    readonly encoding: string;
+    readonly fatal: boolean;
+    readonly ignoreBOM: boolean;
+    readonly readable: ReadableStream<string>;
+    readonly writable: WritableStream<BufferSource>;
+
+    constructor(label?: string, options?: TextDecoderOptions) {
+      const decoder = new TextDecoder(label, options);
+
+      this.encoding = decoder.encoding;
+      this.fatal = decoder.fatal;
+      this.ignoreBOM = decoder.ignoreBOM;
+
+      const { readable, writable } = new TransformStream<BufferSource, string>({
+        transform(chunk, controller) {
+          const text = decoder.decode(chunk, { stream: true });
+
+          if (text) {
+            controller.enqueue(text);
+          }
+        },
+        flush(controller) {
+          const text = decoder.decode();
+
+          if (text) {
+            controller.enqueue(text);
+          }
+        },
+      });
+
+      this.readable = readable;
+      this.writable = writable;
+    }
+  };
+})(globalThis);
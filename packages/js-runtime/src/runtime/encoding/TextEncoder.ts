@@ -27,9 +27,10 @@
               value = ((value & 0x3ff) << 10) + (extra & 0x3ff) + 0x10000;
             }
           }
-          if (value >= 0xd800 && value <= 0xdbff) {
-            continue; // drop lone surrogate
-          }
+        }
+
+        if (value >= 0xd800 && value <= 0xdfff) {
+          value = 0xfffd; // replace lone surrogates
         }
 
         // expand the buffer if we couldn't write 4 bytes
@@ -0,0 +1,40 @@
+(globalThis => {
+  const isHighSurrogate = (code: number) => code >= 0xd800 && code <= 0xdbff;
+
+  // A high surrogate at the end of a chunk is encoded with the next one
+  globalThis.TextEncoderStream = class {
+    readonly encoding = 'utf-8';
+    readonly readable: ReadableStream<Uint8Array>;
+    readonly writable: WritableStream<string>;
+
+    constructor() {
+      const encoder = new TextEncoder();
+      let pending = '';
+
+      const { readable, writable } = new TransformStream<string, Uint8Array>({
+        transform(chunk, controller) {
+          let input = pending + String(chunk);
+          pending = '';
+
+          if (isHighSurrogate(input.charCodeAt(input.length - 1))) {
+            pending = input.slice(-1);
+            input = input.slice(0, -1);
+          }
+
+          if (input) {
+            controller.enqueue(encoder.encode(input));
+          }
+        },
+        flush(controller) {
+          // A lone surrogate is replaced by U+FFFD
+          if (pending) {
+            controller.enqueue(encoder.encode(pending));
+          }
+        },
+      });
+
+      this.readable = readable;
+      this.writable = writable;
+    }
+  };
+})(globalThis);
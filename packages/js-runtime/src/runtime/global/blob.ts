@@ -10,8 +10,10 @@
         const chunks = blobParts.map(blobPart => {
           if (typeof blobPart === 'string') {
             return globalThis.__lagon__.TEXT_ENCODER.encode(blobPart);
-          } else if (blobPart instanceof ArrayBuffer || blobPart instanceof Uint8Array) {
+          } else if (blobPart instanceof ArrayBuffer) {
             return new Uint8Array(blobPart);
+          } else if (ArrayBuffer.isView(blobPart)) {
+            return new Uint8Array(blobPart.buffer, blobPart.byteOffset, blobPart.byteLength);
           } else if (blobPart instanceof Blob) {
             return blobPart.buffer as Uint8Array;
           } else {
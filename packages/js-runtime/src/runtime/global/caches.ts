@@ -0,0 +1,138 @@
+(globalThis => {
+  // Set-Cookie values are iterated separately, so they aren't joined
+  const headersMap = (headers: Headers) => {
+    const map = new Map<string, string[]>();
+
+    for (const [key, value] of headers) {
+      map.set(key, [...(map.get(key) || []), value]);
+    }
+
+    return map;
+  };
+
+  const toRequest = (input: RequestInfo | URL) => (input instanceof Request ? input : new Request(input));
+
+  // URLs are normalized, so `https://example.com` and `https://example.com/` are the same
+  const toCacheRequest = (request: Request) => ({
+    m: request.method,
+    u: new URL(request.url).href,
+    h: headersMap(request.headers),
+  });
+
+  const call = async <T>(promise: Promise<T>) => {
+    try {
+      return await promise;
+    } catch (error) {
+      throw typeof error === 'string' ? new Error(error) : error;
+    }
+  };
+
+  // Responses are stored by the runtime's backend, shared by all the requests. Without
+  // a backend, match() always resolves with undefined and put() doesn't store anything
+  class LagonCache {
+    private readonly name: string;
+
+    constructor(name: string) {
+      this.name = name;
+    }
+
+    async match(input: RequestInfo | URL, options?: CacheQueryOptions): Promise<Response | undefined> {
+      const request = toRequest(input);
+
+      if (request.method !== 'GET' && !options?.ignoreMethod) {
+        return undefined;
+      }
+
+      const response = await call(LagonAsync.cacheMatch(this.name, toCacheRequest(request)));
+
+      if (response === undefined) {
+        return undefined;
+      }
+
+      return new Response(response.b, {
+        headers: response.h,
+        status: response.s,
+      });
+    }
+
+    async matchAll(input?: RequestInfo | URL, options?: CacheQueryOptions): Promise<Response[]> {
+      if (input === undefined) {
+        throw new TypeError('Listing the responses of a cache is not supported');
+      }
+
+      const response = await this.match(input, options);
+
+      return response ? [response] : [];
+    }
+
+    // Responses with a `Cache-Control: no-store` header aren't stored
+    async put(input: RequestInfo | URL, response: Response): Promise<void> {
+      const request = toRequest(input);
+
+      if (request.method !== 'GET') {
+        throw new TypeError(`Cannot cache the response of a ${request.method} request`);
+      }
+
+      if (response.status === 206) {
+        throw new TypeError('Cannot cache a partial response');
+      }
+
+      const vary = response.headers.get('vary') || '';
+
+      if (vary.split(',').some(name => name.trim() === '*')) {
+        throw new TypeError('Cannot cache a response with a `Vary: *` header');
+      }
+
+      if (response.bodyUsed) {
+        throw new TypeError('The body of the response has already been read');
+      }
+
+      const body = new Uint8Array(await response.arrayBuffer());
+
+      await call(
+        LagonAsync.cachePut(this.name, toCacheRequest(request), {
+          s: response.status,
+          h: headersMap(response.headers),
+          b: body,
+        }),
+      );
+    }
+
+    async add(input: RequestInfo | URL): Promise<void> {
+      const request = toRequest(input);
+      const response = await fetch(request.url, { headers: request.headers });
+
+      if (!response.ok) {
+        throw new TypeError(`Cannot cache a response with the status ${response.status}`);
+      }
+
+      await this.put(request, response);
+    }
+
+    async addAll(inputs: Iterable<RequestInfo | URL>): Promise<void> {
+      await Promise.all(Array.from(inputs, input => this.add(input)));
+    }
+
+    async delete(input: RequestInfo | URL, options?: CacheQueryOptions): Promise<boolean> {
+      const request = toRequest(input);
+
+      if (request.method !== 'GET' && !options?.ignoreMethod) {
+        return false;
+      }
+
+      return call(LagonAsync.cacheDelete(this.name, toCacheRequest(request)));
+    }
+  }
+
+  // Like Cloudflare Workers, caches can't be listed and `caches.default` is always available
+  class LagonCacheStorage {
+    readonly default = new LagonCache('default');
+
+    async open(name: string): Promise<LagonCache> {
+      return new LagonCache(String(name));
+    }
+  }
+
+  // @ts-expect-error keys() and the methods listing the caches aren't supported
+  globalThis.caches = new LagonCacheStorage();
+})(globalThis);
@@ -0,0 +1,8 @@
+(globalThis => {
+  globalThis.structuredClone = <T>(value: T, options?: StructuredSerializeOptions): T => {
+    // Spread the transfer list to throw a TypeError if it isn't iterable
+    const transfer = options?.transfer === undefined ? [] : [...options.transfer];
+
+    return LagonSync.structuredClone(value, transfer);
+  };
+})(globalThis);
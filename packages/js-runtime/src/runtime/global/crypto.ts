@@ -4,10 +4,169 @@ interface CryptoKey {
 
 /* eslint-disable @typescript-eslint/no-unused-vars */
 (globalThis => {
-  const getRandomValues = <T extends ArrayBufferView | null>(array: T): T => LagonSync.randomValues(array);
+  const INTEGER_ARRAYS = [
+    Int8Array,
+    Uint8Array,
+    Uint8ClampedArray,
+    Int16Array,
+    Uint16Array,
+    Int32Array,
+    Uint32Array,
+    BigInt64Array,
+    BigUint64Array,
+  ];
+  // The maximum number of bytes getRandomValues() can fill at once
+  const MAX_RANDOM_BYTES = 65536;
+
+  const getRandomValues = <T extends ArrayBufferView | null>(array: T): T => {
+    if (!ArrayBuffer.isView(array) || array instanceof DataView) {
+      throw new TypeError("Parameter 1 is not of type 'TypedArray'");
+    }
+
+    if (!INTEGER_ARRAYS.some(type => array instanceof type)) {
+      throw new DOMException('The provided ArrayBufferView is not an integer array type', 'TypeMismatchError');
+    }
+
+    if (array.byteLength > MAX_RANDOM_BYTES) {
+      throw new DOMException(
+        `The ArrayBufferView's byte length (${array.byteLength}) exceeds the number of bytes of entropy available via this API (${MAX_RANDOM_BYTES})`,
+        'QuotaExceededError',
+      );
+    }
+
+    // Fill the array in place, whatever the size of its elements
+    new Uint8Array(array.buffer, array.byteOffset, array.byteLength).set(LagonSync.randomValues(array));
+
+    return array;
+  };
   const randomUUID = () => LagonSync.uuid();
 
   const SYMMETRIC_ALGORITHMS = ['HMAC', 'AES-CBC', 'AES-CTR', 'AES-GCM', 'AES-KW'];
+  const RSA_ALGORITHMS = ['RSASSA-PKCS1-v1_5', 'RSA-PSS', 'RSA-OAEP'];
+  const EC_ALGORITHMS = ['ECDSA', 'ECDH'];
+  // Algorithms whose keys are imported and exported by the runtime
+  const ASYMMETRIC_ALGORITHMS = [...EC_ALGORITHMS, 'Ed25519', ...RSA_ALGORITHMS];
+  // Algorithms whose keys are only used to derive bits and other keys
+  const KDF_ALGORITHMS = ['PBKDF2', 'HKDF'];
+  const NAMED_CURVES = ['P-256', 'P-384'];
+  const RSA_HASHES = ['SHA-256', 'SHA-384', 'SHA-512'];
+  // The default length of derived HMAC keys
+  const HASH_BLOCK_SIZES: Record<string, number> = {
+    'SHA-1': 512,
+    'SHA-256': 512,
+    'SHA-384': 1024,
+    'SHA-512': 1024,
+  };
+
+  const SIGNATURE_USAGES: Record<KeyType, KeyUsage[]> = { private: ['sign'], public: ['verify'], secret: [] };
+  const KEY_USAGES: Record<string, Record<KeyType, KeyUsage[]>> = {
+    ECDSA: SIGNATURE_USAGES,
+    ECDH: { private: ['deriveKey', 'deriveBits'], public: [], secret: [] },
+    Ed25519: SIGNATURE_USAGES,
+    'RSASSA-PKCS1-v1_5': SIGNATURE_USAGES,
+    'RSA-PSS': SIGNATURE_USAGES,
+    'RSA-OAEP': { private: ['decrypt', 'unwrapKey'], public: ['encrypt', 'wrapKey'], secret: [] },
+  };
+
+  // The JWK `alg` prefixes, followed by the hash size (e.g. RS256)
+  const RSA_JWK_ALGS: Record<string, string> = {
+    'RSASSA-PKCS1-v1_5': 'RS',
+    'RSA-PSS': 'PS',
+    'RSA-OAEP': 'RSA-OAEP-',
+  };
+
+  const getAlgorithmName = (algorithm: AlgorithmIdentifier | KeyAlgorithm): string =>
+    typeof algorithm === 'string' ? algorithm : algorithm.name;
+
+  const getJwkAlg = (name: string, hash: string): string => RSA_JWK_ALGS[name] + hash.slice('SHA-'.length);
+
+  // The modulus is big-endian, without leading zeros
+  const getModulusLength = (modulus: Uint8Array): number => modulus.length * 8 - (Math.clz32(modulus[0]) - 24);
+
+  const toUint8Array = (data: BufferSource): Uint8Array => {
+    if (ArrayBuffer.isView(data)) {
+      return new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+    }
+
+    return new Uint8Array(data);
+  };
+
+  // The runtime rejects with a string, e.g. when the key data is invalid
+  const rejectWith =
+    (name: string) =>
+    (error: unknown): never => {
+      throw new DOMException(String(error), name);
+    };
+
+  // Keys can only be used with their algorithm and, except for HMAC and AES keys, for their usages
+  const checkKey = (algorithm: AlgorithmIdentifier, key: CryptoKey, usage: KeyUsage) => {
+    const name = getAlgorithmName(key.algorithm);
+
+    if (getAlgorithmName(algorithm) !== name) {
+      throw new DOMException("The algorithm doesn't match the key's algorithm", 'InvalidAccessError');
+    }
+
+    if (!SYMMETRIC_ALGORITHMS.includes(name) && !key.usages.includes(usage)) {
+      throw new DOMException(`The key can't be used to ${usage}`, 'InvalidAccessError');
+    }
+  };
+
+  // The runtime only reads Uint8Array parameters
+  const BUFFER_PARAMETERS = ['label', 'salt', 'info'];
+
+  const toRuntimeAlgorithm = <T extends AlgorithmIdentifier>(algorithm: T): T => {
+    if (typeof algorithm === 'string') {
+      return algorithm;
+    }
+
+    const runtimeAlgorithm: Record<string, unknown> = { ...algorithm };
+
+    for (const parameter of BUFFER_PARAMETERS) {
+      if (runtimeAlgorithm[parameter] !== undefined) {
+        runtimeAlgorithm[parameter] = toUint8Array(runtimeAlgorithm[parameter] as BufferSource);
+      }
+    }
+
+    return runtimeAlgorithm as T;
+  };
+
+  const encrypt = (
+    algorithm: AlgorithmIdentifier | RsaOaepParams | AesCtrParams | AesCbcParams | AesGcmParams,
+    key: CryptoKey,
+    data: BufferSource,
+  ): Promise<ArrayBuffer> =>
+    LagonAsync.encrypt(toRuntimeAlgorithm(algorithm), key, toUint8Array(data)).catch(rejectWith('OperationError'));
+
+  const decrypt = (
+    algorithm: AlgorithmIdentifier | RsaOaepParams | AesCtrParams | AesCbcParams | AesGcmParams,
+    key: CryptoKey,
+    data: BufferSource,
+  ): Promise<ArrayBuffer> =>
+    LagonAsync.decrypt(toRuntimeAlgorithm(algorithm), key, toUint8Array(data)).catch(rejectWith('OperationError'));
+
+  const deriveBits = (
+    algorithm: AlgorithmIdentifier | EcdhKeyDeriveParams | HkdfParams | Pbkdf2Params,
+    baseKey: CryptoKey,
+    length: number | null,
+    usage: KeyUsage,
+  ): Promise<ArrayBuffer> => {
+    checkKey(algorithm, baseKey, usage);
+
+    if (getAlgorithmName(algorithm) === 'ECDH') {
+      const publicKey = (algorithm as EcdhKeyDeriveParams).public;
+
+      if (
+        !(publicKey instanceof CryptoKey) ||
+        publicKey.type !== 'public' ||
+        getAlgorithmName(publicKey.algorithm) !== 'ECDH' ||
+        (publicKey.algorithm as EcKeyAlgorithm).namedCurve !== (baseKey.algorithm as EcKeyAlgorithm).namedCurve
+      ) {
+        throw new DOMException('The public key must be an ECDH public key on the same curve', 'InvalidAccessError');
+      }
+    }
+
+    return LagonAsync.deriveBits(toRuntimeAlgorithm(algorithm), baseKey, length).catch(rejectWith('OperationError'));
+  };
 
   globalThis.CryptoKey = class {
     readonly algorithm: KeyAlgorithm;
@@ -15,18 +174,25 @@ interface CryptoKey {
     readonly type: KeyType;
     readonly usages: KeyUsage[];
 
-    // Store the randomly generate key value here
+    // Store the imported key (as PKCS#8 or SPKI for asymmetric keys),
+    // or a randomly generated key value here
     readonly keyValue: ArrayBuffer;
 
     // Trick to make TypeScript happy, CryptoKey constructor is normally empty
     // but we need to construct it at some point.
-    constructor(algorithm?: KeyAlgorithm, extractable?: boolean, type?: KeyType, usages?: KeyUsage[]) {
+    constructor(
+      algorithm?: KeyAlgorithm,
+      extractable?: boolean,
+      type?: KeyType,
+      usages?: KeyUsage[],
+      keyValue?: ArrayBuffer,
+    ) {
       this.algorithm = algorithm!;
       this.extractable = extractable!;
       this.type = type!;
       this.usages = usages!;
 
-      this.keyValue = LagonSync.getKeyValue();
+      this.keyValue = keyValue ?? LagonSync.getKeyValue();
     }
   };
 
@@ -36,15 +202,17 @@ interface CryptoKey {
       key: CryptoKey,
       data: BufferSource,
     ): Promise<ArrayBuffer> {
-      return LagonAsync.decrypt(algorithm, key, data);
+      checkKey(algorithm, key, 'decrypt');
+
+      return decrypt(algorithm, key, data);
     }
 
     async deriveBits(
       algorithm: AlgorithmIdentifier | EcdhKeyDeriveParams | HkdfParams | Pbkdf2Params,
       baseKey: CryptoKey,
-      length: number,
+      length: number | null,
     ): Promise<ArrayBuffer> {
-      throw new Error('Not implemented');
+      return deriveBits(algorithm, baseKey, length, 'deriveBits');
     }
 
     async deriveKey(
@@ -54,7 +222,35 @@ interface CryptoKey {
       extractable: boolean,
       keyUsages: Iterable<KeyUsage>,
     ): Promise<CryptoKey> {
-      throw new Error('Not implemented');
+      const name = getAlgorithmName(derivedKeyType);
+      let keyAlgorithm: AesKeyAlgorithm | HmacKeyAlgorithm;
+
+      if (name === 'AES-GCM') {
+        const { length } = derivedKeyType as AesDerivedKeyParams;
+
+        // AES-GCM keys are always AES-256 keys
+        if (length !== 256) {
+          throw new DOMException('Only 256-bit AES-GCM keys are supported', 'NotSupportedError');
+        }
+
+        keyAlgorithm = { name, length };
+      } else if (name === 'HMAC') {
+        const hash = getAlgorithmName((derivedKeyType as HmacImportParams).hash);
+        const length = (derivedKeyType as HmacImportParams).length ?? HASH_BLOCK_SIZES[hash];
+
+        if (length === undefined) {
+          throw new DOMException(`Hash ${hash} is not supported`, 'NotSupportedError');
+        }
+
+        keyAlgorithm = { name, hash: { name: hash }, length };
+      } else {
+        throw new DOMException(`${name} keys can't be derived`, 'NotSupportedError');
+      }
+
+      const keyValue = await deriveBits(algorithm, baseKey, keyAlgorithm.length, 'deriveKey');
+
+      // @ts-expect-error CryptoKey constructor is empty, but we know our implementation is not
+      return new CryptoKey(keyAlgorithm, extractable, 'secret', [...keyUsages], keyValue);
     }
 
     async digest(algorithm: AlgorithmIdentifier, data: BufferSource): Promise<ArrayBuffer> {
@@ -66,7 +262,9 @@ interface CryptoKey {
       key: CryptoKey,
       data: BufferSource,
     ): Promise<ArrayBuffer> {
-      return LagonAsync.encrypt(algorithm, key, data);
+      checkKey(algorithm, key, 'encrypt');
+
+      return encrypt(algorithm, key, data);
     }
 
     async exportKey(format: 'jwk', key: CryptoKey): Promise<JsonWebKey>;
@@ -76,6 +274,31 @@ interface CryptoKey {
         throw new TypeError('Key is not extractable');
       }
 
+      const name = getAlgorithmName(key.algorithm);
+
+      if (ASYMMETRIC_ALGORITHMS.includes(name)) {
+        const isRsa = RSA_ALGORITHMS.includes(name);
+        const formats = key.type === 'private' ? ['pkcs8', 'jwk'] : isRsa ? ['spki', 'jwk'] : ['raw', 'spki', 'jwk'];
+
+        if (!formats.includes(format)) {
+          throw new DOMException(`A ${key.type} key can't be exported as ${format}`, 'InvalidAccessError');
+        }
+
+        const exported = await LagonAsync.exportKey(format, key);
+
+        if (format === 'jwk') {
+          const jwk: JsonWebKey = JSON.parse(globalThis.__lagon__.TEXT_DECODER.decode(exported));
+
+          if (isRsa) {
+            jwk.alg = getJwkAlg(name, (key.algorithm as RsaHashedKeyAlgorithm).hash.name);
+          }
+
+          return { ...jwk, key_ops: [...key.usages], ext: key.extractable };
+        }
+
+        return exported;
+      }
+
       // TODO
       if (format === 'jwk') {
         throw new Error('jwk format is not supported');
@@ -99,15 +322,65 @@ interface CryptoKey {
       extractable: boolean,
       keyUsages: Iterable<KeyUsage>,
     ): Promise<CryptoKeyPair | CryptoKey> {
-      let isSymmetric;
+      const name = getAlgorithmName(algorithm);
 
-      if (typeof algorithm === 'string') {
-        isSymmetric = SYMMETRIC_ALGORITHMS.includes(algorithm);
-      } else {
-        isSymmetric = SYMMETRIC_ALGORITHMS.includes(algorithm.name);
+      // Key pairs generated by the runtime
+      if (RSA_ALGORITHMS.includes(name) || EC_ALGORITHMS.includes(name)) {
+        let runtimeAlgorithm: Parameters<typeof LagonAsync.generateKey>[0];
+        let keyAlgorithm: RsaHashedKeyAlgorithm | EcKeyAlgorithm;
+
+        if (RSA_ALGORITHMS.includes(name)) {
+          const { modulusLength, publicExponent, hash } = algorithm as RsaHashedKeyGenParams;
+          const hashName = getAlgorithmName(hash);
+
+          if (!RSA_HASHES.includes(hashName)) {
+            throw new DOMException(`Hash ${hashName} is not supported`, 'NotSupportedError');
+          }
+
+          runtimeAlgorithm = { name, modulusLength, publicExponent: toUint8Array(publicExponent) };
+          keyAlgorithm = {
+            name,
+            modulusLength,
+            publicExponent: new Uint8Array(publicExponent),
+            hash: { name: hashName },
+          };
+        } else {
+          const { namedCurve } = algorithm as EcKeyGenParams;
+
+          if (!NAMED_CURVES.includes(namedCurve)) {
+            throw new DOMException(`Named curve ${namedCurve} is not supported`, 'NotSupportedError');
+          }
+
+          runtimeAlgorithm = keyAlgorithm = { name, namedCurve };
+        }
+
+        const usages = [...keyUsages];
+        const allowedUsages = KEY_USAGES[name];
+        const privateUsages = usages.filter(usage => allowedUsages.private.includes(usage));
+        const publicUsages = usages.filter(usage => allowedUsages.public.includes(usage));
+
+        if (privateUsages.length + publicUsages.length !== usages.length) {
+          throw new DOMException(`Invalid key usages for ${name}`, 'SyntaxError');
+        }
+
+        if (privateUsages.length === 0) {
+          throw new DOMException('The private key must have at least one usage', 'SyntaxError');
+        }
+
+        const [privateKeyValue, publicKeyValue] = await LagonAsync.generateKey(runtimeAlgorithm).catch(
+          rejectWith('OperationError'),
+        );
+
+        return {
+          // @ts-expect-error CryptoKey constructor is empty, but we know our implementation is not
+          privateKey: new CryptoKey(keyAlgorithm, extractable, 'private', privateUsages, privateKeyValue),
+          // Public keys are always extractable
+          // @ts-expect-error CryptoKey constructor is empty, but we know our implementation is not
+          publicKey: new CryptoKey(keyAlgorithm, true, 'public', publicUsages, publicKeyValue),
+        };
       }
 
-      if (isSymmetric) {
+      if (SYMMETRIC_ALGORITHMS.includes(name)) {
         // @ts-expect-error CryptoKey constructor is empty, but we know our implementation is not
         return new CryptoKey(algorithm, extractable, 'secret', keyUsages);
       } else {
@@ -141,8 +414,105 @@ interface CryptoKey {
       extractable: boolean,
       keyUsages: ReadonlyArray<KeyUsage> | Iterable<KeyUsage>,
     ): Promise<CryptoKey> {
+      const name = getAlgorithmName(algorithm);
+
+      // Passwords and key material are kept as-is, to derive bits from them
+      if (KDF_ALGORITHMS.includes(name)) {
+        const usages = [...keyUsages];
+
+        if (format !== 'raw') {
+          throw new DOMException(`${name} keys can only be imported as raw`, 'NotSupportedError');
+        }
+
+        if (extractable) {
+          throw new DOMException(`${name} keys can't be extractable`, 'SyntaxError');
+        }
+
+        if (usages.some(usage => usage !== 'deriveKey' && usage !== 'deriveBits')) {
+          throw new DOMException(`${name} keys can only be used to deriveKey or deriveBits`, 'SyntaxError');
+        }
+
+        const keyValue = new Uint8Array(toUint8Array(keyData as BufferSource));
+
+        // @ts-expect-error CryptoKey constructor is empty, but we know our implementation is not
+        return new CryptoKey({ name }, false, 'secret', usages, keyValue);
+      }
+
+      if (!ASYMMETRIC_ALGORITHMS.includes(name)) {
+        // @ts-expect-error CryptoKey constructor is empty, but we know our implementation is not
+        return new CryptoKey(algorithm, extractable, 'secret', keyUsages);
+      }
+
+      const isRsa = RSA_ALGORITHMS.includes(name);
+      const namedCurve = (algorithm as EcKeyImportParams).namedCurve;
+      const hashName = isRsa ? getAlgorithmName((algorithm as RsaHashedImportParams).hash) : '';
+
+      if (EC_ALGORITHMS.includes(name) && !NAMED_CURVES.includes(namedCurve)) {
+        throw new DOMException(`Named curve ${namedCurve} is not supported`, 'NotSupportedError');
+      }
+
+      if (isRsa && !RSA_HASHES.includes(hashName)) {
+        throw new DOMException(`Hash ${hashName} is not supported`, 'NotSupportedError');
+      }
+
+      if (isRsa && format === 'raw') {
+        throw new DOMException("RSA keys can't be imported as raw", 'NotSupportedError');
+      }
+
+      let type: KeyType;
+      let data: string | Uint8Array;
+
+      if (format === 'jwk') {
+        const jwk = keyData as JsonWebKey;
+
+        if (isRsa && jwk.alg !== undefined && jwk.alg !== getJwkAlg(name, hashName)) {
+          throw new DOMException(`The JWK algorithm ${jwk.alg} doesn't match ${name}`, 'DataError');
+        }
+
+        // Only keep the members needed to import the key
+        const { kty, crv, x, y, d, n, e, p, q, dp, dq, qi } = jwk;
+
+        type = d === undefined ? 'public' : 'private';
+        data = JSON.stringify({ kty, crv, x, y, d, n, e, p, q, dp, dq, qi });
+      } else {
+        // Raw keys are always public keys
+        type = format === 'pkcs8' ? 'private' : 'public';
+        data = toUint8Array(keyData as BufferSource);
+      }
+
+      const usages = [...keyUsages];
+      const allowedUsages = KEY_USAGES[name][type];
+
+      if (usages.some(usage => !allowedUsages.includes(usage))) {
+        throw new DOMException(`Invalid key usages for a ${type} ${name} key`, 'SyntaxError');
+      }
+
+      if (type === 'private' && usages.length === 0) {
+        throw new DOMException('A private key must have at least one usage', 'SyntaxError');
+      }
+
+      // RSA keys also come with their modulus and public exponent
+      const [keyValue, modulus, publicExponent] = await LagonAsync.importKey(format, data, algorithm).catch(
+        rejectWith('DataError'),
+      );
+
+      let keyAlgorithm: KeyAlgorithm;
+
+      if (isRsa) {
+        keyAlgorithm = {
+          name,
+          modulusLength: getModulusLength(new Uint8Array(modulus)),
+          publicExponent: new Uint8Array(publicExponent),
+          hash: { name: hashName },
+        } as RsaHashedKeyAlgorithm;
+      } else if (EC_ALGORITHMS.includes(name)) {
+        keyAlgorithm = { name, namedCurve } as EcKeyAlgorithm;
+      } else {
+        keyAlgorithm = { name };
+      }
+
       // @ts-expect-error CryptoKey constructor is empty, but we know our implementation is not
-      return new CryptoKey(algorithm, extractable, 'secret', keyUsages);
+      return new CryptoKey(keyAlgorithm, extractable, type, usages, keyValue);
     }
 
     async sign(
@@ -150,7 +520,9 @@ interface CryptoKey {
       key: CryptoKey,
       data: BufferSource,
     ): Promise<ArrayBuffer> {
-      return LagonAsync.sign(algorithm, key, data);
+      checkKey(algorithm, key, 'sign');
+
+      return LagonAsync.sign(algorithm, key, toUint8Array(data));
     }
 
     async unwrapKey(
@@ -167,7 +539,9 @@ interface CryptoKey {
       extractable: boolean,
       keyUsages: Iterable<KeyUsage>,
     ): Promise<CryptoKey> {
-      const content = await this.decrypt(unwrapAlgorithm, unwrappingKey, wrappedKey);
+      checkKey(unwrapAlgorithm, unwrappingKey, 'unwrapKey');
+
+      const content = await decrypt(unwrapAlgorithm, unwrappingKey, wrappedKey);
 
       return this.importKey(
         format as Exclude<KeyFormat, 'jwk'>,
@@ -184,7 +558,9 @@ interface CryptoKey {
       signature: BufferSource,
       data: BufferSource,
     ): Promise<boolean> {
-      return LagonAsync.verify(algorithm, key, signature, data);
+      checkKey(algorithm, key, 'verify');
+
+      return LagonAsync.verify(algorithm, key, toUint8Array(signature), toUint8Array(data));
     }
 
     async wrapKey(
@@ -193,9 +569,11 @@ interface CryptoKey {
       wrappingKey: CryptoKey,
       wrapAlgorithm: AlgorithmIdentifier | RsaOaepParams | AesCtrParams | AesCbcParams | AesGcmParams,
     ): Promise<ArrayBuffer> {
+      checkKey(wrapAlgorithm, wrappingKey, 'wrapKey');
+
       const content = await this.exportKey(format as Exclude<KeyFormat, 'jwk'>, key);
 
-      return this.encrypt(wrapAlgorithm, wrappingKey, content);
+      return encrypt(wrapAlgorithm, wrappingKey, content);
     }
   }
 
@@ -0,0 +1,41 @@
+(globalThis => {
+  // https://webidl.spec.whatwg.org/#dfn-error-names-table
+  const LEGACY_CODES: Record<string, number> = {
+    IndexSizeError: 1,
+    HierarchyRequestError: 3,
+    WrongDocumentError: 4,
+    InvalidCharacterError: 5,
+    NoModificationAllowedError: 7,
+    NotFoundError: 8,
+    NotSupportedError: 9,
+    InvalidStateError: 11,
+    SyntaxError: 12,
+    InvalidModificationError: 13,
+    NamespaceError: 14,
+    InvalidAccessError: 15,
+    TypeMismatchError: 17,
+    SecurityError: 18,
+    NetworkError: 19,
+    AbortError: 20,
+    URLMismatchError: 21,
+    QuotaExceededError: 22,
+    TimeoutError: 23,
+    InvalidNodeTypeError: 24,
+    DataCloneError: 25,
+  };
+
+  // The legacy code constants (e.g ABORT_ERR) aren't defined
+  // eslint-disable-next-line @typescript-eslint/ban-ts-comment
+  // @ts-ignore
+  globalThis.DOMException = class extends Error {
+    readonly name: string;
+    readonly code: number;
+
+    constructor(message = '', name = 'Error') {
+      super(message);
+
+      this.name = String(name);
+      this.code = LEGACY_CODES[this.name] ?? 0;
+    }
+  };
+})(globalThis);
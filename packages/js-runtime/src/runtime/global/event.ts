@@ -1,71 +1,146 @@
 (globalThis => {
+  // The internal state of events, only updated by EventTarget while dispatching
+  const EVENT_STATE = Symbol('EventState');
+  const LISTENERS = Symbol('Listeners');
+
+  type EventState = {
+    target: EventTarget | null;
+    currentTarget: EventTarget | null;
+    eventPhase: number;
+    dispatching: boolean;
+    canceled: boolean;
+    inPassiveListener: boolean;
+    stopPropagation: boolean;
+    stopImmediatePropagation: boolean;
+  };
+
   type Listener = {
-    callback: EventListenerOrEventListenerObject | null;
-    options?: AddEventListenerOptions | boolean;
+    callback: EventListenerOrEventListenerObject;
+    capture: boolean;
+    once: boolean;
+    passive: boolean;
+    removed: boolean;
+  };
+
+  // https://dom.spec.whatwg.org/#dom-event-eventphase
+  const NONE = 0;
+  const AT_TARGET = 2;
+
+  const flattenOptions = (options?: AddEventListenerOptions | EventListenerOptions | boolean) => {
+    if (typeof options === 'boolean') {
+      return { capture: options, once: false, passive: false, signal: undefined };
+    }
+
+    const { capture, once, passive, signal } = (options ?? {}) as AddEventListenerOptions;
+
+    return { capture: !!capture, once: !!once, passive: !!passive, signal };
+  };
+
+  // Errors thrown by listeners don't stop the dispatch, and are reported
+  // like uncaught errors instead
+  const reportListenerError = (error: unknown) => {
+    Promise.reject(error);
+  };
+
+  const removeListener = (listeners: Listener[] | undefined, listener: Listener) => {
+    // Mark the listener as removed, so an ongoing dispatch skips it
+    listener.removed = true;
+
+    const index = listeners?.indexOf(listener) ?? -1;
+
+    if (index !== -1) {
+      listeners?.splice(index, 1);
+    }
   };
 
   globalThis.EventTarget = class {
-    private listeners: Map<string, Listener[]> = new Map();
+    private [LISTENERS]: Map<string, Listener[]> = new Map();
 
     addEventListener(
       type: string,
       callback: EventListenerOrEventListenerObject | null,
       options?: AddEventListenerOptions | boolean,
     ) {
+      const { capture, once, passive, signal } = flattenOptions(options);
+
       if (typeof options === 'object' && options?.signal === null) {
         throw new TypeError('signal is null');
       }
 
-      const listeners = this.listeners.get(type) ?? [];
-      const exists = listeners.find(current => current.callback === callback);
-
-      if (!exists) {
-        this.listeners.set(
-          type,
-          listeners.concat({
-            callback,
-            options,
-          }),
-        );
+      if (!callback || signal?.aborted) {
+        return;
+      }
+
+      const listeners = this[LISTENERS].get(type) ?? [];
+
+      // Capture is part of the listener's identity, even if there is no tree to capture events from
+      if (listeners.some(listener => listener.callback === callback && listener.capture === capture)) {
+        return;
       }
+
+      const listener: Listener = { callback, capture, once, passive, removed: false };
+      listeners.push(listener);
+      this[LISTENERS].set(type, listeners);
+
+      signal?.addEventListener('abort', () => removeListener(this[LISTENERS].get(type), listener), { once: true });
     }
 
     dispatchEvent(event: Event): boolean {
-      const { type, cancelable } = event;
+      const state = (event as Event & { [EVENT_STATE]?: EventState })?.[EVENT_STATE];
 
-      for (const { callback, options } of this.listeners.get(type) ?? []) {
-        const initialPreventDefault = event.preventDefault;
-        const passive = typeof options === 'object' && !!options.passive;
+      if (!state) {
+        throw new TypeError("Failed to execute 'dispatchEvent': parameter 1 is not of type 'Event'");
+      }
 
-        if (passive) {
-          // eslint-disable-next-line @typescript-eslint/no-empty-function
-          event.preventDefault = () => {};
-        }
+      if (state.dispatching) {
+        throw new DOMException('The event is already being dispatched', 'InvalidStateError');
+      }
 
-        if (typeof options === 'object' && (options.once || options.signal?.aborted)) {
-          this.removeEventListener(type, callback, options);
+      state.dispatching = true;
+      state.target = this;
+      state.currentTarget = this;
+      state.eventPhase = AT_TARGET;
 
-          if (options.signal?.aborted) {
-            continue;
-          }
+      // Listeners added while dispatching only receive the next events
+      for (const listener of [...(this[LISTENERS].get(event.type) ?? [])]) {
+        if (listener.removed) {
+          continue;
         }
 
-        if (typeof callback === 'function') {
-          callback(event);
-        } else {
-          callback?.handleEvent(event);
+        if (listener.once) {
+          removeListener(this[LISTENERS].get(event.type), listener);
         }
 
-        if (passive) {
-          event.preventDefault = initialPreventDefault;
+        state.inPassiveListener = listener.passive;
+
+        try {
+          const { callback } = listener;
+
+          if (typeof callback === 'function') {
+            callback.call(this, event);
+          } else if (typeof callback.handleEvent === 'function') {
+            callback.handleEvent(event);
+          } else {
+            throw new TypeError("The listener's handleEvent property is not a function");
+          }
+        } catch (error) {
+          reportListenerError(error);
         }
 
-        if (cancelable && event.defaultPrevented && !passive) {
-          return false;
+        state.inPassiveListener = false;
+
+        if (state.stopImmediatePropagation) {
+          break;
         }
       }
 
-      return true;
+      state.dispatching = false;
+      state.currentTarget = null;
+      state.eventPhase = NONE;
+      state.stopPropagation = false;
+      state.stopImmediatePropagation = false;
+
+      return !state.canceled;
     }
 
     removeEventListener(
@@ -73,11 +148,13 @@
       callback: EventListenerOrEventListenerObject | null,
       options?: EventListenerOptions | boolean,
     ) {
-      const listeners = this.listeners.get(type) ?? [];
-      this.listeners.set(
-        type,
-        listeners.filter(listener => listener.callback !== callback && listener.options !== options),
-      );
+      const { capture } = flattenOptions(options);
+      const listeners = this[LISTENERS].get(type);
+      const listener = listeners?.find(listener => listener.callback === callback && listener.capture === capture);
+
+      if (listener) {
+        removeListener(listeners, listener);
+      }
     }
   };
 
@@ -87,23 +164,27 @@
   // @ts-ignore
   globalThis.Event = class {
     readonly bubbles: boolean;
-    cancelBubble: boolean;
     readonly cancelable: boolean;
     readonly composed: boolean;
-    readonly currentTarget: EventTarget | null;
-    readonly defaultPrevented: boolean;
-    readonly eventPhase: number;
     readonly isTrusted: boolean;
-    returnValue: boolean;
-    readonly srcElement: EventTarget | null;
-    readonly target: EventTarget | null;
     readonly timeStamp: DOMHighResTimeStamp;
     readonly type: string;
 
+    private [EVENT_STATE]: EventState = {
+      target: null,
+      currentTarget: null,
+      eventPhase: NONE,
+      dispatching: false,
+      canceled: false,
+      inPassiveListener: false,
+      stopPropagation: false,
+      stopImmediatePropagation: false,
+    };
+
     // https://dom.spec.whatwg.org/#dom-event-eventphase
     static readonly NONE = 0;
     static readonly CAPTURING_PHASE = 1;
-    static readonly AT_TARGET: 2;
+    static readonly AT_TARGET = 2;
     static readonly BUBBLING_PHASE = 3;
 
     constructor(type: string, eventInitDict?: EventInit) {
@@ -111,27 +192,72 @@
         throw new TypeError('Event requires at least one argument');
       }
 
-      this.type = type;
+      this.type = String(type);
       this.bubbles = eventInitDict?.bubbles ?? false;
       this.cancelable = eventInitDict?.cancelable ?? false;
       this.composed = eventInitDict?.composed ?? false;
-
-      this.cancelBubble = false;
-      this.currentTarget = null;
-      this.defaultPrevented = false;
-      this.eventPhase = 0;
       this.isTrusted = false;
-      this.returnValue = true;
-      this.srcElement = null;
-      this.target = null;
       this.timeStamp = Date.now();
     }
 
+    get target() {
+      return this[EVENT_STATE].target;
+    }
+
+    get srcElement() {
+      return this[EVENT_STATE].target;
+    }
+
+    get currentTarget() {
+      return this[EVENT_STATE].currentTarget;
+    }
+
+    get eventPhase() {
+      return this[EVENT_STATE].eventPhase;
+    }
+
+    get defaultPrevented() {
+      return this[EVENT_STATE].canceled;
+    }
+
+    get returnValue() {
+      return !this[EVENT_STATE].canceled;
+    }
+
+    set returnValue(value: boolean) {
+      if (!value) {
+        this.preventDefault();
+      }
+    }
+
+    get cancelBubble() {
+      return this[EVENT_STATE].stopPropagation;
+    }
+
+    set cancelBubble(value: boolean) {
+      if (value) {
+        this.stopPropagation();
+      }
+    }
+
     composedPath(): EventTarget[] {
-      return [];
+      const { currentTarget } = this[EVENT_STATE];
+
+      return currentTarget ? [currentTarget] : [];
     }
 
     initEvent(type: string, bubbles?: boolean, cancelable?: boolean) {
+      const state = this[EVENT_STATE];
+
+      if (state.dispatching) {
+        return;
+      }
+
+      state.canceled = false;
+      state.stopPropagation = false;
+      state.stopImmediatePropagation = false;
+      state.target = null;
+
       // @ts-expect-error we assign to a readonly property
       this.type = type;
       // @ts-expect-error we assign to a readonly property
@@ -140,16 +266,24 @@
       this.cancelable = cancelable ?? false;
     }
 
+    // Passive listeners can't cancel events
     preventDefault() {
-      // @ts-expect-error we assign to a readonly property
-      this.defaultPrevented = true;
+      const state = this[EVENT_STATE];
+
+      if (this.cancelable && !state.inPassiveListener) {
+        state.canceled = true;
+      }
     }
 
-    // eslint-disable-next-line @typescript-eslint/no-empty-function
-    stopImmediatePropagation() {}
+    stopImmediatePropagation() {
+      const state = this[EVENT_STATE];
+      state.stopPropagation = true;
+      state.stopImmediatePropagation = true;
+    }
 
-    // eslint-disable-next-line @typescript-eslint/no-empty-function
-    stopPropagation() {}
+    stopPropagation() {
+      this[EVENT_STATE].stopPropagation = true;
+    }
   };
 
   globalThis.CustomEvent = class<T> extends Event {
@@ -171,7 +305,7 @@
   globalThis.ProgressEvent = class<T extends EventTarget = EventTarget> extends Event {
     readonly lengthComputable: boolean;
     readonly loaded: number;
-    readonly target: T | null = null;
+    declare readonly target: T | null;
     readonly total: number;
 
     constructor(type: string, eventInitDict?: ProgressEventInit) {
@@ -182,4 +316,35 @@
       this.total = eventInitDict?.total ?? 0;
     }
   };
+
+  // @ts-expect-error initMessageEvent is deprecated
+  globalThis.MessageEvent = class<T> extends Event {
+    readonly data: T;
+    readonly origin: string;
+    readonly lastEventId: string;
+    readonly source: MessageEventSource | null = null;
+    readonly ports: ReadonlyArray<MessagePort> = [];
+
+    constructor(type: string, eventInitDict?: MessageEventInit<T>) {
+      super(type, eventInitDict);
+
+      this.data = eventInitDict?.data ?? (null as T);
+      this.origin = eventInitDict?.origin ?? '';
+      this.lastEventId = eventInitDict?.lastEventId ?? '';
+    }
+  };
+
+  globalThis.CloseEvent = class extends Event {
+    readonly code: number;
+    readonly reason: string;
+    readonly wasClean: boolean;
+
+    constructor(type: string, eventInitDict?: CloseEventInit) {
+      super(type, eventInitDict);
+
+      this.code = eventInitDict?.code ?? 0;
+      this.reason = eventInitDict?.reason ?? '';
+      this.wasClean = eventInitDict?.wasClean ?? false;
+    }
+  };
 })(globalThis);
@@ -7,7 +7,7 @@
     constructor(fileBits: BlobPart[], fileName: string, options?: FilePropertyBag) {
       super(fileBits, options);
 
-      this.lastModified = options?.lastModified || Date.now();
+      this.lastModified = options?.lastModified ?? Date.now();
       this.name = fileName;
       this.webkitRelativePath = '';
     }
@@ -0,0 +1,210 @@
+(globalThis => {
+  // Entries created outside of a request, e.g when the Function is initialized,
+  // are visible to all requests. Entries created during a request are stored by
+  // the runtime and dropped with it, so they don't leak into the next requests
+  const initEntries: PerformanceEntry[] = [];
+  const getOwnEntries = (): PerformanceEntry[] => LagonSync.performanceEntries() ?? initEntries;
+
+  const getEntries = (): PerformanceEntry[] => {
+    const entries = LagonSync.performanceEntries();
+
+    return entries ? [...initEntries, ...entries] : [...initEntries];
+  };
+
+  const now = () => LagonSync.performanceNow();
+
+  // Entries are returned in chronological order
+  const sortEntries = (entries: PerformanceEntry[]) => entries.sort((a, b) => a.startTime - b.startTime);
+
+  const clearEntries = (entryType: string, name?: string) => {
+    const entries = getOwnEntries();
+
+    // Remove in place, since the runtime keeps a reference to the array
+    for (let index = entries.length - 1; index >= 0; index--) {
+      if (entries[index].entryType === entryType && (name === undefined || entries[index].name === name)) {
+        entries.splice(index, 1);
+      }
+    }
+  };
+
+  // @ts-expect-error PerformanceEntry can't be constructed by Functions
+  globalThis.PerformanceEntry = class {
+    readonly name: string;
+    readonly entryType: string;
+    readonly startTime: number;
+    readonly duration: number;
+
+    constructor(name: string, entryType: string, startTime: number, duration: number) {
+      this.name = name;
+      this.entryType = entryType;
+      this.startTime = startTime;
+      this.duration = duration;
+    }
+
+    toJSON() {
+      return {
+        name: this.name,
+        entryType: this.entryType,
+        startTime: this.startTime,
+        duration: this.duration,
+      };
+    }
+  };
+
+  globalThis.PerformanceMark = class extends PerformanceEntry {
+    readonly detail: unknown;
+
+    constructor(markName: string, markOptions?: PerformanceMarkOptions) {
+      const startTime = markOptions?.startTime ?? now();
+
+      if (startTime < 0) {
+        throw new TypeError(`'${startTime}' is a negative value`);
+      }
+
+      // @ts-expect-error PerformanceEntry constructor is empty, but we know our implementation is not
+      super(String(markName), 'mark', startTime, 0);
+      this.detail = markOptions?.detail === undefined ? null : structuredClone(markOptions.detail);
+    }
+
+    toJSON() {
+      return { ...super.toJSON(), detail: this.detail };
+    }
+  };
+
+  // @ts-expect-error PerformanceMeasure can't be constructed by Functions
+  globalThis.PerformanceMeasure = class extends PerformanceEntry {
+    readonly detail: unknown;
+
+    constructor(measureName: string, startTime: number, duration: number, detail: unknown) {
+      // @ts-expect-error PerformanceEntry constructor is empty, but we know our implementation is not
+      super(measureName, 'measure', startTime, duration);
+      this.detail = detail;
+    }
+
+    toJSON() {
+      return { ...super.toJSON(), detail: this.detail };
+    }
+  };
+
+  // The timestamp of the given mark name, or the given timestamp
+  const toTimestamp = (mark: string | number): number => {
+    if (typeof mark === 'number') {
+      if (mark < 0) {
+        throw new TypeError(`'${mark}' is a negative value`);
+      }
+
+      return mark;
+    }
+
+    const entries = getEntries();
+
+    for (let index = entries.length - 1; index >= 0; index--) {
+      if (entries[index].entryType === 'mark' && entries[index].name === mark) {
+        return entries[index].startTime;
+      }
+    }
+
+    throw new DOMException(`The mark '${mark}' does not exist`, 'SyntaxError');
+  };
+
+  class LagonPerformance extends EventTarget {
+    get timeOrigin() {
+      return LagonSync.performanceTimeOrigin();
+    }
+
+    now() {
+      return now();
+    }
+
+    mark(markName: string, markOptions?: PerformanceMarkOptions) {
+      const mark = new PerformanceMark(markName, markOptions);
+      getOwnEntries().push(mark);
+
+      return mark;
+    }
+
+    measure(measureName: string, startOrMeasureOptions?: string | PerformanceMeasureOptions, endMark?: string) {
+      const options = typeof startOrMeasureOptions === 'object' ? startOrMeasureOptions : undefined;
+
+      if (
+        options &&
+        (options.start !== undefined ||
+          options.end !== undefined ||
+          options.duration !== undefined ||
+          options.detail !== undefined)
+      ) {
+        if (endMark !== undefined) {
+          throw new TypeError('endMark must not be passed when using measure options');
+        }
+
+        if (options.start === undefined && options.end === undefined) {
+          throw new TypeError('At least one of start and end must be passed in measure options');
+        }
+
+        if (options.start !== undefined && options.end !== undefined && options.duration !== undefined) {
+          throw new TypeError('Only two of start, end and duration can be passed in measure options');
+        }
+      }
+
+      let endTime: number;
+
+      if (endMark !== undefined) {
+        endTime = toTimestamp(endMark);
+      } else if (options?.end !== undefined) {
+        endTime = toTimestamp(options.end);
+      } else if (options?.start !== undefined && options.duration !== undefined) {
+        endTime = toTimestamp(options.start) + toTimestamp(options.duration);
+      } else {
+        endTime = now();
+      }
+
+      let startTime: number;
+
+      if (options?.start !== undefined) {
+        startTime = toTimestamp(options.start);
+      } else if (options?.duration !== undefined && options.end !== undefined) {
+        startTime = endTime - toTimestamp(options.duration);
+      } else if (typeof startOrMeasureOptions === 'string') {
+        startTime = toTimestamp(startOrMeasureOptions);
+      } else {
+        startTime = 0;
+      }
+
+      const detail = options?.detail === undefined ? null : structuredClone(options.detail);
+      // @ts-expect-error PerformanceMeasure constructor is empty, but we know our implementation is not
+      const measure = new PerformanceMeasure(String(measureName), startTime, endTime - startTime, detail);
+      getOwnEntries().push(measure);
+
+      return measure;
+    }
+
+    getEntries() {
+      return sortEntries(getEntries());
+    }
+
+    getEntriesByName(name: string, type?: string) {
+      return sortEntries(
+        getEntries().filter(entry => entry.name === name && (type === undefined || entry.entryType === type)),
+      );
+    }
+
+    getEntriesByType(type: string) {
+      return sortEntries(getEntries().filter(entry => entry.entryType === type));
+    }
+
+    clearMarks(markName?: string) {
+      clearEntries('mark', markName);
+    }
+
+    clearMeasures(measureName?: string) {
+      clearEntries('measure', measureName);
+    }
+
+    toJSON() {
+      return { timeOrigin: this.timeOrigin };
+    }
+  }
+
+  // @ts-expect-error resource timings aren't supported
+  globalThis.performance = new LagonPerformance();
+})(globalThis);
@@ -4,47 +4,125 @@
     repeat: boolean;
   };
 
+  // Like browsers, timers nested deeper than this are delayed by at least 4ms,
+  // so tight timer loops can't flood the event loop
+  const MAX_NESTING_LEVEL = 5;
+  const MIN_NESTED_TIMEOUT = 4;
+  // Bigger timeouts overflow in browsers, and fire after 1ms instead
+  const MAX_TIMEOUT = 2 ** 31 - 1;
+
   let counter = 0;
   const timers = new Map<number, Timer>();
+  const immediates = new Map<number, () => void>();
+  let immediatesScheduled = false;
+  // The nesting level of the timer currently running, 0 outside of timers
+  let currentNestingLevel = 0;
 
-  const addTimer = (handler: () => void, timeout = 0, repeat: boolean) => {
-    const id = counter++;
+  const runImmediates = () => {
+    immediatesScheduled = false;
 
-    timers.set(id, {
-      handler: AsyncContext.wrap(handler),
-      repeat,
-    });
+    // Immediates queued while running these ones will run on the next turn
+    for (const [id, handler] of [...immediates]) {
+      if (immediates.delete(id)) {
+        handler();
+      }
+    }
+  };
 
-    LagonAsync.sleep(timeout).then(() => {
+  const scheduleTimer = (id: number, timeout: number) => {
+    const nestingLevel = currentNestingLevel;
+
+    if (nestingLevel > MAX_NESTING_LEVEL && timeout < MIN_NESTED_TIMEOUT) {
+      timeout = MIN_NESTED_TIMEOUT;
+    }
+
+    const runTimer = () => {
       const timer = timers.get(id);
 
       if (timer) {
-        timer.handler();
-        timers.delete(id);
+        if (!timer.repeat) {
+          timers.delete(id);
+        }
+
+        currentNestingLevel = nestingLevel + 1;
+
+        try {
+          timer.handler();
+        } finally {
+          currentNestingLevel = 0;
+        }
 
-        if (timer.repeat) {
-          addTimer(timer.handler, timeout, repeat);
+        // The interval might have been cleared by its own handler
+        if (timer.repeat && timers.has(id)) {
+          currentNestingLevel = nestingLevel + 1;
+          scheduleTimer(id, timeout);
+          currentNestingLevel = 0;
         }
       }
+    };
+
+    LagonAsync.sleep(timeout).then(() => {
+      // Immediates run before the timers that are due on the same turn,
+      // and the timer only runs once their microtasks are done
+      if (immediates.size > 0) {
+        runImmediates();
+        LagonAsync.sleep(0).then(runTimer);
+      } else {
+        runTimer();
+      }
+    });
+  };
+
+  // Invalid and negative timeouts are the same as 0
+  const toTimeout = (timeout: unknown) => {
+    const value = Math.max(Number(timeout) || 0, 0);
+
+    return value > MAX_TIMEOUT ? 1 : value;
+  };
+
+  const addTimer = (handler: (...args: unknown[]) => void, timeout: unknown, repeat: boolean, args: unknown[]) => {
+    const id = counter++;
+
+    timers.set(id, {
+      handler: AsyncContext.wrap(() => handler(...args)),
+      repeat,
     });
 
+    scheduleTimer(id, toTimeout(timeout));
+
     return id;
   };
 
   // @ts-expect-error missing __promisify__
-  globalThis.setTimeout = (handler, timeout) => addTimer(handler, timeout, false);
+  globalThis.setTimeout = (handler, timeout, ...args) => addTimer(handler, timeout, false, args);
 
   globalThis.clearTimeout = id => {
     timers.delete(id as number);
   };
 
   // @ts-expect-error missing __promisify__
-  globalThis.setInterval = (handler, timeout) => addTimer(handler, timeout, true);
+  globalThis.setInterval = (handler, timeout, ...args) => addTimer(handler, timeout, true, args);
 
   globalThis.clearInterval = id => {
     timers.delete(id as number);
   };
 
+  globalThis.setImmediate = (handler: (...args: unknown[]) => void, ...args: unknown[]) => {
+    const id = counter++;
+    immediates.set(id, AsyncContext.wrap(() => handler(...args)));
+
+    if (!immediatesScheduled) {
+      immediatesScheduled = true;
+      LagonAsync.sleep(0).then(runImmediates);
+    }
+
+    return id;
+  };
+
+  globalThis.clearImmediate = (id: number) => {
+    immediates.delete(id);
+  };
+
   globalThis.queueMicrotask = callback => {
     LagonSync.queueMicrotask(AsyncContext.wrap(callback));
   };
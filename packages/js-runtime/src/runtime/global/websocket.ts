@@ -0,0 +1,274 @@
+(globalThis => {
+  const CONNECTING = 0;
+  const OPEN = 1;
+  const CLOSING = 2;
+  const CLOSED = 3;
+  // https://www.rfc-editor.org/rfc/rfc6455#section-7.4.1
+  const ABNORMAL_CLOSURE = 1006;
+  // https://www.rfc-editor.org/rfc/rfc2616#section-2.2
+  const TOKEN = /^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/;
+
+  // Upgraded WebSockets can only be created by upgradeWebSocket()
+  const UPGRADE = Symbol('WebSocketUpgrade');
+
+  let websocketCounter = 0;
+  // The upgraded WebSockets by their upgrade response
+  const upgrades = new WeakMap<Response, LagonWebSocket>();
+
+  // Like onabort, the event handler attribute is called before the listeners
+  const fireEvent = (websocket: LagonWebSocket, event: Event) => {
+    const handler = websocket[`on${event.type}` as 'onopen'];
+    handler?.call(websocket, event);
+    websocket.dispatchEvent(event);
+  };
+
+  const parseUrl = (url: string | URL) => {
+    let parsed: URL;
+
+    try {
+      parsed = new URL(url);
+    } catch {
+      throw new DOMException(`Invalid URL '${url}'`, 'SyntaxError');
+    }
+
+    if (parsed.protocol === 'http:' || parsed.protocol === 'https:') {
+      parsed.protocol = parsed.protocol === 'http:' ? 'ws:' : 'wss:';
+    }
+
+    if (parsed.protocol !== 'ws:' && parsed.protocol !== 'wss:') {
+      throw new DOMException(`The URL's scheme must be either 'ws' or 'wss', got '${parsed.protocol}'`, 'SyntaxError');
+    }
+
+    if (parsed.hash) {
+      throw new DOMException("The URL can't contain a fragment", 'SyntaxError');
+    }
+
+    return parsed.href;
+  };
+
+  class LagonWebSocket extends EventTarget {
+    static readonly CONNECTING = CONNECTING;
+    static readonly OPEN = OPEN;
+    static readonly CLOSING = CLOSING;
+    static readonly CLOSED = CLOSED;
+    readonly CONNECTING = CONNECTING;
+    readonly OPEN = OPEN;
+    readonly CLOSING = CLOSING;
+    readonly CLOSED = CLOSED;
+
+    readonly url: string;
+    readonly extensions = '';
+    readonly bufferedAmount = 0;
+    protocol = '';
+    readyState = CONNECTING;
+    binaryType: BinaryType = 'blob';
+    onopen: ((this: WebSocket, event: Event) => any) | null = null;
+    onmessage: ((this: WebSocket, event: MessageEvent) => any) | null = null;
+    onclose: ((this: WebSocket, event: CloseEvent) => any) | null = null;
+    onerror: ((this: WebSocket, event: Event) => any) | null = null;
+    // Read when the upgrade response is returned
+    readonly id: number;
+    private upgraded: boolean;
+
+    constructor(url: string | URL, protocols: string | string[] = [], key?: symbol) {
+      super();
+
+      this.url = parseUrl(url);
+      this.id = websocketCounter++;
+      this.upgraded = key === UPGRADE;
+
+      // The messages of upgraded WebSockets are received once accepted
+      if (this.upgraded) {
+        LagonSync.webSocketUpgrade(this.id);
+        return;
+      }
+
+      const protocolsList = typeof protocols === 'string' ? [protocols] : [...protocols];
+
+      if (protocolsList.some((protocol, index) => !TOKEN.test(protocol) || protocolsList.indexOf(protocol) !== index)) {
+        throw new DOMException('The subprotocols must be unique tokens', 'SyntaxError');
+      }
+
+      // The connection is force-closed by the runtime once the request is done
+      LagonAsync.webSocketConnect({ u: this.url, p: protocolsList, i: this.id }).then(
+        protocol => {
+          // Closed before the connection was established
+          if (this.readyState !== CONNECTING) {
+            this.fail();
+            return;
+          }
+
+          this.protocol = protocol;
+          this.readyState = OPEN;
+          fireEvent(this, new Event('open'));
+
+          this.receive();
+        },
+        () => this.fail(),
+      );
+    }
+
+    // Starts receiving the messages of an upgraded WebSocket
+    accept() {
+      if (!this.upgraded) {
+        throw new DOMException('Only WebSockets from upgradeWebSocket() can be accepted', 'InvalidStateError');
+      }
+
+      if (this.readyState !== CONNECTING) {
+        throw new DOMException('The WebSocket was already accepted', 'InvalidStateError');
+      }
+
+      this.readyState = OPEN;
+      this.receive();
+    }
+
+    private async receive() {
+      for (;;) {
+        let message: Awaited<ReturnType<typeof LagonAsync.webSocketReceive>>;
+
+        try {
+          message = await LagonAsync.webSocketReceive(this.id);
+        } catch {
+          this.fail();
+          return;
+        }
+
+        if (typeof message === 'string' || message instanceof Uint8Array) {
+          // Messages received once closing are dropped
+          if (this.readyState !== OPEN) {
+            continue;
+          }
+
+          const data =
+            typeof message === 'string'
+              ? message
+              : this.binaryType === 'arraybuffer'
+              ? message.buffer
+              : new Blob([message]);
+
+          fireEvent(this, new MessageEvent('message', { data, origin: new URL(this.url).origin }));
+        } else {
+          this.finish(message.c, message.r);
+          return;
+        }
+      }
+    }
+
+    private fail() {
+      if (this.readyState === CLOSED) {
+        return;
+      }
+
+      this.readyState = CLOSED;
+      LagonSync.webSocketAbort(this.id);
+
+      fireEvent(this, new Event('error'));
+      fireEvent(this, new CloseEvent('close', { code: ABNORMAL_CLOSURE, wasClean: false }));
+    }
+
+    private finish(code: number, reason: string) {
+      if (this.readyState === CLOSED) {
+        return;
+      }
+
+      const wasClean = code !== ABNORMAL_CLOSURE;
+
+      this.readyState = CLOSED;
+      LagonSync.webSocketAbort(this.id);
+
+      if (!wasClean) {
+        fireEvent(this, new Event('error'));
+      }
+
+      fireEvent(this, new CloseEvent('close', { code, reason, wasClean }));
+    }
+
+    send(data: string | ArrayBufferLike | Blob | ArrayBufferView) {
+      if (this.readyState === CONNECTING) {
+        throw new DOMException("Can't send data while the WebSocket is connecting", 'InvalidStateError');
+      }
+
+      // Like browsers, data sent once closing is silently discarded
+      if (this.readyState !== OPEN) {
+        return;
+      }
+
+      let frame: string | Uint8Array;
+
+      if (typeof data === 'string') {
+        frame = data;
+      } else if (data instanceof Blob) {
+        frame = data.buffer;
+      } else if (data instanceof ArrayBuffer) {
+        frame = new Uint8Array(data);
+      } else if (ArrayBuffer.isView(data)) {
+        frame = new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+      } else {
+        frame = String(data);
+      }
+
+      LagonSync.webSocketSend(this.id, frame);
+    }
+
+    close(code?: number, reason?: string) {
+      if (code !== undefined && code !== 1000 && (code < 3000 || code > 4999)) {
+        throw new DOMException(
+          `The close code must be either 1000, or between 3000 and 4999, got ${code}`,
+          'InvalidAccessError',
+        );
+      }
+
+      if (reason !== undefined && globalThis.__lagon__.TEXT_ENCODER.encode(reason).byteLength > 123) {
+        throw new DOMException('The close reason must not be longer than 123 bytes', 'SyntaxError');
+      }
+
+      if (this.readyState === CLOSING || this.readyState === CLOSED) {
+        return;
+      }
+
+      if (this.readyState === CONNECTING) {
+        // Fails the connection, which fires the error and close events. Upgraded
+        // WebSockets that weren't accepted don't have any listener yet
+        this.readyState = this.upgraded ? CLOSED : CLOSING;
+        LagonSync.webSocketAbort(this.id);
+        return;
+      }
+
+      this.readyState = CLOSING;
+      // The close event is fired once the server completed the closing handshake
+      LagonSync.webSocketClose(this.id, code ?? (reason !== undefined ? 1000 : undefined), reason ?? '');
+    }
+  }
+
+  globalThis.WebSocket = LagonWebSocket;
+
+  globalThis.upgradeWebSocket = (request, options) => {
+    if (request.headers.get('upgrade')?.toLowerCase() !== 'websocket') {
+      throw new TypeError("The request's Upgrade header must be 'websocket'");
+    }
+
+    const protocol = options?.protocol;
+    const socket = new LagonWebSocket(request.url, [], UPGRADE);
+    socket.protocol = protocol ?? '';
+
+    // The handshake headers are set by the runtime
+    const response = new Response(null, {
+      status: 101,
+      headers: protocol ? { 'sec-websocket-protocol': protocol } : undefined,
+    });
+
+    upgrades.set(response, socket);
+
+    return { socket, response };
+  };
+
+  globalThis.__lagon__.getWebSocketUpgrade = response => {
+    const socket = upgrades.get(response);
+
+    if (socket?.readyState === CONNECTING) {
+      throw new TypeError('The WebSocket must be accepted with accept() before returning its response');
+    }
+
+    return socket?.id;
+  };
+})(globalThis);
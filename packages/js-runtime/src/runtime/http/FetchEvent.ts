@@ -0,0 +1,76 @@
+(globalThis => {
+  // Service worker style Functions register a fetch listener on the global
+  // scope instead of exporting a handler, like Cloudflare Workers
+  const globalTarget = new EventTarget();
+
+  const responses = new WeakMap<FetchEvent, Promise<Response>>();
+
+  const dispatchFetchEvent = (request: Request) => {
+    const event = new FetchEvent('fetch', { request });
+    globalTarget.dispatchEvent(event);
+
+    const response = responses.get(event);
+
+    if (!response) {
+      throw new Error('No fetch listener called respondWith() to provide a response');
+    }
+
+    return response;
+  };
+
+  // @ts-expect-error clientId, handled and the other service worker properties aren't supported
+  globalThis.FetchEvent = class extends Event {
+    readonly request: Request;
+
+    constructor(type: string, eventInitDict: FetchEventInit) {
+      if (!(eventInitDict?.request instanceof Request)) {
+        throw new TypeError("Failed to construct 'FetchEvent': required member request is undefined");
+      }
+
+      super(type, eventInitDict);
+      this.request = eventInitDict.request;
+    }
+
+    respondWith(response: Response | PromiseLike<Response>) {
+      // The response has to be provided synchronously, while the event is dispatched
+      if (this.eventPhase === Event.NONE) {
+        throw new DOMException('The event handler is already finished', 'InvalidStateError');
+      }
+
+      if (responses.has(this)) {
+        throw new DOMException('respondWith() has already been called', 'InvalidStateError');
+      }
+
+      responses.set(this, Promise.resolve(response));
+      this.stopImmediatePropagation();
+    }
+
+    waitUntil(promise: Promise<unknown>) {
+      __lagon__.waitUntil(promise);
+    }
+  };
+
+  globalThis.addEventListener = ((
+    type: string,
+    listener: EventListenerOrEventListenerObject | null,
+    options?: AddEventListenerOptions | boolean,
+  ) => {
+    // Used by masterHandler when no handler is exported. Listeners can be removed,
+    // but the request then fails since no listener provides a response
+    if (type === 'fetch' && listener) {
+      globalThis.__lagon__.fetchHandler = dispatchFetchEvent;
+    }
+
+    globalTarget.addEventListener(type, listener, options);
+  }) as typeof globalThis.addEventListener;
+
+  globalThis.removeEventListener = ((
+    type: string,
+    listener: EventListenerOrEventListenerObject | null,
+    options?: EventListenerOptions | boolean,
+  ) => {
+    globalTarget.removeEventListener(type, listener, options);
+  }) as typeof globalThis.removeEventListener;
+
+  globalThis.dispatchEvent = event => globalTarget.dispatchEvent(event);
+})(globalThis);
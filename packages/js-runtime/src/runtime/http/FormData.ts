@@ -1,9 +1,25 @@
 (globalThis => {
+  // Blobs are always stored as Files, like in browsers
+  const toEntryValue = (value: string | Blob, filename?: string): FormDataEntryValue => {
+    if (!(value instanceof Blob)) {
+      return String(value);
+    }
+
+    if (value instanceof File && filename === undefined) {
+      return value;
+    }
+
+    return new File([value], filename ?? (value instanceof File ? value.name : 'blob'), {
+      type: value.type,
+      lastModified: value instanceof File ? value.lastModified : undefined,
+    });
+  };
+
   // Almost the same implementation as URLSearchParams
   globalThis.FormData = class {
-    private fields: Map<string, string[]> = new Map();
+    private fields: Map<string, FormDataEntryValue[]> = new Map();
 
-    private addValue(name: string, value: string) {
+    private addValue(name: string, value: FormDataEntryValue) {
       const values = this.fields.get(name);
 
       if (values) {
@@ -13,15 +29,15 @@
       }
     }
 
-    append(name: string, value: string) {
-      this.addValue(name, value);
+    append(name: string, value: string | Blob, filename?: string) {
+      this.addValue(String(name), toEntryValue(value, filename));
     }
 
     delete(name: string) {
       this.fields.delete(name);
     }
 
-    *entries(): IterableIterator<[string, string]> {
+    *entries(): IterableIterator<[string, FormDataEntryValue]> {
       for (const [key, values] of this.fields) {
         for (const value of values) {
           yield [key, value];
@@ -29,7 +45,7 @@
       }
     }
 
-    forEach(callbackfn: (value: string, key: string, parent: FormData) => void, thisArg?: any) {
+    forEach(callbackfn: (value: FormDataEntryValue, key: string, parent: FormData) => void, thisArg?: any) {
       this.fields.forEach((values, key) => {
         values.forEach(value => {
           callbackfn.call(thisArg, value, key, this);
@@ -37,12 +53,12 @@
       });
     }
 
-    get(name: string): string | null {
-      return this.fields.get(name)?.[0] || null;
+    get(name: string): FormDataEntryValue | null {
+      return this.fields.get(name)?.[0] ?? null;
     }
 
-    getAll(name: string): string[] {
-      return this.fields.get(name) || [];
+    getAll(name: string): FormDataEntryValue[] {
+      return [...(this.fields.get(name) || [])];
     }
 
     has(name: string): boolean {
@@ -53,11 +69,11 @@
       return this.fields.keys();
     }
 
-    set(name: string, value: string) {
-      this.fields.set(name, [value]);
+    set(name: string, value: string | Blob, filename?: string) {
+      this.fields.set(String(name), [toEntryValue(value, filename)]);
     }
 
-    *values(): IterableIterator<string> {
+    *values(): IterableIterator<FormDataEntryValue> {
       for (const [, values] of this.fields) {
         for (const value of values) {
           yield value;
@@ -65,7 +81,7 @@
       }
     }
 
-    [Symbol.iterator](): IterableIterator<[string, string]> {
+    [Symbol.iterator](): IterableIterator<[string, FormDataEntryValue]> {
       return this.entries();
     }
   };
@@ -1,5 +1,32 @@
 (globalThis => {
   const SET_COOKIE = 'set-cookie';
+  // https://fetch.spec.whatwg.org/#header-name
+  const HEADER_NAME = /^[!#$%&'*+\-.^_`|~0-9A-Za-z]+$/;
+  // https://fetch.spec.whatwg.org/#header-value
+  const INVALID_HEADER_VALUE = /[\0\r\n]/;
+  // https://fetch.spec.whatwg.org/#concept-header-value-normalize
+  const HTTP_WHITESPACE = /^[\t\n\r ]+|[\t\n\r ]+$/g;
+
+  const normalizeName = (name: string): string => {
+    name = String(name);
+
+    if (!HEADER_NAME.test(name)) {
+      throw new TypeError(`Invalid header name: ${name}`);
+    }
+
+    // The original casing isn't kept, since hyper lowercases the names anyway
+    return name.toLowerCase();
+  };
+
+  const normalizeValue = (name: string, value: string): string => {
+    value = String(value).replace(HTTP_WHITESPACE, '');
+
+    if (INVALID_HEADER_VALUE.test(value)) {
+      throw new TypeError(`Invalid value for header ${name}`);
+    }
+
+    return value;
+  };
 
   globalThis.Headers = class {
     private readonly h: Map<string, string[]> = new Map();
@@ -33,15 +60,22 @@
           }
 
           Object.entries(init).forEach(([key, value]) => {
-            this.addValue(key, value);
+            // Headers coming from the runtime (incoming requests and fetch
+            // responses) have the list of values of each header, which
+            // allows repeated headers like Set-Cookie to stay separate
+            if (Array.isArray(value)) {
+              value.forEach(value => this.addValue(key, value));
+            } else {
+              this.addValue(key, value);
+            }
           });
         }
       }
     }
 
     private addValue(name: string, value: string) {
-      name = name.toLowerCase();
-      value = String(value);
+      name = normalizeName(name);
+      value = normalizeValue(name, value);
       const values = this.h.get(name);
 
       if (values) {
@@ -52,7 +86,7 @@
     }
 
     getSetCookie(): string[] {
-      return this.h.get(SET_COOKIE) || [];
+      return [...(this.h.get(SET_COOKIE) || [])];
     }
 
     append(name: string, value: string) {
@@ -60,7 +94,6 @@
         throw new TypeError('Headers are immutable');
       }
 
-      name = name.toLowerCase();
       this.addValue(name, value);
     }
 
@@ -69,12 +102,12 @@
         throw new TypeError('Headers are immutable');
       }
 
-      name = name.toLowerCase();
-      this.h.delete(name);
+      this.h.delete(normalizeName(name));
     }
 
     *entries(): IterableIterator<[string, string]> {
-      const sorted = [...this.h.entries()].sort(([a], [b]) => a.localeCompare(b));
+      // Sorted by code units, not by locale
+      const sorted = [...this.h.entries()].sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0));
 
       for (const [key, values] of sorted) {
         if (key == SET_COOKIE) {
@@ -88,17 +121,17 @@
     }
 
     get(name: string): string | null {
-      name = name.toLowerCase();
-      return this.h.get(name)?.join(', ') || null;
+      return this.h.get(normalizeName(name))?.join(', ') ?? null;
     }
 
     has(name: string): boolean {
-      name = name.toLowerCase();
-      return this.h.has(name);
+      return this.h.has(normalizeName(name));
     }
 
-    keys(): IterableIterator<string> {
-      return this.h.keys();
+    *keys(): IterableIterator<string> {
+      for (const [key] of this.entries()) {
+        yield key;
+      }
     }
 
     set(name: string, value: string) {
@@ -106,16 +139,13 @@
         throw new TypeError('Headers are immutable');
       }
 
-      name = name.toLowerCase();
-      value = String(value);
-      this.h.set(name, [value]);
+      name = normalizeName(name);
+      this.h.set(name, [normalizeValue(name, value)]);
     }
 
     *values(): IterableIterator<string> {
-      for (const [, values] of this.h) {
-        for (const value of values) {
-          yield value;
-        }
+      for (const [, value] of this.entries()) {
+        yield value;
       }
     }
 
@@ -12,14 +12,13 @@ import { RequestResponseBody } from './body';
     readonly redirect: RequestRedirect;
     readonly referrer: string;
     readonly referrerPolicy: ReferrerPolicy;
+    readonly signal: AbortSignal;
 
-    private readonly init?: RequestInit;
     private readonly input: RequestInfo | URL;
 
     constructor(input: RequestInfo | URL, init?: RequestInit) {
       super(init?.body, init?.headers);
 
-      this.init = init;
       this.input = input;
 
       this.method = init?.method || 'GET';
@@ -32,21 +31,19 @@ import { RequestResponseBody } from './body';
       this.redirect = init?.redirect || 'follow';
       this.referrer = init?.referrer || '';
       this.referrerPolicy = init?.referrerPolicy || '';
+      this.signal = init?.signal || new AbortController().signal;
     }
 
     get url(): string {
       return this.input.toString();
     }
 
-    get signal(): AbortSignal {
-      return this.init?.signal || new AbortSignal();
-    }
-
     clone(): Request {
       return new Request(this.url, {
         method: this.method,
         body: this.body,
         headers: this.headers,
+        signal: this.signal,
       });
     }
   };
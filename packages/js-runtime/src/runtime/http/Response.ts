@@ -60,19 +60,23 @@ import { RequestResponseBody } from './body';
         throw new RangeError('Invalid status code');
       }
 
+      const location = new URL(url);
+
       const response = new Response(null, {
         status,
         headers: {
-          Location: new URL(url).toString(),
+          Location: location.toString(),
         },
       });
       // @ts-expect-error we modify a read-only property
       response.type = 'default';
+      response.headers.immutable = true;
 
       return response;
     }
 
-    static json(data?: BodyInit | null, init?: ResponseInit): Response {
+    // JSON.stringify throws a TypeError on circular structures and BigInts
+    static json(data?: unknown, init?: ResponseInit): Response {
       const body = JSON.stringify(data);
 
       if (body === undefined) {
@@ -82,7 +86,7 @@ import { RequestResponseBody } from './body';
       const headers = new Headers(init?.headers);
 
       if (!headers.has('content-type')) {
-        headers.set('content-type', 'application/json');
+        headers.set('content-type', 'application/json;charset=UTF-8');
       }
 
       return new Response(body, {
@@ -1,139 +1,134 @@
 (globalThis => {
-  // From https://url.spec.whatwg.org/#url-miscellaneous
-  const DEFAULT_PORTS: Record<string, string> = {
-    'ftp:': '21',
-    'http:': '80',
-    'https:': '443',
-    'ws:': '80',
-    'wss:': '443',
-  };
-
+  // Parsing and serializing is done by the runtime, following https://url.spec.whatwg.org
   globalThis.URL = class {
-    public hash = '';
-    public hostname = '';
-    public origin = '';
-    public password = '';
-    public pathname = '';
-    public port = '';
-    public protocol = '';
-    public searchParams!: URLSearchParams;
-    public username = '';
+    private components: UrlComponents;
+    private searchParamsCache: URLSearchParams | undefined;
 
     constructor(url: string | URL, base?: string | URL) {
-      this.initialize(url, base);
+      this.components = LagonSync.parseUrl(String(url), base === undefined ? undefined : String(base));
     }
 
-    private initialize(url: string | URL, base?: string | URL) {
-      let finalUrl = url.toString();
+    static canParse(url: string | URL, base?: string | URL): boolean {
+      try {
+        new URL(url, base);
+        return true;
+      } catch {
+        return false;
+      }
+    }
 
-      if (base) {
-        const baseUrl = new URL(base);
-        finalUrl = baseUrl.protocol + '//' + baseUrl.host; // + '/' + url;
+    static createObjectURL(obj: Blob): string {
+      // TODO
+      throw new Error('Not implemented');
+    }
+
+    static revokeObjectURL(url: string) {
+      // TODO
+      throw new Error('Not implemented');
+    }
 
-        if (!url.toString().startsWith('/')) {
-          finalUrl += '/';
-        }
+    private update(component: Exclude<keyof UrlComponents, 'origin'>, value: string) {
+      this.components = LagonSync.setUrl(this.components.href, component, String(value));
 
-        finalUrl += url;
+      if (component === 'href' || component === 'search') {
+        this.searchParamsCache?.replaceWith(this.components.search);
       }
+    }
 
-      const result =
-        // eslint-disable-next-line
-        /((?:blob|file):)?(https?\:)\/\/(?:(.*):(.*)@)?([^:\/?#]*)(?:\:([0-9]+))?([\/]{0,1}[^?#]*)(\?[^#]*|)(#.*|)$/.exec(
-          finalUrl,
-        );
+    get hash(): string {
+      return this.components.hash;
+    }
 
-      if (result) {
-        const [_href, origin, protocol, username, password, hostname, port, pathname, search, hash] = result;
+    set hash(hash: string) {
+      this.update('hash', hash);
+    }
 
-        void _href;
+    get host(): string {
+      return this.components.host;
+    }
 
-        this.hash = hash;
-        this.hostname = hostname;
+    set host(host: string) {
+      this.update('host', host);
+    }
 
-        this.port = port === DEFAULT_PORTS[protocol] ? '' : port;
+    get hostname(): string {
+      return this.components.hostname;
+    }
 
-        if (['http:', 'https:'].includes(protocol) || ['blob:', 'file:'].includes(origin)) {
-          this.origin = protocol + '//' + hostname;
-          if (this.port) {
-            this.origin += ':' + this.port;
-          }
-        }
+    set hostname(hostname: string) {
+      this.update('hostname', hostname);
+    }
 
-        this.password = password || '';
-        this.pathname = pathname === '' ? '/' : pathname;
-        this.protocol = protocol;
-        this.searchParams = new URLSearchParams(search);
-        this.username = username || '';
-      } else {
-        this.searchParams = new URLSearchParams();
-      }
+    get href(): string {
+      return this.components.href;
     }
 
-    static createObjectURL(obj: Blob): string {
-      // TODO
-      throw new Error('Not implemented');
+    set href(href: string) {
+      this.update('href', href);
     }
 
-    static revokeObjectURL(url: string) {
-      // TODO
-      throw new Error('Not implemented');
+    get origin(): string {
+      return this.components.origin;
     }
 
-    get href(): string {
-      const credentials = this.username + (this.password ? ':' + this.password : '');
-
-      let href =
-        this.protocol +
-        '//' +
-        (credentials ? credentials + '@' : '') +
-        this.host +
-        this.pathname +
-        this.search +
-        this.hash;
-
-      if (this.protocol === 'file:') {
-        href = href.replace('//', '');
-      }
+    get password(): string {
+      return this.components.password;
+    }
 
-      return href;
+    set password(password: string) {
+      this.update('password', password);
     }
 
-    set href(href: string) {
-      this.initialize(href);
+    get pathname(): string {
+      return this.components.pathname;
     }
 
-    get host(): string {
-      return this.hostname + (this.port ? ':' + this.port : '');
+    set pathname(pathname: string) {
+      this.update('pathname', pathname);
     }
 
-    set host(host: string) {
-      // eslint-disable-next-line no-useless-escape
-      const result = /^([^:\/?#]*)(?:\:([0-9]+))$/.exec(host);
-      if (result) {
-        const [, hostname, port] = result;
-        this.hostname = hostname;
-        this.port = port;
-      } else {
-        this.hostname = host;
-        this.port = '';
-      }
+    get port(): string {
+      return this.components.port;
+    }
+
+    set port(port: string) {
+      this.update('port', port);
+    }
+
+    get protocol(): string {
+      return this.components.protocol;
+    }
+
+    set protocol(protocol: string) {
+      this.update('protocol', protocol);
     }
 
     get search(): string {
-      const search = this.searchParams.toString();
-      return search ? '?' + search : '';
+      return this.components.search;
     }
 
     set search(search: string) {
-      const newSearchParams = new URLSearchParams(search);
-      for (const key of this.searchParams.keys()) {
-        this.searchParams.delete(key);
-      }
+      this.update('search', search);
+    }
 
-      for (const [key, value] of newSearchParams) {
-        this.searchParams.append(key, value);
+    // Always the same instance, kept in sync with the search
+    get searchParams(): URLSearchParams {
+      if (!this.searchParamsCache) {
+        this.searchParamsCache = new URLSearchParams(this.components.search);
+        this.searchParamsCache.onUpdate = search => {
+          this.components = LagonSync.setUrl(this.components.href, 'search', search);
+        };
       }
+
+      return this.searchParamsCache;
+    }
+
+    get username(): string {
+      return this.components.username;
+    }
+
+    set username(username: string) {
+      this.update('username', username);
     }
 
     toString(): string {
@@ -141,7 +136,7 @@
     }
 
     toJSON(): string {
-      return this.toString();
+      return this.href;
     }
   };
 })(globalThis);
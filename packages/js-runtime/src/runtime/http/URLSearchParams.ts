@@ -1,102 +1,172 @@
 (globalThis => {
+  // https://url.spec.whatwg.org/#application-x-www-form-urlencoded-percent-encode-set
+  const UNESCAPED = /[A-Za-z0-9*\-._]/;
+
+  const encode = (value: string): string => {
+    let result = '';
+
+    for (const byte of globalThis.__lagon__.TEXT_ENCODER.encode(value)) {
+      const char = String.fromCharCode(byte);
+
+      if (char === ' ') {
+        result += '+';
+      } else if (UNESCAPED.test(char)) {
+        result += char;
+      } else {
+        result += '%' + byte.toString(16).toUpperCase().padStart(2, '0');
+      }
+    }
+
+    return result;
+  };
+
+  // Invalid percent-encoded sequences are kept as-is, unlike decodeURIComponent()
+  const decode = (value: string): string => {
+    const bytes = globalThis.__lagon__.TEXT_ENCODER.encode(value.replace(/\+/g, ' '));
+    const decoded = new Uint8Array(bytes.length);
+    let length = 0;
+
+    for (let i = 0; i < bytes.length; i++) {
+      const hex = String.fromCharCode(bytes[i + 1], bytes[i + 2]);
+
+      if (bytes[i] === 0x25 && /^[0-9A-Fa-f]{2}$/.test(hex)) {
+        decoded[length++] = parseInt(hex, 16);
+        i += 2;
+      } else {
+        decoded[length++] = bytes[i];
+      }
+    }
+
+    return globalThis.__lagon__.TEXT_DECODER.decode(decoded.subarray(0, length));
+  };
+
+  const parse = (init: string): [string, string][] => {
+    const list: [string, string][] = [];
+
+    for (const entry of init.split('&')) {
+      if (entry === '') {
+        continue;
+      }
+
+      const index = entry.indexOf('=');
+      const name = index === -1 ? entry : entry.slice(0, index);
+      const value = index === -1 ? '' : entry.slice(index + 1);
+
+      list.push([decode(name), decode(value)]);
+    }
+
+    return list;
+  };
+
   globalThis.URLSearchParams = class {
-    private params: Map<string, string[]> = new Map();
+    private list: [string, string][] = [];
+
+    // Set by the URL owning these params, to update its search
+    onUpdate?: (search: string) => void;
 
     constructor(init?: string[][] | Record<string, string> | string | URLSearchParams) {
-      if (init) {
-        if (typeof init === 'string') {
-          init
-            .replace('?', '')
-            .split('&')
-            .forEach(entry => {
-              const [key, value] = entry.split('=');
-
-              this.addValue(key, decodeURIComponent(value));
-            });
-        } else if (typeof init === 'object') {
-          if (Array.isArray(init)) {
-            init.forEach(([key, value]) => {
-              this.addValue(key, value);
-            });
-          } else {
-            Object.entries(init).forEach(([key, value]) => {
-              this.addValue(key, value);
-            });
+      if (init === undefined || init === null) {
+        return;
+      }
+
+      if (init instanceof URLSearchParams) {
+        this.list = [...init];
+      } else if (typeof init === 'object') {
+        if (typeof (init as Iterable<string[]>)[Symbol.iterator] === 'function') {
+          for (const pair of init as Iterable<string[]>) {
+            const [name, value, ...rest] = [...pair];
+
+            if (value === undefined || rest.length > 0) {
+              throw new TypeError('Each pair must be an iterable [name, value] tuple');
+            }
+
+            this.list.push([String(name), String(value)]);
+          }
+        } else {
+          for (const [name, value] of Object.entries(init)) {
+            this.list.push([name, String(value)]);
           }
         }
+      } else {
+        const search = String(init);
+        this.list = parse(search.startsWith('?') ? search.slice(1) : search);
       }
     }
 
-    private addValue(name: string, value: string) {
-      const values = this.params.get(name);
+    // Used by the URL owning these params when its search changes
+    replaceWith(search: string) {
+      this.list = parse(search.startsWith('?') ? search.slice(1) : search);
+    }
 
-      if (values) {
-        values.push(value);
-      } else {
-        this.params.set(name, [value]);
-      }
+    private update() {
+      this.onUpdate?.(this.toString());
+    }
+
+    get size(): number {
+      return this.list.length;
     }
 
     append(name: string, value: string) {
-      this.addValue(name, value);
+      this.list.push([String(name), String(value)]);
+      this.update();
     }
 
-    delete(name: string) {
-      this.params.delete(name);
+    delete(name: string, value?: string) {
+      this.list = this.list.filter(([key, entry]) => key !== name || (value !== undefined && entry !== String(value)));
+      this.update();
     }
 
-    *entries(): IterableIterator<[string, string]> {
-      for (const [key, values] of this.params) {
-        for (const value of values) {
-          yield [key, value];
-        }
-      }
+    entries(): IterableIterator<[string, string]> {
+      return this.list.map<[string, string]>(([name, value]) => [name, value])[Symbol.iterator]();
     }
 
     forEach(callbackfn: (value: string, key: string, parent: URLSearchParams) => void, thisArg?: any) {
-      this.params.forEach((values, key) => {
-        values.forEach(value => {
-          callbackfn.call(thisArg, value, key, this);
-        });
-      });
+      for (const [name, value] of this.list) {
+        callbackfn.call(thisArg, value, name, this);
+      }
     }
 
     get(name: string): string | null {
-      return this.params.get(name)?.[0] || null;
+      return this.list.find(([key]) => key === name)?.[1] ?? null;
     }
 
     getAll(name: string): string[] {
-      return this.params.get(name) || [];
+      return this.list.filter(([key]) => key === name).map(([, value]) => value);
     }
 
-    has(name: string): boolean {
-      return this.params.has(name);
+    has(name: string, value?: string): boolean {
+      return this.list.some(([key, entry]) => key === name && (value === undefined || entry === String(value)));
     }
 
     keys(): IterableIterator<string> {
-      return this.params.keys();
+      return this.list.map(([name]) => name)[Symbol.iterator]();
     }
 
     set(name: string, value: string) {
-      this.params.set(name, [value]);
+      const index = this.list.findIndex(([key]) => key === name);
+
+      if (index === -1) {
+        this.list.push([String(name), String(value)]);
+      } else {
+        this.list[index] = [String(name), String(value)];
+        this.list = this.list.filter(([key], i) => i <= index || key !== name);
+      }
+
+      this.update();
     }
 
+    // Array.prototype.sort is stable, and compares code units
     sort() {
-      this.params = new Map([...this.params].sort());
+      this.list.sort(([a], [b]) => (a < b ? -1 : a > b ? 1 : 0));
+      this.update();
     }
 
     toString(): string {
-      return Array.from(this.params.entries())
-        .map(([key, value]) => `${key}=${value}`)
-        .join('&');
+      return this.list.map(([name, value]) => `${encode(name)}=${encode(value)}`).join('&');
     }
 
-    *values(): IterableIterator<string> {
-      for (const [, values] of this.params) {
-        for (const value of values) {
-          yield value;
-        }
-      }
+    values(): IterableIterator<string> {
+      return this.list.map(([, value]) => value)[Symbol.iterator]();
     }
 
     [Symbol.iterator](): IterableIterator<[string, string]> {
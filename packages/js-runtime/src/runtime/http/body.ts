@@ -82,25 +82,32 @@ export class RequestResponseBody {
       return globalThis.__lagon__.TEXT_ENCODER.encode(this.body);
     }
 
-    return new Promise(resolve => {
+    return new Promise((resolve, reject) => {
       const reader = (this.body as ReadableStream<Uint8Array>).getReader();
-      let result = new Uint8Array();
+      // Concatenate the chunks once at the end, since bodies can have many chunks
+      const chunks: Uint8Array[] = [];
+      let length = 0;
 
       const pull = () => {
         reader.read().then(({ done, value }) => {
           if (done) {
+            const result = new Uint8Array(length);
+            let offset = 0;
+
+            for (const chunk of chunks) {
+              result.set(chunk, offset);
+              offset += chunk.length;
+            }
+
             this.bodyUsed = true;
             return resolve(result);
           }
 
-          const newResult = new Uint8Array(result.length + value.length);
-          newResult.set(result);
-          newResult.set(value, result.length);
-
-          result = newResult;
+          chunks.push(value);
+          length += value.length;
 
           pull();
-        });
+        }, reject);
       };
 
       pull();
@@ -138,7 +145,7 @@ export class RequestResponseBody {
       return this.body;
     }
 
-    return new Promise(resolve => {
+    return new Promise((resolve, reject) => {
       const reader = (this.body as ReadableStream<Uint8Array>).getReader();
       let result = '';
 
@@ -156,7 +163,7 @@ export class RequestResponseBody {
           }
 
           pull();
-        });
+        }, reject);
       };
 
       pull();
@@ -1,58 +1,168 @@
 (globalThis => {
-  const isHeadersObject = (headers?: HeadersInit): headers is Headers => !!headers && 'entries' in headers;
+  // https://fetch.spec.whatwg.org/#null-body-status
+  const NULL_BODY_STATUS = [101, 103, 204, 205, 304];
+  // https://fetch.spec.whatwg.org/#redirect-status
+  const REDIRECT_STATUS = [301, 302, 303, 307, 308];
+  const REDIRECT_MODES = ['follow', 'manual', 'error'];
+  // 'only-if-cached' isn't supported
+  const CACHE_MODES = ['default', 'no-store', 'reload', 'no-cache', 'force-cache'];
+
+  let fetchCounter = 0;
+
+  // Chunks are pulled from the runtime as they arrive. Reading the body after the
+  // signal is aborted rejects with its reason, and cancelling it drops the connection
+  const streamBody = (id: number, signal: AbortSignal | null | undefined, done: () => void) =>
+    new ReadableStream<Uint8Array>({
+      async pull(controller) {
+        try {
+          signal?.throwIfAborted();
+          const chunk = await LagonAsync.pullFetchBody(id);
+          signal?.throwIfAborted();
+
+          if (chunk === undefined) {
+            done();
+            controller.close();
+          } else {
+            controller.enqueue(chunk);
+          }
+        } catch (error) {
+          done();
+          controller.error(signal?.aborted ? signal.reason : typeof error === 'string' ? new Error(error) : error);
+        }
+      },
+      cancel() {
+        done();
+        LagonSync.abortFetch(id);
+      },
+    });
 
   globalThis.fetch = async (input, init) => {
-    let headers: Map<string, string> | undefined = undefined;
+    let headers: Map<string, string[]> | undefined = undefined;
 
-    if (isHeadersObject(init?.headers)) {
+    if (init?.headers) {
       headers = new Map();
 
-      for (const [key, value] of (init?.headers as Headers).entries()) {
-        headers.set(key, value);
+      // Set-Cookie values are iterated separately, so they aren't joined
+      for (const [key, value] of new Headers(init.headers)) {
+        headers.set(key, [...(headers.get(key) || []), value]);
       }
-    } else if (init?.headers) {
-      headers = new Map(Object.entries(init.headers));
     }
 
-    let body: string | undefined;
+    let body: string | Uint8Array | undefined;
+    let contentType: string | undefined;
 
     if (init?.body) {
-      if (globalThis.__lagon__.isIterable(init.body)) {
-        body = globalThis.__lagon__.TEXT_DECODER.decode(init.body);
-      } else {
-        if (typeof init.body !== 'string') {
-          // TODO: Support other body types
-          throw new Error('Body must be a string or an iterable');
-        }
-
+      if (typeof init.body === 'string') {
         body = init.body;
+      } else if (init.body instanceof FormData) {
+        const multipart = globalThis.__lagon__.encodeMultipart(init.body);
+
+        body = multipart.body;
+        contentType = `multipart/form-data; boundary=${multipart.boundary}`;
+      } else if (init.body instanceof Blob) {
+        body = init.body.buffer;
+        contentType = init.body.type || undefined;
+      } else if (init.body instanceof ArrayBuffer) {
+        body = new Uint8Array(init.body);
+      } else if (ArrayBuffer.isView(init.body)) {
+        body = new Uint8Array(init.body.buffer, init.body.byteOffset, init.body.byteLength);
+      } else {
+        // TODO: Support other body types
+        throw new Error('Body must be a string, a BufferSource, a Blob or a FormData');
       }
     }
 
-    const checkAborted = () => {
-      if (init?.signal?.aborted) {
-        throw new Error('Aborted');
-      }
-    };
+    // Like browsers, don't override an explicit content-type
+    if (contentType && !headers?.has('content-type')) {
+      headers = headers || new Map();
+      headers.set('content-type', [contentType]);
+    }
 
-    try {
-      checkAborted();
+    // Redirects are followed by default by the runtime
+    const redirect = init?.redirect;
+
+    if (redirect !== undefined && !REDIRECT_MODES.includes(redirect)) {
+      throw new TypeError(`Invalid redirect mode '${redirect}'`);
+    }
+
+    // Responses are only cached when the isolate has an HTTP cache
+    const cache = init?.cache;
+
+    if (cache !== undefined && !CACHE_MODES.includes(cache)) {
+      throw new TypeError(`Invalid cache mode '${cache}'`);
+    }
+
+    // Defaults to the timeout of the runtime
+    const timeout = init?.timeout;
 
+    if (timeout !== undefined && !(Number.isFinite(timeout) && timeout >= 0)) {
+      throw new TypeError(`Invalid timeout '${timeout}'`);
+    }
+
+    const signal = init?.signal;
+    signal?.throwIfAborted();
+
+    // Aborting the signal cancels the request in the runtime
+    const id = fetchCounter++;
+    const onAbort = () => LagonSync.abortFetch(id);
+    const done = () => signal?.removeEventListener('abort', onAbort);
+
+    signal?.addEventListener('abort', onAbort);
+
+    try {
       const response = await LagonAsync.fetch({
         m: init?.method || 'GET',
         u: input.toString(),
         b: body,
         h: headers,
+        i: id,
+        r: redirect,
+        t: timeout,
+        c: cache,
+        s: init?.integrity,
       });
 
-      checkAborted();
+      signal?.throwIfAborted();
+
+      let responseBody: Uint8Array | ReadableStream<Uint8Array> | null;
+      // Redirects that weren't followed don't expose their body
+      const opaqueRedirect = redirect === 'manual' && REDIRECT_STATUS.includes(response.s);
 
-      return new Response(response.b, {
-        // url: response.init.url,
+      if (response.b !== undefined) {
+        // Intercepted requests already have their whole body
+        done();
+        responseBody = response.b;
+      } else if (NULL_BODY_STATUS.includes(response.s) || opaqueRedirect) {
+        done();
+        LagonSync.abortFetch(id);
+        responseBody = null;
+      } else {
+        responseBody = streamBody(id, signal, done);
+      }
+
+      const fetchResponse = new Response(responseBody, {
         headers: response.h,
         status: response.s,
       });
+
+      // @ts-expect-error we modify a read-only property
+      fetchResponse.url = response.u ?? input.toString();
+      // @ts-expect-error we modify a read-only property
+      fetchResponse.redirected = response.r ?? false;
+
+      if (opaqueRedirect) {
+        // @ts-expect-error we modify a read-only property
+        fetchResponse.type = 'opaqueredirect';
+      }
+
+      return fetchResponse;
     } catch (error) {
+      done();
+
+      if (signal?.aborted) {
+        throw signal.reason;
+      }
+
       if (typeof error === 'string') {
         throw new Error(error);
       }
@@ -1,17 +1,31 @@
 (globalThis => {
   const {
+    ByteLengthQueuingStrategy,
+    CountQueuingStrategy,
+    ReadableByteStreamController,
     ReadableStream,
     ReadableStreamBYOBReader,
+    ReadableStreamBYOBRequest,
+    ReadableStreamDefaultController,
     ReadableStreamDefaultReader,
     TransformStream,
+    TransformStreamDefaultController,
     WritableStream,
+    WritableStreamDefaultController,
     WritableStreamDefaultWriter,
   } = require('web-streams-polyfill');
 
+  globalThis.ByteLengthQueuingStrategy = ByteLengthQueuingStrategy;
+  globalThis.CountQueuingStrategy = CountQueuingStrategy;
+  globalThis.ReadableByteStreamController = ReadableByteStreamController;
   globalThis.ReadableStream = ReadableStream;
   globalThis.ReadableStreamBYOBReader = ReadableStreamBYOBReader;
+  globalThis.ReadableStreamBYOBRequest = ReadableStreamBYOBRequest;
+  globalThis.ReadableStreamDefaultController = ReadableStreamDefaultController;
   globalThis.ReadableStreamDefaultReader = ReadableStreamDefaultReader;
   globalThis.TransformStream = TransformStream;
+  globalThis.TransformStreamDefaultController = TransformStreamDefaultController;
   globalThis.WritableStream = WritableStream;
+  globalThis.WritableStreamDefaultController = WritableStreamDefaultController;
   globalThis.WritableStreamDefaultWriter = WritableStreamDefaultWriter;
 })(globalThis);